@@ -1,6 +1,8 @@
+use futures_util::{StreamExt, TryStreamExt};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 use reqwest::Client;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
@@ -8,13 +10,21 @@ use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 
 use codex_types::{
     AdminUser, ApplyOrganizationSuggestionRequest, ApplyOrganizationSuggestionResponse,
-    CreateFileRequest, CreateUploadSessionRequest, CreateUserRequest, CreateUserResponse,
+    AuditLogPage, AuditLogQuery, CreateFileRequest, CreateScimTokenRequest,
+    CreateScimTokenResponse, CreateUploadSessionRequest, CreateUserRequest, CreateUserResponse,
     CreateVaultRequest, FileChangeEvent, FileContent, FileNode,
     GenerateOrganizationSuggestionsRequest, GenerateOutlineRequest, NoteOutlineResponse,
-    OrganizationSuggestionsResponse, PagedSearchResult, UndoMlActionResponse, UpdateFileRequest,
-    UploadSessionResponse, UserPreferences, Vault,
+    OrganizationSuggestionsResponse, PagedSearchResult, ScimTokenInfo, UndoMlActionResponse,
+    UpdateFileRequest, UploadSessionResponse, UserPreferences, Vault, WebAuthnCeremony,
+    WebAuthnLoginFinishRequest,
 };
 
+/// Chunk size used by [`ObsidianClient::upload_file`].
+pub const UPLOAD_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Chunks [`ObsidianClient::upload_file`] sends concurrently.
+const UPLOAD_PARALLELISM: usize = 4;
+
 pub type WsStream =
     tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
 
@@ -45,6 +55,14 @@ pub enum ClientError {
 
     #[error("invalid header value for auth token")]
     InvalidAuthHeader,
+
+    /// A chunked upload stopped part-way. Pass `session_id` to
+    /// [`ObsidianClient::resume_upload`] to send only the missing bytes.
+    #[error("upload {session_id} interrupted: {source}")]
+    UploadInterrupted {
+        session_id: String,
+        source: Box<ClientError>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +70,12 @@ pub struct LoginResponse {
     pub access_token: String,
     pub refresh_token: String,
     pub expires_in: u64,
+    /// The password was accepted but the account requires a WebAuthn
+    /// authenticator; complete `webauthn` with [`ObsidianClient::finish_webauthn_login`].
+    #[serde(default)]
+    pub webauthn_required: bool,
+    #[serde(default)]
+    pub webauthn: Option<WebAuthnCeremony>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +161,9 @@ pub struct TogglePluginResponse {
 pub struct FinishUploadSessionRequest {
     pub filename: String,
     pub path: String,
+    /// Hex SHA-256 of the complete file; the server rejects a mismatch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -310,6 +337,32 @@ impl ObsidianClient {
             )
             .await?;
 
+        if !response.webauthn_required {
+            self.set_tokens(
+                response.access_token.clone(),
+                response.refresh_token.clone(),
+                response.expires_in,
+            );
+        }
+        Ok(response)
+    }
+
+    /// Complete a WebAuthn sign-in, either passwordless or the second step
+    /// of [`ObsidianClient::login`].
+    pub async fn finish_webauthn_login(
+        &mut self,
+        request: &WebAuthnLoginFinishRequest,
+    ) -> Result<LoginResponse, ClientError> {
+        let response: LoginResponse = self
+            .send_json_with_options(
+                HttpMethod::Post,
+                "/api/auth/webauthn/login/finish",
+                Some(request),
+                false,
+                false,
+            )
+            .await?;
+
         self.set_tokens(
             response.access_token.clone(),
             response.refresh_token.clone(),
@@ -723,19 +776,52 @@ impl ObsidianClient {
             .await
     }
 
+    /// Append a chunk after the last byte the server has received.
     pub async fn upload_chunk(
         &self,
         vault_id: &str,
         session_id: &str,
         chunk: Vec<u8>,
+    ) -> Result<UploadChunkResponse, ClientError> {
+        self.put_upload_chunk(vault_id, session_id, None, chunk)
+            .await
+    }
+
+    /// Write a chunk at `offset`. Chunks may be sent in any order and in
+    /// parallel.
+    pub async fn upload_chunk_at(
+        &self,
+        vault_id: &str,
+        session_id: &str,
+        offset: u64,
+        chunk: Vec<u8>,
+    ) -> Result<UploadChunkResponse, ClientError> {
+        self.put_upload_chunk(vault_id, session_id, Some(offset), chunk)
+            .await
+    }
+
+    async fn put_upload_chunk(
+        &self,
+        vault_id: &str,
+        session_id: &str,
+        offset: Option<u64>,
+        chunk: Vec<u8>,
     ) -> Result<UploadChunkResponse, ClientError> {
         self.ensure_token_fresh().await?;
 
-        let endpoint = format!(
+        let mut endpoint = format!(
             "{}/api/vaults/{vault_id}/upload-sessions/{session_id}",
             self.base_url
         );
-        let mut req = self.inner.put(endpoint).body(chunk);
+        if let Some(offset) = offset {
+            endpoint.push_str(&format!("?offset={offset}"));
+        }
+        let checksum = hex::encode(Sha256::digest(&chunk));
+        let mut req = self
+            .inner
+            .put(endpoint)
+            .header("X-Chunk-Sha256", checksum)
+            .body(chunk);
         if let Some(token) = self.current_access_token()? {
             req = req.headers(Self::auth_header(&token)?);
         }
@@ -754,6 +840,104 @@ impl ObsidianClient {
         Ok(response.json::<UploadChunkResponse>().await?)
     }
 
+    pub async fn get_upload_session(
+        &self,
+        vault_id: &str,
+        session_id: &str,
+    ) -> Result<UploadSessionResponse, ClientError> {
+        let endpoint = format!("/api/vaults/{vault_id}/upload-sessions/{session_id}");
+        self.send_json(HttpMethod::Get, &endpoint, Option::<&()>::None)
+            .await
+    }
+
+    pub async fn cancel_upload_session(
+        &self,
+        vault_id: &str,
+        session_id: &str,
+    ) -> Result<(), ClientError> {
+        let endpoint = format!("/api/vaults/{vault_id}/upload-sessions/{session_id}");
+        self.send_no_content(HttpMethod::Delete, &endpoint).await
+    }
+
+    /// Upload `contents` as `path/filename` through a chunked, checksummed
+    /// upload session. If a chunk fails, the error is
+    /// [`ClientError::UploadInterrupted`] and the upload can be continued
+    /// with [`Self::resume_upload`].
+    pub async fn upload_file(
+        &self,
+        vault_id: &str,
+        path: &str,
+        filename: &str,
+        contents: &[u8],
+    ) -> Result<serde_json::Value, ClientError> {
+        let session = self
+            .create_upload_session(
+                vault_id,
+                &CreateUploadSessionRequest {
+                    filename: filename.to_string(),
+                    path: path.to_string(),
+                    total_size: Some(contents.len() as u64),
+                    sha256: Some(hex::encode(Sha256::digest(contents))),
+                },
+            )
+            .await?;
+        self.resume_upload(vault_id, &session.session_id, path, filename, contents)
+            .await
+    }
+
+    /// Send whatever part of `contents` the session has not received yet,
+    /// then finish it.
+    pub async fn resume_upload(
+        &self,
+        vault_id: &str,
+        session_id: &str,
+        path: &str,
+        filename: &str,
+        contents: &[u8],
+    ) -> Result<serde_json::Value, ClientError> {
+        let interrupted = |source: ClientError| ClientError::UploadInterrupted {
+            session_id: session_id.to_string(),
+            source: Box::new(source),
+        };
+
+        let status = self
+            .get_upload_session(vault_id, session_id)
+            .await
+            .map_err(interrupted)?;
+        let missing: Vec<(u64, &[u8])> = contents
+            .chunks(UPLOAD_CHUNK_SIZE)
+            .enumerate()
+            .map(|(index, chunk)| ((index * UPLOAD_CHUNK_SIZE) as u64, chunk))
+            .filter(|(offset, chunk)| {
+                let end = offset + chunk.len() as u64;
+                !status
+                    .received
+                    .iter()
+                    .any(|range| range.offset <= *offset && end <= range.offset + range.length)
+            })
+            .collect();
+
+        futures_util::stream::iter(missing)
+            .map(|(offset, chunk)| {
+                self.upload_chunk_at(vault_id, session_id, offset, chunk.to_vec())
+            })
+            .buffer_unordered(UPLOAD_PARALLELISM)
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(interrupted)?;
+
+        self.finish_upload_session(
+            vault_id,
+            session_id,
+            &FinishUploadSessionRequest {
+                filename: filename.to_string(),
+                path: path.to_string(),
+                sha256: Some(hex::encode(Sha256::digest(contents))),
+            },
+        )
+        .await
+    }
+
     pub async fn finish_upload_session(
         &self,
         vault_id: &str,
@@ -875,6 +1059,66 @@ impl ObsidianClient {
             .await
     }
 
+    /// One page of the audit log, newest first. Pass the returned
+    /// `next_cursor` as `query.cursor` to fetch the next page.
+    pub async fn admin_audit_log(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<AuditLogPage, ClientError> {
+        let params = match serde_json::to_value(query)? {
+            serde_json::Value::Object(map) => map
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        serde_json::Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    format!("{key}={}", urlencoding::encode(&value))
+                })
+                .collect::<Vec<_>>(),
+            _ => Vec::new(),
+        };
+        let endpoint = if params.is_empty() {
+            "/api/admin/audit-log".to_string()
+        } else {
+            format!("/api/admin/audit-log?{}", params.join("&"))
+        };
+        self.send_json(HttpMethod::Get, &endpoint, Option::<&()>::None)
+            .await
+    }
+
+    pub async fn admin_list_scim_tokens(&self) -> Result<Vec<ScimTokenInfo>, ClientError> {
+        self.send_json(
+            HttpMethod::Get,
+            "/api/admin/scim/tokens",
+            Option::<&()>::None,
+        )
+        .await
+    }
+
+    pub async fn admin_create_scim_token(
+        &self,
+        name: &str,
+    ) -> Result<CreateScimTokenResponse, ClientError> {
+        self.send_json(
+            HttpMethod::Post,
+            "/api/admin/scim/tokens",
+            Some(&CreateScimTokenRequest {
+                name: name.to_string(),
+            }),
+        )
+        .await
+    }
+
+    pub async fn admin_revoke_scim_token(
+        &self,
+        token_id: &str,
+    ) -> Result<serde_json::Value, ClientError> {
+        let endpoint = format!("/api/admin/scim/tokens/{token_id}");
+        self.send_json(HttpMethod::Delete, &endpoint, Option::<&()>::None)
+            .await
+    }
+
     pub async fn connect_ws(&self) -> Result<WsStream, ClientError> {
         self.ensure_token_fresh().await?;
 
@@ -0,0 +1,14 @@
+-- Index of files moved to a vault's trash, for retention and bulk operations.
+
+CREATE TABLE IF NOT EXISTS trash_items (
+    vault_id      TEXT NOT NULL,
+    trash_name    TEXT NOT NULL,
+    original_path TEXT NOT NULL,
+    deleted_by    TEXT,
+    deleted_at    TEXT NOT NULL,
+    size          BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (vault_id, trash_name),
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_trash_items_deleted_at ON trash_items(deleted_at);
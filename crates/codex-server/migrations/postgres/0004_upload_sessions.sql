@@ -0,0 +1,25 @@
+-- Resumable upload sessions and the chunks received for them.
+
+CREATE TABLE IF NOT EXISTS upload_sessions (
+    id         TEXT PRIMARY KEY,
+    vault_id   TEXT NOT NULL,
+    owner_id   TEXT,
+    filename   TEXT NOT NULL,
+    path       TEXT NOT NULL,
+    total_size BIGINT,
+    sha256     TEXT,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_upload_sessions_expires_at ON upload_sessions(expires_at);
+
+CREATE TABLE IF NOT EXISTS upload_session_chunks (
+    session_id   TEXT NOT NULL,
+    chunk_offset BIGINT NOT NULL,
+    length       BIGINT NOT NULL,
+    sha256       TEXT,
+    PRIMARY KEY (session_id, chunk_offset),
+    FOREIGN KEY (session_id) REFERENCES upload_sessions(id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- How strictly entity frontmatter is checked against its entity type
+-- schema on save: 'off', 'warn' (log only) or 'reject' (refuse the write).
+-- Vaults without a row use 'warn'.
+
+CREATE TABLE IF NOT EXISTS vault_entity_settings (
+    vault_id        TEXT PRIMARY KEY NOT NULL,
+    validation_mode TEXT NOT NULL DEFAULT 'warn',
+    updated_at      TEXT NOT NULL,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
@@ -0,0 +1,253 @@
+-- Schema as it stood before versioned migrations were introduced.
+
+CREATE TABLE IF NOT EXISTS vaults (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    path TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    owner_user_id TEXT REFERENCES users(id),
+    visibility TEXT NOT NULL DEFAULT 'private',
+    document_format TEXT NOT NULL DEFAULT 'markdown'
+);
+
+CREATE TABLE IF NOT EXISTS preferences (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    theme TEXT NOT NULL DEFAULT 'dark',
+    editor_mode TEXT NOT NULL DEFAULT 'side_by_side',
+    font_size INTEGER NOT NULL DEFAULT 14,
+    window_layout TEXT,
+    icon_map TEXT,
+    updated_at TEXT NOT NULL
+);
+
+INSERT OR IGNORE INTO preferences (id, theme, editor_mode, font_size, updated_at)
+VALUES (1, 'dark', 'side_by_side', 14, strftime('%Y-%m-%dT%H:%M:%SZ', 'now'));
+
+CREATE TABLE IF NOT EXISTS recent_files (
+    vault_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    last_accessed TEXT NOT NULL,
+    PRIMARY KEY (vault_id, path),
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    is_admin INTEGER NOT NULL DEFAULT 0,
+    must_change_password INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TEXT,
+    totp_secret TEXT,
+    totp_enabled INTEGER NOT NULL DEFAULT 0,
+    totp_backup_codes TEXT
+);
+
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id TEXT PRIMARY KEY,
+    theme TEXT NOT NULL DEFAULT 'dark',
+    editor_mode TEXT NOT NULL DEFAULT 'side_by_side',
+    font_size INTEGER NOT NULL DEFAULT 14,
+    window_layout TEXT,
+    icon_map TEXT,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS groups (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    created_by_user_id TEXT NOT NULL,
+    FOREIGN KEY (created_by_user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS group_members (
+    group_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    added_at TEXT NOT NULL,
+    PRIMARY KEY (group_id, user_id),
+    FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS vault_user_shares (
+    vault_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (vault_id, user_id),
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS vault_group_shares (
+    vault_id TEXT NOT NULL,
+    group_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (vault_id, group_id),
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+    FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_group_members_user_id ON group_members(user_id);
+CREATE INDEX IF NOT EXISTS idx_vault_user_shares_user_id ON vault_user_shares(user_id);
+CREATE INDEX IF NOT EXISTS idx_vault_group_shares_group_id ON vault_group_shares(group_id);
+
+CREATE TABLE IF NOT EXISTS file_change_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vault_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    etag TEXT,
+    old_path TEXT,
+    timestamp INTEGER NOT NULL,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_file_change_log_vault_timestamp
+ON file_change_log(vault_id, timestamp);
+
+CREATE TABLE IF NOT EXISTS bookmarks (
+    id TEXT PRIMARY KEY NOT NULL,
+    vault_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    title TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+    UNIQUE(vault_id, path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_bookmarks_vault_id ON bookmarks(vault_id);
+
+CREATE TABLE IF NOT EXISTS ml_undo_receipts (
+    receipt_id TEXT PRIMARY KEY NOT NULL,
+    vault_id TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    description TEXT NOT NULL,
+    reverse_action TEXT NOT NULL,
+    applied_at TEXT NOT NULL,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_ml_undo_receipts_vault_id ON ml_undo_receipts(vault_id);
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TEXT NOT NULL,
+    user_id TEXT,
+    username TEXT,
+    event_type TEXT NOT NULL,
+    detail TEXT,
+    ip_address TEXT,
+    success INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp);
+CREATE INDEX IF NOT EXISTS idx_audit_log_user_id ON audit_log(user_id);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    token_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    revoked INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id);
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT,
+    revoked INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_user_id ON api_keys(user_id);
+CREATE INDEX IF NOT EXISTS idx_api_keys_prefix ON api_keys(prefix);
+
+CREATE TABLE IF NOT EXISTS invitations (
+    id TEXT PRIMARY KEY,
+    token TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL DEFAULT 'viewer',
+    vault_id TEXT,
+    created_by_user_id TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    accepted INTEGER NOT NULL DEFAULT 0,
+    accepted_by_user_id TEXT,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by_user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_invitations_token ON invitations(token);
+
+-- Typed entities and their relations (plugin schema registry)
+CREATE TABLE IF NOT EXISTS labels (
+    name        TEXT PRIMARY KEY NOT NULL,
+    description TEXT,
+    source      TEXT NOT NULL CHECK(source IN ('core', 'plugin')),
+    plugin_id   TEXT
+);
+
+CREATE TABLE IF NOT EXISTS entities (
+    id          TEXT PRIMARY KEY NOT NULL,
+    vault_id    TEXT NOT NULL,
+    path        TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    plugin_id   TEXT NOT NULL,
+    labels      TEXT NOT NULL,
+    fields      TEXT NOT NULL,
+    modified_at TEXT NOT NULL,
+    indexed_at  TEXT NOT NULL,
+    UNIQUE(vault_id, path),
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_entities_vault_id ON entities(vault_id);
+CREATE INDEX IF NOT EXISTS idx_entities_vault_type ON entities(vault_id, entity_type);
+
+CREATE TABLE IF NOT EXISTS relations (
+    id              TEXT PRIMARY KEY NOT NULL,
+    vault_id        TEXT NOT NULL,
+    from_entity_id  TEXT NOT NULL,
+    to_entity_id    TEXT NOT NULL,
+    relation_type   TEXT NOT NULL,
+    direction       TEXT NOT NULL CHECK(direction IN ('forward', 'inverse')),
+    metadata        TEXT,
+    source          TEXT NOT NULL CHECK(source IN ('field', 'explicit')),
+    source_field    TEXT,
+    created_at      TEXT NOT NULL,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+    FOREIGN KEY (from_entity_id) REFERENCES entities(id) ON DELETE CASCADE,
+    FOREIGN KEY (to_entity_id) REFERENCES entities(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_relations_from ON relations(from_entity_id);
+CREATE INDEX IF NOT EXISTS idx_relations_to ON relations(to_entity_id);
+CREATE INDEX IF NOT EXISTS idx_relations_vault ON relations(vault_id);
+
+CREATE TABLE IF NOT EXISTS reindex_log (
+    vault_id     TEXT PRIMARY KEY NOT NULL,
+    completed_at TEXT NOT NULL,
+    file_count   INTEGER NOT NULL,
+    duration_ms  INTEGER NOT NULL,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+
+-- The oldest account becomes the administrator when none is flagged yet.
+UPDATE users
+SET is_admin = 1
+WHERE id = (SELECT id FROM users ORDER BY created_at ASC LIMIT 1)
+AND NOT EXISTS (SELECT 1 FROM users WHERE is_admin = 1);
@@ -0,0 +1,14 @@
+-- Opt-in git integration per vault.
+
+CREATE TABLE IF NOT EXISTS vault_git_settings (
+    vault_id           TEXT PRIMARY KEY NOT NULL,
+    enabled            INTEGER NOT NULL DEFAULT 0,
+    auto_commit        INTEGER NOT NULL DEFAULT 1,
+    remote_url         TEXT,
+    branch             TEXT NOT NULL DEFAULT 'main',
+    sync_interval_secs INTEGER NOT NULL DEFAULT 0,
+    last_sync_at       TEXT,
+    last_sync_error    TEXT,
+    updated_at         TEXT NOT NULL,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
@@ -0,0 +1,5 @@
+-- Scoped API keys and last-use tracking.
+
+ALTER TABLE api_keys ADD COLUMN restrictions TEXT;
+ALTER TABLE api_keys ADD COLUMN last_used_at TEXT;
+ALTER TABLE api_keys ADD COLUMN last_used_ip TEXT;
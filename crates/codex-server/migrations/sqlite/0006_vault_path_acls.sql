@@ -0,0 +1,14 @@
+-- Path-scoped ACL rules narrowing vault roles for parts of a vault.
+
+CREATE TABLE IF NOT EXISTS vault_path_acls (
+    id TEXT PRIMARY KEY,
+    vault_id TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    principal_type TEXT NOT NULL,
+    principal_id TEXT,
+    access TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_path_acls_vault_id ON vault_path_acls(vault_id);
@@ -0,0 +1,20 @@
+-- Public links to a single note or folder. Only a hash of the token is kept.
+
+CREATE TABLE IF NOT EXISTS share_links (
+    id TEXT PRIMARY KEY,
+    token_hash TEXT NOT NULL UNIQUE,
+    vault_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    is_folder INTEGER NOT NULL DEFAULT 0,
+    permission TEXT NOT NULL DEFAULT 'view',
+    password_hash TEXT,
+    expires_at TEXT,
+    created_by TEXT,
+    created_at TEXT NOT NULL,
+    revoked_at TEXT,
+    access_count INTEGER NOT NULL DEFAULT 0,
+    last_accessed_at TEXT,
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_share_links_vault_id ON share_links(vault_id);
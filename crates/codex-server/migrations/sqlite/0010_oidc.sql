@@ -0,0 +1,18 @@
+-- OIDC sign-ins in progress, each bound to the state value sent to the
+-- provider, and the ID token behind each OIDC session for RP-initiated
+-- logout.
+
+CREATE TABLE IF NOT EXISTS oidc_auth_requests (
+    state TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    code_verifier TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS oidc_sessions (
+    session_id TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    id_token TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,17 @@
+-- Structured audit entries: where an event happened, the request behind
+-- it and its outcome, plus the optional hash chain that makes edits and
+-- deletions detectable.
+
+ALTER TABLE audit_log ADD COLUMN outcome TEXT NOT NULL DEFAULT 'success';
+ALTER TABLE audit_log ADD COLUMN vault_id TEXT;
+ALTER TABLE audit_log ADD COLUMN path TEXT;
+ALTER TABLE audit_log ADD COLUMN request_id TEXT;
+ALTER TABLE audit_log ADD COLUMN prev_hash TEXT;
+ALTER TABLE audit_log ADD COLUMN hash TEXT;
+
+UPDATE audit_log SET outcome = 'failure' WHERE success = 0;
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_event_type ON audit_log(event_type);
+CREATE INDEX IF NOT EXISTS idx_audit_log_vault_id ON audit_log(vault_id);
+-- Two entries can never extend the chain from the same link.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_audit_log_prev_hash ON audit_log(prev_hash);
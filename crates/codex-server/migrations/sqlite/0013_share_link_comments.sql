@@ -0,0 +1,14 @@
+-- Comments left on shared notes through links with 'comment' permission.
+-- Commenters are anonymous; author_name is whatever they chose to give.
+
+CREATE TABLE IF NOT EXISTS share_link_comments (
+    id TEXT PRIMARY KEY,
+    link_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    author_name TEXT,
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (link_id) REFERENCES share_links(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_share_link_comments_link_id ON share_link_comments(link_id);
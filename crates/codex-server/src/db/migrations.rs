@@ -0,0 +1,479 @@
+//! Ordered, checksummed schema migrations.
+//!
+//! Each migration is a pair of SQL files, one per backend, under
+//! `crates/codex-server/migrations/{sqlite,postgres}/`. A migration is
+//! applied once inside its own transaction and recorded in the
+//! `schema_migrations` table together with a SHA-256 of its contents.
+
+use crate::config::DatabaseBackend;
+use crate::error::{AppError, AppResult};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Any, AnyPool, Transaction};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sqlite: &'static str,
+    pub postgres: &'static str,
+}
+
+impl Migration {
+    pub fn sql(&self, backend: DatabaseBackend) -> &'static str {
+        match backend {
+            DatabaseBackend::Sqlite => self.sqlite,
+            DatabaseBackend::Postgres => self.postgres,
+        }
+    }
+
+    pub fn checksum(&self, backend: DatabaseBackend) -> String {
+        hex::encode(Sha256::digest(self.sql(backend).as_bytes()))
+    }
+}
+
+macro_rules! migration {
+    ($version:literal, $name:literal, $file:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            sqlite: include_str!(concat!("../../migrations/sqlite/", $file)),
+            postgres: include_str!(concat!("../../migrations/postgres/", $file)),
+        }
+    };
+}
+
+/// Every migration this build knows about, in application order.
+/// Never edit a released migration: add a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "initial", "0001_initial.sql"),
+    migration!(2, "vault_git_settings", "0002_vault_git_settings.sql"),
+    migration!(3, "trash_items", "0003_trash_items.sql"),
+    migration!(4, "upload_sessions", "0004_upload_sessions.sql"),
+    migration!(5, "api_key_restrictions", "0005_api_key_restrictions.sql"),
+    migration!(6, "vault_path_acls", "0006_vault_path_acls.sql"),
+    migration!(7, "share_links", "0007_share_links.sql"),
+    migration!(8, "webauthn", "0008_webauthn.sql"),
+    migration!(9, "scim", "0009_scim.sql"),
+    migration!(10, "oidc", "0010_oidc.sql"),
+    migration!(11, "audit_log_fields", "0011_audit_log_fields.sql"),
+    migration!(12, "entity_validation", "0012_entity_validation.sql"),
+    migration!(13, "share_link_comments", "0013_share_link_comments.sql"),
+];
+
+/// Databases created before `schema_migrations` existed were kept up to
+/// date by idempotent bootstrap statements; they are adopted at this version.
+const LEGACY_VERSION: i64 = 7;
+
+/// Columns the old bootstrap added with `ALTER TABLE` to tables created by
+/// earlier releases. `0001_initial` folds them into the table definitions.
+const LEGACY_COLUMNS: &[(&str, &str, &str)] = &[
+    ("vaults", "owner_user_id", "TEXT REFERENCES users(id)"),
+    ("vaults", "visibility", "TEXT NOT NULL DEFAULT 'private'"),
+    (
+        "vaults",
+        "document_format",
+        "TEXT NOT NULL DEFAULT 'markdown'",
+    ),
+    ("preferences", "window_layout", "TEXT"),
+    ("preferences", "icon_map", "TEXT"),
+    ("user_preferences", "icon_map", "TEXT"),
+    ("users", "is_admin", "INTEGER NOT NULL DEFAULT 0"),
+    (
+        "users",
+        "must_change_password",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("users", "is_active", "INTEGER NOT NULL DEFAULT 1"),
+    (
+        "users",
+        "failed_login_attempts",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("users", "locked_until", "TEXT"),
+    ("users", "totp_secret", "TEXT"),
+    ("users", "totp_enabled", "INTEGER NOT NULL DEFAULT 0"),
+    ("users", "totp_backup_codes", "TEXT"),
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: String,
+    pub duration_ms: i64,
+}
+
+impl AppliedMigration {
+    pub fn applied_at(&self) -> DateTime<Utc> {
+        super::parse_rfc3339_utc(&self.applied_at)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingMigration {
+    pub version: i64,
+    pub name: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    /// Highest applied version, `0` for an empty or pre-migration database.
+    pub current_version: i64,
+    pub latest_version: i64,
+    /// True for a database created before versioned migrations; the next
+    /// migration run adopts it instead of replaying `0001_initial`.
+    pub legacy: bool,
+    pub applied: Vec<AppliedMigration>,
+    pub pending: Vec<PendingMigration>,
+}
+
+async fn table_exists(pool: &AnyPool, backend: DatabaseBackend, table: &str) -> AppResult<bool> {
+    let sql = match backend {
+        DatabaseBackend::Sqlite => {
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = $1"
+        }
+        DatabaseBackend::Postgres => {
+            "SELECT COUNT(*) FROM information_schema.tables \
+             WHERE table_schema = current_schema() AND table_name = $1"
+        }
+    };
+    let (count,): (i64,) = sqlx::query_as(sql).bind(table).fetch_one(pool).await?;
+    Ok(count > 0)
+}
+
+async fn load_applied(
+    pool: &AnyPool,
+    backend: DatabaseBackend,
+) -> AppResult<Vec<AppliedMigration>> {
+    if !table_exists(pool, backend, "schema_migrations").await? {
+        return Ok(Vec::new());
+    }
+    Ok(sqlx::query_as(
+        "SELECT version, name, checksum, applied_at, duration_ms FROM schema_migrations ORDER BY version ASC",
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Refuse databases written by a newer build, and migrations whose SQL
+/// changed after they were applied.
+fn verify_applied(applied: &[AppliedMigration], backend: DatabaseBackend) -> AppResult<()> {
+    let latest = latest_version();
+    for record in applied {
+        let Some(known) = MIGRATIONS.iter().find(|m| m.version == record.version) else {
+            return Err(AppError::InternalError(format!(
+                "Database schema version {} is newer than this build supports ({}); \
+                 upgrade codex before using this database",
+                record.version, latest
+            )));
+        };
+        if known.checksum(backend) != record.checksum {
+            return Err(AppError::InternalError(format!(
+                "Migration {:04}_{} was modified after it was applied (checksum mismatch)",
+                record.version, record.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub async fn status(pool: &AnyPool, backend: DatabaseBackend) -> AppResult<MigrationStatus> {
+    let applied = load_applied(pool, backend).await?;
+    verify_applied(&applied, backend)?;
+    let current_version = applied.last().map_or(0, |m| m.version);
+    // Only SQLite databases predate versioned migrations.
+    let legacy = backend == DatabaseBackend::Sqlite
+        && applied.is_empty()
+        && table_exists(pool, backend, "vaults").await?;
+    let pending = MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current_version && !(legacy && m.version <= LEGACY_VERSION))
+        .map(|m| PendingMigration {
+            version: m.version,
+            name: m.name,
+        })
+        .collect();
+
+    Ok(MigrationStatus {
+        current_version,
+        latest_version: latest_version(),
+        legacy,
+        applied,
+        pending,
+    })
+}
+
+/// Key for the Postgres advisory lock that keeps instances starting at the
+/// same time from migrating concurrently.
+const POSTGRES_MIGRATION_LOCK: i64 = 0x636f_6465_785f_6d69;
+
+/// Apply pending migrations up to `target` (the latest when `None`) and
+/// return the ones applied by this call.
+pub async fn migrate(
+    pool: &AnyPool,
+    backend: DatabaseBackend,
+    target: Option<i64>,
+) -> AppResult<Vec<AppliedMigration>> {
+    let target = target.unwrap_or_else(latest_version);
+    if !MIGRATIONS.iter().any(|m| m.version == target) {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown schema version {target}"
+        )));
+    }
+
+    if backend == DatabaseBackend::Sqlite {
+        return migrate_locked(pool, backend, target).await;
+    }
+
+    let mut lock = pool.acquire().await?;
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(POSTGRES_MIGRATION_LOCK)
+        .execute(&mut *lock)
+        .await?;
+    let result = migrate_locked(pool, backend, target).await;
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(POSTGRES_MIGRATION_LOCK)
+        .execute(&mut *lock)
+        .await?;
+    result
+}
+
+async fn migrate_locked(
+    pool: &AnyPool,
+    backend: DatabaseBackend,
+    target: i64,
+) -> AppResult<Vec<AppliedMigration>> {
+    let integer = match backend {
+        DatabaseBackend::Sqlite => "INTEGER",
+        DatabaseBackend::Postgres => "BIGINT",
+    };
+    sqlx::query(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version     {integer} PRIMARY KEY NOT NULL,
+            name        TEXT NOT NULL,
+            checksum    TEXT NOT NULL,
+            applied_at  TEXT NOT NULL,
+            duration_ms {integer} NOT NULL DEFAULT 0
+        )
+        "#
+    ))
+    .execute(pool)
+    .await?;
+
+    let status = status(pool, backend).await?;
+    if target < status.current_version {
+        return Err(AppError::InvalidInput(format!(
+            "Database is at schema version {}; downgrading to {} is not supported",
+            status.current_version, target
+        )));
+    }
+
+    let mut newly_applied = Vec::new();
+    if status.legacy {
+        newly_applied.extend(adopt_legacy(pool).await?);
+    }
+    let current = newly_applied
+        .last()
+        .map_or(status.current_version, |m: &AppliedMigration| m.version);
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current && m.version <= target)
+    {
+        let started = std::time::Instant::now();
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(migration.sql(backend))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::InternalError(format!(
+                    "Migration {:04}_{} failed and was rolled back: {e}",
+                    migration.version, migration.name
+                ))
+            })?;
+        let record = record_applied(&mut tx, backend, migration, started).await?;
+        tx.commit().await?;
+        tracing::info!(
+            "Applied schema migration {:04}_{} in {} ms",
+            record.version,
+            record.name,
+            record.duration_ms
+        );
+        newly_applied.push(record);
+    }
+
+    Ok(newly_applied)
+}
+
+async fn record_applied(
+    tx: &mut Transaction<'_, Any>,
+    backend: DatabaseBackend,
+    migration: &Migration,
+    started: std::time::Instant,
+) -> AppResult<AppliedMigration> {
+    let record = AppliedMigration {
+        version: migration.version,
+        name: migration.name.to_string(),
+        checksum: migration.checksum(backend),
+        applied_at: Utc::now().to_rfc3339(),
+        duration_ms: started.elapsed().as_millis() as i64,
+    };
+    sqlx::query(
+        "INSERT INTO schema_migrations (version, name, checksum, applied_at, duration_ms) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(record.version)
+    .bind(&record.name)
+    .bind(&record.checksum)
+    .bind(&record.applied_at)
+    .bind(record.duration_ms)
+    .execute(&mut **tx)
+    .await?;
+    Ok(record)
+}
+
+async fn column_exists(
+    tx: &mut Transaction<'_, Any>,
+    table: &str,
+    column: &str,
+) -> AppResult<bool> {
+    let (count,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM pragma_table_info($1) WHERE name = $2")
+            .bind(table)
+            .bind(column)
+            .fetch_one(&mut **tx)
+            .await?;
+    Ok(count > 0)
+}
+
+/// `(table, column)` of an `ALTER TABLE .. ADD COLUMN ..` statement.
+fn added_column(statement: &str) -> Option<(&str, &str)> {
+    let mut words = statement.split_whitespace();
+    let keywords = ["ALTER", "TABLE"];
+    if !keywords
+        .iter()
+        .all(|k| words.next().is_some_and(|w| w.eq_ignore_ascii_case(k)))
+    {
+        return None;
+    }
+    let table = words.next()?;
+    let add = words.next()?;
+    let column = match words.next()? {
+        w if w.eq_ignore_ascii_case("COLUMN") => words.next()?,
+        w => w,
+    };
+    add.eq_ignore_ascii_case("ADD").then_some((table, column))
+}
+
+/// Bring a pre-migration database to the schema of `LEGACY_VERSION` and
+/// record the migrations up to it as applied, all in one transaction.
+///
+/// Tables are created only when missing, columns the old bootstrap added
+/// later are added when absent, and nothing else is touched.
+async fn adopt_legacy(pool: &AnyPool) -> AppResult<Vec<AppliedMigration>> {
+    let started = std::time::Instant::now();
+    let mut tx = pool.begin().await?;
+
+    for (table, column, definition) in LEGACY_COLUMNS {
+        let (table_count,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = $1")
+                .bind(table)
+                .fetch_one(&mut *tx)
+                .await?;
+        if table_count > 0 && !column_exists(&mut tx, table, column).await? {
+            sqlx::query(&format!(
+                "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+            ))
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version <= LEGACY_VERSION) {
+        for statement in split_statements(migration.sqlite) {
+            if let Some((table, column)) = added_column(&statement) {
+                if column_exists(&mut tx, table, column).await? {
+                    continue;
+                }
+            }
+            sqlx::query(&statement)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    AppError::InternalError(format!(
+                        "Adopting the existing database at migration {:04}_{} failed and was rolled back: {e}",
+                        migration.version, migration.name
+                    ))
+                })?;
+        }
+        applied.push(record_applied(&mut tx, DatabaseBackend::Sqlite, migration, started).await?);
+    }
+
+    tx.commit().await?;
+    tracing::info!(
+        "Adopted pre-migration database at schema version {}",
+        LEGACY_VERSION
+    );
+    Ok(applied)
+}
+
+/// Split a migration file into statements. Migrations never put `;` inside
+/// string literals, so splitting after comment removal is sufficient.
+fn split_statements(sql: &str) -> Vec<String> {
+    let without_comments: String = sql
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("--"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    without_comments
+        .split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_are_ordered_and_unique() {
+        for pair in MIGRATIONS.windows(2) {
+            assert_eq!(pair[1].version, pair[0].version + 1);
+        }
+        assert_eq!(MIGRATIONS[0].version, 1);
+        assert!(LEGACY_VERSION <= latest_version());
+    }
+
+    #[test]
+    fn recognises_add_column_statements() {
+        assert_eq!(
+            added_column("ALTER TABLE api_keys ADD COLUMN restrictions TEXT"),
+            Some(("api_keys", "restrictions"))
+        );
+        assert_eq!(
+            added_column("alter table users add locked_until TEXT"),
+            Some(("users", "locked_until"))
+        );
+        assert_eq!(added_column("ALTER TABLE users RENAME TO people"), None);
+        assert_eq!(added_column("CREATE TABLE x (id TEXT)"), None);
+    }
+
+    #[test]
+    fn splits_statements_and_drops_comments() {
+        let statements =
+            split_statements("-- a; comment\nCREATE TABLE a (x);\n\nINSERT INTO a VALUES (1);\n");
+        assert_eq!(
+            statements,
+            vec!["CREATE TABLE a (x)", "INSERT INTO a VALUES (1)"]
+        );
+    }
+}
@@ -1,15 +1,34 @@
+mod migrations;
+
+pub use migrations::{AppliedMigration, MigrationStatus, PendingMigration};
+
+pub use crate::config::DatabaseBackend;
 use crate::error::{AppError, AppResult};
+use crate::models::audit::{chain_hash, AuditContext, AuditEvent, AUDIT_CHAIN_GENESIS};
+use crate::models::git::VaultGitSettings;
+use crate::models::oidc::OidcAuthRequest;
+use crate::models::scim::{
+    ScimGroupFilter, ScimGroupRecord, ScimUserAttributes, ScimUserFilter, ScimUserRecord,
+};
+use crate::models::trash::TrashItem;
+use crate::models::upload::UploadSession;
+use crate::models::webauthn::{WebAuthnChallenge, WebAuthnCredential, WebAuthnPurpose};
 use crate::models::{
-    AdminUser, ApiKeyInfo, AuditLogEntry, EditorMode, GroupInfo, GroupMember, MlUndoReceipt,
-    ReverseAction, SessionInfo, UserPreferences, Vault, VaultRole, VaultRow, VaultShareEntry,
-    VaultShareList,
+    AclPrincipalType, AdminUser, ApiKeyInfo, ApiKeyRestrictions, AuditChainReport, AuditLogEntry,
+    AuditLogQuery, AuditOutcome, EditorMode, EnrolledFactors, EntityValidationMode, GroupInfo,
+    GroupMember, MlUndoReceipt, PathAccess, PathAclEntry, ReverseAction, ScimTokenInfo,
+    SessionInfo, ShareLink, ShareLinkComment, ShareLinkPermission, UserPreferences, Vault,
+    VaultRole, VaultRow, VaultShareEntry, VaultShareList,
 };
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
     Argon2,
 };
 use chrono::{DateTime, Utc};
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::any::AnyPoolOptions;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::AnyPool;
+use sqlx::ConnectOptions;
 use std::str::FromStr;
 use uuid::Uuid;
 
@@ -73,571 +92,480 @@ struct MlUndoReceiptRow {
     applied_at: String,
 }
 
-#[derive(Clone)]
-pub struct Database {
-    pool: SqlitePool,
+#[derive(sqlx::FromRow)]
+struct VaultGitSettingsRow {
+    vault_id: String,
+    enabled: i64,
+    auto_commit: i64,
+    remote_url: Option<String>,
+    branch: String,
+    sync_interval_secs: i64,
+    last_sync_at: Option<String>,
+    last_sync_error: Option<String>,
 }
 
-impl Database {
-    pub async fn new(database_url: &str) -> AppResult<Self> {
-        let options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+impl From<VaultGitSettingsRow> for VaultGitSettings {
+    fn from(row: VaultGitSettingsRow) -> Self {
+        Self {
+            vault_id: row.vault_id,
+            enabled: row.enabled != 0,
+            auto_commit: row.auto_commit != 0,
+            remote_url: row.remote_url.filter(|url| !url.is_empty()),
+            branch: row.branch,
+            sync_interval_secs: row.sync_interval_secs.max(0) as u64,
+            last_sync_at: row.last_sync_at.as_deref().map(parse_rfc3339_utc),
+            last_sync_error: row.last_sync_error,
+        }
+    }
+}
 
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect_with(options)
-            .await?;
+#[derive(sqlx::FromRow)]
+struct TrashItemRow {
+    trash_name: String,
+    original_path: String,
+    deleted_by: Option<String>,
+    deleted_by_username: Option<String>,
+    deleted_at: String,
+    size: i64,
+}
 
-        let db = Database { pool };
-        db.run_migrations().await?;
+impl From<TrashItemRow> for TrashItem {
+    fn from(row: TrashItemRow) -> Self {
+        Self {
+            trash_name: row.trash_name,
+            original_path: row.original_path,
+            trashed_at: row.deleted_at,
+            size: row.size.max(0) as u64,
+            deleted_by: row.deleted_by,
+            deleted_by_username: row.deleted_by_username,
+        }
+    }
+}
 
-        Ok(db)
+#[derive(sqlx::FromRow)]
+struct UploadSessionRow {
+    id: String,
+    vault_id: String,
+    owner_id: Option<String>,
+    filename: String,
+    path: String,
+    total_size: Option<i64>,
+    sha256: Option<String>,
+    created_at: String,
+    expires_at: String,
+}
+
+impl From<UploadSessionRow> for UploadSession {
+    fn from(row: UploadSessionRow) -> Self {
+        Self {
+            id: row.id,
+            vault_id: row.vault_id,
+            owner_id: row.owner_id,
+            filename: row.filename,
+            path: row.path,
+            total_size: row.total_size.map(|size| size.max(0) as u64),
+            sha256: row.sha256,
+            created_at: parse_rfc3339_utc(&row.created_at),
+            expires_at: parse_rfc3339_utc(&row.expires_at),
+        }
     }
+}
 
-    async fn run_migrations(&self) -> AppResult<()> {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS vaults (
-                id TEXT PRIMARY KEY NOT NULL,
-                name TEXT NOT NULL,
-                path TEXT NOT NULL UNIQUE,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+#[derive(sqlx::FromRow)]
+struct ApiKeyRow {
+    id: String,
+    name: String,
+    prefix: String,
+    key_hash: String,
+    user_id: String,
+    created_at: String,
+    expires_at: Option<String>,
+    revoked: i64,
+    restrictions: Option<String>,
+    last_used_at: Option<String>,
+    last_used_ip: Option<String>,
+}
 
-        // Create preferences table
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS preferences (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                theme TEXT NOT NULL DEFAULT 'dark',
-                editor_mode TEXT NOT NULL DEFAULT 'side_by_side',
-                font_size INTEGER NOT NULL DEFAULT 14,
-                window_layout TEXT,
-                icon_map TEXT,
-                updated_at TEXT NOT NULL
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+impl ApiKeyRow {
+    fn into_info(self) -> (ApiKeyInfo, String) {
+        let info = ApiKeyInfo {
+            id: self.id,
+            name: self.name,
+            prefix: self.prefix,
+            user_id: self.user_id,
+            created_at: parse_rfc3339_utc(&self.created_at),
+            expires_at: self.expires_at.map(|s| parse_rfc3339_utc(&s)),
+            revoked: self.revoked != 0,
+            restrictions: self
+                .restrictions
+                .as_deref()
+                .and_then(|raw| serde_json::from_str(raw).ok())
+                .unwrap_or_default(),
+            last_used_at: self.last_used_at.map(|s| parse_rfc3339_utc(&s)),
+            last_used_ip: self.last_used_ip,
+        };
+        (info, self.key_hash)
+    }
+}
 
-        // Initialize default preferences if not exists
-        sqlx::query(
-            r#"
-            INSERT OR IGNORE INTO preferences (id, theme, editor_mode, font_size, updated_at)
-            VALUES (1, 'dark', 'side_by_side', 14, ?)
-            "#,
-        )
-        .bind(Utc::now().to_rfc3339())
-        .execute(&self.pool)
-        .await?;
+#[derive(sqlx::FromRow)]
+struct PathAclRow {
+    id: String,
+    vault_id: String,
+    pattern: String,
+    principal_type: String,
+    principal_id: Option<String>,
+    access: String,
+    created_at: String,
+}
 
-        // Add window_layout column if it doesn't exist (for existing DBs)
-        let _ = sqlx::query("ALTER TABLE preferences ADD COLUMN window_layout TEXT")
-            .execute(&self.pool)
-            .await;
+impl PathAclRow {
+    fn into_entry(self) -> Option<PathAclEntry> {
+        let principal_type = match self.principal_type.as_str() {
+            "user" => AclPrincipalType::User,
+            "group" => AclPrincipalType::Group,
+            "everyone" => AclPrincipalType::Everyone,
+            _ => return None,
+        };
+        let access = match self.access.as_str() {
+            "none" => PathAccess::None,
+            "read" => PathAccess::Read,
+            "write" => PathAccess::Write,
+            _ => return None,
+        };
+        Some(PathAclEntry {
+            id: self.id,
+            vault_id: self.vault_id,
+            pattern: self.pattern,
+            principal_type,
+            principal_id: self.principal_id,
+            access,
+            created_at: parse_rfc3339_utc(&self.created_at),
+        })
+    }
+}
 
-        let _ = sqlx::query("ALTER TABLE preferences ADD COLUMN icon_map TEXT")
-            .execute(&self.pool)
-            .await;
+#[derive(sqlx::FromRow)]
+struct ShareLinkRow {
+    id: String,
+    vault_id: String,
+    path: String,
+    is_folder: i64,
+    permission: String,
+    password_hash: Option<String>,
+    expires_at: Option<String>,
+    created_by: Option<String>,
+    created_at: String,
+    revoked_at: Option<String>,
+    access_count: i64,
+    last_accessed_at: Option<String>,
+}
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS recent_files (
-                vault_id TEXT NOT NULL,
-                path TEXT NOT NULL,
-                last_accessed TEXT NOT NULL,
-                PRIMARY KEY (vault_id, path),
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+impl ShareLinkRow {
+    /// Split into the public link description and its password hash.
+    fn into_link(self) -> (ShareLink, Option<String>) {
+        let permission = match self.permission.as_str() {
+            "comment" => ShareLinkPermission::Comment,
+            _ => ShareLinkPermission::View,
+        };
+        let link = ShareLink {
+            id: self.id,
+            vault_id: self.vault_id,
+            path: self.path,
+            is_folder: self.is_folder != 0,
+            permission,
+            password_protected: self.password_hash.is_some(),
+            expires_at: self.expires_at.as_deref().map(parse_rfc3339_utc),
+            created_by: self.created_by,
+            created_at: parse_rfc3339_utc(&self.created_at),
+            revoked_at: self.revoked_at.as_deref().map(parse_rfc3339_utc),
+            access_count: self.access_count,
+            last_accessed_at: self.last_accessed_at.as_deref().map(parse_rfc3339_utc),
+        };
+        (link, self.password_hash)
+    }
+}
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS users (
-                id TEXT PRIMARY KEY,
-                username TEXT NOT NULL UNIQUE,
-                password_hash TEXT NOT NULL,
-                is_admin INTEGER NOT NULL DEFAULT 0,
-                must_change_password INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+#[derive(sqlx::FromRow)]
+struct ShareLinkCommentRow {
+    id: String,
+    link_id: String,
+    path: String,
+    author_name: Option<String>,
+    body: String,
+    created_at: String,
+}
 
-        let _ = sqlx::query("ALTER TABLE users ADD COLUMN is_admin INTEGER NOT NULL DEFAULT 0")
-            .execute(&self.pool)
-            .await;
+impl From<ShareLinkCommentRow> for ShareLinkComment {
+    fn from(row: ShareLinkCommentRow) -> Self {
+        Self {
+            id: row.id,
+            link_id: row.link_id,
+            path: row.path,
+            author_name: row.author_name,
+            body: row.body,
+            created_at: parse_rfc3339_utc(&row.created_at),
+        }
+    }
+}
 
-        let _ = sqlx::query(
-            "ALTER TABLE users ADD COLUMN must_change_password INTEGER NOT NULL DEFAULT 0",
-        )
-        .execute(&self.pool)
-        .await;
+#[derive(sqlx::FromRow)]
+struct WebAuthnCredentialRow {
+    id: String,
+    user_id: String,
+    name: String,
+    public_key: String,
+    algorithm: i64,
+    sign_count: i64,
+    aaguid: Option<String>,
+    created_at: String,
+    last_used_at: Option<String>,
+}
 
-        sqlx::query(
-            r#"
-            UPDATE users
-            SET is_admin = 1
-            WHERE id = (
-                SELECT id FROM users ORDER BY created_at ASC LIMIT 1
-            )
-            AND NOT EXISTS (
-                SELECT 1 FROM users WHERE is_admin = 1
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+impl From<WebAuthnCredentialRow> for WebAuthnCredential {
+    fn from(row: WebAuthnCredentialRow) -> Self {
+        Self {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            public_key: hex::decode(&row.public_key).unwrap_or_default(),
+            algorithm: row.algorithm,
+            sign_count: row.sign_count.clamp(0, u32::MAX as i64) as u32,
+            aaguid: row.aaguid,
+            created_at: parse_rfc3339_utc(&row.created_at),
+            last_used_at: row.last_used_at.as_deref().map(parse_rfc3339_utc),
+        }
+    }
+}
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS user_preferences (
-                user_id TEXT PRIMARY KEY,
-                theme TEXT NOT NULL DEFAULT 'dark',
-                editor_mode TEXT NOT NULL DEFAULT 'side_by_side',
-                font_size INTEGER NOT NULL DEFAULT 14,
-                window_layout TEXT,
-                icon_map TEXT,
-                updated_at TEXT NOT NULL,
-                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+#[derive(sqlx::FromRow)]
+struct ScimTokenRow {
+    id: String,
+    name: String,
+    created_by_user_id: String,
+    created_at: String,
+    last_used_at: Option<String>,
+    revoked_at: Option<String>,
+}
 
-        let _ = sqlx::query("ALTER TABLE user_preferences ADD COLUMN icon_map TEXT")
-            .execute(&self.pool)
-            .await;
+impl From<ScimTokenRow> for ScimTokenInfo {
+    fn from(row: ScimTokenRow) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            created_by_user_id: row.created_by_user_id,
+            created_at: parse_rfc3339_utc(&row.created_at),
+            last_used_at: row.last_used_at.as_deref().map(parse_rfc3339_utc),
+            revoked_at: row.revoked_at.as_deref().map(parse_rfc3339_utc),
+        }
+    }
+}
 
-        let _ =
-            sqlx::query("ALTER TABLE vaults ADD COLUMN owner_user_id TEXT REFERENCES users(id)")
-                .execute(&self.pool)
-                .await;
+#[derive(sqlx::FromRow)]
+struct ScimUserRow {
+    id: String,
+    username: String,
+    external_id: Option<String>,
+    display_name: Option<String>,
+    email: Option<String>,
+    is_active: i64,
+    created_at: String,
+}
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS groups (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL UNIQUE,
-                created_at TEXT NOT NULL,
-                created_by_user_id TEXT NOT NULL,
-                FOREIGN KEY (created_by_user_id) REFERENCES users(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+impl From<ScimUserRow> for ScimUserRecord {
+    fn from(row: ScimUserRow) -> Self {
+        Self {
+            id: row.id,
+            username: row.username,
+            external_id: row.external_id,
+            display_name: row.display_name,
+            email: row.email,
+            is_active: row.is_active != 0,
+            created_at: parse_rfc3339_utc(&row.created_at),
+        }
+    }
+}
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS group_members (
-                group_id TEXT NOT NULL,
-                user_id TEXT NOT NULL,
-                added_at TEXT NOT NULL,
-                PRIMARY KEY (group_id, user_id),
-                FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
-                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+const SCIM_USER_COLUMNS: &str =
+    "id, username, external_id, display_name, email, is_active, created_at";
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS vault_user_shares (
-                vault_id TEXT NOT NULL,
-                user_id TEXT NOT NULL,
-                role TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                PRIMARY KEY (vault_id, user_id),
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
-                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+const AUDIT_LOG_COLUMNS: &str =
+    "id, timestamp, user_id, username, event_type, detail, ip_address, \
+     success, outcome, vault_id, path, request_id, prev_hash, hash";
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS vault_group_shares (
-                vault_id TEXT NOT NULL,
-                group_id TEXT NOT NULL,
-                role TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                PRIMARY KEY (vault_id, group_id),
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
-                FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+#[derive(sqlx::FromRow)]
+struct AuditLogRow {
+    id: i64,
+    timestamp: String,
+    user_id: Option<String>,
+    username: Option<String>,
+    event_type: String,
+    detail: Option<String>,
+    ip_address: Option<String>,
+    success: i64,
+    outcome: String,
+    vault_id: Option<String>,
+    path: Option<String>,
+    request_id: Option<String>,
+    prev_hash: Option<String>,
+    hash: Option<String>,
+}
 
-        sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_group_members_user_id ON group_members(user_id)",
-        )
-        .execute(&self.pool)
-        .await?;
+impl From<AuditLogRow> for AuditLogEntry {
+    fn from(row: AuditLogRow) -> Self {
+        Self {
+            id: row.id,
+            timestamp: parse_rfc3339_utc(&row.timestamp),
+            user_id: row.user_id,
+            username: row.username,
+            event_type: row.event_type,
+            detail: row.detail,
+            ip_address: row.ip_address,
+            success: row.success != 0,
+            outcome: AuditOutcome::parse(&row.outcome).unwrap_or(if row.success != 0 {
+                AuditOutcome::Success
+            } else {
+                AuditOutcome::Failure
+            }),
+            vault_id: row.vault_id,
+            path: row.path,
+            request_id: row.request_id,
+            prev_hash: row.prev_hash,
+            hash: row.hash,
+        }
+    }
+}
 
-        sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_vault_user_shares_user_id ON vault_user_shares(user_id)"
-        )
-        .execute(&self.pool)
-        .await?;
+#[derive(Clone)]
+pub struct Database {
+    pool: AnyPool,
+    backend: DatabaseBackend,
+    audit_hash_chain: bool,
+    /// Serializes chained audit appends within this process; the unique
+    /// `prev_hash` index settles races between instances.
+    audit_chain_lock: std::sync::Arc<tokio::sync::Mutex<()>>,
+}
 
-        sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_vault_group_shares_group_id ON vault_group_shares(group_id)"
-        )
-        .execute(&self.pool)
-        .await?;
+impl Database {
+    /// Open the database and bring its schema up to date.
+    pub async fn new(database_url: &str) -> AppResult<Self> {
+        let db = Self::connect(database_url).await?;
+        db.migrate().await?;
+        Ok(db)
+    }
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS file_change_log (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                vault_id TEXT NOT NULL,
-                path TEXT NOT NULL,
-                event_type TEXT NOT NULL,
-                etag TEXT,
-                old_path TEXT,
-                timestamp INTEGER NOT NULL,
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+    /// Open the database without touching its schema. The backend follows
+    /// the URL scheme: `sqlite:` or `postgres://`.
+    pub async fn connect(database_url: &str) -> AppResult<Self> {
+        Self::connect_with(database_url, 5).await
+    }
 
-        sqlx::query(
-            r#"
-            CREATE INDEX IF NOT EXISTS idx_file_change_log_vault_timestamp
-            ON file_change_log(vault_id, timestamp)
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+    pub async fn connect_with(database_url: &str, max_connections: u32) -> AppResult<Self> {
+        sqlx::any::install_default_drivers();
+        let backend =
+            if database_url.starts_with("postgres:") || database_url.starts_with("postgresql:") {
+                DatabaseBackend::Postgres
+            } else if database_url.starts_with("sqlite:") {
+                DatabaseBackend::Sqlite
+            } else {
+                return Err(AppError::InvalidInput(format!(
+                    "Unsupported database URL scheme: {}",
+                    database_url.split(':').next().unwrap_or_default()
+                )));
+            };
 
-        // Bookmarks table
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS bookmarks (
-                id TEXT PRIMARY KEY NOT NULL,
-                vault_id TEXT NOT NULL,
-                path TEXT NOT NULL,
-                title TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
-                UNIQUE(vault_id, path)
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+        let pool_options = AnyPoolOptions::new().max_connections(max_connections.max(2));
+        let pool = match backend {
+            // The Any driver only takes URLs; round-trip through the SQLite
+            // options so the file is created when missing.
+            DatabaseBackend::Sqlite => {
+                let options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+                pool_options
+                    .connect(options.to_url_lossy().as_str())
+                    .await?
+            }
+            DatabaseBackend::Postgres => pool_options.connect(database_url).await?,
+        };
 
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_bookmarks_vault_id ON bookmarks(vault_id)")
-            .execute(&self.pool)
-            .await?;
+        Ok(Database {
+            pool,
+            backend,
+            audit_hash_chain: false,
+            audit_chain_lock: Default::default(),
+        })
+    }
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS ml_undo_receipts (
-                receipt_id TEXT PRIMARY KEY NOT NULL,
-                vault_id TEXT NOT NULL,
-                file_path TEXT NOT NULL,
-                description TEXT NOT NULL,
-                reverse_action TEXT NOT NULL,
-                applied_at TEXT NOT NULL,
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+    /// Chain every audit entry written from now on to the one before it
+    /// (`[audit] hash_chain`).
+    pub fn with_audit_hash_chain(mut self, enabled: bool) -> Self {
+        self.audit_hash_chain = enabled;
+        self
+    }
 
-        sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_ml_undo_receipts_vault_id ON ml_undo_receipts(vault_id)",
-        )
-        .execute(&self.pool)
-        .await?;
+    pub fn backend(&self) -> DatabaseBackend {
+        self.backend
+    }
 
-        // ── Multi-user hardening migrations ─────────────────────────────────
+    /// Apply every pending schema migration. Fails without changes when the
+    /// database was written by a newer build.
+    pub async fn migrate(&self) -> AppResult<Vec<AppliedMigration>> {
+        migrations::migrate(&self.pool, self.backend, None).await
+    }
 
-        // Add is_active flag for user deactivation (default true = active).
-        let _ = sqlx::query("ALTER TABLE users ADD COLUMN is_active INTEGER NOT NULL DEFAULT 1")
-            .execute(&self.pool)
-            .await;
+    /// Apply pending schema migrations up to and including `version`.
+    pub async fn migrate_to(&self, version: i64) -> AppResult<Vec<AppliedMigration>> {
+        migrations::migrate(&self.pool, self.backend, Some(version)).await
+    }
 
-        // Failed-login tracking columns for account lockout.
-        let _ = sqlx::query(
-            "ALTER TABLE users ADD COLUMN failed_login_attempts INTEGER NOT NULL DEFAULT 0",
-        )
-        .execute(&self.pool)
-        .await;
+    pub async fn migration_status(&self) -> AppResult<MigrationStatus> {
+        migrations::status(&self.pool, self.backend).await
+    }
+
+    pub fn pool(&self) -> &AnyPool {
+        &self.pool
+    }
 
-        let _ = sqlx::query("ALTER TABLE users ADD COLUMN locked_until TEXT")
+    /// Write a consistent copy of the whole database to `path` (which must
+    /// not exist yet) without blocking other connections for long. Only
+    /// SQLite databases can be copied this way; back up Postgres with its
+    /// own tooling.
+    pub async fn vacuum_into(&self, path: &std::path::Path) -> AppResult<()> {
+        if self.backend != DatabaseBackend::Sqlite {
+            return Err(AppError::InvalidInput(
+                "Database snapshots are only supported for the SQLite backend".to_string(),
+            ));
+        }
+        sqlx::query("VACUUM INTO $1")
+            .bind(path.to_string_lossy().to_string())
             .execute(&self.pool)
-            .await;
+            .await?;
+        Ok(())
+    }
+
+    pub async fn user_count(&self) -> AppResult<i64> {
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::from)?;
+        Ok(count.0)
+    }
+
+    pub async fn save_ml_undo_receipt(&self, receipt: &MlUndoReceipt) -> AppResult<()> {
+        let reverse_action = serde_json::to_string(&receipt.reverse_action).map_err(|e| {
+            AppError::InternalError(format!("Failed to serialize reverse action: {e}"))
+        })?;
 
-        // Audit log table for security-relevant events.
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS audit_log (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                timestamp TEXT NOT NULL,
-                user_id TEXT,
-                username TEXT,
-                event_type TEXT NOT NULL,
-                detail TEXT,
-                ip_address TEXT,
-                success INTEGER NOT NULL DEFAULT 1
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp)")
-            .execute(&self.pool)
-            .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_log_user_id ON audit_log(user_id)")
-            .execute(&self.pool)
-            .await?;
-
-        // Active sessions table for token revocation support.
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS sessions (
-                token_id TEXT PRIMARY KEY,
-                user_id TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                expires_at TEXT NOT NULL,
-                revoked INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id)")
-            .execute(&self.pool)
-            .await?;
-
-        // API keys table for programmatic access.
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS api_keys (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                prefix TEXT NOT NULL,
-                key_hash TEXT NOT NULL,
-                user_id TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                expires_at TEXT,
-                revoked INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_api_keys_user_id ON api_keys(user_id)")
-            .execute(&self.pool)
-            .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_api_keys_prefix ON api_keys(prefix)")
-            .execute(&self.pool)
-            .await?;
-
-        // Vault visibility: 'private' (default) or 'public'.
-        let _ =
-            sqlx::query("ALTER TABLE vaults ADD COLUMN visibility TEXT NOT NULL DEFAULT 'private'")
-                .execute(&self.pool)
-                .await;
-
-        // Document format used by this vault. Currently always 'markdown';
-        // reserved for a future 'mdx' upgrade (Phase 5 — format abstraction).
-        let _ = sqlx::query(
-            "ALTER TABLE vaults ADD COLUMN document_format TEXT NOT NULL DEFAULT 'markdown'",
-        )
-        .execute(&self.pool)
-        .await;
-
-        // ── Phase 4b: TOTP 2FA ──────────────────────────────────────────
-        let _ = sqlx::query("ALTER TABLE users ADD COLUMN totp_secret TEXT")
-            .execute(&self.pool)
-            .await;
-
-        let _ = sqlx::query("ALTER TABLE users ADD COLUMN totp_enabled INTEGER NOT NULL DEFAULT 0")
-            .execute(&self.pool)
-            .await;
-
-        let _ = sqlx::query("ALTER TABLE users ADD COLUMN totp_backup_codes TEXT")
-            .execute(&self.pool)
-            .await;
-
-        // ── Phase 4b: Invitations ───────────────────────────────────────
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS invitations (
-                id TEXT PRIMARY KEY,
-                token TEXT NOT NULL UNIQUE,
-                role TEXT NOT NULL DEFAULT 'viewer',
-                vault_id TEXT,
-                created_by_user_id TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                expires_at TEXT NOT NULL,
-                accepted INTEGER NOT NULL DEFAULT 0,
-                accepted_by_user_id TEXT,
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
-                FOREIGN KEY (created_by_user_id) REFERENCES users(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query(
-            "CREATE UNIQUE INDEX IF NOT EXISTS idx_invitations_token ON invitations(token)",
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // ── Worldbuilding: label registry ────────────────────────────────────
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS labels (
-                name        TEXT PRIMARY KEY NOT NULL,
-                description TEXT,
-                source      TEXT NOT NULL CHECK(source IN ('core', 'plugin')),
-                plugin_id   TEXT
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // ── Worldbuilding: entity store (derived from frontmatter) ───────────
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS entities (
-                id          TEXT PRIMARY KEY NOT NULL,
-                vault_id    TEXT NOT NULL,
-                path        TEXT NOT NULL,
-                entity_type TEXT NOT NULL,
-                plugin_id   TEXT NOT NULL,
-                labels      TEXT NOT NULL,
-                fields      TEXT NOT NULL,
-                modified_at TEXT NOT NULL,
-                indexed_at  TEXT NOT NULL,
-                UNIQUE(vault_id, path),
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_entities_vault_id ON entities(vault_id)")
-            .execute(&self.pool)
-            .await?;
-
-        sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_entities_vault_type ON entities(vault_id, entity_type)",
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // ── Worldbuilding: relation edges (derived from entity_ref fields) ────
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS relations (
-                id              TEXT PRIMARY KEY NOT NULL,
-                vault_id        TEXT NOT NULL,
-                from_entity_id  TEXT NOT NULL,
-                to_entity_id    TEXT NOT NULL,
-                relation_type   TEXT NOT NULL,
-                direction       TEXT NOT NULL CHECK(direction IN ('forward', 'inverse')),
-                metadata        TEXT,
-                source          TEXT NOT NULL CHECK(source IN ('field', 'explicit')),
-                source_field    TEXT,
-                created_at      TEXT NOT NULL,
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
-                FOREIGN KEY (from_entity_id) REFERENCES entities(id) ON DELETE CASCADE,
-                FOREIGN KEY (to_entity_id) REFERENCES entities(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_relations_from ON relations(from_entity_id)")
-            .execute(&self.pool)
-            .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_relations_to ON relations(to_entity_id)")
-            .execute(&self.pool)
-            .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_relations_vault ON relations(vault_id)")
-            .execute(&self.pool)
-            .await?;
-
-        // ── Worldbuilding: reindex audit log ─────────────────────────────────
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS reindex_log (
-                vault_id     TEXT PRIMARY KEY NOT NULL,
-                completed_at TEXT NOT NULL,
-                file_count   INTEGER NOT NULL,
-                duration_ms  INTEGER NOT NULL,
-                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
-
-    pub fn pool(&self) -> &SqlitePool {
-        &self.pool
-    }
-
-    pub async fn user_count(&self) -> AppResult<i64> {
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
-            .fetch_one(&self.pool)
-            .await
-            .map_err(AppError::from)?;
-        Ok(count.0)
-    }
-
-    pub async fn save_ml_undo_receipt(&self, receipt: &MlUndoReceipt) -> AppResult<()> {
-        let reverse_action = serde_json::to_string(&receipt.reverse_action).map_err(|e| {
-            AppError::InternalError(format!("Failed to serialize reverse action: {e}"))
-        })?;
-
-        sqlx::query(
-            r#"
-            INSERT OR REPLACE INTO ml_undo_receipts
-            (receipt_id, vault_id, file_path, description, reverse_action, applied_at)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO ml_undo_receipts
+            (receipt_id, vault_id, file_path, description, reverse_action, applied_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (receipt_id) DO UPDATE SET
+                vault_id = excluded.vault_id,
+                file_path = excluded.file_path,
+                description = excluded.description,
+                reverse_action = excluded.reverse_action,
+                applied_at = excluded.applied_at
             "#,
         )
         .bind(&receipt.receipt_id)
@@ -662,7 +590,7 @@ impl Database {
             r#"
             SELECT receipt_id, vault_id, file_path, description, reverse_action, applied_at
             FROM ml_undo_receipts
-            WHERE vault_id = ? AND receipt_id = ?
+            WHERE vault_id = $1 AND receipt_id = $2
             "#,
         )
         .bind(vault_id)
@@ -678,7 +606,7 @@ impl Database {
         })?;
 
         let delete_result =
-            sqlx::query("DELETE FROM ml_undo_receipts WHERE vault_id = ? AND receipt_id = ?")
+            sqlx::query("DELETE FROM ml_undo_receipts WHERE vault_id = $1 AND receipt_id = $2")
                 .bind(vault_id)
                 .bind(receipt_id)
                 .execute(&self.pool)
@@ -712,7 +640,7 @@ impl Database {
         vault_id: &str,
     ) -> AppResult<Vec<crate::models::bookmarks::Bookmark>> {
         let rows = sqlx::query_as::<_, crate::models::bookmarks::Bookmark>(
-            "SELECT id, vault_id, path, title, created_at FROM bookmarks WHERE vault_id = ? ORDER BY created_at DESC"
+            "SELECT id, vault_id, path, title, created_at FROM bookmarks WHERE vault_id = $1 ORDER BY created_at DESC"
         )
         .bind(vault_id)
         .fetch_all(&self.pool)
@@ -723,7 +651,13 @@ impl Database {
 
     pub async fn create_bookmark(&self, bm: &crate::models::bookmarks::Bookmark) -> AppResult<()> {
         sqlx::query(
-            "INSERT OR REPLACE INTO bookmarks (id, vault_id, path, title, created_at) VALUES (?, ?, ?, ?, ?)"
+            r#"
+            INSERT INTO bookmarks (id, vault_id, path, title, created_at) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (vault_id, path) DO UPDATE SET
+                id = excluded.id,
+                title = excluded.title,
+                created_at = excluded.created_at
+            "#,
         )
         .bind(&bm.id)
         .bind(&bm.vault_id)
@@ -737,7 +671,7 @@ impl Database {
     }
 
     pub async fn delete_bookmark(&self, vault_id: &str, bookmark_id: &str) -> AppResult<()> {
-        sqlx::query("DELETE FROM bookmarks WHERE vault_id = ? AND id = ?")
+        sqlx::query("DELETE FROM bookmarks WHERE vault_id = $1 AND id = $2")
             .bind(vault_id)
             .bind(bookmark_id)
             .execute(&self.pool)
@@ -776,7 +710,7 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO users (id, username, password_hash, is_admin, must_change_password, created_at)
-            VALUES (?, ?, ?, ?, ?, ?)
+            VALUES ($1, $2, $3, $4, $5, $6)
             "#,
         )
         .bind(&id)
@@ -788,7 +722,7 @@ impl Database {
         .execute(&self.pool)
         .await
         .map_err(|e| match e {
-            sqlx::Error::Database(db_err) if db_err.message().contains("UNIQUE") => {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
                 AppError::Conflict("User with this username already exists".to_string())
             }
             _ => AppError::from(e),
@@ -807,7 +741,7 @@ impl Database {
         username: &str,
     ) -> AppResult<Option<(String, String, String)>> {
         let row: Option<(String, String, String)> =
-            sqlx::query_as("SELECT id, username, password_hash FROM users WHERE username = ?")
+            sqlx::query_as("SELECT id, username, password_hash FROM users WHERE username = $1")
                 .bind(username)
                 .fetch_optional(&self.pool)
                 .await
@@ -821,7 +755,7 @@ impl Database {
         user_id: &str,
     ) -> AppResult<Option<(String, String, String)>> {
         let row: Option<(String, String, String)> =
-            sqlx::query_as("SELECT id, username, password_hash FROM users WHERE id = ?")
+            sqlx::query_as("SELECT id, username, password_hash FROM users WHERE id = $1")
                 .bind(user_id)
                 .fetch_optional(&self.pool)
                 .await
@@ -831,7 +765,7 @@ impl Database {
     }
 
     pub async fn is_user_admin(&self, user_id: &str) -> AppResult<bool> {
-        let row: Option<(i64,)> = sqlx::query_as("SELECT is_admin FROM users WHERE id = ?")
+        let row: Option<(i64,)> = sqlx::query_as("SELECT is_admin FROM users WHERE id = $1")
             .bind(user_id)
             .fetch_optional(&self.pool)
             .await
@@ -841,7 +775,7 @@ impl Database {
 
     pub async fn user_must_change_password(&self, user_id: &str) -> AppResult<bool> {
         let row: Option<(i64,)> =
-            sqlx::query_as("SELECT must_change_password FROM users WHERE id = ?")
+            sqlx::query_as("SELECT must_change_password FROM users WHERE id = $1")
                 .bind(user_id)
                 .fetch_optional(&self.pool)
                 .await
@@ -857,10 +791,29 @@ impl Database {
         .await
         .map_err(AppError::from)?;
 
+        let totp_users: Vec<(String,)> =
+            sqlx::query_as("SELECT id FROM users WHERE totp_enabled != 0")
+                .fetch_all(&self.pool)
+                .await?;
+        let totp_users: std::collections::HashSet<String> =
+            totp_users.into_iter().map(|(id,)| id).collect();
+        let mut credentials: std::collections::HashMap<String, Vec<_>> =
+            std::collections::HashMap::new();
+        for credential in self.list_all_webauthn_credentials().await? {
+            credentials
+                .entry(credential.user_id.clone())
+                .or_default()
+                .push(credential.info());
+        }
+
         Ok(rows
             .into_iter()
             .map(
                 |(id, username, is_admin, must_change_password, is_active, created_at)| AdminUser {
+                    factors: EnrolledFactors {
+                        totp: totp_users.contains(&id),
+                        webauthn: credentials.remove(&id).unwrap_or_default(),
+                    },
                     id,
                     username,
                     is_admin: is_admin != 0,
@@ -872,6 +825,18 @@ impl Database {
             .collect())
     }
 
+    pub async fn set_user_admin(&self, user_id: &str, is_admin: bool) -> AppResult<()> {
+        let result = sqlx::query("UPDATE users SET is_admin = $1 WHERE id = $2")
+            .bind(if is_admin { 1_i64 } else { 0_i64 })
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("User {user_id} not found")));
+        }
+        Ok(())
+    }
+
     pub async fn set_user_password(
         &self,
         user_id: &str,
@@ -879,7 +844,7 @@ impl Database {
         must_change_password: bool,
     ) -> AppResult<()> {
         let result = sqlx::query(
-            "UPDATE users SET password_hash = ?, must_change_password = ? WHERE id = ?",
+            "UPDATE users SET password_hash = $1, must_change_password = $2 WHERE id = $3",
         )
         .bind(password_hash)
         .bind(if must_change_password { 1_i64 } else { 0_i64 })
@@ -896,7 +861,7 @@ impl Database {
 
     pub async fn get_user_by_id(&self, user_id: &str) -> AppResult<Option<(String, String)>> {
         let row =
-            sqlx::query_as::<_, (String, String)>("SELECT id, username FROM users WHERE id = ?")
+            sqlx::query_as::<_, (String, String)>("SELECT id, username FROM users WHERE id = $1")
                 .bind(user_id)
                 .fetch_optional(&self.pool)
                 .await
@@ -910,7 +875,7 @@ impl Database {
         username: &str,
     ) -> AppResult<Option<(String, String)>> {
         let row = sqlx::query_as::<_, (String, String)>(
-            "SELECT id, username FROM users WHERE username = ?",
+            "SELECT id, username FROM users WHERE username = $1",
         )
         .bind(username)
         .fetch_optional(&self.pool)
@@ -934,7 +899,7 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO groups (id, name, created_at, created_by_user_id)
-            VALUES (?, ?, ?, ?)
+            VALUES ($1, $2, $3, $4)
             "#,
         )
         .bind(&id)
@@ -944,7 +909,7 @@ impl Database {
         .execute(&self.pool)
         .await
         .map_err(|e| match e {
-            sqlx::Error::Database(db_err) if db_err.message().contains("UNIQUE") => {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
                 AppError::Conflict("Group with this name already exists".to_string())
             }
             _ => AppError::from(e),
@@ -952,8 +917,9 @@ impl Database {
 
         sqlx::query(
             r#"
-            INSERT OR IGNORE INTO group_members (group_id, user_id, added_at)
-            VALUES (?, ?, ?)
+            INSERT INTO group_members (group_id, user_id, added_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
             "#,
         )
         .bind(&id)
@@ -969,13 +935,27 @@ impl Database {
         })
     }
 
+    pub async fn get_group_by_name(&self, name: &str) -> AppResult<Option<GroupInfo>> {
+        let row = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT id, name, created_at FROM groups WHERE name = $1",
+        )
+        .bind(name.trim())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|(id, name, created_at)| GroupInfo {
+            id,
+            name,
+            created_at: parse_rfc3339_utc(&created_at),
+        }))
+    }
+
     pub async fn list_groups_for_user(&self, user_id: &str) -> AppResult<Vec<GroupInfo>> {
         let rows = sqlx::query_as::<_, (String, String, String)>(
             r#"
             SELECT DISTINCT g.id, g.name, g.created_at
             FROM groups g
             LEFT JOIN group_members gm ON gm.group_id = g.id
-            WHERE gm.user_id = ? OR g.created_by_user_id = ?
+            WHERE gm.user_id = $1 OR g.created_by_user_id = $2
             ORDER BY g.name ASC
             "#,
         )
@@ -995,9 +975,19 @@ impl Database {
             .collect())
     }
 
+    /// Ids of the groups `user_id` is a member of.
+    pub async fn list_group_ids_for_member(&self, user_id: &str) -> AppResult<Vec<String>> {
+        let rows =
+            sqlx::query_as::<_, (String,)>("SELECT group_id FROM group_members WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
     pub async fn is_group_manager(&self, group_id: &str, user_id: &str) -> AppResult<bool> {
         let row = sqlx::query_as::<_, (i64,)>(
-            "SELECT COUNT(*) FROM groups WHERE id = ? AND created_by_user_id = ?",
+            "SELECT COUNT(*) FROM groups WHERE id = $1 AND created_by_user_id = $2",
         )
         .bind(group_id)
         .bind(user_id)
@@ -1013,7 +1003,7 @@ impl Database {
             SELECT u.id, u.username
             FROM group_members gm
             INNER JOIN users u ON u.id = gm.user_id
-            WHERE gm.group_id = ?
+            WHERE gm.group_id = $1
             ORDER BY u.username ASC
             "#,
         )
@@ -1029,7 +1019,7 @@ impl Database {
     }
 
     pub async fn add_user_to_group(&self, group_id: &str, user_id: &str) -> AppResult<()> {
-        let group_exists: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM groups WHERE id = ?")
+        let group_exists: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM groups WHERE id = $1")
             .bind(group_id)
             .fetch_one(&self.pool)
             .await?;
@@ -1037,7 +1027,7 @@ impl Database {
             return Err(AppError::NotFound(format!("Group {} not found", group_id)));
         }
 
-        let user_exists: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE id = ?")
+        let user_exists: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE id = $1")
             .bind(user_id)
             .fetch_one(&self.pool)
             .await?;
@@ -1047,8 +1037,9 @@ impl Database {
 
         sqlx::query(
             r#"
-            INSERT OR IGNORE INTO group_members (group_id, user_id, added_at)
-            VALUES (?, ?, ?)
+            INSERT INTO group_members (group_id, user_id, added_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
             "#,
         )
         .bind(group_id)
@@ -1061,7 +1052,7 @@ impl Database {
     }
 
     pub async fn remove_user_from_group(&self, group_id: &str, user_id: &str) -> AppResult<()> {
-        let result = sqlx::query("DELETE FROM group_members WHERE group_id = ? AND user_id = ?")
+        let result = sqlx::query("DELETE FROM group_members WHERE group_id = $1 AND user_id = $2")
             .bind(group_id)
             .bind(user_id)
             .execute(&self.pool)
@@ -1141,7 +1132,7 @@ impl Database {
         let row = sqlx::query_as::<_, VaultRow>(
             r#"
             INSERT INTO vaults (id, name, path, created_at, updated_at, owner_user_id, document_format)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             RETURNING id, name, path, created_at, updated_at, document_format
             "#,
         )
@@ -1155,7 +1146,7 @@ impl Database {
         .fetch_one(&self.pool)
         .await
         .map_err(|e| match e {
-            sqlx::Error::Database(db_err) if db_err.message().contains("UNIQUE") => {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
                 AppError::Conflict("Vault with this path already exists".to_string())
             }
             _ => AppError::from(e),
@@ -1165,7 +1156,7 @@ impl Database {
     }
 
     pub async fn get_vault(&self, id: &str) -> AppResult<Vault> {
-        let row = sqlx::query_as::<_, VaultRow>("SELECT * FROM vaults WHERE id = ?")
+        let row = sqlx::query_as::<_, VaultRow>("SELECT * FROM vaults WHERE id = $1")
             .bind(id)
             .fetch_one(&self.pool)
             .await
@@ -1191,10 +1182,10 @@ impl Database {
             r#"
             SELECT DISTINCT v.id, v.name, v.path, v.created_at, v.updated_at, v.document_format
             FROM vaults v
-            LEFT JOIN vault_user_shares vus ON vus.vault_id = v.id AND vus.user_id = ?
-            LEFT JOIN group_members gm ON gm.user_id = ?
+            LEFT JOIN vault_user_shares vus ON vus.vault_id = v.id AND vus.user_id = $1
+            LEFT JOIN group_members gm ON gm.user_id = $2
             LEFT JOIN vault_group_shares vgs ON vgs.vault_id = v.id AND vgs.group_id = gm.group_id
-            WHERE v.owner_user_id = ?
+            WHERE v.owner_user_id = $3
                OR vus.user_id IS NOT NULL
                OR vgs.group_id IS NOT NULL
                OR (
@@ -1220,11 +1211,12 @@ impl Database {
         vault_id: &str,
         user_id: &str,
     ) -> AppResult<Option<VaultRole>> {
-        let owner_row =
-            sqlx::query_as::<_, (Option<String>,)>("SELECT owner_user_id FROM vaults WHERE id = ?")
-                .bind(vault_id)
-                .fetch_optional(&self.pool)
-                .await?;
+        let owner_row = sqlx::query_as::<_, (Option<String>,)>(
+            "SELECT owner_user_id FROM vaults WHERE id = $1",
+        )
+        .bind(vault_id)
+        .fetch_optional(&self.pool)
+        .await?;
 
         let Some((owner_user_id,)) = owner_row else {
             return Ok(None);
@@ -1235,7 +1227,7 @@ impl Database {
         }
 
         let direct_roles = sqlx::query_as::<_, (String,)>(
-            "SELECT role FROM vault_user_shares WHERE vault_id = ? AND user_id = ?",
+            "SELECT role FROM vault_user_shares WHERE vault_id = $1 AND user_id = $2",
         )
         .bind(vault_id)
         .bind(user_id)
@@ -1254,7 +1246,7 @@ impl Database {
             SELECT vgs.role
             FROM vault_group_shares vgs
             INNER JOIN group_members gm ON gm.group_id = vgs.group_id
-            WHERE vgs.vault_id = ? AND gm.user_id = ?
+            WHERE vgs.vault_id = $1 AND gm.user_id = $2
             "#,
         )
         .bind(vault_id)
@@ -1278,9 +1270,9 @@ impl Database {
         let share_count: (i64,) = sqlx::query_as(
             r#"
             SELECT (
-                (SELECT COUNT(*) FROM vault_user_shares WHERE vault_id = ?)
+                (SELECT COUNT(*) FROM vault_user_shares WHERE vault_id = $1)
                 +
-                (SELECT COUNT(*) FROM vault_group_shares WHERE vault_id = ?)
+                (SELECT COUNT(*) FROM vault_group_shares WHERE vault_id = $2)
             )
             "#,
         )
@@ -1308,7 +1300,7 @@ impl Database {
             ));
         }
 
-        let user_exists: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE id = ?")
+        let user_exists: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE id = $1")
             .bind(user_id)
             .fetch_one(&self.pool)
             .await?;
@@ -1319,7 +1311,7 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO vault_user_shares (vault_id, user_id, role, created_at)
-            VALUES (?, ?, ?, ?)
+            VALUES ($1, $2, $3, $4)
             ON CONFLICT(vault_id, user_id) DO UPDATE SET role = excluded.role
             "#,
         )
@@ -1345,7 +1337,7 @@ impl Database {
             ));
         }
 
-        let group_exists: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM groups WHERE id = ?")
+        let group_exists: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM groups WHERE id = $1")
             .bind(group_id)
             .fetch_one(&self.pool)
             .await?;
@@ -1356,7 +1348,7 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO vault_group_shares (vault_id, group_id, role, created_at)
-            VALUES (?, ?, ?, ?)
+            VALUES ($1, $2, $3, $4)
             ON CONFLICT(vault_id, group_id) DO UPDATE SET role = excluded.role
             "#,
         )
@@ -1372,7 +1364,7 @@ impl Database {
 
     pub async fn revoke_vault_user_share(&self, vault_id: &str, user_id: &str) -> AppResult<()> {
         let result =
-            sqlx::query("DELETE FROM vault_user_shares WHERE vault_id = ? AND user_id = ?")
+            sqlx::query("DELETE FROM vault_user_shares WHERE vault_id = $1 AND user_id = $2")
                 .bind(vault_id)
                 .bind(user_id)
                 .execute(&self.pool)
@@ -1390,7 +1382,7 @@ impl Database {
 
     pub async fn revoke_vault_group_share(&self, vault_id: &str, group_id: &str) -> AppResult<()> {
         let result =
-            sqlx::query("DELETE FROM vault_group_shares WHERE vault_id = ? AND group_id = ?")
+            sqlx::query("DELETE FROM vault_group_shares WHERE vault_id = $1 AND group_id = $2")
                 .bind(vault_id)
                 .bind(group_id)
                 .execute(&self.pool)
@@ -1406,108 +1398,299 @@ impl Database {
         Ok(())
     }
 
-    pub async fn list_vault_shares(&self, vault_id: &str) -> AppResult<VaultShareList> {
-        let owner =
-            sqlx::query_as::<_, (Option<String>,)>("SELECT owner_user_id FROM vaults WHERE id = ?")
-                .bind(vault_id)
-                .fetch_optional(&self.pool)
-                .await?
-                .ok_or_else(|| AppError::NotFound(format!("Vault {} not found", vault_id)))?;
-
-        let user_rows = sqlx::query_as::<_, (String, String, String)>(
+    pub async fn create_path_acl(&self, entry: &PathAclEntry) -> AppResult<()> {
+        let principal_type = match entry.principal_type {
+            AclPrincipalType::User => "user",
+            AclPrincipalType::Group => "group",
+            AclPrincipalType::Everyone => "everyone",
+        };
+        let access = match entry.access {
+            PathAccess::None => "none",
+            PathAccess::Read => "read",
+            PathAccess::Write => "write",
+        };
+        sqlx::query(
             r#"
-            SELECT u.id, u.username, vus.role
-            FROM vault_user_shares vus
-            INNER JOIN users u ON u.id = vus.user_id
-            WHERE vus.vault_id = ?
-            ORDER BY u.username ASC
+            INSERT INTO vault_path_acls (id, vault_id, pattern, principal_type, principal_id, access, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             "#,
         )
-        .bind(vault_id)
-        .fetch_all(&self.pool)
+        .bind(&entry.id)
+        .bind(&entry.vault_id)
+        .bind(&entry.pattern)
+        .bind(principal_type)
+        .bind(&entry.principal_id)
+        .bind(access)
+        .bind(entry.created_at.to_rfc3339())
+        .execute(&self.pool)
         .await?;
+        Ok(())
+    }
 
-        let group_rows = sqlx::query_as::<_, (String, String, String)>(
-            r#"
-            SELECT g.id, g.name, vgs.role
-            FROM vault_group_shares vgs
-            INNER JOIN groups g ON g.id = vgs.group_id
-            WHERE vgs.vault_id = ?
-            ORDER BY g.name ASC
-            "#,
+    pub async fn list_path_acls(&self, vault_id: &str) -> AppResult<Vec<PathAclEntry>> {
+        let rows: Vec<PathAclRow> = sqlx::query_as(
+            "SELECT * FROM vault_path_acls WHERE vault_id = $1 ORDER BY pattern ASC, created_at ASC",
         )
         .bind(vault_id)
         .fetch_all(&self.pool)
         .await?;
-
-        Ok(VaultShareList {
-            owner_user_id: owner.0,
-            user_shares: user_rows
-                .into_iter()
-                .filter_map(|(principal_id, principal_name, role)| {
-                    parse_vault_role(&role).map(|role| VaultShareEntry {
-                        principal_type: "user".to_string(),
-                        principal_id,
-                        principal_name,
-                        role,
-                    })
-                })
-                .collect(),
-            group_shares: group_rows
-                .into_iter()
-                .filter_map(|(principal_id, principal_name, role)| {
-                    parse_vault_role(&role).map(|role| VaultShareEntry {
-                        principal_type: "group".to_string(),
-                        principal_id,
-                        principal_name,
-                        role,
-                    })
-                })
-                .collect(),
-        })
+        Ok(rows
+            .into_iter()
+            .filter_map(PathAclRow::into_entry)
+            .collect())
     }
 
-    pub async fn delete_vault(&self, id: &str) -> AppResult<()> {
-        let result = sqlx::query("DELETE FROM vaults WHERE id = ?")
-            .bind(id)
+    pub async fn delete_path_acl(&self, vault_id: &str, acl_id: &str) -> AppResult<()> {
+        let result = sqlx::query("DELETE FROM vault_path_acls WHERE vault_id = $1 AND id = $2")
+            .bind(vault_id)
+            .bind(acl_id)
             .execute(&self.pool)
             .await?;
 
         if result.rows_affected() == 0 {
-            return Err(AppError::NotFound(format!("Vault {} not found", id)));
+            return Err(AppError::NotFound(format!(
+                "Path ACL {} not found in vault {}",
+                acl_id, vault_id
+            )));
         }
 
         Ok(())
     }
 
-    pub async fn update_vault_timestamp(&self, id: &str) -> AppResult<()> {
-        sqlx::query("UPDATE vaults SET updated_at = ? WHERE id = ?")
-            .bind(Utc::now().to_rfc3339())
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
-
+    pub async fn create_share_link(
+        &self,
+        link: &ShareLink,
+        token_hash: &str,
+        password_hash: Option<&str>,
+    ) -> AppResult<()> {
+        let permission = match link.permission {
+            ShareLinkPermission::View => "view",
+            ShareLinkPermission::Comment => "comment",
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO share_links
+                (id, token_hash, vault_id, path, is_folder, permission, password_hash, expires_at, created_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(&link.id)
+        .bind(token_hash)
+        .bind(&link.vault_id)
+        .bind(&link.path)
+        .bind(link.is_folder as i64)
+        .bind(permission)
+        .bind(password_hash)
+        .bind(link.expires_at.map(|t| t.to_rfc3339()))
+        .bind(&link.created_by)
+        .bind(link.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
-    // Preferences operations
-    pub async fn get_preferences(&self) -> AppResult<UserPreferences> {
-        self.get_preferences_for_user(None).await
+    pub async fn list_share_links(&self, vault_id: &str) -> AppResult<Vec<ShareLink>> {
+        let rows: Vec<ShareLinkRow> = sqlx::query_as(
+            r#"
+            SELECT id, vault_id, path, is_folder, permission, password_hash, expires_at,
+                   created_by, created_at, revoked_at, access_count, last_accessed_at
+            FROM share_links WHERE vault_id = $1 ORDER BY created_at DESC
+            "#,
+        )
+        .bind(vault_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|row| row.into_link().0).collect())
     }
 
-    pub async fn get_preferences_for_user(
+    /// Look up a link by the SHA-256 of its token, with its password hash.
+    pub async fn get_share_link_by_token_hash(
         &self,
-        user_id: Option<&str>,
-    ) -> AppResult<UserPreferences> {
-        let row: Option<(String, String, i64, Option<String>, Option<String>)> = if let Some(
-            user_id,
-        ) = user_id
-        {
-            sqlx::query_as(
-                "SELECT theme, editor_mode, font_size, window_layout, icon_map FROM user_preferences WHERE user_id = ?",
-            )
-            .bind(user_id)
-            .fetch_optional(&self.pool)
+        token_hash: &str,
+    ) -> AppResult<Option<(ShareLink, Option<String>)>> {
+        let row: Option<ShareLinkRow> = sqlx::query_as(
+            r#"
+            SELECT id, vault_id, path, is_folder, permission, password_hash, expires_at,
+                   created_by, created_at, revoked_at, access_count, last_accessed_at
+            FROM share_links WHERE token_hash = $1
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(ShareLinkRow::into_link))
+    }
+
+    pub async fn revoke_share_link(&self, vault_id: &str, link_id: &str) -> AppResult<()> {
+        let result = sqlx::query(
+            "UPDATE share_links SET revoked_at = $1 WHERE vault_id = $2 AND id = $3 AND revoked_at IS NULL",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(vault_id)
+        .bind(link_id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Active share link {} not found in vault {}",
+                link_id, vault_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_share_link_comment(&self, comment: &ShareLinkComment) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO share_link_comments (id, link_id, path, author_name, body, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&comment.id)
+        .bind(&comment.link_id)
+        .bind(&comment.path)
+        .bind(&comment.author_name)
+        .bind(&comment.body)
+        .bind(comment.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Comments left through a link, oldest first.
+    pub async fn list_share_link_comments(
+        &self,
+        link_id: &str,
+    ) -> AppResult<Vec<ShareLinkComment>> {
+        let rows: Vec<ShareLinkCommentRow> = sqlx::query_as(
+            r#"
+            SELECT id, link_id, path, author_name, body, created_at
+            FROM share_link_comments WHERE link_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(link_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(ShareLinkComment::from).collect())
+    }
+
+    pub async fn record_share_link_access(&self, link_id: &str) -> AppResult<()> {
+        sqlx::query(
+            "UPDATE share_links SET access_count = access_count + 1, last_accessed_at = $1 WHERE id = $2",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(link_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_vault_shares(&self, vault_id: &str) -> AppResult<VaultShareList> {
+        let owner = sqlx::query_as::<_, (Option<String>,)>(
+            "SELECT owner_user_id FROM vaults WHERE id = $1",
+        )
+        .bind(vault_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Vault {} not found", vault_id)))?;
+
+        let user_rows = sqlx::query_as::<_, (String, String, String)>(
+            r#"
+            SELECT u.id, u.username, vus.role
+            FROM vault_user_shares vus
+            INNER JOIN users u ON u.id = vus.user_id
+            WHERE vus.vault_id = $1
+            ORDER BY u.username ASC
+            "#,
+        )
+        .bind(vault_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let group_rows = sqlx::query_as::<_, (String, String, String)>(
+            r#"
+            SELECT g.id, g.name, vgs.role
+            FROM vault_group_shares vgs
+            INNER JOIN groups g ON g.id = vgs.group_id
+            WHERE vgs.vault_id = $1
+            ORDER BY g.name ASC
+            "#,
+        )
+        .bind(vault_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(VaultShareList {
+            owner_user_id: owner.0,
+            user_shares: user_rows
+                .into_iter()
+                .filter_map(|(principal_id, principal_name, role)| {
+                    parse_vault_role(&role).map(|role| VaultShareEntry {
+                        principal_type: "user".to_string(),
+                        principal_id,
+                        principal_name,
+                        role,
+                    })
+                })
+                .collect(),
+            group_shares: group_rows
+                .into_iter()
+                .filter_map(|(principal_id, principal_name, role)| {
+                    parse_vault_role(&role).map(|role| VaultShareEntry {
+                        principal_type: "group".to_string(),
+                        principal_id,
+                        principal_name,
+                        role,
+                    })
+                })
+                .collect(),
+        })
+    }
+
+    pub async fn delete_vault(&self, id: &str) -> AppResult<()> {
+        let result = sqlx::query("DELETE FROM vaults WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("Vault {} not found", id)));
+        }
+
+        Ok(())
+    }
+
+    pub async fn update_vault_timestamp(&self, id: &str) -> AppResult<()> {
+        sqlx::query("UPDATE vaults SET updated_at = $1 WHERE id = $2")
+            .bind(Utc::now().to_rfc3339())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // Preferences operations
+    pub async fn get_preferences(&self) -> AppResult<UserPreferences> {
+        self.get_preferences_for_user(None).await
+    }
+
+    pub async fn get_preferences_for_user(
+        &self,
+        user_id: Option<&str>,
+    ) -> AppResult<UserPreferences> {
+        let row: Option<(String, String, i64, Option<String>, Option<String>)> = if let Some(
+            user_id,
+        ) = user_id
+        {
+            sqlx::query_as(
+                "SELECT theme, editor_mode, font_size, window_layout, icon_map FROM user_preferences WHERE user_id = $1",
+            )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
             .await
             .map_err(AppError::from)?
         } else {
@@ -1586,7 +1769,7 @@ impl Database {
             sqlx::query(
                 r#"
                 INSERT INTO user_preferences (user_id, theme, editor_mode, font_size, window_layout, icon_map, updated_at)
-                VALUES (?, ?, ?, ?, ?, ?, ?)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
                 ON CONFLICT(user_id) DO UPDATE SET
                     theme = excluded.theme,
                     editor_mode = excluded.editor_mode,
@@ -1611,7 +1794,7 @@ impl Database {
         sqlx::query(
             r#"
             UPDATE preferences
-            SET theme = ?, editor_mode = ?, font_size = ?, window_layout = ?, icon_map = ?, updated_at = ?
+            SET theme = $1, editor_mode = $2, font_size = $3, window_layout = $4, icon_map = $5, updated_at = $6
             WHERE id = 1
             "#,
         )
@@ -1687,7 +1870,7 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO recent_files (vault_id, path, last_accessed)
-            VALUES (?, ?, ?)
+            VALUES ($1, $2, $3)
             ON CONFLICT(vault_id, path) DO UPDATE SET last_accessed = excluded.last_accessed
             "#,
         )
@@ -1701,9 +1884,9 @@ impl Database {
         sqlx::query(
             r#"
             DELETE FROM recent_files 
-            WHERE vault_id = ? AND path NOT IN (
+            WHERE vault_id = $1 AND path NOT IN (
                 SELECT path FROM recent_files 
-                WHERE vault_id = ? 
+                WHERE vault_id = $2 
                 ORDER BY last_accessed DESC 
                 LIMIT 20
             )
@@ -1721,9 +1904,9 @@ impl Database {
         let rows: Vec<(String,)> = sqlx::query_as(
             r#"
             SELECT path FROM recent_files 
-            WHERE vault_id = ? 
+            WHERE vault_id = $1 
             ORDER BY last_accessed DESC 
-            LIMIT ?
+            LIMIT $2
             "#,
         )
         .bind(vault_id)
@@ -1749,7 +1932,7 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO file_change_log (vault_id, path, event_type, etag, old_path, timestamp)
-            VALUES (?, ?, ?, ?, ?, ?)
+            VALUES ($1, $2, $3, $4, $5, $6)
             "#,
         )
         .bind(vault_id)
@@ -1765,7 +1948,7 @@ impl Database {
             let retention_ms = (retention_days as i64).saturating_mul(24 * 60 * 60 * 1000);
             let cutoff = now_ms.saturating_sub(retention_ms);
 
-            sqlx::query("DELETE FROM file_change_log WHERE timestamp < ?")
+            sqlx::query("DELETE FROM file_change_log WHERE timestamp < $1")
                 .bind(cutoff)
                 .execute(&self.pool)
                 .await?;
@@ -1783,7 +1966,7 @@ impl Database {
             r#"
             SELECT path, event_type, old_path, timestamp
             FROM file_change_log
-            WHERE vault_id = ? AND timestamp > ?
+            WHERE vault_id = $1 AND timestamp > $2
             ORDER BY timestamp ASC
             "#,
         )
@@ -1828,7 +2011,7 @@ impl Database {
 
     /// Soft-deactivate a user (keeps data, prevents login).
     pub async fn deactivate_user(&self, user_id: &str) -> AppResult<()> {
-        let result = sqlx::query("UPDATE users SET is_active = 0 WHERE id = ?")
+        let result = sqlx::query("UPDATE users SET is_active = 0 WHERE id = $1")
             .bind(user_id)
             .execute(&self.pool)
             .await?;
@@ -1840,7 +2023,7 @@ impl Database {
 
     /// Reactivate a previously deactivated user.
     pub async fn reactivate_user(&self, user_id: &str) -> AppResult<()> {
-        let result = sqlx::query("UPDATE users SET is_active = 1 WHERE id = ?")
+        let result = sqlx::query("UPDATE users SET is_active = 1 WHERE id = $1")
             .bind(user_id)
             .execute(&self.pool)
             .await?;
@@ -1852,7 +2035,7 @@ impl Database {
 
     /// Check whether a user account is active.
     pub async fn is_user_active(&self, user_id: &str) -> AppResult<bool> {
-        let row: Option<(i64,)> = sqlx::query_as("SELECT is_active FROM users WHERE id = ?")
+        let row: Option<(i64,)> = sqlx::query_as("SELECT is_active FROM users WHERE id = $1")
             .bind(user_id)
             .fetch_optional(&self.pool)
             .await
@@ -1863,31 +2046,31 @@ impl Database {
     /// Hard-delete a user and cascade-remove group memberships and vault shares.
     pub async fn delete_user(&self, user_id: &str) -> AppResult<()> {
         // Remove vault shares
-        sqlx::query("DELETE FROM vault_user_shares WHERE user_id = ?")
+        sqlx::query("DELETE FROM vault_user_shares WHERE user_id = $1")
             .bind(user_id)
             .execute(&self.pool)
             .await?;
 
         // Remove group memberships
-        sqlx::query("DELETE FROM group_members WHERE user_id = ?")
+        sqlx::query("DELETE FROM group_members WHERE user_id = $1")
             .bind(user_id)
             .execute(&self.pool)
             .await?;
 
         // Remove user preferences
-        sqlx::query("DELETE FROM user_preferences WHERE user_id = ?")
+        sqlx::query("DELETE FROM user_preferences WHERE user_id = $1")
             .bind(user_id)
             .execute(&self.pool)
             .await?;
 
         // Unset vault ownership (vaults become unowned, not deleted)
-        sqlx::query("UPDATE vaults SET owner_user_id = NULL WHERE owner_user_id = ?")
+        sqlx::query("UPDATE vaults SET owner_user_id = NULL WHERE owner_user_id = $1")
             .bind(user_id)
             .execute(&self.pool)
             .await?;
 
         // Delete the user row
-        let result = sqlx::query("DELETE FROM users WHERE id = ?")
+        let result = sqlx::query("DELETE FROM users WHERE id = $1")
             .bind(user_id)
             .execute(&self.pool)
             .await?;
@@ -1903,13 +2086,13 @@ impl Database {
     /// Record a failed login attempt and return the new attempt count.
     pub async fn record_failed_login(&self, user_id: &str) -> AppResult<i64> {
         sqlx::query(
-            "UPDATE users SET failed_login_attempts = failed_login_attempts + 1 WHERE id = ?",
+            "UPDATE users SET failed_login_attempts = failed_login_attempts + 1 WHERE id = $1",
         )
         .bind(user_id)
         .execute(&self.pool)
         .await?;
 
-        let row: (i64,) = sqlx::query_as("SELECT failed_login_attempts FROM users WHERE id = ?")
+        let row: (i64,) = sqlx::query_as("SELECT failed_login_attempts FROM users WHERE id = $1")
             .bind(user_id)
             .fetch_one(&self.pool)
             .await
@@ -1919,7 +2102,7 @@ impl Database {
 
     /// Lock a user account until a specified time.
     pub async fn lock_user_until(&self, user_id: &str, until: DateTime<Utc>) -> AppResult<()> {
-        sqlx::query("UPDATE users SET locked_until = ? WHERE id = ?")
+        sqlx::query("UPDATE users SET locked_until = $1 WHERE id = $2")
             .bind(until.to_rfc3339())
             .bind(user_id)
             .execute(&self.pool)
@@ -1929,10 +2112,12 @@ impl Database {
 
     /// Clear failed login attempts and unlock the account after successful login.
     pub async fn clear_failed_logins(&self, user_id: &str) -> AppResult<()> {
-        sqlx::query("UPDATE users SET failed_login_attempts = 0, locked_until = NULL WHERE id = ?")
-            .bind(user_id)
-            .execute(&self.pool)
-            .await?;
+        sqlx::query(
+            "UPDATE users SET failed_login_attempts = 0, locked_until = NULL WHERE id = $1",
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
@@ -1940,7 +2125,7 @@ impl Database {
     /// locked, `None` if not.
     pub async fn get_lockout_status(&self, user_id: &str) -> AppResult<Option<DateTime<Utc>>> {
         let row: Option<(Option<String>,)> =
-            sqlx::query_as("SELECT locked_until FROM users WHERE id = ?")
+            sqlx::query_as("SELECT locked_until FROM users WHERE id = $1")
                 .bind(user_id)
                 .fetch_optional(&self.pool)
                 .await
@@ -1967,53 +2152,213 @@ impl Database {
         ip_address: Option<&str>,
         success: bool,
     ) -> AppResult<()> {
-        let now = Utc::now().to_rfc3339();
+        self.record_audit_event(AuditEvent {
+            user_id: user_id.map(str::to_string),
+            username: username.map(str::to_string),
+            event_type: event_type.to_string(),
+            detail: detail.map(str::to_string),
+            ip_address: ip_address.map(str::to_string),
+            outcome: if success {
+                AuditOutcome::Success
+            } else {
+                AuditOutcome::Failure
+            },
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Append an event to the audit log, taking the IP address, request ID
+    /// and vault from the current request where the event leaves them out.
+    pub async fn record_audit_event(&self, mut event: AuditEvent) -> AppResult<()> {
+        if let Some(context) = AuditContext::current() {
+            context.apply(&mut event);
+        }
+        let mut entry = AuditLogEntry {
+            id: 0,
+            timestamp: Utc::now(),
+            user_id: event.user_id,
+            username: event.username,
+            event_type: event.event_type,
+            detail: event.detail,
+            ip_address: event.ip_address,
+            success: event.outcome == AuditOutcome::Success,
+            outcome: event.outcome,
+            vault_id: event.vault_id,
+            path: event.path,
+            request_id: event.request_id,
+            prev_hash: None,
+            hash: None,
+        };
+        if !self.audit_hash_chain {
+            return self.insert_audit_entry(&entry).await;
+        }
+
+        let _guard = self.audit_chain_lock.lock().await;
+        let mut attempts = 0;
+        loop {
+            let head: Option<(String,)> = sqlx::query_as(
+                "SELECT hash FROM audit_log WHERE hash IS NOT NULL ORDER BY id DESC LIMIT 1",
+            )
+            .fetch_optional(&self.pool)
+            .await?;
+            let prev_hash = head.map_or_else(|| AUDIT_CHAIN_GENESIS.to_string(), |(hash,)| hash);
+            entry.hash = Some(chain_hash(&prev_hash, &entry));
+            entry.prev_hash = Some(prev_hash);
+            match self.insert_audit_entry(&entry).await {
+                // Another instance extended the chain first; link to its entry.
+                Err(AppError::Conflict(_)) if attempts < 5 => attempts += 1,
+                result => return result,
+            }
+        }
+    }
+
+    async fn insert_audit_entry(&self, entry: &AuditLogEntry) -> AppResult<()> {
         sqlx::query(
             r#"
-            INSERT INTO audit_log (timestamp, user_id, username, event_type, detail, ip_address, success)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO audit_log (timestamp, user_id, username, event_type, detail, ip_address,
+                                   success, outcome, vault_id, path, request_id, prev_hash, hash)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
             "#,
         )
-        .bind(&now)
-        .bind(user_id)
-        .bind(username)
-        .bind(event_type)
-        .bind(detail)
-        .bind(ip_address)
-        .bind(if success { 1_i64 } else { 0_i64 })
+        .bind(entry.timestamp.to_rfc3339())
+        .bind(&entry.user_id)
+        .bind(&entry.username)
+        .bind(&entry.event_type)
+        .bind(&entry.detail)
+        .bind(&entry.ip_address)
+        .bind(if entry.success { 1_i64 } else { 0_i64 })
+        .bind(entry.outcome.as_str())
+        .bind(&entry.vault_id)
+        .bind(&entry.path)
+        .bind(&entry.request_id)
+        .bind(&entry.prev_hash)
+        .bind(&entry.hash)
         .execute(&self.pool)
-        .await?;
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                AppError::Conflict("Audit log chain head moved".to_string())
+            }
+            _ => AppError::from(e),
+        })?;
         Ok(())
     }
 
     /// Get recent audit log entries (newest first), with an optional limit.
     pub async fn get_audit_log(&self, limit: Option<i64>) -> AppResult<Vec<AuditLogEntry>> {
-        let limit = limit.unwrap_or(100).min(1000);
-        let rows = sqlx::query_as::<_, (i64, String, Option<String>, Option<String>, String, Option<String>, Option<String>, i64)>(
-            "SELECT id, timestamp, user_id, username, event_type, detail, ip_address, success FROM audit_log ORDER BY id DESC LIMIT ?",
-        )
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await
-        .map_err(AppError::from)?;
+        self.query_audit_log(&AuditLogQuery::default(), None, false, limit.unwrap_or(100))
+            .await
+    }
 
-        Ok(rows
-            .into_iter()
-            .map(
-                |(id, timestamp, user_id, username, event_type, detail, ip_address, success)| {
-                    AuditLogEntry {
-                        id,
-                        timestamp: parse_rfc3339_utc(&timestamp),
-                        user_id,
-                        username,
-                        event_type,
-                        detail,
-                        ip_address,
-                        success: success != 0,
-                    }
-                },
-            )
-            .collect())
+    /// Audit entries matching `query`: newest first with ids below `cursor`
+    /// or, with `oldest_first`, oldest first with ids above it.
+    pub async fn query_audit_log(
+        &self,
+        query: &AuditLogQuery,
+        cursor: Option<i64>,
+        oldest_first: bool,
+        limit: i64,
+    ) -> AppResult<Vec<AuditLogEntry>> {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut binds: Vec<String> = Vec::new();
+        let mut bind = |value: String| {
+            binds.push(value);
+            format!("${}", binds.len())
+        };
+        if let Some(since) = query.since {
+            conditions.push(format!("timestamp >= {}", bind(since.to_rfc3339())));
+        }
+        if let Some(until) = query.until {
+            conditions.push(format!("timestamp < {}", bind(until.to_rfc3339())));
+        }
+        if let Some(user_id) = &query.user_id {
+            conditions.push(format!("user_id = {}", bind(user_id.clone())));
+        }
+        if let Some(vault_id) = &query.vault_id {
+            conditions.push(format!("vault_id = {}", bind(vault_id.clone())));
+        }
+        if let Some(event_types) = &query.event_type {
+            let placeholders: Vec<String> = event_types
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(|t| bind(t.to_string()))
+                .collect();
+            if !placeholders.is_empty() {
+                conditions.push(format!("event_type IN ({})", placeholders.join(", ")));
+            }
+        }
+        if let Some(outcome) = query.outcome {
+            conditions.push(format!("outcome = {}", bind(outcome.as_str().to_string())));
+        }
+        let cursor_placeholder = format!("${}", binds.len() + 1);
+        if cursor.is_some() {
+            conditions.push(if oldest_first {
+                format!("id > {cursor_placeholder}")
+            } else {
+                format!("id < {cursor_placeholder}")
+            });
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT {AUDIT_LOG_COLUMNS} FROM audit_log {where_clause} ORDER BY id {} LIMIT {}",
+            if oldest_first { "ASC" } else { "DESC" },
+            limit.clamp(1, 1000)
+        );
+        let mut sql_query = sqlx::query_as::<_, AuditLogRow>(&sql);
+        for value in binds {
+            sql_query = sql_query.bind(value);
+        }
+        if let Some(cursor) = cursor {
+            sql_query = sql_query.bind(cursor);
+        }
+        let rows = sql_query.fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().map(AuditLogEntry::from).collect())
+    }
+
+    /// Re-compute the audit hash chain from its first entry.
+    pub async fn verify_audit_chain(&self) -> AppResult<AuditChainReport> {
+        let mut report = AuditChainReport {
+            enabled: self.audit_hash_chain,
+            checked: 0,
+            valid: true,
+            head_hash: None,
+            broken_at_id: None,
+        };
+        let mut expected_prev = AUDIT_CHAIN_GENESIS.to_string();
+        let mut after_id = 0_i64;
+        loop {
+            let rows: Vec<AuditLogRow> = sqlx::query_as(&format!(
+                "SELECT {AUDIT_LOG_COLUMNS} FROM audit_log \
+                 WHERE hash IS NOT NULL AND id > $1 ORDER BY id ASC LIMIT 1000"
+            ))
+            .bind(after_id)
+            .fetch_all(&self.pool)
+            .await?;
+            if rows.is_empty() {
+                return Ok(report);
+            }
+            for row in rows {
+                let entry = AuditLogEntry::from(row);
+                after_id = entry.id;
+                let linked = entry.prev_hash.as_deref() == Some(expected_prev.as_str());
+                let expected_hash = chain_hash(&expected_prev, &entry);
+                if !linked || entry.hash.as_deref() != Some(expected_hash.as_str()) {
+                    report.valid = false;
+                    report.broken_at_id = Some(entry.id);
+                    return Ok(report);
+                }
+                report.checked += 1;
+                report.head_hash = Some(expected_hash.clone());
+                expected_prev = expected_hash;
+            }
+        }
     }
 
     // ── Session management ──────────────────────────────────────────────
@@ -2027,7 +2372,7 @@ impl Database {
     ) -> AppResult<()> {
         let now = Utc::now().to_rfc3339();
         sqlx::query(
-            "INSERT INTO sessions (token_id, user_id, created_at, expires_at) VALUES (?, ?, ?, ?)",
+            "INSERT INTO sessions (token_id, user_id, created_at, expires_at) VALUES ($1, $2, $3, $4)",
         )
         .bind(token_id)
         .bind(user_id)
@@ -2040,18 +2385,19 @@ impl Database {
 
     /// Check whether a session has been revoked.
     pub async fn is_session_revoked(&self, token_id: &str) -> AppResult<bool> {
-        let row: Option<(i64,)> = sqlx::query_as("SELECT revoked FROM sessions WHERE token_id = ?")
-            .bind(token_id)
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(AppError::from)?;
+        let row: Option<(i64,)> =
+            sqlx::query_as("SELECT revoked FROM sessions WHERE token_id = $1")
+                .bind(token_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(AppError::from)?;
         // If the session isn't tracked at all, treat it as valid (backwards compat).
         Ok(row.map(|(v,)| v != 0).unwrap_or(false))
     }
 
     /// Revoke a specific session.
     pub async fn revoke_session(&self, token_id: &str) -> AppResult<()> {
-        sqlx::query("UPDATE sessions SET revoked = 1 WHERE token_id = ?")
+        sqlx::query("UPDATE sessions SET revoked = 1 WHERE token_id = $1")
             .bind(token_id)
             .execute(&self.pool)
             .await?;
@@ -2061,7 +2407,7 @@ impl Database {
     /// Revoke all active sessions for a user (log out everywhere).
     pub async fn revoke_all_sessions(&self, user_id: &str) -> AppResult<u64> {
         let result =
-            sqlx::query("UPDATE sessions SET revoked = 1 WHERE user_id = ? AND revoked = 0")
+            sqlx::query("UPDATE sessions SET revoked = 1 WHERE user_id = $1 AND revoked = 0")
                 .bind(user_id)
                 .execute(&self.pool)
                 .await?;
@@ -2072,7 +2418,7 @@ impl Database {
     pub async fn list_active_sessions(&self, user_id: &str) -> AppResult<Vec<SessionInfo>> {
         let now = Utc::now().to_rfc3339();
         let rows = sqlx::query_as::<_, (String, String, String)>(
-            "SELECT token_id, created_at, expires_at FROM sessions WHERE user_id = ? AND revoked = 0 AND expires_at > ? ORDER BY created_at DESC",
+            "SELECT token_id, created_at, expires_at FROM sessions WHERE user_id = $1 AND revoked = 0 AND expires_at > $2 ORDER BY created_at DESC",
         )
         .bind(user_id)
         .bind(&now)
@@ -2093,16 +2439,22 @@ impl Database {
     /// Clean up expired sessions (housekeeping).
     pub async fn purge_expired_sessions(&self) -> AppResult<u64> {
         let now = Utc::now().to_rfc3339();
-        let result = sqlx::query("DELETE FROM sessions WHERE expires_at < ?")
+        let result = sqlx::query("DELETE FROM sessions WHERE expires_at < $1")
             .bind(&now)
             .execute(&self.pool)
             .await?;
+        sqlx::query(
+            "DELETE FROM oidc_sessions WHERE session_id NOT IN (SELECT token_id FROM sessions)",
+        )
+        .execute(&self.pool)
+        .await?;
         Ok(result.rows_affected())
     }
 
     // ── API keys ────────────────────────────────────────────────────────
 
     /// Store a new API key record (the hash, not the raw key).
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_api_key(
         &self,
         id: &str,
@@ -2111,12 +2463,20 @@ impl Database {
         key_hash: &str,
         user_id: &str,
         expires_at: Option<DateTime<Utc>>,
+        restrictions: &ApiKeyRestrictions,
     ) -> AppResult<()> {
         let now = Utc::now().to_rfc3339();
+        let restrictions = if restrictions.is_unrestricted() {
+            None
+        } else {
+            Some(serde_json::to_string(restrictions).map_err(|e| {
+                AppError::InternalError(format!("Failed to encode API key restrictions: {}", e))
+            })?)
+        };
         sqlx::query(
             r#"
-            INSERT INTO api_keys (id, name, prefix, key_hash, user_id, created_at, expires_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO api_keys (id, name, prefix, key_hash, user_id, created_at, expires_at, restrictions)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             "#,
         )
         .bind(id)
@@ -2126,55 +2486,50 @@ impl Database {
         .bind(user_id)
         .bind(&now)
         .bind(expires_at.map(|t| t.to_rfc3339()))
+        .bind(restrictions)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
-    /// Look up an API key by its prefix, returning (id, key_hash, user_id, expires_at, revoked).
+    /// Look up an API key by its prefix, returning its info and argon2 hash.
     pub async fn get_api_key_by_prefix(
         &self,
         prefix: &str,
-    ) -> AppResult<Option<(String, String, String, Option<String>, bool)>> {
-        let row: Option<(String, String, String, Option<String>, i64)> = sqlx::query_as(
-            "SELECT id, key_hash, user_id, expires_at, revoked FROM api_keys WHERE prefix = ?",
-        )
-        .bind(prefix)
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(AppError::from)?;
-        Ok(row.map(|(id, hash, uid, exp, rev)| (id, hash, uid, exp, rev != 0)))
+    ) -> AppResult<Option<(ApiKeyInfo, String)>> {
+        let row: Option<ApiKeyRow> = sqlx::query_as("SELECT * FROM api_keys WHERE prefix = $1")
+            .bind(prefix)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::from)?;
+        Ok(row.map(ApiKeyRow::into_info))
     }
 
     /// List all API keys for a user (without hashes).
     pub async fn list_api_keys(&self, user_id: &str) -> AppResult<Vec<ApiKeyInfo>> {
-        let rows = sqlx::query_as::<_, (String, String, String, String, String, Option<String>, i64)>(
-            "SELECT id, name, prefix, user_id, created_at, expires_at, revoked FROM api_keys WHERE user_id = ? ORDER BY created_at DESC",
-        )
-        .bind(user_id)
-        .fetch_all(&self.pool)
-        .await
-        .map_err(AppError::from)?;
-
-        Ok(rows
-            .into_iter()
-            .map(
-                |(id, name, prefix, user_id, created_at, expires_at, revoked)| ApiKeyInfo {
-                    id,
-                    name,
-                    prefix,
-                    user_id,
-                    created_at: parse_rfc3339_utc(&created_at),
-                    expires_at: expires_at.map(|s| parse_rfc3339_utc(&s)),
-                    revoked: revoked != 0,
-                },
-            )
-            .collect())
+        let rows: Vec<ApiKeyRow> =
+            sqlx::query_as("SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC")
+                .bind(user_id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(AppError::from)?;
+        Ok(rows.into_iter().map(|row| row.into_info().0).collect())
     }
 
-    /// Revoke an API key.
-    pub async fn revoke_api_key(&self, key_id: &str, user_id: &str) -> AppResult<()> {
-        let result = sqlx::query("UPDATE api_keys SET revoked = 1 WHERE id = ? AND user_id = ?")
+    /// Record that an API key was just used from `ip_address`.
+    pub async fn touch_api_key(&self, key_id: &str, ip_address: Option<&str>) -> AppResult<()> {
+        sqlx::query("UPDATE api_keys SET last_used_at = $1, last_used_ip = $2 WHERE id = $3")
+            .bind(Utc::now().to_rfc3339())
+            .bind(ip_address)
+            .bind(key_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Revoke an API key.
+    pub async fn revoke_api_key(&self, key_id: &str, user_id: &str) -> AppResult<()> {
+        let result = sqlx::query("UPDATE api_keys SET revoked = 1 WHERE id = $1 AND user_id = $2")
             .bind(key_id)
             .bind(user_id)
             .execute(&self.pool)
@@ -2189,7 +2544,7 @@ impl Database {
 
     /// Set vault visibility to 'public' or 'private'.
     pub async fn set_vault_visibility(&self, vault_id: &str, visibility: &str) -> AppResult<()> {
-        let result = sqlx::query("UPDATE vaults SET visibility = ? WHERE id = ?")
+        let result = sqlx::query("UPDATE vaults SET visibility = $1 WHERE id = $2")
             .bind(visibility)
             .bind(vault_id)
             .execute(&self.pool)
@@ -2202,7 +2557,7 @@ impl Database {
 
     /// Get vault visibility ('public' or 'private').
     pub async fn get_vault_visibility(&self, vault_id: &str) -> AppResult<String> {
-        let row: Option<(String,)> = sqlx::query_as("SELECT visibility FROM vaults WHERE id = ?")
+        let row: Option<(String,)> = sqlx::query_as("SELECT visibility FROM vaults WHERE id = $1")
             .bind(vault_id)
             .fetch_optional(&self.pool)
             .await
@@ -2210,6 +2565,45 @@ impl Database {
         Ok(row.map(|(v,)| v).unwrap_or_else(|| "private".to_string()))
     }
 
+    /// How strictly entity frontmatter is validated when a file in the vault
+    /// is saved.
+    pub async fn get_vault_entity_validation(
+        &self,
+        vault_id: &str,
+    ) -> AppResult<EntityValidationMode> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT validation_mode FROM vault_entity_settings WHERE vault_id = $1")
+                .bind(vault_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row
+            .and_then(|(mode,)| EntityValidationMode::parse(&mode))
+            .unwrap_or_default())
+    }
+
+    pub async fn set_vault_entity_validation(
+        &self,
+        vault_id: &str,
+        mode: EntityValidationMode,
+    ) -> AppResult<()> {
+        self.get_vault(vault_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO vault_entity_settings (vault_id, validation_mode, updated_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT(vault_id) DO UPDATE SET
+                validation_mode = excluded.validation_mode,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(vault_id)
+        .bind(mode.as_str())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     /// Transfer vault ownership to a new user.
     pub async fn transfer_vault_ownership(
         &self,
@@ -2217,109 +2611,758 @@ impl Database {
         new_owner_id: &str,
     ) -> AppResult<()> {
         // Set the new owner.
-        sqlx::query("UPDATE vaults SET owner_user_id = ? WHERE id = ?")
+        sqlx::query("UPDATE vaults SET owner_user_id = $1 WHERE id = $2")
             .bind(new_owner_id)
             .bind(vault_id)
             .execute(&self.pool)
             .await?;
 
-        // Ensure the new owner has an 'owner' share entry.
+        // Ensure the new owner has an 'owner' share entry.
+        sqlx::query(
+            r#"
+            INSERT INTO vault_user_shares (vault_id, user_id, role, created_at)
+            VALUES ($1, $2, 'owner', $3)
+            ON CONFLICT (vault_id, user_id) DO UPDATE SET role = 'owner'
+            "#,
+        )
+        .bind(vault_id)
+        .bind(new_owner_id)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // ── TOTP 2FA ────────────────────────────────────────────────────────
+
+    /// Store a TOTP secret for a user (enrollment step).
+    pub async fn set_totp_secret(
+        &self,
+        user_id: &str,
+        secret: &str,
+        backup_codes: &[String],
+    ) -> AppResult<()> {
+        let codes_json = serde_json::to_string(backup_codes).map_err(|e| {
+            AppError::InternalError(format!("Failed to serialize backup codes: {e}"))
+        })?;
+        sqlx::query("UPDATE users SET totp_secret = $1, totp_backup_codes = $2 WHERE id = $3")
+            .bind(secret)
+            .bind(&codes_json)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Enable TOTP after successful verification.
+    pub async fn enable_totp(&self, user_id: &str) -> AppResult<()> {
+        sqlx::query("UPDATE users SET totp_enabled = 1 WHERE id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Disable TOTP and clear the secret.
+    pub async fn disable_totp(&self, user_id: &str) -> AppResult<()> {
+        sqlx::query(
+            "UPDATE users SET totp_enabled = 0, totp_secret = NULL, totp_backup_codes = NULL WHERE id = $1",
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get TOTP state for a user: (totp_enabled, totp_secret, backup_codes_json).
+    pub async fn get_totp_state(
+        &self,
+        user_id: &str,
+    ) -> AppResult<(bool, Option<String>, Option<String>)> {
+        let row: Option<(i64, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT totp_enabled, totp_secret, totp_backup_codes FROM users WHERE id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::from)?;
+
+        match row {
+            Some((enabled, secret, codes)) => Ok((enabled != 0, secret, codes)),
+            None => Err(AppError::NotFound("User not found".to_string())),
+        }
+    }
+
+    /// Consume a backup code (marks it as used by removing from the list).
+    pub async fn consume_backup_code(&self, user_id: &str, code: &str) -> AppResult<bool> {
+        let (_, _, codes_json) = self.get_totp_state(user_id).await?;
+        let Some(codes_json) = codes_json else {
+            return Ok(false);
+        };
+        let mut codes: Vec<String> = serde_json::from_str(&codes_json).unwrap_or_default();
+        if let Some(pos) = codes.iter().position(|c| c == code) {
+            codes.remove(pos);
+            let updated = serde_json::to_string(&codes)
+                .map_err(|e| AppError::InternalError(e.to_string()))?;
+            sqlx::query("UPDATE users SET totp_backup_codes = $1 WHERE id = $2")
+                .bind(&updated)
+                .bind(user_id)
+                .execute(&self.pool)
+                .await?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    // ── WebAuthn ────────────────────────────────────────────────────────
+
+    pub async fn create_webauthn_credential(
+        &self,
+        credential: &WebAuthnCredential,
+    ) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO webauthn_credentials
+                (id, user_id, name, public_key, algorithm, sign_count, aaguid, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(&credential.id)
+        .bind(&credential.user_id)
+        .bind(&credential.name)
+        .bind(hex::encode(&credential.public_key))
+        .bind(credential.algorithm)
+        .bind(credential.sign_count as i64)
+        .bind(&credential.aaguid)
+        .bind(credential.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => AppError::Conflict(
+                "An authenticator with this name or ID is already registered".to_string(),
+            ),
+            _ => AppError::from(e),
+        })?;
+        Ok(())
+    }
+
+    pub async fn get_webauthn_credential(
+        &self,
+        credential_id: &str,
+    ) -> AppResult<Option<WebAuthnCredential>> {
+        let row = sqlx::query_as::<_, WebAuthnCredentialRow>(
+            r#"
+            SELECT id, user_id, name, public_key, algorithm, sign_count, aaguid, created_at, last_used_at
+            FROM webauthn_credentials WHERE id = $1
+            "#,
+        )
+        .bind(credential_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(WebAuthnCredential::from))
+    }
+
+    pub async fn list_webauthn_credentials(
+        &self,
+        user_id: &str,
+    ) -> AppResult<Vec<WebAuthnCredential>> {
+        let rows = sqlx::query_as::<_, WebAuthnCredentialRow>(
+            r#"
+            SELECT id, user_id, name, public_key, algorithm, sign_count, aaguid, created_at, last_used_at
+            FROM webauthn_credentials WHERE user_id = $1 ORDER BY created_at ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(WebAuthnCredential::from).collect())
+    }
+
+    async fn list_all_webauthn_credentials(&self) -> AppResult<Vec<WebAuthnCredential>> {
+        let rows = sqlx::query_as::<_, WebAuthnCredentialRow>(
+            r#"
+            SELECT id, user_id, name, public_key, algorithm, sign_count, aaguid, created_at, last_used_at
+            FROM webauthn_credentials ORDER BY created_at ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(WebAuthnCredential::from).collect())
+    }
+
+    pub async fn rename_webauthn_credential(
+        &self,
+        user_id: &str,
+        credential_id: &str,
+        name: &str,
+    ) -> AppResult<()> {
+        let result =
+            sqlx::query("UPDATE webauthn_credentials SET name = $1 WHERE id = $2 AND user_id = $3")
+                .bind(name)
+                .bind(credential_id)
+                .bind(user_id)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| match &e {
+                    sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                        AppError::Conflict(format!(
+                            "An authenticator named '{name}' is already registered"
+                        ))
+                    }
+                    _ => AppError::from(e),
+                })?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Authenticator not found".to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn delete_webauthn_credential(
+        &self,
+        user_id: &str,
+        credential_id: &str,
+    ) -> AppResult<()> {
+        let result = sqlx::query("DELETE FROM webauthn_credentials WHERE id = $1 AND user_id = $2")
+            .bind(credential_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Authenticator not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Record a successful assertion. Fails if another assertion with the
+    /// same or a later counter got there first.
+    pub async fn record_webauthn_use(
+        &self,
+        credential_id: &str,
+        previous_sign_count: u32,
+        sign_count: u32,
+    ) -> AppResult<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE webauthn_credentials SET sign_count = $1, last_used_at = $2
+            WHERE id = $3 AND sign_count = $4
+            "#,
+        )
+        .bind(sign_count as i64)
+        .bind(Utc::now().to_rfc3339())
+        .bind(credential_id)
+        .bind(previous_sign_count as i64)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::Unauthorized(
+                "Authenticator was used concurrently".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn create_webauthn_challenge(&self, challenge: &WebAuthnChallenge) -> AppResult<()> {
+        // Opportunistically drop abandoned ceremonies.
+        sqlx::query("DELETE FROM webauthn_challenges WHERE expires_at <= $1")
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO webauthn_challenges (id, user_id, purpose, challenge, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&challenge.id)
+        .bind(&challenge.user_id)
+        .bind(challenge.purpose.as_str())
+        .bind(hex::encode(&challenge.challenge))
+        .bind(challenge.expires_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remove and return a pending ceremony, so each can be finished once.
+    pub async fn take_webauthn_challenge(
+        &self,
+        challenge_id: &str,
+    ) -> AppResult<Option<WebAuthnChallenge>> {
+        let row: Option<(String, Option<String>, String, String, String)> = sqlx::query_as(
+            "SELECT id, user_id, purpose, challenge, expires_at FROM webauthn_challenges WHERE id = $1",
+        )
+        .bind(challenge_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((id, user_id, purpose, challenge, expires_at)) = row else {
+            return Ok(None);
+        };
+
+        let deleted = sqlx::query("DELETE FROM webauthn_challenges WHERE id = $1")
+            .bind(&id)
+            .execute(&self.pool)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let Some(purpose) = WebAuthnPurpose::parse(&purpose) else {
+            return Ok(None);
+        };
+        Ok(Some(WebAuthnChallenge {
+            id,
+            user_id,
+            purpose,
+            challenge: hex::decode(challenge).unwrap_or_default(),
+            expires_at: parse_rfc3339_utc(&expires_at),
+        }))
+    }
+
+    // ── OIDC ────────────────────────────────────────────────────────────
+
+    pub async fn create_oidc_auth_request(&self, request: &OidcAuthRequest) -> AppResult<()> {
+        // Opportunistically drop abandoned sign-ins.
+        sqlx::query("DELETE FROM oidc_auth_requests WHERE expires_at <= $1")
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO oidc_auth_requests (state, provider, nonce, code_verifier, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&request.state)
+        .bind(&request.provider)
+        .bind(&request.nonce)
+        .bind(&request.code_verifier)
+        .bind(request.expires_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remove and return a pending sign-in, so each `state` is used once.
+    pub async fn take_oidc_auth_request(&self, state: &str) -> AppResult<Option<OidcAuthRequest>> {
+        let row: Option<(String, String, String, String, String)> = sqlx::query_as(
+            "SELECT state, provider, nonce, code_verifier, expires_at FROM oidc_auth_requests WHERE state = $1",
+        )
+        .bind(state)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((state, provider, nonce, code_verifier, expires_at)) = row else {
+            return Ok(None);
+        };
+
+        let deleted = sqlx::query("DELETE FROM oidc_auth_requests WHERE state = $1")
+            .bind(&state)
+            .execute(&self.pool)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(OidcAuthRequest {
+            state,
+            provider,
+            nonce,
+            code_verifier,
+            expires_at: parse_rfc3339_utc(&expires_at),
+        }))
+    }
+
+    /// Remember the ID token behind a refresh session for RP-initiated logout.
+    pub async fn create_oidc_session(
+        &self,
+        session_id: &str,
+        provider: &str,
+        id_token: &str,
+    ) -> AppResult<()> {
+        sqlx::query(
+            "INSERT INTO oidc_sessions (session_id, provider, id_token, created_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(session_id)
+        .bind(provider)
+        .bind(id_token)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Follow a refresh-token rotation from `old_session_id` to `new_session_id`.
+    pub async fn move_oidc_session(
+        &self,
+        old_session_id: &str,
+        new_session_id: &str,
+    ) -> AppResult<()> {
+        sqlx::query("UPDATE oidc_sessions SET session_id = $1 WHERE session_id = $2")
+            .bind(new_session_id)
+            .bind(old_session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Remove and return `(provider, id_token)` for a session.
+    pub async fn take_oidc_session(&self, session_id: &str) -> AppResult<Option<(String, String)>> {
+        let row: Option<(String, String)> =
+            sqlx::query_as("SELECT provider, id_token FROM oidc_sessions WHERE session_id = $1")
+                .bind(session_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        sqlx::query("DELETE FROM oidc_sessions WHERE session_id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    // ── SCIM provisioning ───────────────────────────────────────────────
+
+    pub async fn create_scim_token(
+        &self,
+        name: &str,
+        token_hash: &str,
+        created_by_user_id: &str,
+    ) -> AppResult<ScimTokenInfo> {
+        let info = ScimTokenInfo {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            created_by_user_id: created_by_user_id.to_string(),
+            created_at: Utc::now(),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO scim_tokens (id, name, token_hash, created_by_user_id, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&info.id)
+        .bind(&info.name)
+        .bind(token_hash)
+        .bind(&info.created_by_user_id)
+        .bind(info.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(info)
+    }
+
+    pub async fn list_scim_tokens(&self) -> AppResult<Vec<ScimTokenInfo>> {
+        let rows = sqlx::query_as::<_, ScimTokenRow>(
+            r#"
+            SELECT id, name, created_by_user_id, created_at, last_used_at, revoked_at
+            FROM scim_tokens ORDER BY created_at DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(ScimTokenInfo::from).collect())
+    }
+
+    /// Look up an unrevoked token by the SHA-256 of its value.
+    pub async fn get_active_scim_token(
+        &self,
+        token_hash: &str,
+    ) -> AppResult<Option<ScimTokenInfo>> {
+        let row = sqlx::query_as::<_, ScimTokenRow>(
+            r#"
+            SELECT id, name, created_by_user_id, created_at, last_used_at, revoked_at
+            FROM scim_tokens WHERE token_hash = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(ScimTokenInfo::from))
+    }
+
+    pub async fn touch_scim_token(&self, token_id: &str) -> AppResult<()> {
+        sqlx::query("UPDATE scim_tokens SET last_used_at = $1 WHERE id = $2")
+            .bind(Utc::now().to_rfc3339())
+            .bind(token_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn revoke_scim_token(&self, token_id: &str) -> AppResult<()> {
+        let result = sqlx::query(
+            "UPDATE scim_tokens SET revoked_at = $1 WHERE id = $2 AND revoked_at IS NULL",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(token_id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "SCIM token {token_id} not found"
+            )));
+        }
+        Ok(())
+    }
+
+    pub async fn get_scim_user(&self, user_id: &str) -> AppResult<Option<ScimUserRecord>> {
+        let row = sqlx::query_as::<_, ScimUserRow>(&format!(
+            "SELECT {SCIM_USER_COLUMNS} FROM users WHERE id = $1"
+        ))
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(ScimUserRecord::from))
+    }
+
+    /// One page of users ordered by creation, and the total matching `filter`.
+    pub async fn list_scim_users(
+        &self,
+        filter: Option<&ScimUserFilter>,
+        offset: u64,
+        limit: u64,
+    ) -> AppResult<(u64, Vec<ScimUserRecord>)> {
+        let (condition, value) = match filter {
+            None => ("", None),
+            Some(ScimUserFilter::UserName(v)) => ("WHERE username = $1", Some(v.as_str())),
+            Some(ScimUserFilter::ExternalId(v)) => ("WHERE external_id = $1", Some(v.as_str())),
+        };
+        let count_sql = format!("SELECT COUNT(*) FROM users {condition}");
+        let page_sql = format!(
+            "SELECT {SCIM_USER_COLUMNS} FROM users {condition} ORDER BY created_at ASC, id ASC LIMIT {limit} OFFSET {offset}"
+        );
+
+        let mut count = sqlx::query_as::<_, (i64,)>(&count_sql);
+        let mut page = sqlx::query_as::<_, ScimUserRow>(&page_sql);
+        if let Some(value) = value {
+            count = count.bind(value);
+            page = page.bind(value);
+        }
+        let (total,) = count.fetch_one(&self.pool).await?;
+        let rows = page.fetch_all(&self.pool).await?;
+        Ok((
+            total.max(0) as u64,
+            rows.into_iter().map(ScimUserRecord::from).collect(),
+        ))
+    }
+
+    /// Overwrite a user's SCIM-managed attributes, including its username.
+    pub async fn update_scim_user(
+        &self,
+        user_id: &str,
+        attributes: &ScimUserAttributes,
+    ) -> AppResult<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE users SET username = $1, external_id = $2, display_name = $3, email = $4
+            WHERE id = $5
+            "#,
+        )
+        .bind(attributes.username.trim())
+        .bind(&attributes.external_id)
+        .bind(&attributes.display_name)
+        .bind(&attributes.email)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                AppError::Conflict("User with this username already exists".to_string())
+            }
+            _ => AppError::from(e),
+        })?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("User {user_id} not found")));
+        }
+        Ok(())
+    }
+
+    /// Groups `user_id` is a member of (not merely the creator of).
+    pub async fn list_member_groups(&self, user_id: &str) -> AppResult<Vec<GroupInfo>> {
+        let rows = sqlx::query_as::<_, (String, String, String)>(
+            r#"
+            SELECT g.id, g.name, g.created_at
+            FROM groups g
+            INNER JOIN group_members gm ON gm.group_id = g.id
+            WHERE gm.user_id = $1
+            ORDER BY g.name ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(id, name, created_at)| GroupInfo {
+                id,
+                name,
+                created_at: parse_rfc3339_utc(&created_at),
+            })
+            .collect())
+    }
+
+    pub async fn get_scim_group(&self, group_id: &str) -> AppResult<Option<ScimGroupRecord>> {
+        let row = sqlx::query_as::<_, (String, String, Option<String>, String)>(
+            "SELECT id, name, external_id, created_at FROM groups WHERE id = $1",
+        )
+        .bind(group_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((id, name, external_id, created_at)) = row else {
+            return Ok(None);
+        };
+        let members = self.list_group_members(&id).await?;
+        Ok(Some(ScimGroupRecord {
+            id,
+            name,
+            external_id,
+            created_at: parse_rfc3339_utc(&created_at),
+            members,
+        }))
+    }
+
+    /// One page of groups ordered by creation, and the total matching `filter`.
+    pub async fn list_scim_groups(
+        &self,
+        filter: Option<&ScimGroupFilter>,
+        offset: u64,
+        limit: u64,
+    ) -> AppResult<(u64, Vec<ScimGroupRecord>)> {
+        let (condition, value) = match filter {
+            None => ("", None),
+            Some(ScimGroupFilter::DisplayName(v)) => ("WHERE name = $1", Some(v.as_str())),
+            Some(ScimGroupFilter::ExternalId(v)) => ("WHERE external_id = $1", Some(v.as_str())),
+        };
+        let count_sql = format!("SELECT COUNT(*) FROM groups {condition}");
+        let page_sql = format!(
+            "SELECT id FROM groups {condition} ORDER BY created_at ASC, id ASC LIMIT {limit} OFFSET {offset}"
+        );
+
+        let mut count = sqlx::query_as::<_, (i64,)>(&count_sql);
+        let mut page = sqlx::query_as::<_, (String,)>(&page_sql);
+        if let Some(value) = value {
+            count = count.bind(value);
+            page = page.bind(value);
+        }
+        let (total,) = count.fetch_one(&self.pool).await?;
+        let mut groups = Vec::new();
+        for (id,) in page.fetch_all(&self.pool).await? {
+            if let Some(group) = self.get_scim_group(&id).await? {
+                groups.push(group);
+            }
+        }
+        Ok((total.max(0) as u64, groups))
+    }
+
+    /// Create a group on behalf of an identity provider. Unlike
+    /// [`Database::create_group`], the creator is not added as a member.
+    pub async fn create_scim_group(
+        &self,
+        name: &str,
+        external_id: Option<&str>,
+        created_by_user_id: &str,
+    ) -> AppResult<String> {
+        let trimmed = name.trim();
+        if trimmed.is_empty() {
+            return Err(AppError::InvalidInput(
+                "Group name cannot be empty".to_string(),
+            ));
+        }
+        let id = Uuid::new_v4().to_string();
         sqlx::query(
             r#"
-            INSERT INTO vault_user_shares (vault_id, user_id, role, created_at)
-            VALUES (?, ?, 'owner', ?)
-            ON CONFLICT (vault_id, user_id) DO UPDATE SET role = 'owner'
+            INSERT INTO groups (id, name, created_at, created_by_user_id, external_id)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
         )
-        .bind(vault_id)
-        .bind(new_owner_id)
+        .bind(&id)
+        .bind(trimmed)
         .bind(Utc::now().to_rfc3339())
+        .bind(created_by_user_id)
+        .bind(external_id)
         .execute(&self.pool)
-        .await?;
-
-        Ok(())
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                AppError::Conflict("Group with this name already exists".to_string())
+            }
+            _ => AppError::from(e),
+        })?;
+        Ok(id)
     }
 
-    // ── TOTP 2FA ────────────────────────────────────────────────────────
-
-    /// Store a TOTP secret for a user (enrollment step).
-    pub async fn set_totp_secret(
+    pub async fn update_scim_group(
         &self,
-        user_id: &str,
-        secret: &str,
-        backup_codes: &[String],
+        group_id: &str,
+        name: &str,
+        external_id: Option<&str>,
     ) -> AppResult<()> {
-        let codes_json = serde_json::to_string(backup_codes).map_err(|e| {
-            AppError::InternalError(format!("Failed to serialize backup codes: {e}"))
-        })?;
-        sqlx::query("UPDATE users SET totp_secret = ?, totp_backup_codes = ? WHERE id = ?")
-            .bind(secret)
-            .bind(&codes_json)
-            .bind(user_id)
+        let trimmed = name.trim();
+        if trimmed.is_empty() {
+            return Err(AppError::InvalidInput(
+                "Group name cannot be empty".to_string(),
+            ));
+        }
+        let result = sqlx::query("UPDATE groups SET name = $1, external_id = $2 WHERE id = $3")
+            .bind(trimmed)
+            .bind(external_id)
+            .bind(group_id)
             .execute(&self.pool)
-            .await?;
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                    AppError::Conflict("Group with this name already exists".to_string())
+                }
+                _ => AppError::from(e),
+            })?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("Group {group_id} not found")));
+        }
         Ok(())
     }
 
-    /// Enable TOTP after successful verification.
-    pub async fn enable_totp(&self, user_id: &str) -> AppResult<()> {
-        sqlx::query("UPDATE users SET totp_enabled = 1 WHERE id = ?")
-            .bind(user_id)
-            .execute(&self.pool)
-            .await?;
+    /// Make `user_ids` the exact membership of a group.
+    pub async fn set_group_members(&self, group_id: &str, user_ids: &[String]) -> AppResult<()> {
+        let current = self.list_group_members(group_id).await?;
+        for member in &current {
+            if !user_ids.contains(&member.user_id) {
+                self.remove_user_from_group(group_id, &member.user_id)
+                    .await?;
+            }
+        }
+        for user_id in user_ids {
+            if !current.iter().any(|m| &m.user_id == user_id) {
+                self.add_user_to_group(group_id, user_id).await?;
+            }
+        }
         Ok(())
     }
 
-    /// Disable TOTP and clear the secret.
-    pub async fn disable_totp(&self, user_id: &str) -> AppResult<()> {
+    /// Delete a group. Memberships, vault shares and path rules go with it.
+    pub async fn delete_group(&self, group_id: &str) -> AppResult<()> {
+        sqlx::query("DELETE FROM group_members WHERE group_id = $1")
+            .bind(group_id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM vault_group_shares WHERE group_id = $1")
+            .bind(group_id)
+            .execute(&self.pool)
+            .await?;
         sqlx::query(
-            "UPDATE users SET totp_enabled = 0, totp_secret = NULL, totp_backup_codes = NULL WHERE id = ?",
+            "DELETE FROM vault_path_acls WHERE principal_type = 'group' AND principal_id = $1",
         )
-        .bind(user_id)
+        .bind(group_id)
         .execute(&self.pool)
         .await?;
-        Ok(())
-    }
-
-    /// Get TOTP state for a user: (totp_enabled, totp_secret, backup_codes_json).
-    pub async fn get_totp_state(
-        &self,
-        user_id: &str,
-    ) -> AppResult<(bool, Option<String>, Option<String>)> {
-        let row: Option<(i64, Option<String>, Option<String>)> = sqlx::query_as(
-            "SELECT totp_enabled, totp_secret, totp_backup_codes FROM users WHERE id = ?",
-        )
-        .bind(user_id)
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(AppError::from)?;
-
-        match row {
-            Some((enabled, secret, codes)) => Ok((enabled != 0, secret, codes)),
-            None => Err(AppError::NotFound("User not found".to_string())),
-        }
-    }
-
-    /// Consume a backup code (marks it as used by removing from the list).
-    pub async fn consume_backup_code(&self, user_id: &str, code: &str) -> AppResult<bool> {
-        let (_, _, codes_json) = self.get_totp_state(user_id).await?;
-        let Some(codes_json) = codes_json else {
-            return Ok(false);
-        };
-        let mut codes: Vec<String> = serde_json::from_str(&codes_json).unwrap_or_default();
-        if let Some(pos) = codes.iter().position(|c| c == code) {
-            codes.remove(pos);
-            let updated = serde_json::to_string(&codes)
-                .map_err(|e| AppError::InternalError(e.to_string()))?;
-            sqlx::query("UPDATE users SET totp_backup_codes = ? WHERE id = ?")
-                .bind(&updated)
-                .bind(user_id)
-                .execute(&self.pool)
-                .await?;
-            Ok(true)
-        } else {
-            Ok(false)
+        let result = sqlx::query("DELETE FROM groups WHERE id = $1")
+            .bind(group_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("Group {group_id} not found")));
         }
+        Ok(())
     }
 
     // ── Invitations ─────────────────────────────────────────────────────
@@ -2338,7 +3381,7 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO invitations (id, token, role, vault_id, created_by_user_id, created_at, expires_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             "#,
         )
         .bind(id)
@@ -2370,7 +3413,7 @@ impl Database {
         )>,
     > {
         let row = sqlx::query_as::<_, (String, String, Option<String>, String, String, String, i64, Option<String>)>(
-            "SELECT id, role, vault_id, created_by_user_id, created_at, expires_at, accepted, accepted_by_user_id FROM invitations WHERE token = ?",
+            "SELECT id, role, vault_id, created_by_user_id, created_at, expires_at, accepted, accepted_by_user_id FROM invitations WHERE token = $1",
         )
         .bind(token)
         .fetch_optional(&self.pool)
@@ -2388,7 +3431,7 @@ impl Database {
         invite_id: &str,
         accepted_by_user_id: &str,
     ) -> AppResult<()> {
-        sqlx::query("UPDATE invitations SET accepted = 1, accepted_by_user_id = ? WHERE id = ?")
+        sqlx::query("UPDATE invitations SET accepted = 1, accepted_by_user_id = $1 WHERE id = $2")
             .bind(accepted_by_user_id)
             .bind(invite_id)
             .execute(&self.pool)
@@ -2402,7 +3445,7 @@ impl Database {
         user_id: &str,
     ) -> AppResult<Vec<crate::models::InviteInfo>> {
         let rows = sqlx::query_as::<_, (String, String, String, Option<String>, String, String, String, i64, Option<String>)>(
-            "SELECT id, token, role, vault_id, created_by_user_id, created_at, expires_at, accepted, accepted_by_user_id FROM invitations WHERE created_by_user_id = ? ORDER BY created_at DESC",
+            "SELECT id, token, role, vault_id, created_by_user_id, created_at, expires_at, accepted, accepted_by_user_id FROM invitations WHERE created_by_user_id = $1 ORDER BY created_at DESC",
         )
         .bind(user_id)
         .fetch_all(&self.pool)
@@ -2438,4 +3481,277 @@ impl Database {
             )
             .collect())
     }
+    // ── Upload sessions ─────────────────────────────────────────────────
+
+    pub async fn create_upload_session(&self, session: &UploadSession) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO upload_sessions
+                (id, vault_id, owner_id, filename, path, total_size, sha256, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(&session.id)
+        .bind(&session.vault_id)
+        .bind(&session.owner_id)
+        .bind(&session.filename)
+        .bind(&session.path)
+        .bind(session.total_size.map(|size| size as i64))
+        .bind(&session.sha256)
+        .bind(session.created_at.to_rfc3339())
+        .bind(session.expires_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_upload_session(&self, session_id: &str) -> AppResult<UploadSession> {
+        sqlx::query_as::<_, UploadSessionRow>(
+            r#"
+            SELECT id, vault_id, owner_id, filename, path, total_size, sha256, created_at, expires_at
+            FROM upload_sessions WHERE id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(UploadSession::from)
+        .ok_or_else(|| AppError::NotFound("Upload session not found".to_string()))
+    }
+
+    /// Sessions whose expiry is at or before `now`.
+    pub async fn list_expired_upload_sessions(
+        &self,
+        now: DateTime<Utc>,
+    ) -> AppResult<Vec<UploadSession>> {
+        let rows = sqlx::query_as::<_, UploadSessionRow>(
+            r#"
+            SELECT id, vault_id, owner_id, filename, path, total_size, sha256, created_at, expires_at
+            FROM upload_sessions WHERE expires_at <= $1
+            "#,
+        )
+        .bind(now.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(UploadSession::from).collect())
+    }
+
+    /// Ids of every session, for telling live temp files from orphans.
+    pub async fn list_upload_session_ids(&self, vault_id: &str) -> AppResult<Vec<String>> {
+        let rows: Vec<(String,)> =
+            sqlx::query_as("SELECT id FROM upload_sessions WHERE vault_id = $1")
+                .bind(vault_id)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    pub async fn extend_upload_session(
+        &self,
+        session_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> AppResult<()> {
+        sqlx::query("UPDATE upload_sessions SET expires_at = $1 WHERE id = $2")
+            .bind(expires_at.to_rfc3339())
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Record a received chunk. Re-sending the same offset replaces it.
+    pub async fn record_upload_chunk(
+        &self,
+        session_id: &str,
+        offset: u64,
+        length: u64,
+        sha256: Option<&str>,
+    ) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO upload_session_chunks (session_id, chunk_offset, length, sha256)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (session_id, chunk_offset) DO UPDATE SET
+                length = excluded.length,
+                sha256 = excluded.sha256
+            "#,
+        )
+        .bind(session_id)
+        .bind(offset as i64)
+        .bind(length as i64)
+        .bind(sha256)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// `(offset, length)` of every received chunk, ordered by offset.
+    pub async fn list_upload_chunks(&self, session_id: &str) -> AppResult<Vec<(u64, u64)>> {
+        let rows: Vec<(i64, i64)> = sqlx::query_as(
+            "SELECT chunk_offset, length FROM upload_session_chunks WHERE session_id = $1 ORDER BY chunk_offset",
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(offset, length)| (offset.max(0) as u64, length.max(0) as u64))
+            .collect())
+    }
+
+    pub async fn delete_upload_session(&self, session_id: &str) -> AppResult<()> {
+        sqlx::query("DELETE FROM upload_session_chunks WHERE session_id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM upload_sessions WHERE id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // ── Trash index ─────────────────────────────────────────────────────
+
+    /// Record a file that was just moved to a vault's trash.
+    pub async fn insert_trash_item(
+        &self,
+        vault_id: &str,
+        item: &TrashItem,
+        deleted_by: Option<&str>,
+    ) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO trash_items
+                (vault_id, trash_name, original_path, deleted_by, deleted_at, size)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (vault_id, trash_name) DO UPDATE SET
+                original_path = excluded.original_path,
+                deleted_by = excluded.deleted_by,
+                deleted_at = excluded.deleted_at,
+                size = excluded.size
+            "#,
+        )
+        .bind(vault_id)
+        .bind(&item.trash_name)
+        .bind(&item.original_path)
+        .bind(deleted_by)
+        .bind(&item.trashed_at)
+        .bind(item.size as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Trash index of a vault, newest first, with the deleter's username.
+    pub async fn list_trash_items(&self, vault_id: &str) -> AppResult<Vec<TrashItem>> {
+        let rows = sqlx::query_as::<_, TrashItemRow>(
+            r#"
+            SELECT t.trash_name, t.original_path, t.deleted_by,
+                   u.username AS deleted_by_username, t.deleted_at, t.size
+            FROM trash_items t
+            LEFT JOIN users u ON u.id = t.deleted_by
+            WHERE t.vault_id = $1
+            ORDER BY t.deleted_at DESC
+            "#,
+        )
+        .bind(vault_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(TrashItem::from).collect())
+    }
+
+    pub async fn delete_trash_item(&self, vault_id: &str, trash_name: &str) -> AppResult<()> {
+        sqlx::query("DELETE FROM trash_items WHERE vault_id = $1 AND trash_name = $2")
+            .bind(vault_id)
+            .bind(trash_name)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // ── Git-backed vaults ───────────────────────────────────────────────
+
+    /// Git settings for a vault; a disabled default when none were saved.
+    pub async fn get_vault_git_settings(&self, vault_id: &str) -> AppResult<VaultGitSettings> {
+        let row = sqlx::query_as::<_, VaultGitSettingsRow>(
+            r#"
+            SELECT vault_id, enabled, auto_commit, remote_url, branch, sync_interval_secs,
+                   last_sync_at, last_sync_error
+            FROM vault_git_settings
+            WHERE vault_id = $1
+            "#,
+        )
+        .bind(vault_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::from)?;
+
+        Ok(row
+            .map(VaultGitSettings::from)
+            .unwrap_or_else(|| VaultGitSettings::disabled(vault_id)))
+    }
+
+    /// Insert or replace the user-editable git settings of a vault.
+    /// Sync bookkeeping (`last_sync_at`/`last_sync_error`) is preserved.
+    pub async fn upsert_vault_git_settings(&self, settings: &VaultGitSettings) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO vault_git_settings
+                (vault_id, enabled, auto_commit, remote_url, branch, sync_interval_secs, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT(vault_id) DO UPDATE SET
+                enabled = excluded.enabled,
+                auto_commit = excluded.auto_commit,
+                remote_url = excluded.remote_url,
+                branch = excluded.branch,
+                sync_interval_secs = excluded.sync_interval_secs,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(&settings.vault_id)
+        .bind(if settings.enabled { 1_i64 } else { 0_i64 })
+        .bind(if settings.auto_commit { 1_i64 } else { 0_i64 })
+        .bind(settings.remote_url.as_deref())
+        .bind(&settings.branch)
+        .bind(settings.sync_interval_secs as i64)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// All vaults that have git integration switched on.
+    pub async fn list_git_enabled_vaults(&self) -> AppResult<Vec<VaultGitSettings>> {
+        let rows = sqlx::query_as::<_, VaultGitSettingsRow>(
+            r#"
+            SELECT vault_id, enabled, auto_commit, remote_url, branch, sync_interval_secs,
+                   last_sync_at, last_sync_error
+            FROM vault_git_settings
+            WHERE enabled = 1
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::from)?;
+        Ok(rows.into_iter().map(VaultGitSettings::from).collect())
+    }
+
+    /// Record the result of a pull/push round-trip with the vault's remote.
+    pub async fn record_vault_git_sync(
+        &self,
+        vault_id: &str,
+        error: Option<&str>,
+    ) -> AppResult<()> {
+        sqlx::query(
+            "UPDATE vault_git_settings SET last_sync_at = $1, last_sync_error = $2 WHERE vault_id = $3",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(error)
+        .bind(vault_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
 }
@@ -206,9 +206,9 @@ impl AppError {
             sqlx::Error::RowNotFound => "Record not found in database".to_string(),
             sqlx::Error::ColumnNotFound(_) => "Database schema mismatch".to_string(),
             sqlx::Error::Database(db_err) => {
-                if db_err.message().contains("UNIQUE") {
+                if db_err.is_unique_violation() {
                     "A record with this value already exists".to_string()
-                } else if db_err.message().contains("FOREIGN KEY") {
+                } else if db_err.is_foreign_key_violation() {
                     "Referenced record does not exist".to_string()
                 } else {
                     format!("Database constraint violation: {}", db_err.message())
@@ -189,11 +189,30 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
     }
 
     // --- Database ----------------------------------------------------------
-    let db_url = format!("sqlite:{}", config.database.path);
-    let db = Database::new(&db_url)
+    let db_url = config
+        .database
+        .connection_url()
+        .expect("Invalid [database] configuration");
+    let db = Database::connect_with(&db_url, config.database.max_connections)
         .await
-        .expect("Failed to initialize database");
-    info!("Database initialized at {}", config.database.path);
+        .expect("Failed to open database")
+        .with_audit_hash_chain(config.audit.hash_chain);
+    if config.database.auto_migrate {
+        db.migrate().await.expect("Failed to migrate database");
+    } else {
+        let status = db
+            .migration_status()
+            .await
+            .expect("Failed to read database schema version");
+        if status.legacy || !status.pending.is_empty() {
+            panic!(
+                "Database schema is at version {} but this build expects {}; \
+                 run `codex db migrate` or enable [database].auto_migrate",
+                status.current_version, status.latest_version
+            );
+        }
+    }
+    info!("Database initialized at {}", config.database.location());
 
     match db
         .bootstrap_admin_if_empty(
@@ -243,13 +262,50 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
     let (event_tx, _) = broadcast::channel::<models::FileChangeEvent>(100);
     let event_tx_clone = event_tx.clone();
     let (ws_tx, _) = broadcast::channel::<models::WsMessage>(64);
+    services::ClusterService::relay_events(&db, &db_url, event_tx.clone(), ws_tx.clone())
+        .await
+        .expect("Failed to start cross-instance event relay");
+    // Only the leader runs the background jobs started further down
+    let leadership = services::ClusterService::elect_leader(&db, &db_url).await;
+
+    // Plugin and vault-local types share these registries
+    let entity_type_registry = EntityTypeRegistry::new();
+    let relation_type_registry = RelationTypeRegistry::new();
 
+    let git_autocommit = services::GitAutoCommitter::new();
+    let git_autocommit_clone = git_autocommit.clone();
     let search_index_clone = search_index.clone();
     let db_clone = db.clone();
+    let entity_type_registry_clone = entity_type_registry.clone();
+    let relation_type_registry_clone = relation_type_registry.clone();
     tokio::spawn(async move {
         while let Some(change_event) = change_rx.recv().await {
             info!("File change detected: {:?}", change_event);
 
+            git_autocommit_clone.note_change(&change_event.vault_id, &change_event.path);
+            if let models::FileChangeType::Renamed { to, .. } = &change_event.event_type {
+                git_autocommit_clone.note_change(&change_event.vault_id, to);
+            }
+
+            // Vault type definitions are reloaded, not indexed or broadcast
+            let types_dir = services::schema_service::VAULT_TYPES_DIR;
+            if std::path::Path::new(&change_event.path).starts_with(types_dir) {
+                if let Ok(vault) = db_clone.get_vault(&change_event.vault_id).await {
+                    if let Err(e) = ReindexService::reload_vault_types(
+                        &db_clone,
+                        &vault.id,
+                        &vault.path,
+                        &entity_type_registry_clone,
+                        &relation_type_registry_clone,
+                    )
+                    .await
+                    {
+                        warn!("Reloading types failed for vault {}: {e}", vault.id);
+                    }
+                }
+                continue;
+            }
+
             match &change_event.event_type {
                 models::FileChangeType::Created | models::FileChangeType::Modified => {
                     if change_event.path.ends_with(".md") {
@@ -332,6 +388,34 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
         }
     });
 
+    // --- Plugin schemas ----------------------------------------------------
+    let (shutdown_tx, _) = broadcast::channel::<()>(1);
+    let plugins_dir = services::resolve_plugins_dir();
+    info!("Using plugins directory: {}", plugins_dir.display());
+
+    {
+        use services::PluginService;
+        let mut plugin_svc = PluginService::new(plugins_dir.clone());
+        match plugin_svc.discover_plugins() {
+            Ok(plugins) => {
+                if let Err(e) = SchemaService::load_plugin_schemas(
+                    &db,
+                    &plugins,
+                    &entity_type_registry,
+                    &relation_type_registry,
+                )
+                .await
+                {
+                    warn!("Schema loading error: {e}");
+                }
+            }
+            Err(e) => {
+                warn!("Plugin discovery failed during schema load: {e}");
+            }
+        }
+    }
+    info!("Plugin schemas loaded");
+
     // --- Vault loading -----------------------------------------------------
     let vaults = db.list_vaults().await.expect("Failed to list vaults");
     for vault in vaults {
@@ -365,6 +449,15 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
             Err(e) => error!("Failed to index vault {}: {}", vault.id, e),
         }
 
+        SchemaService::load_vault_schemas(
+            &db,
+            &vault.id,
+            &vault.path,
+            &entity_type_registry,
+            &relation_type_registry,
+        )
+        .await;
+
         let db_reindex = db.clone();
         let vid = vault.id.clone();
         let vpath = vault.path.clone();
@@ -375,35 +468,133 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
         });
     }
 
-    // --- Plugin schemas ----------------------------------------------------
-    let (shutdown_tx, _) = broadcast::channel::<()>(1);
-    let plugins_dir = services::resolve_plugins_dir();
-    info!("Using plugins directory: {}", plugins_dir.display());
-
-    let entity_type_registry = EntityTypeRegistry::new();
-    let relation_type_registry = RelationTypeRegistry::new();
+    // --- Git auto-commit and remote sync ------------------------------------
     {
-        use services::PluginService;
-        let mut plugin_svc = PluginService::new(plugins_dir.clone());
-        match plugin_svc.discover_plugins() {
-            Ok(plugins) => {
-                if let Err(e) = SchemaService::load_plugin_schemas(
-                    &db,
-                    &plugins,
-                    &entity_type_registry,
-                    &relation_type_registry,
-                )
-                .await
-                {
-                    warn!("Schema loading error: {e}");
+        let db = db.clone();
+        let git_config = config.git.clone();
+        let ws_tx = ws_tx.clone();
+        let git_autocommit = git_autocommit.clone();
+        let leadership = leadership.clone();
+        tokio::spawn(async move {
+            let quiet = std::time::Duration::from_secs(git_config.commit_debounce_secs);
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+
+                // Followers drop what they noted; the leader watches the
+                // same vault directories and commits those changes itself.
+                let due = git_autocommit.take_due(quiet);
+                if !leadership.is_leader() {
+                    continue;
+                }
+                for due in due {
+                    match db.get_vault_git_settings(&due.vault_id).await {
+                        Ok(settings) if settings.enabled && settings.auto_commit => {}
+                        _ => continue,
+                    }
+                    let message = services::git_service::auto_commit_message(&due.paths);
+                    if let Err(e) = services::git_service::commit_vault(
+                        &db,
+                        &git_config,
+                        &due.vault_id,
+                        message,
+                        due.author,
+                    )
+                    .await
+                    {
+                        warn!("Git auto-commit failed for vault {}: {}", due.vault_id, e);
+                    }
+                }
+
+                let Ok(enabled) = db.list_git_enabled_vaults().await else {
+                    continue;
+                };
+                let now = chrono::Utc::now();
+                for settings in enabled {
+                    if settings.remote_url.is_none() || settings.sync_interval_secs == 0 {
+                        continue;
+                    }
+                    let due = settings.last_sync_at.is_none_or(|last| {
+                        (now - last).num_seconds() >= settings.sync_interval_secs as i64
+                    });
+                    if !due {
+                        continue;
+                    }
+                    if let Err(e) = services::git_service::sync_vault(
+                        &db,
+                        &git_config,
+                        &ws_tx,
+                        &settings.vault_id,
+                    )
+                    .await
+                    {
+                        warn!("Git sync failed for vault {}: {}", settings.vault_id, e);
+                    }
                 }
             }
-            Err(e) => {
-                warn!("Plugin discovery failed during schema load: {e}");
+        });
+    }
+
+    // --- Scheduled snapshots ----------------------------------------------
+    if config.backup.enabled {
+        let db = db.clone();
+        let backup_config = config.backup.clone();
+        let leadership = leadership.clone();
+        info!(
+            "Snapshot scheduler enabled: every {} min into {}",
+            backup_config.interval_minutes, backup_config.directory
+        );
+        tokio::spawn(async move {
+            let period = std::time::Duration::from_secs(backup_config.interval_minutes.max(1) * 60);
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                if !leadership.is_leader() {
+                    continue;
+                }
+                if let Err(e) = services::backup_service::snapshot_all(&db, &backup_config).await {
+                    error!("Scheduled snapshot failed: {}", e);
+                }
             }
-        }
+        });
+    }
+
+    // --- Trash retention ----------------------------------------------------
+    if config.vault.trash_retention_days > 0 {
+        let db = db.clone();
+        let retention_days = config.vault.trash_retention_days;
+        let leadership = leadership.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(3600));
+            loop {
+                interval.tick().await;
+                if !leadership.is_leader() {
+                    continue;
+                }
+                if let Err(e) = services::TrashService::purge_expired(&db, retention_days).await {
+                    error!("Trash retention sweep failed: {}", e);
+                }
+            }
+        });
+    }
+
+    // --- Upload session sweeper --------------------------------------------
+    {
+        let db = db.clone();
+        let ttl_hours = config.vault.upload_session_ttl_hours;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(600));
+            loop {
+                interval.tick().await;
+                if !leadership.is_leader() {
+                    continue;
+                }
+                if let Err(e) = services::UploadService::sweep(&db, ttl_hours).await {
+                    error!("Upload session sweep failed: {}", e);
+                }
+            }
+        });
     }
-    info!("Plugin schemas loaded");
 
     // --- HTTP server -------------------------------------------------------
     let app_state = web::Data::new(AppState {
@@ -419,6 +610,7 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
         plugins_dir: plugins_dir.clone(),
         shutdown_tx: shutdown_tx.clone(),
         document_parser: Arc::new(MarkdownParser),
+        git_autocommit,
     });
     let app_config = web::Data::new(config.clone());
 
@@ -446,9 +638,10 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
             .app_data(app_config.clone())
             .wrap(cors)
             .wrap(middleware::RequestLogging)
-            .wrap(middleware::RequestIdMiddleware)
             .wrap(middleware::RateLimitMiddleware)
             .wrap(middleware::AuthMiddleware)
+            .wrap(middleware::AuditMiddleware)
+            .wrap(middleware::RequestIdMiddleware)
             .wrap(actix_web::middleware::Compress::default())
             .configure(routes::health::configure)
             .configure(routes::version::configure)
@@ -457,6 +650,7 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
             .configure(routes::groups::configure)
             .configure(routes::vaults::configure)
             .configure(routes::files::configure)
+            .configure(routes::git::configure)
             .configure(routes::search::configure)
             .configure(routes::ml::configure)
             .configure(routes::ws::configure)
@@ -464,12 +658,15 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
             .configure(routes::preferences::configure)
             .configure(routes::entities::configure)
             .configure(routes::plugins::configure)
+            .configure(routes::scim::configure)
             .configure(configure_static)
             .configure(routes::bookmarks::configure)
             .configure(routes::tags::configure)
             .configure(routes::api_keys::configure)
             .configure(routes::totp::configure)
+            .configure(routes::webauthn::configure)
             .configure(routes::invitations::configure)
+            .configure(routes::share_links::configure)
             .configure(routes::oidc::configure)
     })
     .shutdown_timeout(10);
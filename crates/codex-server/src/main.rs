@@ -1,4 +1,7 @@
-use clap::Parser;
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use codex::config::AppConfig;
+use codex::services::backup_service::{self, BackupService};
 
 /// Command-line arguments for the Codex knowledge server.
 #[derive(Parser, Debug)]
@@ -11,17 +14,222 @@ struct Args {
         long,
         default_value = "./config.toml",
         env = "CODEX_CONFIG",
-        value_name = "PATH"
+        value_name = "PATH",
+        global = true
     )]
     config: std::path::PathBuf,
+
+    /// Run an offline maintenance command instead of starting the server.
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Take a snapshot of every vault (and the database) into `[backup].directory`.
+    Backup {
+        /// List existing snapshots instead of taking a new one.
+        #[arg(long)]
+        list: bool,
+        /// Verify the stored contents of a snapshot instead of taking a new one.
+        #[arg(long, value_name = "SNAPSHOT")]
+        verify: Option<String>,
+    },
+    /// Restore a vault, a single path, or the database from a snapshot.
+    ///
+    /// Stop the server before restoring the database.
+    Restore {
+        /// Snapshot id as printed by `codex backup --list`.
+        snapshot: String,
+        /// Vault to restore.
+        #[arg(long, value_name = "VAULT_ID", required_unless_present = "database")]
+        vault: Option<String>,
+        /// File or directory inside the vault; the whole vault when omitted.
+        #[arg(long, requires = "vault")]
+        path: Option<String>,
+        /// Write the restored path here instead of over the original.
+        #[arg(long, requires = "path")]
+        target: Option<String>,
+        /// Replace the database file with the snapshot's copy.
+        #[arg(long)]
+        database: bool,
+    },
+    /// Inspect or apply database schema migrations.
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    /// Apply pending schema migrations.
+    Migrate {
+        /// Stop after this schema version instead of the latest.
+        #[arg(long, value_name = "VERSION")]
+        to: Option<i64>,
+    },
+    /// Show applied and pending schema migrations.
+    Status,
 }
 
 #[actix_web::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
-    let config = codex::config::AppConfig::load_from_file(args.config).unwrap_or_else(|e| {
+    let config = AppConfig::load_from_file(args.config).unwrap_or_else(|e| {
         eprintln!("Warning: {e}. Using default configuration.");
-        codex::config::AppConfig::default()
+        AppConfig::default()
     });
-    codex::run(config).await
+
+    match args.command {
+        None => codex::run(config).await,
+        Some(command) => run_command(config, command).await,
+    }
+}
+
+async fn run_command(config: AppConfig, command: Command) -> anyhow::Result<()> {
+    let service = BackupService::new(&config.backup);
+    match command {
+        Command::Db { command } => run_db_command(&config, command).await?,
+        Command::Backup { list: true, .. } => {
+            for snapshot in service.list_snapshots()? {
+                println!(
+                    "{}  {}  {} vault(s)  {} file(s)  {} bytes{}",
+                    snapshot.id,
+                    snapshot.created_at.to_rfc3339(),
+                    snapshot.vault_count,
+                    snapshot.file_count,
+                    snapshot.total_size,
+                    if snapshot.includes_database {
+                        "  +db"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        Command::Backup {
+            verify: Some(snapshot_id),
+            ..
+        } => {
+            let report = service.verify(&snapshot_id)?;
+            println!(
+                "{}: {} object(s) checked, {} missing, {} corrupted",
+                report.snapshot_id,
+                report.objects_checked,
+                report.missing.len(),
+                report.corrupted.len()
+            );
+            if !report.is_ok() {
+                anyhow::bail!("snapshot {} failed verification", report.snapshot_id);
+            }
+        }
+        Command::Backup { .. } => {
+            let db = open_database(&config).await?;
+            let manifest = backup_service::snapshot_all(&db, &config.backup).await?;
+            println!("Created snapshot {}", manifest.id);
+        }
+        Command::Restore {
+            snapshot,
+            vault,
+            path,
+            target,
+            database,
+        } => {
+            if database {
+                if config.database.backend != codex::config::DatabaseBackend::Sqlite {
+                    anyhow::bail!("--database restores only apply to the SQLite backend");
+                }
+                let dest = service
+                    .restore_database(&snapshot, std::path::Path::new(&config.database.path))?;
+                println!("Restored database to {}", dest.display());
+            }
+            if let Some(vault_id) = vault {
+                // Prefer the vault's current location; fall back to where it
+                // lived when the snapshot was taken.
+                let db = open_database(&config).await?;
+                let vault_path = match db.get_vault(&vault_id).await {
+                    Ok(vault) => vault.path,
+                    Err(_) => service
+                        .load_manifest(&snapshot)?
+                        .vaults
+                        .into_iter()
+                        .find(|v| v.vault_id == vault_id)
+                        .map(|v| v.path)
+                        .with_context(|| {
+                            format!("snapshot {snapshot} does not contain vault {vault_id}")
+                        })?,
+                };
+                std::fs::create_dir_all(&vault_path)?;
+                let report = service.restore(
+                    &snapshot,
+                    &vault_id,
+                    &vault_path,
+                    path.as_deref(),
+                    target.as_deref(),
+                )?;
+                println!(
+                    "Restored {} file(s), {} unchanged, {} moved to trash",
+                    report.restored.len(),
+                    report.unchanged,
+                    report.trashed.len()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_db_command(config: &AppConfig, command: DbCommand) -> anyhow::Result<()> {
+    let db = codex::db::Database::connect(&config.database.connection_url()?)
+        .await
+        .with_context(|| format!("failed to open database {}", config.database.location()))?;
+    match command {
+        DbCommand::Migrate { to } => {
+            let applied = match to {
+                Some(version) => db.migrate_to(version).await?,
+                None => db.migrate().await?,
+            };
+            if applied.is_empty() {
+                println!("Database is up to date");
+            }
+            for migration in applied {
+                println!(
+                    "Applied {:04}_{} ({} ms)",
+                    migration.version, migration.name, migration.duration_ms
+                );
+            }
+        }
+        DbCommand::Status => {
+            let status = db.migration_status().await?;
+            println!(
+                "Schema version {} of {}{}",
+                status.current_version,
+                status.latest_version,
+                if status.legacy {
+                    " (created before versioned migrations; `codex db migrate` will adopt it)"
+                } else {
+                    ""
+                }
+            );
+            for migration in &status.applied {
+                println!(
+                    "  {:04}_{}  applied {}",
+                    migration.version,
+                    migration.name,
+                    migration.applied_at().to_rfc3339()
+                );
+            }
+            for migration in &status.pending {
+                println!("  {:04}_{}  pending", migration.version, migration.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn open_database(config: &AppConfig) -> anyhow::Result<codex::db::Database> {
+    codex::db::Database::new(&config.database.connection_url()?)
+        .await
+        .with_context(|| format!("failed to open database {}", config.database.location()))
 }
@@ -0,0 +1,273 @@
+use crate::middleware::request_id::RequestId;
+use crate::middleware::AuthenticatedUser;
+use crate::models::audit::{AuditContext, AuditEvent};
+use crate::models::AuditOutcome;
+use crate::routes::vaults::AppState;
+use actix_web::{
+    dev::{
+        forward_ready, Path, ResourceDef, Service, ServiceRequest, ServiceResponse, Transform, Url,
+    },
+    http::{Method, StatusCode},
+    web, Error, HttpMessage,
+};
+use std::future::{ready, Ready};
+use std::pin::Pin;
+
+/// Middleware that records file access, sharing and admin requests in the
+/// audit log, and makes the caller's IP address, request ID and vault
+/// available to audit entries written while the request is handled.
+///
+/// Requests are classified by method and route pattern once the response
+/// is ready, so requests refused by `AuthMiddleware` are recorded with a
+/// `denied` outcome. Any other 403 is recorded as `permission_denied`.
+/// Must wrap `AuthMiddleware` and be wrapped by `RequestIdMiddleware`.
+pub struct AuditMiddleware;
+
+/// Request extension naming the vault path a request acts on, for routes
+/// that take it in the body rather than the URL.
+#[derive(Clone, Debug)]
+pub struct AuditPath(pub String);
+
+/// Request extension set by code that has already written the audit entry
+/// for a request, so `AuditMiddleware` does not record it again.
+#[derive(Clone, Copy, Debug)]
+pub struct AuditRecorded;
+
+impl<S, B> Transform<S, ServiceRequest> for AuditMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuditMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuditMiddlewareService { service }))
+    }
+}
+
+pub struct AuditMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for AuditMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let context = AuditContext {
+            ip_address: req.connection_info().peer_addr().map(str::to_string),
+            request_id: req.extensions().get::<RequestId>().map(|r| r.0.clone()),
+            vault_id: vault_id_from_path(req.path()),
+        };
+        let state = req.app_data::<web::Data<AppState>>().cloned();
+
+        let fut = context.clone().scope(self.service.call(req));
+
+        Box::pin(async move {
+            let res = fut.await?;
+            let Some(state) = state else {
+                return Ok(res);
+            };
+
+            let request = res.request();
+            if request.extensions().contains::<AuditRecorded>() {
+                return Ok(res);
+            }
+            let status = res.status();
+            let pattern = request.match_pattern();
+            let Some(event_type) = classify(request.method(), pattern.as_deref(), status) else {
+                return Ok(res);
+            };
+
+            let user = request.extensions().get::<AuthenticatedUser>().cloned();
+            let captured = pattern.as_deref().map(|p| capture(p, request.uri().path()));
+            let param = |name: &str| {
+                captured
+                    .as_ref()
+                    .and_then(|path| path.get(name))
+                    .map(str::to_string)
+            };
+            let event = AuditEvent {
+                user_id: user.as_ref().map(|u| u.user_id.clone()),
+                username: user.map(|u| u.username),
+                event_type: event_type.to_string(),
+                detail: Some(format!(
+                    "{} {} -> {}",
+                    request.method(),
+                    request.path(),
+                    status.as_u16()
+                )),
+                vault_id: param("vault_id").or_else(|| param("id")),
+                path: request
+                    .extensions()
+                    .get::<AuditPath>()
+                    .map(|p| p.0.clone())
+                    .or_else(|| param("file_path")),
+                outcome: outcome_for(status),
+                ..Default::default()
+            };
+            let _ = context.scope(state.db.record_audit_event(event)).await;
+            Ok(res)
+        })
+    }
+}
+
+fn outcome_for(status: StatusCode) -> AuditOutcome {
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        AuditOutcome::Denied
+    } else if status.is_success() || status.is_redirection() {
+        AuditOutcome::Success
+    } else {
+        AuditOutcome::Failure
+    }
+}
+
+/// The audit event type of a request, or `None` for requests that are not
+/// audited here (including those whose handlers write their own entry).
+fn classify(method: &Method, pattern: Option<&str>, status: StatusCode) -> Option<&'static str> {
+    let event =
+        pattern.and_then(|pattern| match (method.as_str(), pattern) {
+            (
+                "GET",
+                "/api/vaults/{vault_id}/files/{file_path:.*}"
+                | "/api/vaults/{vault_id}/raw/{file_path:.*}"
+                | "/api/vaults/{vault_id}/download/{file_path:.*}"
+                | "/api/vaults/{vault_id}/git/diff/{file_path:.*}"
+                | "/api/vaults/{vault_id}/git/blame/{file_path:.*}"
+                | "/api/public/shares/{token}/files/{file_path:.*}",
+            )
+            | (
+                "POST",
+                "/api/vaults/{vault_id}/download-zip" | "/api/vaults/{vault_id}/download-tar",
+            ) => Some("file_read"),
+            ("PUT", "/api/vaults/{vault_id}/files/{file_path:.*}")
+            | (
+                "POST",
+                "/api/vaults/{vault_id}/files"
+                | "/api/vaults/{vault_id}/directories"
+                | "/api/vaults/{vault_id}/rename"
+                | "/api/vaults/{vault_id}/upload"
+                | "/api/vaults/{vault_id}/upload-sessions/{session_id}/finish"
+                | "/api/vaults/{vault_id}/import-archive"
+                | "/api/vaults/{vault_id}/daily"
+                | "/api/vaults/{vault_id}/trash/restore"
+                | "/api/vaults/{vault_id}/trash/{trash_name}/restore"
+                | "/api/vaults/{vault_id}/entities"
+                | "/api/vaults/{vault_id}/relations"
+                | "/api/vaults/{vault_id}/entity-types/{type_id}/migrate",
+            )
+            | ("PATCH" | "DELETE", "/api/vaults/{vault_id}/relations/{relation_id}")
+            | ("PATCH", "/api/vaults/{vault_id}/entities/{entity_id}") => Some("file_write"),
+            (
+                "DELETE",
+                "/api/vaults/{vault_id}/files/{file_path:.*}"
+                | "/api/vaults/{vault_id}/trash/{trash_name}"
+                | "/api/vaults/{vault_id}/entities/{entity_id}",
+            )
+            | ("POST", "/api/vaults/{vault_id}/trash/purge") => Some("file_delete"),
+            ("POST", "/api/vaults/{id}/shares/users" | "/api/vaults/{id}/shares/groups") => {
+                Some("vault_share_granted")
+            }
+            (
+                "DELETE",
+                "/api/vaults/{id}/shares/users/{user_id}"
+                | "/api/vaults/{id}/shares/groups/{group_id}",
+            ) => Some("vault_share_revoked"),
+            ("POST", "/api/vaults/{id}/shares/paths") => Some("path_acl_granted"),
+            ("DELETE", "/api/vaults/{id}/shares/paths/{acl_id}") => Some("path_acl_revoked"),
+            ("POST", "/api/vaults/{vault_id}/visibility") => Some("vault_visibility_changed"),
+            ("POST", "/api/vaults") => Some("vault_created"),
+            ("DELETE", "/api/vaults/{id}") => Some("vault_deleted"),
+            ("PUT", "/api/vaults/{vault_id}/git") => Some("vault_git_settings_changed"),
+            ("PUT" | "DELETE", "/api/vaults/{vault_id}/types/{kind}/{type_id}") => {
+                Some("vault_types_changed")
+            }
+            ("POST", "/api/admin/users") => Some("user_created"),
+            ("POST", "/api/groups") => Some("group_created"),
+            ("POST", "/api/groups/{group_id}/members") => Some("group_member_added"),
+            ("DELETE", "/api/groups/{group_id}/members/{user_id}") => Some("group_member_removed"),
+            ("POST", "/api/admin/backups/{snapshot_id}/verify") => Some("backup_verified"),
+            _ => None,
+        });
+    event.or((status == StatusCode::FORBIDDEN).then_some("permission_denied"))
+}
+
+/// Match `path` against a route pattern. Unlike `match_info`, this also
+/// works for requests that were refused before reaching their handler.
+fn capture(pattern: &str, path: &str) -> Path<Url> {
+    let mut captured = Path::new(Url::new(path.parse().unwrap_or_default()));
+    ResourceDef::new(pattern).capture_match_info(&mut captured);
+    captured
+}
+
+fn vault_id_from_path(path: &str) -> Option<String> {
+    path.strip_prefix("/api/vaults/")?
+        .split('/')
+        .next()
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_by_method_and_route() {
+        let pattern = Some("/api/vaults/{vault_id}/files/{file_path:.*}");
+        assert_eq!(
+            classify(&Method::GET, pattern, StatusCode::OK),
+            Some("file_read")
+        );
+        assert_eq!(
+            classify(&Method::PUT, pattern, StatusCode::FORBIDDEN),
+            Some("file_write")
+        );
+        assert_eq!(
+            classify(&Method::DELETE, pattern, StatusCode::NOT_FOUND),
+            Some("file_delete")
+        );
+        assert_eq!(
+            classify(&Method::GET, Some("/api/vaults"), StatusCode::OK),
+            None
+        );
+        assert_eq!(
+            classify(
+                &Method::GET,
+                Some("/api/admin/users"),
+                StatusCode::FORBIDDEN
+            ),
+            Some("permission_denied")
+        );
+        assert_eq!(classify(&Method::GET, None, StatusCode::UNAUTHORIZED), None);
+    }
+
+    #[test]
+    fn captures_file_path_and_vault() {
+        let captured = capture(
+            "/api/vaults/{vault_id}/files/{file_path:.*}",
+            "/api/vaults/v1/files/notes/My%20Note.md",
+        );
+        assert_eq!(captured.get("vault_id"), Some("v1"));
+        assert_eq!(captured.get("file_path"), Some("notes/My Note.md"));
+        assert_eq!(
+            vault_id_from_path("/api/vaults/v1/files/a.md").as_deref(),
+            Some("v1")
+        );
+        assert_eq!(vault_id_from_path("/api/vaults"), None);
+        assert_eq!(vault_id_from_path("/api/groups"), None);
+    }
+}
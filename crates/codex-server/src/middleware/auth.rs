@@ -1,7 +1,11 @@
 use argon2::PasswordVerifier;
 
 use crate::config::AppConfig;
+use crate::middleware::AuditRecorded;
+use crate::models::audit::AuditEvent;
+use crate::models::{ApiKeyRestrictions, ApiKeyScope, AuditOutcome, PathAccess};
 use crate::routes::AppState;
+use crate::services::path_acl_service::{glob_matches, normalize_path, PathAclService};
 use actix_web::body::{EitherBody, MessageBody};
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
@@ -22,6 +26,14 @@ pub struct AuthenticatedUser {
     pub username: String,
 }
 
+/// Inserted into request extensions when the caller authenticated with an
+/// API key, so handlers can honour the key's restrictions.
+#[derive(Debug, Clone)]
+pub struct ApiKeyContext {
+    pub key_id: String,
+    pub restrictions: ApiKeyRestrictions,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum RequiredVaultRole {
     Read,
@@ -130,6 +142,19 @@ where
 
                     if is_public {
                         // Public vault — serve the read request without authentication.
+                        if let Some(state) = state_clone.as_ref() {
+                            if let Err(response) = authorize_path_access(
+                                state,
+                                &req,
+                                &vault_id,
+                                None,
+                                RequiredVaultRole::Read,
+                            )
+                            .await
+                            {
+                                return Ok(req.into_response(response).map_into_right_body());
+                            }
+                        }
                         let fut = service.call(req);
                         return Ok(fut.await?.map_into_left_body());
                     }
@@ -180,18 +205,17 @@ where
                     .collect();
 
                 let row = state.db.get_api_key_by_prefix(&prefix).await;
-                let Ok(Some((_id, key_hash, user_id, expires_at, revoked))) = row else {
+                let Ok(Some((key, key_hash))) = row else {
                     unauthorized!("Invalid API key");
                 };
-                if revoked {
+                if key.revoked {
                     unauthorized!("API key has been revoked");
                 }
-                if let Some(exp_str) = expires_at {
-                    if let Ok(exp) = chrono::DateTime::parse_from_rfc3339(&exp_str) {
-                        if exp < chrono::Utc::now() {
-                            unauthorized!("API key has expired");
-                        }
-                    }
+                if key.expires_at.is_some_and(|exp| exp < chrono::Utc::now()) {
+                    unauthorized!("API key has expired");
+                }
+                if !state.db.is_user_active(&key.user_id).await.unwrap_or(false) {
+                    unauthorized!("Account is deactivated");
                 }
                 let Ok(parsed_hash) = argon2::password_hash::PasswordHash::new(&key_hash) else {
                     unauthorized!("Invalid API key");
@@ -205,6 +229,7 @@ where
 
                 // Resolve username and inject AuthenticatedUser into request extensions
                 // so downstream handlers (e.g. /api/auth/me) can identify the caller.
+                let user_id = key.user_id.clone();
                 let username = state
                     .db
                     .get_user_by_id(&user_id)
@@ -213,11 +238,66 @@ where
                     .flatten()
                     .map(|(_, u)| u)
                     .unwrap_or_else(|| user_id.clone());
+                let ip_address = req.connection_info().peer_addr().map(str::to_string);
 
+                // Identify the caller before authorizing so that denials
+                // are attributed in the audit log.
                 req.extensions_mut().insert(UserId(user_id.clone()));
                 req.extensions_mut().insert(AuthenticatedUser {
                     user_id: user_id.clone(),
-                    username,
+                    username: username.clone(),
+                });
+
+                if let Some(reason) = api_key_denial(&req, &key.restrictions) {
+                    let _ = state
+                        .db
+                        .record_audit_event(AuditEvent {
+                            user_id: Some(user_id.clone()),
+                            username: Some(username.clone()),
+                            detail: Some(format!(
+                                "API key {} denied {} {}: {}",
+                                key.prefix,
+                                req.method(),
+                                req.path(),
+                                reason
+                            )),
+                            ip_address: ip_address.clone(),
+                            outcome: AuditOutcome::Denied,
+                            ..AuditEvent::new("api_key_denied")
+                        })
+                        .await;
+                    req.extensions_mut().insert(AuditRecorded);
+                    let response = HttpResponse::Forbidden().json(serde_json::json!({
+                        "error": "FORBIDDEN",
+                        "message": reason
+                    }));
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+
+                if let Some((vault_id, required_role)) = required_vault_role(&req) {
+                    if let Err(response) =
+                        authorize_vault_access(&state, &vault_id, &user_id, required_role).await
+                    {
+                        return Ok(req.into_response(response).map_into_right_body());
+                    }
+                    if let Err(response) = authorize_path_access(
+                        &state,
+                        &req,
+                        &vault_id,
+                        Some(&user_id),
+                        required_role,
+                    )
+                    .await
+                    {
+                        return Ok(req.into_response(response).map_into_right_body());
+                    }
+                }
+
+                let _ = state.db.touch_api_key(&key.id, ip_address.as_deref()).await;
+
+                req.extensions_mut().insert(ApiKeyContext {
+                    key_id: key.id,
+                    restrictions: key.restrictions,
                 });
 
                 let fut = service.call(req);
@@ -264,6 +344,8 @@ where
 
         Box::pin(async move {
             let req = req;
+            req.extensions_mut().insert(UserId(user.user_id.clone()));
+            req.extensions_mut().insert(user.clone());
 
             if let Some(state) = state.as_ref() {
                 if !is_password_change_exempt_path(req.path()) {
@@ -289,40 +371,24 @@ where
             }
 
             if let (Some((vault_id, required_role)), Some(state)) = (required_vault_role, state) {
-                let user_role = state
-                    .db
-                    .get_vault_role_for_user(&vault_id, &user.user_id)
-                    .await;
-                match user_role {
-                    Ok(Some(role)) if role_allows(&role, required_role) => {}
-                    Ok(Some(_)) | Ok(None) => {
-                        let vault_exists = state.db.get_vault(&vault_id).await.is_ok();
-                        let response = if vault_exists {
-                            HttpResponse::Forbidden().json(serde_json::json!({
-                                "error": "FORBIDDEN",
-                                "message": "You do not have access to this vault"
-                            }))
-                        } else {
-                            HttpResponse::NotFound().json(serde_json::json!({
-                                "error": "NOT_FOUND",
-                                "message": "Vault not found"
-                            }))
-                        };
-                        return Ok(req.into_response(response).map_into_right_body());
-                    }
-                    Err(_) => {
-                        let response =
-                            HttpResponse::InternalServerError().json(serde_json::json!({
-                                "error": "INTERNAL_ERROR",
-                                "message": "Failed to authorize vault access"
-                            }));
-                        return Ok(req.into_response(response).map_into_right_body());
-                    }
+                if let Err(response) =
+                    authorize_vault_access(&state, &vault_id, &user.user_id, required_role).await
+                {
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+                if let Err(response) = authorize_path_access(
+                    &state,
+                    &req,
+                    &vault_id,
+                    Some(&user.user_id),
+                    required_role,
+                )
+                .await
+                {
+                    return Ok(req.into_response(response).map_into_right_body());
                 }
             }
 
-            req.extensions_mut().insert(UserId(user.user_id.clone()));
-            req.extensions_mut().insert(user);
             let fut = service.call(req);
             Ok(fut.await?.map_into_left_body())
         })
@@ -341,7 +407,10 @@ fn should_skip_auth(req: &ServiceRequest) -> bool {
         || path == "/api/auth/login"
         || path == "/api/auth/refresh"
         || path.starts_with("/api/auth/oidc/")
+        || path == "/api/auth/webauthn/login/start"
+        || path == "/api/auth/webauthn/login/finish"
         || path == "/api/invitations/accept"
+        || path.starts_with("/api/public/shares/")
     {
         return true;
     }
@@ -401,6 +470,18 @@ fn required_vault_role(req: &ServiceRequest) -> Option<(String, RequiredVaultRol
 
     let required = if tail.first() == Some(&"shares") {
         RequiredVaultRole::Manage
+    } else if tail == ["git"] && *method == Method::PUT {
+        // Enabling git or changing the remote affects everyone with access.
+        RequiredVaultRole::Manage
+    } else if tail == ["entities", "validation"] && *method == Method::PUT {
+        // Strict validation refuses saves for everyone with access.
+        RequiredVaultRole::Manage
+    } else if tail.first() == Some(&"types") && *method != Method::GET && *method != Method::HEAD {
+        // Vault types change how every entity in the vault is validated.
+        RequiredVaultRole::Manage
+    } else if tail.first() == Some(&"entity-types") && *method == Method::POST {
+        // Migrations rewrite every entity of a type.
+        RequiredVaultRole::Manage
     } else if tail.is_empty() {
         match *method {
             Method::GET | Method::HEAD => RequiredVaultRole::Read,
@@ -411,7 +492,9 @@ fn required_vault_role(req: &ServiceRequest) -> Option<(String, RequiredVaultRol
         RequiredVaultRole::Read
     } else if *method == Method::POST {
         match tail[0] {
-            "render" | "resolve-link" | "resolve-links" | "download-zip" => RequiredVaultRole::Read,
+            "render" | "resolve-link" | "resolve-links" | "download-zip" | "download-tar" => {
+                RequiredVaultRole::Read
+            }
             _ => RequiredVaultRole::Write,
         }
     } else {
@@ -421,6 +504,200 @@ fn required_vault_role(req: &ServiceRequest) -> Option<(String, RequiredVaultRol
     Some((vault_id, required))
 }
 
+/// Check that `user_id` holds a vault role sufficient for `required`,
+/// returning the error response to send otherwise.
+async fn authorize_vault_access(
+    state: &AppState,
+    vault_id: &str,
+    user_id: &str,
+    required: RequiredVaultRole,
+) -> Result<(), HttpResponse> {
+    match state.db.get_vault_role_for_user(vault_id, user_id).await {
+        Ok(Some(role)) if role_allows(&role, required) => Ok(()),
+        Ok(Some(_)) | Ok(None) => {
+            let vault_exists = state.db.get_vault(vault_id).await.is_ok();
+            Err(if vault_exists {
+                HttpResponse::Forbidden().json(serde_json::json!({
+                    "error": "FORBIDDEN",
+                    "message": "You do not have access to this vault"
+                }))
+            } else {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "NOT_FOUND",
+                    "message": "Vault not found"
+                }))
+            })
+        }
+        Err(_) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "INTERNAL_ERROR",
+            "message": "Failed to authorize vault access"
+        }))),
+    }
+}
+
+/// Endpoints that manage how a user signs in, closed to every API key.
+const CREDENTIAL_PATHS: [&str; 3] = [
+    "/api/auth/change-password",
+    "/api/auth/totp",
+    "/api/auth/webauthn",
+];
+
+/// The endpoint family a request belongs to, for API key scopes.
+fn request_scope(req: &ServiceRequest, vault_role: Option<RequiredVaultRole>) -> ApiKeyScope {
+    let path = req.path();
+    let is_admin_path = [
+        "/api/admin",
+        "/api/auth/api-keys",
+        "/api/groups",
+        "/api/invitations",
+    ]
+    .iter()
+    .any(|prefix| path.starts_with(prefix));
+    if is_admin_path || vault_role == Some(RequiredVaultRole::Manage) {
+        ApiKeyScope::Admin
+    } else if path.split('/').any(|segment| segment == "search") {
+        ApiKeyScope::Search
+    } else {
+        match vault_role {
+            Some(RequiredVaultRole::Read) => ApiKeyScope::Read,
+            Some(_) => ApiKeyScope::Write,
+            None if matches!(*req.method(), Method::GET | Method::HEAD) => ApiKeyScope::Read,
+            None => ApiKeyScope::Write,
+        }
+    }
+}
+
+/// Why an API key's restrictions forbid this request, if they do.
+fn api_key_denial(req: &ServiceRequest, restrictions: &ApiKeyRestrictions) -> Option<String> {
+    // No key may touch sign-in credentials: a passkey or password set
+    // through a key would turn it into a full session for its owner.
+    if CREDENTIAL_PATHS
+        .iter()
+        .any(|prefix| req.path().starts_with(prefix))
+    {
+        return Some("API keys cannot manage sign-in credentials".to_string());
+    }
+    if restrictions.is_unrestricted() {
+        return None;
+    }
+
+    let vault_role = required_vault_role(req);
+    let scope = request_scope(req, vault_role.as_ref().map(|(_, role)| *role));
+    if !restrictions.scopes.is_empty() && !restrictions.scopes.contains(&scope) {
+        let family = match scope {
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::Write => "write",
+            ApiKeyScope::Search => "search",
+            ApiKeyScope::Admin => "admin",
+        };
+        return Some(format!("API key is not allowed to make {family} requests"));
+    }
+
+    let Some((vault_id, required)) = vault_role else {
+        // Outside a vault, keys limited to vaults, a role or paths may only
+        // read unless the family was granted explicitly.
+        let limited = !restrictions.vault_ids.is_empty()
+            || restrictions.role.is_some()
+            || !restrictions.path_globs.is_empty();
+        if limited && scope != ApiKeyScope::Read && !restrictions.scopes.contains(&scope) {
+            return Some("API key is limited to vault access".to_string());
+        }
+        return None;
+    };
+
+    if !restrictions.vault_ids.is_empty() && !restrictions.vault_ids.contains(&vault_id) {
+        return Some("API key is not allowed to access this vault".to_string());
+    }
+    if let Some(role) = &restrictions.role {
+        if !role_allows(role, required) {
+            return Some("API key role does not permit this request".to_string());
+        }
+    }
+    if !restrictions.path_globs.is_empty() {
+        let segments: Vec<&str> = req
+            .path()
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let tail = &segments[3..];
+        // Path-restricted keys can only use endpoints that carry the file
+        // path in the URL, plus reading the vault itself.
+        let allowed = match request_file_path(tail, req.method()) {
+            Some(path) => path_matches_any(&restrictions.path_globs, &path),
+            None => tail.is_empty() && required == RequiredVaultRole::Read,
+        };
+        if !allowed {
+            return Some("API key is not allowed to access this path".to_string());
+        }
+    }
+    None
+}
+
+/// The decoded vault-relative file path addressed by a vault route, if any.
+fn request_file_path(tail: &[&str], method: &Method) -> Option<String> {
+    let rest = match tail {
+        ["files" | "raw" | "download" | "thumbnail", rest @ ..] => rest,
+        ["git", "blame" | "diff", rest @ ..] => rest,
+        _ => return None,
+    };
+    let rest = match rest {
+        [path @ .., "metadata"] if tail[0] == "files" && *method == Method::GET => path,
+        _ => rest,
+    };
+    if rest.is_empty() {
+        return None;
+    }
+
+    // Decode and resolve `.`/`..` so rules see the path the handler will
+    // actually touch.
+    let decoded = rest
+        .iter()
+        .map(|segment| urlencoding::decode(segment).map(|s| s.into_owned()))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    Some(normalize_path(&decoded.join("/")))
+}
+
+fn path_matches_any(globs: &[String], path: &str) -> bool {
+    globs.iter().any(|pattern| glob_matches(pattern, path))
+}
+
+/// Check path ACLs for requests that address a file in the URL.
+async fn authorize_path_access(
+    state: &AppState,
+    req: &ServiceRequest,
+    vault_id: &str,
+    user_id: Option<&str>,
+    required: RequiredVaultRole,
+) -> Result<(), HttpResponse> {
+    let segments: Vec<&str> = req
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let Some(path) = request_file_path(&segments[3..], req.method()) else {
+        return Ok(());
+    };
+    let required = match required {
+        RequiredVaultRole::Read => PathAccess::Read,
+        RequiredVaultRole::Write | RequiredVaultRole::Manage => PathAccess::Write,
+    };
+    let acl = PathAclService::for_user(&state.db, vault_id, user_id)
+        .await
+        .map_err(|_| {
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "INTERNAL_ERROR",
+                "message": "Failed to authorize path access"
+            }))
+        })?;
+    acl.require(&path, required).map_err(|e| {
+        HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "FORBIDDEN",
+            "message": e.to_string()
+        }))
+    })
+}
+
 fn role_allows(role: &crate::models::VaultRole, required: RequiredVaultRole) -> bool {
     match required {
         RequiredVaultRole::Read => true,
@@ -1,9 +1,11 @@
+pub mod audit;
 pub mod auth;
 pub mod logging;
 pub mod rate_limit;
 pub mod request_id;
 
-pub use auth::{AuthMiddleware, AuthenticatedUser, UserId};
+pub use audit::{AuditMiddleware, AuditPath, AuditRecorded};
+pub use auth::{ApiKeyContext, AuthMiddleware, AuthenticatedUser, UserId};
 pub use logging::RequestLogging;
 pub use rate_limit::RateLimitMiddleware;
 pub use request_id::RequestIdMiddleware;
@@ -0,0 +1,92 @@
+use super::{AuditLogEntry, AuditOutcome};
+use chrono::{DateTime, SecondsFormat, Utc};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+
+/// `prev_hash` of the first entry in the audit hash chain.
+pub const AUDIT_CHAIN_GENESIS: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// An event to append to `audit_log`. Fields left empty are filled from the
+/// current request's [`AuditContext`] where one is set.
+#[derive(Debug, Clone, Default)]
+pub struct AuditEvent {
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    pub event_type: String,
+    pub detail: Option<String>,
+    pub ip_address: Option<String>,
+    pub vault_id: Option<String>,
+    pub path: Option<String>,
+    pub request_id: Option<String>,
+    pub outcome: AuditOutcome,
+}
+
+impl AuditEvent {
+    pub fn new(event_type: impl Into<String>) -> Self {
+        Self {
+            event_type: event_type.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Who made the request an event happened in, and where from. Set by
+/// `AuditMiddleware` for the duration of each request.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub ip_address: Option<String>,
+    pub request_id: Option<String>,
+    pub vault_id: Option<String>,
+}
+
+tokio::task_local! {
+    static AUDIT_CONTEXT: AuditContext;
+}
+
+impl AuditContext {
+    /// Run `fut` with this context visible to [`AuditContext::current`].
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        AUDIT_CONTEXT.scope(self, fut).await
+    }
+
+    pub fn current() -> Option<Self> {
+        AUDIT_CONTEXT.try_with(Clone::clone).ok()
+    }
+
+    /// Fill whatever `event` left empty.
+    pub fn apply(&self, event: &mut AuditEvent) {
+        if event.ip_address.is_none() {
+            event.ip_address.clone_from(&self.ip_address);
+        }
+        if event.request_id.is_none() {
+            event.request_id.clone_from(&self.request_id);
+        }
+        if event.vault_id.is_none() {
+            event.vault_id.clone_from(&self.vault_id);
+        }
+    }
+}
+
+/// Hash of an entry chained to `prev_hash`: SHA-256 over a JSON array of
+/// the link and every recorded field, hex-encoded.
+pub fn chain_hash(prev_hash: &str, entry: &AuditLogEntry) -> String {
+    let canonical = serde_json::json!([
+        prev_hash,
+        timestamp_for_chain(entry.timestamp),
+        entry.user_id,
+        entry.username,
+        entry.event_type,
+        entry.detail,
+        entry.ip_address,
+        entry.outcome.as_str(),
+        entry.vault_id,
+        entry.path,
+        entry.request_id,
+    ]);
+    hex::encode(Sha256::digest(canonical.to_string().as_bytes()))
+}
+
+fn timestamp_for_chain(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A stored blob: gzip-compressed contents addressed by their SHA-256.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotObject {
+    pub sha256: String,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Vault-relative path using `/` separators.
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+    /// Modification time (unix milliseconds) used to skip re-hashing unchanged files.
+    pub modified: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotVault {
+    pub vault_id: String,
+    pub name: String,
+    /// Vault root at the time of the snapshot.
+    pub path: String,
+    pub files: Vec<SnapshotFile>,
+}
+
+/// Everything captured by one snapshot (`snapshots/<id>.json`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub vaults: Vec<SnapshotVault>,
+    pub database: Option<SnapshotObject>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSummary {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub vault_count: usize,
+    pub file_count: usize,
+    /// Uncompressed size of all captured files and the database.
+    pub total_size: u64,
+    pub includes_database: bool,
+}
+
+impl From<&SnapshotManifest> for SnapshotSummary {
+    fn from(manifest: &SnapshotManifest) -> Self {
+        let files = manifest.vaults.iter().flat_map(|v| v.files.iter());
+        Self {
+            id: manifest.id.clone(),
+            created_at: manifest.created_at,
+            vault_count: manifest.vaults.len(),
+            file_count: files.clone().count(),
+            total_size: files.map(|f| f.size).sum::<u64>()
+                + manifest.database.as_ref().map(|d| d.size).unwrap_or(0),
+            includes_database: manifest.database.is_some(),
+        }
+    }
+}
+
+/// Result of re-reading every object a snapshot references.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotVerifyReport {
+    pub snapshot_id: String,
+    pub objects_checked: usize,
+    /// Hashes whose object file is missing.
+    pub missing: Vec<String>,
+    /// Hashes whose object no longer decompresses to the recorded contents.
+    pub corrupted: Vec<String>,
+}
+
+impl SnapshotVerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupted.is_empty()
+    }
+}
+
+/// Body of `POST /api/admin/backups/{id}/restore`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RestoreSnapshotRequest {
+    pub vault_id: String,
+    /// File or directory to restore. The whole vault when omitted.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Restore `path` under a different name instead of over the original.
+    #[serde(default)]
+    pub target_path: Option<String>,
+}
+
+/// Files touched by a restore. Anything overwritten or removed was moved
+/// to the vault trash first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub snapshot_id: String,
+    pub vault_id: String,
+    pub restored: Vec<String>,
+    pub unchanged: usize,
+    pub trashed: Vec<String>,
+}
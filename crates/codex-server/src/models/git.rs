@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Per-vault git integration settings (one row in `vault_git_settings`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultGitSettings {
+    pub vault_id: String,
+    /// Master switch; when false the vault is never committed, pulled or pushed.
+    pub enabled: bool,
+    /// Commit automatically after watcher events settle.
+    pub auto_commit: bool,
+    /// URL of the `origin` remote. `None` keeps the repository local-only.
+    pub remote_url: Option<String>,
+    pub branch: String,
+    /// Pull/push interval for the configured remote (0 = manual sync only).
+    pub sync_interval_secs: u64,
+    pub last_sync_at: Option<DateTime<Utc>>,
+    pub last_sync_error: Option<String>,
+}
+
+impl VaultGitSettings {
+    pub fn disabled(vault_id: &str) -> Self {
+        Self {
+            vault_id: vault_id.to_string(),
+            enabled: false,
+            auto_commit: true,
+            remote_url: None,
+            branch: default_branch(),
+            sync_interval_secs: 0,
+            last_sync_at: None,
+            last_sync_error: None,
+        }
+    }
+}
+
+fn default_branch() -> String {
+    "main".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Body of `PUT /api/vaults/{id}/git` — replaces the vault's git settings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateVaultGitSettingsRequest {
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub auto_commit: bool,
+    #[serde(default)]
+    pub remote_url: Option<String>,
+    #[serde(default = "default_branch")]
+    pub branch: String,
+    #[serde(default)]
+    pub sync_interval_secs: u64,
+}
+
+/// Current repository state plus the stored settings.
+#[derive(Debug, Clone, Serialize)]
+pub struct GitStatusResponse {
+    pub settings: VaultGitSettings,
+    pub is_repository: bool,
+    pub head: Option<String>,
+    /// Vault-relative paths with uncommitted changes.
+    pub dirty_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitCommitInfo {
+    pub id: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub timestamp: DateTime<Utc>,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitBlameLine {
+    pub line_number: usize,
+    pub commit_id: String,
+    pub author_name: String,
+    pub timestamp: DateTime<Utc>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitDiff {
+    pub path: String,
+    pub from: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// Unified diff as produced by `git diff`.
+    pub patch: String,
+}
+
+/// A file that conflicted while merging the remote branch.
+///
+/// The local version is kept in place and the incoming version is written
+/// next to it as a `conflict_<stem>_<timestamp>` copy, the same convention
+/// used when a write collides with an external modification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitConflict {
+    pub path: String,
+    pub conflict_copy: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitSyncOutcome {
+    pub pulled: bool,
+    pub pushed: bool,
+    pub conflicts: Vec<GitConflict>,
+}
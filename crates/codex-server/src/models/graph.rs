@@ -15,6 +15,18 @@ pub struct GraphNode {
     pub color: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// File name without extension, for file-backed nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    /// Hops from the queried entity, for neighbourhood queries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +37,7 @@ pub enum NodeType {
     Tag,
     Attachment,
     Virtual, // For non-existing files that are linked to
+    Entity,  // Typed entity indexed from frontmatter
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,12 +46,41 @@ pub struct GraphEdge {
     pub target: String, // ID of target node
     pub count: u32,     // Number of links (strength of edge)
     pub edge_type: EdgeType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relation_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum EdgeType {
-    Link,  // [[link]]
-    Embed, // ![[embed]]
-    Tag,   // File -> Tag relationship
+    Link,     // [[link]]
+    Embed,    // ![[embed]]
+    Tag,      // File -> Tag relationship
+    Relation, // Entity -> Entity relation
+}
+
+/// Size and centrality figures for a vault's entity graph.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub component_count: usize,
+    /// The best-connected nodes, by degree
+    pub nodes: Vec<GraphNodeStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNodeStats {
+    pub id: String,
+    pub label: String,
+    pub degree: usize,
+    pub in_degree: usize,
+    pub out_degree: usize,
+    /// Distinct neighbours as a fraction of all other nodes
+    pub degree_centrality: f64,
+    /// Normalised betweenness; omitted for graphs too large to compute it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub betweenness: Option<f64>,
 }
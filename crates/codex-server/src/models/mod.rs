@@ -1,29 +1,51 @@
 use chrono::{DateTime, Utc};
 use sqlx::FromRow;
 
+pub mod audit;
+pub mod backup;
 pub mod bookmarks;
+pub mod git;
 pub mod graph;
+pub mod oidc;
 pub mod plugin;
 pub mod schema;
+pub mod scim;
+pub mod timeline;
+pub mod trash;
+pub mod upload;
+pub mod webauthn;
 
 pub use schema::{
-    EntityTypeSchema, FieldSchema, FieldType, PluginLabelDeclaration, RelationTypeSchema,
+    ComputedField, CountDirection, CreateEntityRequest, CreateRelationRequest, EntityMigration,
+    EntityTypeBody, EntityTypeSchema, EntityValidationIssue, EntityValidationMode,
+    EntityValidationReport, FieldChange, FieldErrorCode, FieldSchema, FieldType,
+    FieldValidationError, PluginLabelDeclaration, RelationTypeBody, RelationTypeSchema,
+    SchemaMigrationOp, SchemaMigrationReport, SchemaMigrationRequest,
+    SetEntityValidationModeRequest, UpdateEntityRequest, UpdateRelationRequest, VaultTypeChange,
+    VaultTypeConflict, VaultTypeFileError, VaultTypeKind, VaultTypesReport,
 };
 
 pub use codex_types::{
-    AcceptInviteRequest, AddGroupMemberRequest, AdminUser, ApiKeyInfo, ApplyChange,
-    ApplyOrganizationSuggestionRequest, ApplyOrganizationSuggestionResponse, AuditLogEntry,
-    AuthenticatedUserProfile, BulkImportError, BulkImportResult, BulkUserEntry,
-    ChangePasswordRequest, CreateApiKeyRequest, CreateApiKeyResponse, CreateFileRequest,
-    CreateGroupRequest, CreateInviteRequest, CreateUploadSessionRequest, CreateUserRequest,
-    CreateUserResponse, CreateVaultRequest, EditorMode, FileChangeEvent, FileChangeType,
-    FileContent, FileNode, GenerateOrganizationSuggestionsRequest, GenerateOutlineRequest,
-    GroupInfo, GroupMember, InviteInfo, MlUndoReceipt, NoteOutlineResponse, OrganizationSuggestion,
-    OrganizationSuggestionKind, OrganizationSuggestionsResponse, OutlineSection, PagedSearchResult,
-    ReverseAction, SearchMatch, SearchResult, SessionInfo, ShareVaultWithGroupRequest,
-    ShareVaultWithUserRequest, TotpEnrollResponse, TotpVerifyRequest, UndoMlActionResponse,
-    UpdateFileRequest, UploadSessionResponse, UserPreferences, Vault, VaultRole, VaultShareEntry,
-    VaultShareList, WsMessage,
+    AcceptInviteRequest, AclPrincipalType, AddGroupMemberRequest, AdminUser, ApiKeyInfo,
+    ApiKeyRestrictions, ApiKeyScope, ApplyChange, ApplyOrganizationSuggestionRequest,
+    ApplyOrganizationSuggestionResponse, AuditChainReport, AuditLogEntry, AuditLogPage,
+    AuditLogQuery, AuditOutcome, AuthenticatedUserProfile, BulkImportError, BulkImportResult,
+    BulkUserEntry, ChangePasswordRequest, CreateApiKeyRequest, CreateApiKeyResponse,
+    CreateFileRequest, CreateGroupRequest, CreateInviteRequest, CreatePathAclRequest,
+    CreateScimTokenRequest, CreateScimTokenResponse, CreateShareLinkCommentRequest,
+    CreateShareLinkRequest, CreateShareLinkResponse, CreateUploadSessionRequest, CreateUserRequest,
+    CreateUserResponse, CreateVaultRequest, EditorMode, EnrolledFactors, FileChangeEvent,
+    FileChangeType, FileContent, FileNode, GenerateOrganizationSuggestionsRequest,
+    GenerateOutlineRequest, GroupInfo, GroupMember, InviteInfo, MlUndoReceipt, NoteOutlineResponse,
+    OrganizationSuggestion, OrganizationSuggestionKind, OrganizationSuggestionsResponse,
+    OutlineSection, PagedSearchResult, PathAccess, PathAclEntry, PublicKeyCredential,
+    RenameWebAuthnCredentialRequest, ReverseAction, ScimTokenInfo, SearchMatch, SearchResult,
+    SessionInfo, ShareLink, ShareLinkComment, ShareLinkPermission, ShareVaultWithGroupRequest,
+    ShareVaultWithUserRequest, SharedContent, TotpEnrollResponse, TotpVerifyRequest,
+    UndoMlActionResponse, UpdateFileRequest, UploadSessionResponse, UploadedRange, UserPreferences,
+    Vault, VaultRole, VaultShareEntry, VaultShareList, WebAuthnCeremony, WebAuthnCredentialInfo,
+    WebAuthnLoginFinishRequest, WebAuthnLoginStartRequest, WebAuthnRegisterFinishRequest,
+    WebAuthnRegisterStartRequest, WsMessage,
 };
 
 #[derive(Debug, Clone, FromRow)]
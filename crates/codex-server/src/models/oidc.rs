@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+
+/// A sign-in in `oidc_auth_requests`, started at `/api/auth/oidc/authorize`
+/// and consumed by the first callback carrying its `state`.
+#[derive(Debug, Clone)]
+pub struct OidcAuthRequest {
+    pub state: String,
+    pub provider: String,
+    /// Must come back unchanged in the ID token's `nonce` claim.
+    pub nonce: String,
+    /// PKCE verifier whose S256 challenge went to the provider.
+    pub code_verifier: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl OidcAuthRequest {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
@@ -58,6 +58,47 @@ pub struct FieldSchema {
     pub relation: Option<String>,
     /// Optional description shown in the UI
     pub description: Option<String>,
+    /// Derive the value instead of reading it from frontmatter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub computed: Option<ComputedField>,
+}
+
+/// How a computed field's value is derived. Computed values are stored with
+/// the entity's other fields and take precedence over a frontmatter value
+/// under the same key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ComputedField {
+    /// Number of relations of a type to (`inbound`) or from (`outbound`)
+    /// the entity.
+    Count {
+        relation: String,
+        #[serde(default)]
+        direction: CountDirection,
+    },
+    /// Titles of the entities reached by following a relation type
+    /// outwards, nearest first (e.g. the locations a place is inside).
+    Transitive {
+        relation: String,
+        /// Defaults to 10 hops
+        max_depth: Option<u32>,
+    },
+    /// Whole years from the date in `from` to the date in `to`, or to the
+    /// calendar's current date when `to` is absent or empty.
+    Age {
+        from: String,
+        to: Option<String>,
+        /// Calendar the dates are in; defaults to `gregorian`
+        calendar: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CountDirection {
+    #[default]
+    Inbound,
+    Outbound,
 }
 
 // ──────────────────────────────────────────────────────────────────────────────
@@ -66,13 +107,13 @@ pub struct FieldSchema {
 
 /// Top-level wrapper used when deserialising an entity-type TOML file.
 /// The file must have an `[entity_type]` section.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EntityTypeToml {
     pub entity_type: EntityTypeBody,
 }
 
 /// The `[entity_type]` body.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EntityTypeBody {
     /// Display name (e.g. `"Character"`)
     pub name: String,
@@ -91,6 +132,12 @@ pub struct EntityTypeBody {
     /// (defaults to all required fields if absent)
     #[serde(default)]
     pub show_on_create: Vec<String>,
+    /// Entity type whose fields, labels and settings this one inherits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Entity types whose fields and labels are merged in, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mixins: Vec<String>,
     /// Field definitions (TOML array-of-tables)
     #[serde(default)]
     pub fields: Vec<FieldSchema>,
@@ -114,21 +161,237 @@ pub struct EntityTypeSchema {
     pub display_field: Option<String>,
     /// Field keys shown in the "New Entity" creation dialog
     pub show_on_create: Vec<String>,
+    /// Parent type, as declared; `fields` and `labels` already include
+    /// what it and `mixins` contribute
+    #[serde(default)]
+    pub extends: Option<String>,
+    #[serde(default)]
+    pub mixins: Vec<String>,
     pub fields: Vec<FieldSchema>,
 }
 
+// ──────────────────────────────────────────────────────────────────────────────
+// Entity validation
+// ──────────────────────────────────────────────────────────────────────────────
+
+/// How strictly a vault checks entity frontmatter against its schema when a
+/// file is saved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityValidationMode {
+    /// No checks on save.
+    Off,
+    /// Violations are logged; the save goes ahead.
+    #[default]
+    Warn,
+    /// Saves that violate the schema are refused with 422.
+    Reject,
+}
+
+impl EntityValidationMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Warn => "warn",
+            Self::Reject => "reject",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Self::Off),
+            "warn" => Some(Self::Warn),
+            "reject" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// Why a field value does not satisfy its `FieldSchema`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldErrorCode {
+    /// A required field is missing or empty.
+    Required,
+    /// The value is not of the field's type.
+    InvalidType,
+    /// The value is not one of the field's enum `values`.
+    InvalidValue,
+    /// An entity reference points at an entity without the `target_label`.
+    WrongTargetLabel,
+    /// The key is not declared (relation metadata only).
+    UnknownField,
+}
+
+/// One field-level schema violation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldValidationError {
+    /// Frontmatter key of the field.
+    pub field: String,
+    /// Position of the offending item in a `List` field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    pub code: FieldErrorCode,
+    pub message: String,
+}
+
+/// An entity whose frontmatter violates its entity type schema.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityValidationIssue {
+    pub entity_id: String,
+    pub path: String,
+    pub entity_type: String,
+    pub errors: Vec<FieldValidationError>,
+}
+
+/// Result of `GET /api/vaults/{vault_id}/entities/validation`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityValidationReport {
+    pub vault_id: String,
+    pub mode: EntityValidationMode,
+    /// Entities whose type has a registered schema and were checked.
+    pub checked: usize,
+    /// Entities whose type has no registered schema.
+    pub unknown_type: usize,
+    pub invalid: Vec<EntityValidationIssue>,
+}
+
+/// Body of `PUT /api/vaults/{vault_id}/entities/validation`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetEntityValidationModeRequest {
+    pub mode: EntityValidationMode,
+}
+
+/// Body of `POST /api/vaults/{vault_id}/entities`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateEntityRequest {
+    /// Entity type id, e.g. `"character"`.
+    pub entity_type: String,
+    #[serde(default)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    /// Vault-relative path of the new file. When omitted, the file is named
+    /// after the type's display field and placed in `folder`.
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Markdown written below the frontmatter.
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+/// Body of `PATCH /api/vaults/{vault_id}/entities/{entity_id}`. A `null`
+/// value removes the field.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateEntityRequest {
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Body of `POST /api/vaults/{vault_id}/relations`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateRelationRequest {
+    pub from_entity_id: String,
+    pub to_entity_id: String,
+    /// Registered relation type name, e.g. `"member_of"`.
+    pub relation_type: String,
+    #[serde(default)]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Body of `PATCH /api/vaults/{vault_id}/relations/{relation_id}`. Replaces
+/// the relation's metadata.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateRelationRequest {
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+/// One step of an entity type migration, applied to each entity's
+/// frontmatter in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SchemaMigrationOp {
+    /// Move a field's value to a new key, keeping its position.
+    RenameField {
+        from: String,
+        to: String,
+    },
+    /// Convert a field's value to another field type.
+    ChangeType {
+        field: String,
+        to: FieldType,
+    },
+    /// Set a field on entities where it is missing or `null`.
+    SetDefault {
+        field: String,
+        value: serde_json::Value,
+    },
+    DropField {
+        field: String,
+    },
+    /// Split a string field into a list on `separator`.
+    SplitList {
+        field: String,
+        #[serde(default = "default_list_separator")]
+        separator: String,
+    },
+}
+
+fn default_list_separator() -> String {
+    ",".to_string()
+}
+
+/// Body of `POST /api/vaults/{vault_id}/entity-types/{type_id}/migrate`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchemaMigrationRequest {
+    pub operations: Vec<SchemaMigrationOp>,
+    /// Report the changes without writing any file.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// A frontmatter key whose value a migration changes. `None` means absent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+/// The changes a migration makes to one entity, and the operations it
+/// could not apply there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityMigration {
+    pub entity_id: String,
+    pub path: String,
+    pub changes: Vec<FieldChange>,
+    pub errors: Vec<String>,
+}
+
+/// Result of an entity type migration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaMigrationReport {
+    pub entity_type: String,
+    pub dry_run: bool,
+    /// Entities of the type that were examined.
+    pub checked: usize,
+    /// Entities that would change or could not be migrated.
+    pub entities: Vec<EntityMigration>,
+    /// Files rewritten; always 0 for a dry run.
+    pub migrated: usize,
+}
+
 // ──────────────────────────────────────────────────────────────────────────────
 // Relation type schema (parsed from TOML)
 // ──────────────────────────────────────────────────────────────────────────────
 
 /// Top-level wrapper for relation-type TOML files.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RelationTypeToml {
     pub relation_type: RelationTypeBody,
 }
 
 /// The `[relation_type]` body.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RelationTypeBody {
     /// Canonical name / id (e.g. `"member_of"`)
     pub name: String,
@@ -169,6 +432,54 @@ pub struct RelationTypeSchema {
     pub metadata_fields: Vec<FieldSchema>,
 }
 
+// ──────────────────────────────────────────────────────────────────────────────
+// Vault-local types (`.codex/types/*.toml` inside a vault)
+// ──────────────────────────────────────────────────────────────────────────────
+
+/// Whether a vault type file defines an entity type or a relation type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultTypeKind {
+    Entity,
+    Relation,
+}
+
+/// A vault-local type whose id a plugin also defines. Within the vault the
+/// local definition is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultTypeConflict {
+    pub kind: VaultTypeKind,
+    pub id: String,
+    pub plugin_id: String,
+}
+
+/// A vault type file that could not be loaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultTypeFileError {
+    /// Path relative to the vault's types folder
+    pub file: String,
+    pub message: String,
+}
+
+/// Result of `GET /api/vaults/{vault_id}/types`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultTypesReport {
+    pub entity_types: Vec<EntityTypeSchema>,
+    pub relation_types: Vec<RelationTypeSchema>,
+    pub conflicts: Vec<VaultTypeConflict>,
+    pub errors: Vec<VaultTypeFileError>,
+}
+
+/// Result of saving or deleting a vault-local type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultTypeChange {
+    pub kind: VaultTypeKind,
+    pub id: String,
+    /// Entities re-indexed because a type they use changed
+    pub reindexed: usize,
+    pub conflicts: Vec<VaultTypeConflict>,
+}
+
 // ──────────────────────────────────────────────────────────────────────────────
 // Plugin label declaration (inline in manifest.json)
 // ──────────────────────────────────────────────────────────────────────────────
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+/// Top-level wrapper for calendar TOML files (`.codex/calendars/<id>.toml`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarToml {
+    pub calendar: CalendarBody,
+}
+
+/// The `[calendar]` body. A year is the months in order, so its length is
+/// the sum of their days.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarBody {
+    /// Display name (e.g. `"Harptos"`)
+    pub name: String,
+    /// Suffix shown after years (e.g. `"DR"`)
+    pub era: Option<String>,
+    pub months: Vec<CalendarMonth>,
+    /// Names of the days of the week; the first day of year 0 is the first
+    #[serde(default)]
+    pub weekdays: Vec<String>,
+    /// The in-world date it is now, which ages are computed up to
+    pub current: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarMonth {
+    pub name: String,
+    pub days: u32,
+}
+
+/// A calendar available to a vault's timeline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarInfo {
+    /// `gregorian`, or the file stem of a vault calendar
+    pub id: String,
+    pub name: String,
+    pub era: Option<String>,
+    pub months: Vec<CalendarMonth>,
+    pub weekdays: Vec<String>,
+    /// Days in a year; leap days of the Gregorian calendar are not counted
+    pub year_length: u32,
+    /// The in-world date it is now; today for the Gregorian calendar
+    pub current: Option<String>,
+}
+
+/// Result of `GET /api/vaults/{vault_id}/calendars`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarList {
+    pub calendars: Vec<CalendarInfo>,
+    /// Calendar files that could not be loaded, with the reason
+    pub errors: Vec<CalendarFileError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarFileError {
+    pub file: String,
+    pub message: String,
+}
+
+/// A date read from a field, placed in a calendar. Dates given as a year
+/// or a month fall on its first day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineDate {
+    /// The field value as written
+    pub raw: String,
+    /// Days since the calendar's epoch, for ordering and ranges
+    pub ordinal: i64,
+    pub year: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub month: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<u32>,
+    /// The date written out in the calendar, e.g. `"15 Alturiak 1492 DR"`
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weekday: Option<String>,
+}
+
+/// An entity placed on the timeline by its start (and optional end) field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub entity_id: String,
+    pub path: String,
+    pub title: String,
+    pub entity_type: String,
+    pub labels: Vec<String>,
+    pub start_field: String,
+    pub start: TimelineDate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<TimelineDate>,
+    pub relations: Vec<TimelineRelation>,
+}
+
+/// A relation from a timeline entity, forward or inverse, to another
+/// entity the caller can read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineRelation {
+    pub relation_id: String,
+    pub relation_type: String,
+    pub direction: String,
+    pub entity_id: String,
+    pub title: String,
+}
+
+/// Result of `GET /api/vaults/{vault_id}/timeline`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timeline {
+    pub calendar: CalendarInfo,
+    pub events: Vec<TimelineEvent>,
+    /// Entities with a date field that does not parse in the calendar
+    pub unparsed: Vec<TimelineUnparsed>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineUnparsed {
+    pub entity_id: String,
+    pub path: String,
+    pub field: String,
+    pub value: String,
+}
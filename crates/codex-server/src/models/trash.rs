@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// Represents a file that has been moved to the vault's `.trash/` folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    /// The timestamped filename inside `.trash/` (e.g. `20240101_120000.000_notes.md`).
+    pub trash_name: String,
+    /// The original vault-relative path before the file was trashed.
+    pub original_path: String,
+    /// RFC-3339 timestamp of when the file was trashed.
+    pub trashed_at: String,
+    /// Size in bytes (the total for a trashed directory).
+    #[serde(default)]
+    pub size: u64,
+    /// Id of the user who deleted the file, when known.
+    #[serde(default)]
+    pub deleted_by: Option<String>,
+    /// Username of the user who deleted the file, when known.
+    #[serde(default)]
+    pub deleted_by_username: Option<String>,
+}
+
+/// Body of `POST /api/vaults/{id}/trash/restore`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BulkRestoreTrashRequest {
+    pub trash_names: Vec<String>,
+    /// `fail` (default), `overwrite` or `autorename` when the original path is taken.
+    #[serde(default)]
+    pub strategy: Option<String>,
+}
+
+/// Body of `POST /api/vaults/{id}/trash/purge`. With neither field set the
+/// whole trash is emptied.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PurgeTrashRequest {
+    #[serde(default)]
+    pub trash_names: Option<Vec<String>>,
+    /// Only purge items deleted at least this many days ago.
+    #[serde(default)]
+    pub older_than_days: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashOperationSuccess {
+    pub trash_name: String,
+    /// Vault-relative path the item was restored to (restore only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashOperationFailure {
+    pub trash_name: String,
+    pub error: String,
+}
+
+/// Per-item outcome of a bulk restore or purge.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkTrashResult {
+    pub succeeded: Vec<TrashOperationSuccess>,
+    pub failed: Vec<TrashOperationFailure>,
+}
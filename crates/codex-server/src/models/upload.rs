@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+
+/// A resumable upload tracked in `upload_sessions`. Bytes are staged in
+/// `.obsidian/uploads/<id>` inside the vault until the session finishes.
+#[derive(Debug, Clone)]
+pub struct UploadSession {
+    pub id: String,
+    pub vault_id: String,
+    /// User who created the session; `None` when auth is disabled.
+    pub owner_id: Option<String>,
+    pub filename: String,
+    pub path: String,
+    pub total_size: Option<u64>,
+    /// Expected hex SHA-256 of the complete file.
+    pub sha256: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl UploadSession {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
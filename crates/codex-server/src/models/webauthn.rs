@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use codex_types::WebAuthnCredentialInfo;
+
+/// An authenticator registered in `webauthn_credentials`.
+#[derive(Debug, Clone)]
+pub struct WebAuthnCredential {
+    /// Base64url credential ID.
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    /// The credential public key as a CBOR-encoded COSE_Key.
+    pub public_key: Vec<u8>,
+    /// COSE algorithm identifier (-7 ES256, -8 EdDSA, -257 RS256).
+    pub algorithm: i64,
+    pub sign_count: u32,
+    pub aaguid: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl WebAuthnCredential {
+    pub fn info(&self) -> WebAuthnCredentialInfo {
+        WebAuthnCredentialInfo {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            created_at: self.created_at,
+            last_used_at: self.last_used_at,
+        }
+    }
+}
+
+/// What a WebAuthn challenge was issued for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebAuthnPurpose {
+    /// Registering a new authenticator for a signed-in user.
+    Register,
+    /// Passwordless sign-in with a passkey.
+    Login,
+    /// Completing a password sign-in that requires an authenticator.
+    SecondFactor,
+}
+
+impl WebAuthnPurpose {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Register => "register",
+            Self::Login => "login",
+            Self::SecondFactor => "second_factor",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "register" => Some(Self::Register),
+            "login" => Some(Self::Login),
+            "second_factor" => Some(Self::SecondFactor),
+            _ => None,
+        }
+    }
+}
+
+/// A pending ceremony in `webauthn_challenges`. Each is consumed by the
+/// first attempt to finish it.
+#[derive(Debug, Clone)]
+pub struct WebAuthnChallenge {
+    pub id: String,
+    /// The user the ceremony is for; `None` for a discoverable-passkey login.
+    pub user_id: Option<String>,
+    pub purpose: WebAuthnPurpose,
+    /// Raw challenge bytes.
+    pub challenge: Vec<u8>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl WebAuthnChallenge {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
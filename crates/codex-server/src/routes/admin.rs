@@ -1,12 +1,21 @@
 use crate::error::{AppError, AppResult};
 use crate::middleware::AuthenticatedUser;
-use crate::models::{CreateUserRequest, CreateUserResponse};
+use crate::models::audit::AuditEvent;
+use crate::models::backup::{RestoreSnapshotRequest, SnapshotSummary};
+use crate::models::{
+    AuditLogPage, AuditLogQuery, CreateScimTokenRequest, CreateScimTokenResponse,
+    CreateUserRequest, CreateUserResponse,
+};
 use crate::routes::vaults::AppState;
+use crate::services::audit_service::AUDIT_EXPORT_BATCH;
+use crate::services::backup_service::{self, BackupService};
+use crate::services::{AuditExportFormat, AuditService, ScimService};
 use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse};
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
     Argon2,
 };
+use futures::StreamExt;
 use rand::{distr::Alphanumeric, Rng};
 
 fn require_authenticated_user(req: &HttpRequest) -> AppResult<AuthenticatedUser> {
@@ -210,7 +219,7 @@ async fn edit_user(
                 "Cannot revoke your own admin privileges".to_string(),
             ));
         }
-        sqlx::query("UPDATE users SET is_admin = ? WHERE id = ?")
+        sqlx::query("UPDATE users SET is_admin = $1 WHERE id = $2")
             .bind(if is_admin { 1_i64 } else { 0_i64 })
             .bind(&user_id)
             .execute(state.db.pool())
@@ -266,13 +275,111 @@ async fn get_audit_log(
     query: web::Query<AuditLogQuery>,
 ) -> AppResult<HttpResponse> {
     let _admin = require_admin_user(&state, &req).await?;
-    let entries = state.db.get_audit_log(query.limit).await?;
-    Ok(HttpResponse::Ok().json(entries))
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(AuditService::parse_cursor)
+        .transpose()?;
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let entries = state
+        .db
+        .query_audit_log(&query, cursor, false, limit)
+        .await?;
+    let next_cursor = if entries.len() as i64 == limit {
+        entries.last().map(|e| e.id.to_string())
+    } else {
+        None
+    };
+    Ok(HttpResponse::Ok().json(AuditLogPage {
+        entries,
+        next_cursor,
+    }))
 }
 
 #[derive(Debug, serde::Deserialize)]
-struct AuditLogQuery {
-    limit: Option<i64>,
+struct AuditExportQuery {
+    #[serde(default = "default_audit_export_format")]
+    format: String,
+}
+
+fn default_audit_export_format() -> String {
+    "ndjson".to_string()
+}
+
+/// Stream every entry matching the filters, oldest first, as NDJSON or CSV.
+#[get("/api/admin/audit-log/export")]
+async fn export_audit_log(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<AuditLogQuery>,
+    export: web::Query<AuditExportQuery>,
+) -> AppResult<HttpResponse> {
+    let admin = require_admin_user(&state, &req).await?;
+    let format = AuditExportFormat::parse(&export.format)?;
+    let query = query.into_inner();
+
+    let _ = state
+        .db
+        .record_audit_event(AuditEvent {
+            user_id: Some(admin.user_id.clone()),
+            username: Some(admin.username.clone()),
+            detail: Some(format!(
+                "Exported audit log as {} with filters {}",
+                format.extension(),
+                serde_json::to_string(&query).unwrap_or_default()
+            )),
+            ..AuditEvent::new("audit_log_exported")
+        })
+        .await;
+
+    let header = AuditService::export_header(format);
+    let batches = futures::stream::try_unfold(Some(0_i64), move |after| {
+        let state = state.clone();
+        let query = query.clone();
+        async move {
+            let Some(after) = after else {
+                return Ok(None);
+            };
+            let entries = state
+                .db
+                .query_audit_log(&query, Some(after), true, AUDIT_EXPORT_BATCH)
+                .await?;
+            if entries.is_empty() {
+                return Ok(None);
+            }
+            let mut chunk = String::new();
+            for entry in &entries {
+                chunk.push_str(&AuditService::export_line(format, entry)?);
+            }
+            let next = if entries.len() as i64 == AUDIT_EXPORT_BATCH {
+                entries.last().map(|e| e.id)
+            } else {
+                None
+            };
+            Ok::<_, AppError>(Some((web::Bytes::from(chunk), next)))
+        }
+    });
+    let body = futures::stream::once(async move { Ok(web::Bytes::from(header)) }).chain(batches);
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"audit-log-{}.{}\"",
+                chrono::Utc::now().format("%Y%m%dT%H%M%SZ"),
+                format.extension()
+            ),
+        ))
+        .streaming(body))
+}
+
+/// Re-compute the audit hash chain and report the first broken link.
+#[get("/api/admin/audit-log/verify")]
+async fn verify_audit_log(state: web::Data<AppState>, req: HttpRequest) -> AppResult<HttpResponse> {
+    let _admin = require_admin_user(&state, &req).await?;
+    let report = state.db.verify_audit_chain().await?;
+    Ok(HttpResponse::Ok().json(report))
 }
 
 /// Bulk import users from a JSON array.
@@ -358,6 +465,178 @@ async fn bulk_import_users(
     Ok(HttpResponse::Ok().json(crate::models::BulkImportResult { created, failed }))
 }
 
+// ── Backups ─────────────────────────────────────────────────────────────
+
+async fn backup_blocking<T, F>(f: F) -> AppResult<T>
+where
+    F: FnOnce() -> AppResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    web::block(f)
+        .await
+        .map_err(|e| AppError::InternalError(format!("Backup task failed: {e}")))?
+}
+
+#[get("/api/admin/backups")]
+async fn list_backups(
+    state: web::Data<AppState>,
+    config: web::Data<crate::config::AppConfig>,
+    req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let _admin = require_admin_user(&state, &req).await?;
+    let service = BackupService::new(&config.backup);
+    let snapshots = backup_blocking(move || service.list_snapshots()).await?;
+    Ok(HttpResponse::Ok().json(snapshots))
+}
+
+#[post("/api/admin/backups")]
+async fn create_backup(
+    state: web::Data<AppState>,
+    config: web::Data<crate::config::AppConfig>,
+    req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let admin = require_admin_user(&state, &req).await?;
+    let manifest = backup_service::snapshot_all(&state.db, &config.backup).await?;
+    state
+        .db
+        .write_audit_log(
+            Some(&admin.user_id),
+            Some(&admin.username),
+            "backup_created",
+            Some(&format!("Created snapshot {}", manifest.id)),
+            None,
+            true,
+        )
+        .await?;
+    Ok(HttpResponse::Created().json(SnapshotSummary::from(&manifest)))
+}
+
+#[post("/api/admin/backups/{snapshot_id}/verify")]
+async fn verify_backup(
+    state: web::Data<AppState>,
+    config: web::Data<crate::config::AppConfig>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let _admin = require_admin_user(&state, &req).await?;
+    let snapshot_id = path.into_inner();
+    let service = BackupService::new(&config.backup);
+    let report = backup_blocking(move || service.verify(&snapshot_id)).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[post("/api/admin/backups/{snapshot_id}/restore")]
+async fn restore_backup(
+    state: web::Data<AppState>,
+    config: web::Data<crate::config::AppConfig>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<RestoreSnapshotRequest>,
+) -> AppResult<HttpResponse> {
+    let admin = require_admin_user(&state, &req).await?;
+    let snapshot_id = path.into_inner();
+    let body = body.into_inner();
+    let vault = state.db.get_vault(&body.vault_id).await?;
+
+    let service = BackupService::new(&config.backup);
+    let detail = format!(
+        "Restored {} of vault {} from snapshot {snapshot_id}",
+        body.path.as_deref().unwrap_or("everything"),
+        body.vault_id
+    );
+    let report = backup_blocking(move || {
+        service.restore(
+            &snapshot_id,
+            &body.vault_id,
+            &vault.path,
+            body.path.as_deref(),
+            body.target_path.as_deref(),
+        )
+    })
+    .await?;
+
+    state
+        .db
+        .write_audit_log(
+            Some(&admin.user_id),
+            Some(&admin.username),
+            "backup_restored",
+            Some(&detail),
+            None,
+            true,
+        )
+        .await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+// ── SCIM tokens ─────────────────────────────────────────────────────────
+
+#[get("/api/admin/scim/tokens")]
+async fn list_scim_tokens(state: web::Data<AppState>, req: HttpRequest) -> AppResult<HttpResponse> {
+    let _admin = require_admin_user(&state, &req).await?;
+    let tokens = state.db.list_scim_tokens().await?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+/// Issue a bearer token for an identity provider's SCIM client. Groups the
+/// provider creates are recorded as created by the issuing administrator.
+#[post("/api/admin/scim/tokens")]
+async fn create_scim_token(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<CreateScimTokenRequest>,
+) -> AppResult<HttpResponse> {
+    let admin = require_admin_user(&state, &req).await?;
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput(
+            "Token name cannot be empty".to_string(),
+        ));
+    }
+
+    let token = ScimService::generate_token();
+    let info = state
+        .db
+        .create_scim_token(name, &ScimService::hash_token(&token), &admin.user_id)
+        .await?;
+    let _ = state
+        .db
+        .write_audit_log(
+            Some(&admin.user_id),
+            Some(&admin.username),
+            "scim_token_created",
+            Some(&format!("Created SCIM token '{name}' ({})", info.id)),
+            None,
+            true,
+        )
+        .await;
+
+    Ok(HttpResponse::Created().json(CreateScimTokenResponse { token, info }))
+}
+
+#[delete("/api/admin/scim/tokens/{token_id}")]
+async fn revoke_scim_token(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let admin = require_admin_user(&state, &req).await?;
+    let token_id = path.into_inner();
+    state.db.revoke_scim_token(&token_id).await?;
+    let _ = state
+        .db
+        .write_audit_log(
+            Some(&admin.user_id),
+            Some(&admin.username),
+            "scim_token_revoked",
+            Some(&format!("Revoked SCIM token {token_id}")),
+            None,
+            true,
+        )
+        .await;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "success": true })))
+}
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(list_users)
         .service(create_user)
@@ -366,8 +645,17 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
         .service(reactivate_user)
         .service(delete_user)
         .service(get_audit_log)
+        .service(export_audit_log)
+        .service(verify_audit_log)
         .service(bulk_import_users)
-        .service(get_entity_index_stats);
+        .service(get_entity_index_stats)
+        .service(list_backups)
+        .service(create_backup)
+        .service(verify_backup)
+        .service(restore_backup)
+        .service(list_scim_tokens)
+        .service(create_scim_token)
+        .service(revoke_scim_token);
 }
 
 #[derive(serde::Serialize)]
@@ -393,21 +681,21 @@ async fn get_entity_index_stats(
 
     for vault in &vaults {
         let entity_count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM entities WHERE vault_id = ?")
+            sqlx::query_as("SELECT COUNT(*) FROM entities WHERE vault_id = $1")
                 .bind(&vault.id)
                 .fetch_one(state.db.pool())
                 .await
                 .unwrap_or((0,));
 
         let relation_count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM relations WHERE vault_id = ?")
+            sqlx::query_as("SELECT COUNT(*) FROM relations WHERE vault_id = $1")
                 .bind(&vault.id)
                 .fetch_one(state.db.pool())
                 .await
                 .unwrap_or((0,));
 
         let reindex_row: Option<(String, i64, i64)> = sqlx::query_as(
-            "SELECT completed_at, file_count, duration_ms FROM reindex_log WHERE vault_id = ?",
+            "SELECT completed_at, file_count, duration_ms FROM reindex_log WHERE vault_id = $1",
         )
         .bind(&vault.id)
         .fetch_optional(state.db.pool())
@@ -1,6 +1,6 @@
 use crate::error::{AppError, AppResult};
 use crate::middleware::AuthenticatedUser;
-use crate::models::{CreateApiKeyRequest, CreateApiKeyResponse};
+use crate::models::{ApiKeyRestrictions, CreateApiKeyRequest, CreateApiKeyResponse, VaultRole};
 use crate::routes::vaults::AppState;
 use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse};
 use argon2::{
@@ -39,6 +39,50 @@ fn hash_key(key: &str) -> AppResult<String> {
         .map(|h| h.to_string())
 }
 
+/// Check and normalise the restrictions requested for a new key. Keys can
+/// only be limited to vaults the user can already access.
+async fn validate_restrictions(
+    state: &AppState,
+    user_id: &str,
+    restrictions: &ApiKeyRestrictions,
+) -> AppResult<ApiKeyRestrictions> {
+    let mut restrictions = restrictions.clone();
+
+    if restrictions.role == Some(VaultRole::Owner) {
+        return Err(AppError::InvalidInput(
+            "API key role must be 'viewer' or 'editor'".to_string(),
+        ));
+    }
+
+    restrictions.vault_ids.sort();
+    restrictions.vault_ids.dedup();
+    for vault_id in &restrictions.vault_ids {
+        if state
+            .db
+            .get_vault_role_for_user(vault_id, user_id)
+            .await?
+            .is_none()
+        {
+            return Err(AppError::InvalidInput(format!(
+                "You do not have access to vault {vault_id}"
+            )));
+        }
+    }
+
+    for raw in restrictions.path_globs.iter_mut() {
+        *raw = raw.trim().trim_start_matches('/').to_string();
+        if raw.is_empty() || glob::Pattern::new(raw).is_err() {
+            return Err(AppError::InvalidInput(format!("Invalid path glob '{raw}'")));
+        }
+    }
+    restrictions.path_globs.dedup();
+
+    restrictions.scopes.sort_by_key(|scope| *scope as u8);
+    restrictions.scopes.dedup();
+
+    Ok(restrictions)
+}
+
 /// Generate a new API key for the authenticated user.
 #[post("/api/auth/api-keys")]
 async fn create_api_key(
@@ -55,6 +99,8 @@ async fn create_api_key(
         ));
     }
 
+    let restrictions = validate_restrictions(&state, &user.user_id, &body.restrictions).await?;
+
     let raw_key = generate_api_key();
     let prefix = key_prefix(&raw_key);
     let key_hash = hash_key(&raw_key)?;
@@ -66,7 +112,15 @@ async fn create_api_key(
 
     state
         .db
-        .create_api_key(&id, name, &prefix, &key_hash, &user.user_id, expires_at)
+        .create_api_key(
+            &id,
+            name,
+            &prefix,
+            &key_hash,
+            &user.user_id,
+            expires_at,
+            &restrictions,
+        )
         .await?;
 
     let _ = state
@@ -75,7 +129,10 @@ async fn create_api_key(
             Some(&user.user_id),
             Some(&user.username),
             "api_key_created",
-            Some(&format!("Created API key '{name}' (prefix: {prefix})")),
+            Some(&format!(
+                "Created API key '{name}' (prefix: {prefix}, restrictions: {})",
+                serde_json::to_string(&restrictions).unwrap_or_default()
+            )),
             None,
             true,
         )
@@ -87,6 +144,7 @@ async fn create_api_key(
         api_key: raw_key,
         prefix,
         expires_at,
+        restrictions,
     }))
 }
 
@@ -1,10 +1,11 @@
 use crate::config::AppConfig;
 use crate::error::{AppError, AppResult};
 use crate::middleware::AuthenticatedUser;
+use crate::models::webauthn::WebAuthnPurpose;
 use crate::models::AuthenticatedUserProfile;
-use crate::models::ChangePasswordRequest;
+use crate::models::{ChangePasswordRequest, WebAuthnCeremony};
 use crate::routes::vaults::AppState;
-use crate::services::{authenticate_username_password, validate_password_policy};
+use crate::services::{authenticate_username_password, validate_password_policy, WebAuthnService};
 use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse};
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, SaltString},
@@ -34,6 +35,13 @@ pub struct LoginResponse {
     /// tokens are fully authorized for protected endpoints.
     #[serde(default)]
     pub totp_required: bool,
+    /// If true, the password was accepted but the account has a WebAuthn
+    /// authenticator that must also be presented: no tokens are issued until
+    /// `webauthn` is completed at `/api/auth/webauthn/login/finish`.
+    #[serde(default)]
+    pub webauthn_required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webauthn: Option<WebAuthnCeremony>,
 }
 
 #[derive(Debug, Serialize)]
@@ -71,6 +79,30 @@ async fn login(
     let principal =
         authenticate_username_password(&state.db, &config.auth, username, password).await?;
 
+    if config.auth.webauthn_second_factor
+        && !state
+            .db
+            .list_webauthn_credentials(&principal.user_id)
+            .await?
+            .is_empty()
+    {
+        let ceremony = WebAuthnService::begin_login(
+            &state.db,
+            &config.auth,
+            Some(&principal.user_id),
+            WebAuthnPurpose::SecondFactor,
+        )
+        .await?;
+        return Ok(HttpResponse::Ok().json(LoginResponse {
+            access_token: String::new(),
+            refresh_token: String::new(),
+            expires_in: 0,
+            totp_required: principal.totp_required,
+            webauthn_required: true,
+            webauthn: Some(ceremony),
+        }));
+    }
+
     let (mut response, refresh_jti, refresh_exp) = issue_tokens(
         &principal.user_id,
         &principal.username,
@@ -120,6 +152,12 @@ async fn refresh_access_token(
         .db
         .create_session(&refresh_jti, &old_claims.sub, refresh_exp)
         .await;
+    if old_claims.auth_method == "oidc" {
+        let _ = state
+            .db
+            .move_oidc_session(&old_claims.jti, &refresh_jti)
+            .await;
+    }
 
     Ok(HttpResponse::Ok().json(response))
 }
@@ -230,6 +268,19 @@ pub fn issue_tokens_public(
     issue_tokens(user_id, username, auth_method, auth_cfg)
 }
 
+/// Validate a refresh token for other route modules (e.g. OIDC logout).
+/// Returns `(user_id, username, session_id)`.
+pub fn decode_refresh_token(
+    token: &str,
+    auth_cfg: &crate::config::AuthConfig,
+) -> AppResult<(String, String, String)> {
+    let claims = decode_token(token, &auth_cfg.jwt_secret)?;
+    if claims.token_type != "refresh" {
+        return Err(AppError::Unauthorized("Invalid refresh token".to_string()));
+    }
+    Ok((claims.sub, claims.username, claims.jti))
+}
+
 fn issue_tokens(
     user_id: &str,
     username: &str,
@@ -286,6 +337,8 @@ fn issue_tokens(
             refresh_token: refresh_jwt,
             expires_in: auth_cfg.access_token_ttl,
             totp_required: false,
+            webauthn_required: false,
+            webauthn: None,
         },
         refresh_jti,
         refresh_expires_at,
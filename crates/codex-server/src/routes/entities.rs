@@ -1,12 +1,35 @@
+use crate::error::{AppError, AppResult};
+use crate::middleware::{AuditPath, AuthenticatedUser};
+use crate::models::{
+    CreateEntityRequest, CreateRelationRequest, EntityMigration, EntityTypeBody,
+    EntityValidationMode, FieldType, FieldValidationError, FileContent, PathAccess,
+    RelationTypeBody, RelationTypeSchema, SchemaMigrationReport, SchemaMigrationRequest,
+    SetEntityValidationModeRequest, UpdateEntityRequest, UpdateRelationRequest, VaultTypeChange,
+    VaultTypeKind,
+};
+use crate::routes::files::note_git_author;
 use crate::routes::AppState;
-use crate::services::entity_service::EntityService;
+use crate::services::entity_service::{Entity, EntityService, FieldFilter};
+use crate::services::entity_validation_service::EntityValidationService;
+use crate::services::frontmatter_service::{set_frontmatter_values, update_frontmatter_fields};
+use crate::services::graph_service::{
+    EntityGraph, GraphFile, GraphFilter, GraphInclude, NoteGraph, NoteGraphOptions,
+};
+use crate::services::path_acl_service::{PathAcl, PathAclService};
 use crate::services::reindex_service::ReindexService;
-use crate::services::relation_service::RelationService;
-use crate::services::TemplateService;
-use actix_web::{web, HttpResponse};
+use crate::services::relation_service::{
+    ExplicitRelation, RelationService, EXPLICIT_RELATIONS_KEY,
+};
+use crate::services::timeline_service::{TimelineOptions, GREGORIAN};
+use crate::services::{
+    ComputedFieldService, EntityTypeRegistry, FileService, SchemaMigrationService, SchemaService,
+    TemplateService, TimelineService, TrashService,
+};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
 use serde::Deserialize;
 use serde_json::json;
 use std::collections::HashMap;
+use walkdir::WalkDir;
 
 #[derive(Deserialize)]
 pub struct EntityListQuery {
@@ -14,25 +37,130 @@ pub struct EntityListQuery {
     pub label: Option<String>,
     pub plugin: Option<String>,
     pub q: Option<String>,
+    /// Comma-separated field conditions, e.g. `member_count>=3`
+    pub filter: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VaultGraphQuery {
+    pub include: Option<String>,
+    pub folder: Option<String>,
+    pub orphans: Option<bool>,
+    pub unresolved: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct GraphNeighborhoodQuery {
+    pub entity: String,
+    pub depth: Option<u32>,
+    pub relation_types: Option<String>,
+    pub labels: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GraphPathQuery {
+    pub from: String,
+    pub to: String,
+    pub relation_types: Option<String>,
+    pub labels: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GraphQuery {
+    pub relation_types: Option<String>,
+    pub labels: Option<String>,
+    /// Number of nodes listed by `/graph/stats`
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct TimelineQuery {
+    /// Calendar id; `gregorian` when omitted
+    pub calendar: Option<String>,
+    /// Comma-separated fields tried for the start date
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub types: Option<String>,
+    pub labels: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
 }
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg
         // Entities for a vault
         .service(
-            web::resource("/api/vaults/{vault_id}/entities").route(web::get().to(list_entities)),
+            web::resource("/api/vaults/{vault_id}/entities")
+                .route(web::get().to(list_entities))
+                .route(web::post().to(create_entity)),
+        )
+        // Schema validation report and mode (before `{entity_id}` so that
+        // "validation" is not taken for an entity id)
+        .service(
+            web::resource("/api/vaults/{vault_id}/entities/validation")
+                .route(web::get().to(get_entity_validation))
+                .route(web::put().to(set_entity_validation_mode)),
         )
         .service(
             web::resource("/api/vaults/{vault_id}/entities/{entity_id}")
-                .route(web::get().to(get_entity)),
+                .route(web::get().to(get_entity))
+                .route(web::patch().to(update_entity))
+                .route(web::delete().to(delete_entity)),
         )
         // Relations for an entity
         .service(
             web::resource("/api/vaults/{vault_id}/entities/{entity_id}/relations")
                 .route(web::get().to(get_entity_relations)),
         )
+        // Explicit relations between entities
+        .service(
+            web::resource("/api/vaults/{vault_id}/relations")
+                .route(web::post().to(create_relation)),
+        )
+        .service(
+            web::resource("/api/vaults/{vault_id}/relations/{relation_id}")
+                .route(web::patch().to(update_relation))
+                .route(web::delete().to(delete_relation)),
+        )
         // Full graph data (all entities + relations for a vault)
         .service(web::resource("/api/vaults/{vault_id}/graph").route(web::get().to(get_graph)))
+        // Graph queries (?relation_types=a,b&labels=x,y narrow the graph)
+        .service(
+            web::resource("/api/vaults/{vault_id}/graph/neighborhood")
+                .route(web::get().to(get_graph_neighborhood)),
+        )
+        .service(
+            web::resource("/api/vaults/{vault_id}/graph/path").route(web::get().to(get_graph_path)),
+        )
+        .service(
+            web::resource("/api/vaults/{vault_id}/graph/components")
+                .route(web::get().to(get_graph_components)),
+        )
+        .service(
+            web::resource("/api/vaults/{vault_id}/graph/stats")
+                .route(web::get().to(get_graph_stats)),
+        )
+        // Rewrite existing entities after a type's fields change
+        .service(
+            web::resource("/api/vaults/{vault_id}/entity-types/{type_id}/migrate")
+                .route(web::post().to(migrate_entity_type)),
+        )
+        // Entities ordered in time (?calendar=&start=&end=&types=&labels=&from=&to=)
+        .service(
+            web::resource("/api/vaults/{vault_id}/timeline").route(web::get().to(get_timeline)),
+        )
+        .service(
+            web::resource("/api/vaults/{vault_id}/calendars").route(web::get().to(get_calendars)),
+        )
+        // Vault-local entity and relation types (`.codex/types/*.toml`)
+        .service(
+            web::resource("/api/vaults/{vault_id}/types").route(web::get().to(get_vault_types)),
+        )
+        .service(
+            web::resource("/api/vaults/{vault_id}/types/{kind}/{type_id}")
+                .route(web::put().to(put_vault_type))
+                .route(web::delete().to(delete_vault_type)),
+        )
         // Trigger a full reindex
         .service(
             web::resource("/api/vaults/{vault_id}/reindex").route(web::post().to(trigger_reindex)),
@@ -64,20 +192,47 @@ async fn list_entities(
     path: web::Path<String>,
     query: web::Query<EntityListQuery>,
     state: web::Data<AppState>,
+    http_req: HttpRequest,
 ) -> HttpResponse {
     let vault_id = path.into_inner();
+    let filters = match FieldFilter::parse_list(query.filter.as_deref().unwrap_or_default()) {
+        Ok(filters) => filters,
+        Err(e) => return HttpResponse::BadRequest().json(json!({ "error": e.to_string() })),
+    };
+    let acl = match PathAclService::for_request(&state.db, &vault_id, &http_req).await {
+        Ok(acl) => acl,
+        Err(e) => {
+            tracing::error!("list_entities acl error: {e}");
+            return HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }));
+        }
+    };
+    let entity_types =
+        with_subtypes(&state.entity_types(&vault_id), query.entity_type.as_slice()).await;
 
     match EntityService::list(
         &state.db,
         &vault_id,
-        query.entity_type.as_deref(),
+        &entity_types,
         query.label.as_deref(),
         query.plugin.as_deref(),
         query.q.as_deref(),
     )
     .await
     {
-        Ok(entities) => HttpResponse::Ok().json(json!({ "entities": entities })),
+        Ok(mut entities) => {
+            entities.retain(|entity| acl.can_read(&entity.path));
+            if let Err(e) = with_computed(&state, &vault_id, &acl, &mut entities).await {
+                tracing::error!("list_entities computed fields error: {e}");
+                return HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }));
+            }
+            if !filters.is_empty() {
+                entities.retain(|entity| {
+                    let fields = entity.fields_map();
+                    filters.iter().all(|filter| filter.matches(&fields))
+                });
+            }
+            HttpResponse::Ok().json(json!({ "entities": entities }))
+        }
         Err(e) => {
             tracing::error!("list_entities error: {e}");
             HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }))
@@ -85,14 +240,407 @@ async fn list_entities(
     }
 }
 
-async fn get_entity(path: web::Path<(String, String)>, state: web::Data<AppState>) -> HttpResponse {
-    let (_vault_id, entity_id) = path.into_inner();
+async fn get_entity(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let (vault_id, entity_id) = path.into_inner();
+    let entity = find_entity(&state, &vault_id, &entity_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    if !acl.can_read(&entity.path) {
+        return Err(AppError::NotFound("Entity not found".to_string()));
+    }
+    let mut entities = [entity];
+    with_computed(&state, &vault_id, &acl, &mut entities).await?;
+    let [entity] = entities;
+    Ok(HttpResponse::Ok().json(entity))
+}
+
+/// Create an entity file from typed field values. The file is named after
+/// the type's display field unless a `path` is given.
+async fn create_entity(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    body: web::Json<CreateEntityRequest>,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let schema = state
+        .entity_types(&vault_id)
+        .get_by_id(&body.entity_type)
+        .await
+        .ok_or_else(|| {
+            AppError::InvalidInput(format!("Unknown entity type '{}'", body.entity_type))
+        })?;
+
+    let stem = EntityService::file_stem_for(&schema, &body.fields);
+    let file_path = match &body.path {
+        Some(file_path) if file_path.ends_with(".md") => file_path.clone(),
+        Some(file_path) => format!("{file_path}.md"),
+        None => new_entity_path(
+            &vault.path,
+            body.folder.as_deref().unwrap_or_default(),
+            &stem,
+        )?,
+    };
+    http_req
+        .extensions_mut()
+        .insert(AuditPath(file_path.clone()));
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    acl.require(&file_path, PathAccess::Write)?;
+
+    let fields = EntityService::new_entity_fields(&schema, &body.fields);
+    let mut frontmatter = serde_json::Map::new();
+    frontmatter.insert("codex_type".to_string(), json!(schema.id));
+    frontmatter.extend(fields.iter().cloned());
+    if let Some(response) =
+        check_entity_fields(&state, &vault_id, &file_path, &frontmatter.into()).await?
+    {
+        return Ok(response);
+    }
+    note_git_author(&state, &http_req, &vault_id);
+
+    let body_text = match &body.content {
+        Some(content) => content.clone(),
+        None => format!("# {stem}\n"),
+    };
+    let markdown = EntityService::new_entity_markdown(&schema, &fields, &body_text)?;
+    let content = FileService::create_file(&vault.path, &file_path, Some(&markdown))?;
+    record_entity_write(&state, &vault_id, &file_path, "created", &content).await?;
+
+    let entity = index_entity(&state, &vault_id, &file_path, &markdown, &content)
+        .await?
+        .ok_or_else(|| AppError::InternalError("New entity was not indexed".to_string()))?;
+    resync_referrers(&state, &vault_id, &entity).await?;
+    let mut entities = [entity];
+    with_computed(&state, &vault_id, &acl, &mut entities).await?;
+    let [entity] = entities;
+    Ok(HttpResponse::Created().json(entity))
+}
+
+/// Set or remove (`null`) individual fields of an entity. Only the
+/// frontmatter is rewritten; the prose and the order of existing keys are
+/// kept.
+async fn update_entity(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    body: web::Json<UpdateEntityRequest>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, entity_id) = path.into_inner();
+    let entity = find_entity(&state, &vault_id, &entity_id).await?;
+    http_req
+        .extensions_mut()
+        .insert(AuditPath(entity.path.clone()));
+    if let Some(key) = body
+        .fields
+        .keys()
+        .find(|key| *key == "codex_type" || *key == "codex_plugin")
+    {
+        return Err(AppError::InvalidInput(format!(
+            "'{key}' cannot be changed; create an entity of the new type instead"
+        )));
+    }
+    let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    acl.require(&entity.path, PathAccess::Write)?;
+    note_git_author(&state, &http_req, &vault_id);
+
+    let raw = std::fs::read_to_string(FileService::resolve_path(&vault.path, &entity.path)?)?;
+    let updated = update_frontmatter_fields(&raw, &body.fields)?;
+    let frontmatter = EntityService::parse_frontmatter(&updated).unwrap_or_default();
+    if let Some(response) =
+        check_entity_fields(&state, &vault_id, &entity.path, &frontmatter).await?
+    {
+        return Ok(response);
+    }
+
+    let content = FileService::write_file(&vault.path, &entity.path, &updated, None, None)?;
+    record_entity_write(&state, &vault_id, &entity.path, "modified", &content).await?;
+
+    let entity = index_entity(&state, &vault_id, &entity.path, &updated, &content)
+        .await?
+        .ok_or_else(|| AppError::InternalError("Updated entity was not indexed".to_string()))?;
+    let mut entities = [entity];
+    with_computed(&state, &vault_id, &acl, &mut entities).await?;
+    let [entity] = entities;
+    Ok(HttpResponse::Ok().json(entity))
+}
+
+/// Move an entity's file to the trash and drop it and its relations from
+/// the index.
+async fn delete_entity(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let (vault_id, entity_id) = path.into_inner();
+    let entity = find_entity(&state, &vault_id, &entity_id).await?;
+    http_req
+        .extensions_mut()
+        .insert(AuditPath(entity.path.clone()));
+    let vault = state.db.get_vault(&vault_id).await?;
+    PathAclService::for_request(&state.db, &vault_id, &http_req)
+        .await?
+        .require(&entity.path, PathAccess::Write)?;
+    note_git_author(&state, &http_req, &vault_id);
+
+    let deleted_by = http_req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| user.user_id.clone());
+    TrashService::trash(
+        &state.db,
+        &vault_id,
+        &vault.path,
+        &entity.path,
+        deleted_by.as_deref(),
+    )
+    .await?;
+    state
+        .db
+        .log_file_change(
+            &vault_id,
+            &entity.path,
+            "deleted",
+            None,
+            None,
+            state.change_log_retention_days,
+        )
+        .await?;
+    state.search_index.remove_file(&vault_id, &entity.path)?;
+    EntityService::remove(&state.db, &vault_id, &entity.path).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn find_entity(state: &AppState, vault_id: &str, entity_id: &str) -> AppResult<Entity> {
+    EntityService::get(&state.db, entity_id)
+        .await?
+        .filter(|entity| entity.vault_id == vault_id)
+        .ok_or_else(|| AppError::NotFound("Entity not found".to_string()))
+}
+
+/// `<folder>/<stem>.md`, or `<stem> 2.md`, `<stem> 3.md`, … when taken.
+fn new_entity_path(vault_path: &str, folder: &str, stem: &str) -> AppResult<String> {
+    let folder = folder.trim_matches('/');
+    for n in 1.. {
+        let name = if n == 1 {
+            format!("{stem}.md")
+        } else {
+            format!("{stem} {n}.md")
+        };
+        let candidate = if folder.is_empty() {
+            name
+        } else {
+            format!("{folder}/{name}")
+        };
+        if !FileService::resolve_path(vault_path, &candidate)?.exists() {
+            return Ok(candidate);
+        }
+    }
+    unreachable!("an unused file name always exists")
+}
+
+/// Check entity frontmatter written through the entity API. Unlike saves
+/// of the raw file, these are refused in `warn` mode too; only `off` skips
+/// the check.
+async fn check_entity_fields(
+    state: &AppState,
+    vault_id: &str,
+    target: &str,
+    frontmatter: &serde_json::Value,
+) -> AppResult<Option<HttpResponse>> {
+    if state.db.get_vault_entity_validation(vault_id).await? == EntityValidationMode::Off {
+        return Ok(None);
+    }
+    let errors = EntityValidationService::validate_frontmatter(
+        &state.db,
+        &state.entity_types(vault_id),
+        vault_id,
+        frontmatter,
+    )
+    .await?;
+    if errors.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(validation_failed(target, errors)))
+}
+
+/// The 422 response for frontmatter that violates its entity type schema.
+pub(crate) fn validation_failed(target: &str, errors: Vec<FieldValidationError>) -> HttpResponse {
+    let message = format!(
+        "{target} does not match its entity type schema ({} error(s))",
+        errors.len()
+    );
+    unprocessable(message, errors)
+}
+
+fn unprocessable(message: String, errors: Vec<FieldValidationError>) -> HttpResponse {
+    HttpResponse::UnprocessableEntity().json(json!({
+        "error": "VALIDATION_FAILED",
+        "message": message,
+        "errors": errors,
+    }))
+}
+
+async fn record_entity_write(
+    state: &AppState,
+    vault_id: &str,
+    file_path: &str,
+    change: &str,
+    content: &FileContent,
+) -> AppResult<()> {
+    let etag = format!("\"{:x}\"", content.modified.timestamp_millis());
+    state
+        .db
+        .log_file_change(
+            vault_id,
+            file_path,
+            change,
+            Some(etag.as_str()),
+            None,
+            state.change_log_retention_days,
+        )
+        .await?;
+    state
+        .search_index
+        .update_file(vault_id, file_path, content.content.clone())?;
+    Ok(())
+}
+
+/// Index the entity just written to `file_path` and rebuild its relations
+/// right away rather than waiting for the file watcher.
+async fn index_entity(
+    state: &AppState,
+    vault_id: &str,
+    file_path: &str,
+    markdown: &str,
+    content: &FileContent,
+) -> AppResult<Option<Entity>> {
+    let Some(frontmatter) = EntityService::parse_frontmatter(markdown) else {
+        return Ok(None);
+    };
+    let entity = EntityService::upsert(
+        &state.db,
+        vault_id,
+        file_path,
+        &frontmatter,
+        &content.modified.to_rfc3339(),
+        Some(&state.entity_types(vault_id)),
+    )
+    .await?;
+    if let Some(entity) = &entity {
+        RelationService::sync_from_entity(&state.db, entity, Some(&state.relation_types(vault_id)))
+            .await?;
+    }
+    Ok(entity)
+}
+
+/// `types` and every type that extends one of them.
+async fn with_subtypes(registry: &EntityTypeRegistry, types: &[String]) -> Vec<String> {
+    let mut all = Vec::new();
+    for type_id in types {
+        for id in registry.with_subtypes(type_id).await {
+            if !all.contains(&id) {
+                all.push(id);
+            }
+        }
+    }
+    all
+}
+
+/// Fill in the computed fields of `entities` as the caller sees them.
+async fn with_computed(
+    state: &AppState,
+    vault_id: &str,
+    acl: &PathAcl,
+    entities: &mut [Entity],
+) -> AppResult<()> {
+    ComputedFieldService::apply(
+        &state.db,
+        vault_id,
+        &state.entity_types(vault_id),
+        entities,
+        |path| acl.can_read(path),
+    )
+    .await
+}
+
+/// Rebuild the relations of entities that link to `entity` by name, so
+/// references written before it existed resolve now.
+async fn resync_referrers(state: &AppState, vault_id: &str, entity: &Entity) -> AppResult<()> {
+    let Some(stem) = std::path::Path::new(&entity.path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+    else {
+        return Ok(());
+    };
+    let needle = format!("[[{}", stem.to_lowercase());
+    for other in EntityService::list_all_in_vault(&state.db, vault_id).await? {
+        if other.id != entity.id && other.fields.to_lowercase().contains(&needle) {
+            RelationService::sync_from_entity(
+                &state.db,
+                &other,
+                Some(&state.relation_types(vault_id)),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Every entity the caller can read that violates its entity type schema.
+async fn get_entity_validation(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+) -> HttpResponse {
+    let vault_id = path.into_inner();
+
+    let acl = match PathAclService::for_request(&state.db, &vault_id, &http_req).await {
+        Ok(acl) => acl,
+        Err(e) => {
+            tracing::error!("get_entity_validation acl error: {e}");
+            return HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }));
+        }
+    };
+
+    match EntityValidationService::report(
+        &state.db,
+        &state.entity_types(&vault_id),
+        &vault_id,
+        |path| acl.can_read(path),
+    )
+    .await
+    {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => {
+            tracing::error!("get_entity_validation error: {e}");
+            HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }))
+        }
+    }
+}
+
+/// Set how strictly saves in the vault are checked against entity schemas.
+async fn set_entity_validation_mode(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    body: web::Json<SetEntityValidationModeRequest>,
+) -> HttpResponse {
+    let vault_id = path.into_inner();
 
-    match EntityService::get(&state.db, &entity_id).await {
-        Ok(Some(entity)) => HttpResponse::Ok().json(entity),
-        Ok(None) => HttpResponse::NotFound().json(json!({ "error": "Entity not found" })),
+    match state
+        .db
+        .set_vault_entity_validation(&vault_id, body.mode)
+        .await
+    {
+        Ok(()) => HttpResponse::Ok().json(json!({ "mode": body.mode })),
+        Err(AppError::NotFound(msg)) => HttpResponse::NotFound().json(json!({ "error": msg })),
         Err(e) => {
-            tracing::error!("get_entity error: {e}");
+            tracing::error!("set_entity_validation_mode error: {e}");
             HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }))
         }
     }
@@ -101,80 +649,634 @@ async fn get_entity(path: web::Path<(String, String)>, state: web::Data<AppState
 async fn get_entity_relations(
     path: web::Path<(String, String)>,
     state: web::Data<AppState>,
-) -> HttpResponse {
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
     let (vault_id, entity_id) = path.into_inner();
+    let entity = find_entity(&state, &vault_id, &entity_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    if !acl.can_read(&entity.path) {
+        return Err(AppError::NotFound("Entity not found".to_string()));
+    }
 
-    match EntityService::get(&state.db, &entity_id).await {
-        Ok(Some(entity)) => {
-            match build_entity_relations_payload(&state, &vault_id, &entity).await {
-                Ok(relations) => HttpResponse::Ok().json(json!({ "relations": relations })),
-                Err(e) => {
-                    tracing::error!("get_entity_relations error: {e}");
-                    HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }))
-                }
-            }
-        }
-        Ok(None) => HttpResponse::NotFound().json(json!({ "error": "Entity not found" })),
+    match build_entity_relations_payload(&state, &vault_id, &entity, &acl).await {
+        Ok(relations) => Ok(HttpResponse::Ok().json(json!({ "relations": relations }))),
         Err(e) => {
-            tracing::error!("get_entity_relations entity lookup error: {e}");
-            HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }))
+            tracing::error!("get_entity_relations error: {e}");
+            Err(AppError::InternalError(e.to_string()))
         }
     }
 }
 
-/// Returns a graph payload suitable for D3 force simulation:
-/// `{ nodes: [Entity], links: [Relation] }`
-async fn get_graph(path: web::Path<String>, state: web::Data<AppState>) -> HttpResponse {
+/// Create an explicit relation. It is stored in the source note's
+/// `codex_relations` frontmatter, so it survives a reindex, and indexed
+/// together with its inverse edge.
+async fn create_relation(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    body: web::Json<CreateRelationRequest>,
+) -> AppResult<HttpResponse> {
     let vault_id = path.into_inner();
+    let from = find_entity(&state, &vault_id, &body.from_entity_id).await?;
+    let to = find_entity(&state, &vault_id, &body.to_entity_id).await?;
+    http_req
+        .extensions_mut()
+        .insert(AuditPath(from.path.clone()));
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    acl.require(&from.path, PathAccess::Write)?;
+    acl.require(&to.path, PathAccess::Read)?;
 
-    let entities_fut = EntityService::list_all_in_vault(&state.db, &vault_id);
-    let relations_fut = RelationService::list_for_vault(&state.db, &vault_id);
-
-    let (entities_res, relations_res) = tokio::join!(entities_fut, relations_fut);
-
-    match (entities_res, relations_res) {
-        (Ok(entities), Ok(relations)) => {
-            // Shape entities into GraphNode objects
-            let nodes: Vec<serde_json::Value> = entities
-                .iter()
-                .map(|e| {
-                    let title = std::path::Path::new(&e.path)
-                        .file_stem()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or(&e.path)
-                        .to_string();
-                    json!({
-                        "id": e.id,
-                        "path": e.path,
-                        "entity_type": e.entity_type,
-                        "labels": e.labels_vec(),
-                        "title": title,
-                    })
-                })
-                .collect();
+    let relation_type = find_relation_type(&state, &vault_id, &body.relation_type).await?;
+    let errors = EntityValidationService::validate_relation(
+        &state.db,
+        &state.entity_types(&vault_id),
+        &relation_type,
+        &from,
+        &to,
+        &body.metadata,
+    )
+    .await?;
+    if !errors.is_empty() {
+        return Ok(relation_invalid(&relation_type.name, errors));
+    }
+
+    let stem = std::path::Path::new(&to.path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    note_git_author(&state, &http_req, &vault_id);
+    edit_explicit_relations(&state, &vault_id, &from, |explicit| {
+        if explicit
+            .iter()
+            .any(|e| e.relation_type == relation_type.name && e.targets(&to.path))
+        {
+            return Err(AppError::Conflict(format!(
+                "{} is already '{}' {}",
+                from.path, relation_type.name, to.path
+            )));
+        }
+        explicit.push(ExplicitRelation {
+            relation_type: relation_type.name.clone(),
+            target: format!("[[{stem}]]"),
+            metadata: body.metadata.clone(),
+        });
+        Ok(())
+    })
+    .await?;
+
+    let id = RelationService::explicit_relation_id(&from.id, &relation_type.name, &to.id);
+    let relation = RelationService::get(&state.db, &id)
+        .await?
+        .ok_or_else(|| AppError::InternalError("New relation was not indexed".to_string()))?;
+    Ok(HttpResponse::Created().json(relation))
+}
+
+/// Replace the metadata of an explicit relation. Either edge's id may be
+/// given.
+async fn update_relation(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    body: web::Json<UpdateRelationRequest>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, relation_id) = path.into_inner();
+    let (from, relation_type, to) = find_explicit_relation(&state, &vault_id, &relation_id).await?;
+    http_req
+        .extensions_mut()
+        .insert(AuditPath(from.path.clone()));
+    PathAclService::for_request(&state.db, &vault_id, &http_req)
+        .await?
+        .require(&from.path, PathAccess::Write)?;
+
+    let schema = find_relation_type(&state, &vault_id, &relation_type).await?;
+    let errors = EntityValidationService::validate_relation(
+        &state.db,
+        &state.entity_types(&vault_id),
+        &schema,
+        &from,
+        &to,
+        &body.metadata,
+    )
+    .await?;
+    if !errors.is_empty() {
+        return Ok(relation_invalid(&schema.name, errors));
+    }
+
+    note_git_author(&state, &http_req, &vault_id);
+    edit_explicit_relations(&state, &vault_id, &from, |explicit| {
+        let entry = explicit
+            .iter_mut()
+            .find(|e| e.relation_type == relation_type && e.targets(&to.path))
+            .ok_or_else(|| AppError::NotFound("Relation not found".to_string()))?;
+        entry.metadata = body.metadata.clone();
+        Ok(())
+    })
+    .await?;
+
+    let id = RelationService::explicit_relation_id(&from.id, &relation_type, &to.id);
+    let relation = RelationService::get(&state.db, &id)
+        .await?
+        .ok_or_else(|| AppError::InternalError("Updated relation was not indexed".to_string()))?;
+    Ok(HttpResponse::Ok().json(relation))
+}
+
+/// Delete an explicit relation and its inverse edge. Either edge's id may
+/// be given.
+async fn delete_relation(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let (vault_id, relation_id) = path.into_inner();
+    let (from, relation_type, to) = find_explicit_relation(&state, &vault_id, &relation_id).await?;
+    http_req
+        .extensions_mut()
+        .insert(AuditPath(from.path.clone()));
+    PathAclService::for_request(&state.db, &vault_id, &http_req)
+        .await?
+        .require(&from.path, PathAccess::Write)?;
+
+    note_git_author(&state, &http_req, &vault_id);
+    edit_explicit_relations(&state, &vault_id, &from, |explicit| {
+        explicit.retain(|e| !(e.relation_type == relation_type && e.targets(&to.path)));
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn find_relation_type(
+    state: &AppState,
+    vault_id: &str,
+    name: &str,
+) -> AppResult<RelationTypeSchema> {
+    state
+        .relation_types(vault_id)
+        .find_by_name(name)
+        .await
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown relation type '{name}'")))
+}
+
+/// The source entity, relation type and target entity of the explicit
+/// relation one of whose edges is `relation_id`.
+async fn find_explicit_relation(
+    state: &AppState,
+    vault_id: &str,
+    relation_id: &str,
+) -> AppResult<(Entity, String, Entity)> {
+    let relation = RelationService::get(&state.db, relation_id)
+        .await?
+        .filter(|relation| relation.vault_id == vault_id)
+        .ok_or_else(|| AppError::NotFound("Relation not found".to_string()))?;
+    let relation_type = relation.source_field.clone().unwrap_or_default();
+    if relation.source != "explicit" {
+        return Err(AppError::InvalidInput(format!(
+            "This relation comes from the '{relation_type}' field; edit the field instead"
+        )));
+    }
+    let (from_id, to_id) = if relation.direction == "inverse" {
+        (&relation.to_entity_id, &relation.from_entity_id)
+    } else {
+        (&relation.from_entity_id, &relation.to_entity_id)
+    };
+    let from = find_entity(state, vault_id, from_id).await?;
+    let to = find_entity(state, vault_id, to_id).await?;
+    Ok((from, relation_type, to))
+}
+
+fn relation_invalid(relation_type: &str, errors: Vec<FieldValidationError>) -> HttpResponse {
+    let message = format!(
+        "The relation does not match the '{relation_type}' relation type ({} error(s))",
+        errors.len()
+    );
+    unprocessable(message, errors)
+}
+
+/// Apply `edit` to the `codex_relations` frontmatter of `entity`'s note as
+/// it is on disk, then re-index the note, rebuilding its relations.
+async fn edit_explicit_relations(
+    state: &AppState,
+    vault_id: &str,
+    entity: &Entity,
+    edit: impl FnOnce(&mut Vec<ExplicitRelation>) -> AppResult<()>,
+) -> AppResult<()> {
+    let vault = state.db.get_vault(vault_id).await?;
+    let raw = std::fs::read_to_string(FileService::resolve_path(&vault.path, &entity.path)?)?;
+    let frontmatter = EntityService::parse_frontmatter(&raw).unwrap_or_default();
+    let mut explicit = RelationService::explicit_relations(&frontmatter);
+    edit(&mut explicit)?;
+    let value =
+        if explicit.is_empty() {
+            None
+        } else {
+            Some(serde_yaml::to_value(&explicit).map_err(|e| {
+                AppError::InternalError(format!("Failed to serialize relations: {e}"))
+            })?)
+        };
+    let updated = set_frontmatter_values(&raw, [(EXPLICIT_RELATIONS_KEY.to_string(), value)])?;
+
+    let content = FileService::write_file(&vault.path, &entity.path, &updated, None, None)?;
+    record_entity_write(state, vault_id, &entity.path, "modified", &content).await?;
+    index_entity(state, vault_id, &entity.path, &updated, &content).await?;
+    Ok(())
+}
+
+/// The vault graph as `GraphData`. `include` picks the parts drawn
+/// (`links`, `tags`, `relations`, `attachments`; relations only by
+/// default), `folder` limits it to one folder, `orphans=false` drops
+/// unconnected nodes and `unresolved=true` adds links to missing notes.
+async fn get_graph(
+    path: web::Path<String>,
+    query: web::Query<VaultGraphQuery>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    let options = NoteGraphOptions {
+        include: GraphInclude::parse(query.include.as_deref())?,
+        folder: query.folder.clone(),
+        orphans: query.orphans.unwrap_or(true),
+        unresolved: query.unresolved.unwrap_or(false),
+    };
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+
+    let (entities, relations) = tokio::join!(
+        EntityService::list_all_in_vault(&state.db, &vault_id),
+        RelationService::list_for_vault(&state.db, &vault_id)
+    );
+    // Hide entities (and edges touching them) the caller may not read
+    let mut entities = entities?;
+    entities.retain(|e| acl.can_read(&e.path));
+    let files = if options.include.needs_files() {
+        let vault = state.db.get_vault(&vault_id).await?;
+        let read_notes = options.include.needs_content();
+        graph_files(&vault.path, read_notes, |p| acl.can_read(p))
+    } else {
+        Vec::new()
+    };
+    let type_colors: HashMap<String, String> = state
+        .entity_types(&vault_id)
+        .all()
+        .await
+        .into_iter()
+        .filter_map(|t| Some((t.id, t.color?)))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(NoteGraph::build(
+        &files,
+        &entities,
+        &relations?,
+        &type_colors,
+        &options,
+    )))
+}
+
+/// The vault's files, skipping hidden ones and those `can_read` rejects,
+/// with the content of Markdown notes when `read_notes` is set.
+fn graph_files(
+    vault_path: &str,
+    read_notes: bool,
+    can_read: impl Fn(&str) -> bool,
+) -> Vec<GraphFile> {
+    WalkDir::new(vault_path)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|entry| {
+            let path = entry
+                .path()
+                .strip_prefix(vault_path)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/");
+            if !can_read(&path) {
+                return None;
+            }
+            let is_note = entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+            let content = (read_notes && is_note)
+                .then(|| std::fs::read_to_string(entry.path()).ok())
+                .flatten();
+            Some(GraphFile { path, content })
+        })
+        .collect()
+}
+
+/// The entities and relations within `depth` hops (default 1) of an entity.
+async fn get_graph_neighborhood(
+    path: web::Path<String>,
+    query: web::Query<GraphNeighborhoodQuery>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let filter = GraphFilter::from_query(query.relation_types.as_deref(), query.labels.as_deref());
+    let graph = load_entity_graph(&state, &path, &http_req, &filter, &[&query.entity]).await?;
+    Ok(HttpResponse::Ok().json(graph.neighborhood(&query.entity, query.depth.unwrap_or(1))?))
+}
+
+/// A shortest chain of relations between two entities, in path order.
+async fn get_graph_path(
+    path: web::Path<String>,
+    query: web::Query<GraphPathQuery>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let filter = GraphFilter::from_query(query.relation_types.as_deref(), query.labels.as_deref());
+    let graph =
+        load_entity_graph(&state, &path, &http_req, &filter, &[&query.from, &query.to]).await?;
+    match graph.shortest_path(&query.from, &query.to)? {
+        Some(data) => Ok(HttpResponse::Ok().json(data)),
+        None => Err(AppError::NotFound(format!(
+            "No path between '{}' and '{}'",
+            query.from, query.to
+        ))),
+    }
+}
 
-            // Shape relations into GraphEdge objects
-            let edges: Vec<serde_json::Value> = relations
-                .iter()
-                .map(|r| {
-                    json!({
-                        "id": r.id,
-                        "source": r.from_entity_id,
-                        "target": r.to_entity_id,
-                        "relation_type": r.relation_type,
-                        "source_field": r.source_field,
-                        "direction": r.direction,
-                    })
-                })
+/// The graph split into connected components, largest first.
+async fn get_graph_components(
+    path: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let filter = GraphFilter::from_query(query.relation_types.as_deref(), query.labels.as_deref());
+    let graph = load_entity_graph(&state, &path, &http_req, &filter, &[]).await?;
+    Ok(HttpResponse::Ok().json(json!({ "components": graph.components() })))
+}
+
+/// Graph size plus degree and centrality of the best-connected entities.
+async fn get_graph_stats(
+    path: web::Path<String>,
+    query: web::Query<GraphQuery>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let filter = GraphFilter::from_query(query.relation_types.as_deref(), query.labels.as_deref());
+    let graph = load_entity_graph(&state, &path, &http_req, &filter, &[]).await?;
+    Ok(HttpResponse::Ok().json(graph.stats(query.limit.unwrap_or(50))))
+}
+
+/// The vault's entity graph, without entities the caller may not read.
+async fn load_entity_graph(
+    state: &AppState,
+    vault_id: &str,
+    http_req: &HttpRequest,
+    filter: &GraphFilter,
+    keep: &[&str],
+) -> AppResult<EntityGraph> {
+    let acl = PathAclService::for_request(&state.db, vault_id, http_req).await?;
+    let (entities, relations) = tokio::join!(
+        EntityService::list_all_in_vault(&state.db, vault_id),
+        RelationService::list_for_vault(&state.db, vault_id)
+    );
+    let mut entities = entities?;
+    entities.retain(|e| acl.can_read(&e.path));
+    Ok(EntityGraph::build(entities, relations?, filter, keep))
+}
+
+/// Every entity with a date, read in the requested calendar and sorted,
+/// without entities the caller may not read.
+async fn get_timeline(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    query: web::Query<TimelineQuery>,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let calendar =
+        TimelineService::calendar(&vault.path, query.calendar.as_deref().unwrap_or(GREGORIAN))?;
+    let mut options = TimelineOptions::from_query(
+        &calendar,
+        query.start.as_deref(),
+        query.end.as_deref(),
+        query.types.as_deref(),
+        query.labels.as_deref(),
+        query.from.as_deref(),
+        query.to.as_deref(),
+    )?;
+    let entity_types = state.entity_types(&vault_id);
+    options.types = with_subtypes(&entity_types, &options.types).await;
+    let date_fields: HashMap<String, Vec<String>> = entity_types
+        .all()
+        .await
+        .into_iter()
+        .map(|t| {
+            let keys = t
+                .fields
+                .into_iter()
+                .filter(|f| f.field_type == FieldType::Date)
+                .map(|f| f.key)
                 .collect();
+            (t.id, keys)
+        })
+        .collect();
+
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    let (entities, relations) = tokio::join!(
+        EntityService::list_all_in_vault(&state.db, &vault_id),
+        RelationService::list_for_vault(&state.db, &vault_id)
+    );
+    let mut entities = entities?;
+    entities.retain(|e| acl.can_read(&e.path));
+    let timeline =
+        TimelineService::build(&entities, &relations?, &calendar, &options, &date_fields);
+    Ok(HttpResponse::Ok().json(timeline))
+}
+
+async fn get_calendars(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> AppResult<HttpResponse> {
+    let vault = state.db.get_vault(&path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(TimelineService::calendars(&vault.path)))
+}
+
+/// Rewrite the frontmatter of every entity of a type, e.g. after one of the
+/// type's fields was renamed, and re-index them with their relations. A dry
+/// run only reports the changes.
+async fn migrate_entity_type(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    body: web::Json<SchemaMigrationRequest>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, type_id) = path.into_inner();
+    SchemaMigrationService::validate(&body.operations)?;
+    let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    let mut entities = EntityService::list_all_in_vault(&state.db, &vault_id).await?;
+    entities.retain(|e| e.entity_type == type_id);
+    entities.sort_by(|a, b| a.path.cmp(&b.path));
+    if !body.dry_run {
+        note_git_author(&state, &http_req, &vault_id);
+    }
+
+    let mut report = SchemaMigrationReport {
+        entity_type: type_id,
+        dry_run: body.dry_run,
+        checked: entities.len(),
+        entities: Vec::new(),
+        migrated: 0,
+    };
+    for entity in entities {
+        let migrated = acl
+            .require(&entity.path, PathAccess::Write)
+            .and_then(|()| {
+                let file = FileService::resolve_path(&vault.path, &entity.path)?;
+                Ok(std::fs::read_to_string(file)?)
+            })
+            .and_then(|raw| SchemaMigrationService::migrate_document(&raw, &body.operations));
+        let mut migration = EntityMigration {
+            entity_id: entity.id,
+            path: entity.path,
+            changes: Vec::new(),
+            errors: Vec::new(),
+        };
+        match migrated {
+            Ok(document) => {
+                migration.changes = document.changes;
+                migration.errors = document.errors;
+                if !body.dry_run && !migration.changes.is_empty() {
+                    let written = async {
+                        let content = FileService::write_file(
+                            &vault.path,
+                            &migration.path,
+                            &document.markdown,
+                            None,
+                            None,
+                        )?;
+                        record_entity_write(
+                            &state,
+                            &vault_id,
+                            &migration.path,
+                            "modified",
+                            &content,
+                        )
+                        .await?;
+                        index_entity(
+                            &state,
+                            &vault_id,
+                            &migration.path,
+                            &document.markdown,
+                            &content,
+                        )
+                        .await
+                    };
+                    match written.await {
+                        Ok(_) => report.migrated += 1,
+                        Err(e) => migration.errors.push(e.to_string()),
+                    }
+                }
+            }
+            Err(e) => migration.errors.push(e.to_string()),
+        }
+        if !migration.changes.is_empty() || !migration.errors.is_empty() {
+            report.entities.push(migration);
+        }
+    }
+    Ok(HttpResponse::Ok().json(report))
+}
 
-            HttpResponse::Ok().json(json!({ "nodes": nodes, "edges": edges }))
+async fn get_vault_types(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let report = SchemaService::vault_types_report(
+        &vault_id,
+        &vault.path,
+        &state.entity_type_registry,
+        &state.relation_type_registry,
+    )
+    .await;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Create or replace a vault-local type. The body is the `[entity_type]` or
+/// `[relation_type]` table of the TOML file, as JSON.
+async fn put_vault_type(
+    path: web::Path<(String, VaultTypeKind, String)>,
+    state: web::Data<AppState>,
+    body: web::Json<serde_json::Value>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, kind, type_id) = path.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let invalid = |e: serde_json::Error| AppError::InvalidInput(format!("Invalid type: {e}"));
+    match kind {
+        VaultTypeKind::Entity => {
+            let body: EntityTypeBody =
+                serde_json::from_value(body.into_inner()).map_err(invalid)?;
+            SchemaService::save_vault_entity_type(&vault.path, &type_id, body)?;
         }
-        (Err(e), _) | (_, Err(e)) => {
-            tracing::error!("get_graph error: {e}");
-            HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }))
+        VaultTypeKind::Relation => {
+            let body: RelationTypeBody =
+                serde_json::from_value(body.into_inner()).map_err(invalid)?;
+            if body.name != type_id {
+                return Err(AppError::InvalidInput(format!(
+                    "Relation type name '{}' does not match '{type_id}'",
+                    body.name
+                )));
+            }
+            SchemaService::save_vault_relation_type(&vault.path, body)?;
         }
     }
+    vault_type_change(&state, &vault_id, &vault.path, kind, type_id).await
+}
+
+async fn delete_vault_type(
+    path: web::Path<(String, VaultTypeKind, String)>,
+    state: web::Data<AppState>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, kind, type_id) = path.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    SchemaService::delete_vault_type(&vault.path, kind, &type_id)?;
+    vault_type_change(&state, &vault_id, &vault.path, kind, type_id).await
+}
+
+/// Apply a type file change right away rather than waiting for the file
+/// watcher, and report what it affected.
+async fn vault_type_change(
+    state: &AppState,
+    vault_id: &str,
+    vault_path: &str,
+    kind: VaultTypeKind,
+    id: String,
+) -> AppResult<HttpResponse> {
+    let reindexed = ReindexService::reload_vault_types(
+        &state.db,
+        vault_id,
+        vault_path,
+        &state.entity_type_registry,
+        &state.relation_type_registry,
+    )
+    .await?;
+    let report = SchemaService::vault_types_report(
+        vault_id,
+        vault_path,
+        &state.entity_type_registry,
+        &state.relation_type_registry,
+    )
+    .await;
+    let conflicts = report
+        .conflicts
+        .into_iter()
+        .filter(|c| c.kind == kind && c.id == id)
+        .collect();
+    Ok(HttpResponse::Ok().json(VaultTypeChange {
+        kind,
+        id,
+        reindexed,
+        conflicts,
+    }))
 }
 
 async fn trigger_reindex(path: web::Path<String>, state: web::Data<AppState>) -> HttpResponse {
@@ -265,12 +1367,13 @@ struct EntityTemplateQuery {
 }
 
 async fn get_vault_entity_template(
-    _path: web::Path<String>,
+    path: web::Path<String>,
     query: web::Query<EntityTemplateQuery>,
     state: web::Data<AppState>,
 ) -> HttpResponse {
+    let vault_id = path.into_inner();
     match TemplateService::get_template(
-        &state.entity_type_registry,
+        &state.entity_types(&vault_id),
         &query.entity_type,
         &state.plugins_dir,
     )
@@ -296,11 +1399,29 @@ async fn get_entity_by_path(
     vault_path_param: web::Path<String>,
     query: web::Query<EntityByPathQuery>,
     state: web::Data<AppState>,
+    http_req: HttpRequest,
 ) -> HttpResponse {
     let vault_id = vault_path_param.into_inner();
-    match EntityService::get_by_path(&state.db, &vault_id, &query.path).await {
+    let acl = match PathAclService::for_request(&state.db, &vault_id, &http_req).await {
+        Ok(acl) => acl,
+        Err(e) => {
+            tracing::error!("get_entity_by_path acl error: {e}");
+            return HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }));
+        }
+    };
+    // An unreadable path answers like one without an entity.
+    let found = EntityService::get_by_path(&state.db, &vault_id, &query.path)
+        .await
+        .map(|entity| entity.filter(|e| acl.can_read(&e.path)));
+    match found {
         Ok(Some(entity)) => {
-            match build_entity_relations_payload(&state, &vault_id, &entity).await {
+            let mut entities = [entity];
+            if let Err(e) = with_computed(&state, &vault_id, &acl, &mut entities).await {
+                tracing::error!("get_entity_by_path computed fields error: {e}");
+                return HttpResponse::InternalServerError().json(json!({ "error": e.to_string() }));
+            }
+            let [entity] = entities;
+            match build_entity_relations_payload(&state, &vault_id, &entity, &acl).await {
                 Ok(relations) => HttpResponse::Ok().json(json!({
                     "entity": entity,
                     "relations": relations,
@@ -320,10 +1441,13 @@ async fn build_entity_relations_payload(
     state: &web::Data<AppState>,
     vault_id: &str,
     entity: &crate::services::entity_service::Entity,
+    acl: &PathAcl,
 ) -> anyhow::Result<Vec<serde_json::Value>> {
     let related_entities = EntityService::list_all_in_vault(&state.db, vault_id).await?;
+    // Relations to entities the caller may not read are left out
     let path_by_id: HashMap<_, _> = related_entities
         .into_iter()
+        .filter(|related| acl.can_read(&related.path))
         .map(|related| (related.id, related.path))
         .collect();
 
@@ -350,6 +1474,7 @@ async fn build_entity_relations_payload(
                 "directed": true,
                 "metadata": metadata,
                 "plugin_id": serde_json::Value::Null,
+                "direction": relation.direction,
                 "is_inverse": relation.direction == "inverse",
             }))
         })
@@ -1,33 +1,49 @@
 use crate::error::{AppError, AppResult};
+use crate::middleware::{AuditPath, AuthenticatedUser};
+use crate::models::trash::{
+    BulkRestoreTrashRequest, PurgeTrashRequest, TrashItem, TrashOperationFailure,
+};
+use crate::models::WsMessage;
 use crate::models::{
-    CreateFileRequest, CreateUploadSessionRequest, UpdateFileRequest, UploadSessionResponse,
+    CreateFileRequest, CreateUploadSessionRequest, EntityValidationMode, PathAccess,
+    UpdateFileRequest,
 };
+use crate::routes::entities::validation_failed;
 use crate::routes::vaults::AppState;
-use crate::services::{file_service::TrashItem, FileService, ImageService, WikiLinkResolver};
+use crate::services::archive_service::ArchiveProgress;
+use crate::services::{
+    ArchiveFormat, ArchiveService, EntityService, EntityValidationService, FileService,
+    ImageService, PathAcl, PathAclService, RenameStrategy, TrashService, UploadService,
+    WikiLinkResolver,
+};
+use actix_files::NamedFile;
 use actix_multipart::Multipart;
-use actix_web::http::header::{ETAG, IF_NONE_MATCH};
-use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use actix_web::body::SizedStream;
+use actix_web::http::header::{
+    ContentDisposition, DispositionParam, DispositionType, HttpDate, CONTENT_ENCODING,
+    CONTENT_RANGE, ETAG, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED,
+};
+use actix_web::http::StatusCode;
+use actix_web::{delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse};
 use chrono::Utc;
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use futures::{StreamExt, TryStreamExt};
-use std::io::{Cursor, Write};
+use futures::{Stream, StreamExt, TryStreamExt};
+use std::io::{Cursor, Read, Write};
 use std::path::Path;
 use std::time::UNIX_EPOCH;
+use tokio::io::AsyncReadExt;
 use uuid::Uuid;
-use walkdir::WalkDir;
-use zip::write::SimpleFileOptions;
-use zip::ZipWriter;
 
 #[get("/api/vaults/{vault_id}/files")]
 async fn get_file_tree(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<String>,
 ) -> AppResult<HttpResponse> {
     let vault_id = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
 
-    let tree = FileService::get_file_tree(&vault.path)?;
+    let tree = acl.filter_tree(FileService::get_file_tree(&vault.path)?);
     Ok(HttpResponse::Ok().json(tree))
 }
 
@@ -52,6 +68,13 @@ async fn read_file(
     Ok(HttpResponse::Ok().insert_header((ETAG, etag)).json(content))
 }
 
+/// Attribute the next automatic git commit of the vault to the caller.
+pub(crate) fn note_git_author(state: &AppState, http_req: &HttpRequest, vault_id: &str) {
+    if let Some(user) = http_req.extensions().get::<AuthenticatedUser>() {
+        state.git_autocommit.note_author(vault_id, &user.username);
+    }
+}
+
 fn build_file_etag(content: &crate::models::FileContent) -> String {
     format!("\"{:x}\"", content.modified.timestamp_millis())
 }
@@ -87,20 +110,99 @@ fn normalize_etag(value: &str) -> String {
     without_weak.trim_matches('"').to_string()
 }
 
+/// Serve a vault file for inline display. Supports `Range`/`If-Range` so
+/// media players can seek without downloading the whole file.
 #[get("/api/vaults/{vault_id}/raw/{file_path:.*}")]
 async fn serve_raw_file(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<(String, String)>,
 ) -> AppResult<HttpResponse> {
     let (vault_id, file_path) = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
 
-    let raw_content = FileService::read_raw_file(&vault.path, &file_path)?;
+    let full_path = FileService::resolve_existing_file(&vault.path, &file_path)?;
 
     // Determine MIME type based on file extension
     let mime_type = get_mime_type(&file_path);
 
-    Ok(HttpResponse::Ok().content_type(mime_type).body(raw_content))
+    stream_file(&http_req, &full_path, mime_type, DispositionType::Inline).await
+}
+
+/// Stream `full_path` with `Range`, `If-Range` and conditional-request
+/// handling. File contents are never held in memory as a whole.
+pub(crate) async fn stream_file(
+    req: &HttpRequest,
+    full_path: &Path,
+    content_type: &str,
+    disposition: DispositionType,
+) -> AppResult<HttpResponse> {
+    let filename = full_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("download")
+        .to_string();
+    let response = NamedFile::open_async(full_path)
+        .await?
+        .set_content_type(
+            content_type
+                .parse()
+                .unwrap_or(actix_web::mime::APPLICATION_OCTET_STREAM),
+        )
+        .set_content_disposition(ContentDisposition {
+            disposition,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .into_response(req);
+
+    if response.status() != StatusCode::PARTIAL_CONTENT || if_range_matches(req, &response) {
+        return Ok(response);
+    }
+
+    // The client's partial copy is stale: send the whole current file.
+    let mut full = HttpResponse::Ok();
+    for (name, value) in response.headers() {
+        if name != CONTENT_RANGE && name != CONTENT_ENCODING {
+            full.append_header((name.clone(), value.clone()));
+        }
+    }
+    let file = tokio::fs::File::open(full_path).await?;
+    let length = file.metadata().await?.len();
+    Ok(full.body(SizedStream::new(length, file_chunks(file))))
+}
+
+/// Whether a request's `If-Range` validator (if any) still matches the
+/// `ETag`/`Last-Modified` of the response about to be sent.
+fn if_range_matches(req: &HttpRequest, response: &HttpResponse) -> bool {
+    let Some(if_range) = req.headers().get(IF_RANGE).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    let if_range = if_range.trim();
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        // Only strong entity tags can validate a range.
+        let current = response.headers().get(ETAG).and_then(|v| v.to_str().ok());
+        !if_range.starts_with("W/") && current == Some(if_range)
+    } else {
+        let current = response
+            .headers()
+            .get(LAST_MODIFIED)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<HttpDate>().ok());
+        current.is_some() && if_range.parse::<HttpDate>().ok() == current
+    }
+}
+
+/// Read a file as a stream of 64 KiB chunks.
+fn file_chunks(file: tokio::fs::File) -> impl Stream<Item = std::io::Result<web::Bytes>> {
+    futures::stream::try_unfold(file, |mut file| async move {
+        let mut buf = vec![0u8; 64 * 1024];
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.truncate(n);
+        Ok(Some((web::Bytes::from(buf), file)))
+    })
 }
 
 #[derive(serde::Deserialize)]
@@ -152,7 +254,7 @@ async fn get_thumbnail(
         .body(thumbnail_data))
 }
 
-fn get_mime_type(file_path: &str) -> &'static str {
+pub(crate) fn get_mime_type(file_path: &str) -> &'static str {
     let path = Path::new(file_path);
     match path.extension().and_then(|s| s.to_str()) {
         Some("png") => "image/png",
@@ -212,14 +314,17 @@ struct FileMetadataResponse {
 #[get("/api/vaults/{vault_id}/changes")]
 async fn get_file_changes(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     query: web::Query<FileChangesQuery>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
 
     let since = query.since.unwrap_or(0).max(0);
-    let events = state.db.get_file_changes_since(&vault_id, since).await?;
+    let mut events = state.db.get_file_changes_since(&vault_id, since).await?;
+    events.retain(|event| acl.can_read(&event.path));
 
     Ok(HttpResponse::Ok().json(events))
 }
@@ -227,17 +332,24 @@ async fn get_file_changes(
 #[post("/api/vaults/{vault_id}/sync")]
 async fn sync_files(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     req: web::Json<SyncRequest>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
 
     let mut stale = Vec::new();
     let mut deleted = Vec::new();
     let mut server_newer = Vec::new();
 
     for entry in &req.files {
+        // Hidden paths look deleted, so their existence is not revealed.
+        if !acl.can_read(&entry.path) {
+            deleted.push(entry.path.clone());
+            continue;
+        }
         let full_path = match FileService::resolve_path(&vault.path, &entry.path) {
             Ok(path) => path,
             Err(_) => {
@@ -336,11 +448,28 @@ async fn get_file_metadata(
 #[post("/api/vaults/{vault_id}/files")]
 async fn create_file(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     req: web::Json<CreateFileRequest>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
+    http_req
+        .extensions_mut()
+        .insert(AuditPath(req.path.clone()));
     let vault = state.db.get_vault(&vault_id).await?;
+    PathAclService::for_request(&state.db, &vault_id, &http_req)
+        .await?
+        .require(&req.path, PathAccess::Write)?;
+    note_git_author(&state, &http_req, &vault_id);
+
+    if req.path.ends_with(".md") {
+        let content = req.content.as_deref().unwrap_or_default();
+        if let Some(response) =
+            check_entity_schema(&state, &vault_id, &req.path, content, None).await?
+        {
+            return Ok(response);
+        }
+    }
 
     let content = FileService::create_file(&vault.path, &req.path, req.content.as_deref())?;
     let etag = build_file_etag(&content);
@@ -378,6 +507,7 @@ async fn update_file(
 ) -> AppResult<HttpResponse> {
     let (vault_id, file_path) = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    note_git_author(&state, &http_req, &vault_id);
 
     // Support If-Match header for ETag-based conflict detection.
     // When present, read the current file and compare ETags before writing.
@@ -402,6 +532,20 @@ async fn update_file(
         }
     }
 
+    if file_path.ends_with(".md") {
+        if let Some(response) = check_entity_schema(
+            &state,
+            &vault_id,
+            &file_path,
+            &req.content,
+            req.frontmatter.as_ref(),
+        )
+        .await?
+        {
+            return Ok(response);
+        }
+    }
+
     let content = FileService::write_file(
         &vault.path,
         &file_path,
@@ -435,15 +579,91 @@ async fn update_file(
         .json(content))
 }
 
+/// Validate the entity frontmatter of a note about to be written, per the
+/// vault's validation mode. `frontmatter` replaces the one in `content`
+/// when given. Returns the 422 response to send when the write must be
+/// refused.
+async fn check_entity_schema(
+    state: &AppState,
+    vault_id: &str,
+    file_path: &str,
+    content: &str,
+    frontmatter: Option<&serde_json::Value>,
+) -> AppResult<Option<HttpResponse>> {
+    let mode = state.db.get_vault_entity_validation(vault_id).await?;
+    if mode == EntityValidationMode::Off {
+        return Ok(None);
+    }
+    let frontmatter = match frontmatter {
+        Some(frontmatter) => Some(frontmatter.clone()),
+        None => EntityService::parse_frontmatter(content),
+    };
+    let Some(frontmatter) = frontmatter else {
+        return Ok(None);
+    };
+    let errors = EntityValidationService::validate_frontmatter(
+        &state.db,
+        &state.entity_types(vault_id),
+        vault_id,
+        &frontmatter,
+    )
+    .await?;
+    if errors.is_empty() {
+        return Ok(None);
+    }
+    if mode == EntityValidationMode::Warn {
+        for error in &errors {
+            tracing::warn!("Entity at {file_path}: {}", error.message);
+        }
+        return Ok(None);
+    }
+    Ok(Some(validation_failed(file_path, errors)))
+}
+
+/// [`check_entity_schema`] for an upload, before its temp file is moved to
+/// `target` in the vault.
+async fn check_uploaded_note(
+    state: &AppState,
+    vault_id: &str,
+    vault_path: &str,
+    session_id: &str,
+    target: &str,
+) -> AppResult<Option<HttpResponse>> {
+    if !target.ends_with(".md") {
+        return Ok(None);
+    }
+    let bytes = std::fs::read(FileService::upload_session_temp_path(
+        vault_path, session_id,
+    ))?;
+    check_entity_schema(
+        state,
+        vault_id,
+        target,
+        &String::from_utf8_lossy(&bytes),
+        None,
+    )
+    .await
+}
+
 #[delete("/api/vaults/{vault_id}/files/{file_path:.*}")]
 async fn delete_file(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<(String, String)>,
 ) -> AppResult<HttpResponse> {
     let (vault_id, file_path) = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    note_git_author(&state, &http_req, &vault_id);
 
-    FileService::delete_file(&vault.path, &file_path)?;
+    let deleted_by = request_user_id(&http_req);
+    TrashService::trash(
+        &state.db,
+        &vault_id,
+        &vault.path,
+        &file_path,
+        deleted_by.as_deref(),
+    )
+    .await?;
 
     state
         .db
@@ -466,11 +686,18 @@ async fn delete_file(
 #[post("/api/vaults/{vault_id}/directories")]
 async fn create_directory(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     req: web::Json<CreateFileRequest>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
+    http_req
+        .extensions_mut()
+        .insert(AuditPath(req.path.clone()));
     let vault = state.db.get_vault(&vault_id).await?;
+    PathAclService::for_request(&state.db, &vault_id, &http_req)
+        .await?
+        .require(&req.path, PathAccess::Write)?;
 
     FileService::create_directory(&vault.path, &req.path)?;
 
@@ -482,11 +709,13 @@ async fn create_directory(
 #[post("/api/vaults/{vault_id}/rename")]
 async fn rename_file(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     req: web::Json<serde_json::Value>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    note_git_author(&state, &http_req, &vault_id);
 
     let from = req["from"]
         .as_str()
@@ -498,13 +727,15 @@ async fn rename_file(
         .ok_or(crate::error::AppError::InvalidInput(
             "Missing 'to' field".to_string(),
         ))?;
+    http_req
+        .extensions_mut()
+        .insert(AuditPath(from.to_string()));
 
-    let strategy_str = req["strategy"].as_str().unwrap_or("fail");
-    let strategy = match strategy_str {
-        "overwrite" => crate::services::RenameStrategy::Overwrite,
-        "autorename" => crate::services::RenameStrategy::AutoRename,
-        _ => crate::services::RenameStrategy::Fail,
-    };
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    acl.require(from, PathAccess::Write)?;
+    acl.require(to, PathAccess::Write)?;
+
+    let strategy = RenameStrategy::from_name(req["strategy"].as_str().unwrap_or("fail"));
 
     let new_path = FileService::rename(&vault.path, from, to, strategy)?;
 
@@ -541,12 +772,15 @@ async fn rename_file(
 #[post("/api/vaults/{vault_id}/upload")]
 async fn upload_files(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     mut payload: Multipart,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    note_git_author(&state, &http_req, &vault_id);
 
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
     let mut uploaded_files = Vec::new();
     let max_file_size = 100 * 1024 * 1024; // 100MB limit
     let mut requested_target_path = String::new();
@@ -577,6 +811,11 @@ async fn upload_files(
             continue;
         }
 
+        acl.require(
+            &join_vault_path(&requested_target_path, &filename),
+            PathAccess::Write,
+        )?;
+
         let session_id = Uuid::new_v4().to_string();
         FileService::create_upload_session_temp(&vault.path, &session_id)?;
 
@@ -599,6 +838,14 @@ async fn upload_files(
             FileService::append_upload_chunk(&vault.path, &session_id, &data)?;
         }
 
+        let target = join_vault_path(&requested_target_path, &filename);
+        if let Some(response) =
+            check_uploaded_note(&state, &vault_id, &vault.path, &session_id, &target).await?
+        {
+            let _ = FileService::delete_upload_session_temp(&vault.path, &session_id);
+            return Ok(response);
+        }
+
         let final_path_str = FileService::finalize_upload_session(
             &vault.path,
             &session_id,
@@ -631,6 +878,7 @@ async fn upload_files(
 #[get("/api/vaults/{vault_id}/download/{file_path:.*}")]
 async fn download_file(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<(String, String)>,
 ) -> AppResult<HttpResponse> {
     let (vault_id, file_path) = path.into_inner();
@@ -648,122 +896,202 @@ async fn download_file(
         ));
     }
 
-    let content = std::fs::read(&full_path)?;
-    let filename = full_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("download");
+    stream_file(
+        &http_req,
+        &full_path,
+        "application/octet-stream",
+        DispositionType::Attachment,
+    )
+    .await
+}
 
-    Ok(HttpResponse::Ok()
-        .content_type("application/octet-stream")
-        .insert_header((
-            "Content-Disposition",
-            format!("attachment; filename=\"{}\"", filename),
-        ))
-        .body(content))
+/// Body of `download-zip` / `download-tar`. Both the body and `paths` are
+/// optional; without `paths` the whole vault (minus trash and `.git`) is
+/// archived.
+#[derive(Debug, Default, serde::Deserialize)]
+struct DownloadArchiveRequest {
+    #[serde(default)]
+    paths: Option<Vec<String>>,
+    /// Identifier used in `ArchiveProgress` messages, so a client can match
+    /// progress to its request before the response arrives. Generated when
+    /// omitted; always echoed in `X-Archive-Id`.
+    #[serde(default)]
+    archive_id: Option<String>,
 }
 
 #[post("/api/vaults/{vault_id}/download-zip")]
 async fn download_zip(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
-    req: web::Json<serde_json::Value>,
+    req: Option<web::Json<DownloadArchiveRequest>>,
 ) -> AppResult<HttpResponse> {
-    let vault_id = vault_id.into_inner();
-    let vault = state.db.get_vault(&vault_id).await?;
-
-    // Get paths array from request
-    let paths = req["paths"]
-        .as_array()
-        .ok_or(AppError::InvalidInput("Missing 'paths' array".to_string()))?;
+    let vault = state.db.get_vault(&vault_id.into_inner()).await?;
+    let acl = PathAclService::for_request(&state.db, &vault.id, &http_req).await?;
+    let req = req.map(|r| r.into_inner()).unwrap_or_default();
+    stream_archive(&state, vault, ArchiveFormat::Zip, req, &acl)
+}
 
-    if paths.is_empty() {
+/// Minimum delay between two `ArchiveProgress` messages for one archive.
+const ARCHIVE_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Start writing an archive on a blocking thread and return a response
+/// that streams it as it is produced.
+fn stream_archive(
+    state: &AppState,
+    vault: crate::models::Vault,
+    format: ArchiveFormat,
+    req: DownloadArchiveRequest,
+    acl: &PathAcl,
+) -> AppResult<HttpResponse> {
+    if req.paths.as_ref().is_some_and(|paths| paths.is_empty()) {
         return Err(AppError::InvalidInput("No paths provided".to_string()));
     }
 
-    // Create zip file in memory
-    let mut buffer = Cursor::new(Vec::new());
-    {
-        let mut zip = ZipWriter::new(&mut buffer);
-        let options = SimpleFileOptions::default()
-            .compression_method(zip::CompressionMethod::Deflated)
-            .compression_level(Some(6));
-
-        for path_value in paths {
-            let file_path = path_value
-                .as_str()
-                .ok_or(AppError::InvalidInput("Invalid path in array".to_string()))?;
-
-            let full_path = FileService::resolve_path(&vault.path, file_path)?;
+    let mut entries = ArchiveService::collect_entries(&vault.path, req.paths.as_deref())?;
+    entries.retain(|entry| acl.can_read(&entry.name));
 
-            if !full_path.exists() {
-                continue; // Skip non-existent paths
-            }
+    // Generate filename
+    let archive_filename = match req.paths.as_deref() {
+        Some([single_path]) => {
+            let name = Path::new(single_path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("download");
+            format!("{}.{}", name, format.extension())
+        }
+        Some(paths) => format!("{}_files.{}", paths.len(), format.extension()),
+        None => format!("{}.{}", vault.name, format.extension()),
+    };
 
-            if full_path.is_file() {
-                // Add single file
-                let content = std::fs::read(&full_path)?;
-                zip.start_file(file_path, options)?;
-                zip.write_all(&content)?;
-            } else if full_path.is_dir() {
-                // Add directory recursively
-                for entry in WalkDir::new(&full_path).into_iter().filter_map(|e| e.ok()) {
-                    let entry_path = entry.path();
-                    if entry_path.is_file() {
-                        // Get relative path from vault root
-                        let relative_path = entry_path
-                            .strip_prefix(&vault.path)
-                            .map_err(|_| AppError::InternalError("Path error".to_string()))?;
-
-                        let zip_path = relative_path
-                            .to_str()
-                            .ok_or(AppError::InternalError("Invalid UTF-8 in path".to_string()))?;
-
-                        let content = std::fs::read(entry_path)?;
-                        zip.start_file(zip_path, options)?;
-                        zip.write_all(&content)?;
-                    }
+    let archive_id = req.archive_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<web::Bytes>>(8);
+    let ws_broadcaster = state.ws_broadcaster.clone();
+    let vault_id = vault.id.clone();
+    let progress_id = archive_id.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let progress_message =
+            |progress: &ArchiveProgress, finished: bool, error: Option<String>| {
+                WsMessage::ArchiveProgress {
+                    vault_id: vault_id.clone(),
+                    archive_id: progress_id.clone(),
+                    files_done: progress.files_done as u64,
+                    files_total: progress.files_total as u64,
+                    bytes_done: progress.bytes_done,
+                    bytes_total: progress.bytes_total,
+                    finished,
+                    error,
                 }
-            }
-        }
+            };
 
-        zip.finish()?;
-    }
+        let mut latest = ArchiveProgress {
+            files_total: entries.len(),
+            bytes_total: entries.iter().map(|e| e.size).sum(),
+            ..Default::default()
+        };
+        let mut last_sent = std::time::Instant::now();
+        let result = ArchiveService::write_archive(
+            format,
+            &entries,
+            ChannelWriter::new(tx.clone()),
+            |progress| {
+                latest = *progress;
+                if last_sent.elapsed() >= ARCHIVE_PROGRESS_INTERVAL {
+                    last_sent = std::time::Instant::now();
+                    let _ = ws_broadcaster.send(progress_message(progress, false, None));
+                }
+            },
+        );
 
-    let zip_data = buffer.into_inner();
+        let error = result.err().map(|e| {
+            tracing::warn!("Archive {} aborted: {}", progress_id, e);
+            let message = e.to_string();
+            // Surface the failure to the HTTP client as a broken body.
+            let _ = tx.blocking_send(Err(e));
+            message
+        });
+        let _ = ws_broadcaster.send(progress_message(&latest, true, error));
+    });
 
-    // Generate filename
-    let zip_filename = if paths.len() == 1 {
-        let single_path = paths[0].as_str().unwrap_or("download");
-        let name = Path::new(single_path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("download");
-        format!("{}.zip", name)
-    } else {
-        format!("{}_files.zip", paths.len())
-    };
+    let body = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
 
     Ok(HttpResponse::Ok()
-        .content_type("application/zip")
+        .content_type(format.content_type())
         .insert_header((
             "Content-Disposition",
-            format!("attachment; filename=\"{}\"", zip_filename),
+            format!("attachment; filename=\"{}\"", archive_filename),
         ))
-        .body(zip_data))
+        .insert_header(("X-Archive-Id", archive_id))
+        .streaming(body))
+}
+
+/// `Write` adapter that forwards archive bytes to a streaming response in
+/// 64 KiB chunks. Writes fail once the client has gone away, which aborts
+/// the archive.
+struct ChannelWriter {
+    tx: tokio::sync::mpsc::Sender<std::io::Result<web::Bytes>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelWriter {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    fn new(tx: tokio::sync::mpsc::Sender<std::io::Result<web::Bytes>>) -> Self {
+        Self {
+            tx,
+            buf: Vec::with_capacity(Self::CHUNK_SIZE),
+        }
+    }
+
+    fn send_buffered(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(Self::CHUNK_SIZE));
+        self.tx
+            .blocking_send(Ok(web::Bytes::from(chunk)))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "client disconnected"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= Self::CHUNK_SIZE {
+            self.send_buffered()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.send_buffered()
+    }
 }
 
 #[get("/api/vaults/{vault_id}/random")]
 async fn get_random_file(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
 
     // Verify vault exists
     state.db.get_vault(&vault_id).await?;
-
-    let random_file = state.search_index.get_random_file(&vault_id)?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+
+    // Re-draw a few times when the pick is hidden by a path ACL.
+    let mut random_file = None;
+    for _ in 0..16 {
+        random_file = state.search_index.get_random_file(&vault_id)?;
+        if random_file.as_deref().is_none_or(|path| acl.can_read(path)) {
+            break;
+        }
+        random_file = None;
+    }
 
     if let Some(path) = random_file {
         Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -784,6 +1112,7 @@ struct DailyNoteRequest {
 #[post("/api/vaults/{vault_id}/daily")]
 async fn get_daily_note(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     req: web::Json<DailyNoteRequest>,
 ) -> AppResult<HttpResponse> {
@@ -791,11 +1120,14 @@ async fn get_daily_note(
     let vault = state.db.get_vault(&vault_id).await?;
 
     let file_path = format!("{}.md", req.date);
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    acl.require(&file_path, PathAccess::Read)?;
 
     // Try to read the file
     match FileService::read_file(&vault.path, &file_path) {
         Ok(content) => Ok(HttpResponse::Ok().json(content)),
         Err(AppError::NotFound(_)) => {
+            acl.require(&file_path, PathAccess::Write)?;
             // Create the file if it doesn't exist
             let header = format!("# {}\n\n", req.date);
             let content = FileService::create_file(&vault.path, &file_path, Some(&header))?;
@@ -850,17 +1182,20 @@ pub struct ResolveWikiLinkResponse {
 #[post("/api/vaults/{vault_id}/resolve-link")]
 async fn resolve_wiki_link(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     req: web::Json<ResolveWikiLinkRequest>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
 
     let result = if let Some(current_file) = &req.current_file {
         WikiLinkResolver::resolve_relative(&vault.path, &req.link, current_file)?
     } else {
         WikiLinkResolver::resolve(&vault.path, &req.link)?
     };
+    let result = result.retain_readable(&req.link, |path| acl.can_read(path));
 
     let response = ResolveWikiLinkResponse {
         path: result.path,
@@ -890,11 +1225,13 @@ pub struct BatchResolveResponse {
 #[post("/api/vaults/{vault_id}/resolve-links")]
 async fn batch_resolve_wiki_links(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     req: web::Json<BatchResolveRequest>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
 
     let mut resolved = std::collections::HashMap::new();
 
@@ -904,6 +1241,7 @@ async fn batch_resolve_wiki_links(
         } else {
             WikiLinkResolver::resolve(&vault.path, link)?
         };
+        let result = result.retain_readable(link, |path| acl.can_read(path));
 
         resolved.insert(
             link.clone(),
@@ -922,12 +1260,14 @@ async fn batch_resolve_wiki_links(
 #[get("/api/vaults/{vault_id}/files-html")]
 async fn get_file_tree_html(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<String>,
 ) -> AppResult<HttpResponse> {
     let vault_id = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
 
-    let tree = FileService::get_file_tree(&vault.path)?;
+    let tree = acl.filter_tree(FileService::get_file_tree(&vault.path)?);
 
     let html = render_file_tree_to_html(&tree);
 
@@ -1018,6 +1358,9 @@ struct FinishUploadRequest {
     path: String,
     #[serde(default)]
     conflict: ConflictStrategy,
+    /// Hex SHA-256 of the complete file; overrides the one given at creation.
+    #[serde(default)]
+    sha256: Option<String>,
 }
 
 #[derive(serde::Deserialize)]
@@ -1062,34 +1405,102 @@ fn conflict_rename(base: &Path) -> std::path::PathBuf {
     unreachable!()
 }
 
+/// Vault-relative path of `filename` inside `dir` (which may be empty).
+fn join_vault_path(dir: &str, filename: &str) -> String {
+    let dir = dir.trim_matches('/');
+    if dir.is_empty() {
+        filename.to_string()
+    } else {
+        format!("{}/{}", dir, filename)
+    }
+}
+
+/// Id of the authenticated caller, if any.
+fn request_user_id(http_req: &HttpRequest) -> Option<String> {
+    http_req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| user.user_id.clone())
+}
+
 #[post("/api/vaults/{vault_id}/upload-sessions")]
 async fn create_upload_session(
     state: web::Data<AppState>,
+    config: web::Data<crate::config::AppConfig>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     req: web::Json<CreateUploadSessionRequest>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    PathAclService::for_request(&state.db, &vault_id, &http_req)
+        .await?
+        .require(
+            &join_vault_path(&req.path, &req.filename),
+            PathAccess::Write,
+        )?;
+
+    let session = UploadService::create(
+        &state.db,
+        &vault_id,
+        &vault.path,
+        request_user_id(&http_req).as_deref(),
+        &req,
+        config.vault.upload_session_ttl_hours,
+    )
+    .await?;
 
-    let session_id = Uuid::new_v4().to_string();
-    FileService::create_upload_session_temp(&vault.path, &session_id)?;
+    Ok(HttpResponse::Created().json(session))
+}
 
-    Ok(HttpResponse::Created().json(UploadSessionResponse {
-        session_id,
-        uploaded_bytes: 0,
-        total_size: req.total_size,
-    }))
+#[derive(serde::Deserialize)]
+struct UploadChunkQuery {
+    /// Byte offset of the chunk; appended after the last received byte when omitted.
+    offset: Option<u64>,
 }
 
+/// PUT /api/vaults/{vault_id}/upload-sessions/{session_id}?offset=N
+///
+/// Chunks may be sent in any order and in parallel. An `X-Chunk-Sha256`
+/// header (hex) is verified against the body before it is stored.
 #[put("/api/vaults/{vault_id}/upload-sessions/{session_id}")]
 async fn upload_chunk(
     state: web::Data<AppState>,
+    config: web::Data<crate::config::AppConfig>,
+    http_req: HttpRequest,
     path: web::Path<(String, String)>,
+    query: web::Query<UploadChunkQuery>,
     body: web::Bytes,
 ) -> AppResult<HttpResponse> {
     let (vault_id, session_id) = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
-    let uploaded_bytes = FileService::append_upload_chunk(&vault.path, &session_id, &body)?;
+    let session = UploadService::open(
+        &state.db,
+        &vault_id,
+        &session_id,
+        request_user_id(&http_req).as_deref(),
+    )
+    .await?;
+    let chunk_sha = http_req
+        .headers()
+        .get("X-Chunk-Sha256")
+        .map(|value| {
+            value
+                .to_str()
+                .map_err(|_| AppError::InvalidInput("Invalid X-Chunk-Sha256 header".to_string()))
+        })
+        .transpose()?;
+
+    let uploaded_bytes = UploadService::write_chunk(
+        &state.db,
+        &vault.path,
+        &session,
+        query.offset,
+        &body,
+        chunk_sha,
+        config.vault.upload_session_ttl_hours,
+    )
+    .await?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "uploaded_bytes": uploaded_bytes
@@ -1099,26 +1510,67 @@ async fn upload_chunk(
 #[get("/api/vaults/{vault_id}/upload-sessions/{session_id}")]
 async fn get_upload_status(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<(String, String)>,
 ) -> AppResult<HttpResponse> {
     let (vault_id, session_id) = path.into_inner();
-    let vault = state.db.get_vault(&vault_id).await?;
-    let uploaded_bytes = FileService::get_upload_session_size(&vault.path, &session_id)?;
+    state.db.get_vault(&vault_id).await?;
+    let session = UploadService::open(
+        &state.db,
+        &vault_id,
+        &session_id,
+        request_user_id(&http_req).as_deref(),
+    )
+    .await?;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "session_id": session_id,
-        "uploaded_bytes": uploaded_bytes
-    })))
+    Ok(HttpResponse::Ok().json(UploadService::status(&state.db, &session).await?))
+}
+
+/// Abandon an upload and delete the bytes received so far.
+#[delete("/api/vaults/{vault_id}/upload-sessions/{session_id}")]
+async fn cancel_upload_session(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, session_id) = path.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let session = UploadService::open(
+        &state.db,
+        &vault_id,
+        &session_id,
+        request_user_id(&http_req).as_deref(),
+    )
+    .await?;
+    UploadService::discard(&state.db, &vault.path, &session.id).await?;
+    Ok(HttpResponse::NoContent().finish())
 }
 
 #[post("/api/vaults/{vault_id}/upload-sessions/{session_id}/finish")]
 async fn finish_upload_session(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<(String, String)>,
     req: web::Json<FinishUploadRequest>,
 ) -> AppResult<HttpResponse> {
     let (vault_id, session_id) = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    let session = UploadService::open(
+        &state.db,
+        &vault_id,
+        &session_id,
+        request_user_id(&http_req).as_deref(),
+    )
+    .await?;
+    PathAclService::for_request(&state.db, &vault_id, &http_req)
+        .await?
+        .require(
+            &join_vault_path(&req.path, &req.filename),
+            PathAccess::Write,
+        )?;
+    note_git_author(&state, &http_req, &vault_id);
+
+    UploadService::verify_complete(&state.db, &vault.path, &session, req.sha256.as_deref()).await?;
 
     // Resolve where the file would land before finalizing so we can apply conflict logic.
     let safe_target_dir = if req.path.is_empty() {
@@ -1153,12 +1605,20 @@ async fn finish_upload_session(
         (req.path.clone(), req.filename.clone())
     };
 
+    let target = join_vault_path(&effective_path, &effective_filename);
+    if let Some(response) =
+        check_uploaded_note(&state, &vault_id, &vault.path, &session_id, &target).await?
+    {
+        return Ok(response);
+    }
+
     let final_path_str = FileService::finalize_upload_session(
         &vault.path,
         &session_id,
         &effective_path,
         &effective_filename,
     )?;
+    state.db.delete_upload_session(&session_id).await?;
 
     // Update index if markdown
     if final_path_str.ends_with(".md") {
@@ -1186,12 +1646,15 @@ async fn finish_upload_session(
 #[post("/api/vaults/{vault_id}/import-archive")]
 async fn import_archive(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     query: web::Query<ImportArchiveQuery>,
     body: web::Bytes,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    note_git_author(&state, &http_req, &vault_id);
 
     let target_dir = if query.path.is_empty() {
         std::path::PathBuf::from(&vault.path)
@@ -1199,10 +1662,6 @@ async fn import_archive(
         FileService::resolve_path(&vault.path, &query.path)?
     };
 
-    if !target_dir.exists() {
-        std::fs::create_dir_all(&target_dir)?;
-    }
-
     // Detect archive type from the query parameter (or sniff the magic bytes).
     let archive_type = query.archive_type.to_ascii_lowercase();
     let is_zip =
@@ -1222,6 +1681,29 @@ async fn import_archive(
         ));
     }
 
+    // Check every entry before extracting anything, so a refused archive
+    // leaves the vault untouched.
+    let validate =
+        state.db.get_vault_entity_validation(&vault_id).await? != EntityValidationMode::Off;
+    if validate || !acl.is_unrestricted() {
+        for (name, note) in archive_files(&body, is_zip, is_tar_gz)? {
+            let target = join_vault_path(&query.path, &name);
+            acl.require(&target, PathAccess::Write)?;
+            let Some(content) = note.filter(|_| validate) else {
+                continue;
+            };
+            if let Some(response) =
+                check_entity_schema(&state, &vault_id, &target, &content, None).await?
+            {
+                return Ok(response);
+            }
+        }
+    }
+
+    if !target_dir.exists() {
+        std::fs::create_dir_all(&target_dir)?;
+    }
+
     let mut extracted: Vec<String> = Vec::new();
 
     if is_zip {
@@ -1324,126 +1806,261 @@ async fn import_archive(
     })))
 }
 
+/// Name of every file an archive import would extract, with the content
+/// of those that are notes.
+fn archive_files(
+    body: &[u8],
+    is_zip: bool,
+    is_tar_gz: bool,
+) -> AppResult<Vec<(String, Option<String>)>> {
+    let skipped = |name: &str| name.starts_with('/') || name.contains("..");
+    let mut files = Vec::new();
+    if is_zip {
+        let mut archive = zip::ZipArchive::new(Cursor::new(body))
+            .map_err(|e| AppError::InvalidInput(format!("Invalid zip: {}", e)))?;
+        for i in 0..archive.len() {
+            let mut zf = archive
+                .by_index(i)
+                .map_err(|e| AppError::InternalError(format!("Zip read error: {}", e)))?;
+            let name = zf.name().to_string();
+            if zf.is_dir() || skipped(&name) {
+                continue;
+            }
+            let note = if name.ends_with(".md") {
+                let mut bytes = Vec::new();
+                zf.read_to_end(&mut bytes)?;
+                Some(String::from_utf8_lossy(&bytes).into_owned())
+            } else {
+                None
+            };
+            files.push((name, note));
+        }
+    } else {
+        let reader: Box<dyn std::io::Read> = if is_tar_gz {
+            Box::new(flate2::read::GzDecoder::new(Cursor::new(body)))
+        } else {
+            Box::new(Cursor::new(body))
+        };
+        let mut tar = tar::Archive::new(reader);
+        for entry in tar
+            .entries()
+            .map_err(|e| AppError::InvalidInput(format!("Invalid tar: {}", e)))?
+        {
+            let mut entry =
+                entry.map_err(|e| AppError::InternalError(format!("Tar read error: {}", e)))?;
+            let name = entry
+                .path()
+                .map_err(|e| AppError::InternalError(format!("Tar path error: {}", e)))?
+                .to_string_lossy()
+                .to_string();
+            if entry.header().entry_type().is_dir() || skipped(&name) {
+                continue;
+            }
+            let note = if name.ends_with(".md") {
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes)?;
+                Some(String::from_utf8_lossy(&bytes).into_owned())
+            } else {
+                None
+            };
+            files.push((name, note));
+        }
+    }
+    Ok(files)
+}
+
 /// POST /api/vaults/{vault_id}/download-tar
 ///
 /// Same semantics as `download-zip` but produces a `.tar.gz` archive.
 #[post("/api/vaults/{vault_id}/download-tar")]
 async fn download_tar(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
-    req: web::Json<serde_json::Value>,
+    req: Option<web::Json<DownloadArchiveRequest>>,
 ) -> AppResult<HttpResponse> {
-    let vault_id = vault_id.into_inner();
-    let vault = state.db.get_vault(&vault_id).await?;
-
-    let paths = req["paths"]
-        .as_array()
-        .ok_or(AppError::InvalidInput("Missing 'paths' array".to_string()))?;
-
-    if paths.is_empty() {
-        return Err(AppError::InvalidInput("No paths provided".to_string()));
-    }
-
-    // Build tar.gz in memory.
-    let buf = Vec::new();
-    let gz = GzEncoder::new(buf, Compression::default());
-    let mut tar_builder = tar::Builder::new(gz);
-
-    for path_value in paths {
-        let file_path = path_value
-            .as_str()
-            .ok_or(AppError::InvalidInput("Invalid path in array".to_string()))?;
-
-        let full_path = FileService::resolve_path(&vault.path, file_path)?;
-
-        if !full_path.exists() {
-            continue;
-        }
-
-        if full_path.is_file() {
-            tar_builder
-                .append_path_with_name(&full_path, file_path)
-                .map_err(|e| AppError::InternalError(format!("Tar append error: {}", e)))?;
-        } else if full_path.is_dir() {
-            for entry in WalkDir::new(&full_path).into_iter().filter_map(|e| e.ok()) {
-                let entry_path = entry.path();
-                if entry_path.is_file() {
-                    let relative = entry_path
-                        .strip_prefix(&vault.path)
-                        .map_err(|_| AppError::InternalError("Path error".to_string()))?;
-                    let tar_path = relative
-                        .to_str()
-                        .ok_or(AppError::InternalError("Invalid UTF-8 in path".to_string()))?;
-                    tar_builder
-                        .append_path_with_name(entry_path, tar_path)
-                        .map_err(|e| AppError::InternalError(format!("Tar append error: {}", e)))?;
-                }
-            }
-        }
-    }
-
-    let gz = tar_builder
-        .into_inner()
-        .map_err(|e| AppError::InternalError(format!("Tar finish error: {}", e)))?;
-    let tar_gz_data = gz
-        .finish()
-        .map_err(|e| AppError::InternalError(format!("Gzip finish error: {}", e)))?;
-
-    let tar_filename = if paths.len() == 1 {
-        let single_path = paths[0].as_str().unwrap_or("download");
-        let name = Path::new(single_path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("download");
-        format!("{}.tar.gz", name)
-    } else {
-        format!("{}_files.tar.gz", paths.len())
-    };
-
-    Ok(HttpResponse::Ok()
-        .content_type("application/gzip")
-        .insert_header((
-            "Content-Disposition",
-            format!("attachment; filename=\"{}\"", tar_filename),
-        ))
-        .body(tar_gz_data))
+    let vault = state.db.get_vault(&vault_id.into_inner()).await?;
+    let acl = PathAclService::for_request(&state.db, &vault.id, &http_req).await?;
+    let req = req.map(|r| r.into_inner()).unwrap_or_default();
+    stream_archive(&state, vault, ArchiveFormat::TarGz, req, &acl)
 }
 
 // ── Trash endpoints ─────────────────────────────────────────────────────────
 
+#[derive(serde::Deserialize)]
+struct RestoreTrashQuery {
+    strategy: Option<String>,
+}
+
 /// List the files currently sitting in the vault's `.trash/` folder.
 #[get("/api/vaults/{vault_id}/trash")]
 async fn list_trash(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<String>,
 ) -> AppResult<HttpResponse> {
     let vault_id = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
-    let items: Vec<TrashItem> = FileService::list_trash(&vault.path)?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    let mut items: Vec<TrashItem> = TrashService::list(&state.db, &vault_id, &vault.path).await?;
+    items.retain(|item| acl.can_read(&item.original_path));
     Ok(HttpResponse::Ok().json(items))
 }
 
+/// Split `trash_names` into those whose original path the caller may
+/// write and failures for the rest. Names not in the trash pass through
+/// for the trash service to report.
+async fn writable_trash_names(
+    state: &AppState,
+    acl: &PathAcl,
+    vault_id: &str,
+    vault_path: &str,
+    trash_names: &[String],
+) -> AppResult<(Vec<String>, Vec<TrashOperationFailure>)> {
+    if acl.is_unrestricted() {
+        return Ok((trash_names.to_vec(), Vec::new()));
+    }
+    let original_paths: std::collections::HashMap<String, String> =
+        TrashService::list(&state.db, vault_id, vault_path)
+            .await?
+            .into_iter()
+            .map(|item| (item.trash_name, item.original_path))
+            .collect();
+
+    let mut allowed = Vec::new();
+    let mut denied = Vec::new();
+    for trash_name in trash_names {
+        let access = original_paths
+            .get(trash_name)
+            .map(|original| acl.require(original, PathAccess::Write));
+        match access {
+            Some(Err(e)) => denied.push(TrashOperationFailure {
+                trash_name: trash_name.clone(),
+                error: e.to_string(),
+            }),
+            _ => allowed.push(trash_name.clone()),
+        }
+    }
+    Ok((allowed, denied))
+}
+
+/// Fail unless the caller may write the original path of `trash_name`.
+async fn require_writable_trash_item(
+    state: &AppState,
+    http_req: &HttpRequest,
+    vault_id: &str,
+    vault_path: &str,
+    trash_name: &str,
+) -> AppResult<()> {
+    let acl = PathAclService::for_request(&state.db, vault_id, http_req).await?;
+    let names = [trash_name.to_string()];
+    let (_, denied) = writable_trash_names(state, &acl, vault_id, vault_path, &names).await?;
+    match denied.into_iter().next() {
+        Some(failure) => Err(AppError::Forbidden(failure.error)),
+        None => Ok(()),
+    }
+}
+
 /// Restore a trashed file to its original vault location.
+///
+/// `?strategy=overwrite|autorename` decides what happens when the original
+/// path is taken; by default the restore fails with 409.
 #[post("/api/vaults/{vault_id}/trash/{trash_name}/restore")]
 async fn restore_trash_file(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<(String, String)>,
+    query: web::Query<RestoreTrashQuery>,
 ) -> AppResult<HttpResponse> {
     let (vault_id, trash_name) = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
-    FileService::restore_file(&vault.path, &trash_name)?;
-    Ok(HttpResponse::NoContent().finish())
+    require_writable_trash_item(&state, &http_req, &vault_id, &vault.path, &trash_name).await?;
+    note_git_author(&state, &http_req, &vault_id);
+    let strategy = RenameStrategy::from_name(query.strategy.as_deref().unwrap_or("fail"));
+    let restored =
+        TrashService::restore(&state.db, &vault_id, &vault.path, &trash_name, strategy).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "path": restored })))
+}
+
+/// Restore several trashed files at once. Failures are reported per item.
+#[post("/api/vaults/{vault_id}/trash/restore")]
+async fn bulk_restore_trash(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    path: web::Path<String>,
+    req: web::Json<BulkRestoreTrashRequest>,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    let (allowed, denied) =
+        writable_trash_names(&state, &acl, &vault_id, &vault.path, &req.trash_names).await?;
+    note_git_author(&state, &http_req, &vault_id);
+    let strategy = RenameStrategy::from_name(req.strategy.as_deref().unwrap_or("fail"));
+    let mut result =
+        TrashService::restore_many(&state.db, &vault_id, &vault.path, &allowed, strategy).await;
+    result.failed.extend(denied);
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Permanently delete several trashed files, or empty the whole trash.
+#[post("/api/vaults/{vault_id}/trash/purge")]
+async fn bulk_purge_trash(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    path: web::Path<String>,
+    req: Option<web::Json<PurgeTrashRequest>>,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let req = req.map(|r| r.into_inner()).unwrap_or_default();
+    let older_than = req
+        .older_than_days
+        .map(|days| Utc::now() - chrono::Duration::days(days as i64));
+
+    // Emptying the whole trash only empties what the caller could restore.
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    let (trash_names, denied) = match req.trash_names {
+        Some(names) => {
+            let (allowed, denied) =
+                writable_trash_names(&state, &acl, &vault_id, &vault.path, &names).await?;
+            (Some(allowed), denied)
+        }
+        None if !acl.is_unrestricted() => {
+            let writable = TrashService::list(&state.db, &vault_id, &vault.path)
+                .await?
+                .into_iter()
+                .filter(|item| acl.can_write_tree(&item.original_path))
+                .map(|item| item.trash_name)
+                .collect();
+            (Some(writable), Vec::new())
+        }
+        None => (None, Vec::new()),
+    };
+
+    let mut result = TrashService::purge_many(
+        &state.db,
+        &vault_id,
+        &vault.path,
+        trash_names.as_deref(),
+        older_than,
+    )
+    .await?;
+    result.failed.extend(denied);
+    Ok(HttpResponse::Ok().json(result))
 }
 
 /// Permanently delete a file from trash (no recovery possible).
 #[delete("/api/vaults/{vault_id}/trash/{trash_name}")]
 async fn delete_from_trash(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     path: web::Path<(String, String)>,
 ) -> AppResult<HttpResponse> {
     let (vault_id, trash_name) = path.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
-    FileService::delete_from_trash(&vault.path, &trash_name)?;
+    require_writable_trash_item(&state, &http_req, &vault_id, &vault.path, &trash_name).await?;
+    TrashService::purge(&state.db, &vault_id, &vault.path, &trash_name).await?;
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -1465,6 +2082,7 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
         .service(create_upload_session)
         .service(upload_chunk)
         .service(get_upload_status)
+        .service(cancel_upload_session)
         .service(finish_upload_session)
         .service(import_archive)
         .service(download_file)
@@ -1475,6 +2093,8 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
         .service(resolve_wiki_link)
         .service(batch_resolve_wiki_links)
         .service(list_trash)
+        .service(bulk_restore_trash)
+        .service(bulk_purge_trash)
         .service(restore_trash_file)
         .service(delete_from_trash);
 }
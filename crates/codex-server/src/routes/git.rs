@@ -0,0 +1,287 @@
+use crate::config::AppConfig;
+use crate::error::{AppError, AppResult};
+use crate::middleware::{ApiKeyContext, AuthenticatedUser};
+use crate::models::git::{GitStatusResponse, UpdateVaultGitSettingsRequest, VaultGitSettings};
+use crate::models::{ApiKeyScope, PathAccess, VaultRole};
+use crate::routes::vaults::AppState;
+use crate::services::git_service::{self, GitService};
+use crate::services::PathAclService;
+use actix_web::{get, post, put, web, HttpMessage, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+pub struct GitLogQuery {
+    pub path: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitDiffQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitCommitRequest {
+    pub message: Option<String>,
+}
+
+fn current_username(req: &HttpRequest) -> Option<String> {
+    req.extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| user.username.clone())
+}
+
+/// Load the vault and fail unless git integration is enabled for it.
+/// Returns the git handle together with the vault root.
+async fn enabled_git(
+    state: &AppState,
+    config: &AppConfig,
+    vault_id: &str,
+) -> AppResult<(GitService, String)> {
+    let vault = state.db.get_vault(vault_id).await?;
+    let settings = state.db.get_vault_git_settings(vault_id).await?;
+    if !settings.enabled {
+        return Err(AppError::InvalidInput(
+            "Git integration is not enabled for this vault".to_string(),
+        ));
+    }
+    Ok((GitService::new(&config.git, &vault.path), vault.path))
+}
+
+async fn blocking<T, F>(f: F) -> AppResult<T>
+where
+    F: FnOnce() -> AppResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    web::block(f)
+        .await
+        .map_err(|e| AppError::InternalError(format!("Git task failed: {}", e)))?
+}
+
+/// Whether the caller could change the vault's git settings, and so may
+/// see the remote URL with its credentials.
+async fn can_manage(
+    state: &AppState,
+    config: &AppConfig,
+    req: &HttpRequest,
+    vault_id: &str,
+) -> AppResult<bool> {
+    if !config.auth.enabled {
+        return Ok(true);
+    }
+    let Some(user_id) = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|user| user.user_id.clone())
+    else {
+        return Ok(false);
+    };
+    let key_allows = req.extensions().get::<ApiKeyContext>().is_none_or(|key| {
+        key.restrictions.role.is_none()
+            && (key.restrictions.scopes.is_empty()
+                || key.restrictions.scopes.contains(&ApiKeyScope::Admin))
+    });
+    let role = state.db.get_vault_role_for_user(vault_id, &user_id).await?;
+    Ok(key_allows && role == Some(VaultRole::Owner))
+}
+
+#[get("/api/vaults/{vault_id}/git")]
+async fn get_git_status(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    vault_id: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let vault_id = vault_id.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let mut settings = state.db.get_vault_git_settings(&vault_id).await?;
+    if !can_manage(&state, &config, &req, &vault_id).await? {
+        settings.remote_url = settings
+            .remote_url
+            .as_deref()
+            .map(git_service::redact_remote_url);
+    }
+    let acl = PathAclService::for_request(&state.db, &vault_id, &req).await?;
+    let git = GitService::new(&config.git, &vault.path);
+
+    let response = blocking(move || {
+        let is_repository = git.is_repository();
+        let (head, mut dirty_paths) = if is_repository {
+            (git.head()?, git.status_paths()?)
+        } else {
+            (None, Vec::new())
+        };
+        dirty_paths.retain(|path| acl.can_read(path));
+        Ok(GitStatusResponse {
+            settings,
+            is_repository,
+            head,
+            dirty_paths,
+        })
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[put("/api/vaults/{vault_id}/git")]
+async fn update_git_settings(
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    vault_id: web::Path<String>,
+    body: web::Json<UpdateVaultGitSettingsRequest>,
+) -> AppResult<HttpResponse> {
+    let vault_id = vault_id.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let body = body.into_inner();
+
+    let mut settings = state.db.get_vault_git_settings(&vault_id).await?;
+    settings.enabled = body.enabled;
+    settings.auto_commit = body.auto_commit;
+    settings.remote_url = body
+        .remote_url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+    if let Some(url) = &settings.remote_url {
+        git_service::validate_remote_url(url, config.git.allow_local_remotes)?;
+    }
+    settings.branch = body.branch.trim().to_string();
+    settings.sync_interval_secs = body.sync_interval_secs;
+
+    if settings.enabled {
+        let git = GitService::new(&config.git, &vault.path);
+        let branch = settings.branch.clone();
+        let remote_url = settings.remote_url.clone();
+        blocking(move || {
+            git.init(&branch)?;
+            git.set_remote(remote_url.as_deref())
+        })
+        .await?;
+    }
+
+    state.db.upsert_vault_git_settings(&settings).await?;
+    let settings: VaultGitSettings = state.db.get_vault_git_settings(&vault_id).await?;
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+#[get("/api/vaults/{vault_id}/git/log")]
+async fn get_git_log(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    vault_id: web::Path<String>,
+    query: web::Query<GitLogQuery>,
+) -> AppResult<HttpResponse> {
+    let vault_id = vault_id.into_inner();
+    let (git, vault_path) = enabled_git(&state, &config, &vault_id).await?;
+    let path = query.path.as_deref().filter(|p| !p.is_empty());
+
+    // Commit messages name the files they touch, so a caller with hidden
+    // paths only sees the history of a path they can read.
+    let acl = PathAclService::for_request(&state.db, &vault_id, &req).await?;
+    if !acl.is_unrestricted() {
+        let Some(path) = path else {
+            return Err(AppError::Forbidden(
+                "A path is required to read the history of this vault".to_string(),
+            ));
+        };
+        acl.require(path, PathAccess::Read)?;
+    }
+
+    let path = path
+        .map(|p| git_service::vault_relative_path(Path::new(&vault_path), p))
+        .transpose()?;
+    let limit = query.limit.unwrap_or(50).min(1000);
+
+    let commits = blocking(move || git.log(path.as_deref(), limit)).await?;
+    Ok(HttpResponse::Ok().json(commits))
+}
+
+#[get("/api/vaults/{vault_id}/git/diff/{file_path:.*}")]
+async fn get_git_diff(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    path: web::Path<(String, String)>,
+    query: web::Query<GitDiffQuery>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, file_path) = path.into_inner();
+    let (git, vault_path) = enabled_git(&state, &config, &vault_id).await?;
+    PathAclService::for_request(&state.db, &vault_id, &req)
+        .await?
+        .require(&file_path, PathAccess::Read)?;
+    let file_path = git_service::vault_relative_path(Path::new(&vault_path), &file_path)?;
+    let query = query.into_inner();
+
+    let diff =
+        blocking(move || git.diff(&file_path, query.from.as_deref(), query.to.as_deref())).await?;
+    Ok(HttpResponse::Ok().json(diff))
+}
+
+#[get("/api/vaults/{vault_id}/git/blame/{file_path:.*}")]
+async fn get_git_blame(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    path: web::Path<(String, String)>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, file_path) = path.into_inner();
+    let (git, vault_path) = enabled_git(&state, &config, &vault_id).await?;
+    PathAclService::for_request(&state.db, &vault_id, &req)
+        .await?
+        .require(&file_path, PathAccess::Read)?;
+    let file_path = git_service::vault_relative_path(Path::new(&vault_path), &file_path)?;
+
+    let lines = blocking(move || git.blame(&file_path)).await?;
+    Ok(HttpResponse::Ok().json(lines))
+}
+
+#[post("/api/vaults/{vault_id}/git/commit")]
+async fn commit_git(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    vault_id: web::Path<String>,
+    body: Option<web::Json<GitCommitRequest>>,
+) -> AppResult<HttpResponse> {
+    let vault_id = vault_id.into_inner();
+    let message = body
+        .and_then(|b| b.into_inner().message)
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| "Update vault".to_string());
+
+    let commit_id = git_service::commit_vault(
+        &state.db,
+        &config.git,
+        &vault_id,
+        message,
+        current_username(&req),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "commit": commit_id })))
+}
+
+#[post("/api/vaults/{vault_id}/git/sync")]
+async fn sync_git(
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    vault_id: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let vault_id = vault_id.into_inner();
+    state.db.get_vault(&vault_id).await?;
+    let outcome =
+        git_service::sync_vault(&state.db, &config.git, &state.ws_broadcaster, &vault_id).await?;
+    Ok(HttpResponse::Ok().json(outcome))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_git_status)
+        .service(update_git_settings)
+        .service(get_git_log)
+        .service(get_git_diff)
+        .service(get_git_blame)
+        .service(commit_git)
+        .service(sync_git);
+}
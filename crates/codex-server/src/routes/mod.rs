@@ -4,6 +4,7 @@ pub mod auth;
 pub mod bookmarks;
 pub mod entities;
 pub mod files;
+pub mod git;
 pub mod groups;
 pub mod health;
 pub mod invitations;
@@ -12,11 +13,14 @@ pub mod ml;
 pub mod oidc;
 pub mod plugins;
 pub mod preferences;
+pub mod scim;
 pub mod search;
+pub mod share_links;
 pub mod tags;
 pub mod totp;
 pub mod vaults;
 pub mod version;
+pub mod webauthn;
 pub mod ws;
 
 pub use vaults::AppState;
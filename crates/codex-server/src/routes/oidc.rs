@@ -1,58 +1,123 @@
-use crate::config::AppConfig;
+use crate::config::{AppConfig, AuthConfig, OidcProviderConfig};
 use crate::error::{AppError, AppResult};
+use crate::models::oidc::OidcAuthRequest;
+use crate::routes::auth::RefreshRequest;
 use crate::routes::vaults::AppState;
-use crate::services::oidc_provider;
-use actix_web::{get, web, HttpResponse};
+use crate::services::{oidc_provider, GroupMappingService};
+use actix_web::{get, post, web, HttpResponse};
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
     Argon2,
 };
-use rand::Rng;
+use chrono::Utc;
+use std::time::Duration;
 use uuid::Uuid;
 
-/// Generate a random state token for CSRF protection.
-fn generate_state_token() -> String {
-    let mut rng = rand::rng();
-    let bytes: [u8; 16] = rng.random();
-    hex::encode(bytes)
+/// Pick the provider a sign-in is for. Without a name, the only provider
+/// (or the `default` one) is used.
+fn resolve_provider(
+    auth_cfg: &AuthConfig,
+    name: Option<&str>,
+) -> AppResult<(String, OidcProviderConfig)> {
+    let names = auth_cfg.oidc_provider_names();
+    let name = match name {
+        Some(name) => name.to_string(),
+        None if names.is_empty() => {
+            return Err(AppError::InternalError(
+                "OIDC is not configured".to_string(),
+            ))
+        }
+        None if names.len() == 1 => names[0].clone(),
+        None if names
+            .iter()
+            .any(|n| n == crate::config::DEFAULT_OIDC_PROVIDER) =>
+        {
+            crate::config::DEFAULT_OIDC_PROVIDER.to_string()
+        }
+        None => {
+            return Err(AppError::InvalidInput(format!(
+                "Choose an OIDC provider: {}",
+                names.join(", ")
+            )))
+        }
+    };
+    let provider = auth_cfg
+        .oidc_provider(&name)
+        .ok_or_else(|| AppError::NotFound(format!("Unknown OIDC provider '{name}'")))?;
+    Ok((name, provider))
 }
 
-/// Step 1: Redirect the user to the OIDC provider's authorization page.
-/// Returns the URL the client should redirect to.
-#[get("/api/auth/oidc/authorize")]
-async fn oidc_authorize(config: web::Data<AppConfig>) -> AppResult<HttpResponse> {
-    let issuer = config
+/// List the configured OIDC providers for the sign-in page.
+#[get("/api/auth/oidc/providers")]
+async fn oidc_providers(config: web::Data<AppConfig>) -> AppResult<HttpResponse> {
+    let providers: Vec<_> = config
         .auth
-        .oidc_issuer_url
-        .as_deref()
-        .ok_or_else(|| AppError::InternalError("OIDC is not configured".to_string()))?;
+        .oidc_provider_names()
+        .into_iter()
+        .filter_map(|name| {
+            let provider = config.auth.oidc_provider(&name)?;
+            Some(oidc_provider::OidcProviderInfo {
+                display_name: provider.display_name.unwrap_or_else(|| name.clone()),
+                name,
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(providers))
+}
 
-    let discovery = oidc_provider::fetch_discovery(issuer).await?;
-    let state_token = generate_state_token();
-    let authorize_url = oidc_provider::build_authorize_url(&discovery, &config.auth, &state_token)?;
+/// Step 1: Redirect the user to the OIDC provider's authorization page.
+/// Returns the URL the client should redirect to. The state, nonce and
+/// PKCE verifier are kept server-side until the callback.
+#[get("/api/auth/oidc/authorize")]
+async fn oidc_authorize(
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    query: web::Query<OidcAuthorizeQuery>,
+) -> AppResult<HttpResponse> {
+    let (name, provider) = resolve_provider(&config.auth, query.provider.as_deref())?;
+    let discovery = oidc_provider::fetch_discovery(&provider.issuer_url).await?;
+    let request = OidcAuthRequest {
+        state: oidc_provider::random_token(),
+        provider: name,
+        nonce: oidc_provider::random_token(),
+        code_verifier: oidc_provider::random_token(),
+        expires_at: Utc::now() + chrono::Duration::seconds(config.auth.oidc_state_ttl_secs as i64),
+    };
+    let authorize_url = oidc_provider::build_authorize_url(&discovery, &provider, &request)?;
+    state.db.create_oidc_auth_request(&request).await?;
 
     Ok(
         HttpResponse::Ok().json(oidc_provider::OidcAuthorizeResponse {
             authorize_url,
-            state: state_token,
+            state: request.state,
+            provider: request.provider,
         }),
     )
 }
 
 /// Step 2: OIDC callback after the user authenticates with the provider.
-/// Exchanges the code for tokens, fetches user info, creates/finds local user,
-/// and issues our own JWT tokens.
+/// Exchanges the code for tokens, validates the ID token, creates/finds the
+/// local user, and issues our own JWT tokens.
 #[get("/api/auth/oidc/callback")]
 async fn oidc_callback(
     state: web::Data<AppState>,
     config: web::Data<AppConfig>,
     query: web::Query<OidcCallbackQuery>,
 ) -> AppResult<HttpResponse> {
-    let issuer = config
-        .auth
-        .oidc_issuer_url
+    let state_token = query
+        .state
         .as_deref()
-        .ok_or_else(|| AppError::InternalError("OIDC is not configured".to_string()))?;
+        .ok_or_else(|| AppError::InvalidInput("Missing state".to_string()))?;
+    // Consume the sign-in even when the provider reports an error, so the
+    // state cannot be replayed.
+    let request = state
+        .db
+        .take_oidc_auth_request(state_token)
+        .await?
+        .filter(|request| !request.is_expired())
+        .ok_or_else(|| {
+            AppError::Unauthorized("Unknown or expired OIDC sign-in; start again".to_string())
+        })?;
 
     if let Some(ref error) = query.error {
         return Err(AppError::Unauthorized(format!(
@@ -64,14 +129,26 @@ async fn oidc_callback(
         .code
         .as_deref()
         .ok_or_else(|| AppError::InvalidInput("Missing authorization code".to_string()))?;
+    let provider = config
+        .auth
+        .oidc_provider(&request.provider)
+        .ok_or_else(|| {
+            AppError::Unauthorized(format!(
+                "OIDC provider '{}' is no longer configured",
+                request.provider
+            ))
+        })?;
 
-    // Exchange code for tokens.
-    let discovery = oidc_provider::fetch_discovery(issuer).await?;
-    let tokens = oidc_provider::exchange_code(&discovery, &config.auth, code).await?;
-
-    // Fetch user info.
-    let userinfo = oidc_provider::fetch_userinfo(&discovery, &tokens.access_token).await?;
-    let username = oidc_provider::derive_username(&userinfo);
+    let sign_in = oidc_provider::complete_sign_in(
+        &provider,
+        &request,
+        code,
+        config.auth.oidc_clock_skew_secs,
+        Duration::from_secs(config.auth.oidc_jwks_cache_secs),
+    )
+    .await?;
+    let userinfo = &sign_in.user;
+    let username = oidc_provider::derive_username(userinfo);
 
     // Find or create local user.
     let local_user = state.db.get_user_auth_by_username(&username).await?;
@@ -97,8 +174,8 @@ async fn oidc_callback(
                     Some(&username),
                     "oidc_user_provisioned",
                     Some(&format!(
-                        "Auto-provisioned from OIDC (sub={})",
-                        userinfo.sub
+                        "Auto-provisioned from OIDC provider '{}' (sub={})",
+                        request.provider, userinfo.sub
                     )),
                     None,
                     true,
@@ -108,13 +185,40 @@ async fn oidc_callback(
         }
     };
 
+    if !state.db.is_user_active(&user_id).await? {
+        return Err(AppError::Unauthorized(
+            "Account is deactivated. Contact an administrator.".to_string(),
+        ));
+    }
+
+    // Without the claim we cannot tell which groups the user left, so
+    // memberships are only re-evaluated when the provider sends it.
+    let groups_claim = provider
+        .groups_claim
+        .as_deref()
+        .unwrap_or(&config.auth.oidc_groups_claim);
+    if let Some(groups) = oidc_provider::user_groups(userinfo, groups_claim) {
+        GroupMappingService::sync(
+            &state.db,
+            &config.auth,
+            &user_id,
+            &username,
+            "oidc",
+            &groups,
+        )
+        .await?;
+    }
+
     let _ = state
         .db
         .write_audit_log(
             Some(&user_id),
             Some(&username),
             "login_success",
-            Some("Authenticated via OIDC"),
+            Some(&format!(
+                "Authenticated via OIDC provider '{}'",
+                request.provider
+            )),
             None,
             true,
         )
@@ -128,10 +232,54 @@ async fn oidc_callback(
         .db
         .create_session(&refresh_jti, &user_id, refresh_exp)
         .await;
+    let _ = state
+        .db
+        .create_oidc_session(&refresh_jti, &request.provider, &sign_in.id_token)
+        .await;
 
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// End a session signed in through OIDC. Returns the provider's end-session
+/// URL (RP-initiated logout) for the browser to visit, when it has one.
+#[post("/api/auth/oidc/logout")]
+async fn oidc_logout(
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    req: web::Json<RefreshRequest>,
+) -> AppResult<HttpResponse> {
+    let (user_id, username, session_id) =
+        crate::routes::auth::decode_refresh_token(&req.refresh_token, &config.auth)?;
+    state.db.revoke_session(&session_id).await?;
+
+    let mut logout_url = None;
+    if let Some((name, id_token)) = state.db.take_oidc_session(&session_id).await? {
+        if let Some(provider) = config.auth.oidc_provider(&name) {
+            let discovery = oidc_provider::fetch_discovery(&provider.issuer_url).await?;
+            logout_url = oidc_provider::end_session_url(&discovery, &provider, &id_token)?;
+        }
+    }
+
+    let _ = state
+        .db
+        .write_audit_log(
+            Some(&user_id),
+            Some(&username),
+            "logout",
+            Some("Signed out of OIDC session"),
+            None,
+            true,
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(oidc_provider::OidcLogoutResponse { logout_url }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct OidcAuthorizeQuery {
+    provider: Option<String>,
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct OidcCallbackQuery {
     code: Option<String>,
@@ -140,5 +288,8 @@ struct OidcCallbackQuery {
 }
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(oidc_authorize).service(oidc_callback);
+    cfg.service(oidc_providers)
+        .service(oidc_authorize)
+        .service(oidc_callback)
+        .service(oidc_logout);
 }
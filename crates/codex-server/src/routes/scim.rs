@@ -0,0 +1,502 @@
+//! SCIM 2.0 endpoints for identity providers (Okta, Entra ID, …).
+//!
+//! These live outside `/api` and are not covered by the session middleware:
+//! every request must carry `Authorization: Bearer <token>` with a token an
+//! administrator issued through `/api/admin/scim/tokens`. Errors use the
+//! SCIM error schema rather than the usual JSON error body.
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::models::scim::{
+    ScimGroupInput, ScimListResponse, ScimPatchRequest, ScimUserInput, ERROR_SCHEMA,
+    LIST_RESPONSE_SCHEMA, SERVICE_PROVIDER_CONFIG_SCHEMA,
+};
+use crate::models::ScimTokenInfo;
+use crate::routes::vaults::AppState;
+use crate::services::{ActivationChange, ScimService};
+use actix_web::http::{header, StatusCode};
+use actix_web::{delete, get, patch, post, put, web, HttpRequest, HttpResponse, ResponseError};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+const DEFAULT_PAGE_SIZE: u64 = 100;
+const MAX_PAGE_SIZE: u64 = 500;
+
+/// An [`AppError`] rendered as a SCIM error response (RFC 7644 §3.12).
+#[derive(Debug)]
+struct ScimError(AppError);
+
+impl From<AppError> for ScimError {
+    fn from(error: AppError) -> Self {
+        Self(error)
+    }
+}
+
+impl fmt::Display for ScimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ResponseError for ScimError {
+    fn status_code(&self) -> StatusCode {
+        self.0.error_response().status()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let scim_type = match &self.0 {
+            AppError::Conflict(_) => Some("uniqueness"),
+            AppError::InvalidInput(_) => Some("invalidValue"),
+            _ => None,
+        };
+        HttpResponse::build(status)
+            .content_type(SCIM_CONTENT_TYPE)
+            .json(serde_json::json!({
+                "schemas": [ERROR_SCHEMA],
+                "status": status.as_u16().to_string(),
+                "scimType": scim_type,
+                "detail": self.0.to_string(),
+            }))
+    }
+}
+
+type ScimResult<T> = Result<T, ScimError>;
+
+fn scim_response(status: StatusCode, body: &impl Serialize) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(SCIM_CONTENT_TYPE)
+        .json(body)
+}
+
+/// Parse a request body ourselves so malformed JSON gets a SCIM error.
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> ScimResult<T> {
+    serde_json::from_slice(body)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid SCIM request body: {e}")).into())
+}
+
+fn base_url(req: &HttpRequest) -> String {
+    let info = req.connection_info();
+    format!("{}://{}/scim/v2", info.scheme(), info.host())
+}
+
+async fn require_scim_token(state: &AppState, req: &HttpRequest) -> ScimResult<ScimTokenInfo> {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| AppError::Unauthorized("SCIM bearer token required".to_string()))?;
+
+    let token_info = state
+        .db
+        .get_active_scim_token(&ScimService::hash_token(token))
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid SCIM token".to_string()))?;
+    let _ = state.db.touch_scim_token(&token_info.id).await;
+    Ok(token_info)
+}
+
+async fn audit(state: &AppState, token: &ScimTokenInfo, event: &str, detail: &str) {
+    let _ = state
+        .db
+        .write_audit_log(
+            None,
+            Some(&format!("scim:{}", token.name)),
+            event,
+            Some(detail),
+            None,
+            true,
+        )
+        .await;
+}
+
+async fn audit_activation(
+    state: &AppState,
+    token: &ScimTokenInfo,
+    username: &str,
+    change: Option<ActivationChange>,
+) {
+    match change {
+        Some(ActivationChange::Deactivated { sessions_revoked }) => {
+            audit(
+                state,
+                token,
+                "user_deactivated",
+                &format!("Deprovisioned {username}; revoked {sessions_revoked} session(s)"),
+            )
+            .await
+        }
+        Some(ActivationChange::Reactivated) => {
+            audit(
+                state,
+                token,
+                "user_reactivated",
+                &format!("Reactivated {username}"),
+            )
+            .await
+        }
+        None => {}
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListQuery {
+    filter: Option<String>,
+    start_index: Option<u64>,
+    count: Option<u64>,
+}
+
+impl ListQuery {
+    /// Zero-based offset and page size.
+    fn page(&self) -> (u64, u64) {
+        let start_index = self.start_index.unwrap_or(1).max(1);
+        let count = self.count.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+        (start_index - 1, count)
+    }
+}
+
+fn list_response<T: Serialize>(total: u64, offset: u64, resources: Vec<T>) -> HttpResponse {
+    scim_response(
+        StatusCode::OK,
+        &ScimListResponse {
+            schemas: [LIST_RESPONSE_SCHEMA],
+            total_results: total,
+            start_index: offset + 1,
+            items_per_page: resources.len() as u64,
+            resources,
+        },
+    )
+}
+
+#[get("/scim/v2/ServiceProviderConfig")]
+async fn service_provider_config(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+) -> ScimResult<HttpResponse> {
+    require_scim_token(&state, &req).await?;
+    Ok(scim_response(
+        StatusCode::OK,
+        &serde_json::json!({
+            "schemas": [SERVICE_PROVIDER_CONFIG_SCHEMA],
+            "patch": { "supported": true },
+            "bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+            "filter": { "supported": true, "maxResults": MAX_PAGE_SIZE },
+            "changePassword": { "supported": false },
+            "sort": { "supported": false },
+            "etag": { "supported": false },
+            "authenticationSchemes": [{
+                "type": "oauthbearertoken",
+                "name": "Bearer token",
+                "description": "A token issued by a Codex administrator",
+            }],
+        }),
+    ))
+}
+
+// ── Users ───────────────────────────────────────────────────────────────
+
+#[get("/scim/v2/Users")]
+async fn list_users(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<ListQuery>,
+) -> ScimResult<HttpResponse> {
+    require_scim_token(&state, &req).await?;
+    let filter = query
+        .filter
+        .as_deref()
+        .map(ScimService::parse_user_filter)
+        .transpose()?;
+    let (offset, limit) = query.page();
+    let (total, users) = state
+        .db
+        .list_scim_users(filter.as_ref(), offset, limit)
+        .await?;
+
+    let base = base_url(&req);
+    let mut resources = Vec::with_capacity(users.len());
+    for user in &users {
+        resources.push(ScimService::user_resource(&state.db, &base, user).await?);
+    }
+    Ok(list_response(total, offset, resources))
+}
+
+#[get("/scim/v2/Users/{user_id}")]
+async fn get_user(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> ScimResult<HttpResponse> {
+    require_scim_token(&state, &req).await?;
+    let user = ScimService::get_user(&state.db, &path).await?;
+    let resource = ScimService::user_resource(&state.db, &base_url(&req), &user).await?;
+    Ok(scim_response(StatusCode::OK, &resource))
+}
+
+#[post("/scim/v2/Users")]
+async fn create_user(
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> ScimResult<HttpResponse> {
+    let token = require_scim_token(&state, &req).await?;
+    let input: ScimUserInput = parse_body(&body)?;
+
+    let password_hash = match input.password.as_deref().filter(|p| !p.is_empty()) {
+        Some(password) => {
+            crate::services::validate_password_policy(password, &config.auth)?;
+            let salt = SaltString::generate(&mut OsRng);
+            let hash = Argon2::default()
+                .hash_password(password.as_bytes(), &salt)
+                .map_err(|e| AppError::InternalError(format!("Failed to hash password: {e}")))?;
+            Some(hash.to_string())
+        }
+        None => None,
+    };
+
+    let (user, change) =
+        ScimService::create_user(&state.db, &input, password_hash.as_deref()).await?;
+    audit(
+        &state,
+        &token,
+        "scim_user_provisioned",
+        &format!("Provisioned {} ({})", user.username, user.id),
+    )
+    .await;
+    audit_activation(&state, &token, &user.username, change).await;
+
+    let resource = ScimService::user_resource(&state.db, &base_url(&req), &user).await?;
+    Ok(scim_response(StatusCode::CREATED, &resource))
+}
+
+#[put("/scim/v2/Users/{user_id}")]
+async fn replace_user(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> ScimResult<HttpResponse> {
+    let token = require_scim_token(&state, &req).await?;
+    let input: ScimUserInput = parse_body(&body)?;
+    let (user, change) = ScimService::replace_user(&state.db, &path, &input).await?;
+    audit(
+        &state,
+        &token,
+        "scim_user_updated",
+        &format!("Updated {} ({})", user.username, user.id),
+    )
+    .await;
+    audit_activation(&state, &token, &user.username, change).await;
+
+    let resource = ScimService::user_resource(&state.db, &base_url(&req), &user).await?;
+    Ok(scim_response(StatusCode::OK, &resource))
+}
+
+#[patch("/scim/v2/Users/{user_id}")]
+async fn patch_user(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> ScimResult<HttpResponse> {
+    let token = require_scim_token(&state, &req).await?;
+    let patch: ScimPatchRequest = parse_body(&body)?;
+    let (user, change) = ScimService::patch_user(&state.db, &path, &patch.operations).await?;
+    audit(
+        &state,
+        &token,
+        "scim_user_updated",
+        &format!("Updated {} ({})", user.username, user.id),
+    )
+    .await;
+    audit_activation(&state, &token, &user.username, change).await;
+
+    let resource = ScimService::user_resource(&state.db, &base_url(&req), &user).await?;
+    Ok(scim_response(StatusCode::OK, &resource))
+}
+
+/// Deprovision a user. The account and its vaults are kept, deactivated,
+/// so notes are not lost when someone leaves the directory.
+#[delete("/scim/v2/Users/{user_id}")]
+async fn delete_user(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> ScimResult<HttpResponse> {
+    let token = require_scim_token(&state, &req).await?;
+    let user = ScimService::get_user(&state.db, &path).await?;
+    let change = ScimService::set_active(&state.db, &user.id, false).await?;
+    audit_activation(&state, &token, &user.username, change).await;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// ── Groups ──────────────────────────────────────────────────────────────
+
+#[get("/scim/v2/Groups")]
+async fn list_groups(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<ListQuery>,
+) -> ScimResult<HttpResponse> {
+    require_scim_token(&state, &req).await?;
+    let filter = query
+        .filter
+        .as_deref()
+        .map(ScimService::parse_group_filter)
+        .transpose()?;
+    let (offset, limit) = query.page();
+    let (total, groups) = state
+        .db
+        .list_scim_groups(filter.as_ref(), offset, limit)
+        .await?;
+
+    let base = base_url(&req);
+    let resources = groups
+        .iter()
+        .map(|g| ScimService::group_resource(&base, g))
+        .collect();
+    Ok(list_response(total, offset, resources))
+}
+
+#[get("/scim/v2/Groups/{group_id}")]
+async fn get_group(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> ScimResult<HttpResponse> {
+    require_scim_token(&state, &req).await?;
+    let group = ScimService::get_group(&state.db, &path).await?;
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimService::group_resource(&base_url(&req), &group),
+    ))
+}
+
+#[post("/scim/v2/Groups")]
+async fn create_group(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> ScimResult<HttpResponse> {
+    let token = require_scim_token(&state, &req).await?;
+    let input: ScimGroupInput = parse_body(&body)?;
+    let group = ScimService::create_group(&state.db, &input, &token.created_by_user_id).await?;
+    audit(
+        &state,
+        &token,
+        "scim_group_provisioned",
+        &format!(
+            "Provisioned group {} ({}) with {} member(s)",
+            group.name,
+            group.id,
+            group.members.len()
+        ),
+    )
+    .await;
+    Ok(scim_response(
+        StatusCode::CREATED,
+        &ScimService::group_resource(&base_url(&req), &group),
+    ))
+}
+
+#[put("/scim/v2/Groups/{group_id}")]
+async fn replace_group(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> ScimResult<HttpResponse> {
+    let token = require_scim_token(&state, &req).await?;
+    let input: ScimGroupInput = parse_body(&body)?;
+    let group = ScimService::replace_group(&state.db, &path, &input).await?;
+    audit(
+        &state,
+        &token,
+        "scim_group_updated",
+        &format!(
+            "Updated group {} ({}); {} member(s)",
+            group.name,
+            group.id,
+            group.members.len()
+        ),
+    )
+    .await;
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimService::group_resource(&base_url(&req), &group),
+    ))
+}
+
+#[patch("/scim/v2/Groups/{group_id}")]
+async fn patch_group(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> ScimResult<HttpResponse> {
+    let token = require_scim_token(&state, &req).await?;
+    let patch: ScimPatchRequest = parse_body(&body)?;
+    let group = ScimService::patch_group(&state.db, &path, &patch.operations).await?;
+    audit(
+        &state,
+        &token,
+        "scim_group_updated",
+        &format!(
+            "Updated group {} ({}); {} member(s)",
+            group.name,
+            group.id,
+            group.members.len()
+        ),
+    )
+    .await;
+    Ok(scim_response(
+        StatusCode::OK,
+        &ScimService::group_resource(&base_url(&req), &group),
+    ))
+}
+
+#[delete("/scim/v2/Groups/{group_id}")]
+async fn delete_group(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> ScimResult<HttpResponse> {
+    let token = require_scim_token(&state, &req).await?;
+    let group = ScimService::get_group(&state.db, &path).await?;
+    state.db.delete_group(&group.id).await?;
+    audit(
+        &state,
+        &token,
+        "scim_group_deleted",
+        &format!("Deleted group {} ({})", group.name, group.id),
+    )
+    .await;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(service_provider_config)
+        .service(list_users)
+        .service(create_user)
+        .service(get_user)
+        .service(replace_user)
+        .service(patch_user)
+        .service(delete_user)
+        .service(list_groups)
+        .service(create_group)
+        .service(get_group)
+        .service(replace_group)
+        .service(patch_group)
+        .service(delete_group);
+}
@@ -1,6 +1,7 @@
 use crate::error::AppResult;
 use crate::routes::vaults::AppState;
-use actix_web::{get, post, web, HttpResponse};
+use crate::services::PathAclService;
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -23,6 +24,7 @@ fn default_page() -> usize {
 #[get("/api/vaults/{vault_id}/search")]
 async fn search(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     query: web::Query<SearchQuery>,
 ) -> AppResult<HttpResponse> {
@@ -31,9 +33,15 @@ async fn search(
     // Verify vault exists
     state.db.get_vault(&vault_id).await?;
 
-    let results = state
-        .search_index
-        .search(&vault_id, &query.q, query.page, query.page_size)?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+
+    let results = state.search_index.search_visible(
+        &vault_id,
+        &query.q,
+        query.page,
+        query.page_size,
+        |path| acl.can_read(path),
+    )?;
 
     Ok(HttpResponse::Ok().json(results))
 }
@@ -0,0 +1,561 @@
+use crate::error::{AppError, AppResult};
+use crate::middleware::AuthenticatedUser;
+use crate::models::{
+    CreateShareLinkCommentRequest, CreateShareLinkRequest, CreateShareLinkResponse, FileNode,
+    ShareLink, ShareLinkComment, ShareLinkPermission, SharedContent,
+};
+use crate::routes::files::{get_mime_type, stream_file};
+use crate::routes::vaults::AppState;
+use crate::services::{FileService, LinkScope, MarkdownService, RenderOptions};
+use actix_web::http::header::DispositionType;
+use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, SaltString},
+    Argon2, PasswordVerifier,
+};
+use chrono::Utc;
+use rand::Rng;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Header carrying the password of a protected share link.
+const SHARE_PASSWORD_HEADER: &str = "X-Share-Password";
+
+const MAX_COMMENT_CHARS: usize = 10_000;
+const MAX_COMMENT_AUTHOR_CHARS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct ShareCommentQuery {
+    /// Only comments on this note of a shared folder
+    pub path: Option<String>,
+}
+
+fn generate_share_token() -> String {
+    let mut rng = rand::rng();
+    let bytes: [u8; 32] = rng.random();
+    hex::encode(bytes)
+}
+
+fn hash_share_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn public_url(token: &str) -> String {
+    format!("/api/public/shares/{}", token)
+}
+
+fn client_ip(req: &HttpRequest) -> Option<String> {
+    req.connection_info().peer_addr().map(str::to_string)
+}
+
+/// Create a share link for a note or folder of the vault.
+#[post("/api/vaults/{vault_id}/shares/links")]
+async fn create_share_link(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<CreateShareLinkRequest>,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    let vault = state.db.get_vault(&vault_id).await?;
+    let user = req.extensions().get::<AuthenticatedUser>().cloned();
+    let body = body.into_inner();
+
+    let shared_path = body.path.trim().trim_matches('/').to_string();
+    if shared_path.is_empty() {
+        return Err(AppError::InvalidInput(
+            "A note or folder path is required; use vault visibility to share a whole vault"
+                .to_string(),
+        ));
+    }
+    let full_path = FileService::resolve_path(&vault.path, &shared_path)?;
+    if !full_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "Path not found: {}",
+            shared_path
+        )));
+    }
+
+    if body.expires_in_hours == Some(0) {
+        return Err(AppError::InvalidInput(
+            "expires_in_hours must be at least 1".to_string(),
+        ));
+    }
+    let password_hash = match body.password.as_deref() {
+        Some("") => {
+            return Err(AppError::InvalidInput(
+                "Share password must not be empty".to_string(),
+            ));
+        }
+        Some(password) => {
+            let salt = SaltString::generate(&mut OsRng);
+            let hash = Argon2::default()
+                .hash_password(password.as_bytes(), &salt)
+                .map_err(|e| AppError::InternalError(format!("Failed to hash password: {e}")))?;
+            Some(hash.to_string())
+        }
+        None => None,
+    };
+
+    let now = Utc::now();
+    let link = ShareLink {
+        id: Uuid::new_v4().to_string(),
+        vault_id: vault_id.clone(),
+        path: shared_path,
+        is_folder: full_path.is_dir(),
+        permission: body.permission,
+        password_protected: password_hash.is_some(),
+        expires_at: body
+            .expires_in_hours
+            .map(|hours| now + chrono::Duration::hours(hours as i64)),
+        created_by: user.as_ref().map(|u| u.user_id.clone()),
+        created_at: now,
+        revoked_at: None,
+        access_count: 0,
+        last_accessed_at: None,
+    };
+    let token = generate_share_token();
+    state
+        .db
+        .create_share_link(&link, &hash_share_token(&token), password_hash.as_deref())
+        .await?;
+
+    let _ = state
+        .db
+        .write_audit_log(
+            user.as_ref().map(|u| u.user_id.as_str()),
+            user.as_ref().map(|u| u.username.as_str()),
+            "share_link_created",
+            Some(&format!(
+                "Created share link {} for {}:{} (permission={:?}, expires={:?}, password={})",
+                link.id,
+                vault_id,
+                link.path,
+                link.permission,
+                link.expires_at,
+                link.password_protected
+            )),
+            client_ip(&req).as_deref(),
+            true,
+        )
+        .await;
+
+    Ok(HttpResponse::Created().json(CreateShareLinkResponse {
+        url: public_url(&token),
+        link,
+        token,
+    }))
+}
+
+#[get("/api/vaults/{vault_id}/shares/links")]
+async fn list_share_links(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    state.db.get_vault(&vault_id).await?;
+    let links = state.db.list_share_links(&vault_id).await?;
+    Ok(HttpResponse::Ok().json(links))
+}
+
+/// Revoke a share link. Revoked links stay listed for auditing.
+#[delete("/api/vaults/{vault_id}/shares/links/{link_id}")]
+async fn revoke_share_link(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, link_id) = path.into_inner();
+    state.db.revoke_share_link(&vault_id, &link_id).await?;
+
+    let user = req.extensions().get::<AuthenticatedUser>().cloned();
+    let _ = state
+        .db
+        .write_audit_log(
+            user.as_ref().map(|u| u.user_id.as_str()),
+            user.as_ref().map(|u| u.username.as_str()),
+            "share_link_revoked",
+            Some(&format!(
+                "Revoked share link {} in vault {}",
+                link_id, vault_id
+            )),
+            client_ip(&req).as_deref(),
+            true,
+        )
+        .await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Comments left through one of the vault's share links.
+#[get("/api/vaults/{vault_id}/shares/links/{link_id}/comments")]
+async fn list_share_link_comments(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, link_id) = path.into_inner();
+    let links = state.db.list_share_links(&vault_id).await?;
+    if !links.iter().any(|link| link.id == link_id) {
+        return Err(AppError::NotFound(format!(
+            "Share link {} not found in vault {}",
+            link_id, vault_id
+        )));
+    }
+    let comments = state.db.list_share_link_comments(&link_id).await?;
+    Ok(HttpResponse::Ok().json(comments))
+}
+
+/// Why a public share request was refused.
+#[derive(Clone, Copy)]
+enum ShareDenial {
+    UnknownToken,
+    Revoked,
+    Expired,
+    PasswordRequired,
+    WrongPassword,
+    OutsideScope,
+}
+
+impl ShareDenial {
+    fn reason(self) -> &'static str {
+        match self {
+            Self::UnknownToken => "unknown token",
+            Self::Revoked => "revoked",
+            Self::Expired => "expired",
+            Self::PasswordRequired => "password required",
+            Self::WrongPassword => "wrong password",
+            Self::OutsideScope => "outside shared scope",
+        }
+    }
+
+    fn into_error(self) -> AppError {
+        match self {
+            Self::PasswordRequired => AppError::Unauthorized(format!(
+                "This link is password protected; send the password in {}",
+                SHARE_PASSWORD_HEADER
+            )),
+            Self::WrongPassword => AppError::Unauthorized("Incorrect share password".to_string()),
+            // Do not reveal whether a token ever existed.
+            _ => AppError::NotFound("Share link not found or no longer valid".to_string()),
+        }
+    }
+}
+
+/// Resolve a public token to its link, enforcing revocation, expiry, the
+/// password and, for `file_path`, the shared scope. Every attempt,
+/// successful or not, is written to the audit log.
+async fn open_share_link(
+    state: &AppState,
+    req: &HttpRequest,
+    token: &str,
+    file_path: Option<&str>,
+) -> AppResult<ShareLink> {
+    let found = state
+        .db
+        .get_share_link_by_token_hash(&hash_share_token(token))
+        .await?;
+    let ip = client_ip(req);
+    let target = file_path.unwrap_or("");
+
+    let denial = match &found {
+        None => Some(ShareDenial::UnknownToken),
+        Some((link, _)) if link.revoked_at.is_some() => Some(ShareDenial::Revoked),
+        Some((link, _)) if link.expires_at.is_some_and(|at| at <= Utc::now()) => {
+            Some(ShareDenial::Expired)
+        }
+        Some((_, Some(password_hash))) => {
+            let supplied = req
+                .headers()
+                .get(SHARE_PASSWORD_HEADER)
+                .and_then(|v| v.to_str().ok());
+            match supplied {
+                None => Some(ShareDenial::PasswordRequired),
+                Some(password) => {
+                    let parsed = PasswordHash::new(password_hash).map_err(|e| {
+                        AppError::InternalError(format!("Invalid stored share password: {e}"))
+                    })?;
+                    Argon2::default()
+                        .verify_password(password.as_bytes(), &parsed)
+                        .err()
+                        .map(|_| ShareDenial::WrongPassword)
+                }
+            }
+        }
+        Some(_) => None,
+    };
+    let denial = denial.or_else(|| {
+        let (link, _) = found.as_ref()?;
+        let scope = LinkScope {
+            root: &link.path,
+            is_folder: link.is_folder,
+            url_prefix: "",
+        };
+        file_path
+            .filter(|path| !scope.contains(path))
+            .map(|_| ShareDenial::OutsideScope)
+    });
+
+    let link_desc = found
+        .as_ref()
+        .map(|(link, _)| format!("{} ({}:{})", link.id, link.vault_id, link.path))
+        .unwrap_or_else(|| "?".to_string());
+    let _ = state
+        .db
+        .write_audit_log(
+            None,
+            None,
+            if denial.is_none() {
+                "share_link_accessed"
+            } else {
+                "share_link_denied"
+            },
+            Some(&format!(
+                "Share link {} requested {}: {}",
+                link_desc,
+                if target.is_empty() { "/" } else { target },
+                denial.map_or("ok", ShareDenial::reason)
+            )),
+            ip.as_deref(),
+            denial.is_none(),
+        )
+        .await;
+
+    match (found, denial) {
+        (Some((link, _)), None) => {
+            state.db.record_share_link_access(&link.id).await?;
+            Ok(link)
+        }
+        (_, Some(denial)) => Err(denial.into_error()),
+        (None, None) => Err(ShareDenial::UnknownToken.into_error()),
+    }
+}
+
+/// Render a note of the vault with links and embeds confined to the share.
+fn render_shared_note(
+    vault_path: &str,
+    link: &ShareLink,
+    token: &str,
+    note_path: &str,
+) -> AppResult<SharedContent> {
+    let file = FileService::read_file(vault_path, note_path)?;
+    let url_prefix = format!("{}/files/", public_url(token));
+    let scope = LinkScope {
+        root: &link.path,
+        is_folder: link.is_folder,
+        url_prefix: &url_prefix,
+    };
+    let opts = RenderOptions {
+        vault_path: Some(vault_path),
+        current_file: Some(note_path),
+        link_scope: Some(&scope),
+        ..Default::default()
+    };
+
+    Ok(SharedContent {
+        path: note_path.to_string(),
+        permission: link.permission,
+        expires_at: link.expires_at,
+        html: Some(MarkdownService::to_html_with_link_resolution(
+            &file.content,
+            &opts,
+        )),
+        files: None,
+    })
+}
+
+fn find_subtree(nodes: Vec<FileNode>, path: &str) -> Option<Vec<FileNode>> {
+    for node in nodes {
+        if node.path == path {
+            return node.children;
+        }
+        if node.is_directory && path.starts_with(&format!("{}/", node.path)) {
+            return find_subtree(node.children.unwrap_or_default(), path);
+        }
+    }
+    None
+}
+
+/// Open a share link: a shared note is rendered, a shared folder is listed.
+#[get("/api/public/shares/{token}")]
+async fn open_public_share(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let token = path.into_inner();
+    let link = open_share_link(&state, &req, &token, None).await?;
+    let vault = state.db.get_vault(&link.vault_id).await?;
+
+    if !link.is_folder {
+        let content = render_shared_note(&vault.path, &link, &token, &link.path)?;
+        return Ok(HttpResponse::Ok().json(content));
+    }
+
+    let tree = FileService::get_file_tree(&vault.path)?;
+    let files = find_subtree(tree, &link.path)
+        .ok_or_else(|| AppError::NotFound(format!("Folder not found: {}", link.path)))?;
+    Ok(HttpResponse::Ok().json(SharedContent {
+        path: link.path.clone(),
+        permission: link.permission,
+        expires_at: link.expires_at,
+        html: None,
+        files: Some(files),
+    }))
+}
+
+/// Fetch a file within a share. Notes are rendered like the share root;
+/// other files (images, PDFs) are streamed for embedding.
+#[get("/api/public/shares/{token}/files/{file_path:.*}")]
+async fn get_public_share_file(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> AppResult<HttpResponse> {
+    let (token, file_path) = path.into_inner();
+    let link = open_share_link(&state, &req, &token, Some(&file_path)).await?;
+    let vault = state.db.get_vault(&link.vault_id).await?;
+
+    if file_path.ends_with(".md") {
+        let content = render_shared_note(&vault.path, &link, &token, &file_path)?;
+        return Ok(HttpResponse::Ok().json(content));
+    }
+
+    let full_path = FileService::resolve_existing_file(&vault.path, &file_path)?;
+    stream_file(
+        &req,
+        &full_path,
+        get_mime_type(&file_path),
+        DispositionType::Inline,
+    )
+    .await
+}
+
+fn requested_note(path: Option<&str>) -> Option<String> {
+    path.map(|p| p.trim().trim_matches('/'))
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+}
+
+fn require_comment_permission(link: &ShareLink) -> AppResult<()> {
+    if link.permission != ShareLinkPermission::Comment {
+        return Err(AppError::Forbidden(
+            "This share link does not allow comments".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Comment on the shared note, or on a note within the shared folder.
+#[post("/api/public/shares/{token}/comments")]
+async fn create_public_share_comment(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<CreateShareLinkCommentRequest>,
+) -> AppResult<HttpResponse> {
+    let token = path.into_inner();
+    let body = body.into_inner();
+    let requested = requested_note(body.path.as_deref());
+    let link = open_share_link(&state, &req, &token, requested.as_deref()).await?;
+    require_comment_permission(&link)?;
+
+    let note_path = match requested {
+        Some(path) => path,
+        None if !link.is_folder => link.path.clone(),
+        None => {
+            return Err(AppError::InvalidInput(
+                "A note path is required to comment through a folder link".to_string(),
+            ));
+        }
+    };
+    if !note_path.ends_with(".md") {
+        return Err(AppError::InvalidInput(
+            "Comments can only be left on notes".to_string(),
+        ));
+    }
+    let vault = state.db.get_vault(&link.vault_id).await?;
+    FileService::resolve_existing_file(&vault.path, &note_path)?;
+
+    let text = body.body.trim();
+    if text.is_empty() || text.chars().count() > MAX_COMMENT_CHARS {
+        return Err(AppError::InvalidInput(format!(
+            "Comment must be between 1 and {} characters",
+            MAX_COMMENT_CHARS
+        )));
+    }
+    let author_name = body
+        .author_name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    if author_name
+        .as_ref()
+        .is_some_and(|name| name.chars().count() > MAX_COMMENT_AUTHOR_CHARS)
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Author name must be at most {} characters",
+            MAX_COMMENT_AUTHOR_CHARS
+        )));
+    }
+
+    let comment = ShareLinkComment {
+        id: Uuid::new_v4().to_string(),
+        link_id: link.id.clone(),
+        path: note_path,
+        author_name,
+        body: text.to_string(),
+        created_at: Utc::now(),
+    };
+    state.db.create_share_link_comment(&comment).await?;
+
+    let _ = state
+        .db
+        .write_audit_log(
+            None,
+            None,
+            "share_link_commented",
+            Some(&format!(
+                "Comment {} on {}:{} through share link {}",
+                comment.id, link.vault_id, comment.path, link.id
+            )),
+            client_ip(&req).as_deref(),
+            true,
+        )
+        .await;
+
+    Ok(HttpResponse::Created().json(comment))
+}
+
+/// Comments left through a link with comment permission, oldest first.
+#[get("/api/public/shares/{token}/comments")]
+async fn list_public_share_comments(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ShareCommentQuery>,
+) -> AppResult<HttpResponse> {
+    let token = path.into_inner();
+    let requested = requested_note(query.path.as_deref());
+    let link = open_share_link(&state, &req, &token, requested.as_deref()).await?;
+    require_comment_permission(&link)?;
+
+    let comments: Vec<ShareLinkComment> = state
+        .db
+        .list_share_link_comments(&link.id)
+        .await?
+        .into_iter()
+        .filter(|comment| requested.as_ref().is_none_or(|path| comment.path == *path))
+        .collect();
+    Ok(HttpResponse::Ok().json(comments))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_share_link)
+        .service(list_share_links)
+        .service(revoke_share_link)
+        .service(list_share_link_comments)
+        .service(open_public_share)
+        .service(get_public_share_file)
+        .service(create_public_share_comment)
+        .service(list_public_share_comments);
+}
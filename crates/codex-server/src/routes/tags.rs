@@ -1,7 +1,8 @@
 use crate::error::AppResult;
+use crate::models::PathAccess;
 use crate::routes::vaults::AppState;
-use crate::services::frontmatter_service;
-use actix_web::{get, web, HttpResponse};
+use crate::services::{frontmatter_service, PathAclService};
+use actix_web::{get, web, HttpRequest, HttpResponse};
 use serde::Serialize;
 use std::collections::HashMap;
 use walkdir::WalkDir;
@@ -18,10 +19,12 @@ pub struct TagEntry {
 #[get("/api/vaults/{vault_id}/tags")]
 async fn list_tags(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
 
     // Map tag -> list of files containing that tag
     let mut tag_map: HashMap<String, Vec<String>> = HashMap::new();
@@ -45,6 +48,9 @@ async fn list_tags(
             .unwrap_or(entry.path())
             .to_string_lossy()
             .replace('\\', "/");
+        if !acl.can_read(&rel_path) {
+            continue;
+        }
 
         if let Ok(raw) = std::fs::read_to_string(entry.path()) {
             let (fm, body) =
@@ -75,12 +81,15 @@ async fn list_tags(
 #[get("/api/vaults/{vault_id}/backlinks")]
 async fn list_backlinks(
     state: web::Data<AppState>,
+    http_req: HttpRequest,
     vault_id: web::Path<String>,
     query: web::Query<BacklinksQuery>,
 ) -> AppResult<HttpResponse> {
     let vault_id = vault_id.into_inner();
     let vault = state.db.get_vault(&vault_id).await?;
     let target_path = query.path.trim();
+    let acl = PathAclService::for_request(&state.db, &vault_id, &http_req).await?;
+    acl.require(target_path, PathAccess::Read)?;
 
     // Derive the stem (filename without extension) for wiki-link matching
     let stem = std::path::Path::new(target_path)
@@ -121,8 +130,8 @@ async fn list_backlinks(
             .to_string_lossy()
             .replace('\\', "/");
 
-        // Don't include the file linking to itself
-        if rel_path.to_lowercase() == path_lower {
+        // Don't include the file linking to itself, or files the caller cannot see
+        if rel_path.to_lowercase() == path_lower || !acl.can_read(&rel_path) {
             continue;
         }
 
@@ -1,12 +1,16 @@
 use crate::config::AppConfig;
 use crate::db::Database;
 use crate::error::{AppError, AppResult};
-use crate::middleware::AuthenticatedUser;
+use crate::middleware::{ApiKeyContext, AuthenticatedUser};
 use crate::models::{
-    CreateVaultRequest, FileChangeEvent, MlUndoReceipt, ShareVaultWithGroupRequest,
-    ShareVaultWithUserRequest, WsMessage,
+    CreatePathAclRequest, CreateVaultRequest, FileChangeEvent, MlUndoReceipt, PathAclEntry,
+    ShareVaultWithGroupRequest, ShareVaultWithUserRequest, WsMessage,
+};
+use crate::services::schema_service::vault_scope;
+use crate::services::{
+    EntityTypeRegistry, GitAutoCommitter, PathAclService, RelationTypeRegistry, SchemaService,
+    SearchIndex,
 };
-use crate::services::{EntityTypeRegistry, RelationTypeRegistry, SearchIndex};
 use crate::watcher::FileWatcher;
 use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse};
 use codex_types::DocumentParser;
@@ -37,6 +41,21 @@ pub struct AppState {
     pub shutdown_tx: broadcast::Sender<()>,
     /// Active document parser (Markdown by default; swappable for MDX in future).
     pub document_parser: Arc<dyn DocumentParser>,
+    /// Debounces watcher events into automatic commits for git-enabled vaults.
+    pub git_autocommit: GitAutoCommitter,
+}
+
+impl AppState {
+    /// Entity types visible in `vault_id`: plugin types plus the vault's own
+    /// `.codex/types/` definitions, which take precedence.
+    pub fn entity_types(&self, vault_id: &str) -> EntityTypeRegistry {
+        self.entity_type_registry.for_vault(vault_id)
+    }
+
+    /// Relation types visible in `vault_id`, as for [`AppState::entity_types`].
+    pub fn relation_types(&self, vault_id: &str) -> RelationTypeRegistry {
+        self.relation_type_registry.for_vault(vault_id)
+    }
 }
 
 fn require_authenticated_user(req: &HttpRequest) -> AppResult<AuthenticatedUser> {
@@ -154,6 +173,16 @@ async fn create_vault(
     let indexed_count = state.search_index.index_vault(&vault.id, &resolved_path)?;
     tracing::info!("Indexed {} files in vault {}", indexed_count, vault.id);
 
+    // Load the vault's own entity and relation types, if it has any
+    SchemaService::load_vault_schemas(
+        &state.db,
+        &vault.id,
+        &resolved_path,
+        &state.entity_type_registry,
+        &state.relation_type_registry,
+    )
+    .await;
+
     Ok(HttpResponse::Created().json(vault))
 }
 
@@ -165,7 +194,13 @@ async fn list_vaults(
 ) -> AppResult<HttpResponse> {
     let vaults = if config.auth.enabled {
         let user = require_authenticated_user(&req)?;
-        state.db.list_vaults_for_user(&user.user_id).await?
+        let mut vaults = state.db.list_vaults_for_user(&user.user_id).await?;
+        if let Some(key) = req.extensions().get::<ApiKeyContext>() {
+            if !key.restrictions.vault_ids.is_empty() {
+                vaults.retain(|vault| key.restrictions.vault_ids.contains(&vault.id));
+            }
+        }
+        vaults
     } else {
         state.db.list_vaults().await?
     };
@@ -193,6 +228,11 @@ async fn delete_vault(
     // Remove from search index
     state.search_index.remove_vault(&vault_id)?;
 
+    // Drop its vault-local types
+    let scope = vault_scope(&vault_id);
+    state.entity_type_registry.remove_plugin(&scope).await;
+    state.relation_type_registry.remove_plugin(&scope).await;
+
     // Delete from database
     state.db.delete_vault(&vault_id).await?;
 
@@ -282,6 +322,51 @@ async fn revoke_vault_group_share(
     Ok(HttpResponse::Ok().json(shares))
 }
 
+#[get("/api/vaults/{id}/shares/paths")]
+async fn list_path_acls(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    state.db.get_vault(&vault_id).await?;
+    let entries = state.db.list_path_acls(&vault_id).await?;
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+#[post("/api/vaults/{id}/shares/paths")]
+async fn create_path_acl(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<CreatePathAclRequest>,
+) -> AppResult<HttpResponse> {
+    let vault_id = path.into_inner();
+    state.db.get_vault(&vault_id).await?;
+
+    let body = body.into_inner();
+    let mut entry = PathAclEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        vault_id: vault_id.clone(),
+        pattern: body.pattern,
+        principal_type: body.principal_type,
+        principal_id: body.principal_id,
+        access: body.access,
+        created_at: chrono::Utc::now(),
+    };
+    PathAclService::validate(&mut entry)?;
+    state.db.create_path_acl(&entry).await?;
+    Ok(HttpResponse::Created().json(entry))
+}
+
+#[delete("/api/vaults/{id}/shares/paths/{acl_id}")]
+async fn delete_path_acl(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> AppResult<HttpResponse> {
+    let (vault_id, acl_id) = path.into_inner();
+    state.db.delete_path_acl(&vault_id, &acl_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
 /// Transfer vault ownership to another user.
 #[post("/api/vaults/{vault_id}/transfer-ownership")]
 async fn transfer_vault_ownership(
@@ -396,6 +481,9 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
         .service(share_vault_with_group)
         .service(revoke_vault_user_share)
         .service(revoke_vault_group_share)
+        .service(list_path_acls)
+        .service(create_path_acl)
+        .service(delete_path_acl)
         .service(transfer_vault_ownership)
         .service(set_vault_visibility);
 }
@@ -0,0 +1,286 @@
+use crate::config::AppConfig;
+use crate::error::{AppError, AppResult};
+use crate::middleware::AuthenticatedUser;
+use crate::models::webauthn::WebAuthnPurpose;
+use crate::models::{
+    RenameWebAuthnCredentialRequest, WebAuthnCredentialInfo, WebAuthnLoginFinishRequest,
+    WebAuthnLoginStartRequest, WebAuthnRegisterFinishRequest, WebAuthnRegisterStartRequest,
+};
+use crate::routes::auth::issue_tokens_public;
+use crate::routes::vaults::AppState;
+use crate::services::WebAuthnService;
+use actix_web::{delete, get, patch, post, web, HttpMessage, HttpRequest, HttpResponse};
+
+fn require_user(req: &HttpRequest) -> AppResult<AuthenticatedUser> {
+    req.extensions()
+        .get::<AuthenticatedUser>()
+        .cloned()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))
+}
+
+fn authenticator_name(name: Option<&str>, existing: usize) -> AppResult<String> {
+    match name.map(str::trim) {
+        Some("") | None => Ok(format!("Authenticator {}", existing + 1)),
+        Some(name) if name.chars().count() > 64 => Err(AppError::InvalidInput(
+            "Authenticator name must be at most 64 characters".to_string(),
+        )),
+        Some(name) => Ok(name.to_string()),
+    }
+}
+
+/// Begin registering a new authenticator for the signed-in user.
+#[post("/api/auth/webauthn/register/start")]
+async fn register_start(
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    _body: Option<web::Json<WebAuthnRegisterStartRequest>>,
+) -> AppResult<HttpResponse> {
+    let user = require_user(&req)?;
+    let ceremony =
+        WebAuthnService::begin_registration(&state.db, &config.auth, &user.user_id, &user.username)
+            .await?;
+    Ok(HttpResponse::Ok().json(ceremony))
+}
+
+/// Verify the authenticator's response and store the credential.
+#[post("/api/auth/webauthn/register/finish")]
+async fn register_finish(
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    body: web::Json<WebAuthnRegisterFinishRequest>,
+) -> AppResult<HttpResponse> {
+    let user = require_user(&req)?;
+    let existing = state.db.list_webauthn_credentials(&user.user_id).await?;
+    let name = authenticator_name(body.name.as_deref(), existing.len())?;
+
+    let credential = WebAuthnService::finish_registration(
+        &state.db,
+        &config.auth,
+        &user.user_id,
+        &body.ceremony_id,
+        &name,
+        &body.credential,
+    )
+    .await?;
+
+    let _ = state
+        .db
+        .write_audit_log(
+            Some(&user.user_id),
+            Some(&user.username),
+            "webauthn_registered",
+            Some(&format!("Registered authenticator '{name}'")),
+            None,
+            true,
+        )
+        .await;
+
+    Ok(HttpResponse::Created().json(credential.info()))
+}
+
+/// List the signed-in user's authenticators.
+#[get("/api/auth/webauthn/credentials")]
+async fn list_credentials(state: web::Data<AppState>, req: HttpRequest) -> AppResult<HttpResponse> {
+    let user = require_user(&req)?;
+    let credentials: Vec<WebAuthnCredentialInfo> = state
+        .db
+        .list_webauthn_credentials(&user.user_id)
+        .await?
+        .iter()
+        .map(|c| c.info())
+        .collect();
+    Ok(HttpResponse::Ok().json(credentials))
+}
+
+#[patch("/api/auth/webauthn/credentials/{credential_id}")]
+async fn rename_credential(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<RenameWebAuthnCredentialRequest>,
+) -> AppResult<HttpResponse> {
+    let user = require_user(&req)?;
+    if body.name.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Authenticator name cannot be empty".to_string(),
+        ));
+    }
+    let name = authenticator_name(Some(&body.name), 0)?;
+    state
+        .db
+        .rename_webauthn_credential(&user.user_id, &path, &name)
+        .await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "success": true })))
+}
+
+#[delete("/api/auth/webauthn/credentials/{credential_id}")]
+async fn delete_credential(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    let user = require_user(&req)?;
+    state
+        .db
+        .delete_webauthn_credential(&user.user_id, &path)
+        .await?;
+
+    let _ = state
+        .db
+        .write_audit_log(
+            Some(&user.user_id),
+            Some(&user.username),
+            "webauthn_removed",
+            Some(&format!("Removed authenticator {}", path.as_str())),
+            None,
+            true,
+        )
+        .await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Begin a passwordless sign-in.
+#[post("/api/auth/webauthn/login/start")]
+async fn login_start(
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    body: Option<web::Json<WebAuthnLoginStartRequest>>,
+) -> AppResult<HttpResponse> {
+    if !config.auth.webauthn_passwordless {
+        return Err(AppError::Forbidden(
+            "Passwordless sign-in is disabled".to_string(),
+        ));
+    }
+
+    // An unknown username gets the same response as no username (any
+    // discoverable passkey may answer) rather than an error, so usernames
+    // cannot be probed.
+    let username = body.as_ref().and_then(|b| b.username.as_deref());
+    let user_id = match username.map(str::trim).filter(|u| !u.is_empty()) {
+        Some(username) => state
+            .db
+            .get_user_auth_by_username(username)
+            .await?
+            .map(|(id, _, _)| id),
+        None => None,
+    };
+
+    let ceremony = WebAuthnService::begin_login(
+        &state.db,
+        &config.auth,
+        user_id.as_deref(),
+        WebAuthnPurpose::Login,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(ceremony))
+}
+
+/// Finish a passwordless sign-in, or the authenticator step of a password
+/// sign-in, and issue tokens.
+#[post("/api/auth/webauthn/login/finish")]
+async fn login_finish(
+    state: web::Data<AppState>,
+    config: web::Data<AppConfig>,
+    body: web::Json<WebAuthnLoginFinishRequest>,
+) -> AppResult<HttpResponse> {
+    let verified =
+        WebAuthnService::finish_login(&state.db, &config.auth, &body.ceremony_id, &body.credential)
+            .await;
+    let verified = match verified {
+        Ok(verified) => verified,
+        Err(e) => {
+            let _ = state
+                .db
+                .write_audit_log(
+                    None,
+                    None,
+                    "webauthn_login_failed",
+                    Some(&e.to_string()),
+                    None,
+                    false,
+                )
+                .await;
+            return Err(e);
+        }
+    };
+    if verified.purpose == WebAuthnPurpose::Login && !config.auth.webauthn_passwordless {
+        return Err(AppError::Forbidden(
+            "Passwordless sign-in is disabled".to_string(),
+        ));
+    }
+
+    let user_id = verified.credential.user_id.clone();
+    if !state.db.is_user_active(&user_id).await? {
+        return Err(AppError::Unauthorized(
+            "Account is deactivated. Contact an administrator.".to_string(),
+        ));
+    }
+    if let Some(locked_until) = state.db.get_lockout_status(&user_id).await? {
+        return Err(AppError::Unauthorized(format!(
+            "Account is temporarily locked until {}. Try again later.",
+            locked_until.format("%Y-%m-%d %H:%M:%S UTC")
+        )));
+    }
+    let username = state
+        .db
+        .get_user_by_id(&user_id)
+        .await?
+        .map(|(_, username)| username)
+        .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+    let auth_method = match verified.purpose {
+        WebAuthnPurpose::SecondFactor => "password+webauthn",
+        _ => "webauthn",
+    };
+    // A passkey alone replaces the password, not the TOTP code: accounts
+    // with TOTP go through the same pending step as a password sign-in.
+    let totp_required = verified.purpose == WebAuthnPurpose::Login
+        && state
+            .db
+            .get_totp_state(&user_id)
+            .await
+            .map(|(enabled, _, _)| enabled)
+            .unwrap_or(false);
+    let (mut response, refresh_jti, refresh_exp) =
+        issue_tokens_public(&user_id, &username, auth_method, &config.auth)?;
+    response.totp_required = totp_required;
+    let _ = state
+        .db
+        .create_session(&refresh_jti, &user_id, refresh_exp)
+        .await;
+
+    let _ = state
+        .db
+        .write_audit_log(
+            Some(&user_id),
+            Some(&username),
+            "login_success",
+            Some(&format!(
+                "Authenticated via {auth_method} ('{}'){}",
+                verified.credential.name,
+                if totp_required {
+                    ", TOTP verification pending"
+                } else {
+                    ""
+                }
+            )),
+            None,
+            true,
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(register_start)
+        .service(register_finish)
+        .service(list_credentials)
+        .service(rename_credential)
+        .service(delete_credential)
+        .service(login_start)
+        .service(login_finish);
+}
@@ -52,6 +52,11 @@ async fn websocket(
                             Ok(Some(_)) => {}
                             _ => continue,
                         }
+
+                        match crate::services::PathAclService::for_user(&state.db, &change_event.vault_id, Some(&current_user.user_id)).await {
+                            Ok(acl) if acl.can_read(&change_event.path) => {}
+                            _ => continue,
+                        }
                     }
 
                     let etag = match &change_event.event_type {
@@ -0,0 +1,505 @@
+use crate::error::{AppError, AppResult};
+use crate::services::file_service::FileService;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::{Compression, Crc};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Folders left out when a whole vault is exported.
+const EXCLUDED_VAULT_DIRS: &[&str] = &[".trash", ".git"];
+
+/// Chunk size used when copying file contents into an archive.
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::TarGz => "tar.gz",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "application/zip",
+            ArchiveFormat::TarGz => "application/gzip",
+        }
+    }
+}
+
+/// A vault file to be written into an archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub full_path: PathBuf,
+    /// Path inside the archive, vault-relative with `/` separators.
+    pub name: String,
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+}
+
+/// Running totals reported while an archive is written.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArchiveProgress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+pub struct ArchiveService;
+
+impl ArchiveService {
+    /// Resolve the files to archive. `paths` may name files or folders;
+    /// `None` selects the whole vault except the trash and `.git`.
+    /// Missing paths are skipped and duplicates are archived once.
+    pub fn collect_entries(
+        vault_path: &str,
+        paths: Option<&[String]>,
+    ) -> AppResult<Vec<ArchiveEntry>> {
+        let vault_root = Path::new(vault_path);
+        let mut entries = BTreeMap::new();
+
+        let roots: Vec<(PathBuf, bool)> = match paths {
+            Some(paths) => paths
+                .iter()
+                .map(|p| Ok((FileService::resolve_path(vault_path, p)?, false)))
+                .collect::<AppResult<_>>()?,
+            None => vec![(vault_root.to_path_buf(), true)],
+        };
+
+        for (root, whole_vault) in roots {
+            if !root.exists() {
+                continue;
+            }
+            let walker = WalkDir::new(&root).into_iter().filter_entry(|entry| {
+                !(whole_vault
+                    && entry.depth() == 1
+                    && entry.file_type().is_dir()
+                    && EXCLUDED_VAULT_DIRS
+                        .iter()
+                        .any(|dir| entry.file_name() == *dir))
+            });
+            for entry in walker.filter_map(|e| e.ok()) {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let relative = entry
+                    .path()
+                    .strip_prefix(vault_root)
+                    .map_err(|_| AppError::InternalError("Path error".to_string()))?;
+                let name = relative
+                    .to_str()
+                    .ok_or(AppError::InternalError("Invalid UTF-8 in path".to_string()))?
+                    .replace('\\', "/");
+                let metadata = entry.metadata().map_err(io::Error::from)?;
+                let modified = metadata
+                    .modified()
+                    .map(DateTime::<Utc>::from)
+                    .unwrap_or_else(|_| Utc::now());
+                entries.insert(
+                    name.clone(),
+                    ArchiveEntry {
+                        full_path: entry.path().to_path_buf(),
+                        name,
+                        size: metadata.len(),
+                        modified,
+                    },
+                );
+            }
+        }
+
+        Ok(entries.into_values().collect())
+    }
+
+    /// Write `entries` as an archive into `out`, calling `on_progress`
+    /// after every file. Nothing is buffered beyond one copy chunk, so
+    /// `out` can be a network stream.
+    pub fn write_archive<W: Write>(
+        format: ArchiveFormat,
+        entries: &[ArchiveEntry],
+        out: W,
+        mut on_progress: impl FnMut(&ArchiveProgress),
+    ) -> io::Result<()> {
+        let mut progress = ArchiveProgress {
+            files_total: entries.len(),
+            bytes_total: entries.iter().map(|e| e.size).sum(),
+            ..Default::default()
+        };
+
+        match format {
+            ArchiveFormat::Zip => {
+                let mut zip = StreamingZipWriter::new(out);
+                for entry in entries {
+                    let mut file = File::open(&entry.full_path)?;
+                    progress.bytes_done += zip.add_file(&entry.name, entry.modified, &mut file)?;
+                    progress.files_done += 1;
+                    on_progress(&progress);
+                }
+                zip.finish()?.flush()?;
+            }
+            ArchiveFormat::TarGz => {
+                let mut tar = tar::Builder::new(GzEncoder::new(out, Compression::default()));
+                for entry in entries {
+                    tar.append_path_with_name(&entry.full_path, &entry.name)?;
+                    progress.bytes_done += entry.size;
+                    progress.files_done += 1;
+                    on_progress(&progress);
+                }
+                tar.into_inner()?.finish()?.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ── Streaming zip writer ─────────────────────────────────────────────────────
+//
+// The `zip` crate needs a seekable output to patch each local header with
+// the entry's CRC and sizes. This writer emits those values in a data
+// descriptor after the entry instead (general purpose flag bit 3), so the
+// archive can be produced front to back. Sizes are unknown when the local
+// header is written, so every local header carries a zip64 extra field
+// and every data descriptor uses 8-byte sizes (APPNOTE 4.3.9.2). In the
+// central directory, zip64 records are added only when a size, offset or
+// entry count overflows the classic format.
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+
+/// Sizes are in a trailing data descriptor; names are UTF-8.
+const FLAGS: u16 = 0x0008 | 0x0800;
+const METHOD_DEFLATE: u16 = 8;
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+/// Header plus the uncompressed and compressed sizes.
+const LOCAL_ZIP64_EXTRA_LEN: u16 = 20;
+
+struct CentralDirectoryEntry {
+    name: String,
+    dos_time: u16,
+    dos_date: u16,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    header_offset: u64,
+}
+
+/// Counts the bytes passed through to the wrapped writer.
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct StreamingZipWriter<W: Write> {
+    out: CountingWriter<W>,
+    entries: Vec<CentralDirectoryEntry>,
+}
+
+impl<W: Write> StreamingZipWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out: CountingWriter {
+                inner: out,
+                written: 0,
+            },
+            entries: Vec::new(),
+        }
+    }
+
+    /// Deflate everything read from `contents` into a new entry.
+    /// Returns the uncompressed size.
+    pub fn add_file(
+        &mut self,
+        name: &str,
+        modified: DateTime<Utc>,
+        contents: &mut impl Read,
+    ) -> io::Result<u64> {
+        let header_offset = self.out.written;
+        let (dos_time, dos_date) = dos_date_time(modified);
+
+        self.out
+            .write_all(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes())?;
+        self.out.write_all(&VERSION_ZIP64.to_le_bytes())?;
+        self.out.write_all(&FLAGS.to_le_bytes())?;
+        self.out.write_all(&METHOD_DEFLATE.to_le_bytes())?;
+        self.out.write_all(&dos_time.to_le_bytes())?;
+        self.out.write_all(&dos_date.to_le_bytes())?;
+        // CRC and sizes follow in the data descriptor; the sizes point at
+        // the zip64 extra field, which is zeroed for the same reason.
+        self.out.write_all(&0u32.to_le_bytes())?;
+        self.out.write_all(&u32::MAX.to_le_bytes())?;
+        self.out.write_all(&u32::MAX.to_le_bytes())?;
+        self.out.write_all(&(name.len() as u16).to_le_bytes())?;
+        self.out.write_all(&LOCAL_ZIP64_EXTRA_LEN.to_le_bytes())?;
+        self.out.write_all(name.as_bytes())?;
+        self.out.write_all(&ZIP64_EXTRA_FIELD_ID.to_le_bytes())?;
+        self.out.write_all(&16u16.to_le_bytes())?;
+        self.out.write_all(&[0u8; 16])?;
+
+        let data_start = self.out.written;
+        let mut crc = Crc::new();
+        let mut uncompressed_size = 0u64;
+        let mut encoder = DeflateEncoder::new(&mut self.out, Compression::new(6));
+        let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+        loop {
+            let n = contents.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            crc.update(&buffer[..n]);
+            uncompressed_size += n as u64;
+            encoder.write_all(&buffer[..n])?;
+        }
+        encoder.finish()?;
+        let compressed_size = self.out.written - data_start;
+        let crc32 = crc.sum();
+
+        self.out
+            .write_all(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes())?;
+        self.out.write_all(&crc32.to_le_bytes())?;
+        self.out.write_all(&compressed_size.to_le_bytes())?;
+        self.out.write_all(&uncompressed_size.to_le_bytes())?;
+
+        self.entries.push(CentralDirectoryEntry {
+            name: name.to_string(),
+            dos_time,
+            dos_date,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            header_offset,
+        });
+        Ok(uncompressed_size)
+    }
+
+    /// Write the central directory and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let directory_offset = self.out.written;
+        for entry in &self.entries {
+            write_central_directory_entry(&mut self.out, entry)?;
+        }
+        let directory_size = self.out.written - directory_offset;
+        let entry_count = self.entries.len() as u64;
+
+        let needs_zip64 = entry_count >= u16::MAX as u64
+            || directory_size >= u32::MAX as u64
+            || directory_offset >= u32::MAX as u64;
+        if needs_zip64 {
+            let zip64_record_offset = self.out.written;
+            let out = &mut self.out;
+            out.write_all(&ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())?;
+            out.write_all(&44u64.to_le_bytes())?;
+            out.write_all(&VERSION_ZIP64.to_le_bytes())?;
+            out.write_all(&VERSION_ZIP64.to_le_bytes())?;
+            out.write_all(&0u32.to_le_bytes())?;
+            out.write_all(&0u32.to_le_bytes())?;
+            out.write_all(&entry_count.to_le_bytes())?;
+            out.write_all(&entry_count.to_le_bytes())?;
+            out.write_all(&directory_size.to_le_bytes())?;
+            out.write_all(&directory_offset.to_le_bytes())?;
+
+            out.write_all(&ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE.to_le_bytes())?;
+            out.write_all(&0u32.to_le_bytes())?;
+            out.write_all(&zip64_record_offset.to_le_bytes())?;
+            out.write_all(&1u32.to_le_bytes())?;
+        }
+
+        let out = &mut self.out;
+        out.write_all(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())?;
+        out.write_all(&0u16.to_le_bytes())?;
+        out.write_all(&0u16.to_le_bytes())?;
+        let count16 = entry_count.min(u16::MAX as u64) as u16;
+        out.write_all(&count16.to_le_bytes())?;
+        out.write_all(&count16.to_le_bytes())?;
+        out.write_all(&clamp_u32(directory_size).to_le_bytes())?;
+        out.write_all(&clamp_u32(directory_offset).to_le_bytes())?;
+        out.write_all(&0u16.to_le_bytes())?;
+
+        Ok(self.out.inner)
+    }
+}
+
+fn write_central_directory_entry(
+    out: &mut impl Write,
+    entry: &CentralDirectoryEntry,
+) -> io::Result<()> {
+    // Values that overflow 32 bits move into the zip64 extra field, in
+    // the order the spec prescribes.
+    let mut zip64_extra = Vec::new();
+    for value in [
+        entry.uncompressed_size,
+        entry.compressed_size,
+        entry.header_offset,
+    ] {
+        if value >= u32::MAX as u64 {
+            zip64_extra.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let mut extra = Vec::new();
+    let version = if zip64_extra.is_empty() {
+        VERSION_DEFAULT
+    } else {
+        extra.extend_from_slice(&ZIP64_EXTRA_FIELD_ID.to_le_bytes());
+        extra.extend_from_slice(&(zip64_extra.len() as u16).to_le_bytes());
+        extra.extend_from_slice(&zip64_extra);
+        VERSION_ZIP64
+    };
+
+    out.write_all(&CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())?;
+    out.write_all(&version.to_le_bytes())?;
+    out.write_all(&version.to_le_bytes())?;
+    out.write_all(&FLAGS.to_le_bytes())?;
+    out.write_all(&METHOD_DEFLATE.to_le_bytes())?;
+    out.write_all(&entry.dos_time.to_le_bytes())?;
+    out.write_all(&entry.dos_date.to_le_bytes())?;
+    out.write_all(&entry.crc32.to_le_bytes())?;
+    out.write_all(&clamp_u32(entry.compressed_size).to_le_bytes())?;
+    out.write_all(&clamp_u32(entry.uncompressed_size).to_le_bytes())?;
+    out.write_all(&(entry.name.len() as u16).to_le_bytes())?;
+    out.write_all(&(extra.len() as u16).to_le_bytes())?;
+    out.write_all(&0u16.to_le_bytes())?;
+    out.write_all(&0u16.to_le_bytes())?;
+    out.write_all(&0u16.to_le_bytes())?;
+    out.write_all(&0u32.to_le_bytes())?;
+    out.write_all(&clamp_u32(entry.header_offset).to_le_bytes())?;
+    out.write_all(entry.name.as_bytes())?;
+    out.write_all(&extra)?;
+    Ok(())
+}
+
+fn clamp_u32(value: u64) -> u32 {
+    value.min(u32::MAX as u64) as u32
+}
+
+/// MS-DOS time and date; timestamps before 1980 are clamped to 1980-01-01.
+fn dos_date_time(at: DateTime<Utc>) -> (u16, u16) {
+    if at.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = ((at.hour() as u16) << 11) | ((at.minute() as u16) << 5) | (at.second() as u16 / 2);
+    let date = (((at.year() - 1980) as u16) << 9) | ((at.month() as u16) << 5) | at.day() as u16;
+    (time, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    #[test]
+    fn streamed_zip_is_readable_and_whole_vault_skips_trash() {
+        let vault = TempDir::new().unwrap();
+        std::fs::create_dir_all(vault.path().join("notes")).unwrap();
+        std::fs::create_dir_all(vault.path().join(".trash")).unwrap();
+        std::fs::write(vault.path().join("notes/a.md"), "alpha".repeat(1000)).unwrap();
+        std::fs::write(vault.path().join("b.md"), "bravo").unwrap();
+        std::fs::write(vault.path().join(".trash/old.md"), "gone").unwrap();
+        let vault_path = vault.path().to_str().unwrap();
+
+        let entries = ArchiveService::collect_entries(vault_path, None).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["b.md", "notes/a.md"]);
+
+        let mut reports = Vec::new();
+        let mut out = Vec::new();
+        ArchiveService::write_archive(ArchiveFormat::Zip, &entries, &mut out, |p| {
+            reports.push((p.files_done, p.bytes_done))
+        })
+        .unwrap();
+        assert_eq!(reports, vec![(1, 5), (2, 5005)]);
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(out)).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut contents = String::new();
+        archive
+            .by_name("notes/a.md")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "alpha".repeat(1000));
+
+        // Explicit paths may reach into excluded folders and overlap.
+        let selected = ArchiveService::collect_entries(
+            vault_path,
+            Some(&[
+                "notes".to_string(),
+                "notes/a.md".to_string(),
+                ".trash/old.md".to_string(),
+                "missing.md".to_string(),
+            ]),
+        )
+        .unwrap();
+        let names: Vec<&str> = selected.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec![".trash/old.md", "notes/a.md"]);
+    }
+
+    #[test]
+    fn local_headers_carry_zip64_extra_for_8_byte_descriptors() {
+        let mut writer = StreamingZipWriter::new(Vec::new());
+        let size = writer
+            .add_file("a.md", Utc::now(), &mut "alpha".as_bytes())
+            .unwrap();
+        let out = writer.finish().unwrap();
+        let u16_at = |at: usize| u16::from_le_bytes(out[at..at + 2].try_into().unwrap());
+        let u32_at = |at: usize| u32::from_le_bytes(out[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(out[at..at + 8].try_into().unwrap());
+
+        assert_eq!(u32_at(0), LOCAL_FILE_HEADER_SIGNATURE);
+        assert_eq!(u16_at(4), VERSION_ZIP64);
+        assert_eq!((u32_at(18), u32_at(22)), (u32::MAX, u32::MAX));
+        assert_eq!(u16_at(26), 4);
+        assert_eq!(u16_at(28), LOCAL_ZIP64_EXTRA_LEN);
+        let extra = 30 + 4;
+        assert_eq!(u16_at(extra), ZIP64_EXTRA_FIELD_ID);
+        assert_eq!(u16_at(extra + 2), 16);
+        assert_eq!((u64_at(extra + 4), u64_at(extra + 12)), (0, 0));
+
+        let directory = out
+            .windows(4)
+            .position(|w| w == CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())
+            .unwrap();
+        let compressed = u32_at(directory + 20) as usize;
+        let descriptor = extra + LOCAL_ZIP64_EXTRA_LEN as usize + compressed;
+        assert_eq!(u32_at(descriptor), DATA_DESCRIPTOR_SIGNATURE);
+        assert_eq!(u64_at(descriptor + 8), compressed as u64);
+        assert_eq!(u64_at(descriptor + 16), size);
+        assert_eq!(descriptor + 24, directory);
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(out)).unwrap();
+        let mut contents = String::new();
+        archive
+            .by_index(0)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "alpha");
+    }
+}
@@ -0,0 +1,191 @@
+use crate::error::{AppError, AppResult};
+use crate::models::AuditLogEntry;
+
+/// Entries fetched per query while an export is streamed.
+pub const AUDIT_EXPORT_BATCH: i64 = 500;
+
+const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "timestamp",
+    "user_id",
+    "username",
+    "event_type",
+    "outcome",
+    "vault_id",
+    "path",
+    "ip_address",
+    "request_id",
+    "detail",
+    "prev_hash",
+    "hash",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditExportFormat {
+    Ndjson,
+    Csv,
+}
+
+impl AuditExportFormat {
+    pub fn parse(value: &str) -> AppResult<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            "csv" => Ok(Self::Csv),
+            other => Err(AppError::InvalidInput(format!(
+                "Unsupported audit log export format '{other}'; use 'ndjson' or 'csv'"
+            ))),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Ndjson => "ndjson",
+            Self::Csv => "csv",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Ndjson => "application/x-ndjson",
+            Self::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+pub struct AuditService;
+
+impl AuditService {
+    /// The bytes an export starts with: the CSV header row, or nothing.
+    pub fn export_header(format: AuditExportFormat) -> String {
+        match format {
+            AuditExportFormat::Ndjson => String::new(),
+            AuditExportFormat::Csv => format!("{}\r\n", CSV_COLUMNS.join(",")),
+        }
+    }
+
+    /// One entry as an NDJSON line or CSV record, including the line ending.
+    pub fn export_line(format: AuditExportFormat, entry: &AuditLogEntry) -> AppResult<String> {
+        match format {
+            AuditExportFormat::Ndjson => {
+                let mut line = serde_json::to_string(entry)?;
+                line.push('\n');
+                Ok(line)
+            }
+            AuditExportFormat::Csv => {
+                let id = entry.id.to_string();
+                let timestamp = entry.timestamp.to_rfc3339();
+                let fields = [
+                    id.as_str(),
+                    timestamp.as_str(),
+                    entry.user_id.as_deref().unwrap_or_default(),
+                    entry.username.as_deref().unwrap_or_default(),
+                    entry.event_type.as_str(),
+                    entry.outcome.as_str(),
+                    entry.vault_id.as_deref().unwrap_or_default(),
+                    entry.path.as_deref().unwrap_or_default(),
+                    entry.ip_address.as_deref().unwrap_or_default(),
+                    entry.request_id.as_deref().unwrap_or_default(),
+                    entry.detail.as_deref().unwrap_or_default(),
+                    entry.prev_hash.as_deref().unwrap_or_default(),
+                    entry.hash.as_deref().unwrap_or_default(),
+                ];
+                let mut line = fields.map(csv_field).join(",");
+                line.push_str("\r\n");
+                Ok(line)
+            }
+        }
+    }
+
+    /// Cursors are the id of the last entry on the previous page.
+    pub fn parse_cursor(cursor: &str) -> AppResult<i64> {
+        cursor
+            .parse::<i64>()
+            .map_err(|_| AppError::InvalidInput(format!("Invalid audit log cursor '{cursor}'")))
+    }
+}
+
+/// Quote a CSV field per RFC 4180 when it needs it. Fields starting with a
+/// formula character are prefixed with `'` so spreadsheets show them as text.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AuditOutcome;
+    use chrono::{TimeZone, Utc};
+
+    fn entry() -> AuditLogEntry {
+        AuditLogEntry {
+            id: 7,
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            user_id: Some("u1".to_string()),
+            username: Some("ada".to_string()),
+            event_type: "file_write".to_string(),
+            detail: Some("PUT /api/vaults/v1/files/a,b.md -> 200".to_string()),
+            ip_address: Some("10.0.0.1".to_string()),
+            success: true,
+            outcome: AuditOutcome::Success,
+            vault_id: Some("v1".to_string()),
+            path: Some("say \"hi\".md".to_string()),
+            request_id: Some("req-1".to_string()),
+            prev_hash: None,
+            hash: None,
+        }
+    }
+
+    #[test]
+    fn csv_quotes_and_neutralises_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(csv_field("=SUM(A1)"), "'=SUM(A1)");
+    }
+
+    #[test]
+    fn csv_record_matches_header() {
+        let header = AuditService::export_header(AuditExportFormat::Csv);
+        let line = AuditService::export_line(AuditExportFormat::Csv, &entry()).unwrap();
+        assert_eq!(
+            header,
+            "id,timestamp,user_id,username,event_type,outcome,vault_id,path,ip_address,\
+             request_id,detail,prev_hash,hash\r\n"
+        );
+        assert_eq!(
+            line,
+            "7,2026-01-02T03:04:05+00:00,u1,ada,file_write,success,v1,\"say \"\"hi\"\".md\",\
+             10.0.0.1,req-1,\"PUT /api/vaults/v1/files/a,b.md -> 200\",,\r\n"
+        );
+    }
+
+    #[test]
+    fn ndjson_is_one_entry_per_line() {
+        let line = AuditService::export_line(AuditExportFormat::Ndjson, &entry()).unwrap();
+        assert!(line.ends_with('\n'));
+        let parsed: AuditLogEntry = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(parsed.id, 7);
+        assert_eq!(parsed.request_id.as_deref(), Some("req-1"));
+    }
+
+    #[test]
+    fn parses_formats_and_cursors() {
+        assert_eq!(
+            AuditExportFormat::parse("CSV").unwrap(),
+            AuditExportFormat::Csv
+        );
+        assert!(AuditExportFormat::parse("xml").is_err());
+        assert_eq!(AuditService::parse_cursor("42").unwrap(), 42);
+        assert!(AuditService::parse_cursor("abc").is_err());
+    }
+}
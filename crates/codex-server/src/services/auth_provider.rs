@@ -217,8 +217,9 @@ async fn authenticate_with_ldap_provider(
     password: &str,
 ) -> AppResult<AuthenticatedPrincipal> {
     // Verify credentials against LDAP.
-    let canonical_username =
+    let identity =
         crate::services::ldap_provider::authenticate_ldap(auth_cfg, username, password).await?;
+    let canonical_username = identity.username;
 
     // Find or create local user row (LDAP users get a placeholder password hash
     // since their password is managed by the directory).
@@ -245,6 +246,24 @@ async fn authenticate_with_ldap_provider(
         }
     };
 
+    // The directory vouches for the password, not for access: a deactivated
+    // (e.g. SCIM-deprovisioned) account stays locked out.
+    if !db.is_user_active(&user_id).await? {
+        return Err(AppError::Unauthorized(
+            "Account is deactivated. Contact an administrator.".to_string(),
+        ));
+    }
+
+    crate::services::GroupMappingService::sync(
+        db,
+        auth_cfg,
+        &user_id,
+        &canonical_username,
+        "ldap",
+        &identity.groups,
+    )
+    .await?;
+
     let _ = db
         .write_audit_log(
             Some(&user_id),
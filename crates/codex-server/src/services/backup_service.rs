@@ -0,0 +1,739 @@
+use crate::config::{BackupConfig, DatabaseBackend};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::backup::{
+    RestoreReport, SnapshotFile, SnapshotManifest, SnapshotObject, SnapshotSummary, SnapshotVault,
+    SnapshotVerifyReport,
+};
+use crate::services::file_service::FileService;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tracing::{info, warn};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+/// Serialises snapshot creation and pruning so garbage collection never
+/// removes an object that an in-flight snapshot has just written.
+static REPOSITORY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Vault-relative prefixes that are never captured or touched by a restore.
+const EXCLUDED_PREFIXES: &[&str] = &[".git", ".trash", ".obsidian/uploads"];
+
+/// A vault to include in a snapshot.
+#[derive(Debug, Clone)]
+pub struct SnapshotSource {
+    pub vault_id: String,
+    pub name: String,
+    pub path: String,
+}
+
+/// Incremental, content-addressed snapshot repository.
+///
+/// Layout of `BackupConfig::directory`:
+/// - `objects/<aa>/<sha256>.gz` — gzip-compressed file contents
+/// - `snapshots/<id>.json` — one manifest per snapshot
+///
+/// All methods are blocking; call them from `spawn_blocking`/`web::block`.
+pub struct BackupService {
+    root: PathBuf,
+    config: BackupConfig,
+}
+
+impl BackupService {
+    pub fn new(config: &BackupConfig) -> Self {
+        Self {
+            root: PathBuf::from(&config.directory),
+            config: config.clone(),
+        }
+    }
+
+    fn snapshots_dir(&self) -> PathBuf {
+        self.root.join("snapshots")
+    }
+
+    fn objects_dir(&self) -> PathBuf {
+        self.root.join("objects")
+    }
+
+    fn tmp_dir(&self) -> PathBuf {
+        self.root.join("tmp")
+    }
+
+    fn object_path(&self, sha256: &str) -> PathBuf {
+        self.objects_dir()
+            .join(&sha256[..2])
+            .join(format!("{}.gz", sha256))
+    }
+
+    fn manifest_path(&self, snapshot_id: &str) -> AppResult<PathBuf> {
+        if snapshot_id.is_empty()
+            || !snapshot_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid snapshot id: {}",
+                snapshot_id
+            )));
+        }
+        Ok(self.snapshots_dir().join(format!("{}.json", snapshot_id)))
+    }
+
+    /// Fresh path inside the repository for staging a temporary file.
+    pub fn temp_path(&self, suffix: &str) -> AppResult<PathBuf> {
+        fs::create_dir_all(self.tmp_dir())?;
+        Ok(self.tmp_dir().join(format!("{}{}", Uuid::new_v4(), suffix)))
+    }
+
+    /// Capture `vaults` (and optionally a database copy) as a new snapshot.
+    ///
+    /// Files whose size and mtime match the previous snapshot reuse the
+    /// stored object without being read again.
+    pub fn create_snapshot(
+        &self,
+        vaults: &[SnapshotSource],
+        database_copy: Option<&Path>,
+    ) -> AppResult<SnapshotManifest> {
+        let _guard = REPOSITORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        fs::create_dir_all(self.snapshots_dir())?;
+        fs::create_dir_all(self.objects_dir())?;
+
+        let previous: HashMap<(String, String), SnapshotFile> = self
+            .latest_manifest()?
+            .map(|manifest| {
+                manifest
+                    .vaults
+                    .into_iter()
+                    .flat_map(|vault| {
+                        let vault_id = vault.vault_id;
+                        vault
+                            .files
+                            .into_iter()
+                            .map(move |file| ((vault_id.clone(), file.path.clone()), file))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut snapshot_vaults = Vec::with_capacity(vaults.len());
+        for source in vaults {
+            let mut files = Vec::new();
+            for (rel_path, abs_path) in vault_files(Path::new(&source.path)) {
+                let metadata = match fs::metadata(&abs_path) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        warn!("Skipping {:?} in snapshot: {}", abs_path, e);
+                        continue;
+                    }
+                };
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_millis() as i64)
+                    .unwrap_or(0);
+
+                let reused = previous
+                    .get(&(source.vault_id.clone(), rel_path.clone()))
+                    .filter(|prev| {
+                        prev.size == metadata.len()
+                            && prev.modified == modified
+                            && self.object_path(&prev.sha256).exists()
+                    })
+                    .map(|prev| prev.sha256.clone());
+
+                let (sha256, size) = match reused {
+                    Some(sha256) => (sha256, metadata.len()),
+                    None => match self.store_object(&abs_path) {
+                        Ok(object) => (object.sha256, object.size),
+                        Err(e) => {
+                            warn!("Skipping {:?} in snapshot: {}", abs_path, e);
+                            continue;
+                        }
+                    },
+                };
+                files.push(SnapshotFile {
+                    path: rel_path,
+                    sha256,
+                    size,
+                    modified,
+                });
+            }
+            snapshot_vaults.push(SnapshotVault {
+                vault_id: source.vault_id.clone(),
+                name: source.name.clone(),
+                path: source.path.clone(),
+                files,
+            });
+        }
+
+        let database = database_copy
+            .map(|path| self.store_object(path))
+            .transpose()?;
+
+        let created_at = Utc::now();
+        let manifest = SnapshotManifest {
+            id: format!(
+                "{}-{}",
+                created_at.format("%Y%m%dT%H%M%SZ"),
+                &Uuid::new_v4().simple().to_string()[..8]
+            ),
+            created_at,
+            vaults: snapshot_vaults,
+            database,
+        };
+
+        let manifest_path = self.manifest_path(&manifest.id)?;
+        let tmp = self.temp_path(".json")?;
+        fs::write(&tmp, serde_json::to_vec_pretty(&manifest)?)?;
+        fs::rename(&tmp, &manifest_path)?;
+
+        info!(
+            "Created snapshot {} ({} vault(s))",
+            manifest.id,
+            manifest.vaults.len()
+        );
+        Ok(manifest)
+    }
+
+    /// Compress `path` into the object store, returning its hash and size.
+    fn store_object(&self, path: &Path) -> AppResult<SnapshotObject> {
+        let tmp = self.temp_path(".gz")?;
+        let mut input = File::open(path)?;
+        let mut encoder = GzEncoder::new(File::create(&tmp)?, Compression::default());
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let n = input.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            encoder.write_all(&buf[..n])?;
+            size += n as u64;
+        }
+        encoder.finish()?;
+
+        let sha256 = hex::encode(hasher.finalize());
+        let object_path = self.object_path(&sha256);
+        if object_path.exists() {
+            fs::remove_file(&tmp)?;
+        } else {
+            if let Some(parent) = object_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&tmp, &object_path)?;
+        }
+        Ok(SnapshotObject { sha256, size })
+    }
+
+    fn open_object(&self, sha256: &str) -> AppResult<GzDecoder<File>> {
+        let path = self.object_path(sha256);
+        let file = File::open(&path)
+            .map_err(|_| AppError::NotFound(format!("Snapshot object {} is missing", sha256)))?;
+        Ok(GzDecoder::new(file))
+    }
+
+    /// Decompress an object to `dest` via a temporary sibling file.
+    fn extract_object(&self, sha256: &str, dest: &Path) -> AppResult<()> {
+        let mut decoder = self.open_object(sha256)?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = dest.with_file_name(format!(".restore-{}", Uuid::new_v4()));
+        let mut out = File::create(&tmp)?;
+        if let Err(e) = io::copy(&mut decoder, &mut out) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        out.sync_all()?;
+        fs::rename(&tmp, dest)?;
+        Ok(())
+    }
+
+    pub fn load_manifest(&self, snapshot_id: &str) -> AppResult<SnapshotManifest> {
+        let path = self.manifest_path(snapshot_id)?;
+        let bytes = fs::read(&path)
+            .map_err(|_| AppError::NotFound(format!("Snapshot not found: {}", snapshot_id)))?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    fn manifests(&self) -> AppResult<Vec<SnapshotManifest>> {
+        let dir = self.snapshots_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut manifests = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match fs::read(&path)
+                .map_err(AppError::from)
+                .and_then(|bytes| Ok(serde_json::from_slice::<SnapshotManifest>(&bytes)?))
+            {
+                Ok(manifest) => manifests.push(manifest),
+                Err(e) => warn!("Ignoring unreadable snapshot manifest {:?}: {}", path, e),
+            }
+        }
+        manifests.sort_by_key(|m| std::cmp::Reverse(m.created_at));
+        Ok(manifests)
+    }
+
+    fn latest_manifest(&self) -> AppResult<Option<SnapshotManifest>> {
+        Ok(self.manifests()?.into_iter().next())
+    }
+
+    /// All snapshots, newest first.
+    pub fn list_snapshots(&self) -> AppResult<Vec<SnapshotSummary>> {
+        Ok(self
+            .manifests()?
+            .iter()
+            .map(SnapshotSummary::from)
+            .collect())
+    }
+
+    /// Re-read every object referenced by a snapshot and check its hash.
+    pub fn verify(&self, snapshot_id: &str) -> AppResult<SnapshotVerifyReport> {
+        let manifest = self.load_manifest(snapshot_id)?;
+        let mut expected: HashMap<&str, u64> = HashMap::new();
+        for file in manifest.vaults.iter().flat_map(|v| v.files.iter()) {
+            expected.insert(&file.sha256, file.size);
+        }
+        if let Some(db) = &manifest.database {
+            expected.insert(&db.sha256, db.size);
+        }
+
+        let mut report = SnapshotVerifyReport {
+            snapshot_id: manifest.id.clone(),
+            objects_checked: expected.len(),
+            missing: Vec::new(),
+            corrupted: Vec::new(),
+        };
+        for (sha256, size) in expected {
+            if !self.object_path(sha256).exists() {
+                report.missing.push(sha256.to_string());
+                continue;
+            }
+            let intact = self
+                .open_object(sha256)
+                .and_then(|mut decoder| {
+                    let mut hasher = Sha256::new();
+                    let read = io::copy(&mut decoder, &mut hasher)?;
+                    Ok(read == size && hex::encode(hasher.finalize()) == sha256)
+                })
+                .unwrap_or(false);
+            if !intact {
+                report.corrupted.push(sha256.to_string());
+            }
+        }
+        report.missing.sort();
+        report.corrupted.sort();
+        Ok(report)
+    }
+
+    /// Restore a vault, or one file/directory of it, from a snapshot into
+    /// `vault_path`. With `target_path` the selection is written under that
+    /// path instead of its original location.
+    ///
+    /// Existing files that would be overwritten are moved to the vault trash
+    /// first; a whole-vault restore also trashes files the snapshot did not
+    /// contain, so the vault matches the snapshot exactly.
+    pub fn restore(
+        &self,
+        snapshot_id: &str,
+        vault_id: &str,
+        vault_path: &str,
+        path: Option<&str>,
+        target_path: Option<&str>,
+    ) -> AppResult<RestoreReport> {
+        let manifest = self.load_manifest(snapshot_id)?;
+        let vault = manifest
+            .vaults
+            .iter()
+            .find(|v| v.vault_id == vault_id)
+            .ok_or_else(|| {
+                AppError::NotFound(format!(
+                    "Snapshot {} does not contain vault {}",
+                    snapshot_id, vault_id
+                ))
+            })?;
+
+        let prefix = path
+            .map(|p| p.trim_matches('/').replace('\\', "/"))
+            .filter(|p| !p.is_empty());
+        let target = target_path
+            .map(|p| p.trim_matches('/').replace('\\', "/"))
+            .filter(|p| !p.is_empty());
+        if target.is_some() && prefix.is_none() {
+            return Err(AppError::InvalidInput(
+                "target_path requires a path to restore".to_string(),
+            ));
+        }
+
+        let selected: Vec<&SnapshotFile> = vault
+            .files
+            .iter()
+            .filter(|f| match &prefix {
+                Some(p) => f.path == *p || f.path.starts_with(&format!("{}/", p)),
+                None => true,
+            })
+            .collect();
+        if prefix.is_some() && selected.is_empty() {
+            return Err(AppError::NotFound(format!(
+                "{} is not part of snapshot {}",
+                prefix.unwrap_or_default(),
+                snapshot_id
+            )));
+        }
+
+        let mut report = RestoreReport {
+            snapshot_id: manifest.id.clone(),
+            vault_id: vault_id.to_string(),
+            ..Default::default()
+        };
+
+        for file in selected {
+            let dest_rel = match (&prefix, &target) {
+                (Some(p), Some(t)) => format!("{}{}", t, &file.path[p.len()..]),
+                _ => file.path.clone(),
+            };
+            let dest = FileService::resolve_path(vault_path, &dest_rel)?;
+            if dest.exists() {
+                if file_sha256(&dest).ok().as_deref() == Some(file.sha256.as_str()) {
+                    report.unchanged += 1;
+                    continue;
+                }
+                FileService::move_to_trash(vault_path, &dest_rel)?;
+                report.trashed.push(dest_rel.clone());
+            }
+            self.extract_object(&file.sha256, &dest)?;
+            report.restored.push(dest_rel);
+        }
+
+        if prefix.is_none() {
+            let captured: HashSet<&str> = vault.files.iter().map(|f| f.path.as_str()).collect();
+            for (rel_path, _) in vault_files(Path::new(vault_path)) {
+                if !captured.contains(rel_path.as_str()) {
+                    FileService::move_to_trash(vault_path, &rel_path)?;
+                    report.trashed.push(rel_path);
+                }
+            }
+        }
+
+        info!(
+            "Restored {} file(s) of vault {} from snapshot {}",
+            report.restored.len(),
+            vault_id,
+            snapshot_id
+        );
+        Ok(report)
+    }
+
+    /// Replace the database file at `dest` with the snapshot's copy. The
+    /// current file (and any WAL/SHM sidecars) is kept as
+    /// `<name>.pre-restore-<timestamp>`. Only safe while the server is stopped.
+    pub fn restore_database(&self, snapshot_id: &str, dest: &Path) -> AppResult<PathBuf> {
+        let manifest = self.load_manifest(snapshot_id)?;
+        let database = manifest.database.ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Snapshot {} does not include the database",
+                snapshot_id
+            ))
+        })?;
+
+        let staged = self.temp_path(".sqlite")?;
+        self.extract_object(&database.sha256, &staged)?;
+
+        let suffix = format!("pre-restore-{}", Utc::now().format("%Y%m%d_%H%M%S"));
+        for sidecar in ["", "-wal", "-shm"] {
+            let current = PathBuf::from(format!("{}{}", dest.display(), sidecar));
+            if current.exists() {
+                let kept = PathBuf::from(format!("{}{}.{}", dest.display(), sidecar, suffix));
+                fs::rename(&current, &kept)?;
+            }
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::rename(&staged, dest).is_err() {
+            // Different filesystem: fall back to copy + remove.
+            fs::copy(&staged, dest)?;
+            fs::remove_file(&staged)?;
+        }
+        Ok(dest.to_path_buf())
+    }
+
+    /// Apply the retention rules and delete objects no snapshot references.
+    /// Returns the ids of removed snapshots.
+    pub fn prune(&self) -> AppResult<Vec<String>> {
+        let _guard = REPOSITORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let manifests = self.manifests()?;
+        let stamps: Vec<(String, DateTime<Utc>)> = manifests
+            .iter()
+            .map(|m| (m.id.clone(), m.created_at))
+            .collect();
+        let keep = retained_snapshots(&stamps, &self.config);
+
+        let mut removed = Vec::new();
+        let mut referenced = HashSet::new();
+        for manifest in &manifests {
+            if keep.contains(&manifest.id) {
+                referenced.extend(
+                    manifest
+                        .vaults
+                        .iter()
+                        .flat_map(|v| v.files.iter().map(|f| f.sha256.clone())),
+                );
+                referenced.extend(manifest.database.iter().map(|d| d.sha256.clone()));
+            } else {
+                fs::remove_file(self.manifest_path(&manifest.id)?)?;
+                removed.push(manifest.id.clone());
+            }
+        }
+
+        if self.objects_dir().exists() {
+            for entry in WalkDir::new(self.objects_dir())
+                .min_depth(2)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                let name = entry.file_name().to_string_lossy();
+                let sha256 = name.trim_end_matches(".gz");
+                if !referenced.contains(sha256) {
+                    fs::remove_file(entry.path())?;
+                }
+            }
+        }
+
+        if !removed.is_empty() {
+            info!("Pruned {} snapshot(s)", removed.len());
+        }
+        Ok(removed)
+    }
+}
+
+/// Ids of the snapshots kept by the hourly/daily/weekly rules: for each rule
+/// the newest snapshot of each of the `keep_*` most recent periods. The
+/// newest snapshot overall is always kept.
+pub fn retained_snapshots(
+    snapshots: &[(String, DateTime<Utc>)],
+    config: &BackupConfig,
+) -> HashSet<String> {
+    let mut sorted: Vec<&(String, DateTime<Utc>)> = snapshots.iter().collect();
+    sorted.sort_by_key(|s| std::cmp::Reverse(s.1));
+
+    let mut keep = HashSet::new();
+    if let Some((id, _)) = sorted.first() {
+        keep.insert(id.clone());
+    }
+
+    let rules: [(usize, &str); 3] = [
+        (config.keep_hourly, "%Y%m%d%H"),
+        (config.keep_daily, "%Y%m%d"),
+        (config.keep_weekly, "%G%V"),
+    ];
+    for (count, bucket_format) in rules {
+        let mut buckets = HashSet::new();
+        for (id, created_at) in &sorted {
+            if buckets.len() >= count {
+                break;
+            }
+            if buckets.insert(created_at.format(bucket_format).to_string()) {
+                keep.insert(id.clone());
+            }
+        }
+    }
+    keep
+}
+
+/// Regular files in a vault as `(relative path, absolute path)` pairs,
+/// skipping the excluded directories.
+fn vault_files(vault_path: &Path) -> Vec<(String, PathBuf)> {
+    WalkDir::new(vault_path)
+        .into_iter()
+        .filter_entry(|entry| {
+            let rel = entry
+                .path()
+                .strip_prefix(vault_path)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/");
+            !is_excluded(&rel)
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|entry| {
+            let rel = entry
+                .path()
+                .strip_prefix(vault_path)
+                .ok()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            Some((rel, entry.into_path()))
+        })
+        .collect()
+}
+
+fn is_excluded(rel_path: &str) -> bool {
+    EXCLUDED_PREFIXES
+        .iter()
+        .any(|prefix| rel_path == *prefix || rel_path.starts_with(&format!("{}/", prefix)))
+        || Path::new(rel_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with(".restore-"))
+}
+
+fn file_sha256(path: &Path) -> AppResult<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Snapshot every vault (and the database when configured), then prune.
+pub async fn snapshot_all(db: &Database, config: &BackupConfig) -> AppResult<SnapshotManifest> {
+    let service = BackupService::new(config);
+    let sources: Vec<SnapshotSource> = db
+        .list_vaults()
+        .await?
+        .into_iter()
+        .map(|vault| SnapshotSource {
+            vault_id: vault.id,
+            name: vault.name,
+            path: vault.path,
+        })
+        .collect();
+
+    let database_copy = if config.include_database && db.backend() == DatabaseBackend::Sqlite {
+        let path = service.temp_path(".sqlite")?;
+        db.vacuum_into(&path).await?;
+        Some(path)
+    } else {
+        None
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let manifest = service.create_snapshot(&sources, database_copy.as_deref());
+        if let Some(path) = &database_copy {
+            let _ = fs::remove_file(path);
+        }
+        let manifest = manifest?;
+        service.prune()?;
+        Ok(manifest)
+    })
+    .await
+    .map_err(|e| AppError::InternalError(format!("Snapshot task failed: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn config(dir: &Path) -> BackupConfig {
+        BackupConfig {
+            directory: dir.to_string_lossy().to_string(),
+            ..BackupConfig::default()
+        }
+    }
+
+    #[test]
+    fn retention_keeps_newest_per_period() {
+        let cfg = BackupConfig {
+            keep_hourly: 2,
+            keep_daily: 2,
+            keep_weekly: 0,
+            ..BackupConfig::default()
+        };
+        let at = |d, h, m| Utc.with_ymd_and_hms(2026, 3, d, h, m, 0).unwrap();
+        let snapshots = vec![
+            ("a".to_string(), at(10, 12, 30)),
+            ("b".to_string(), at(10, 12, 0)),
+            ("c".to_string(), at(10, 11, 0)),
+            ("d".to_string(), at(9, 8, 0)),
+            ("e".to_string(), at(8, 8, 0)),
+        ];
+        let keep = retained_snapshots(&snapshots, &cfg);
+        let mut keep: Vec<_> = keep.into_iter().collect();
+        keep.sort();
+        assert_eq!(keep, vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn snapshot_is_incremental_and_restorable() {
+        let repo = TempDir::new().unwrap();
+        let vault = TempDir::new().unwrap();
+        fs::create_dir_all(vault.path().join("notes")).unwrap();
+        fs::write(vault.path().join("notes/a.md"), "alpha").unwrap();
+        fs::write(vault.path().join("b.md"), "beta").unwrap();
+        fs::create_dir_all(vault.path().join(".git")).unwrap();
+        fs::write(vault.path().join(".git/HEAD"), "ref").unwrap();
+
+        let service = BackupService::new(&config(repo.path()));
+        let source = SnapshotSource {
+            vault_id: "v1".to_string(),
+            name: "Vault".to_string(),
+            path: vault.path().to_string_lossy().to_string(),
+        };
+        let first = service
+            .create_snapshot(std::slice::from_ref(&source), None)
+            .unwrap();
+        assert_eq!(first.vaults[0].files.len(), 2);
+        let second = service.create_snapshot(&[source], None).unwrap();
+        assert_eq!(service.list_snapshots().unwrap().len(), 2);
+        assert!(service.verify(&second.id).unwrap().is_ok());
+
+        let objects = WalkDir::new(repo.path().join("objects"))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .count();
+        assert_eq!(objects, 2);
+
+        let vault_path = vault.path().to_string_lossy().to_string();
+        fs::write(vault.path().join("notes/a.md"), "changed").unwrap();
+        fs::write(vault.path().join("new.md"), "new").unwrap();
+
+        let single = service
+            .restore(
+                &first.id,
+                "v1",
+                &vault_path,
+                Some("notes/a.md"),
+                Some("a-old.md"),
+            )
+            .unwrap();
+        assert_eq!(single.restored, vec!["a-old.md".to_string()]);
+        assert_eq!(
+            fs::read_to_string(vault.path().join("a-old.md")).unwrap(),
+            "alpha"
+        );
+
+        let full = service
+            .restore(&first.id, "v1", &vault_path, None, None)
+            .unwrap();
+        assert_eq!(full.unchanged, 1);
+        assert_eq!(
+            fs::read_to_string(vault.path().join("notes/a.md")).unwrap(),
+            "alpha"
+        );
+        assert!(!vault.path().join("new.md").exists());
+        assert!(!vault.path().join("a-old.md").exists());
+        assert!(vault.path().join(".git/HEAD").exists());
+    }
+}
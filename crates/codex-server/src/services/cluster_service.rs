@@ -0,0 +1,302 @@
+//! Coordination between server instances that share a Postgres database:
+//! fan-out of live events and election of the instance that runs the
+//! background jobs.
+//!
+//! Every instance forwards what is sent on its local broadcasters to a
+//! `LISTEN/NOTIFY` channel and replays what other instances publish there,
+//! so WebSocket clients see the same events whichever instance they are
+//! connected to.
+//!
+//! Git auto-commit and remote sync, scheduled snapshots, trash retention
+//! and upload session sweeps assume a single runner, so only the instance
+//! holding a session-level advisory lock runs them. With SQLite there is
+//! only one instance, which is always the leader.
+
+use crate::config::DatabaseBackend;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::{FileChangeEvent, WsMessage};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgConnection, PgListener};
+use sqlx::Connection;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+pub const EVENT_CHANNEL: &str = "codex_events";
+
+/// Postgres rejects notification payloads of 8000 bytes or more.
+const MAX_PAYLOAD_BYTES: usize = 7900;
+
+/// Key of the advisory lock the leader holds ("codex_ld").
+const LEADER_LOCK_KEY: i64 = 0x636f_6465_785f_6c64;
+
+/// How often followers try to take the leader lock and the leader checks
+/// its connection. A leader whose connection drops may keep running jobs
+/// for up to this long after another instance took over.
+const LEADER_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Whether this instance currently runs the single-runner background jobs.
+#[derive(Clone)]
+pub struct Leadership(Arc<AtomicBool>);
+
+impl Leadership {
+    pub fn is_leader(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "event", rename_all = "snake_case")]
+enum RelayedEvent {
+    FileChange(FileChangeEvent),
+    Ws(WsMessage),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    origin: String,
+    #[serde(flatten)]
+    event: RelayedEvent,
+}
+
+/// Events this instance replayed from other instances, keyed by their JSON,
+/// so the forwarder does not publish them back.
+type Replayed = Arc<Mutex<HashMap<String, usize>>>;
+
+fn take_replayed(replayed: &Replayed, key: &str) -> bool {
+    let mut replayed = replayed.lock().unwrap_or_else(|e| e.into_inner());
+    match replayed.get_mut(key) {
+        Some(count) if *count > 1 => {
+            *count -= 1;
+            true
+        }
+        Some(_) => {
+            replayed.remove(key);
+            true
+        }
+        None => false,
+    }
+}
+
+pub struct ClusterService;
+
+impl ClusterService {
+    /// Start relaying `event_tx` and `ws_tx` through Postgres. Returns
+    /// without doing anything for the SQLite backend.
+    pub async fn relay_events(
+        db: &Database,
+        database_url: &str,
+        event_tx: broadcast::Sender<FileChangeEvent>,
+        ws_tx: broadcast::Sender<WsMessage>,
+    ) -> AppResult<()> {
+        if db.backend() != DatabaseBackend::Postgres {
+            return Ok(());
+        }
+
+        let mut listener = PgListener::connect(database_url).await.map_err(|e| {
+            AppError::InternalError(format!("Failed to listen for cluster events: {e}"))
+        })?;
+        listener.listen(EVENT_CHANNEL).await.map_err(|e| {
+            AppError::InternalError(format!("Failed to listen for cluster events: {e}"))
+        })?;
+
+        let origin = Uuid::new_v4().to_string();
+        let replayed: Replayed = Arc::default();
+        info!("Relaying live events across instances as {origin}");
+
+        tokio::spawn(Self::forward(
+            db.clone(),
+            origin.clone(),
+            replayed.clone(),
+            event_tx.subscribe(),
+            ws_tx.subscribe(),
+        ));
+        tokio::spawn(Self::replay(listener, origin, replayed, event_tx, ws_tx));
+        Ok(())
+    }
+
+    /// Elect the instance that runs the background jobs. With Postgres the
+    /// leader is whichever instance holds the leader advisory lock; the
+    /// others keep trying and take over when its session ends. The first
+    /// attempt is made before returning.
+    pub async fn elect_leader(db: &Database, database_url: &str) -> Leadership {
+        if db.backend() != DatabaseBackend::Postgres {
+            return Leadership(Arc::new(AtomicBool::new(true)));
+        }
+
+        let leadership = Leadership(Arc::new(AtomicBool::new(false)));
+        let flag = leadership.0.clone();
+        let database_url = database_url.to_string();
+        let mut conn = None;
+        Self::claim_leadership(&mut conn, &database_url, &flag).await;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(LEADER_CHECK_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                Self::claim_leadership(&mut conn, &database_url, &flag).await;
+            }
+        });
+        leadership
+    }
+
+    /// Take the leader lock, or check that the connection holding it is
+    /// still alive, and record the outcome in `flag`.
+    async fn claim_leadership(
+        conn: &mut Option<PgConnection>,
+        database_url: &str,
+        flag: &AtomicBool,
+    ) {
+        let was_leader = flag.load(Ordering::Relaxed);
+        let leader = match Self::hold_leader_lock(conn, database_url, was_leader).await {
+            Ok(leader) => leader,
+            Err(e) => {
+                warn!("Leader election failed: {e}");
+                // The lock, if held, went with the session.
+                *conn = None;
+                false
+            }
+        };
+        flag.store(leader, Ordering::Relaxed);
+        match (was_leader, leader) {
+            (false, true) => info!("This instance now runs the background jobs"),
+            (true, false) => warn!("This instance no longer runs the background jobs"),
+            _ => {}
+        }
+    }
+
+    async fn hold_leader_lock(
+        conn: &mut Option<PgConnection>,
+        database_url: &str,
+        held: bool,
+    ) -> Result<bool, sqlx::Error> {
+        let conn = match conn {
+            Some(conn) => conn,
+            None => conn.insert(PgConnection::connect(database_url).await?),
+        };
+        if held {
+            conn.ping().await?;
+            return Ok(true);
+        }
+        sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(LEADER_LOCK_KEY)
+            .fetch_one(conn)
+            .await
+    }
+
+    async fn forward(
+        db: Database,
+        origin: String,
+        replayed: Replayed,
+        mut event_rx: broadcast::Receiver<FileChangeEvent>,
+        mut ws_rx: broadcast::Receiver<WsMessage>,
+    ) {
+        loop {
+            let event = tokio::select! {
+                received = event_rx.recv() => match received {
+                    Ok(event) => RelayedEvent::FileChange(event),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Cluster relay skipped {skipped} file events");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                received = ws_rx.recv() => match received {
+                    Ok(message) => RelayedEvent::Ws(message),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Cluster relay skipped {skipped} WebSocket messages");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            };
+
+            let Ok(key) = serde_json::to_string(&event) else {
+                continue;
+            };
+            if take_replayed(&replayed, &key) {
+                continue;
+            }
+
+            let envelope = Envelope {
+                origin: origin.clone(),
+                event,
+            };
+            let Ok(payload) = serde_json::to_string(&envelope) else {
+                continue;
+            };
+            if payload.len() > MAX_PAYLOAD_BYTES {
+                warn!(
+                    "Not relaying a {} byte event to other instances: too large",
+                    payload.len()
+                );
+                continue;
+            }
+
+            // pg_notify returns void, which the Any driver cannot decode.
+            if let Err(e) =
+                sqlx::query("SELECT COUNT(*) FROM (SELECT pg_notify($1, $2)) AS notified")
+                    .bind(EVENT_CHANNEL)
+                    .bind(payload)
+                    .execute(db.pool())
+                    .await
+            {
+                warn!("Failed to relay event to other instances: {e}");
+            }
+        }
+    }
+
+    async fn replay(
+        mut listener: PgListener,
+        origin: String,
+        replayed: Replayed,
+        event_tx: broadcast::Sender<FileChangeEvent>,
+        ws_tx: broadcast::Sender<WsMessage>,
+    ) {
+        loop {
+            // PgListener reconnects and re-subscribes on its own after the
+            // connection drops; notifications sent meanwhile are lost.
+            let notification = match listener.recv().await {
+                Ok(notification) => notification,
+                Err(e) => {
+                    warn!("Cluster event listener error: {e}");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            let envelope: Envelope = match serde_json::from_str(notification.payload()) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    debug!("Ignoring malformed cluster event: {e}");
+                    continue;
+                }
+            };
+            if envelope.origin == origin {
+                continue;
+            }
+
+            if let Ok(key) = serde_json::to_string(&envelope.event) {
+                *replayed
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .entry(key)
+                    .or_default() += 1;
+            }
+            // Sending fails only when nobody is subscribed, which is fine.
+            match envelope.event {
+                RelayedEvent::FileChange(event) => {
+                    let _ = event_tx.send(event);
+                }
+                RelayedEvent::Ws(message) => {
+                    let _ = ws_tx.send(message);
+                }
+            }
+        }
+    }
+}
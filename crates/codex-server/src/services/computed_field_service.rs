@@ -0,0 +1,388 @@
+//! Evaluates the computed fields entity types declare (relation counts,
+//! transitive relation walks and ages) and fills them in with each entity's
+//! other fields as it is served, so they show up in `Entity::fields_map` and
+//! can be filtered on like stored fields.
+//!
+//! Values are never stored: they depend on who is asking, since only the
+//! entities a caller may read are counted or walked to, and ages run up to
+//! the date it is when they are read.
+
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::models::{ComputedField, CountDirection};
+use crate::services::entity_service::{Entity, EntityService};
+use crate::services::relation_service::{Relation, RelationService};
+use crate::services::schema_service::EntityTypeRegistry;
+use crate::services::timeline_service::{Calendar, TimelineService, GREGORIAN};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use tracing::warn;
+
+/// Hops a `transitive` field follows when it does not set `max_depth`.
+const DEFAULT_MAX_DEPTH: u32 = 10;
+
+/// Forward relations of a vault indexed by entity, with entity titles.
+pub struct RelationIndex<'a> {
+    outgoing: HashMap<&'a str, Vec<&'a Relation>>,
+    incoming: HashMap<&'a str, Vec<&'a Relation>>,
+    titles: HashMap<&'a str, String>,
+}
+
+impl<'a> RelationIndex<'a> {
+    pub fn new(entities: &'a [Entity], relations: &'a [Relation]) -> Self {
+        let mut index = Self {
+            outgoing: HashMap::new(),
+            incoming: HashMap::new(),
+            titles: entities
+                .iter()
+                .map(|e| (e.id.as_str(), title(&e.path)))
+                .collect(),
+        };
+        for relation in relations.iter().filter(|r| r.direction == "forward") {
+            index
+                .outgoing
+                .entry(relation.from_entity_id.as_str())
+                .or_default()
+                .push(relation);
+            index
+                .incoming
+                .entry(relation.to_entity_id.as_str())
+                .or_default()
+                .push(relation);
+        }
+        index
+    }
+
+    fn edges(&self, entity_id: &str, direction: CountDirection) -> &[&'a Relation] {
+        let edges = match direction {
+            CountDirection::Inbound => self.incoming.get(entity_id),
+            CountDirection::Outbound => self.outgoing.get(entity_id),
+        };
+        edges.map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+pub struct ComputedFieldService;
+
+impl ComputedFieldService {
+    /// Fill in the computed fields of `entities`, all from `vault_id`, in
+    /// their `fields`. Only the vault's entities whose path `can_read`
+    /// accepts, and the relations between them, are counted or walked to.
+    /// `registry` should be scoped to the vault.
+    pub async fn apply(
+        db: &Database,
+        vault_id: &str,
+        registry: &EntityTypeRegistry,
+        entities: &mut [Entity],
+        can_read: impl Fn(&str) -> bool,
+    ) -> AppResult<()> {
+        let computed: HashMap<String, Vec<(String, ComputedField)>> = registry
+            .all()
+            .await
+            .into_iter()
+            .filter_map(|schema| {
+                let fields: Vec<(String, ComputedField)> = schema
+                    .fields
+                    .into_iter()
+                    .filter_map(|f| Some((f.key, f.computed?)))
+                    .collect();
+                (!fields.is_empty()).then_some((schema.id, fields))
+            })
+            .collect();
+        if !entities
+            .iter()
+            .any(|e| computed.contains_key(&e.entity_type))
+        {
+            return Ok(());
+        }
+        let mut readable = EntityService::list_all_in_vault(db, vault_id).await?;
+        readable.retain(|e| can_read(&e.path));
+        let readable_ids: HashSet<&str> = readable.iter().map(|e| e.id.as_str()).collect();
+        let mut relations = RelationService::list_for_vault(db, vault_id).await?;
+        relations.retain(|r| {
+            readable_ids.contains(r.from_entity_id.as_str())
+                && readable_ids.contains(r.to_entity_id.as_str())
+        });
+        let index = RelationIndex::new(&readable, &relations);
+        let calendars = Self::load_calendars(db, vault_id, computed.values().flatten()).await?;
+
+        for entity in entities.iter_mut() {
+            let Some(fields) = computed.get(&entity.entity_type) else {
+                continue;
+            };
+            let mut values = entity.fields_map();
+            let Some(map) = values.as_object_mut() else {
+                continue;
+            };
+            for (key, field) in fields {
+                let value = Self::evaluate(field, entity, map, &index, &calendars);
+                map.insert(key.clone(), value);
+            }
+            entity.fields = values.to_string();
+        }
+        Ok(())
+    }
+
+    /// The value of one computed field of `entity`, whose stored fields are
+    /// `fields`. `calendars` holds the calendars `age` fields name; a field
+    /// whose calendar is missing, or whose dates do not parse, is `null`.
+    pub fn evaluate(
+        field: &ComputedField,
+        entity: &Entity,
+        fields: &serde_json::Map<String, Value>,
+        index: &RelationIndex<'_>,
+        calendars: &HashMap<String, Calendar>,
+    ) -> Value {
+        match field {
+            ComputedField::Count {
+                relation,
+                direction,
+            } => {
+                let edges = index.edges(&entity.id, *direction);
+                Value::from(
+                    edges
+                        .iter()
+                        .filter(|r| &r.relation_type == relation)
+                        .count(),
+                )
+            }
+            ComputedField::Transitive {
+                relation,
+                max_depth,
+            } => {
+                let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+                let mut seen: HashSet<&str> = HashSet::from([entity.id.as_str()]);
+                let mut queue = VecDeque::from([(entity.id.as_str(), 0)]);
+                let mut reached = Vec::new();
+                while let Some((id, depth)) = queue.pop_front() {
+                    if depth >= max_depth {
+                        continue;
+                    }
+                    for edge in index.edges(id, CountDirection::Outbound) {
+                        let target = edge.to_entity_id.as_str();
+                        if &edge.relation_type == relation && seen.insert(target) {
+                            if let Some(title) = index.titles.get(target) {
+                                reached.push(Value::from(title.clone()));
+                            }
+                            queue.push_back((target, depth + 1));
+                        }
+                    }
+                }
+                Value::Array(reached)
+            }
+            ComputedField::Age { from, to, calendar } => {
+                let calendar_id = calendar.as_deref().unwrap_or(GREGORIAN);
+                let Some(calendar) = calendars.get(calendar_id) else {
+                    return Value::Null;
+                };
+                let date = |key: &str| match fields.get(key)? {
+                    Value::String(s) if !s.trim().is_empty() => Some(calendar.parse(s)),
+                    Value::Number(n) => Some(calendar.parse(&n.to_string())),
+                    _ => None,
+                };
+                let Some(Some(born)) = date(from) else {
+                    return Value::Null;
+                };
+                let end = match to.as_deref().and_then(date) {
+                    Some(end) => end,
+                    None => calendar.today(),
+                };
+                let Some(end) = end.filter(|end| end.ordinal >= born.ordinal) else {
+                    return Value::Null;
+                };
+                let before_anniversary = (end.month.unwrap_or(1), end.day.unwrap_or(1))
+                    < (born.month.unwrap_or(1), born.day.unwrap_or(1));
+                Value::from(end.year - born.year - i64::from(before_anniversary))
+            }
+        }
+    }
+
+    /// Load each calendar an `age` field names. Calendars that fail to load
+    /// are logged and left out.
+    async fn load_calendars<'a>(
+        db: &Database,
+        vault_id: &str,
+        fields: impl Iterator<Item = &'a (String, ComputedField)>,
+    ) -> AppResult<HashMap<String, Calendar>> {
+        let ids: HashSet<&str> = fields
+            .filter_map(|(_, field)| match field {
+                ComputedField::Age { calendar, .. } => {
+                    Some(calendar.as_deref().unwrap_or(GREGORIAN))
+                }
+                _ => None,
+            })
+            .collect();
+        let mut calendars = HashMap::new();
+        if ids.is_empty() {
+            return Ok(calendars);
+        }
+        let vault = db.get_vault(vault_id).await?;
+        for id in ids {
+            match TimelineService::calendar(&vault.path, id) {
+                Ok(calendar) => {
+                    calendars.insert(id.to_string(), calendar);
+                }
+                Err(e) => warn!("Computed ages in vault {vault_id} cannot use calendar {id}: {e}"),
+            }
+        }
+        Ok(calendars)
+    }
+}
+
+fn title(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::timeline::CalendarToml;
+
+    fn entity(id: &str, fields: Value) -> Entity {
+        Entity {
+            id: id.to_string(),
+            vault_id: "v".to_string(),
+            path: format!("{id}.md"),
+            entity_type: "thing".to_string(),
+            plugin_id: "test".to_string(),
+            labels: "[]".to_string(),
+            fields: fields.to_string(),
+            modified_at: String::new(),
+            indexed_at: String::new(),
+        }
+    }
+
+    fn relation(from: &str, to: &str, kind: &str, direction: &str) -> Relation {
+        Relation {
+            id: format!("{from}-{kind}-{to}"),
+            vault_id: "v".to_string(),
+            from_entity_id: from.to_string(),
+            to_entity_id: to.to_string(),
+            relation_type: kind.to_string(),
+            direction: direction.to_string(),
+            metadata: None,
+            source: "field".to_string(),
+            source_field: Some(kind.to_string()),
+            created_at: String::new(),
+        }
+    }
+
+    fn evaluate(
+        field: &ComputedField,
+        entity: &Entity,
+        index: &RelationIndex<'_>,
+        calendars: &HashMap<String, Calendar>,
+    ) -> Value {
+        let fields = entity.fields_map();
+        ComputedFieldService::evaluate(field, entity, fields.as_object().unwrap(), index, calendars)
+    }
+
+    #[test]
+    fn counts_relations_and_walks_them_transitively() {
+        let entities = [
+            entity("guild", serde_json::json!({})),
+            entity("alice", serde_json::json!({})),
+            entity("bob", serde_json::json!({})),
+            entity("tavern", serde_json::json!({})),
+            entity("city", serde_json::json!({})),
+            entity("realm", serde_json::json!({})),
+        ];
+        let relations = [
+            relation("alice", "guild", "member_of", "forward"),
+            relation("guild", "alice", "inverse_of_member_of", "inverse"),
+            relation("bob", "guild", "member_of", "forward"),
+            relation("bob", "guild", "enemy_of", "forward"),
+            relation("tavern", "city", "located_in", "forward"),
+            relation("city", "realm", "located_in", "forward"),
+            relation("realm", "city", "located_in", "forward"),
+        ];
+        let index = RelationIndex::new(&entities, &relations);
+        let calendars = HashMap::new();
+
+        let members = ComputedField::Count {
+            relation: "member_of".to_string(),
+            direction: CountDirection::Inbound,
+        };
+        assert_eq!(evaluate(&members, &entities[0], &index, &calendars), 2);
+        let guilds = ComputedField::Count {
+            relation: "member_of".to_string(),
+            direction: CountDirection::Outbound,
+        };
+        assert_eq!(evaluate(&guilds, &entities[2], &index, &calendars), 1);
+
+        let within = ComputedField::Transitive {
+            relation: "located_in".to_string(),
+            max_depth: None,
+        };
+        assert_eq!(
+            evaluate(&within, &entities[3], &index, &calendars),
+            serde_json::json!(["city", "realm"])
+        );
+        let nearest = ComputedField::Transitive {
+            relation: "located_in".to_string(),
+            max_depth: Some(1),
+        };
+        assert_eq!(
+            evaluate(&nearest, &entities[3], &index, &calendars),
+            serde_json::json!(["city"])
+        );
+    }
+
+    #[test]
+    fn computes_ages_up_to_the_current_date_or_an_end_date() {
+        let body: CalendarToml = toml::from_str(
+            r#"
+[calendar]
+name = "Harptos"
+era = "DR"
+current = "10 Alturiak 1492"
+months = [
+    { name = "Hammer", days = 30 },
+    { name = "Alturiak", days = 30 },
+]
+"#,
+        )
+        .unwrap();
+        let calendars = HashMap::from([(
+            "harptos".to_string(),
+            Calendar::from_toml("harptos", body.calendar).unwrap(),
+        )]);
+        let entities = [
+            entity(
+                "elminster",
+                serde_json::json!({"born": "12 Alturiak 1450 DR"}),
+            ),
+            entity(
+                "khelben",
+                serde_json::json!({"born": "1 Hammer 1300", "died": "1 Hammer 1400"}),
+            ),
+            entity("unknown", serde_json::json!({"born": ""})),
+        ];
+        let index = RelationIndex::new(&entities, &[]);
+        let age = ComputedField::Age {
+            from: "born".to_string(),
+            to: Some("died".to_string()),
+            calendar: Some("harptos".to_string()),
+        };
+        assert_eq!(evaluate(&age, &entities[0], &index, &calendars), 41);
+        assert_eq!(evaluate(&age, &entities[1], &index, &calendars), 100);
+        assert_eq!(
+            evaluate(&age, &entities[2], &index, &calendars),
+            Value::Null
+        );
+
+        let gregorian = ComputedField::Age {
+            from: "born".to_string(),
+            to: None,
+            calendar: None,
+        };
+        assert_eq!(
+            evaluate(&gregorian, &entities[0], &index, &calendars),
+            Value::Null
+        );
+    }
+}
@@ -1,9 +1,13 @@
 use crate::db::Database;
 use crate::error::{AppError, AppResult};
+use crate::models::EntityTypeSchema;
+use crate::services::entity_validation_service::{EntityValidationService, TargetLabels};
+use crate::services::frontmatter_service;
 use crate::services::schema_service::EntityTypeRegistry;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
 use tracing::{debug, warn};
 
 /// Compute a stable entity ID from vault_id + file path.
@@ -50,9 +54,9 @@ impl EntityService {
     /// keys `codex_type`, `codex_labels`, and `codex_plugin` are extracted;
     /// all remaining keys are stored in the `fields` blob.
     ///
-    /// When `registry` is provided (Phase 2+), required field validation is
-    /// performed. Missing required fields are logged as warnings but do not
-    /// prevent indexing — the file may be mid-edit.
+    /// When `registry` is provided (Phase 2+), the fields are validated
+    /// against the type's schema. Violations are logged as warnings but do
+    /// not prevent indexing — the file may be mid-edit.
     pub async fn upsert(
         db: &Database,
         vault_id: &str,
@@ -100,24 +104,15 @@ impl EntityService {
         let fields_json = serde_json::to_string(&fields).unwrap_or_else(|_| "{}".to_string());
         let now = Utc::now().to_rfc3339();
 
-        // Schema-aware validation (Phase 2): check required fields
+        // Schema-aware validation (Phase 2): check fields against the schema
         if let Some(reg) = registry {
             if let Some(schema) = reg.get_by_id(&entity_type).await {
-                for field_def in &schema.fields {
-                    if field_def.required {
-                        let present = fields
-                            .get(&field_def.key)
-                            .map(|v| {
-                                !v.is_null() && v.as_str().map(|s| !s.is_empty()).unwrap_or(true)
-                            })
-                            .unwrap_or(false);
-                        if !present {
-                            warn!(
-                                "Entity at {path}: required field '{}' is missing or empty",
-                                field_def.key
-                            );
-                        }
-                    }
+                for error in EntityValidationService::validate_fields(
+                    &schema.fields,
+                    &fields,
+                    &TargetLabels::new(),
+                ) {
+                    warn!("Entity at {path}: {}", error.message);
                 }
                 // Merge entity-type labels (from schema) with frontmatter labels
                 let mut merged_labels: Vec<String> = labels.clone();
@@ -176,7 +171,7 @@ impl EntityService {
         sqlx::query(
             r#"
             INSERT INTO entities (id, vault_id, path, entity_type, plugin_id, labels, fields, modified_at, indexed_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             ON CONFLICT(vault_id, path) DO UPDATE SET
                 entity_type = excluded.entity_type,
                 plugin_id   = excluded.plugin_id,
@@ -222,7 +217,7 @@ impl EntityService {
     pub async fn remove(db: &Database, vault_id: &str, path: &str) -> AppResult<()> {
         let id = entity_id(vault_id, path);
         // Relations cascade-delete via FK; explicit delete for clarity.
-        sqlx::query("DELETE FROM relations WHERE from_entity_id = ? OR to_entity_id = ?")
+        sqlx::query("DELETE FROM relations WHERE from_entity_id = $1 OR to_entity_id = $2")
             .bind(&id)
             .bind(&id)
             .execute(db.pool())
@@ -235,7 +230,7 @@ impl EntityService {
                 })
             })?;
 
-        sqlx::query("DELETE FROM entities WHERE id = ?")
+        sqlx::query("DELETE FROM entities WHERE id = $1")
             .bind(&id)
             .execute(db.pool())
             .await
@@ -254,7 +249,7 @@ impl EntityService {
     /// Get a single entity by its ID.
     pub async fn get(db: &Database, entity_id: &str) -> AppResult<Option<Entity>> {
         let entity: Option<Entity> = sqlx::query_as(
-            "SELECT id, vault_id, path, entity_type, plugin_id, labels, fields, modified_at, indexed_at FROM entities WHERE id = ?"
+            "SELECT id, vault_id, path, entity_type, plugin_id, labels, fields, modified_at, indexed_at FROM entities WHERE id = $1"
         )
         .bind(entity_id)
         .fetch_optional(db.pool())
@@ -267,23 +262,27 @@ impl EntityService {
         Ok(entity)
     }
 
-    /// List entities for a vault with optional filters.
+    /// List entities for a vault with optional filters. `entity_types`
+    /// matches any of the given types; an empty slice matches all.
     pub async fn list(
         db: &Database,
         vault_id: &str,
-        entity_type_filter: Option<&str>,
+        entity_types: &[String],
         label_filter: Option<&str>,
         plugin_filter: Option<&str>,
         name_query: Option<&str>,
     ) -> AppResult<Vec<Entity>> {
         // Build dynamic query
-        let mut conditions = vec!["vault_id = ?".to_string()];
+        let mut conditions = vec!["vault_id = $1".to_string()];
 
-        if entity_type_filter.is_some() {
-            conditions.push("entity_type = ?".to_string());
+        if !entity_types.is_empty() {
+            let params: Vec<String> = (0..entity_types.len())
+                .map(|i| format!("${}", i + 2))
+                .collect();
+            conditions.push(format!("entity_type IN ({})", params.join(", ")));
         }
         if plugin_filter.is_some() {
-            conditions.push("plugin_id = ?".to_string());
+            conditions.push(format!("plugin_id = ${}", entity_types.len() + 2));
         }
 
         let sql = format!(
@@ -292,7 +291,7 @@ impl EntityService {
         );
 
         let mut query = sqlx::query_as::<_, Entity>(&sql).bind(vault_id);
-        if let Some(t) = entity_type_filter {
+        for t in entity_types {
             query = query.bind(t);
         }
         if let Some(p) = plugin_filter {
@@ -348,7 +347,7 @@ impl EntityService {
     /// List all entities in a vault (used by reindex and graph queries).
     pub async fn list_all_in_vault(db: &Database, vault_id: &str) -> AppResult<Vec<Entity>> {
         let entities: Vec<Entity> = sqlx::query_as(
-            "SELECT id, vault_id, path, entity_type, plugin_id, labels, fields, modified_at, indexed_at FROM entities WHERE vault_id = ?"
+            "SELECT id, vault_id, path, entity_type, plugin_id, labels, fields, modified_at, indexed_at FROM entities WHERE vault_id = $1"
         )
         .bind(vault_id)
         .fetch_all(db.pool())
@@ -363,7 +362,7 @@ impl EntityService {
 
     /// Get the paths of all indexed entities in a vault (used by reindex cleanup).
     pub async fn get_indexed_paths(db: &Database, vault_id: &str) -> AppResult<Vec<String>> {
-        let rows: Vec<(String,)> = sqlx::query_as("SELECT path FROM entities WHERE vault_id = ?")
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT path FROM entities WHERE vault_id = $1")
             .bind(vault_id)
             .fetch_all(db.pool())
             .await
@@ -384,7 +383,7 @@ impl EntityService {
         path: &str,
     ) -> AppResult<Option<Entity>> {
         let entity: Option<Entity> = sqlx::query_as(
-            "SELECT id, vault_id, path, entity_type, plugin_id, labels, fields, modified_at, indexed_at FROM entities WHERE vault_id = ? AND path = ?"
+            "SELECT id, vault_id, path, entity_type, plugin_id, labels, fields, modified_at, indexed_at FROM entities WHERE vault_id = $1 AND path = $2"
         )
         .bind(vault_id)
         .bind(path)
@@ -415,6 +414,200 @@ impl EntityService {
             }
         }
     }
+
+    /// The fields a new entity of `schema` is written with: the given
+    /// values and then schema defaults, in schema field order, followed by
+    /// keys the schema does not declare. Reserved `codex_*` keys and
+    /// computed fields are dropped.
+    pub fn new_entity_fields(
+        schema: &EntityTypeSchema,
+        fields: &serde_json::Map<String, serde_json::Value>,
+    ) -> Vec<(String, serde_json::Value)> {
+        let mut ordered = Vec::new();
+        for field in schema.fields.iter().filter(|f| f.computed.is_none()) {
+            let value = fields
+                .get(&field.key)
+                .filter(|v| !v.is_null())
+                .or(field.default.as_ref());
+            if let Some(value) = value {
+                ordered.push((field.key.clone(), value.clone()));
+            }
+        }
+        for (key, value) in fields {
+            let declared = schema.fields.iter().any(|f| &f.key == key);
+            if !declared && !key.starts_with("codex_") && !value.is_null() {
+                ordered.push((key.clone(), value.clone()));
+            }
+        }
+        ordered
+    }
+
+    /// Markdown for a new entity: `codex_type`/`codex_plugin` and `fields`
+    /// as frontmatter, then `body`.
+    pub fn new_entity_markdown(
+        schema: &EntityTypeSchema,
+        fields: &[(String, serde_json::Value)],
+        body: &str,
+    ) -> AppResult<String> {
+        let mut frontmatter = serde_yaml::Mapping::new();
+        frontmatter.insert("codex_type".into(), schema.id.clone().into());
+        frontmatter.insert("codex_plugin".into(), schema.plugin_id.clone().into());
+        for (key, value) in fields {
+            let value = serde_yaml::to_value(value)
+                .map_err(|e| AppError::InvalidInput(format!("Invalid value for '{key}': {e}")))?;
+            frontmatter.insert(key.clone().into(), value);
+        }
+        frontmatter_service::render_frontmatter(&frontmatter, body)
+    }
+
+    /// File stem for a new entity: its display field value, or the type
+    /// name when that is empty, without characters that are not allowed in
+    /// file names or that would break `[[wiki links]]` to it.
+    pub fn file_stem_for(
+        schema: &EntityTypeSchema,
+        fields: &serde_json::Map<String, serde_json::Value>,
+    ) -> String {
+        let display = schema
+            .display_field
+            .as_deref()
+            .and_then(|key| fields.get(key))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let cleaned: String = display
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => ' ',
+                c if c.is_control() => ' ',
+                c => c,
+            })
+            .collect();
+        let stem = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+        let stem = stem.trim_start_matches(['.', ' ']);
+        if stem.is_empty() {
+            schema.name.clone()
+        } else {
+            stem.to_string()
+        }
+    }
+}
+
+/// A condition on an entity field, written `key<op>value` where `op` is
+/// `=`, `!=`, `>`, `>=`, `<`, `<=` or `~` (contains). Values compare as
+/// numbers when both sides are numbers and as case-insensitive text
+/// otherwise. A list matches when any item does, and `!=` when none does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFilter {
+    pub key: String,
+    pub op: FilterOp,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+}
+
+impl FieldFilter {
+    /// Parse comma-separated conditions, e.g. `member_count>=3,region~north`.
+    pub fn parse_list(filters: &str) -> AppResult<Vec<Self>> {
+        filters
+            .split(',')
+            .filter(|condition| !condition.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    pub fn parse(condition: &str) -> AppResult<Self> {
+        let invalid = || {
+            AppError::InvalidInput(format!(
+                "Invalid field filter '{condition}'; expected key, operator and value"
+            ))
+        };
+        let at = condition
+            .find(['=', '!', '<', '>', '~'])
+            .ok_or_else(invalid)?;
+        let key = condition[..at].trim();
+        let rest = &condition[at..];
+        let (op, len) = [
+            ("!=", FilterOp::Ne),
+            (">=", FilterOp::Ge),
+            ("<=", FilterOp::Le),
+            ("=", FilterOp::Eq),
+            (">", FilterOp::Gt),
+            ("<", FilterOp::Lt),
+            ("~", FilterOp::Contains),
+        ]
+        .into_iter()
+        .find(|(token, _)| rest.starts_with(token))
+        .map(|(token, op)| (op, token.len()))
+        .ok_or_else(invalid)?;
+        if key.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            key: key.to_string(),
+            op,
+            value: rest[len..].trim().to_string(),
+        })
+    }
+
+    /// Whether the entity `fields` (as from `Entity::fields_map`) satisfy
+    /// the condition. A missing or `null` field only satisfies `!=`.
+    pub fn matches(&self, fields: &serde_json::Value) -> bool {
+        match fields.get(&self.key) {
+            None | Some(serde_json::Value::Null) => self.op == FilterOp::Ne,
+            Some(serde_json::Value::Array(items)) if self.op == FilterOp::Ne => !items
+                .iter()
+                .any(|item| self.compare(item) == Some(Ordering::Equal)),
+            Some(serde_json::Value::Array(items)) => {
+                items.iter().any(|item| self.matches_one(item))
+            }
+            Some(value) => self.matches_one(value),
+        }
+    }
+
+    fn matches_one(&self, value: &serde_json::Value) -> bool {
+        if self.op == FilterOp::Contains {
+            return scalar_text(value)
+                .is_some_and(|text| text.to_lowercase().contains(&self.value.to_lowercase()));
+        }
+        let Some(ordering) = self.compare(value) else {
+            return false;
+        };
+        match self.op {
+            FilterOp::Eq => ordering == Ordering::Equal,
+            FilterOp::Ne => ordering != Ordering::Equal,
+            FilterOp::Gt => ordering == Ordering::Greater,
+            FilterOp::Ge => ordering != Ordering::Less,
+            FilterOp::Lt => ordering == Ordering::Less,
+            FilterOp::Le => ordering != Ordering::Greater,
+            FilterOp::Contains => unreachable!(),
+        }
+    }
+
+    /// How `value` orders against the filter value.
+    fn compare(&self, value: &serde_json::Value) -> Option<Ordering> {
+        let text = scalar_text(value)?;
+        match (text.parse::<f64>(), self.value.parse::<f64>()) {
+            (Ok(a), Ok(b)) => a.partial_cmp(&b),
+            _ => Some(text.to_lowercase().cmp(&self.value.to_lowercase())),
+        }
+    }
+}
+
+fn scalar_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
@@ -538,4 +731,112 @@ mod tests {
         assert!(fields.is_object());
         assert_eq!(fields.as_object().unwrap().len(), 0);
     }
+
+    // ── New entity helpers ────────────────────────────────────────────────
+
+    fn character_schema() -> EntityTypeSchema {
+        use crate::models::{FieldSchema, FieldType};
+        let field = |key: &str, default: Option<serde_json::Value>| FieldSchema {
+            key: key.into(),
+            label: key.into(),
+            field_type: FieldType::String,
+            required: false,
+            item_type: None,
+            values: vec![],
+            default,
+            target_label: None,
+            relation: None,
+            description: None,
+            computed: None,
+        };
+        EntityTypeSchema {
+            id: "character".into(),
+            plugin_id: "worldbuilding".into(),
+            name: "Character".into(),
+            icon: None,
+            color: None,
+            template: None,
+            labels: vec![],
+            display_field: Some("full_name".into()),
+            show_on_create: vec![],
+            extends: None,
+            mixins: vec![],
+            fields: vec![
+                field("full_name", None),
+                field("status", Some(serde_json::json!("Active"))),
+                field("faction", None),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_new_entity_markdown_uses_schema_order() {
+        let schema = character_schema();
+        let given = serde_json::json!({
+            "mood": "grim",
+            "faction": "[[The Guild]]",
+            "full_name": "Alice",
+            "codex_type": "location",
+        });
+        let fields = EntityService::new_entity_fields(&schema, given.as_object().unwrap());
+        let markdown =
+            EntityService::new_entity_markdown(&schema, &fields, "# Alice\n").expect("render");
+        assert_eq!(
+            markdown,
+            "---\ncodex_type: character\ncodex_plugin: worldbuilding\nfull_name: Alice\n\
+             status: Active\nfaction: '[[The Guild]]'\nmood: grim\n---\n# Alice\n"
+        );
+    }
+
+    #[test]
+    fn test_file_stem_for_uses_display_field() {
+        let schema = character_schema();
+        let stem = |fields: serde_json::Value| {
+            EntityService::file_stem_for(&schema, fields.as_object().unwrap())
+        };
+        assert_eq!(stem(serde_json::json!({ "full_name": "Alice" })), "Alice");
+        assert_eq!(
+            stem(serde_json::json!({ "full_name": "  ../Lord: [[Bob]]?  " })),
+            "Lord Bob"
+        );
+        assert_eq!(stem(serde_json::json!({ "full_name": "" })), "Character");
+        assert_eq!(stem(serde_json::json!({})), "Character");
+    }
+
+    #[test]
+    fn test_field_filter_compares_numbers_text_and_lists() {
+        let fields = serde_json::json!({
+            "member_count": 3,
+            "region": "The North",
+            "within": ["Waterdeep", "Sword Coast"],
+            "motto": null,
+        });
+        let matches = |filter: &str| {
+            FieldFilter::parse_list(filter)
+                .unwrap()
+                .iter()
+                .all(|f| f.matches(&fields))
+        };
+        assert!(matches("member_count>=3"));
+        assert!(matches("member_count > 2, member_count<10"));
+        assert!(!matches("member_count>3"));
+        assert!(matches("region=the north"));
+        assert!(matches("region~north"));
+        assert!(matches("within=waterdeep"));
+        assert!(!matches("within!=Waterdeep"));
+        assert!(matches("within!=Neverwinter"));
+        assert!(matches("motto!=anything"));
+        assert!(!matches("missing=x"));
+
+        assert!(FieldFilter::parse("member_count").is_err());
+        assert!(FieldFilter::parse(">=3").is_err());
+        assert_eq!(
+            FieldFilter::parse("a<=b").unwrap(),
+            FieldFilter {
+                key: "a".to_string(),
+                op: FilterOp::Le,
+                value: "b".to_string(),
+            }
+        );
+    }
 }
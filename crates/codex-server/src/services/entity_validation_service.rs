@@ -0,0 +1,486 @@
+//! Checks entity frontmatter against the `EntityTypeSchema` of its
+//! `codex_type`: required fields, field types, enum values, list item types
+//! and the labels of entities that `EntityRef` fields point at.
+//!
+//! References to entities that do not exist (yet) are not errors, so notes
+//! can link forward to entities that have not been written.
+
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::models::{
+    EntityValidationIssue, EntityValidationReport, FieldErrorCode, FieldSchema, FieldType,
+    FieldValidationError, RelationTypeSchema,
+};
+use crate::services::entity_service::{Entity, EntityService};
+use crate::services::relation_service::extract_wiki_title;
+use crate::services::schema_service::EntityTypeRegistry;
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Labels of every entity in a vault keyed by lower-cased file stem — the
+/// name `[[Title]]` references resolve by.
+pub type TargetLabels = HashMap<String, Vec<String>>;
+
+pub struct EntityValidationService;
+
+impl EntityValidationService {
+    /// Every way `fields` violates the `schema` field declarations. Keys
+    /// that are not declared, and computed fields, are ignored.
+    pub fn validate_fields(
+        schema: &[FieldSchema],
+        fields: &Map<String, Value>,
+        targets: &TargetLabels,
+    ) -> Vec<FieldValidationError> {
+        let mut errors = Vec::new();
+        for field in schema.iter().filter(|f| f.computed.is_none()) {
+            let value = fields.get(&field.key).filter(|v| !is_empty(v));
+            let Some(value) = value else {
+                if field.required {
+                    errors.push(error(
+                        field,
+                        None,
+                        FieldErrorCode::Required,
+                        format!("'{}' is required", field.label),
+                    ));
+                }
+                continue;
+            };
+
+            if field.field_type != FieldType::List {
+                if let Some((code, message)) = check_value(field, &field.field_type, value, targets)
+                {
+                    errors.push(error(field, None, code, message));
+                }
+                continue;
+            }
+            let Some(items) = value.as_array() else {
+                errors.push(error(
+                    field,
+                    None,
+                    FieldErrorCode::InvalidType,
+                    format!("'{}' must be a list", field.label),
+                ));
+                continue;
+            };
+            let Some(item_type) = &field.item_type else {
+                continue;
+            };
+            for (index, item) in items.iter().enumerate() {
+                if let Some((code, message)) = check_value(field, item_type, item, targets) {
+                    errors.push(error(field, Some(index), code, message));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Labels of each entity in the vault, including those its type's schema
+    /// applies to every instance.
+    pub async fn target_labels(
+        db: &Database,
+        registry: &EntityTypeRegistry,
+        vault_id: &str,
+    ) -> AppResult<TargetLabels> {
+        let mut targets = TargetLabels::new();
+        for entity in EntityService::list_all_in_vault(db, vault_id).await? {
+            let Some(stem) = Path::new(&entity.path).file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            targets.insert(
+                stem.to_lowercase(),
+                Self::labels_of(registry, &entity).await,
+            );
+        }
+        Ok(targets)
+    }
+
+    /// An entity's labels, including those its type's schema applies to
+    /// every instance.
+    pub async fn labels_of(registry: &EntityTypeRegistry, entity: &Entity) -> Vec<String> {
+        let mut labels = entity.labels_vec();
+        if let Some(schema) = registry.get_by_id(&entity.entity_type).await {
+            labels.extend(schema.labels);
+        }
+        labels
+    }
+
+    /// Check an explicit relation against its relation type: the labels
+    /// both ends must carry and the declared metadata fields. Metadata keys
+    /// the type does not declare are errors. Fails for relation types that
+    /// are not registered.
+    pub async fn validate_relation(
+        db: &Database,
+        registry: &EntityTypeRegistry,
+        relation_type: &RelationTypeSchema,
+        from: &Entity,
+        to: &Entity,
+        metadata: &Map<String, Value>,
+    ) -> AppResult<Vec<FieldValidationError>> {
+        let mut errors = Vec::new();
+        let ends = [
+            ("from_entity_id", "source", from, &relation_type.from_label),
+            ("to_entity_id", "target", to, &relation_type.to_label),
+        ];
+        for (field, role, entity, required) in ends {
+            let Some(required) = required.as_deref().filter(|l| !l.is_empty()) else {
+                continue;
+            };
+            if !Self::labels_of(registry, entity)
+                .await
+                .iter()
+                .any(|l| l == required)
+            {
+                errors.push(FieldValidationError {
+                    field: field.to_string(),
+                    index: None,
+                    code: FieldErrorCode::WrongTargetLabel,
+                    message: format!(
+                        "The {role} of '{}' must be a '{required}' entity; {} is not one",
+                        relation_type.name, entity.path
+                    ),
+                });
+            }
+        }
+
+        for key in metadata.keys() {
+            if !relation_type.metadata_fields.iter().any(|f| &f.key == key) {
+                errors.push(FieldValidationError {
+                    field: format!("metadata.{key}"),
+                    index: None,
+                    code: FieldErrorCode::UnknownField,
+                    message: format!("'{}' has no metadata field '{key}'", relation_type.name),
+                });
+            }
+        }
+        let targets = if relation_type
+            .metadata_fields
+            .iter()
+            .any(|f| f.target_label.is_some())
+        {
+            Self::target_labels(db, registry, &from.vault_id).await?
+        } else {
+            TargetLabels::new()
+        };
+        errors.extend(
+            Self::validate_fields(&relation_type.metadata_fields, metadata, &targets)
+                .into_iter()
+                .map(|error| FieldValidationError {
+                    field: format!("metadata.{}", error.field),
+                    ..error
+                }),
+        );
+        Ok(errors)
+    }
+
+    /// Check frontmatter about to be saved. Returns no errors for notes
+    /// without a `codex_type` or whose type has no registered schema.
+    pub async fn validate_frontmatter(
+        db: &Database,
+        registry: &EntityTypeRegistry,
+        vault_id: &str,
+        frontmatter: &Value,
+    ) -> AppResult<Vec<FieldValidationError>> {
+        let Some(fields) = frontmatter.as_object() else {
+            return Ok(Vec::new());
+        };
+        let Some(entity_type) = fields.get("codex_type").and_then(Value::as_str) else {
+            return Ok(Vec::new());
+        };
+        let Some(schema) = registry.get_by_id(entity_type).await else {
+            return Ok(Vec::new());
+        };
+        let targets = if schema.fields.iter().any(|f| f.target_label.is_some()) {
+            Self::target_labels(db, registry, vault_id).await?
+        } else {
+            TargetLabels::new()
+        };
+        Ok(Self::validate_fields(&schema.fields, fields, &targets))
+    }
+
+    /// Check every indexed entity in a vault that `can_read` allows.
+    pub async fn report(
+        db: &Database,
+        registry: &EntityTypeRegistry,
+        vault_id: &str,
+        can_read: impl Fn(&str) -> bool,
+    ) -> AppResult<EntityValidationReport> {
+        let mode = db.get_vault_entity_validation(vault_id).await?;
+        let targets = Self::target_labels(db, registry, vault_id).await?;
+        let mut entities = EntityService::list_all_in_vault(db, vault_id).await?;
+        entities.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut report = EntityValidationReport {
+            vault_id: vault_id.to_string(),
+            mode,
+            checked: 0,
+            unknown_type: 0,
+            invalid: Vec::new(),
+        };
+        for entity in entities.into_iter().filter(|e| can_read(&e.path)) {
+            let Some(schema) = registry.get_by_id(&entity.entity_type).await else {
+                report.unknown_type += 1;
+                continue;
+            };
+            report.checked += 1;
+            let fields = entity.fields_map();
+            let errors = fields
+                .as_object()
+                .map(|fields| Self::validate_fields(&schema.fields, fields, &targets))
+                .unwrap_or_default();
+            if !errors.is_empty() {
+                report.invalid.push(EntityValidationIssue {
+                    entity_id: entity.id,
+                    path: entity.path,
+                    entity_type: entity.entity_type,
+                    errors,
+                });
+            }
+        }
+        Ok(report)
+    }
+}
+
+fn error(
+    field: &FieldSchema,
+    index: Option<usize>,
+    code: FieldErrorCode,
+    message: String,
+) -> FieldValidationError {
+    FieldValidationError {
+        field: field.key.clone(),
+        index,
+        code,
+        message,
+    }
+}
+
+/// Missing, null, blank text and empty lists all count as no value.
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.trim().is_empty(),
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+/// Check one value (or list item) against `field_type`.
+fn check_value(
+    field: &FieldSchema,
+    field_type: &FieldType,
+    value: &Value,
+    targets: &TargetLabels,
+) -> Option<(FieldErrorCode, String)> {
+    let label = &field.label;
+    let invalid_type = |expected: &str| {
+        Some((
+            FieldErrorCode::InvalidType,
+            format!("'{label}' must be {expected}"),
+        ))
+    };
+    match field_type {
+        FieldType::String | FieldType::Text => {
+            if !value.is_string() {
+                return invalid_type("text");
+            }
+        }
+        FieldType::Number => {
+            if !value.is_number() {
+                return invalid_type("a number");
+            }
+        }
+        FieldType::Boolean => {
+            if !value.is_boolean() {
+                return invalid_type("true or false");
+            }
+        }
+        FieldType::Date => {
+            let valid = value
+                .as_str()
+                .is_some_and(|s| NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").is_ok());
+            if !valid {
+                return invalid_type("a date (YYYY-MM-DD)");
+            }
+        }
+        FieldType::Enum => {
+            let Some(s) = value.as_str() else {
+                return invalid_type("text");
+            };
+            if !field.values.is_empty() && !field.values.iter().any(|v| v == s) {
+                return Some((
+                    FieldErrorCode::InvalidValue,
+                    format!(
+                        "'{s}' is not an allowed value for '{label}' (expected one of: {})",
+                        field.values.join(", ")
+                    ),
+                ));
+            }
+        }
+        FieldType::EntityRef => {
+            let Some(title) = value.as_str().and_then(extract_wiki_title) else {
+                return invalid_type("a [[link]] to an entity");
+            };
+            let target_label = field.target_label.as_ref()?;
+            let labels = targets.get(&title.to_lowercase())?;
+            if !labels.contains(target_label) {
+                return Some((
+                    FieldErrorCode::WrongTargetLabel,
+                    format!(
+                        "'{label}' must link to a '{target_label}' entity; [[{title}]] is not one"
+                    ),
+                ));
+            }
+        }
+        // Lists of lists are not checked item by item.
+        FieldType::List => {
+            if !value.is_array() {
+                return invalid_type("a list");
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::EntityTypeSchema;
+    use serde_json::json;
+
+    fn field(key: &str, field_type: FieldType) -> FieldSchema {
+        FieldSchema {
+            key: key.to_string(),
+            label: key.to_string(),
+            field_type,
+            required: false,
+            item_type: None,
+            values: Vec::new(),
+            default: None,
+            target_label: None,
+            relation: None,
+            description: None,
+            computed: None,
+        }
+    }
+
+    fn schema() -> EntityTypeSchema {
+        let mut name = field("name", FieldType::String);
+        name.required = true;
+        let mut status = field("status", FieldType::Enum);
+        status.values = vec!["alive".to_string(), "dead".to_string()];
+        let mut faction = field("faction", FieldType::EntityRef);
+        faction.target_label = Some("organization".to_string());
+        let mut allies = field("allies", FieldType::List);
+        allies.item_type = Some(FieldType::EntityRef);
+        allies.target_label = Some("person".to_string());
+        let mut titles = field("titles", FieldType::List);
+        titles.item_type = Some(FieldType::Enum);
+        titles.values = vec!["Lord".to_string(), "Knight".to_string()];
+        EntityTypeSchema {
+            id: "character".to_string(),
+            plugin_id: "worldbuilding".to_string(),
+            name: "Character".to_string(),
+            icon: None,
+            color: None,
+            template: None,
+            labels: vec!["person".to_string()],
+            display_field: Some("name".to_string()),
+            show_on_create: Vec::new(),
+            extends: None,
+            mixins: vec![],
+            fields: vec![
+                name,
+                field("age", FieldType::Number),
+                field("born", FieldType::Date),
+                field("living", FieldType::Boolean),
+                status,
+                faction,
+                allies,
+                titles,
+            ],
+        }
+    }
+
+    fn targets() -> TargetLabels {
+        TargetLabels::from([
+            ("the guild".to_string(), vec!["organization".to_string()]),
+            ("bob".to_string(), vec!["person".to_string()]),
+            ("rivertown".to_string(), vec!["place".to_string()]),
+        ])
+    }
+
+    fn codes(fields: Value) -> Vec<(String, Option<usize>, FieldErrorCode)> {
+        EntityValidationService::validate_fields(
+            &schema().fields,
+            fields.as_object().unwrap(),
+            &targets(),
+        )
+        .into_iter()
+        .map(|e| (e.field, e.index, e.code))
+        .collect()
+    }
+
+    #[test]
+    fn valid_frontmatter_has_no_errors() {
+        let errors = codes(json!({
+            "codex_type": "character",
+            "name": "Alice",
+            "age": 31,
+            "born": "1990-04-01",
+            "living": true,
+            "status": "alive",
+            "faction": "[[The Guild]]",
+            "allies": ["[[Bob]]", "[[Nobody Yet]]"],
+            "titles": ["Knight"],
+            "notes": 12,
+        }));
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn reports_each_violation_by_field() {
+        let errors = codes(json!({
+            "name": "  ",
+            "age": "old",
+            "born": "spring",
+            "living": "yes",
+            "status": "undead",
+            "faction": "[[Rivertown]]",
+            "allies": ["[[Bob]]", "[[The Guild]]", 7],
+            "titles": "Lord",
+        }));
+        assert_eq!(
+            errors,
+            vec![
+                ("name".to_string(), None, FieldErrorCode::Required),
+                ("age".to_string(), None, FieldErrorCode::InvalidType),
+                ("born".to_string(), None, FieldErrorCode::InvalidType),
+                ("living".to_string(), None, FieldErrorCode::InvalidType),
+                ("status".to_string(), None, FieldErrorCode::InvalidValue),
+                (
+                    "faction".to_string(),
+                    None,
+                    FieldErrorCode::WrongTargetLabel
+                ),
+                (
+                    "allies".to_string(),
+                    Some(1),
+                    FieldErrorCode::WrongTargetLabel
+                ),
+                ("allies".to_string(), Some(2), FieldErrorCode::InvalidType),
+                ("titles".to_string(), None, FieldErrorCode::InvalidType),
+            ]
+        );
+    }
+
+    #[test]
+    fn list_items_use_enum_values() {
+        let errors = codes(json!({ "name": "Alice", "titles": ["Lord", "Jester"] }));
+        assert_eq!(
+            errors,
+            vec![("titles".to_string(), Some(1), FieldErrorCode::InvalidValue)]
+        );
+    }
+}
@@ -1,21 +1,11 @@
 use crate::error::{AppError, AppResult};
 use crate::models::{FileContent, FileNode};
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
-/// Represents a file that has been moved to the vault's `.trash/` folder.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TrashItem {
-    /// The timestamped filename inside `.trash/` (e.g. `20240101_120000.000_notes.md`).
-    pub trash_name: String,
-    /// The original vault-relative path before the file was trashed.
-    pub original_path: String,
-    /// RFC-3339 timestamp of when the file was trashed.
-    pub trashed_at: String,
-}
+pub use crate::models::trash::TrashItem;
 
 /// Convert a `SystemTime` to `DateTime<Utc>` preserving sub-second precision.
 /// Using `subsec_nanos()` ensures that two writes in the same second still
@@ -25,15 +15,42 @@ fn system_time_to_datetime(t: std::time::SystemTime) -> Option<DateTime<Utc>> {
     DateTime::from_timestamp(dur.as_secs() as i64, dur.subsec_nanos())
 }
 
+/// Size of a file, or the total size of the files below a directory.
+fn path_size(path: &Path) -> u64 {
+    if path.is_dir() {
+        walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum()
+    } else {
+        fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
+
 pub struct FileService;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RenameStrategy {
     Fail,
     Overwrite,
     AutoRename, // e.g., file.txt -> file (1).txt
 }
 
+impl RenameStrategy {
+    /// Parse the `strategy` value accepted by the API (`fail`, `overwrite`,
+    /// `autorename`); anything else means `Fail`.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "overwrite" => RenameStrategy::Overwrite,
+            "autorename" => RenameStrategy::AutoRename,
+            _ => RenameStrategy::Fail,
+        }
+    }
+}
+
 impl FileService {
     /// Get file tree for a vault
     pub fn get_file_tree(vault_path: &str) -> AppResult<Vec<FileNode>> {
@@ -171,8 +188,9 @@ impl FileService {
         })
     }
 
-    /// Read raw file content (for binary files like images, PDFs, etc.)
-    pub fn read_raw_file(vault_path: &str, file_path: &str) -> AppResult<Vec<u8>> {
+    /// Resolve a path that must name an existing regular file, for
+    /// handlers that stream binary content (images, PDFs, video).
+    pub fn resolve_existing_file(vault_path: &str, file_path: &str) -> AppResult<PathBuf> {
         let full_path = Self::resolve_path(vault_path, file_path)?;
 
         if !full_path.exists() {
@@ -185,8 +203,7 @@ impl FileService {
             ));
         }
 
-        let content = fs::read(&full_path)?;
-        Ok(content)
+        Ok(full_path)
     }
 
     /// Write file content with conflict detection
@@ -260,6 +277,13 @@ impl FileService {
         }
 
         let content = fs::read_to_string(&full_path)?;
+        fs::write(Self::conflict_copy_path(&full_path), content)?;
+        Ok(())
+    }
+
+    /// Sibling path used for a conflicting copy of `full_path`:
+    /// `conflict_<stem>_<YYYYMMDD_HHMMSS>.<ext>`.
+    pub fn conflict_copy_path(full_path: &Path) -> PathBuf {
         let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
 
         let conflict_filename = format!(
@@ -271,14 +295,11 @@ impl FileService {
             timestamp
         );
 
-        let conflict_path = if let Some(ext) = full_path.extension().and_then(|s| s.to_str()) {
+        if let Some(ext) = full_path.extension().and_then(|s| s.to_str()) {
             full_path.with_file_name(format!("{}.{}", conflict_filename, ext))
         } else {
             full_path.with_file_name(conflict_filename)
-        };
-
-        fs::write(conflict_path, content)?;
-        Ok(())
+        }
     }
 
     /// Create a new file
@@ -321,14 +342,14 @@ impl FileService {
     }
 
     /// Delete a file (move to trash)
-    pub fn delete_file(vault_path: &str, file_path: &str) -> AppResult<()> {
+    pub fn delete_file(vault_path: &str, file_path: &str) -> AppResult<TrashItem> {
         info!(vault_path = %vault_path, file_path = %file_path, "Deleting file (moving to trash)");
         Self::move_to_trash(vault_path, file_path)
     }
 
     /// Move file to .trash folder, writing a sidecar `.meta.json` with the original path
     /// so the file can later be restored.
-    pub fn move_to_trash(vault_path: &str, file_path: &str) -> AppResult<()> {
+    pub fn move_to_trash(vault_path: &str, file_path: &str) -> AppResult<TrashItem> {
         let full_path = Self::resolve_path(vault_path, file_path)?;
 
         if !full_path.exists() {
@@ -344,18 +365,28 @@ impl FileService {
         let file_name = full_path.file_name().unwrap_or_default().to_string_lossy();
         let trash_name = format!("{timestamp}_{file_name}");
 
+        let size = path_size(&full_path);
         let dest_path = trash_dir.join(&trash_name);
         fs::rename(&full_path, &dest_path)?;
 
         // Write sidecar so we can restore the original path later
+        let trashed_at = Utc::now().to_rfc3339();
         let meta = serde_json::json!({
             "original_path": file_path,
-            "trashed_at": Utc::now().to_rfc3339(),
+            "trashed_at": trashed_at,
+            "size": size,
         });
         let meta_path = trash_dir.join(format!("{trash_name}.meta.json"));
         fs::write(&meta_path, serde_json::to_vec_pretty(&meta)?)?;
 
-        Ok(())
+        Ok(TrashItem {
+            trash_name,
+            original_path: file_path.to_string(),
+            trashed_at,
+            size,
+            deleted_by: None,
+            deleted_by_username: None,
+        })
     }
 
     /// List items currently in the vault's .trash folder.
@@ -379,20 +410,33 @@ impl FileService {
             let meta_bytes = fs::read(entry.path())?;
             let meta: serde_json::Value =
                 serde_json::from_slice(&meta_bytes).unwrap_or(serde_json::Value::Null);
+            let size = meta["size"]
+                .as_u64()
+                .unwrap_or_else(|| path_size(&trash_dir.join(&trash_name)));
             items.push(TrashItem {
                 trash_name,
                 original_path: meta["original_path"].as_str().unwrap_or("").to_string(),
                 trashed_at: meta["trashed_at"].as_str().unwrap_or("").to_string(),
+                size,
+                deleted_by: None,
+                deleted_by_username: None,
             });
         }
         items.sort_by(|a, b| b.trashed_at.cmp(&a.trashed_at));
         Ok(items)
     }
 
-    /// Restore a file from trash to its original location.
+    /// Restore a file from trash to its original location, recreating any
+    /// missing parent directories. `strategy` decides what happens when
+    /// something already exists at the original path. Returns the
+    /// vault-relative path the file was restored to.
     ///
     /// `trash_name` is the timestamped filename in `.trash/` (as returned by `list_trash`).
-    pub fn restore_file(vault_path: &str, trash_name: &str) -> AppResult<()> {
+    pub fn restore_file(
+        vault_path: &str,
+        trash_name: &str,
+        strategy: RenameStrategy,
+    ) -> AppResult<String> {
         // Validate that trash_name contains no path separators (basic traversal guard)
         if trash_name.contains('/') || trash_name.contains('\\') || trash_name.contains("..") {
             return Err(AppError::InvalidInput(
@@ -422,23 +466,22 @@ impl FileService {
             AppError::InternalError("Missing original_path in trash metadata".to_string())
         })?;
 
-        let restore_path = Self::resolve_path(vault_path, original_path)?;
-
-        // Ensure the parent directory exists
-        if let Some(parent) = restore_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-
-        if restore_path.exists() {
-            return Err(AppError::Conflict(format!(
+        // `rename` creates missing parent directories and applies the strategy.
+        let restored_path = Self::rename(
+            vault_path,
+            &format!(".trash/{trash_name}"),
+            original_path,
+            strategy,
+        )
+        .map_err(|e| match e {
+            AppError::Conflict(_) => AppError::Conflict(format!(
                 "Cannot restore: a file already exists at '{original_path}'"
-            )));
-        }
-
-        fs::rename(&trashed_file, &restore_path)?;
+            )),
+            other => other,
+        })?;
         fs::remove_file(&meta_file)?;
 
-        Ok(())
+        Ok(restored_path)
     }
 
     /// Permanently delete a file from trash (no recovery possible).
@@ -549,7 +592,7 @@ impl FileService {
 
                     loop {
                         let new_name = if let Some(extension) = ext {
-                            format!("{} ({}) .{}", stem, counter, extension)
+                            format!("{} ({}).{}", stem, counter, extension)
                         } else {
                             format!("{} ({})", stem, counter)
                         };
@@ -649,7 +692,7 @@ impl FileService {
 
     /// Creates the temp directory and empty file for a chunked upload session.
     pub fn create_upload_session_temp(vault_path: &str, session_id: &str) -> AppResult<()> {
-        let upload_dir = Path::new(vault_path).join(".obsidian").join("uploads");
+        let upload_dir = Self::upload_temp_dir(vault_path);
         std::fs::create_dir_all(&upload_dir)?;
         let temp_file_path = upload_dir.join(session_id);
         std::fs::File::create(temp_file_path)?;
@@ -670,6 +713,36 @@ impl FileService {
         Ok(file.metadata()?.len())
     }
 
+    /// Writes a chunk at `offset` in an upload session temp file. Chunks may
+    /// arrive in any order and from concurrent requests.
+    pub fn write_upload_chunk_at(
+        vault_path: &str,
+        session_id: &str,
+        offset: u64,
+        bytes: &[u8],
+    ) -> AppResult<()> {
+        use std::fs::OpenOptions;
+        use std::io::{Seek, SeekFrom, Write};
+        let temp_file_path = upload_temp_file_path(vault_path, session_id);
+        if !temp_file_path.exists() {
+            return Err(AppError::NotFound("Upload session not found".to_string()));
+        }
+        let mut file = OpenOptions::new().write(true).open(&temp_file_path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Path of an upload session's temp file.
+    pub fn upload_session_temp_path(vault_path: &str, session_id: &str) -> PathBuf {
+        upload_temp_file_path(vault_path, session_id)
+    }
+
+    /// Folder holding the temp files of a vault's in-progress uploads.
+    pub fn upload_temp_dir(vault_path: &str) -> PathBuf {
+        Path::new(vault_path).join(".obsidian").join("uploads")
+    }
+
     /// Returns the current byte size of an upload session temp file.
     pub fn get_upload_session_size(vault_path: &str, session_id: &str) -> AppResult<u64> {
         let temp_file_path = upload_temp_file_path(vault_path, session_id);
@@ -767,10 +840,7 @@ impl FileService {
 }
 
 fn upload_temp_file_path(vault_path: &str, session_id: &str) -> std::path::PathBuf {
-    Path::new(vault_path)
-        .join(".obsidian")
-        .join("uploads")
-        .join(session_id)
+    FileService::upload_temp_dir(vault_path).join(session_id)
 }
 
 fn validate_upload_filename(filename: &str) -> AppResult<()> {
@@ -820,4 +890,41 @@ mod tests {
         let existing = FileService::resolve_path(vault_path, "existing.md");
         assert!(existing.is_ok());
     }
+    #[test]
+    fn test_restore_from_trash_recreates_parents_and_applies_strategy() {
+        let temp = TempDir::new().unwrap();
+        let vault_path = temp.path().to_str().unwrap();
+        fs::create_dir_all(temp.path().join("notes/deep")).unwrap();
+        fs::write(temp.path().join("notes/deep/a.md"), "first").unwrap();
+
+        let item = FileService::move_to_trash(vault_path, "notes/deep/a.md").unwrap();
+        assert_eq!(item.original_path, "notes/deep/a.md");
+        assert_eq!(item.size, 5);
+        fs::remove_dir_all(temp.path().join("notes")).unwrap();
+
+        let restored =
+            FileService::restore_file(vault_path, &item.trash_name, RenameStrategy::Fail).unwrap();
+        assert_eq!(restored, "notes/deep/a.md");
+        assert_eq!(
+            fs::read_to_string(temp.path().join("notes/deep/a.md")).unwrap(),
+            "first"
+        );
+
+        let item = FileService::move_to_trash(vault_path, "notes/deep/a.md").unwrap();
+        fs::write(temp.path().join("notes/deep/a.md"), "second").unwrap();
+        assert!(matches!(
+            FileService::restore_file(vault_path, &item.trash_name, RenameStrategy::Fail),
+            Err(AppError::Conflict(_))
+        ));
+
+        let restored =
+            FileService::restore_file(vault_path, &item.trash_name, RenameStrategy::AutoRename)
+                .unwrap();
+        assert_eq!(restored, "notes/deep/a (1).md");
+        assert_eq!(
+            fs::read_to_string(temp.path().join("notes/deep/a (1).md")).unwrap(),
+            "first"
+        );
+        assert!(FileService::list_trash(vault_path).unwrap().is_empty());
+    }
 }
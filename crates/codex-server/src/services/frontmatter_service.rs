@@ -64,6 +64,102 @@ pub fn serialize_frontmatter(frontmatter: Option<&Value>, content: &str) -> AppR
     }
 }
 
+/// Split a markdown document into its raw YAML frontmatter and the text that
+/// follows the closing `---` line, byte for byte. Documents without a closed
+/// frontmatter block are returned whole as the body.
+pub fn split_frontmatter(raw: &str) -> (Option<&str>, &str) {
+    let Some(first_line_end) = raw.find('\n') else {
+        return (None, raw);
+    };
+    if raw[..first_line_end].trim_end() != "---" {
+        return (None, raw);
+    }
+    let yaml_start = first_line_end + 1;
+    let mut line_start = yaml_start;
+    while line_start <= raw.len() {
+        let line_end = raw[line_start..]
+            .find('\n')
+            .map_or(raw.len(), |i| line_start + i);
+        if raw[line_start..line_end].trim() == "---" {
+            let body_start = (line_end + 1).min(raw.len());
+            return (Some(&raw[yaml_start..line_start]), &raw[body_start..]);
+        }
+        line_start = line_end + 1;
+    }
+    (None, raw)
+}
+
+/// Render a frontmatter block, keeping the mapping's key order, followed by
+/// `body` unchanged.
+pub fn render_frontmatter(frontmatter: &serde_yaml::Mapping, body: &str) -> AppResult<String> {
+    if frontmatter.is_empty() {
+        return Ok(body.to_string());
+    }
+    let yaml_str = serde_yaml::to_string(frontmatter)
+        .map_err(|e| AppError::InternalError(format!("Failed to serialize frontmatter: {}", e)))?;
+    Ok(format!("---\n{}---\n{}", yaml_str, body))
+}
+
+/// Set or remove (`null`) frontmatter keys of a markdown document, leaving
+/// its body untouched. Existing keys keep their position; new keys are
+/// appended.
+pub fn update_frontmatter_fields(
+    raw: &str,
+    updates: &serde_json::Map<String, Value>,
+) -> AppResult<String> {
+    let mut yaml_updates = Vec::with_capacity(updates.len());
+    for (key, value) in updates {
+        let value = if value.is_null() {
+            None
+        } else {
+            Some(serde_yaml::to_value(value).map_err(|e| {
+                AppError::InvalidInput(format!("Invalid value for '{}': {}", key, e))
+            })?)
+        };
+        yaml_updates.push((key.clone(), value));
+    }
+    set_frontmatter_values(raw, yaml_updates)
+}
+
+/// Like [`update_frontmatter_fields`], with YAML values so that nested
+/// mappings keep their key order. `None` removes the key.
+pub fn set_frontmatter_values(
+    raw: &str,
+    updates: impl IntoIterator<Item = (String, Option<serde_yaml::Value>)>,
+) -> AppResult<String> {
+    let (yaml, body) = split_frontmatter(raw);
+    let mut frontmatter = match yaml.map(serde_yaml::from_str::<serde_yaml::Value>) {
+        None => serde_yaml::Mapping::new(),
+        Some(Ok(serde_yaml::Value::Mapping(mapping))) => mapping,
+        Some(Ok(serde_yaml::Value::Null)) => serde_yaml::Mapping::new(),
+        Some(Ok(_)) => {
+            return Err(AppError::InvalidInput(
+                "Frontmatter is not a mapping".to_string(),
+            ))
+        }
+        Some(Err(e)) => {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid YAML frontmatter: {}",
+                e
+            )))
+        }
+    };
+
+    for (key, value) in updates {
+        let Some(value) = value else {
+            frontmatter.shift_remove(key.as_str());
+            continue;
+        };
+        if let Some(existing) = frontmatter.get_mut(key.as_str()) {
+            *existing = value;
+        } else {
+            frontmatter.insert(serde_yaml::Value::String(key), value);
+        }
+    }
+
+    render_frontmatter(&frontmatter, body)
+}
+
 /// Extract tags from frontmatter and content
 pub fn extract_tags(frontmatter: Option<&Value>, content: &str) -> Vec<String> {
     let mut tags = Vec::new();
@@ -135,6 +231,41 @@ Some content here"#;
         assert_eq!(remaining, content);
     }
 
+    #[test]
+    fn test_split_frontmatter_keeps_body_bytes() {
+        let raw = "---\ntitle: A\n---\n\n# Heading\r\nText\n\n";
+        let (yaml, body) = split_frontmatter(raw);
+        assert_eq!(yaml, Some("title: A\n"));
+        assert_eq!(body, "\n# Heading\r\nText\n\n");
+
+        assert_eq!(
+            split_frontmatter("# No frontmatter"),
+            (None, "# No frontmatter")
+        );
+        assert_eq!(
+            split_frontmatter("---\nunclosed: true\n"),
+            (None, "---\nunclosed: true\n")
+        );
+    }
+
+    #[test]
+    fn test_update_frontmatter_fields_preserves_order_and_body() {
+        let raw = "---\nzeta: 1\nalpha: old\nmiddle: keep\n---\nProse stays *exactly*.\n";
+        let updates = serde_json::json!({ "alpha": "new", "middle": null, "added": [1, 2] });
+        let updated = update_frontmatter_fields(raw, updates.as_object().unwrap()).unwrap();
+        assert_eq!(
+            updated,
+            "---\nzeta: 1\nalpha: new\nadded:\n- 1\n- 2\n---\nProse stays *exactly*.\n"
+        );
+    }
+
+    #[test]
+    fn test_update_frontmatter_fields_adds_block() {
+        let updates = serde_json::json!({ "title": "Note" });
+        let updated = update_frontmatter_fields("Body\n", updates.as_object().unwrap()).unwrap();
+        assert_eq!(updated, "---\ntitle: Note\n---\nBody\n");
+    }
+
     #[test]
     fn test_extract_tags() {
         let fm_json = serde_json::json!({
@@ -0,0 +1,926 @@
+use crate::error::{AppError, AppResult};
+use crate::models::graph::{
+    EdgeType, GraphData, GraphEdge, GraphNode, GraphNodeStats, GraphStats, NodeType,
+};
+use crate::services::entity_service::Entity;
+use crate::services::frontmatter_service;
+use crate::services::markdown_service::MarkdownService;
+use crate::services::relation_service::Relation;
+use crate::services::wiki_link_service::FileIndex;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Deepest neighbourhood a single query may expand.
+pub const MAX_NEIGHBORHOOD_DEPTH: u32 = 5;
+
+/// Betweenness is O(nodes × edges), so it is skipped for larger graphs.
+pub const BETWEENNESS_MAX_NODES: usize = 2000;
+
+/// Which entities and relations a graph query considers.
+#[derive(Debug, Clone, Default)]
+pub struct GraphFilter {
+    /// Relation types to follow; empty follows all.
+    pub relation_types: Vec<String>,
+    /// Keep only entities carrying one of these labels; empty keeps all.
+    pub labels: Vec<String>,
+}
+
+impl GraphFilter {
+    /// Build a filter from comma-separated query string values.
+    pub fn from_query(relation_types: Option<&str>, labels: Option<&str>) -> Self {
+        let list = |value: Option<&str>| {
+            value
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        Self {
+            relation_types: list(relation_types),
+            labels: list(labels),
+        }
+    }
+}
+
+/// An in-memory graph of a vault's entities for traversal queries.
+///
+/// Only forward relations are kept, since every inverse relation mirrors
+/// one of them; traversal follows edges in both directions.
+pub struct EntityGraph {
+    entities: Vec<Entity>,
+    index: HashMap<String, usize>,
+    edges: Vec<Relation>,
+    /// Edge indices touching each node
+    incident: Vec<Vec<usize>>,
+    /// Distinct neighbours of each node, self excluded
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl EntityGraph {
+    /// Build the graph from entities and relations matching `filter`.
+    /// Entities in `keep` are kept even when the label filter excludes
+    /// them, so that the entities a query starts from are always present.
+    pub fn build(
+        entities: Vec<Entity>,
+        relations: Vec<Relation>,
+        filter: &GraphFilter,
+        keep: &[&str],
+    ) -> Self {
+        let entities: Vec<Entity> = entities
+            .into_iter()
+            .filter(|e| {
+                filter.labels.is_empty()
+                    || keep.contains(&e.id.as_str())
+                    || e.labels_vec().iter().any(|l| filter.labels.contains(l))
+            })
+            .collect();
+        let index: HashMap<String, usize> = entities
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.id.clone(), i))
+            .collect();
+        let edges: Vec<Relation> = relations
+            .into_iter()
+            .filter(|r| {
+                r.direction == "forward"
+                    && (filter.relation_types.is_empty()
+                        || filter.relation_types.contains(&r.relation_type))
+                    && index.contains_key(&r.from_entity_id)
+                    && index.contains_key(&r.to_entity_id)
+            })
+            .collect();
+
+        let mut incident = vec![Vec::new(); entities.len()];
+        let mut neighbours = vec![HashSet::new(); entities.len()];
+        for (edge, r) in edges.iter().enumerate() {
+            let (from, to) = (index[&r.from_entity_id], index[&r.to_entity_id]);
+            incident[from].push(edge);
+            if from != to {
+                incident[to].push(edge);
+                neighbours[from].insert(to);
+                neighbours[to].insert(from);
+            }
+        }
+        let neighbours = neighbours
+            .into_iter()
+            .map(|set| {
+                let mut list: Vec<usize> = set.into_iter().collect();
+                list.sort_unstable();
+                list
+            })
+            .collect();
+
+        Self {
+            entities,
+            index,
+            edges,
+            incident,
+            neighbours,
+        }
+    }
+
+    fn node_index(&self, entity_id: &str) -> AppResult<usize> {
+        self.index
+            .get(entity_id)
+            .copied()
+            .ok_or_else(|| AppError::NotFound(format!("Entity '{entity_id}' not found")))
+    }
+
+    /// The entities within `depth` hops of `entity_id` and every relation
+    /// between them.
+    pub fn neighborhood(&self, entity_id: &str, depth: u32) -> AppResult<GraphData> {
+        if depth > MAX_NEIGHBORHOOD_DEPTH {
+            return Err(AppError::InvalidInput(format!(
+                "depth must be at most {MAX_NEIGHBORHOOD_DEPTH}"
+            )));
+        }
+        let start = self.node_index(entity_id)?;
+        let mut distance = HashMap::from([(start, 0u32)]);
+        let mut order = vec![start];
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            let hops = distance[&node];
+            if hops == depth {
+                continue;
+            }
+            for &next in &self.neighbours[node] {
+                if let Entry::Vacant(slot) = distance.entry(next) {
+                    slot.insert(hops + 1);
+                    order.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let nodes = order
+            .iter()
+            .map(|&i| self.node(i, Some(distance[&i])))
+            .collect();
+        let edges = self
+            .edges
+            .iter()
+            .filter(|r| {
+                distance.contains_key(&self.index[&r.from_entity_id])
+                    && distance.contains_key(&self.index[&r.to_entity_id])
+            })
+            .map(edge)
+            .collect();
+        Ok(GraphData { nodes, edges })
+    }
+
+    /// A shortest chain of relations between two entities, with nodes and
+    /// edges in path order, or `None` when they are not connected.
+    pub fn shortest_path(&self, from_id: &str, to_id: &str) -> AppResult<Option<GraphData>> {
+        let from = self.node_index(from_id)?;
+        let to = self.node_index(to_id)?;
+        // Node → (previous node, edge taken to reach it)
+        let mut previous: HashMap<usize, Option<(usize, usize)>> = HashMap::from([(from, None)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                break;
+            }
+            for &edge in &self.incident[node] {
+                let r = &self.edges[edge];
+                let (a, b) = (self.index[&r.from_entity_id], self.index[&r.to_entity_id]);
+                let next = if a == node { b } else { a };
+                if let Entry::Vacant(slot) = previous.entry(next) {
+                    slot.insert(Some((node, edge)));
+                    queue.push_back(next);
+                }
+            }
+        }
+        if !previous.contains_key(&to) {
+            return Ok(None);
+        }
+
+        let mut nodes = vec![to];
+        let mut edges = Vec::new();
+        let mut current = to;
+        while let Some((prev, edge_index)) = previous[&current] {
+            nodes.push(prev);
+            edges.push(edge_index);
+            current = prev;
+        }
+        nodes.reverse();
+        edges.reverse();
+        Ok(Some(GraphData {
+            nodes: nodes
+                .iter()
+                .enumerate()
+                .map(|(hops, &i)| self.node(i, Some(hops as u32)))
+                .collect(),
+            edges: edges.iter().map(|&e| edge(&self.edges[e])).collect(),
+        }))
+    }
+
+    /// Connected components, largest first.
+    pub fn components(&self) -> Vec<GraphData> {
+        let mut component_of = vec![usize::MAX; self.entities.len()];
+        let mut members: Vec<Vec<usize>> = Vec::new();
+        for start in 0..self.entities.len() {
+            if component_of[start] != usize::MAX {
+                continue;
+            }
+            let id = members.len();
+            let mut nodes = vec![start];
+            component_of[start] = id;
+            let mut queue = VecDeque::from([start]);
+            while let Some(node) = queue.pop_front() {
+                for &next in &self.neighbours[node] {
+                    if component_of[next] == usize::MAX {
+                        component_of[next] = id;
+                        nodes.push(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+            members.push(nodes);
+        }
+
+        let mut edges: Vec<Vec<GraphEdge>> = vec![Vec::new(); members.len()];
+        for r in &self.edges {
+            edges[component_of[self.index[&r.from_entity_id]]].push(edge(r));
+        }
+        let mut components: Vec<GraphData> = members
+            .into_iter()
+            .zip(edges)
+            .map(|(nodes, edges)| GraphData {
+                nodes: nodes.into_iter().map(|i| self.node(i, None)).collect(),
+                edges,
+            })
+            .collect();
+        components.sort_by_key(|c| std::cmp::Reverse(c.nodes.len()));
+        components
+    }
+
+    /// Degree and centrality of the `limit` best-connected entities.
+    pub fn stats(&self, limit: usize) -> GraphStats {
+        let n = self.entities.len();
+        let betweenness = (n <= BETWEENNESS_MAX_NODES).then(|| self.betweenness());
+        let mut out_degree = vec![0; n];
+        let mut in_degree = vec![0; n];
+        for r in &self.edges {
+            out_degree[self.index[&r.from_entity_id]] += 1;
+            in_degree[self.index[&r.to_entity_id]] += 1;
+        }
+
+        let mut nodes: Vec<GraphNodeStats> = (0..n)
+            .map(|i| GraphNodeStats {
+                id: self.entities[i].id.clone(),
+                label: title(&self.entities[i]),
+                degree: in_degree[i] + out_degree[i],
+                in_degree: in_degree[i],
+                out_degree: out_degree[i],
+                degree_centrality: if n > 1 {
+                    self.neighbours[i].len() as f64 / (n - 1) as f64
+                } else {
+                    0.0
+                },
+                betweenness: betweenness.as_ref().map(|b| b[i]),
+            })
+            .collect();
+        nodes.sort_by(|a, b| b.degree.cmp(&a.degree).then_with(|| a.label.cmp(&b.label)));
+        nodes.truncate(limit);
+
+        GraphStats {
+            node_count: n,
+            edge_count: self.edges.len(),
+            component_count: self.components().len(),
+            nodes,
+        }
+    }
+
+    /// Normalised betweenness centrality of every node (Brandes).
+    fn betweenness(&self) -> Vec<f64> {
+        let n = self.entities.len();
+        let mut centrality = vec![0.0; n];
+        for source in 0..n {
+            let mut stack = Vec::new();
+            let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+            let mut paths = vec![0.0; n];
+            let mut distance = vec![-1i64; n];
+            paths[source] = 1.0;
+            distance[source] = 0;
+            let mut queue = VecDeque::from([source]);
+            while let Some(v) = queue.pop_front() {
+                stack.push(v);
+                for &w in &self.neighbours[v] {
+                    if distance[w] < 0 {
+                        distance[w] = distance[v] + 1;
+                        queue.push_back(w);
+                    }
+                    if distance[w] == distance[v] + 1 {
+                        paths[w] += paths[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+            let mut dependency = vec![0.0; n];
+            while let Some(w) = stack.pop() {
+                for &v in &predecessors[w] {
+                    dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+                }
+                if w != source {
+                    centrality[w] += dependency[w];
+                }
+            }
+        }
+        // Each pair was counted from both ends; normalise by the number of
+        // pairs not involving the node.
+        let pairs = if n > 2 {
+            ((n - 1) * (n - 2)) as f64
+        } else {
+            1.0
+        };
+        centrality.into_iter().map(|c| c / pairs).collect()
+    }
+
+    fn node(&self, i: usize, depth: Option<u32>) -> GraphNode {
+        let entity = &self.entities[i];
+        GraphNode {
+            id: entity.id.clone(),
+            label: title(entity),
+            node_type: NodeType::Entity,
+            size: self.incident[i].len() as f32,
+            color: None,
+            tags: None,
+            path: Some(entity.path.clone()),
+            title: Some(title(entity)),
+            entity_type: Some(entity.entity_type.clone()),
+            labels: Some(entity.labels_vec()),
+            depth,
+        }
+    }
+}
+
+/// Which parts of a vault the note graph draws.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GraphInclude {
+    /// Notes and the wiki links and embeds between them
+    pub links: bool,
+    /// Tags, linked to the notes carrying them
+    pub tags: bool,
+    /// Entities and the relations between them
+    pub relations: bool,
+    /// Images and other non-Markdown files
+    pub attachments: bool,
+}
+
+impl GraphInclude {
+    /// Parse a comma-separated `include` value. Without one, only entities
+    /// and their relations are drawn.
+    pub fn parse(value: Option<&str>) -> AppResult<Self> {
+        let Some(value) = value else {
+            return Ok(Self {
+                relations: true,
+                ..Self::default()
+            });
+        };
+        let mut include = Self::default();
+        for part in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part {
+                "links" => include.links = true,
+                "tags" => include.tags = true,
+                "relations" => include.relations = true,
+                "attachments" => include.attachments = true,
+                other => {
+                    return Err(AppError::InvalidInput(format!(
+                        "Unknown graph include '{other}'; use links, tags, relations or attachments"
+                    )))
+                }
+            }
+        }
+        Ok(include)
+    }
+
+    /// Whether the vault's files have to be listed.
+    pub fn needs_files(&self) -> bool {
+        self.links || self.tags || self.attachments
+    }
+
+    /// Whether Markdown notes have to be read.
+    pub fn needs_content(&self) -> bool {
+        self.links || self.tags
+    }
+}
+
+/// Options for [`NoteGraph::build`].
+#[derive(Debug, Clone, Default)]
+pub struct NoteGraphOptions {
+    pub include: GraphInclude,
+    /// Only draw files under this folder
+    pub folder: Option<String>,
+    /// Keep nodes without any edge
+    pub orphans: bool,
+    /// Draw links to missing notes as virtual nodes
+    pub unresolved: bool,
+}
+
+/// A vault file the note graph may draw. `content` is the raw Markdown of
+/// notes when links or tags are included.
+#[derive(Debug, Clone)]
+pub struct GraphFile {
+    pub path: String,
+    pub content: Option<String>,
+}
+
+/// Builds the vault-wide graph of notes, links, tags, attachments and
+/// entity relations.
+///
+/// Files that are entities are drawn once, keyed by entity id so that
+/// their ids match the entity graph queries; other files are keyed by
+/// path, tags by `#tag`. Nodes are sized by the edges pointing at them.
+pub struct NoteGraph {
+    nodes: Vec<GraphNode>,
+    keys: HashMap<String, usize>,
+    edges: Vec<GraphEdge>,
+    /// (source, target, kind) → edge index, for counting repeated links
+    counted: HashMap<(String, String, &'static str), usize>,
+}
+
+impl NoteGraph {
+    pub fn build(
+        files: &[GraphFile],
+        entities: &[Entity],
+        relations: &[Relation],
+        type_colors: &HashMap<String, String>,
+        options: &NoteGraphOptions,
+    ) -> GraphData {
+        let include = options.include;
+        let in_folder = |path: &str| match options.folder.as_deref().map(|f| f.trim_matches('/')) {
+            Some(folder) if !folder.is_empty() => path
+                .strip_prefix(folder)
+                .is_some_and(|rest| rest.starts_with('/')),
+            _ => true,
+        };
+        let entity_at: HashMap<&str, &Entity> =
+            entities.iter().map(|e| (e.path.as_str(), e)).collect();
+        let mut graph = Self {
+            nodes: Vec::new(),
+            keys: HashMap::new(),
+            edges: Vec::new(),
+            counted: HashMap::new(),
+        };
+
+        if include.relations {
+            for entity in entities.iter().filter(|e| in_folder(&e.path)) {
+                graph.add_entity(entity, type_colors);
+            }
+        }
+        for file in files.iter().filter(|f| in_folder(&f.path)) {
+            if is_markdown(&file.path) {
+                if !include.needs_content() {
+                    continue;
+                }
+                match entity_at.get(file.path.as_str()) {
+                    Some(entity) => graph.add_entity(entity, type_colors),
+                    None => graph.add_file(&file.path, NodeType::File),
+                }
+            } else if include.attachments {
+                let node_type = if is_image(&file.path) {
+                    NodeType::Image
+                } else {
+                    NodeType::Attachment
+                };
+                graph.add_file(&file.path, node_type);
+            }
+        }
+        let key_of = |path: &str| match entity_at.get(path) {
+            Some(entity) => entity.id.clone(),
+            None => path.to_string(),
+        };
+
+        if include.relations {
+            for r in relations.iter().filter(|r| r.direction == "forward") {
+                if graph.keys.contains_key(&r.from_entity_id)
+                    && graph.keys.contains_key(&r.to_entity_id)
+                {
+                    graph.edges.push(edge(r));
+                }
+            }
+        }
+
+        let index = FileIndex::from_paths(files.iter().map(|f| f.path.as_str()));
+        for file in files.iter().filter(|f| in_folder(&f.path)) {
+            let Some(content) = &file.content else {
+                continue;
+            };
+            let source = key_of(&file.path);
+            let (frontmatter, body) = frontmatter_service::parse_frontmatter(content)
+                .unwrap_or_else(|_| (None, content.clone()));
+
+            if include.links {
+                for (target, embed) in MarkdownService::wiki_links(&body) {
+                    let resolved = index.resolve(&target);
+                    let target_key = if resolved.exists {
+                        key_of(&resolved.path)
+                    } else if options.unresolved {
+                        graph.add_node(GraphNode {
+                            id: resolved.path.clone(),
+                            label: stem(&resolved.path),
+                            node_type: NodeType::Virtual,
+                            size: 1.0,
+                            color: None,
+                            tags: None,
+                            path: None,
+                            title: None,
+                            entity_type: None,
+                            labels: None,
+                            depth: None,
+                        });
+                        resolved.path
+                    } else {
+                        continue;
+                    };
+                    let edge_type = if embed {
+                        EdgeType::Embed
+                    } else {
+                        EdgeType::Link
+                    };
+                    graph.count_edge(&source, &target_key, edge_type);
+                }
+            }
+            if include.tags {
+                let tags = frontmatter_service::extract_tags(frontmatter.as_ref(), &body);
+                for tag in &tags {
+                    let key = format!("#{tag}");
+                    graph.add_node(GraphNode {
+                        id: key.clone(),
+                        label: key.clone(),
+                        node_type: NodeType::Tag,
+                        size: 1.0,
+                        color: None,
+                        tags: None,
+                        path: None,
+                        title: None,
+                        entity_type: None,
+                        labels: None,
+                        depth: None,
+                    });
+                    graph.count_edge(&source, &key, EdgeType::Tag);
+                }
+                if let Some(&i) = graph.keys.get(&source) {
+                    graph.nodes[i].tags = (!tags.is_empty()).then_some(tags);
+                }
+            }
+        }
+
+        graph.finish(options.orphans)
+    }
+
+    fn add_node(&mut self, node: GraphNode) {
+        if let Entry::Vacant(slot) = self.keys.entry(node.id.clone()) {
+            slot.insert(self.nodes.len());
+            self.nodes.push(node);
+        }
+    }
+
+    fn add_entity(&mut self, entity: &Entity, type_colors: &HashMap<String, String>) {
+        self.add_node(GraphNode {
+            id: entity.id.clone(),
+            label: title(entity),
+            node_type: NodeType::Entity,
+            size: 1.0,
+            color: type_colors.get(&entity.entity_type).cloned(),
+            tags: None,
+            path: Some(entity.path.clone()),
+            title: Some(title(entity)),
+            entity_type: Some(entity.entity_type.clone()),
+            labels: Some(entity.labels_vec()),
+            depth: None,
+        });
+    }
+
+    fn add_file(&mut self, path: &str, node_type: NodeType) {
+        self.add_node(GraphNode {
+            id: path.to_string(),
+            label: stem(path),
+            node_type,
+            size: 1.0,
+            color: None,
+            tags: None,
+            path: Some(path.to_string()),
+            title: Some(stem(path)),
+            entity_type: None,
+            labels: None,
+            depth: None,
+        });
+    }
+
+    /// Add one link, embed or tag edge, merging repeats into its count.
+    /// Edges to files outside the drawn set are dropped.
+    fn count_edge(&mut self, source: &str, target: &str, edge_type: EdgeType) {
+        if !self.keys.contains_key(source) || !self.keys.contains_key(target) {
+            return;
+        }
+        let kind = match edge_type {
+            EdgeType::Link => "link",
+            EdgeType::Embed => "embed",
+            EdgeType::Tag => "tag",
+            EdgeType::Relation => "relation",
+        };
+        let key = (source.to_string(), target.to_string(), kind);
+        match self.counted.entry(key) {
+            Entry::Occupied(slot) => self.edges[*slot.get()].count += 1,
+            Entry::Vacant(slot) => {
+                slot.insert(self.edges.len());
+                self.edges.push(GraphEdge {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    count: 1,
+                    edge_type,
+                    id: None,
+                    relation_type: None,
+                });
+            }
+        }
+    }
+
+    /// Size nodes by incoming edges and drop orphans unless asked not to.
+    fn finish(mut self, orphans: bool) -> GraphData {
+        let mut incoming = vec![0u32; self.nodes.len()];
+        let mut connected = vec![false; self.nodes.len()];
+        for e in &self.edges {
+            let (source, target) = (self.keys[&e.source], self.keys[&e.target]);
+            incoming[target] += e.count;
+            connected[source] = true;
+            connected[target] = true;
+        }
+        for (i, node) in self.nodes.iter_mut().enumerate() {
+            node.size = 1.0 + incoming[i] as f32;
+        }
+        let nodes = self
+            .nodes
+            .into_iter()
+            .zip(connected)
+            .filter(|(_, connected)| orphans || *connected)
+            .map(|(node, _)| node)
+            .collect();
+        GraphData {
+            nodes,
+            edges: self.edges,
+        }
+    }
+}
+
+fn is_markdown(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case("md"))
+}
+
+fn is_image(path: &str) -> bool {
+    path.rsplit_once('.').is_some_and(|(_, ext)| {
+        ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"]
+            .iter()
+            .any(|image| ext.eq_ignore_ascii_case(image))
+    })
+}
+
+fn title(entity: &Entity) -> String {
+    stem(&entity.path)
+}
+
+fn stem(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(path)
+        .to_string()
+}
+
+fn edge(r: &Relation) -> GraphEdge {
+    GraphEdge {
+        source: r.from_entity_id.clone(),
+        target: r.to_entity_id.clone(),
+        count: 1,
+        edge_type: EdgeType::Relation,
+        id: Some(r.id.clone()),
+        relation_type: Some(r.relation_type.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(id: &str, labels: &[&str]) -> Entity {
+        Entity {
+            id: id.to_string(),
+            vault_id: "v".to_string(),
+            path: format!("{id}.md"),
+            entity_type: "character".to_string(),
+            plugin_id: "test".to_string(),
+            labels: serde_json::to_string(labels).unwrap(),
+            fields: "{}".to_string(),
+            modified_at: String::new(),
+            indexed_at: String::new(),
+        }
+    }
+
+    fn relation(from: &str, to: &str, relation_type: &str, direction: &str) -> Relation {
+        Relation {
+            id: format!("{from}-{relation_type}-{to}-{direction}"),
+            vault_id: "v".to_string(),
+            from_entity_id: from.to_string(),
+            to_entity_id: to.to_string(),
+            relation_type: relation_type.to_string(),
+            direction: direction.to_string(),
+            metadata: None,
+            source: "field".to_string(),
+            source_field: Some(relation_type.to_string()),
+            created_at: String::new(),
+        }
+    }
+
+    /// a - b - c - d, plus e - f and a lone g. b and c are organisations.
+    fn graph(filter: &GraphFilter, keep: &[&str]) -> EntityGraph {
+        let entities = vec![
+            entity("a", &["person"]),
+            entity("b", &["organization"]),
+            entity("c", &["organization"]),
+            entity("d", &["person"]),
+            entity("e", &["person"]),
+            entity("f", &["person"]),
+            entity("g", &["person"]),
+        ];
+        let mut relations = Vec::new();
+        for (from, to, kind) in [
+            ("a", "b", "member_of"),
+            ("c", "b", "ally"),
+            ("d", "c", "member_of"),
+            ("e", "f", "ally"),
+        ] {
+            relations.push(relation(from, to, kind, "forward"));
+            relations.push(relation(to, from, &format!("inverse_of_{kind}"), "inverse"));
+        }
+        EntityGraph::build(entities, relations, filter, keep)
+    }
+
+    fn ids(data: &GraphData) -> Vec<&str> {
+        data.nodes.iter().map(|n| n.id.as_str()).collect()
+    }
+
+    #[test]
+    fn neighborhood_expands_by_depth_in_both_directions() {
+        let graph = graph(&GraphFilter::default(), &[]);
+        let near = graph.neighborhood("b", 1).unwrap();
+        assert_eq!(ids(&near), ["b", "a", "c"]);
+        assert_eq!(near.edges.len(), 2);
+        assert_eq!(near.nodes[1].depth, Some(1));
+
+        let far = graph.neighborhood("a", 3).unwrap();
+        assert_eq!(ids(&far), ["a", "b", "c", "d"]);
+        assert_eq!(far.edges.len(), 3);
+        assert!(graph.neighborhood("a", MAX_NEIGHBORHOOD_DEPTH + 1).is_err());
+        assert!(matches!(
+            graph.neighborhood("zzz", 1),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn filters_restrict_relation_types_and_labels() {
+        let by_type = GraphFilter::from_query(Some("member_of, "), None);
+        assert_eq!(by_type.relation_types, ["member_of"]);
+        let near = graph(&by_type, &[]).neighborhood("b", 2).unwrap();
+        assert_eq!(ids(&near), ["b", "a"]);
+
+        let by_label = GraphFilter::from_query(None, Some("organization"));
+        let graph = graph(&by_label, &["a"]);
+        let near = graph.neighborhood("a", 3).unwrap();
+        assert_eq!(ids(&near), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn shortest_path_is_in_order() {
+        let graph = graph(&GraphFilter::default(), &[]);
+        let path = graph.shortest_path("a", "d").unwrap().unwrap();
+        assert_eq!(ids(&path), ["a", "b", "c", "d"]);
+        let types: Vec<_> = path
+            .edges
+            .iter()
+            .map(|e| e.relation_type.as_deref().unwrap())
+            .collect();
+        assert_eq!(types, ["member_of", "ally", "member_of"]);
+        assert!(graph.shortest_path("a", "e").unwrap().is_none());
+        assert_eq!(ids(&graph.shortest_path("g", "g").unwrap().unwrap()), ["g"]);
+    }
+
+    #[test]
+    fn components_and_stats() {
+        let graph = graph(&GraphFilter::default(), &[]);
+        let components = graph.components();
+        let sizes: Vec<_> = components.iter().map(|c| c.nodes.len()).collect();
+        assert_eq!(sizes, [4, 2, 1]);
+        assert_eq!(components[0].edges.len(), 3);
+
+        let stats = graph.stats(2);
+        assert_eq!(stats.node_count, 7);
+        assert_eq!(stats.edge_count, 4);
+        assert_eq!(stats.component_count, 3);
+        assert_eq!(stats.nodes.len(), 2);
+        let b = &stats.nodes[0];
+        assert_eq!((b.id.as_str(), b.in_degree, b.out_degree), ("b", 2, 0));
+        assert!((b.degree_centrality - 2.0 / 6.0).abs() < 1e-9);
+        // b lies on the a–c and a–d paths: 2 of the 15 pairs without it
+        assert!((b.betweenness.unwrap() - 2.0 / 15.0).abs() < 1e-9);
+    }
+
+    fn note(path: &str, content: &str) -> GraphFile {
+        GraphFile {
+            path: path.to_string(),
+            content: Some(content.to_string()),
+        }
+    }
+
+    fn note_graph(
+        include: &str,
+        folder: Option<&str>,
+        orphans: bool,
+        unresolved: bool,
+    ) -> GraphData {
+        let mut hero = entity("hero", &["person"]);
+        hero.path = "people/hero.md".to_string();
+        let files = vec![
+            note(
+                "people/hero.md",
+                "---\ntags: [cast]\n---\nLives in [[City]], see [[City]] and ![[map.png]].",
+            ),
+            note("City.md", "Home of [[hero]] and [[Nowhere]]. #place"),
+            note("Lonely.md", "No links."),
+            GraphFile {
+                path: "map.png".to_string(),
+                content: None,
+            },
+        ];
+        let relations = [relation("hero", "hero", "self", "forward")];
+        let colors = HashMap::from([("character".to_string(), "#f00".to_string())]);
+        let options = NoteGraphOptions {
+            include: GraphInclude::parse(Some(include)).unwrap(),
+            folder: folder.map(str::to_string),
+            orphans,
+            unresolved,
+        };
+        NoteGraph::build(&files, &[hero], &relations, &colors, &options)
+    }
+
+    fn edge_list(data: &GraphData) -> Vec<(String, String, u32)> {
+        data.edges
+            .iter()
+            .map(|e| (e.source.clone(), e.target.clone(), e.count))
+            .collect()
+    }
+
+    #[test]
+    fn note_graph_includes_parse() {
+        let default = GraphInclude::parse(None).unwrap();
+        assert!(default.relations && !default.needs_files());
+        let all = GraphInclude::parse(Some("links, tags,attachments")).unwrap();
+        assert!(all.links && all.tags && all.attachments && !all.relations);
+        assert!(GraphInclude::parse(Some("links,bogus")).is_err());
+    }
+
+    #[test]
+    fn note_graph_draws_links_as_counted_edges() {
+        let data = note_graph("links", None, true, false);
+        assert_eq!(ids(&data), ["hero", "City.md", "Lonely.md"]);
+        assert_eq!(
+            edge_list(&data),
+            [
+                ("hero".to_string(), "City.md".to_string(), 2),
+                ("City.md".to_string(), "hero".to_string(), 1),
+            ]
+        );
+        // The entity keeps its type colour; City has two backlinks
+        assert_eq!(data.nodes[0].color.as_deref(), Some("#f00"));
+        assert_eq!(data.nodes[1].size, 3.0);
+
+        let connected = note_graph("links", None, false, true);
+        assert_eq!(ids(&connected), ["hero", "City.md", "Nowhere.md"]);
+        assert!(matches!(connected.nodes[2].node_type, NodeType::Virtual));
+    }
+
+    #[test]
+    fn note_graph_draws_tags_attachments_and_relations() {
+        let data = note_graph("tags,attachments,relations", None, false, false);
+        assert_eq!(ids(&data), ["hero", "City.md", "#cast", "#place"]);
+        assert!(matches!(data.edges[0].edge_type, EdgeType::Relation));
+        assert_eq!(
+            data.nodes[0].tags.as_deref(),
+            Some(&["cast".to_string()][..])
+        );
+
+        let embeds = note_graph("links,attachments", Some("people"), true, false);
+        assert_eq!(ids(&embeds), ["hero"]);
+        assert!(embeds.edges.is_empty());
+        let embeds = note_graph("links,attachments", None, true, false);
+        assert!(embeds
+            .edges
+            .iter()
+            .any(|e| e.target == "map.png" && matches!(e.edge_type, EdgeType::Embed)));
+        assert!(matches!(embeds.nodes[3].node_type, NodeType::Image));
+    }
+}
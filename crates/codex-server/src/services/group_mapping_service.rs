@@ -0,0 +1,206 @@
+//! Keeps Codex group membership and admin status in step with the groups a
+//! directory reports at sign-in (an OIDC claim or an LDAP attribute).
+//!
+//! Only Codex groups named in `auth.group_mappings` are managed: a user is
+//! added to those their directory groups map to and removed from the rest.
+//! Memberships of other groups are left alone. Mapped groups must already
+//! exist; missing ones are skipped with a warning.
+
+use crate::config::AuthConfig;
+use crate::db::Database;
+use crate::error::AppResult;
+use std::collections::BTreeSet;
+use tracing::warn;
+
+/// What a sync changed, by Codex group name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupSyncOutcome {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// The new admin status, if it changed.
+    pub admin: Option<bool>,
+}
+
+pub struct GroupMappingService;
+
+impl GroupMappingService {
+    pub fn is_configured(cfg: &AuthConfig) -> bool {
+        !cfg.group_mappings.is_empty() || !cfg.admin_groups.is_empty()
+    }
+
+    /// The Codex groups a user with `directory_groups` belongs in.
+    pub fn mapped_groups(cfg: &AuthConfig, directory_groups: &[String]) -> BTreeSet<String> {
+        cfg.group_mappings
+            .iter()
+            .filter(|(key, _)| directory_groups.iter().any(|g| matches(key, g)))
+            .flat_map(|(_, targets)| targets.iter().map(|t| t.trim().to_string()))
+            .collect()
+    }
+
+    pub fn is_admin(cfg: &AuthConfig, directory_groups: &[String]) -> bool {
+        cfg.admin_groups
+            .iter()
+            .any(|key| directory_groups.iter().any(|g| matches(key, g)))
+    }
+
+    /// Apply the mapping for one sign-in. `source` names the directory in
+    /// audit entries (`"oidc"`, `"ldap"`).
+    pub async fn sync(
+        db: &Database,
+        cfg: &AuthConfig,
+        user_id: &str,
+        username: &str,
+        source: &str,
+        directory_groups: &[String],
+    ) -> AppResult<GroupSyncOutcome> {
+        let mut outcome = GroupSyncOutcome::default();
+        if !Self::is_configured(cfg) {
+            return Ok(outcome);
+        }
+
+        let wanted = Self::mapped_groups(cfg, directory_groups);
+        let managed: BTreeSet<String> = cfg
+            .group_mappings
+            .values()
+            .flatten()
+            .map(|name| name.trim().to_string())
+            .collect();
+        let current = db.list_group_ids_for_member(user_id).await?;
+
+        for name in &managed {
+            let Some(group) = db.get_group_by_name(name).await? else {
+                if wanted.contains(name) {
+                    warn!("Mapped group '{name}' does not exist; skipping");
+                }
+                continue;
+            };
+            let is_member = current.contains(&group.id);
+            let should_be = wanted.contains(name);
+            let event = if should_be && !is_member {
+                db.add_user_to_group(&group.id, user_id).await?;
+                outcome.added.push(group.name.clone());
+                "directory_group_added"
+            } else if !should_be && is_member {
+                db.remove_user_from_group(&group.id, user_id).await?;
+                outcome.removed.push(group.name.clone());
+                "directory_group_removed"
+            } else {
+                continue;
+            };
+            let _ = db
+                .write_audit_log(
+                    Some(user_id),
+                    Some(username),
+                    event,
+                    Some(&format!(
+                        "{} {username} {} group '{}' per {source} directory groups",
+                        if should_be { "Added" } else { "Removed" },
+                        if should_be { "to" } else { "from" },
+                        group.name
+                    )),
+                    None,
+                    true,
+                )
+                .await;
+        }
+
+        if !cfg.admin_groups.is_empty() {
+            let should_be_admin = Self::is_admin(cfg, directory_groups);
+            if db.is_user_admin(user_id).await? != should_be_admin {
+                db.set_user_admin(user_id, should_be_admin).await?;
+                outcome.admin = Some(should_be_admin);
+                let _ = db
+                    .write_audit_log(
+                        Some(user_id),
+                        Some(username),
+                        if should_be_admin {
+                            "directory_admin_granted"
+                        } else {
+                            "directory_admin_revoked"
+                        },
+                        Some(&format!(
+                            "Set is_admin={should_be_admin} for {username} per {source} directory groups"
+                        )),
+                        None,
+                        true,
+                    )
+                    .await;
+            }
+        }
+
+        Ok(outcome)
+    }
+}
+
+/// A mapping key matches a directory group by exact name or, for LDAP DNs
+/// such as `cn=writers,ou=groups,dc=example,dc=com`, by its CN. Directory
+/// names are compared case-insensitively.
+fn matches(key: &str, group: &str) -> bool {
+    let key = key.trim();
+    key.eq_ignore_ascii_case(group.trim())
+        || common_name(group).is_some_and(|cn| key.eq_ignore_ascii_case(cn))
+}
+
+fn common_name(dn: &str) -> Option<&str> {
+    let first = dn.split(',').next()?.trim();
+    let (attr, value) = first.split_once('=')?;
+    attr.trim().eq_ignore_ascii_case("cn").then(|| value.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AuthConfig {
+        let mut cfg = AuthConfig::default();
+        cfg.group_mappings.insert(
+            "engineering".to_string(),
+            vec!["Engineering".to_string(), "Writers".to_string()],
+        );
+        cfg.group_mappings.insert(
+            "CN=Editors,OU=Groups,DC=example,DC=com".to_string(),
+            vec!["Editors".to_string()],
+        );
+        cfg.admin_groups = vec!["codex-admins".to_string()];
+        cfg
+    }
+
+    #[test]
+    fn maps_by_name_dn_or_cn() {
+        let cfg = config();
+        let groups = vec![
+            "cn=Engineering,ou=groups,dc=example,dc=com".to_string(),
+            "cn=editors,ou=groups,dc=example,dc=com".to_string(),
+        ];
+        let mapped = GroupMappingService::mapped_groups(&cfg, &groups);
+        assert_eq!(
+            mapped.into_iter().collect::<Vec<_>>(),
+            vec!["Editors", "Engineering", "Writers"]
+        );
+        assert!(GroupMappingService::mapped_groups(&cfg, &["marketing".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn admin_follows_admin_groups() {
+        let cfg = config();
+        assert!(GroupMappingService::is_admin(
+            &cfg,
+            &["Codex-Admins".to_string()]
+        ));
+        assert!(!GroupMappingService::is_admin(
+            &cfg,
+            &["engineering".to_string()]
+        ));
+        assert!(!GroupMappingService::is_admin(
+            &AuthConfig::default(),
+            &["codex-admins".to_string()]
+        ));
+    }
+
+    #[test]
+    fn common_name_needs_a_cn_rdn() {
+        assert_eq!(common_name("cn=writers,dc=example"), Some("writers"));
+        assert_eq!(common_name("ou=writers,dc=example"), None);
+        assert_eq!(common_name("writers"), None);
+    }
+}
@@ -26,14 +26,15 @@ pub struct Label {
 pub struct LabelService;
 
 impl LabelService {
-    /// Seed the 8 core reserved labels on startup. Uses INSERT OR IGNORE so
+    /// Seed the 8 core reserved labels on startup. Ignores existing rows so
     /// this is safe to call on every startup.
     pub async fn seed_core_labels(db: &Database) -> AppResult<()> {
         for (name, description) in CORE_LABELS {
             sqlx::query(
                 r#"
-                INSERT OR IGNORE INTO labels (name, description, source, plugin_id)
-                VALUES (?, ?, 'core', NULL)
+                INSERT INTO labels (name, description, source, plugin_id)
+                VALUES ($1, $2, 'core', NULL)
+                ON CONFLICT DO NOTHING
                 "#,
             )
             .bind(name)
@@ -62,7 +63,7 @@ impl LabelService {
     ) -> AppResult<()> {
         // Check for existing registration
         let existing: Option<Label> = sqlx::query_as(
-            "SELECT name, description, source, plugin_id FROM labels WHERE name = ?",
+            "SELECT name, description, source, plugin_id FROM labels WHERE name = $1",
         )
         .bind(name)
         .fetch_optional(db.pool())
@@ -89,7 +90,7 @@ impl LabelService {
         sqlx::query(
             r#"
             INSERT INTO labels (name, description, source, plugin_id)
-            VALUES (?, ?, 'plugin', ?)
+            VALUES ($1, $2, 'plugin', $3)
             "#,
         )
         .bind(name)
@@ -110,7 +111,7 @@ impl LabelService {
 
     /// Remove all labels registered by the given plugin.
     pub async fn remove_plugin_labels(db: &Database, plugin_id: &str) -> AppResult<()> {
-        sqlx::query("DELETE FROM labels WHERE plugin_id = ?")
+        sqlx::query("DELETE FROM labels WHERE plugin_id = $1")
             .bind(plugin_id)
             .execute(db.pool())
             .await
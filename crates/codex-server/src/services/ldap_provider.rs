@@ -4,18 +4,27 @@
 //! 1. Bind with the service account (ldap_bind_dn / ldap_bind_password)
 //! 2. Search for the user entry using the configured filter
 //! 3. Attempt a bind with the found DN and the user's password
-//! 4. If successful, return the username from the LDAP entry
+//! 4. If successful, return the username and group memberships from the LDAP entry
 
 use crate::config::AuthConfig;
 use crate::error::{AppError, AppResult};
 use ldap3::{LdapConnAsync, Scope, SearchEntry};
 
-/// Authenticate a user against LDAP. Returns the canonical username from the directory.
+/// A user as the directory describes them.
+#[derive(Debug, Clone)]
+pub struct LdapIdentity {
+    /// The canonical username from the directory.
+    pub username: String,
+    /// Values of `ldap_group_attr` (usually group DNs from `memberOf`).
+    pub groups: Vec<String>,
+}
+
+/// Authenticate a user against LDAP.
 pub async fn authenticate_ldap(
     auth_cfg: &AuthConfig,
     username: &str,
     password: &str,
-) -> AppResult<String> {
+) -> AppResult<LdapIdentity> {
     let ldap_url = auth_cfg
         .ldap_url
         .as_deref()
@@ -60,7 +69,11 @@ pub async fn authenticate_ldap(
             base_dn,
             Scope::Subtree,
             &filter,
-            vec![&auth_cfg.ldap_user_attr, "dn"],
+            vec![
+                auth_cfg.ldap_user_attr.as_str(),
+                auth_cfg.ldap_group_attr.as_str(),
+                "dn",
+            ],
         )
         .await
         .map_err(|e| AppError::InternalError(format!("LDAP search failed: {e}")))?
@@ -83,6 +96,13 @@ pub async fn authenticate_ldap(
         .and_then(|vals| vals.first())
         .cloned()
         .unwrap_or_else(|| username.to_string());
+    // A user in no groups has no values, so the attribute is simply absent.
+    let groups = entry
+        .attrs
+        .iter()
+        .find(|(attr, _)| attr.eq_ignore_ascii_case(&auth_cfg.ldap_group_attr))
+        .map(|(_, values)| values.clone())
+        .unwrap_or_default();
 
     // Unbind the service account.
     let _ = ldap.unbind().await;
@@ -107,5 +127,8 @@ pub async fn authenticate_ldap(
         ));
     }
 
-    Ok(canonical_username)
+    Ok(LdapIdentity {
+        username: canonical_username,
+        groups,
+    })
 }
@@ -30,6 +30,35 @@ pub struct RenderOptions<'a> {
     pub file_index: Option<&'a FileIndex>,
     /// Whether to enable syntax highlighting
     pub enable_highlighting: bool,
+    /// Confine wiki links and embeds to a shared subset of the vault
+    pub link_scope: Option<&'a LinkScope<'a>>,
+}
+
+/// The part of a vault exposed by a public share. Wiki links and embeds
+/// pointing outside it render as plain text instead of links.
+pub struct LinkScope<'a> {
+    /// The shared note, or the shared folder when `is_folder` is set
+    pub root: &'a str,
+    pub is_folder: bool,
+    /// Prepended to in-scope paths to build public URLs
+    pub url_prefix: &'a str,
+}
+
+impl LinkScope<'_> {
+    pub fn contains(&self, path: &str) -> bool {
+        let path = path.trim_start_matches('/');
+        if path.split('/').any(|segment| segment == "..") {
+            return false;
+        }
+        let root = self.root.trim_matches('/');
+        if !self.is_folder {
+            return path == root;
+        }
+        root.is_empty()
+            || path
+                .strip_prefix(root)
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
 }
 
 impl Default for RenderOptions<'_> {
@@ -39,6 +68,7 @@ impl Default for RenderOptions<'_> {
             current_file: None,
             file_index: None,
             enable_highlighting: true,
+            link_scope: None,
         }
     }
 }
@@ -207,8 +237,18 @@ impl MarkdownService {
                 };
 
                 // Resolve the wiki link to an actual file path if render options are provided
-                let (resolved_url, link_exists) =
-                    Self::resolve_wiki_link_url(link_url, render_opts);
+                let Some((resolved_url, link_exists)) =
+                    Self::resolve_wiki_link_url(link_url, render_opts)
+                else {
+                    // Outside the shared scope: keep the text, drop the target
+                    let html = format!(
+                        "<span class=\"wiki-link restricted-link\">{}</span>",
+                        Self::html_escape(&link_text)
+                    );
+                    html::push_html(html_output, vec![Event::Html(html.into())].into_iter());
+                    last_end = match_end;
+                    continue;
+                };
 
                 // Add CSS class based on whether link exists
                 let link_class = if link_exists {
@@ -254,8 +294,12 @@ impl MarkdownService {
         }
     }
 
-    /// Resolve a wiki link to a URL, returning (url, exists)
-    fn resolve_wiki_link_url(link: &str, render_opts: Option<&RenderOptions>) -> (String, bool) {
+    /// Resolve a wiki link to a URL, returning (url, exists), or `None` when
+    /// the target lies outside the link scope of `render_opts`
+    fn resolve_wiki_link_url(
+        link: &str,
+        render_opts: Option<&RenderOptions>,
+    ) -> Option<(String, bool)> {
         // Extract fragment if present
         let (base_link, fragment) = if let Some(hash_pos) = link.find('#') {
             (&link[..hash_pos], Some(&link[hash_pos..]))
@@ -295,7 +339,21 @@ impl MarkdownService {
                     resolved.path.clone()
                 };
 
-                return (Self::percent_encode_path(&url), resolved.exists);
+                if let Some(scope) = opts.link_scope {
+                    if !resolved.exists || !scope.contains(&resolved.path) {
+                        return None;
+                    }
+                    return Some((
+                        format!("{}{}", scope.url_prefix, Self::percent_encode_path(&url)),
+                        true,
+                    ));
+                }
+
+                return Some((Self::percent_encode_path(&url), resolved.exists));
+            }
+
+            if opts.link_scope.is_some() {
+                return None;
             }
         }
 
@@ -306,7 +364,7 @@ impl MarkdownService {
             base_link.to_string()
         };
 
-        (Self::percent_encode_path(&url), true) // Assume exists if we can't check
+        Some((Self::percent_encode_path(&url), true)) // Assume exists if we can't check
     }
 
     /// Percent-encode a path for use in URLs
@@ -478,6 +536,15 @@ impl MarkdownService {
         plain_text.trim().to_string()
     }
 
+    /// The `[[target]]` wiki links in markdown, in order, with whether each
+    /// is an `![[embed]]`. Aliases are dropped; fragments are kept.
+    pub fn wiki_links(markdown: &str) -> Vec<(String, bool)> {
+        WIKI_LINK_REGEX
+            .captures_iter(markdown)
+            .map(|cap| (cap[2].trim().to_string(), &cap[1] == "!"))
+            .collect()
+    }
+
     /// Get a preview/excerpt from markdown (first N characters of plain text)
     pub fn get_excerpt(markdown: &str, max_length: usize) -> String {
         let plain = Self::to_plain_text(markdown);
@@ -523,6 +590,7 @@ impl DocumentParser for MarkdownParser {
             current_file,
             file_index: None,
             enable_highlighting: true,
+            link_scope: None,
         };
         RenderedDocument {
             html: MarkdownService::to_html_with_link_resolution(source, &opts),
@@ -733,6 +801,20 @@ mod tests {
         assert!(html.contains("Alt text"));
     }
 
+    #[test]
+    fn test_wiki_links() {
+        let links =
+            MarkdownService::wiki_links("See [[Note|alias]], ![[pic.png]] and [[Other#Part]].");
+        assert_eq!(
+            links,
+            [
+                ("Note".to_string(), false),
+                ("pic.png".to_string(), true),
+                ("Other#Part".to_string(), false),
+            ]
+        );
+    }
+
     // ── DocumentParser trait tests ──────────────────────────────────────────
 
     #[test]
@@ -875,8 +957,44 @@ mod tests {
             current_file: None,
             file_index: None,
             enable_highlighting: true,
+            link_scope: None,
         };
         let direct = MarkdownService::to_html_with_link_resolution(src, &opts);
         assert_eq!(doc.html, direct);
     }
+
+    #[test]
+    fn link_scope_hides_targets_outside_the_share() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("shared")).unwrap();
+        std::fs::create_dir_all(dir.path().join("private")).unwrap();
+        std::fs::write(dir.path().join("shared/a.md"), "a").unwrap();
+        std::fs::write(dir.path().join("shared/pic.png"), "png").unwrap();
+        std::fs::write(dir.path().join("private/b.md"), "b").unwrap();
+
+        let scope = LinkScope {
+            root: "shared",
+            is_folder: true,
+            url_prefix: "/api/public/shares/tok/raw/",
+        };
+        let vault_path = dir.path().to_string_lossy().to_string();
+        let opts = RenderOptions {
+            vault_path: Some(&vault_path),
+            current_file: Some("shared/index.md"),
+            link_scope: Some(&scope),
+            ..Default::default()
+        };
+        let html = MarkdownService::to_html_with_link_resolution(
+            "See [[a]], [[b|secret]] and ![[pic.png]]",
+            &opts,
+        );
+
+        assert!(html.contains("href=\"/api/public/shares/tok/raw/shared/a.md\""));
+        assert!(html.contains("src=\"/api/public/shares/tok/raw/shared/pic.png\""));
+        assert!(html.contains("<span class=\"wiki-link restricted-link\">secret</span>"));
+        assert!(!html.contains("private/b.md"));
+
+        assert!(!scope.contains("shared/../private/b.md"));
+        assert!(!scope.contains("sharedother/c.md"));
+    }
 }
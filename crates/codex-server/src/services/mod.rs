@@ -1,37 +1,69 @@
+pub mod archive_service;
+pub mod audit_service;
 pub mod auth_provider;
+pub mod backup_service;
+pub mod cluster_service;
+pub mod computed_field_service;
 pub mod entity_service;
+pub mod entity_validation_service;
 pub mod file_service;
 pub mod frontmatter_service;
+pub mod git_service;
+pub mod graph_service;
+pub mod group_mapping_service;
 pub mod image_service;
 pub mod label_service;
 pub mod ldap_provider;
 pub mod markdown_service;
 pub mod ml_service;
 pub mod oidc_provider;
+pub mod path_acl_service;
 pub mod plugin_api;
 pub mod plugin_service;
 pub mod reindex_service;
 pub mod relation_service;
+pub mod schema_migration_service;
 pub mod schema_service;
+pub mod scim_service;
 pub mod search_service;
 pub mod template_service;
+pub mod timeline_service;
+pub mod trash_service;
+pub mod upload_service;
+pub mod webauthn_service;
 pub mod wiki_link_service;
 
+pub use archive_service::{ArchiveFormat, ArchiveService};
+pub use audit_service::{AuditExportFormat, AuditService};
 pub use auth_provider::{
     authenticate_username_password, validate_password_policy, AuthProviderKind,
     AuthenticatedPrincipal,
 };
-pub use entity_service::{Entity, EntityService};
+pub use backup_service::BackupService;
+pub use cluster_service::{ClusterService, Leadership};
+pub use computed_field_service::ComputedFieldService;
+pub use entity_service::{Entity, EntityService, FieldFilter, FilterOp};
+pub use entity_validation_service::EntityValidationService;
 pub use file_service::{FileService, RenameStrategy};
+pub use git_service::{GitAutoCommitter, GitService};
+pub use graph_service::{EntityGraph, GraphFilter};
+pub use group_mapping_service::{GroupMappingService, GroupSyncOutcome};
 pub use image_service::ImageService;
 pub use label_service::{Label, LabelService};
-pub use markdown_service::{MarkdownParser, MarkdownService, RenderOptions};
+pub use markdown_service::{LinkScope, MarkdownParser, MarkdownService, RenderOptions};
 pub use ml_service::MlService;
+pub use path_acl_service::{PathAcl, PathAclService};
 pub use plugin_api::{Command, Event, EventBus, EventType, PluginApi, PluginStorage};
 pub use plugin_service::{PluginService, resolve_plugins_dir};
 pub use reindex_service::ReindexService;
 pub use relation_service::{Relation, RelationService};
+pub use schema_migration_service::SchemaMigrationService;
 pub use schema_service::{EntityTypeRegistry, RelationTypeRegistry, SchemaService};
+pub use scim_service::{ActivationChange, ScimService};
 pub use search_service::SearchIndex;
 pub use template_service::TemplateService;
+pub use timeline_service::TimelineService;
+pub use trash_service::TrashService;
+pub use upload_service::UploadService;
+pub use webauthn_service::WebAuthnService;
 pub use wiki_link_service::{FileIndex, ResolvedLink, WikiLinkResolver};
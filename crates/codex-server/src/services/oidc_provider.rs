@@ -1,23 +1,62 @@
 //! OAuth2 / OIDC authentication provider.
 //!
-//! Implements the Authorization Code flow:
-//! 1. Client redirects user to `GET /api/auth/oidc/authorize`
-//! 2. Server redirects to the OIDC provider's authorization endpoint
-//! 3. Provider redirects back to `GET /api/auth/oidc/callback?code=...&state=...`
-//! 4. Server exchanges code for tokens, extracts user info, creates/finds local user
+//! Implements the Authorization Code flow with PKCE:
+//! 1. Client calls `GET /api/auth/oidc/authorize?provider=...`; the server
+//!    records a state, nonce and PKCE verifier and returns the provider's
+//!    authorization URL
+//! 2. Provider redirects back to `GET /api/auth/oidc/callback?code=...&state=...`
+//! 3. Server exchanges the code (with the verifier), validates the ID token
+//!    against the provider's JWKS, merges userinfo, creates/finds local user
+//!
+//! Signing keys are cached per JWKS URL and re-fetched when they age out or
+//! a token names a key we have not seen, which is how rotation is picked up.
 
-use crate::config::AuthConfig;
+use crate::config::OidcProviderConfig;
 use crate::error::{AppError, AppResult};
+use crate::models::oidc::OidcAuthRequest;
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::jwk::{Jwk, JwkSet, PublicKeyUse};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Signature algorithms accepted on ID tokens. Symmetric algorithms are
+/// refused so a leaked client secret cannot mint tokens.
+const ID_TOKEN_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+static JWKS_CACHE: LazyLock<Mutex<HashMap<String, CachedJwks>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 /// Minimal OIDC discovery document (only the fields we need).
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct OidcDiscovery {
+    pub issuer: String,
     pub authorization_endpoint: String,
     pub token_endpoint: String,
-    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
     #[serde(default)]
-    pub issuer: String,
+    pub userinfo_endpoint: Option<String>,
+    #[serde(default)]
+    pub end_session_endpoint: Option<String>,
 }
 
 /// Token response from the OIDC provider.
@@ -42,6 +81,17 @@ pub struct OidcUserInfo {
     pub email: Option<String>,
     #[serde(default)]
     pub name: Option<String>,
+    /// Every other claim, e.g. the one carrying group membership.
+    #[serde(flatten)]
+    pub claims: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A completed sign-in: who the user is, and the validated ID token kept
+/// for RP-initiated logout.
+#[derive(Debug)]
+pub struct OidcSignIn {
+    pub user: OidcUserInfo,
+    pub id_token: String,
 }
 
 /// OIDC authorize URL response sent to the client.
@@ -49,6 +99,32 @@ pub struct OidcUserInfo {
 pub struct OidcAuthorizeResponse {
     pub authorize_url: String,
     pub state: String,
+    pub provider: String,
+}
+
+/// A configured provider, as listed for the sign-in page.
+#[derive(Debug, Serialize)]
+pub struct OidcProviderInfo {
+    pub name: String,
+    pub display_name: String,
+}
+
+/// Where to send the browser to end the provider session as well, if the
+/// provider supports RP-initiated logout.
+#[derive(Debug, Serialize)]
+pub struct OidcLogoutResponse {
+    pub logout_url: Option<String>,
+}
+
+/// A random URL-safe value for `state`, `nonce` and the PKCE verifier.
+pub fn random_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    BASE64URL_NOPAD.encode(&bytes)
+}
+
+/// The PKCE `S256` code challenge for `verifier` (RFC 7636 §4.2).
+pub fn pkce_challenge(verifier: &str) -> String {
+    BASE64URL_NOPAD.encode(&Sha256::digest(verifier.as_bytes()))
 }
 
 /// Fetch the OIDC discovery document from `{issuer}/.well-known/openid-configuration`.
@@ -71,65 +147,68 @@ pub async fn fetch_discovery(issuer_url: &str) -> AppResult<OidcDiscovery> {
         )));
     }
 
-    resp.json::<OidcDiscovery>()
+    let discovery = resp
+        .json::<OidcDiscovery>()
         .await
-        .map_err(|e| AppError::InternalError(format!("Failed to parse OIDC discovery: {e}")))
+        .map_err(|e| AppError::InternalError(format!("Failed to parse OIDC discovery: {e}")))?;
+
+    // OIDC Discovery §4.3: the document must describe the issuer we asked.
+    if discovery.issuer.trim_end_matches('/') != issuer_url.trim_end_matches('/') {
+        return Err(AppError::InternalError(format!(
+            "OIDC discovery issuer '{}' does not match configured issuer '{issuer_url}'",
+            discovery.issuer
+        )));
+    }
+    Ok(discovery)
 }
 
 /// Build the authorization URL that the user should be redirected to.
 pub fn build_authorize_url(
     discovery: &OidcDiscovery,
-    auth_cfg: &AuthConfig,
-    state_token: &str,
+    provider: &OidcProviderConfig,
+    request: &OidcAuthRequest,
 ) -> AppResult<String> {
-    let client_id = auth_cfg
-        .oidc_client_id
-        .as_deref()
-        .ok_or_else(|| AppError::InternalError("oidc_client_id not configured".to_string()))?;
-    let redirect_uri = auth_cfg
-        .oidc_redirect_uri
-        .as_deref()
-        .ok_or_else(|| AppError::InternalError("oidc_redirect_uri not configured".to_string()))?;
-
-    let url = format!(
-        "{}?response_type=code&client_id={}&redirect_uri={}&scope=openid%20profile%20email&state={}",
-        discovery.authorization_endpoint,
-        urlencoding::encode(client_id),
-        urlencoding::encode(redirect_uri),
-        urlencoding::encode(state_token),
-    );
-    Ok(url)
+    let scope = provider.scopes.join(" ");
+    let challenge = pkce_challenge(&request.code_verifier);
+    let url = reqwest::Url::parse_with_params(
+        &discovery.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", provider.redirect_uri.as_str()),
+            ("scope", scope.as_str()),
+            ("state", request.state.as_str()),
+            ("nonce", request.nonce.as_str()),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|e| AppError::InternalError(format!("Invalid OIDC authorization endpoint: {e}")))?;
+    Ok(url.into())
 }
 
 /// Exchange an authorization code for tokens.
 pub async fn exchange_code(
     discovery: &OidcDiscovery,
-    auth_cfg: &AuthConfig,
+    provider: &OidcProviderConfig,
     code: &str,
+    code_verifier: &str,
 ) -> AppResult<OidcTokenResponse> {
-    let client_id = auth_cfg
-        .oidc_client_id
-        .as_deref()
-        .ok_or_else(|| AppError::InternalError("oidc_client_id not configured".to_string()))?;
-    let client_secret = auth_cfg
-        .oidc_client_secret
-        .as_deref()
-        .ok_or_else(|| AppError::InternalError("oidc_client_secret not configured".to_string()))?;
-    let redirect_uri = auth_cfg
-        .oidc_redirect_uri
-        .as_deref()
-        .ok_or_else(|| AppError::InternalError("oidc_redirect_uri not configured".to_string()))?;
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", provider.redirect_uri.as_str()),
+        ("client_id", provider.client_id.as_str()),
+        ("code_verifier", code_verifier),
+    ];
+    if let Some(secret) = provider.client_secret.as_deref() {
+        form.push(("client_secret", secret));
+    }
 
     let client = reqwest::Client::new();
     let resp = client
         .post(&discovery.token_endpoint)
-        .form(&[
-            ("grant_type", "authorization_code"),
-            ("code", code),
-            ("redirect_uri", redirect_uri),
-            ("client_id", client_id),
-            ("client_secret", client_secret),
-        ])
+        .form(&form)
         .send()
         .await
         .map_err(|e| AppError::InternalError(format!("OIDC token exchange failed: {e}")))?;
@@ -146,14 +225,14 @@ pub async fn exchange_code(
         .map_err(|e| AppError::InternalError(format!("Failed to parse OIDC token response: {e}")))
 }
 
-/// Fetch user info using the access token.
+/// Fetch user info claims using the access token.
 pub async fn fetch_userinfo(
-    discovery: &OidcDiscovery,
+    userinfo_endpoint: &str,
     access_token: &str,
-) -> AppResult<OidcUserInfo> {
+) -> AppResult<serde_json::Map<String, serde_json::Value>> {
     let client = reqwest::Client::new();
     let resp = client
-        .get(&discovery.userinfo_endpoint)
+        .get(userinfo_endpoint)
         .bearer_auth(access_token)
         .send()
         .await
@@ -165,11 +244,182 @@ pub async fn fetch_userinfo(
         ));
     }
 
-    resp.json::<OidcUserInfo>()
+    resp.json()
         .await
         .map_err(|e| AppError::InternalError(format!("Failed to parse OIDC userinfo: {e}")))
 }
 
+async fn fetch_jwks(jwks_uri: &str) -> AppResult<JwkSet> {
+    let resp = reqwest::Client::new()
+        .get(jwks_uri)
+        .send()
+        .await
+        .map_err(|e| AppError::InternalError(format!("OIDC JWKS request failed: {e}")))?;
+    if !resp.status().is_success() {
+        return Err(AppError::InternalError(format!(
+            "OIDC JWKS returned status {}",
+            resp.status()
+        )));
+    }
+    resp.json::<JwkSet>()
+        .await
+        .map_err(|e| AppError::InternalError(format!("Failed to parse OIDC JWKS: {e}")))
+}
+
+/// The signing key named by `kid`, or the only signing key when the token
+/// names none.
+fn find_signing_key(keys: &JwkSet, kid: Option<&str>) -> Option<Jwk> {
+    if let Some(kid) = kid {
+        return keys.find(kid).cloned();
+    }
+    let mut signing = keys
+        .keys
+        .iter()
+        .filter(|jwk| !matches!(jwk.common.public_key_use, Some(PublicKeyUse::Encryption)));
+    match (signing.next(), signing.next()) {
+        (Some(jwk), None) => Some(jwk.clone()),
+        _ => None,
+    }
+}
+
+async fn signing_key(jwks_uri: &str, kid: Option<&str>, max_age: Duration) -> AppResult<Jwk> {
+    {
+        let cache = JWKS_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = cache.get(jwks_uri) {
+            if cached.fetched_at.elapsed() < max_age {
+                if let Some(jwk) = find_signing_key(&cached.keys, kid) {
+                    return Ok(jwk);
+                }
+            }
+        }
+    }
+
+    // Stale, or the provider has rotated to a key we have not seen. ID
+    // tokens only arrive from the token endpoint, so others cannot force
+    // these re-fetches with made-up key IDs.
+    let keys = fetch_jwks(jwks_uri).await?;
+    let jwk = find_signing_key(&keys, kid);
+    JWKS_CACHE.lock().unwrap_or_else(|e| e.into_inner()).insert(
+        jwks_uri.to_string(),
+        CachedJwks {
+            keys,
+            fetched_at: Instant::now(),
+        },
+    );
+    jwk.ok_or_else(|| AppError::Unauthorized("ID token is signed with an unknown key".to_string()))
+}
+
+/// Check an ID token's signature against the provider's JWKS and its
+/// claims against this sign-in (OIDC Core §3.1.3.7); returns the claims.
+pub async fn validate_id_token(
+    id_token: &str,
+    discovery: &OidcDiscovery,
+    provider: &OidcProviderConfig,
+    nonce: &str,
+    clock_skew_secs: u64,
+    jwks_max_age: Duration,
+) -> AppResult<serde_json::Map<String, serde_json::Value>> {
+    let invalid = |reason: &str| AppError::Unauthorized(format!("Invalid ID token: {reason}"));
+
+    let header = decode_header(id_token).map_err(|_| invalid("malformed"))?;
+    if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+        return Err(invalid("unsupported signature algorithm"));
+    }
+    let jwk = signing_key(&discovery.jwks_uri, header.kid.as_deref(), jwks_max_age).await?;
+    let key = DecodingKey::from_jwk(&jwk).map_err(|_| invalid("unusable signing key"))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.leeway = clock_skew_secs;
+    validation.validate_nbf = true;
+    validation.set_issuer(&[&discovery.issuer]);
+    validation.set_audience(&[&provider.client_id]);
+    validation.set_required_spec_claims(&["exp", "iat", "iss", "aud", "sub"]);
+    let claims = decode::<serde_json::Map<String, serde_json::Value>>(id_token, &key, &validation)
+        .map_err(|e| invalid(&e.to_string()))?
+        .claims;
+
+    let now = chrono::Utc::now().timestamp();
+    let issued_at = claims.get("iat").and_then(|v| v.as_i64()).unwrap_or(0);
+    if issued_at > now + clock_skew_secs as i64 {
+        return Err(invalid("issued in the future"));
+    }
+    if claims.get("nonce").and_then(|v| v.as_str()) != Some(nonce) {
+        return Err(invalid("nonce mismatch"));
+    }
+    // With several audiences the token must name us as the authorized party.
+    let audiences = claims
+        .get("aud")
+        .and_then(|v| v.as_array())
+        .map_or(1, |a| a.len());
+    match claims.get("azp").and_then(|v| v.as_str()) {
+        Some(azp) if azp != provider.client_id => return Err(invalid("azp mismatch")),
+        None if audiences > 1 => return Err(invalid("azp missing")),
+        _ => {}
+    }
+    Ok(claims)
+}
+
+/// Finish a sign-in started by `request`: exchange `code`, validate the ID
+/// token and merge in userinfo claims.
+pub async fn complete_sign_in(
+    provider: &OidcProviderConfig,
+    request: &OidcAuthRequest,
+    code: &str,
+    clock_skew_secs: u64,
+    jwks_max_age: Duration,
+) -> AppResult<OidcSignIn> {
+    let discovery = fetch_discovery(&provider.issuer_url).await?;
+    let tokens = exchange_code(&discovery, provider, code, &request.code_verifier).await?;
+    let id_token = tokens.id_token.ok_or_else(|| {
+        AppError::Unauthorized("OIDC provider did not return an ID token".to_string())
+    })?;
+    let mut claims = validate_id_token(
+        &id_token,
+        &discovery,
+        provider,
+        &request.nonce,
+        clock_skew_secs,
+        jwks_max_age,
+    )
+    .await?;
+
+    if let Some(endpoint) = discovery.userinfo_endpoint.as_deref() {
+        let userinfo = fetch_userinfo(endpoint, &tokens.access_token).await?;
+        // OIDC Core §5.3.2: userinfo for another subject must be ignored.
+        if userinfo.get("sub") != claims.get("sub") {
+            return Err(AppError::Unauthorized(
+                "OIDC userinfo subject does not match the ID token".to_string(),
+            ));
+        }
+        claims.extend(userinfo);
+    }
+
+    let user = serde_json::from_value(serde_json::Value::Object(claims))
+        .map_err(|e| AppError::Unauthorized(format!("Invalid OIDC claims: {e}")))?;
+    Ok(OidcSignIn { user, id_token })
+}
+
+/// The provider's end-session URL for RP-initiated logout, if it has one.
+pub fn end_session_url(
+    discovery: &OidcDiscovery,
+    provider: &OidcProviderConfig,
+    id_token: &str,
+) -> AppResult<Option<String>> {
+    let Some(endpoint) = discovery.end_session_endpoint.as_deref() else {
+        return Ok(None);
+    };
+    let mut params = vec![
+        ("id_token_hint", id_token),
+        ("client_id", provider.client_id.as_str()),
+    ];
+    if let Some(redirect) = provider.post_logout_redirect_uri.as_deref() {
+        params.push(("post_logout_redirect_uri", redirect));
+    }
+    let url = reqwest::Url::parse_with_params(endpoint, &params)
+        .map_err(|e| AppError::InternalError(format!("Invalid OIDC end-session endpoint: {e}")))?;
+    Ok(Some(url.into()))
+}
+
 /// Derive a local username from the OIDC user info.
 pub fn derive_username(info: &OidcUserInfo) -> String {
     info.preferred_username
@@ -177,3 +427,106 @@ pub fn derive_username(info: &OidcUserInfo) -> String {
         .or_else(|| info.email.clone())
         .unwrap_or_else(|| format!("oidc_{}", &info.sub[..8.min(info.sub.len())]))
 }
+
+/// The groups listed in `claim`, or `None` if the provider did not send it.
+/// A claim name that is not a top-level key is tried as a dotted path
+/// (e.g. Keycloak's `realm_access.roles`).
+pub fn user_groups(info: &OidcUserInfo, claim: &str) -> Option<Vec<String>> {
+    let value = info.claims.get(claim).or_else(|| {
+        let (first, rest) = claim.split_once('.')?;
+        rest.split('.')
+            .try_fold(info.claims.get(first)?, |value, key| value.get(key))
+    })?;
+    match value {
+        serde_json::Value::Array(items) => Some(
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect(),
+        ),
+        serde_json::Value::String(group) => Some(vec![group.clone()]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkce_challenge_matches_rfc_7636_example() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn authorize_url_carries_state_nonce_and_challenge() {
+        let discovery = OidcDiscovery {
+            issuer: "https://idp.example.com".to_string(),
+            authorization_endpoint: "https://idp.example.com/authorize?tenant=a".to_string(),
+            token_endpoint: "https://idp.example.com/token".to_string(),
+            jwks_uri: "https://idp.example.com/jwks".to_string(),
+            userinfo_endpoint: None,
+            end_session_endpoint: None,
+        };
+        let provider = OidcProviderConfig {
+            display_name: None,
+            issuer_url: "https://idp.example.com".to_string(),
+            client_id: "codex".to_string(),
+            client_secret: None,
+            redirect_uri: "https://notes.example.com/api/auth/oidc/callback".to_string(),
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            groups_claim: None,
+            post_logout_redirect_uri: None,
+        };
+        let request = OidcAuthRequest {
+            state: "s1".to_string(),
+            provider: "default".to_string(),
+            nonce: "n1".to_string(),
+            code_verifier: "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string(),
+            expires_at: chrono::Utc::now(),
+        };
+        let url =
+            reqwest::Url::parse(&build_authorize_url(&discovery, &provider, &request).unwrap())
+                .unwrap();
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["tenant"], "a");
+        assert_eq!(params["state"], "s1");
+        assert_eq!(params["nonce"], "n1");
+        assert_eq!(params["scope"], "openid email");
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(
+            params["code_challenge"],
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    fn userinfo(json: serde_json::Value) -> OidcUserInfo {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn reads_groups_from_top_level_or_nested_claims() {
+        let info = userinfo(serde_json::json!({
+            "sub": "123",
+            "groups": ["engineering", "codex-admins"],
+            "https://example.com/roles": "writer",
+            "realm_access": { "roles": ["editor"] },
+        }));
+        assert_eq!(
+            user_groups(&info, "groups"),
+            Some(vec!["engineering".to_string(), "codex-admins".to_string()])
+        );
+        assert_eq!(
+            user_groups(&info, "https://example.com/roles"),
+            Some(vec!["writer".to_string()])
+        );
+        assert_eq!(
+            user_groups(&info, "realm_access.roles"),
+            Some(vec!["editor".to_string()])
+        );
+        assert_eq!(user_groups(&info, "missing"), None);
+    }
+}
@@ -0,0 +1,358 @@
+use crate::config::AppConfig;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::middleware::AuthenticatedUser;
+use crate::models::{AclPrincipalType, FileNode, PathAccess, PathAclEntry, VaultRole};
+use actix_web::{web, HttpMessage, HttpRequest};
+
+const GLOB_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// `path` with empty and `.` segments dropped and `..` resolved, so rules
+/// see the path the file service will actually touch. Paths escaping the
+/// vault are returned as they are; the file service rejects them.
+pub fn normalize_path(path: &str) -> String {
+    let mut resolved: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if resolved.pop().is_none() {
+                    return path.to_string();
+                }
+            }
+            part => resolved.push(part),
+        }
+    }
+    resolved.join("/")
+}
+
+/// Whether the vault-relative `path` matches `pattern`. A trailing `/**`
+/// also covers the folder itself, so `hr/**` applies to `hr`.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    let path = normalize_path(path);
+    if let Some(base) = pattern.strip_suffix("/**") {
+        if path == base {
+            return true;
+        }
+    }
+    glob::Pattern::new(pattern)
+        .map(|compiled| compiled.matches_with(&path, GLOB_OPTIONS))
+        .unwrap_or(false)
+}
+
+struct CompiledRule {
+    pattern: String,
+    /// Literal leading part of the pattern, used to find rules below a folder.
+    prefix: String,
+    /// User rules beat group rules, which beat `everyone` rules.
+    tier: u8,
+    access: PathAccess,
+}
+
+/// The path ACL rules that apply to one caller in one vault, combined with
+/// the caller's vault role.
+pub struct PathAcl {
+    base: PathAccess,
+    rules: Vec<CompiledRule>,
+}
+
+impl PathAcl {
+    pub fn unrestricted() -> Self {
+        Self {
+            base: PathAccess::Write,
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        self.base == PathAccess::Write && self.rules.is_empty()
+    }
+
+    /// Effective access to `path`: the most specific matching rule for the
+    /// closest principal, capped by the vault role.
+    pub fn access(&self, path: &str) -> PathAccess {
+        self.rules
+            .iter()
+            .filter(|rule| glob_matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.tier, rule.pattern.len(), rule.access))
+            .map(|rule| rule.access.min(self.base))
+            .unwrap_or(self.base)
+    }
+
+    pub fn can_read(&self, path: &str) -> bool {
+        self.access(path) >= PathAccess::Read
+    }
+
+    /// Whether `path` and everything that may live below it is writable,
+    /// as required to delete or move a folder.
+    pub fn can_write_tree(&self, path: &str) -> bool {
+        if self.access(path) < PathAccess::Write {
+            return false;
+        }
+        let folder = format!("{}/", normalize_path(path));
+        !self
+            .rules
+            .iter()
+            .any(|rule| rule.access < PathAccess::Write && rule.prefix.starts_with(&folder))
+    }
+
+    pub fn require(&self, path: &str, required: PathAccess) -> AppResult<()> {
+        let allowed = match required {
+            PathAccess::Write => self.can_write_tree(path),
+            _ => self.access(path) >= required,
+        };
+        if allowed {
+            Ok(())
+        } else if self.can_read(path) {
+            Err(AppError::Forbidden(format!(
+                "You do not have write access to '{}'",
+                path
+            )))
+        } else {
+            Err(AppError::Forbidden(format!(
+                "You do not have access to '{}'",
+                path
+            )))
+        }
+    }
+
+    /// Drop nodes the caller cannot read. Folders stay when they are
+    /// readable or still contain something readable.
+    pub fn filter_tree(&self, nodes: Vec<FileNode>) -> Vec<FileNode> {
+        if self.is_unrestricted() {
+            return nodes;
+        }
+        nodes
+            .into_iter()
+            .filter_map(|mut node| {
+                if let Some(children) = node.children.take() {
+                    let children = self.filter_tree(children);
+                    if children.is_empty() && !self.can_read(&node.path) {
+                        return None;
+                    }
+                    node.children = Some(children);
+                    Some(node)
+                } else {
+                    self.can_read(&node.path).then_some(node)
+                }
+            })
+            .collect()
+    }
+}
+
+pub struct PathAclService;
+
+impl PathAclService {
+    /// Rules for `user_id` in `vault_id`. Anonymous callers (public vaults)
+    /// get read access narrowed by `everyone` rules.
+    pub async fn for_user(
+        db: &Database,
+        vault_id: &str,
+        user_id: Option<&str>,
+    ) -> AppResult<PathAcl> {
+        let entries = db.list_path_acls(vault_id).await?;
+        if entries.is_empty() {
+            // The vault role itself is enforced by the auth middleware.
+            return Ok(PathAcl::unrestricted());
+        }
+
+        let (base, group_ids) = match user_id {
+            Some(user_id) => {
+                let base = match db.get_vault_role_for_user(vault_id, user_id).await? {
+                    // Owners are never narrowed by path rules.
+                    Some(VaultRole::Owner) => return Ok(PathAcl::unrestricted()),
+                    Some(VaultRole::Editor) => PathAccess::Write,
+                    Some(VaultRole::Viewer) => PathAccess::Read,
+                    None => PathAccess::None,
+                };
+                (base, db.list_group_ids_for_member(user_id).await?)
+            }
+            None => (PathAccess::Read, Vec::new()),
+        };
+
+        let rules = entries
+            .into_iter()
+            .filter_map(|entry| {
+                let principal = entry.principal_id.as_deref();
+                let tier = match entry.principal_type {
+                    AclPrincipalType::User if principal.is_some() && principal == user_id => 2,
+                    AclPrincipalType::Group
+                        if principal.is_some_and(|id| group_ids.iter().any(|g| g == id)) =>
+                    {
+                        1
+                    }
+                    AclPrincipalType::Everyone => 0,
+                    _ => return None,
+                };
+                let prefix = entry
+                    .pattern
+                    .split(['*', '?', '['])
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                Some(CompiledRule {
+                    pattern: entry.pattern,
+                    prefix,
+                    tier,
+                    access: entry.access,
+                })
+            })
+            .collect();
+
+        Ok(PathAcl { base, rules })
+    }
+
+    /// Rules for the caller of `req`. Everything is allowed when auth is
+    /// disabled.
+    pub async fn for_request(
+        db: &Database,
+        vault_id: &str,
+        req: &HttpRequest,
+    ) -> AppResult<PathAcl> {
+        let auth_enabled = req
+            .app_data::<web::Data<AppConfig>>()
+            .is_some_and(|config| config.auth.enabled);
+        if !auth_enabled {
+            return Ok(PathAcl::unrestricted());
+        }
+        let user_id = req
+            .extensions()
+            .get::<AuthenticatedUser>()
+            .map(|user| user.user_id.clone());
+        Self::for_user(db, vault_id, user_id.as_deref()).await
+    }
+
+    /// Normalise and validate a new rule before it is stored.
+    pub fn validate(entry: &mut PathAclEntry) -> AppResult<()> {
+        let pattern = entry.pattern.trim().trim_matches('/').to_string();
+        if pattern.is_empty() || glob::Pattern::new(&pattern).is_err() {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid path pattern '{}'",
+                entry.pattern
+            )));
+        }
+        if pattern.split('/').any(|segment| segment == "..") {
+            return Err(AppError::InvalidInput(
+                "Path patterns cannot contain '..'".to_string(),
+            ));
+        }
+        entry.pattern = pattern;
+
+        match entry.principal_type {
+            AclPrincipalType::Everyone => entry.principal_id = None,
+            _ if entry
+                .principal_id
+                .as_deref()
+                .is_none_or(|id| id.trim().is_empty()) =>
+            {
+                return Err(AppError::InvalidInput(
+                    "principal_id is required for user and group rules".to_string(),
+                ));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, tier: u8, access: PathAccess) -> CompiledRule {
+        CompiledRule {
+            pattern: pattern.to_string(),
+            prefix: pattern.split(['*', '?', '[']).next().unwrap().to_string(),
+            tier,
+            access,
+        }
+    }
+
+    #[test]
+    fn closest_principal_then_most_specific_pattern_wins() {
+        let acl = PathAcl {
+            base: PathAccess::Write,
+            rules: vec![
+                rule("hr/**", 0, PathAccess::None),
+                rule("hr/**", 1, PathAccess::Write),
+                rule("drafts/**", 1, PathAccess::Read),
+                rule("drafts/mine/**", 1, PathAccess::Write),
+            ],
+        };
+        assert_eq!(acl.access("hr/salaries.md"), PathAccess::Write);
+        assert_eq!(acl.access("hr"), PathAccess::Write);
+        assert_eq!(acl.access("drafts/a.md"), PathAccess::Read);
+        assert_eq!(acl.access("drafts/mine/a.md"), PathAccess::Write);
+        assert_eq!(acl.access("notes/a.md"), PathAccess::Write);
+        assert!(!acl.can_write_tree("drafts"));
+        assert!(acl.can_write_tree("notes"));
+    }
+
+    #[test]
+    fn paths_are_normalized_before_matching() {
+        let acl = PathAcl {
+            base: PathAccess::Write,
+            rules: vec![rule("hr/**", 0, PathAccess::None)],
+        };
+        for path in [
+            "./hr/a.md",
+            "hr//a.md",
+            "/hr/a.md",
+            "notes/../hr/a.md",
+            "hr/./a.md",
+        ] {
+            assert_eq!(acl.access(path), PathAccess::None, "{path}");
+        }
+        assert!(!acl.can_write_tree("./hr"));
+        assert_eq!(normalize_path("../hr/a.md"), "../hr/a.md");
+    }
+
+    #[test]
+    fn rules_never_exceed_the_vault_role() {
+        let acl = PathAcl {
+            base: PathAccess::Read,
+            rules: vec![rule("shared/**", 2, PathAccess::Write)],
+        };
+        assert_eq!(acl.access("shared/a.md"), PathAccess::Read);
+    }
+
+    #[test]
+    fn filter_tree_keeps_folders_with_visible_children() {
+        let file = |path: &str| FileNode {
+            name: path.rsplit('/').next().unwrap().to_string(),
+            path: path.to_string(),
+            is_directory: false,
+            children: None,
+            size: None,
+            modified: None,
+        };
+        let folder = |path: &str, children: Vec<FileNode>| FileNode {
+            name: path.to_string(),
+            path: path.to_string(),
+            is_directory: true,
+            children: Some(children),
+            size: None,
+            modified: None,
+        };
+        let acl = PathAcl {
+            base: PathAccess::Write,
+            rules: vec![
+                rule("hr/**", 0, PathAccess::None),
+                rule("hr/handbook.md", 0, PathAccess::Read),
+                rule("secret/**", 0, PathAccess::None),
+            ],
+        };
+        let tree = acl.filter_tree(vec![
+            folder("hr", vec![file("hr/handbook.md"), file("hr/salaries.md")]),
+            folder("secret", vec![file("secret/x.md")]),
+            file("readme.md"),
+        ]);
+        let paths: Vec<&str> = tree.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(paths, vec!["hr", "readme.md"]);
+        assert_eq!(tree[0].children.as_ref().unwrap().len(), 1);
+    }
+}
@@ -1,8 +1,10 @@
 use crate::db::Database;
 use crate::error::AppResult;
-use crate::services::entity_service::EntityService;
+use crate::services::entity_service::{Entity, EntityService};
 use crate::services::relation_service::RelationService;
+use crate::services::schema_service::{EntityTypeRegistry, RelationTypeRegistry, SchemaService};
 use chrono::Utc;
+use std::collections::HashSet;
 use std::path::Path;
 use tokio::fs;
 use tracing::{debug, error, info, warn};
@@ -113,7 +115,7 @@ impl ReindexService {
         let _ = sqlx::query(
             r#"
             INSERT INTO reindex_log (vault_id, started_at, finished_at, files_indexed, errors)
-            VALUES (?, ?, ?, ?, ?)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
         )
         .bind(vault_id)
@@ -134,6 +136,83 @@ impl ReindexService {
         vault_id: &str,
         rel_path: &str,
         abs_path: &str,
+    ) -> AppResult<()> {
+        Self::index_file_with(db, vault_id, rel_path, abs_path, None, None).await
+    }
+
+    /// Reload a vault's `.codex/types/` definitions and re-index only the
+    /// entities they affect: those of a changed entity type and those with
+    /// relations of a changed relation type. Returns how many were re-indexed.
+    /// Called when a type file changes on disk or through the API.
+    pub async fn reload_vault_types(
+        db: &Database,
+        vault_id: &str,
+        vault_path: &str,
+        entity_registry: &EntityTypeRegistry,
+        relation_registry: &RelationTypeRegistry,
+    ) -> AppResult<usize> {
+        let changes = SchemaService::load_vault_schemas(
+            db,
+            vault_id,
+            vault_path,
+            entity_registry,
+            relation_registry,
+        )
+        .await;
+        if changes.is_empty() {
+            return Ok(0);
+        }
+
+        let mut affected: HashSet<String> = HashSet::new();
+        if !changes.relation_types.is_empty() {
+            for relation in RelationService::list_for_vault(db, vault_id).await? {
+                if relation.direction == "forward"
+                    && changes.relation_types.contains(&relation.relation_type)
+                {
+                    affected.insert(relation.from_entity_id);
+                }
+            }
+        }
+        let entities: Vec<Entity> = EntityService::list_all_in_vault(db, vault_id)
+            .await?
+            .into_iter()
+            .filter(|e| affected.contains(&e.id) || changes.entity_types.contains(&e.entity_type))
+            .collect();
+
+        let entity_view = entity_registry.for_vault(vault_id);
+        let relation_view = relation_registry.for_vault(vault_id);
+        for entity in &entities {
+            let abs_path = format!("{}/{}", vault_path.trim_end_matches('/'), entity.path);
+            if let Err(e) = Self::index_file_with(
+                db,
+                vault_id,
+                &entity.path,
+                &abs_path,
+                Some(&entity_view),
+                Some(&relation_view),
+            )
+            .await
+            {
+                warn!(
+                    "Failed to re-index {} after a type change: {e}",
+                    entity.path
+                );
+            }
+        }
+        info!(
+            "Re-indexed {} entities in vault {vault_id} after a type change",
+            entities.len()
+        );
+        Ok(entities.len())
+    }
+
+    async fn index_file_with(
+        db: &Database,
+        vault_id: &str,
+        rel_path: &str,
+        abs_path: &str,
+        entity_registry: Option<&EntityTypeRegistry>,
+        relation_registry: Option<&RelationTypeRegistry>,
     ) -> AppResult<()> {
         let content = match fs::read_to_string(abs_path).await {
             Ok(c) => c,
@@ -155,10 +234,17 @@ impl ReindexService {
                     })
                     .unwrap_or_else(|| Utc::now().to_rfc3339());
 
-                if let Ok(Some(entity)) =
-                    EntityService::upsert(db, vault_id, rel_path, &fm, &modified_at, None).await
+                if let Ok(Some(entity)) = EntityService::upsert(
+                    db,
+                    vault_id,
+                    rel_path,
+                    &fm,
+                    &modified_at,
+                    entity_registry,
+                )
+                .await
                 {
-                    RelationService::sync_from_entity(db, &entity, None).await?;
+                    RelationService::sync_from_entity(db, &entity, relation_registry).await?;
                 }
                 return Ok(());
             }
@@ -311,7 +397,7 @@ mod tests {
         let db = Database::new(&db_url).await.expect("db should initialise");
         // Seed vault row required by FK constraint on entities.vault_id
         sqlx::query(
-            "INSERT OR IGNORE INTO vaults (id, name, path, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
+            "INSERT OR IGNORE INTO vaults (id, name, path, created_at, updated_at) VALUES ($1, $2, $3, $4, $5)",
         )
         .bind("v1")
         .bind("Test Vault")
@@ -4,6 +4,7 @@ use crate::services::entity_service::{Entity, EntityService};
 use crate::services::schema_service::RelationTypeRegistry;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tracing::{debug, warn};
 use uuid::Uuid;
 
@@ -21,10 +22,37 @@ pub struct Relation {
     pub created_at: String,
 }
 
+/// Frontmatter key holding a note's explicit relations, so that they are
+/// rebuilt with the note on every reindex.
+pub const EXPLICIT_RELATIONS_KEY: &str = "codex_relations";
+
+/// One entry of a note's `codex_relations` frontmatter list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExplicitRelation {
+    #[serde(rename = "type")]
+    pub relation_type: String,
+    /// `[[Title]]` link to the target entity.
+    pub target: String,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ExplicitRelation {
+    /// Whether this entry points at the entity stored at `path`.
+    pub fn targets(&self, path: &str) -> bool {
+        let stem = std::path::Path::new(path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        extract_wiki_title(&self.target).is_some_and(|title| title.eq_ignore_ascii_case(stem))
+    }
+}
+
 pub struct RelationService;
 
 impl RelationService {
-    /// Derive relation edges from an entity's `entity_ref` fields.
+    /// Derive relation edges from an entity's `entity_ref` fields and its
+    /// `codex_relations` list.
     ///
     /// When `registry` is provided, the inverse label is looked up from the
     /// registered relation type schema. Otherwise falls back to the Phase 1
@@ -34,13 +62,15 @@ impl RelationService {
         entity: &Entity,
         registry: Option<&RelationTypeRegistry>,
     ) -> AppResult<()> {
-        // Remove all existing field-derived relations for this entity so we
-        // start fresh (avoids stale edges after a field value changes).
-        // This includes both outgoing forward edges AND the mirror inverse edges
-        // that point back to this entity from its targets.
+        // Remove all existing relations derived from this entity so we start
+        // fresh (avoids stale edges after a field value changes). This
+        // includes both outgoing forward edges AND the mirror inverse edges
+        // that point back to this entity from its targets — but not the
+        // inverse edges other entities' references created from this one.
         sqlx::query(
-            "DELETE FROM relations WHERE source = 'field' AND \
-             (from_entity_id = ? OR (to_entity_id = ? AND direction = 'inverse'))",
+            "DELETE FROM relations WHERE \
+             (from_entity_id = $1 AND direction = 'forward') OR \
+             (to_entity_id = $2 AND direction = 'inverse')",
         )
         .bind(&entity.id)
         .bind(&entity.id)
@@ -61,6 +91,9 @@ impl RelationService {
         };
 
         for (field_key, field_value) in obj {
+            if field_key == EXPLICIT_RELATIONS_KEY {
+                continue;
+            }
             // Handle both scalar and list entity refs
             let ref_values: Vec<&str> = match field_value {
                 serde_json::Value::String(s) => vec![s.as_str()],
@@ -131,9 +164,96 @@ impl RelationService {
             }
         }
 
+        for explicit in Self::explicit_relations(&fields) {
+            let Some(title) = extract_wiki_title(&explicit.target) else {
+                continue;
+            };
+            let Some(target_entity) = Self::resolve_target(db, &entity.vault_id, title).await?
+            else {
+                debug!(
+                    "Unresolved relation target in {}: [[{title}]] — skipping",
+                    entity.path
+                );
+                continue;
+            };
+            let inverse_type = match registry {
+                Some(reg) => reg
+                    .find_by_name(&explicit.relation_type)
+                    .await
+                    .and_then(|rt| rt.inverse_label),
+                None => None,
+            }
+            .unwrap_or_else(|| format!("inverse_of_{}", explicit.relation_type));
+            let metadata = (!explicit.metadata.is_empty())
+                .then(|| serde_json::to_string(&explicit.metadata))
+                .transpose()?;
+            let (forward_id, inverse_id) =
+                explicit_relation_ids(&entity.id, &explicit.relation_type, &target_entity.id);
+            let now = Utc::now().to_rfc3339();
+
+            Self::insert(
+                db,
+                &Relation {
+                    id: forward_id,
+                    vault_id: entity.vault_id.clone(),
+                    from_entity_id: entity.id.clone(),
+                    to_entity_id: target_entity.id.clone(),
+                    relation_type: explicit.relation_type.clone(),
+                    direction: "forward".into(),
+                    metadata: metadata.clone(),
+                    source: "explicit".into(),
+                    source_field: Some(explicit.relation_type.clone()),
+                    created_at: now.clone(),
+                },
+            )
+            .await?;
+            Self::insert(
+                db,
+                &Relation {
+                    id: inverse_id,
+                    vault_id: entity.vault_id.clone(),
+                    from_entity_id: target_entity.id.clone(),
+                    to_entity_id: entity.id.clone(),
+                    relation_type: inverse_type,
+                    direction: "inverse".into(),
+                    metadata,
+                    source: "explicit".into(),
+                    source_field: Some(explicit.relation_type.clone()),
+                    created_at: now,
+                },
+            )
+            .await?;
+        }
+
         Ok(())
     }
 
+    /// The `codex_relations` entries in an entity's fields. Malformed
+    /// entries are skipped.
+    pub fn explicit_relations(fields: &serde_json::Value) -> Vec<ExplicitRelation> {
+        fields
+            .get(EXPLICIT_RELATIONS_KEY)
+            .and_then(|v| v.as_array())
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|entry| serde_json::from_value(entry.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Id of the forward edge of the explicit relation `from -type-> to`.
+    /// Explicit relation ids are derived from their ends so they stay the
+    /// same across reindexes.
+    pub fn explicit_relation_id(
+        from_entity_id: &str,
+        relation_type: &str,
+        to_entity_id: &str,
+    ) -> String {
+        explicit_relation_ids(from_entity_id, relation_type, to_entity_id).0
+    }
+
     /// Resolve a `[[Title]]` or `[[Title|Alias]]` reference to an entity by
     /// matching the path's file stem (case-insensitive) within the vault.
     ///
@@ -165,23 +285,43 @@ impl RelationService {
         direction: &str,
         source_field: &str,
     ) -> AppResult<()> {
-        let id = Uuid::new_v4().to_string();
-        let now = Utc::now().to_rfc3339();
+        Self::insert(
+            db,
+            &Relation {
+                id: Uuid::new_v4().to_string(),
+                vault_id: vault_id.to_string(),
+                from_entity_id: from_id.to_string(),
+                to_entity_id: to_id.to_string(),
+                relation_type: relation_type.to_string(),
+                direction: direction.to_string(),
+                metadata: None,
+                source: "field".to_string(),
+                source_field: Some(source_field.to_string()),
+                created_at: Utc::now().to_rfc3339(),
+            },
+        )
+        .await
+    }
+
+    async fn insert(db: &Database, relation: &Relation) -> AppResult<()> {
         sqlx::query(
             r#"
-            INSERT OR IGNORE INTO relations
+            INSERT INTO relations
             (id, vault_id, from_entity_id, to_entity_id, relation_type, direction, metadata, source, source_field, created_at)
-            VALUES (?, ?, ?, ?, ?, ?, NULL, 'field', ?, ?)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT DO NOTHING
             "#,
         )
-        .bind(id)
-        .bind(vault_id)
-        .bind(from_id)
-        .bind(to_id)
-        .bind(relation_type)
-        .bind(direction)
-        .bind(source_field)
-        .bind(now)
+        .bind(&relation.id)
+        .bind(&relation.vault_id)
+        .bind(&relation.from_entity_id)
+        .bind(&relation.to_entity_id)
+        .bind(&relation.relation_type)
+        .bind(&relation.direction)
+        .bind(relation.metadata.as_deref())
+        .bind(&relation.source)
+        .bind(relation.source_field.as_deref())
+        .bind(&relation.created_at)
         .execute(db.pool())
         .await
         .map_err(|e| AppError::DatabaseError(crate::error::DatabaseErrorContext {
@@ -192,6 +332,28 @@ impl RelationService {
         Ok(())
     }
 
+    /// Get a single relation by its ID.
+    pub async fn get(db: &Database, relation_id: &str) -> AppResult<Option<Relation>> {
+        let relation: Option<Relation> = sqlx::query_as(
+            r#"
+            SELECT id, vault_id, from_entity_id, to_entity_id, relation_type, direction,
+                   metadata, source, source_field, created_at
+            FROM relations WHERE id = $1
+            "#,
+        )
+        .bind(relation_id)
+        .fetch_optional(db.pool())
+        .await
+        .map_err(|e| {
+            AppError::DatabaseError(crate::error::DatabaseErrorContext {
+                error: e,
+                operation: "get_relation".into(),
+                details: None,
+            })
+        })?;
+        Ok(relation)
+    }
+
     /// Get all relations connected to an entity (both directions).
     pub async fn get_for_entity(db: &Database, entity_id: &str) -> AppResult<Vec<Relation>> {
         let relations: Vec<Relation> = sqlx::query_as(
@@ -199,7 +361,7 @@ impl RelationService {
             SELECT id, vault_id, from_entity_id, to_entity_id, relation_type, direction,
                    metadata, source, source_field, created_at
             FROM relations
-            WHERE from_entity_id = ? OR to_entity_id = ?
+            WHERE from_entity_id = $1 OR to_entity_id = $2
             "#,
         )
         .bind(entity_id)
@@ -222,7 +384,7 @@ impl RelationService {
             r#"
             SELECT id, vault_id, from_entity_id, to_entity_id, relation_type, direction,
                    metadata, source, source_field, created_at
-            FROM relations WHERE vault_id = ?
+            FROM relations WHERE vault_id = $1
             "#,
         )
         .bind(vault_id)
@@ -247,7 +409,7 @@ impl RelationService {
         let metadata_json = serde_json::to_string(metadata).map_err(|e| {
             AppError::InternalError(format!("Failed to serialize relation metadata: {e}"))
         })?;
-        sqlx::query("UPDATE relations SET metadata = ? WHERE id = ?")
+        sqlx::query("UPDATE relations SET metadata = $1 WHERE id = $2")
             .bind(metadata_json)
             .bind(relation_id)
             .execute(db.pool())
@@ -263,9 +425,21 @@ impl RelationService {
     }
 }
 
+/// Forward and inverse edge ids of an explicit relation.
+fn explicit_relation_ids(from_id: &str, relation_type: &str, to_id: &str) -> (String, String) {
+    let id = |direction: &str| {
+        let mut hasher = Sha256::new();
+        hasher.update(format!(
+            "explicit:{from_id}:{relation_type}:{to_id}:{direction}"
+        ));
+        hex::encode(hasher.finalize())
+    };
+    (id("forward"), id("inverse"))
+}
+
 /// Extract the title from a wiki-link string like `[[Title]]` or `[[Title|Alias]]`.
 /// Returns `None` if the string is not a wiki-link.
-fn extract_wiki_title(s: &str) -> Option<&str> {
+pub(crate) fn extract_wiki_title(s: &str) -> Option<&str> {
     let s = s.trim();
     if !s.starts_with("[[") || !s.ends_with("]]") {
         return None;
@@ -343,7 +517,7 @@ mod tests {
         // Seed vault rows required by FK constraint on entities.vault_id
         for (id, path) in &[("vault-1", "/tmp/rel-vault-1"), ("v1", "/tmp/rel-v1")] {
             sqlx::query(
-                "INSERT OR IGNORE INTO vaults (id, name, path, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
+                "INSERT OR IGNORE INTO vaults (id, name, path, created_at, updated_at) VALUES ($1, $2, $3, $4, $5)",
             )
             .bind(id)
             .bind("Test Vault")
@@ -560,6 +734,69 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_sync_from_target_keeps_inverse_edges_of_referrers() {
+        let temp = TempDir::new().unwrap();
+        let db = setup_db(&temp).await;
+
+        let alice_fm = make_frontmatter("character", serde_json::json!({ "faction": "[[Order]]" }));
+        let alice = EntityService::upsert(
+            &db,
+            "v1",
+            "alice.md",
+            &alice_fm,
+            "2024-01-01T00:00:00Z",
+            None,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let order = EntityService::upsert(
+            &db,
+            "v1",
+            "Order.md",
+            &make_frontmatter("faction", serde_json::json!({})),
+            "2024-01-01T00:00:00Z",
+            None,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        RelationService::sync_from_entity(&db, &alice, None)
+            .await
+            .unwrap();
+        RelationService::sync_from_entity(&db, &order, None)
+            .await
+            .unwrap();
+
+        let relations = RelationService::get_for_entity(&db, &order.id)
+            .await
+            .unwrap();
+        assert!(
+            relations
+                .iter()
+                .any(|r| r.direction == "inverse" && r.from_entity_id == order.id),
+            "syncing Order must not drop the inverse edge of Alice's reference"
+        );
+    }
+
+    #[test]
+    fn test_explicit_relations_skip_malformed_entries() {
+        let fields = serde_json::json!({
+            "codex_relations": [
+                { "type": "member_of", "target": "[[Order]]", "metadata": { "rank": "captain" } },
+                { "target": "[[Nobody]]" },
+                "[[Order]]",
+            ]
+        });
+        let explicit = RelationService::explicit_relations(&fields);
+        assert_eq!(explicit.len(), 1);
+        assert_eq!(explicit[0].relation_type, "member_of");
+        assert!(explicit[0].targets("factions/order.md"));
+        assert!(!explicit[0].targets("orders.md"));
+    }
+
     #[tokio::test]
     async fn test_update_metadata_stores_json() {
         let temp = TempDir::new().unwrap();
@@ -0,0 +1,357 @@
+use crate::error::{AppError, AppResult};
+use crate::models::{FieldChange, FieldType, SchemaMigrationOp};
+use crate::services::frontmatter_service::{render_frontmatter, split_frontmatter};
+use serde_json::Value;
+use serde_yaml::Mapping;
+
+/// A note rewritten by [`SchemaMigrationService::migrate_document`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigratedDocument {
+    /// The full markdown with its new frontmatter; the body is unchanged.
+    pub markdown: String,
+    pub changes: Vec<FieldChange>,
+    /// Operations that could not be applied, which left their field as is.
+    pub errors: Vec<String>,
+}
+
+pub struct SchemaMigrationService;
+
+impl SchemaMigrationService {
+    /// Apply `operations` in order to the frontmatter of `raw`.
+    pub fn migrate_document(
+        raw: &str,
+        operations: &[SchemaMigrationOp],
+    ) -> AppResult<MigratedDocument> {
+        let (yaml, body) = split_frontmatter(raw);
+        let before = match yaml.map(serde_yaml::from_str::<serde_yaml::Value>) {
+            None | Some(Ok(serde_yaml::Value::Null)) => Mapping::new(),
+            Some(Ok(serde_yaml::Value::Mapping(mapping))) => mapping,
+            Some(Ok(_)) => {
+                return Err(AppError::InvalidInput(
+                    "Frontmatter is not a mapping".to_string(),
+                ))
+            }
+            Some(Err(e)) => {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid YAML frontmatter: {e}"
+                )))
+            }
+        };
+
+        let mut after = before.clone();
+        let mut errors = Vec::new();
+        for op in operations {
+            if let Err(message) = apply(&mut after, op) {
+                errors.push(message);
+            }
+        }
+
+        let changes = diff(&before, &after);
+        let markdown = if changes.is_empty() {
+            raw.to_string()
+        } else {
+            render_frontmatter(&after, body)?
+        };
+        Ok(MigratedDocument {
+            markdown,
+            changes,
+            errors,
+        })
+    }
+
+    /// Check that a migration can be applied at all, before touching any note.
+    pub fn validate(operations: &[SchemaMigrationOp]) -> AppResult<()> {
+        if operations.is_empty() {
+            return Err(AppError::InvalidInput(
+                "A migration needs at least one operation".to_string(),
+            ));
+        }
+        for op in operations {
+            let fields = match op {
+                SchemaMigrationOp::RenameField { from, to } => vec![from, to],
+                SchemaMigrationOp::ChangeType { field, .. }
+                | SchemaMigrationOp::SetDefault { field, .. }
+                | SchemaMigrationOp::DropField { field }
+                | SchemaMigrationOp::SplitList { field, .. } => vec![field],
+            };
+            if let Some(field) = fields
+                .into_iter()
+                .find(|f| f.is_empty() || f.starts_with("codex_"))
+            {
+                return Err(AppError::InvalidInput(format!(
+                    "Field '{field}' cannot be migrated"
+                )));
+            }
+            if let SchemaMigrationOp::SplitList { separator, .. } = op {
+                if separator.is_empty() {
+                    return Err(AppError::InvalidInput(
+                        "The list separator cannot be empty".to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn apply(frontmatter: &mut Mapping, op: &SchemaMigrationOp) -> Result<(), String> {
+    match op {
+        SchemaMigrationOp::RenameField { from, to } => {
+            if !frontmatter.contains_key(from.as_str()) || from == to {
+                return Ok(());
+            }
+            if frontmatter.get(to.as_str()).is_some_and(|v| !v.is_null()) {
+                return Err(format!(
+                    "Cannot rename '{from}' to '{to}': '{to}' is already set"
+                ));
+            }
+            frontmatter.shift_remove(to.as_str());
+            *frontmatter = std::mem::take(frontmatter)
+                .into_iter()
+                .map(|(key, value)| match key.as_str() {
+                    Some(key) if key == from => (serde_yaml::Value::String(to.clone()), value),
+                    _ => (key, value),
+                })
+                .collect();
+        }
+        SchemaMigrationOp::ChangeType { field, to } => {
+            let Some(value) = get(frontmatter, field) else {
+                return Ok(());
+            };
+            let converted = convert(&value, to)
+                .ok_or_else(|| format!("Cannot convert '{field}' value {value} to {to:?}"))?;
+            set(frontmatter, field, &converted)?;
+        }
+        SchemaMigrationOp::SetDefault { field, value } => {
+            if get(frontmatter, field).is_none() {
+                set(frontmatter, field, value)?;
+            }
+        }
+        SchemaMigrationOp::DropField { field } => {
+            frontmatter.shift_remove(field.as_str());
+        }
+        SchemaMigrationOp::SplitList { field, separator } => {
+            if let Some(Value::String(text)) = get(frontmatter, field) {
+                set(frontmatter, field, &split(&text, separator))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A field's value, or `None` when it is missing or `null`.
+fn get(frontmatter: &Mapping, field: &str) -> Option<Value> {
+    frontmatter
+        .get(field)
+        .filter(|v| !v.is_null())
+        .and_then(|v| serde_json::to_value(v).ok())
+}
+
+fn set(frontmatter: &mut Mapping, field: &str, value: &Value) -> Result<(), String> {
+    let value =
+        serde_yaml::to_value(value).map_err(|e| format!("Invalid value for '{field}': {e}"))?;
+    match frontmatter.get_mut(field) {
+        Some(existing) => *existing = value,
+        None => {
+            frontmatter.insert(serde_yaml::Value::String(field.to_string()), value);
+        }
+    }
+    Ok(())
+}
+
+/// Convert a value to the representation `to` expects, or `None` when it
+/// has no sensible one.
+fn convert(value: &Value, to: &FieldType) -> Option<Value> {
+    match to {
+        FieldType::String | FieldType::Text | FieldType::Enum => match value {
+            Value::Array(items) => {
+                let items: Option<Vec<String>> = items.iter().map(as_text).collect();
+                Some(Value::String(items?.join(", ")))
+            }
+            other => as_text(other).map(Value::String),
+        },
+        FieldType::Date => {
+            let text = as_text(value)?;
+            chrono::NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()?;
+            Some(Value::String(text.trim().to_string()))
+        }
+        FieldType::Number => match value {
+            Value::Number(_) => Some(value.clone()),
+            Value::String(text) => {
+                let text = text.trim();
+                text.parse::<i64>()
+                    .map(Value::from)
+                    .ok()
+                    .or_else(|| serde_json::Number::from_f64(text.parse().ok()?).map(Value::Number))
+            }
+            _ => None,
+        },
+        FieldType::Boolean => match value {
+            Value::Bool(_) => Some(value.clone()),
+            Value::String(text) => match text.trim().to_ascii_lowercase().as_str() {
+                "true" | "yes" | "y" | "on" | "1" => Some(Value::Bool(true)),
+                "false" | "no" | "n" | "off" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            Value::Number(n) => match n.as_i64() {
+                Some(0) => Some(Value::Bool(false)),
+                Some(1) => Some(Value::Bool(true)),
+                _ => None,
+            },
+            _ => None,
+        },
+        FieldType::EntityRef => match value {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| convert(item, to))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array),
+            Value::String(text) if text.trim().is_empty() => None,
+            Value::String(text) if text.trim().starts_with("[[") => {
+                Some(Value::String(text.trim().to_string()))
+            }
+            Value::String(text) => Some(Value::String(format!("[[{}]]", text.trim()))),
+            _ => None,
+        },
+        FieldType::List => match value {
+            Value::Array(_) => Some(value.clone()),
+            other => Some(Value::Array(vec![other.clone()])),
+        },
+    }
+}
+
+/// Scalars as plain text; entity references lose their brackets.
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => {
+            let trimmed = text.trim();
+            Some(
+                trimmed
+                    .strip_prefix("[[")
+                    .and_then(|t| t.strip_suffix("]]"))
+                    .map(|t| t.split('|').next().unwrap_or(t).to_string())
+                    .unwrap_or_else(|| text.clone()),
+            )
+        }
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn split(text: &str, separator: &str) -> Value {
+    Value::Array(
+        text.split(separator)
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(|part| Value::String(part.to_string()))
+            .collect(),
+    )
+}
+
+/// The keys whose values differ, in their original order followed by new
+/// keys.
+fn diff(before: &Mapping, after: &Mapping) -> Vec<FieldChange> {
+    let json = |value: Option<&serde_yaml::Value>| value.and_then(|v| serde_json::to_value(v).ok());
+    let mut keys: Vec<&str> = before.keys().filter_map(|k| k.as_str()).collect();
+    for key in after.keys().filter_map(|k| k.as_str()) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys.into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(|key| FieldChange {
+            field: key.to_string(),
+            before: json(before.get(key)),
+            after: json(after.get(key)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn migrate(raw: &str, ops: serde_json::Value) -> MigratedDocument {
+        let ops: Vec<SchemaMigrationOp> = serde_json::from_value(ops).unwrap();
+        SchemaMigrationService::migrate_document(raw, &ops).unwrap()
+    }
+
+    #[test]
+    fn renames_in_place_and_converts() {
+        let raw = "---\ncodex_type: character\nbirthplace: Rivertown\nage: '42'\n\
+                   aliases: Red, The Fox,\n---\n# Body\n";
+        let migrated = migrate(
+            raw,
+            json!([
+                { "op": "rename_field", "from": "birthplace", "to": "origin" },
+                { "op": "change_type", "field": "origin", "to": "entity_ref" },
+                { "op": "change_type", "field": "age", "to": "number" },
+                { "op": "split_list", "field": "aliases" },
+                { "op": "set_default", "field": "status", "value": "alive" },
+            ]),
+        );
+        assert_eq!(
+            migrated.markdown,
+            "---\ncodex_type: character\norigin: '[[Rivertown]]'\nage: 42\naliases:\n\
+             - Red\n- The Fox\nstatus: alive\n---\n# Body\n"
+        );
+        let fields: Vec<&str> = migrated.changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["birthplace", "age", "aliases", "origin", "status"]);
+        assert_eq!(migrated.changes[0].after, None);
+        assert_eq!(migrated.changes[3].after, Some(json!("[[Rivertown]]")));
+        assert!(migrated.errors.is_empty());
+    }
+
+    #[test]
+    fn reports_operations_it_cannot_apply() {
+        let raw = "---\nname: Ann\nalias: Annie\nborn: spring\n---\n";
+        let migrated = migrate(
+            raw,
+            json!([
+                { "op": "rename_field", "from": "name", "to": "alias" },
+                { "op": "change_type", "field": "born", "to": "date" },
+                { "op": "drop_field", "field": "missing" },
+            ]),
+        );
+        assert_eq!(migrated.markdown, raw);
+        assert!(migrated.changes.is_empty());
+        assert_eq!(migrated.errors.len(), 2);
+    }
+
+    #[test]
+    fn converts_between_field_types() {
+        let convert = |value: Value, to: FieldType| convert(&value, &to);
+        assert_eq!(
+            convert(json!("[[Ann|Annie]]"), FieldType::String),
+            Some(json!("Ann"))
+        );
+        assert_eq!(
+            convert(json!(["a", "[[b]]"]), FieldType::Text),
+            Some(json!("a, b"))
+        );
+        assert_eq!(convert(json!("2.5"), FieldType::Number), Some(json!(2.5)));
+        assert_eq!(convert(json!("many"), FieldType::Number), None);
+        assert_eq!(convert(json!("Yes"), FieldType::Boolean), Some(json!(true)));
+        assert_eq!(
+            convert(json!(["Ann", "[[Bob]]"]), FieldType::EntityRef),
+            Some(json!(["[[Ann]]", "[[Bob]]"]))
+        );
+        assert_eq!(convert(json!("x"), FieldType::List), Some(json!(["x"])));
+        assert_eq!(
+            convert(json!("2026-03-01"), FieldType::Date),
+            Some(json!("2026-03-01"))
+        );
+    }
+
+    #[test]
+    fn rejects_reserved_fields() {
+        let ops = [SchemaMigrationOp::DropField {
+            field: "codex_type".into(),
+        }];
+        assert!(SchemaMigrationService::validate(&ops).is_err());
+        assert!(SchemaMigrationService::validate(&[]).is_err());
+    }
+}
@@ -1,12 +1,14 @@
 use crate::db::Database;
-use crate::error::AppResult;
+use crate::error::{AppError, AppResult};
 use crate::models::plugin::Plugin;
 use crate::models::schema::{
-    EntityTypeSchema, EntityTypeToml, PluginLabelDeclaration, RelationTypeSchema, RelationTypeToml,
+    EntityTypeBody, EntityTypeSchema, EntityTypeToml, PluginLabelDeclaration, RelationTypeBody,
+    RelationTypeSchema, RelationTypeToml, VaultTypeConflict, VaultTypeFileError, VaultTypeKind,
+    VaultTypesReport,
 };
 use crate::services::LabelService;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{info, warn};
@@ -17,9 +19,19 @@ use tracing::{info, warn};
 
 /// In-memory registry of all entity types across loaded plugins.
 /// Keyed by `"<plugin_id>/<type_id>"` (e.g. `"com.example.worldbuilding/character"`).
+///
+/// Vault-local types are registered under [`vault_scope`] and are only
+/// visible through a view returned by [`EntityTypeRegistry::for_vault`],
+/// where they take precedence over plugin types with the same id.
+///
+/// Types are also kept as declared, before inheritance, so that
+/// [`EntityTypeRegistry::resolve_declared`] can merge parents again after
+/// the plugin that defines them is loaded or unloaded.
 #[derive(Clone, Default)]
 pub struct EntityTypeRegistry {
     inner: Arc<RwLock<HashMap<String, EntityTypeSchema>>>,
+    declared: Arc<RwLock<HashMap<String, EntityTypeSchema>>>,
+    vault_id: Option<String>,
 }
 
 impl EntityTypeRegistry {
@@ -27,21 +39,86 @@ impl EntityTypeRegistry {
         Self::default()
     }
 
+    /// A view of the registry that also sees `vault_id`'s own types.
+    pub fn for_vault(&self, vault_id: &str) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            declared: self.declared.clone(),
+            vault_id: Some(vault_id.to_string()),
+        }
+    }
+
     pub async fn register(&self, schema: EntityTypeSchema) {
         let key = format!("{}/{}", schema.plugin_id, schema.id);
         self.inner.write().await.insert(key, schema);
     }
 
+    /// Keep `schema` as declared, before its inheritance is resolved.
+    pub async fn declare(&self, schema: EntityTypeSchema) {
+        let key = format!("{}/{}", schema.plugin_id, schema.id);
+        self.declared.write().await.insert(key, schema);
+    }
+
     pub async fn remove_plugin(&self, plugin_id: &str) {
         let prefix = format!("{plugin_id}/");
         self.inner
             .write()
             .await
             .retain(|k, _| !k.starts_with(&prefix));
+        self.declared
+            .write()
+            .await
+            .retain(|k, _| !k.starts_with(&prefix));
+    }
+
+    /// Resolve every declared type again and replace the registered ones:
+    /// plugin types against each other, then each vault's types against
+    /// the plugin types. Types that no longer resolve are unregistered, and
+    /// returned by id with the reason.
+    pub async fn resolve_declared(&self) -> Vec<(String, String)> {
+        let declared = self.declared.read().await.clone();
+        let mut scopes: HashMap<&str, Vec<EntityTypeSchema>> = HashMap::new();
+        for schema in declared.values() {
+            let scope = if is_vault_scope(&schema.plugin_id) {
+                schema.plugin_id.as_str()
+            } else {
+                ""
+            };
+            scopes.entry(scope).or_default().push(schema.clone());
+        }
+
+        let plugin_declared = scopes.remove("").unwrap_or_default();
+        let (plugin_types, mut failed) = resolve_entity_types(plugin_declared, &[]);
+        let mut resolved = plugin_types.clone();
+        for vault_declared in scopes.into_values() {
+            let (vault_types, errors) = resolve_entity_types(vault_declared, &plugin_types);
+            resolved.extend(vault_types);
+            failed.extend(errors);
+        }
+
+        let mut inner = self.inner.write().await;
+        inner.retain(|key, _| !declared.contains_key(key));
+        for schema in resolved {
+            inner.insert(format!("{}/{}", schema.plugin_id, schema.id), schema);
+        }
+        failed
     }
 
     pub async fn all(&self) -> Vec<EntityTypeSchema> {
-        self.inner.read().await.values().cloned().collect()
+        let inner = self.inner.read().await;
+        let local: Vec<&str> = inner
+            .values()
+            .filter(|s| self.is_own(&s.plugin_id))
+            .map(|s| s.id.as_str())
+            .collect();
+        inner
+            .values()
+            .filter(|s| {
+                self.is_own(&s.plugin_id)
+                    || (!is_vault_scope(&s.plugin_id) && !local.contains(&s.id.as_str()))
+            })
+            .cloned()
+            .collect()
     }
 
     pub async fn get(&self, plugin_id: &str, type_id: &str) -> Option<EntityTypeSchema> {
@@ -50,30 +127,60 @@ impl EntityTypeRegistry {
     }
 
     pub async fn get_by_id(&self, type_id: &str) -> Option<EntityTypeSchema> {
-        self.inner
-            .read()
-            .await
-            .values()
-            .find(|s| s.id == type_id)
-            .cloned()
+        self.all().await.into_iter().find(|s| s.id == type_id)
     }
 
     /// Returns all entity types that declare a specific label.
     pub async fn find_types_by_label(&self, label: &str) -> Vec<EntityTypeSchema> {
+        self.all()
+            .await
+            .into_iter()
+            .filter(|s| s.labels.iter().any(|l| l == label))
+            .collect()
+    }
+
+    /// `type_id` followed by every type that extends it, directly or
+    /// through other types.
+    pub async fn with_subtypes(&self, type_id: &str) -> Vec<String> {
+        let all = self.all().await;
+        let mut ids = vec![type_id.to_string()];
+        let mut next = 0;
+        while next < ids.len() {
+            let parent = ids[next].clone();
+            for schema in &all {
+                if schema.extends.as_deref() == Some(&parent) && !ids.contains(&schema.id) {
+                    ids.push(schema.id.clone());
+                }
+            }
+            next += 1;
+        }
+        ids
+    }
+
+    /// The plugin that defines entity type `type_id`, if any.
+    pub async fn plugin_defining(&self, type_id: &str) -> Option<String> {
         self.inner
             .read()
             .await
             .values()
-            .filter(|s| s.labels.iter().any(|l| l == label))
-            .cloned()
-            .collect()
+            .find(|s| s.id == type_id && !is_vault_scope(&s.plugin_id))
+            .map(|s| s.plugin_id.clone())
+    }
+
+    /// Whether `plugin_id` is the scope of this view's vault.
+    fn is_own(&self, plugin_id: &str) -> bool {
+        self.vault_id
+            .as_deref()
+            .is_some_and(|vault_id| plugin_id == vault_scope(vault_id))
     }
 }
 
-/// In-memory registry of all relation types across loaded plugins.
+/// In-memory registry of all relation types across loaded plugins. Scoped
+/// to vaults like [`EntityTypeRegistry`].
 #[derive(Clone, Default)]
 pub struct RelationTypeRegistry {
     inner: Arc<RwLock<HashMap<String, RelationTypeSchema>>>,
+    vault_id: Option<String>,
 }
 
 impl RelationTypeRegistry {
@@ -81,6 +188,14 @@ impl RelationTypeRegistry {
         Self::default()
     }
 
+    /// A view of the registry that also sees `vault_id`'s own types.
+    pub fn for_vault(&self, vault_id: &str) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            vault_id: Some(vault_id.to_string()),
+        }
+    }
+
     pub async fn register(&self, schema: RelationTypeSchema) {
         let key = format!("{}/{}", schema.plugin_id, schema.id);
         self.inner.write().await.insert(key, schema);
@@ -95,18 +210,55 @@ impl RelationTypeRegistry {
     }
 
     pub async fn all(&self) -> Vec<RelationTypeSchema> {
-        self.inner.read().await.values().cloned().collect()
+        let inner = self.inner.read().await;
+        let local: Vec<&str> = inner
+            .values()
+            .filter(|s| self.is_own(&s.plugin_id))
+            .map(|s| s.name.as_str())
+            .collect();
+        inner
+            .values()
+            .filter(|s| {
+                self.is_own(&s.plugin_id)
+                    || (!is_vault_scope(&s.plugin_id) && !local.contains(&s.name.as_str()))
+            })
+            .cloned()
+            .collect()
     }
 
     /// Find a relation type by its canonical name across all plugins.
     pub async fn find_by_name(&self, name: &str) -> Option<RelationTypeSchema> {
+        self.all().await.into_iter().find(|s| s.name == name)
+    }
+
+    /// The plugin that defines relation type `name`, if any.
+    pub async fn plugin_defining(&self, name: &str) -> Option<String> {
         self.inner
             .read()
             .await
             .values()
-            .find(|s| s.name == name)
-            .cloned()
+            .find(|s| s.name == name && !is_vault_scope(&s.plugin_id))
+            .map(|s| s.plugin_id.clone())
     }
+
+    /// Whether `plugin_id` is the scope of this view's vault.
+    fn is_own(&self, plugin_id: &str) -> bool {
+        self.vault_id
+            .as_deref()
+            .is_some_and(|vault_id| plugin_id == vault_scope(vault_id))
+    }
+}
+
+/// Folder inside a vault holding the vault's own type definitions.
+pub const VAULT_TYPES_DIR: &str = ".codex/types";
+
+/// The `plugin_id` under which a vault's own types are registered.
+pub fn vault_scope(vault_id: &str) -> String {
+    format!("vault:{vault_id}")
+}
+
+fn is_vault_scope(plugin_id: &str) -> bool {
+    plugin_id.starts_with("vault:")
 }
 
 // ──────────────────────────────────────────────────────────────────────────────
@@ -118,27 +270,54 @@ pub struct SchemaService;
 impl SchemaService {
     /// Load all schemas from a slice of enabled plugins. Registers labels,
     /// entity types, and relation types. Called at startup and on plugin toggle.
+    ///
+    /// Entity types are resolved together with every type already declared,
+    /// so a type can extend or mix in one from any loaded plugin, and types
+    /// waiting on a parent from this slice are resolved now.
     pub async fn load_plugin_schemas(
         db: &Database,
         plugins: &[Plugin],
         entity_registry: &EntityTypeRegistry,
         relation_registry: &RelationTypeRegistry,
     ) -> AppResult<()> {
+        let mut declared = Vec::new();
         for plugin in plugins {
             if !plugin.enabled {
                 continue;
             }
-            Self::load_one(db, plugin, entity_registry, relation_registry).await;
+            declared.extend(Self::load_one(db, plugin, relation_registry).await);
+        }
+
+        for schema in &declared {
+            entity_registry.declare(schema.clone()).await;
+        }
+        for (type_id, message) in entity_registry.resolve_declared().await {
+            warn!("Failed to resolve entity type {type_id}: {message}");
+        }
+        for declared in declared {
+            let Some(schema) = entity_registry.get(&declared.plugin_id, &declared.id).await else {
+                continue;
+            };
+            // Register labels declared in the entity type
+            for label in &schema.labels {
+                let decl = PluginLabelDeclaration {
+                    name: label.clone(),
+                    description: None,
+                };
+                register_label(db, &schema.plugin_id, &decl).await;
+            }
+            info!("Registered entity type {}/{}", schema.plugin_id, schema.id);
         }
         Ok(())
     }
 
+    /// Register a plugin's labels and relation types, and return its entity
+    /// types as declared, before inheritance is resolved.
     async fn load_one(
         db: &Database,
         plugin: &Plugin,
-        entity_registry: &EntityTypeRegistry,
         relation_registry: &RelationTypeRegistry,
-    ) {
+    ) -> Vec<EntityTypeSchema> {
         let plugin_id = &plugin.manifest.id;
         let plugin_dir = Path::new(&plugin.path);
 
@@ -148,24 +327,11 @@ impl SchemaService {
         }
 
         // Load entity types
+        let mut entity_types = Vec::new();
         for rel_path in &plugin.manifest.entity_types {
             let abs = plugin_dir.join(rel_path);
             match load_entity_type_toml(&abs, plugin_id) {
-                Ok(schema) => {
-                    // Register labels declared in the entity type
-                    for label in &schema.labels {
-                        let decl = PluginLabelDeclaration {
-                            name: label.clone(),
-                            description: None,
-                        };
-                        register_label(db, plugin_id, &decl).await;
-                    }
-                    info!(
-                        "Registered entity type {}/{} from {}",
-                        plugin_id, schema.id, rel_path
-                    );
-                    entity_registry.register(schema).await;
-                }
+                Ok(schema) => entity_types.push(schema),
                 Err(e) => {
                     warn!("Failed to load entity type {rel_path} for plugin {plugin_id}: {e}");
                 }
@@ -188,9 +354,11 @@ impl SchemaService {
                 }
             }
         }
+        entity_types
     }
 
     /// Unload all schemas for a plugin (called when plugin is disabled).
+    /// Types that inherit from the plugin's types are resolved again.
     pub async fn unload_plugin_schemas(
         plugin_id: &str,
         entity_registry: &EntityTypeRegistry,
@@ -198,8 +366,477 @@ impl SchemaService {
     ) {
         entity_registry.remove_plugin(plugin_id).await;
         relation_registry.remove_plugin(plugin_id).await;
+        for (type_id, message) in entity_registry.resolve_declared().await {
+            warn!("Failed to resolve entity type {type_id}: {message}");
+        }
         info!("Unloaded schemas for plugin {plugin_id}");
     }
+
+    /// Load a vault's `.codex/types/*.toml` definitions into the registries,
+    /// replacing the ones loaded before, and return the types that were
+    /// added, changed or removed. Files that fail to load are skipped.
+    pub async fn load_vault_schemas(
+        db: &Database,
+        vault_id: &str,
+        vault_path: &str,
+        entity_registry: &EntityTypeRegistry,
+        relation_registry: &RelationTypeRegistry,
+    ) -> VaultTypeChanges {
+        let scope = vault_scope(vault_id);
+        let keyed = |values: Vec<(String, serde_json::Value)>| -> HashMap<_, _> {
+            values.into_iter().collect()
+        };
+        let own_entities = |types: Vec<EntityTypeSchema>| {
+            keyed(
+                types
+                    .into_iter()
+                    .filter(|t| t.plugin_id == scope)
+                    .map(|t| (t.id.clone(), serde_json::to_value(t).unwrap_or_default()))
+                    .collect(),
+            )
+        };
+        let own_relations = |types: Vec<RelationTypeSchema>| {
+            keyed(
+                types
+                    .into_iter()
+                    .filter(|t| t.plugin_id == scope)
+                    .map(|t| (t.name.clone(), serde_json::to_value(t).unwrap_or_default()))
+                    .collect(),
+            )
+        };
+        let entity_view = entity_registry.for_vault(vault_id);
+        let relation_view = relation_registry.for_vault(vault_id);
+        let entities_before = own_entities(entity_view.all().await);
+        let relations_before = own_relations(relation_view.all().await);
+
+        let mut loaded = Self::read_vault_types(vault_id, vault_path);
+        let declared = loaded.entity_types.clone();
+        resolve_vault_types(&mut loaded, entity_registry).await;
+        for error in &loaded.errors {
+            warn!(
+                "Failed to load vault type {} in vault {vault_id}: {}",
+                error.file, error.message
+            );
+        }
+        entity_registry.remove_plugin(&scope).await;
+        relation_registry.remove_plugin(&scope).await;
+        for schema in declared {
+            entity_registry.declare(schema).await;
+        }
+        for schema in loaded.entity_types {
+            for label in &schema.labels {
+                let decl = PluginLabelDeclaration {
+                    name: label.clone(),
+                    description: None,
+                };
+                register_label(db, &scope, &decl).await;
+            }
+            entity_registry.register(schema).await;
+        }
+        for schema in loaded.relation_types {
+            relation_registry.register(schema).await;
+        }
+
+        let changes = VaultTypeChanges {
+            entity_types: changed_keys(&entities_before, &own_entities(entity_view.all().await)),
+            relation_types: changed_keys(
+                &relations_before,
+                &own_relations(relation_view.all().await),
+            ),
+        };
+        if !changes.is_empty() {
+            info!(
+                "Vault {vault_id} types changed: entity types {:?}, relation types {:?}",
+                changes.entity_types, changes.relation_types
+            );
+        }
+        changes
+    }
+
+    /// Parse a vault's type files without registering them. Inheritance is
+    /// not resolved and conflicts are left for
+    /// [`SchemaService::vault_types_report`] to fill in.
+    pub fn read_vault_types(vault_id: &str, vault_path: &str) -> VaultTypesReport {
+        let scope = vault_scope(vault_id);
+        let mut report = VaultTypesReport {
+            entity_types: Vec::new(),
+            relation_types: Vec::new(),
+            conflicts: Vec::new(),
+            errors: Vec::new(),
+        };
+        for path in vault_type_files(vault_path) {
+            let file = file_name(&path);
+            let message = match load_vault_type_file(&path, &scope) {
+                Ok(VaultTypeDefinition::Entity(schema)) => {
+                    report.entity_types.push(schema);
+                    continue;
+                }
+                Ok(VaultTypeDefinition::Relation(schema)) => {
+                    match report.relation_types.iter().find(|r| r.name == schema.name) {
+                        Some(_) => format!("Relation type '{}' is defined twice", schema.name),
+                        None => {
+                            report.relation_types.push(schema);
+                            continue;
+                        }
+                    }
+                }
+                Err(message) => message,
+            };
+            report.errors.push(VaultTypeFileError { file, message });
+        }
+        report
+    }
+
+    /// A vault's own types as defined on disk with inheritance resolved,
+    /// with load errors and the plugin types they shadow.
+    pub async fn vault_types_report(
+        vault_id: &str,
+        vault_path: &str,
+        entity_registry: &EntityTypeRegistry,
+        relation_registry: &RelationTypeRegistry,
+    ) -> VaultTypesReport {
+        let mut report = Self::read_vault_types(vault_id, vault_path);
+        resolve_vault_types(&mut report, entity_registry).await;
+        for schema in &report.entity_types {
+            if let Some(plugin_id) = entity_registry.plugin_defining(&schema.id).await {
+                report.conflicts.push(VaultTypeConflict {
+                    kind: VaultTypeKind::Entity,
+                    id: schema.id.clone(),
+                    plugin_id,
+                });
+            }
+        }
+        for schema in &report.relation_types {
+            if let Some(plugin_id) = relation_registry.plugin_defining(&schema.name).await {
+                report.conflicts.push(VaultTypeConflict {
+                    kind: VaultTypeKind::Relation,
+                    id: schema.name.clone(),
+                    plugin_id,
+                });
+            }
+        }
+        report
+    }
+
+    /// Write a vault-local entity type to `.codex/types/<type_id>.toml`.
+    pub fn save_vault_entity_type(
+        vault_path: &str,
+        type_id: &str,
+        body: EntityTypeBody,
+    ) -> AppResult<()> {
+        check_type_id(type_id)?;
+        let path = vault_types_dir(vault_path).join(format!("{type_id}.toml"));
+        if let Ok(VaultTypeDefinition::Relation(_)) = load_vault_type_file(&path, "") {
+            return Err(AppError::Conflict(format!(
+                "{type_id}.toml already defines a relation type"
+            )));
+        }
+        write_vault_type_file(&path, &EntityTypeToml { entity_type: body })
+    }
+
+    /// Write a vault-local relation type to the file that already defines
+    /// it, or to `.codex/types/<name>.toml`.
+    pub fn save_vault_relation_type(vault_path: &str, body: RelationTypeBody) -> AppResult<()> {
+        check_type_id(&body.name)?;
+        let path = match find_vault_type_file(vault_path, VaultTypeKind::Relation, &body.name) {
+            Some(path) => path,
+            None => {
+                let path = vault_types_dir(vault_path).join(format!("{}.toml", body.name));
+                if path.exists() {
+                    return Err(AppError::Conflict(format!(
+                        "{}.toml already defines another type",
+                        body.name
+                    )));
+                }
+                path
+            }
+        };
+        write_vault_type_file(
+            &path,
+            &RelationTypeToml {
+                relation_type: body,
+            },
+        )
+    }
+
+    /// Delete the file defining a vault-local type.
+    pub fn delete_vault_type(vault_path: &str, kind: VaultTypeKind, id: &str) -> AppResult<()> {
+        let path = find_vault_type_file(vault_path, kind, id)
+            .ok_or_else(|| AppError::NotFound(format!("Vault type '{id}' not found")))?;
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
+
+/// Types whose vault-local definition was added, changed or removed by
+/// [`SchemaService::load_vault_schemas`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultTypeChanges {
+    /// Entity type ids
+    pub entity_types: Vec<String>,
+    /// Relation type names
+    pub relation_types: Vec<String>,
+}
+
+impl VaultTypeChanges {
+    pub fn is_empty(&self) -> bool {
+        self.entity_types.is_empty() && self.relation_types.is_empty()
+    }
+}
+
+fn changed_keys(
+    before: &HashMap<String, serde_json::Value>,
+    after: &HashMap<String, serde_json::Value>,
+) -> Vec<String> {
+    let mut keys: Vec<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|key| before.get(*key) != after.get(*key))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+// ──────────────────────────────────────────────────────────────────────────────
+// Inheritance
+// ──────────────────────────────────────────────────────────────────────────────
+
+/// Resolve the `extends` and `mixins` of `declared` entity types. Parents
+/// are looked up among `declared` first and then among the already
+/// resolved `available` types. Returns the resolved types, and the id and
+/// reason for each type that could not be resolved.
+fn resolve_entity_types(
+    declared: Vec<EntityTypeSchema>,
+    available: &[EntityTypeSchema],
+) -> (Vec<EntityTypeSchema>, Vec<(String, String)>) {
+    let mut resolver = Resolver {
+        declared: &declared,
+        available,
+        resolved: HashMap::new(),
+    };
+    let mut resolved = Vec::new();
+    let mut errors = Vec::new();
+    for schema in &declared {
+        let key = resolver_key(schema);
+        // Reuse a type already resolved as a parent. Failures are retried,
+        // as their message depends on the chain that reached them.
+        let result = match resolver.resolved.get(&key) {
+            Some(Ok(cached)) => Ok(cached.clone()),
+            _ => {
+                let result = resolver.resolve(schema, &mut Vec::new());
+                resolver.resolved.insert(key, result.clone());
+                result
+            }
+        };
+        match result {
+            Ok(schema) => resolved.push(schema),
+            Err(message) => errors.push((schema.id.clone(), message)),
+        }
+    }
+    (resolved, errors)
+}
+
+struct Resolver<'a> {
+    declared: &'a [EntityTypeSchema],
+    available: &'a [EntityTypeSchema],
+    /// Declared types resolved so far, by [`resolver_key`]
+    resolved: HashMap<String, Result<EntityTypeSchema, String>>,
+}
+
+fn resolver_key(schema: &EntityTypeSchema) -> String {
+    format!("{}/{}", schema.plugin_id, schema.id)
+}
+
+impl Resolver<'_> {
+    /// `schema` with its parent's and mixins' fields and labels merged in.
+    /// `chain` holds the ids being resolved, to detect cycles.
+    fn resolve(
+        &mut self,
+        schema: &EntityTypeSchema,
+        chain: &mut Vec<String>,
+    ) -> Result<EntityTypeSchema, String> {
+        if schema.extends.is_none() && schema.mixins.is_empty() {
+            return Ok(schema.clone());
+        }
+        chain.push(schema.id.clone());
+        let parent = schema
+            .extends
+            .as_deref()
+            .map(|id| self.lookup(id, chain))
+            .transpose();
+        let mixins: Result<Vec<_>, _> = schema
+            .mixins
+            .iter()
+            .map(|id| self.lookup(id, chain))
+            .collect();
+        chain.pop();
+
+        let mut merged = schema.clone();
+        merged.labels = Vec::new();
+        merged.fields = Vec::new();
+        let (parent, mixins) = (parent?, mixins?);
+        for source in parent.iter().chain(&mixins).chain([schema]) {
+            for label in &source.labels {
+                if !merged.labels.contains(label) {
+                    merged.labels.push(label.clone());
+                }
+            }
+            for field in &source.fields {
+                match merged.fields.iter_mut().find(|f| f.key == field.key) {
+                    Some(existing) => *existing = field.clone(),
+                    None => merged.fields.push(field.clone()),
+                }
+            }
+        }
+        if let Some(parent) = parent {
+            merged.icon = merged.icon.or(parent.icon);
+            merged.color = merged.color.or(parent.color);
+            merged.display_field = merged.display_field.or(parent.display_field);
+            if merged.show_on_create.is_empty() {
+                merged.show_on_create = parent.show_on_create;
+            }
+        }
+        Ok(merged)
+    }
+
+    /// The resolved type `id` that a type in `chain` inherits from.
+    fn lookup(&mut self, id: &str, chain: &mut Vec<String>) -> Result<EntityTypeSchema, String> {
+        if chain.iter().any(|c| c == id) {
+            return Err(format!(
+                "Entity type '{id}' inherits from itself ({} -> {id})",
+                chain.join(" -> ")
+            ));
+        }
+        if let Some(schema) = self.declared.iter().find(|s| s.id == id) {
+            let key = resolver_key(schema);
+            if let Some(Ok(cached)) = self.resolved.get(&key) {
+                return Ok(cached.clone());
+            }
+            let result = self.resolve(schema, chain);
+            self.resolved.insert(key, result.clone());
+            return result;
+        }
+        self.available
+            .iter()
+            .find(|s| s.id == id)
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "Entity type '{}' inherits from unknown type '{id}'",
+                    chain.last().map(String::as_str).unwrap_or_default()
+                )
+            })
+    }
+}
+
+/// Resolve the inheritance of a vault's own entity types against each
+/// other and the plugin types, moving those that fail to `errors`.
+async fn resolve_vault_types(report: &mut VaultTypesReport, entity_registry: &EntityTypeRegistry) {
+    let plugin_types: Vec<EntityTypeSchema> = entity_registry
+        .all()
+        .await
+        .into_iter()
+        .filter(|s| !is_vault_scope(&s.plugin_id))
+        .collect();
+    let declared = std::mem::take(&mut report.entity_types);
+    let (resolved, errors) = resolve_entity_types(declared, &plugin_types);
+    report.entity_types = resolved;
+    for (type_id, message) in errors {
+        report.errors.push(VaultTypeFileError {
+            file: format!("{type_id}.toml"),
+            message,
+        });
+    }
+}
+
+// ──────────────────────────────────────────────────────────────────────────────
+// Vault type files
+// ──────────────────────────────────────────────────────────────────────────────
+
+enum VaultTypeDefinition {
+    Entity(EntityTypeSchema),
+    Relation(RelationTypeSchema),
+}
+
+fn vault_types_dir(vault_path: &str) -> PathBuf {
+    Path::new(vault_path).join(VAULT_TYPES_DIR)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The `*.toml` files directly inside a vault's types folder, by name.
+fn vault_type_files(vault_path: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(vault_types_dir(vault_path)) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Parse a type file as whichever of `[entity_type]` or `[relation_type]`
+/// it contains.
+fn load_vault_type_file(path: &Path, scope: &str) -> Result<VaultTypeDefinition, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {e}", file_name(path)))?;
+    let table: toml::Table = toml::from_str(&content)
+        .map_err(|e| format!("TOML parse error in {}: {e}", file_name(path)))?;
+    if table.contains_key("entity_type") {
+        load_entity_type_toml(path, scope).map(VaultTypeDefinition::Entity)
+    } else if table.contains_key("relation_type") {
+        load_relation_type_toml(path, scope).map(VaultTypeDefinition::Relation)
+    } else {
+        Err(format!(
+            "{} has neither an [entity_type] nor a [relation_type] table",
+            file_name(path)
+        ))
+    }
+}
+
+fn find_vault_type_file(vault_path: &str, kind: VaultTypeKind, id: &str) -> Option<PathBuf> {
+    vault_type_files(vault_path).into_iter().find(|path| {
+        match (kind, load_vault_type_file(path, "")) {
+            (VaultTypeKind::Entity, Ok(VaultTypeDefinition::Entity(schema))) => schema.id == id,
+            (VaultTypeKind::Relation, Ok(VaultTypeDefinition::Relation(schema))) => {
+                schema.name == id
+            }
+            _ => false,
+        }
+    })
+}
+
+fn write_vault_type_file(path: &Path, definition: &impl serde::Serialize) -> AppResult<()> {
+    let content = toml::to_string_pretty(definition)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid type definition: {e}")))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// Type ids double as file names, so keep them to a safe character set.
+fn check_type_id(id: &str) -> AppResult<()> {
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid type id '{id}'; use letters, digits, '_' and '-'"
+        )));
+    }
+    Ok(())
 }
 
 // ──────────────────────────────────────────────────────────────────────────────
@@ -230,6 +867,8 @@ fn load_entity_type_toml(path: &Path, plugin_id: &str) -> Result<EntityTypeSchem
         labels: body.labels,
         display_field: body.display_field,
         show_on_create: body.show_on_create,
+        extends: body.extends,
+        mixins: body.mixins,
         fields: body.fields,
     })
 }
@@ -306,6 +945,8 @@ mod tests {
             labels: vec!["graphable".into()],
             display_field: Some("full_name".into()),
             show_on_create: vec!["full_name".into()],
+            extends: None,
+            mixins: vec![],
             fields: vec![],
         };
         registry.register(schema.clone()).await;
@@ -339,6 +980,8 @@ mod tests {
                     labels: vec![],
                     display_field: None,
                     show_on_create: vec![],
+                    extends: None,
+                    mixins: vec![],
                     fields: vec![],
                 })
                 .await;
@@ -361,6 +1004,8 @@ mod tests {
                 labels: vec![],
                 display_field: None,
                 show_on_create: vec![],
+                extends: None,
+                mixins: vec![],
                 fields: vec![],
             })
             .await;
@@ -375,6 +1020,8 @@ mod tests {
                 labels: vec![],
                 display_field: None,
                 show_on_create: vec![],
+                extends: None,
+                mixins: vec![],
                 fields: vec![],
             })
             .await;
@@ -500,4 +1147,236 @@ type = "string"
         assert_eq!(schema.name, "TestType");
         assert_eq!(schema.fields.len(), 1);
     }
+
+    // ── Vault-local types ─────────────────────────────────────────────────
+
+    fn entity_type(id: &str, plugin_id: &str, name: &str) -> EntityTypeSchema {
+        EntityTypeSchema {
+            id: id.into(),
+            plugin_id: plugin_id.into(),
+            name: name.into(),
+            icon: None,
+            color: None,
+            template: None,
+            labels: vec![],
+            display_field: None,
+            show_on_create: vec![],
+            extends: None,
+            mixins: vec![],
+            fields: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vault_types_shadow_plugin_types_in_their_vault_only() {
+        let registry = EntityTypeRegistry::new();
+        registry
+            .register(entity_type("character", "wb", "Character"))
+            .await;
+        registry
+            .register(entity_type("character", &vault_scope("v1"), "Hero"))
+            .await;
+        registry
+            .register(entity_type("ship", &vault_scope("v2"), "Ship"))
+            .await;
+
+        let global = registry.all().await;
+        assert_eq!(global.len(), 1);
+        assert_eq!(global[0].plugin_id, "wb");
+
+        let v1 = registry.for_vault("v1");
+        assert_eq!(v1.all().await.len(), 1);
+        assert_eq!(v1.get_by_id("character").await.unwrap().name, "Hero");
+        assert!(v1.get_by_id("ship").await.is_none());
+        assert_eq!(v1.plugin_defining("character").await.as_deref(), Some("wb"));
+
+        let v2 = registry.for_vault("v2");
+        assert_eq!(v2.get_by_id("character").await.unwrap().name, "Character");
+        assert!(v2.get_by_id("ship").await.is_some());
+    }
+
+    #[test]
+    fn test_save_read_and_delete_vault_types() {
+        let temp = TempDir::new().unwrap();
+        let vault = temp.path().to_str().unwrap();
+        let body: EntityTypeBody = toml::from_str(
+            r#"
+name = "Starship"
+labels = ["graphable"]
+
+[[fields]]
+key = "crew"
+label = "Crew"
+type = "number"
+"#,
+        )
+        .unwrap();
+        SchemaService::save_vault_entity_type(vault, "starship", body).unwrap();
+        let relation: RelationTypeBody =
+            toml::from_str("name = \"docked_at\"\nlabel = \"Docked At\"").unwrap();
+        SchemaService::save_vault_relation_type(vault, relation).unwrap();
+        std::fs::write(
+            temp.path().join(VAULT_TYPES_DIR).join("broken.toml"),
+            "[entity_type\n",
+        )
+        .unwrap();
+
+        let report = SchemaService::read_vault_types("v1", vault);
+        assert_eq!(report.entity_types.len(), 1);
+        assert_eq!(report.entity_types[0].id, "starship");
+        assert_eq!(report.entity_types[0].plugin_id, "vault:v1");
+        assert_eq!(report.entity_types[0].fields[0].key, "crew");
+        assert_eq!(report.relation_types[0].name, "docked_at");
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].file, "broken.toml");
+
+        // A relation type's file cannot be overwritten with an entity type
+        let body: EntityTypeBody = toml::from_str("name = \"Dock\"").unwrap();
+        assert!(matches!(
+            SchemaService::save_vault_entity_type(vault, "docked_at", body),
+            Err(AppError::Conflict(_))
+        ));
+        assert!(matches!(
+            SchemaService::save_vault_entity_type(
+                vault,
+                "../escape",
+                toml::from_str("name = \"X\"").unwrap()
+            ),
+            Err(AppError::InvalidInput(_))
+        ));
+
+        SchemaService::delete_vault_type(vault, VaultTypeKind::Relation, "docked_at").unwrap();
+        assert!(SchemaService::read_vault_types("v1", vault)
+            .relation_types
+            .is_empty());
+        assert!(matches!(
+            SchemaService::delete_vault_type(vault, VaultTypeKind::Entity, "docked_at"),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    // ── Inheritance ───────────────────────────────────────────────────────
+
+    fn field(key: &str, label: &str) -> FieldSchema {
+        serde_json::from_value(serde_json::json!({ "key": key, "label": label })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_extends_and_mixins_merge_fields_and_labels() {
+        let mut base = entity_type("base_entity", "wb", "Base");
+        base.icon = Some("label".into());
+        base.display_field = Some("full_name".into());
+        base.labels = vec!["graphable".into()];
+        base.fields = vec![field("full_name", "Name"), field("summary", "Summary")];
+        let mut located = entity_type("has_location", "wb", "Located");
+        located.labels = vec!["placed".into()];
+        located.fields = vec![field("location", "Location")];
+        let mut character = entity_type("character", "wb", "Character");
+        character.extends = Some("base_entity".into());
+        character.mixins = vec!["has_location".into()];
+        character.labels = vec!["person".into(), "graphable".into()];
+        character.fields = vec![field("summary", "Biography"), field("status", "Status")];
+        let mut hero = entity_type("hero", "vault:v1", "Hero");
+        hero.extends = Some("character".into());
+
+        let (resolved, errors) =
+            resolve_entity_types(vec![hero.clone(), character, located], &[base]);
+        assert!(errors.is_empty(), "{errors:?}");
+        let character = resolved.iter().find(|s| s.id == "character").unwrap();
+        let keys: Vec<&str> = character.fields.iter().map(|f| f.key.as_str()).collect();
+        assert_eq!(keys, ["full_name", "summary", "location", "status"]);
+        assert_eq!(character.fields[1].label, "Biography");
+        assert_eq!(character.labels, ["graphable", "placed", "person"]);
+        assert_eq!(character.icon.as_deref(), Some("label"));
+        assert_eq!(character.display_field.as_deref(), Some("full_name"));
+        let hero = resolved.iter().find(|s| s.id == "hero").unwrap();
+        assert_eq!(hero.fields.len(), 4);
+        assert_eq!(hero.extends.as_deref(), Some("character"));
+
+        let registry = EntityTypeRegistry::new();
+        for schema in resolved {
+            registry.register(schema).await;
+        }
+        let view = registry.for_vault("v1");
+        assert_eq!(
+            view.with_subtypes("base_entity").await,
+            ["base_entity", "character", "hero"]
+        );
+        assert_eq!(registry.with_subtypes("character").await, ["character"]);
+    }
+
+    #[tokio::test]
+    async fn test_declared_types_follow_parents_across_plugin_loads() {
+        let registry = EntityTypeRegistry::new();
+        let mut hero = entity_type("hero", "story", "Hero");
+        hero.extends = Some("character".into());
+        let mut villain = entity_type("villain", "vault:v1", "Villain");
+        villain.extends = Some("character".into());
+        registry.declare(hero).await;
+        registry.declare(villain).await;
+        let failed = registry.resolve_declared().await;
+        assert_eq!(failed.len(), 2);
+        assert!(registry.get("story", "hero").await.is_none());
+
+        // The parent's plugin loads later: waiting children resolve.
+        let mut character = entity_type("character", "wb", "Character");
+        character.fields = vec![field("status", "Status")];
+        registry.declare(character.clone()).await;
+        assert!(registry.resolve_declared().await.is_empty());
+        let hero = registry.get("story", "hero").await.unwrap();
+        assert_eq!(hero.fields[0].key, "status");
+        let villain = registry.get("vault:v1", "villain").await.unwrap();
+        assert_eq!(villain.fields[0].key, "status");
+
+        // Reloading the parent changes what children inherit.
+        character.fields = vec![field("rank", "Rank")];
+        registry.declare(character).await;
+        registry.resolve_declared().await;
+        let hero = registry.get("story", "hero").await.unwrap();
+        let keys: Vec<&str> = hero.fields.iter().map(|f| f.key.as_str()).collect();
+        assert_eq!(keys, ["rank"]);
+
+        // Unloading it unregisters them again.
+        registry.remove_plugin("wb").await;
+        assert_eq!(registry.resolve_declared().await.len(), 2);
+        assert!(registry.get("story", "hero").await.is_none());
+        assert!(registry.get("vault:v1", "villain").await.is_none());
+    }
+
+    #[test]
+    fn test_inheritance_cycles_and_unknown_parents_are_errors() {
+        let mut a = entity_type("a", "wb", "A");
+        a.extends = Some("b".into());
+        let mut b = entity_type("b", "wb", "B");
+        b.mixins = vec!["a".into()];
+        let mut c = entity_type("c", "wb", "C");
+        c.extends = Some("c".into());
+        let mut d = entity_type("d", "wb", "D");
+        d.extends = Some("missing".into());
+        let e = entity_type("e", "wb", "E");
+
+        let (resolved, errors) = resolve_entity_types(vec![a, b, c, d, e], &[]);
+        let ids: Vec<&str> = resolved.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["e"]);
+        let messages: HashMap<&str, &str> = errors
+            .iter()
+            .map(|(id, message)| (id.as_str(), message.as_str()))
+            .collect();
+        assert_eq!(
+            messages["a"],
+            "Entity type 'a' inherits from itself (a -> b -> a)"
+        );
+        assert_eq!(
+            messages["b"],
+            "Entity type 'b' inherits from itself (b -> a -> b)"
+        );
+        assert_eq!(
+            messages["c"],
+            "Entity type 'c' inherits from itself (c -> c)"
+        );
+        assert_eq!(
+            messages["d"],
+            "Entity type 'd' inherits from unknown type 'missing'"
+        );
+    }
 }
@@ -0,0 +1,644 @@
+//! SCIM 2.0 provisioning on top of the `users`, `groups` and
+//! `group_members` tables.
+//!
+//! Codex stores `userName`, `externalId`, `displayName`, the primary email
+//! and `active` for users, and `displayName`, `externalId` and `members` for
+//! groups. Other attributes are accepted and ignored, which is what identity
+//! providers expect from a service provider with a smaller schema.
+//! Deactivating a user (`active: false` or `DELETE`) keeps the account and
+//! its vaults but revokes every session; the user cannot sign in again until
+//! the provider reactivates it.
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::scim::{
+    ScimEmail, ScimGroup, ScimGroupFilter, ScimGroupInput, ScimGroupRecord, ScimMeta,
+    ScimPatchOperation, ScimReference, ScimUser, ScimUserAttributes, ScimUserFilter, ScimUserInput,
+    ScimUserRecord, GROUP_SCHEMA, USER_SCHEMA,
+};
+use rand::Rng;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Stored as the password hash of provisioned users. It is not a valid PHC
+/// string, so password sign-in always fails for them, as for LDAP users.
+const PLACEHOLDER_PASSWORD_HASH: &str = "scim-managed";
+
+/// What a write did to a user's `active` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationChange {
+    Deactivated { sessions_revoked: u64 },
+    Reactivated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PatchOp {
+    Add,
+    Remove,
+    Replace,
+}
+
+impl PatchOp {
+    fn parse(op: &str) -> AppResult<Self> {
+        match op.to_ascii_lowercase().as_str() {
+            "add" => Ok(Self::Add),
+            "remove" => Ok(Self::Remove),
+            "replace" => Ok(Self::Replace),
+            other => Err(AppError::InvalidInput(format!(
+                "Unsupported PATCH operation '{other}'"
+            ))),
+        }
+    }
+}
+
+pub struct ScimService;
+
+impl ScimService {
+    /// A new bearer token for an identity provider.
+    pub fn generate_token() -> String {
+        let bytes: [u8; 32] = rand::rng().random();
+        format!("scim_{}", hex::encode(bytes))
+    }
+
+    pub fn hash_token(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    pub fn parse_user_filter(filter: &str) -> AppResult<ScimUserFilter> {
+        let (attribute, value) = parse_eq_filter(filter)?;
+        match attribute.as_str() {
+            "username" => Ok(ScimUserFilter::UserName(value)),
+            "externalid" => Ok(ScimUserFilter::ExternalId(value)),
+            _ => Err(unsupported_filter(filter)),
+        }
+    }
+
+    pub fn parse_group_filter(filter: &str) -> AppResult<ScimGroupFilter> {
+        let (attribute, value) = parse_eq_filter(filter)?;
+        match attribute.as_str() {
+            "displayname" => Ok(ScimGroupFilter::DisplayName(value)),
+            "externalid" => Ok(ScimGroupFilter::ExternalId(value)),
+            _ => Err(unsupported_filter(filter)),
+        }
+    }
+
+    // ── Users ───────────────────────────────────────────────────────────
+
+    pub async fn get_user(db: &Database, user_id: &str) -> AppResult<ScimUserRecord> {
+        db.get_scim_user(user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("User {user_id} not found")))
+    }
+
+    pub async fn user_resource(
+        db: &Database,
+        base_url: &str,
+        user: &ScimUserRecord,
+    ) -> AppResult<ScimUser> {
+        let groups = db
+            .list_member_groups(&user.id)
+            .await?
+            .into_iter()
+            .map(|g| ScimReference {
+                value: g.id,
+                display: Some(g.name),
+            })
+            .collect();
+        Ok(ScimUser {
+            schemas: [USER_SCHEMA],
+            id: user.id.clone(),
+            external_id: user.external_id.clone(),
+            user_name: user.username.clone(),
+            display_name: user.display_name.clone(),
+            emails: user
+                .email
+                .iter()
+                .map(|email| ScimEmail {
+                    value: email.clone(),
+                    primary: Some(true),
+                    kind: None,
+                })
+                .collect(),
+            active: user.is_active,
+            groups,
+            meta: ScimMeta {
+                resource_type: "User",
+                created: user.created_at,
+                location: format!("{base_url}/Users/{}", user.id),
+            },
+        })
+    }
+
+    /// Provision a user. Without a password the account can only sign in
+    /// through the identity provider (OIDC or LDAP).
+    pub async fn create_user(
+        db: &Database,
+        input: &ScimUserInput,
+        password_hash: Option<&str>,
+    ) -> AppResult<(ScimUserRecord, Option<ActivationChange>)> {
+        let attributes = attributes_from_input(input)?;
+        let (user_id, _, _, _) = db
+            .create_user_with_options(
+                &attributes.username,
+                password_hash.unwrap_or(PLACEHOLDER_PASSWORD_HASH),
+                false,
+                false,
+            )
+            .await?;
+        db.update_scim_user(&user_id, &attributes).await?;
+
+        let change = match input.active.as_ref().map(parse_bool).transpose()? {
+            Some(false) => Self::set_active(db, &user_id, false).await?,
+            _ => None,
+        };
+        Ok((Self::get_user(db, &user_id).await?, change))
+    }
+
+    /// `PUT`: overwrite every attribute Codex stores.
+    pub async fn replace_user(
+        db: &Database,
+        user_id: &str,
+        input: &ScimUserInput,
+    ) -> AppResult<(ScimUserRecord, Option<ActivationChange>)> {
+        Self::get_user(db, user_id).await?;
+        let attributes = attributes_from_input(input)?;
+        db.update_scim_user(user_id, &attributes).await?;
+
+        let change = match input.active.as_ref().map(parse_bool).transpose()? {
+            Some(active) => Self::set_active(db, user_id, active).await?,
+            None => None,
+        };
+        Ok((Self::get_user(db, user_id).await?, change))
+    }
+
+    pub async fn patch_user(
+        db: &Database,
+        user_id: &str,
+        operations: &[ScimPatchOperation],
+    ) -> AppResult<(ScimUserRecord, Option<ActivationChange>)> {
+        let user = Self::get_user(db, user_id).await?;
+        let mut attributes = ScimUserAttributes {
+            username: user.username.clone(),
+            external_id: user.external_id.clone(),
+            display_name: user.display_name.clone(),
+            email: user.email.clone(),
+        };
+        let mut active = user.is_active;
+
+        for operation in operations {
+            let op = PatchOp::parse(&operation.op)?;
+            match (operation.path.as_deref(), &operation.value) {
+                (None, Some(Value::Object(values))) if op != PatchOp::Remove => {
+                    for (attribute, value) in values {
+                        apply_user_attribute(
+                            &mut attributes,
+                            &mut active,
+                            op,
+                            attribute,
+                            Some(value),
+                        )?;
+                    }
+                }
+                (None, _) => {
+                    return Err(AppError::InvalidInput(
+                        "PATCH without a path needs an object value".to_string(),
+                    ))
+                }
+                (Some(path), value) => {
+                    apply_user_attribute(&mut attributes, &mut active, op, path, value.as_ref())?
+                }
+            }
+        }
+
+        if attributes.username.trim().is_empty() {
+            return Err(AppError::InvalidInput(
+                "userName cannot be empty".to_string(),
+            ));
+        }
+        db.update_scim_user(user_id, &attributes).await?;
+        let change = Self::set_active(db, user_id, active).await?;
+        Ok((Self::get_user(db, user_id).await?, change))
+    }
+
+    /// Apply `active`, revoking every session when a user is deactivated.
+    /// Returns `None` when the flag already had that value.
+    pub async fn set_active(
+        db: &Database,
+        user_id: &str,
+        active: bool,
+    ) -> AppResult<Option<ActivationChange>> {
+        if db.is_user_active(user_id).await? == active {
+            return Ok(None);
+        }
+        if active {
+            db.reactivate_user(user_id).await?;
+            Ok(Some(ActivationChange::Reactivated))
+        } else {
+            db.deactivate_user(user_id).await?;
+            let sessions_revoked = db.revoke_all_sessions(user_id).await?;
+            Ok(Some(ActivationChange::Deactivated { sessions_revoked }))
+        }
+    }
+
+    // ── Groups ──────────────────────────────────────────────────────────
+
+    pub async fn get_group(db: &Database, group_id: &str) -> AppResult<ScimGroupRecord> {
+        db.get_scim_group(group_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Group {group_id} not found")))
+    }
+
+    pub fn group_resource(base_url: &str, group: &ScimGroupRecord) -> ScimGroup {
+        ScimGroup {
+            schemas: [GROUP_SCHEMA],
+            id: group.id.clone(),
+            external_id: group.external_id.clone(),
+            display_name: group.name.clone(),
+            members: group
+                .members
+                .iter()
+                .map(|m| ScimReference {
+                    value: m.user_id.clone(),
+                    display: Some(m.username.clone()),
+                })
+                .collect(),
+            meta: ScimMeta {
+                resource_type: "Group",
+                created: group.created_at,
+                location: format!("{base_url}/Groups/{}", group.id),
+            },
+        }
+    }
+
+    /// Create a group owned by `created_by_user_id` (the admin who issued
+    /// the provider's token) with exactly the given members.
+    pub async fn create_group(
+        db: &Database,
+        input: &ScimGroupInput,
+        created_by_user_id: &str,
+    ) -> AppResult<ScimGroupRecord> {
+        let members = member_ids(db, &input.members).await?;
+        let group_id = db
+            .create_scim_group(
+                &input.display_name,
+                input.external_id.as_deref(),
+                created_by_user_id,
+            )
+            .await?;
+        db.set_group_members(&group_id, &members).await?;
+        Self::get_group(db, &group_id).await
+    }
+
+    pub async fn replace_group(
+        db: &Database,
+        group_id: &str,
+        input: &ScimGroupInput,
+    ) -> AppResult<ScimGroupRecord> {
+        Self::get_group(db, group_id).await?;
+        let members = member_ids(db, &input.members).await?;
+        db.update_scim_group(group_id, &input.display_name, input.external_id.as_deref())
+            .await?;
+        db.set_group_members(group_id, &members).await?;
+        Self::get_group(db, group_id).await
+    }
+
+    pub async fn patch_group(
+        db: &Database,
+        group_id: &str,
+        operations: &[ScimPatchOperation],
+    ) -> AppResult<ScimGroupRecord> {
+        let group = Self::get_group(db, group_id).await?;
+        let mut name = group.name.clone();
+        let mut external_id = group.external_id.clone();
+        let mut members: Vec<String> = group.members.iter().map(|m| m.user_id.clone()).collect();
+
+        for operation in operations {
+            let op = PatchOp::parse(&operation.op)?;
+            match (operation.path.as_deref(), &operation.value) {
+                (None, Some(Value::Object(values))) if op != PatchOp::Remove => {
+                    for (attribute, value) in values {
+                        apply_group_attribute(
+                            db,
+                            &mut name,
+                            &mut external_id,
+                            &mut members,
+                            op,
+                            attribute,
+                            Some(value),
+                        )
+                        .await?;
+                    }
+                }
+                (None, _) => {
+                    return Err(AppError::InvalidInput(
+                        "PATCH without a path needs an object value".to_string(),
+                    ))
+                }
+                (Some(path), value) => {
+                    apply_group_attribute(
+                        db,
+                        &mut name,
+                        &mut external_id,
+                        &mut members,
+                        op,
+                        path,
+                        value.as_ref(),
+                    )
+                    .await?
+                }
+            }
+        }
+
+        db.update_scim_group(group_id, &name, external_id.as_deref())
+            .await?;
+        db.set_group_members(group_id, &members).await?;
+        Self::get_group(db, group_id).await
+    }
+}
+
+fn unsupported_filter(filter: &str) -> AppError {
+    AppError::InvalidInput(format!("Unsupported filter: {filter}"))
+}
+
+/// Parse `attribute eq "value"`, the only filter form identity providers
+/// need for provisioning. Attribute names are returned lowercased.
+fn parse_eq_filter(filter: &str) -> AppResult<(String, String)> {
+    let filter = filter.trim();
+    let mut parts = filter.splitn(3, char::is_whitespace);
+    let (Some(attribute), Some(operator), Some(value)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(unsupported_filter(filter));
+    };
+    if !operator.eq_ignore_ascii_case("eq") {
+        return Err(unsupported_filter(filter));
+    }
+    let value: String =
+        serde_json::from_str(value.trim()).map_err(|_| unsupported_filter(filter))?;
+    Ok((attribute.to_ascii_lowercase(), value))
+}
+
+/// Booleans arrive as JSON booleans or, from some providers, as strings.
+fn parse_bool(value: &Value) -> AppResult<bool> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        Value::String(s) if s.eq_ignore_ascii_case("true") => Ok(true),
+        Value::String(s) if s.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(AppError::InvalidInput(format!(
+            "Expected a boolean, got {value}"
+        ))),
+    }
+}
+
+fn optional_string(value: Option<&Value>) -> AppResult<Option<String>> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) if s.trim().is_empty() => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.trim().to_string())),
+        Some(other) => Err(AppError::InvalidInput(format!(
+            "Expected a string, got {other}"
+        ))),
+    }
+}
+
+/// The primary email, or the first one listed.
+fn primary_email(emails: &[ScimEmail]) -> Option<String> {
+    emails
+        .iter()
+        .find(|e| e.primary == Some(true))
+        .or_else(|| emails.first())
+        .map(|e| e.value.trim().to_string())
+        .filter(|e| !e.is_empty())
+}
+
+fn attributes_from_input(input: &ScimUserInput) -> AppResult<ScimUserAttributes> {
+    let username = input.user_name.trim();
+    if username.is_empty() {
+        return Err(AppError::InvalidInput(
+            "userName cannot be empty".to_string(),
+        ));
+    }
+    let display_name = input
+        .display_name
+        .clone()
+        .or_else(|| {
+            let name = input.name.as_ref()?;
+            name.formatted.clone().or_else(|| {
+                let parts: Vec<&str> = [name.given_name.as_deref(), name.family_name.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                (!parts.is_empty()).then(|| parts.join(" "))
+            })
+        })
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty());
+    Ok(ScimUserAttributes {
+        username: username.to_string(),
+        external_id: input
+            .external_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(str::to_string),
+        display_name,
+        email: primary_email(&input.emails),
+    })
+}
+
+fn apply_user_attribute(
+    attributes: &mut ScimUserAttributes,
+    active: &mut bool,
+    op: PatchOp,
+    path: &str,
+    value: Option<&Value>,
+) -> AppResult<()> {
+    let value = if op == PatchOp::Remove { None } else { value };
+    let path = path.to_ascii_lowercase();
+    match path.as_str() {
+        "active" => match value {
+            Some(value) => *active = parse_bool(value)?,
+            None => {
+                return Err(AppError::InvalidInput(
+                    "active cannot be removed".to_string(),
+                ))
+            }
+        },
+        "username" => match optional_string(value)? {
+            Some(username) => attributes.username = username,
+            None => {
+                return Err(AppError::InvalidInput(
+                    "userName cannot be removed".to_string(),
+                ))
+            }
+        },
+        "externalid" => attributes.external_id = optional_string(value)?,
+        "displayname" => attributes.display_name = optional_string(value)?,
+        "emails" => {
+            attributes.email = match value {
+                None | Some(Value::Null) => None,
+                Some(value) => {
+                    let emails: Vec<ScimEmail> = serde_json::from_value(value.clone())
+                        .map_err(|e| AppError::InvalidInput(format!("Invalid emails: {e}")))?;
+                    primary_email(&emails)
+                }
+            }
+        }
+        // e.g. `emails[type eq "work"].value`
+        p if p.starts_with("emails[") && p.ends_with("].value") => {
+            attributes.email = optional_string(value)?
+        }
+        // Attributes Codex does not store (name, title, enterprise extension…).
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn member_ids(db: &Database, members: &[ScimReference]) -> AppResult<Vec<String>> {
+    let mut ids: Vec<String> = Vec::with_capacity(members.len());
+    for member in members {
+        if db.get_user_by_id(&member.value).await?.is_none() {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown member {}",
+                member.value
+            )));
+        }
+        if !ids.contains(&member.value) {
+            ids.push(member.value.clone());
+        }
+    }
+    Ok(ids)
+}
+
+async fn apply_group_attribute(
+    db: &Database,
+    name: &mut String,
+    external_id: &mut Option<String>,
+    members: &mut Vec<String>,
+    op: PatchOp,
+    path: &str,
+    value: Option<&Value>,
+) -> AppResult<()> {
+    let lowered = path.to_ascii_lowercase();
+    match lowered.as_str() {
+        "displayname" => match optional_string(value)? {
+            Some(new_name) if op != PatchOp::Remove => *name = new_name,
+            _ => {
+                return Err(AppError::InvalidInput(
+                    "displayName cannot be removed".to_string(),
+                ))
+            }
+        },
+        "externalid" if op == PatchOp::Remove => *external_id = None,
+        "externalid" => *external_id = optional_string(value)?,
+        "members" => {
+            let listed: Vec<ScimReference> = match value {
+                None | Some(Value::Null) => Vec::new(),
+                Some(value) => serde_json::from_value(value.clone())
+                    .map_err(|e| AppError::InvalidInput(format!("Invalid members: {e}")))?,
+            };
+            match op {
+                PatchOp::Add => {
+                    for id in member_ids(db, &listed).await? {
+                        if !members.contains(&id) {
+                            members.push(id);
+                        }
+                    }
+                }
+                PatchOp::Replace => *members = member_ids(db, &listed).await?,
+                // Without a value, `remove` clears the attribute.
+                PatchOp::Remove if value.is_none() => members.clear(),
+                PatchOp::Remove => {
+                    members.retain(|id| !listed.iter().any(|m| &m.value == id));
+                }
+            }
+        }
+        // `members[value eq "<user id>"]`
+        p if p.starts_with("members[") && p.ends_with(']') && op == PatchOp::Remove => {
+            let (attribute, user_id) = parse_eq_filter(&path["members[".len()..path.len() - 1])?;
+            if attribute != "value" {
+                return Err(unsupported_filter(path));
+            }
+            members.retain(|id| id != &user_id);
+        }
+        // `id`, `schemas` and `meta` echoed back by some providers, and
+        // extension attributes.
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_eq_filters_case_insensitively() {
+        assert_eq!(
+            ScimService::parse_user_filter(r#"userName eq "ada@example.com""#).unwrap(),
+            ScimUserFilter::UserName("ada@example.com".to_string())
+        );
+        assert_eq!(
+            ScimService::parse_user_filter(r#"externalId EQ "00u1 \"x\"""#).unwrap(),
+            ScimUserFilter::ExternalId("00u1 \"x\"".to_string())
+        );
+        assert_eq!(
+            ScimService::parse_group_filter(r#"displayName eq "Writers""#).unwrap(),
+            ScimGroupFilter::DisplayName("Writers".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_filters() {
+        assert!(ScimService::parse_user_filter(r#"userName sw "a""#).is_err());
+        assert!(ScimService::parse_user_filter(r#"emails eq "a@b.c""#).is_err());
+        assert!(ScimService::parse_user_filter("userName eq ada").is_err());
+        assert!(ScimService::parse_group_filter(r#"userName eq "ada""#).is_err());
+    }
+
+    #[test]
+    fn accepts_string_booleans() {
+        assert!(!parse_bool(&json!("False")).unwrap());
+        assert!(parse_bool(&json!(true)).unwrap());
+        assert!(parse_bool(&json!(1)).is_err());
+    }
+
+    #[test]
+    fn patches_user_attributes_by_path() {
+        let mut attributes = ScimUserAttributes {
+            username: "ada".to_string(),
+            external_id: None,
+            display_name: None,
+            email: Some("old@example.com".to_string()),
+        };
+        let mut active = true;
+        apply_user_attribute(
+            &mut attributes,
+            &mut active,
+            PatchOp::Replace,
+            r#"emails[type eq "work"].value"#,
+            Some(&json!("ada@example.com")),
+        )
+        .unwrap();
+        apply_user_attribute(
+            &mut attributes,
+            &mut active,
+            PatchOp::Replace,
+            "active",
+            Some(&json!("False")),
+        )
+        .unwrap();
+        apply_user_attribute(
+            &mut attributes,
+            &mut active,
+            PatchOp::Replace,
+            "name.givenName",
+            Some(&json!("Ada")),
+        )
+        .unwrap();
+
+        assert_eq!(attributes.email.as_deref(), Some("ada@example.com"));
+        assert!(!active);
+        assert_eq!(attributes.display_name, None);
+    }
+}
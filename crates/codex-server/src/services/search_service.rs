@@ -172,6 +172,16 @@ impl SearchIndex {
         }
     }
 
+    /// An index kept on disk under `base_dir`, one subdirectory per vault.
+    pub fn in_dir(base_dir: impl Into<PathBuf>) -> Self {
+        let dir = base_dir.into();
+        std::fs::create_dir_all(&dir).ok();
+        Self {
+            vaults: Arc::new(RwLock::new(HashMap::new())),
+            base_dir: Some(dir),
+        }
+    }
+
     fn open_index(&self, vault_id: &str, schema: Schema) -> AppResult<Index> {
         match &self.base_dir {
             Some(base) => {
@@ -365,6 +375,19 @@ impl SearchIndex {
         query: &str,
         page: usize,
         page_size: usize,
+    ) -> AppResult<PagedSearchResult> {
+        self.search_visible(vault_id, query, page, page_size, |_| true)
+    }
+
+    /// Like [`Self::search`], but drops results whose path fails `visible`
+    /// before paging, so counts and pages only cover what the caller may see.
+    pub fn search_visible(
+        &self,
+        vault_id: &str,
+        query: &str,
+        page: usize,
+        page_size: usize,
+        visible: impl Fn(&str) -> bool,
     ) -> AppResult<PagedSearchResult> {
         use tantivy::DocAddress;
 
@@ -506,6 +529,8 @@ impl SearchIndex {
             results = Self::fallback_disk_search(&vault_path, query)?;
         }
 
+        results.retain(|result| visible(&result.path));
+
         // ── Sort descending by score ──────────────────────────────────────────
         results.par_sort_unstable_by(|a, b| {
             b.score
@@ -52,7 +52,7 @@ fn generate_minimal_template(schema: &crate::models::schema::EntityTypeSchema) -
             writeln!(fm, "  - {label}").unwrap();
         }
     }
-    for field in &schema.fields {
+    for field in schema.fields.iter().filter(|f| f.computed.is_none()) {
         let default_val = field
             .default
             .as_ref()
@@ -95,6 +95,8 @@ mod tests {
             labels,
             display_field: None,
             show_on_create: vec![],
+            extends: None,
+            mixins: vec![],
             fields,
         }
     }
@@ -115,6 +117,7 @@ mod tests {
             target_label: None,
             relation: None,
             description: None,
+            computed: None,
         }
     }
 
@@ -247,7 +250,7 @@ mod tests {
     #[tokio::test]
     async fn test_get_template_returns_error_for_unknown_type() {
         let registry = crate::services::schema_service::EntityTypeRegistry::new();
-        let result = TemplateService::get_template(&registry, "ghost_type", "").await;
+        let result = TemplateService::get_template(&registry, "ghost_type", Path::new("")).await;
         assert!(result.is_err(), "missing entity type should return error");
     }
 
@@ -264,13 +267,16 @@ mod tests {
             labels: vec!["graphable".into()],
             display_field: Some("full_name".into()),
             show_on_create: vec![],
+            extends: None,
+            mixins: vec![],
             fields: vec![make_field("full_name", FieldType::String, None)],
         };
         registry.register(schema).await;
 
-        let content = TemplateService::get_template(&registry, "character", "/nonexistent/dir")
-            .await
-            .expect("should return generated fallback");
+        let content =
+            TemplateService::get_template(&registry, "character", Path::new("/nonexistent/dir"))
+                .await
+                .expect("should return generated fallback");
         assert!(
             content.contains("codex_type:"),
             "fallback should include type field"
@@ -301,14 +307,15 @@ mod tests {
             labels: vec![],
             display_field: None,
             show_on_create: vec![],
+            extends: None,
+            mixins: vec![],
             fields: vec![],
         };
         registry.register(schema).await;
 
-        let content =
-            TemplateService::get_template(&registry, "character", temp.path().to_str().unwrap())
-                .await
-                .expect("should read template from disk");
+        let content = TemplateService::get_template(&registry, "character", temp.path())
+            .await
+            .expect("should read template from disk");
         assert_eq!(content, tmpl_content);
     }
 }
@@ -0,0 +1,597 @@
+use crate::error::{AppError, AppResult};
+use crate::models::timeline::{
+    CalendarBody, CalendarFileError, CalendarInfo, CalendarList, CalendarMonth, CalendarToml,
+    Timeline, TimelineDate, TimelineEvent, TimelineRelation, TimelineUnparsed,
+};
+use crate::services::entity_service::Entity;
+use crate::services::relation_service::Relation;
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Folder inside a vault holding its calendar definitions, one per file.
+pub const VAULT_CALENDARS_DIR: &str = ".codex/calendars";
+
+/// Id of the built-in calendar, used when a timeline names none.
+pub const GREGORIAN: &str = "gregorian";
+
+/// Fields tried, in order, for an entity's start date when none are given.
+/// The entity type's `date` fields are tried after these.
+const DEFAULT_START_FIELDS: &[&str] = &["date", "start_date", "start"];
+const DEFAULT_END_FIELDS: &[&str] = &["end_date", "end"];
+
+const GREGORIAN_MONTHS: [(&str, u32); 12] = [
+    ("January", 31),
+    ("February", 28),
+    ("March", 31),
+    ("April", 30),
+    ("May", 31),
+    ("June", 30),
+    ("July", 31),
+    ("August", 31),
+    ("September", 30),
+    ("October", 31),
+    ("November", 30),
+    ("December", 31),
+];
+
+/// A calendar that dates can be read in and ordered by.
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    info: CalendarInfo,
+}
+
+impl Calendar {
+    pub fn gregorian() -> Self {
+        let months: Vec<CalendarMonth> = GREGORIAN_MONTHS
+            .iter()
+            .map(|(name, days)| CalendarMonth {
+                name: name.to_string(),
+                days: *days,
+            })
+            .collect();
+        Self {
+            info: CalendarInfo {
+                id: GREGORIAN.to_string(),
+                name: "Gregorian".to_string(),
+                era: None,
+                year_length: months.iter().map(|m| m.days).sum(),
+                months,
+                weekdays: [
+                    "Monday",
+                    "Tuesday",
+                    "Wednesday",
+                    "Thursday",
+                    "Friday",
+                    "Saturday",
+                    "Sunday",
+                ]
+                .map(str::to_string)
+                .to_vec(),
+                current: Some(chrono::Utc::now().date_naive().to_string()),
+            },
+        }
+    }
+
+    pub fn from_toml(id: &str, body: CalendarBody) -> Result<Self, String> {
+        if body.months.is_empty() {
+            return Err("A calendar needs at least one month".to_string());
+        }
+        if let Some(month) = body.months.iter().find(|m| m.days == 0) {
+            return Err(format!("Month '{}' has no days", month.name));
+        }
+        for (i, month) in body.months.iter().enumerate() {
+            if month.name.trim().is_empty() || month.name.trim().parse::<i64>().is_ok() {
+                return Err(format!("Month {} needs a name", i + 1));
+            }
+            if body.months[..i]
+                .iter()
+                .any(|m| m.name.eq_ignore_ascii_case(&month.name))
+            {
+                return Err(format!("Month '{}' is defined twice", month.name));
+            }
+        }
+        let calendar = Self {
+            info: CalendarInfo {
+                id: id.to_string(),
+                name: body.name,
+                era: body.era.filter(|era| !era.trim().is_empty()),
+                year_length: body.months.iter().map(|m| m.days).sum(),
+                months: body.months,
+                weekdays: body.weekdays,
+                current: body.current.filter(|current| !current.trim().is_empty()),
+            },
+        };
+        if let Some(current) = &calendar.info.current {
+            if calendar.parse(current).is_none() {
+                return Err(format!("Current date '{current}' is not in the calendar"));
+            }
+        }
+        Ok(calendar)
+    }
+
+    pub fn info(&self) -> &CalendarInfo {
+        &self.info
+    }
+
+    /// The calendar's current date, or `None` when it has none.
+    pub fn today(&self) -> Option<TimelineDate> {
+        self.parse(self.info.current.as_deref()?)
+    }
+
+    fn is_gregorian(&self) -> bool {
+        self.info.id == GREGORIAN
+    }
+
+    /// Read a date written as `YYYY`, `YYYY-MM` or `YYYY-MM-DD` (years may
+    /// be negative), or with a month name: `15 Alturiak 1492`,
+    /// `Alturiak 15, 1492` or `Alturiak 1492`. A trailing era is ignored.
+    pub fn parse(&self, raw: &str) -> Option<TimelineDate> {
+        let mut text = raw.trim();
+        if self.is_gregorian() && text.len() > 10 && text.is_char_boundary(10) {
+            // Datetimes are placed on their day
+            if matches!(text.as_bytes()[10], b'T' | b' ') {
+                text = &text[..10];
+            }
+        }
+        if let Some(era) = &self.info.era {
+            if text.len() > era.len()
+                && text.is_char_boundary(text.len() - era.len())
+                && text[text.len() - era.len()..].eq_ignore_ascii_case(era)
+            {
+                text = text[..text.len() - era.len()].trim_end();
+            }
+        }
+        let (year, month, day) = parse_numeric(text).or_else(|| self.parse_named(text))?;
+        self.date(raw, year, month, day)
+    }
+
+    fn parse_named(&self, text: &str) -> Option<(i64, Option<u32>, Option<u32>)> {
+        let tokens: Vec<&str> = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|t| !t.is_empty())
+            .collect();
+        // Prefer the longest name, so "Deep Winter" wins over "Winter".
+        let (month, start, len) = self
+            .info
+            .months
+            .iter()
+            .enumerate()
+            .filter_map(|(i, m)| {
+                let name: Vec<&str> = m.name.split_whitespace().collect();
+                let start = (0..=tokens.len().checked_sub(name.len())?).find(|&start| {
+                    name.iter()
+                        .zip(&tokens[start..])
+                        .all(|(a, b)| a.eq_ignore_ascii_case(b))
+                })?;
+                Some((i as u32 + 1, start, name.len()))
+            })
+            .max_by_key(|(_, _, len)| *len)?;
+
+        let numbers: Option<Vec<i64>> = tokens[..start]
+            .iter()
+            .chain(&tokens[start + len..])
+            .map(|t| {
+                t.trim_end_matches(|c: char| c.is_ascii_alphabetic())
+                    .parse()
+                    .ok()
+            })
+            .collect();
+        match numbers?.as_slice() {
+            [year] => Some((*year, Some(month), None)),
+            [day, year] => Some((*year, Some(month), Some(u32::try_from(*day).ok()?))),
+            _ => None,
+        }
+    }
+
+    /// Place a date in the calendar, or `None` when the month or day does
+    /// not exist in it or the year is too large to count days in.
+    fn date(
+        &self,
+        raw: &str,
+        year: i64,
+        month: Option<u32>,
+        day: Option<u32>,
+    ) -> Option<TimelineDate> {
+        let month_index = month.unwrap_or(1).checked_sub(1)? as usize;
+        let month_info = self.info.months.get(month_index)?;
+        let (ordinal, weekday) = if self.is_gregorian() {
+            let date = NaiveDate::from_ymd_opt(
+                i32::try_from(year).ok()?,
+                month.unwrap_or(1),
+                day.unwrap_or(1),
+            )?;
+            let weekday = date.weekday().num_days_from_monday() as usize;
+            (date.num_days_from_ce() as i64, weekday)
+        } else {
+            let day_index = day.unwrap_or(1).checked_sub(1)?;
+            if day_index >= month_info.days {
+                return None;
+            }
+            let before: u32 = self.info.months[..month_index].iter().map(|m| m.days).sum();
+            let ordinal = year
+                .checked_mul(self.info.year_length as i64)?
+                .checked_add((before + day_index) as i64)?;
+            let weekdays = self.info.weekdays.len().max(1) as i64;
+            (ordinal, ordinal.rem_euclid(weekdays) as usize)
+        };
+
+        let mut label = match (month, day) {
+            (Some(_), Some(day)) => format!("{day} {} {year}", month_info.name),
+            (Some(_), None) => format!("{} {year}", month_info.name),
+            _ => year.to_string(),
+        };
+        if let Some(era) = &self.info.era {
+            label = format!("{label} {era}");
+        }
+        Some(TimelineDate {
+            raw: raw.to_string(),
+            ordinal,
+            year,
+            month,
+            day,
+            label,
+            weekday: day.and(self.info.weekdays.get(weekday).cloned()),
+        })
+    }
+}
+
+fn parse_numeric(text: &str) -> Option<(i64, Option<u32>, Option<u32>)> {
+    let (sign, rest) = match text.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, text),
+    };
+    let parts: Vec<&str> = rest.split('-').collect();
+    if parts.len() > 3
+        || parts
+            .iter()
+            .any(|p| p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return None;
+    }
+    let year = sign * parts[0].parse::<i64>().ok()?;
+    let month = parts.get(1).map(|p| p.parse()).transpose().ok()?;
+    let day = parts.get(2).map(|p| p.parse()).transpose().ok()?;
+    Some((year, month, day))
+}
+
+/// Which entities a timeline shows, and how their dates are read.
+#[derive(Debug, Clone, Default)]
+pub struct TimelineOptions {
+    /// Fields tried in order for the start date; defaults apply when empty
+    pub start_fields: Vec<String>,
+    pub end_fields: Vec<String>,
+    pub types: Vec<String>,
+    pub labels: Vec<String>,
+    /// Keep events that end on or after this ordinal
+    pub from: Option<i64>,
+    /// Keep events that start on or before this ordinal
+    pub to: Option<i64>,
+}
+
+impl TimelineOptions {
+    /// Options from comma-separated query parameters. `from` and `to` are
+    /// read in `calendar`.
+    pub fn from_query(
+        calendar: &Calendar,
+        start: Option<&str>,
+        end: Option<&str>,
+        types: Option<&str>,
+        labels: Option<&str>,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> AppResult<Self> {
+        let list = |value: Option<&str>| {
+            value
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let bound = |value: Option<&str>| {
+            value
+                .map(|value| {
+                    calendar
+                        .parse(value)
+                        .map(|date| date.ordinal)
+                        .ok_or_else(|| {
+                            AppError::InvalidInput(format!(
+                                "'{value}' is not a date in the {} calendar",
+                                calendar.info().name
+                            ))
+                        })
+                })
+                .transpose()
+        };
+        Ok(Self {
+            start_fields: list(start),
+            end_fields: list(end),
+            types: list(types),
+            labels: list(labels),
+            from: bound(from)?,
+            to: bound(to)?,
+        })
+    }
+}
+
+pub struct TimelineService;
+
+impl TimelineService {
+    /// The built-in calendar followed by the vault's own, by id.
+    pub fn calendars(vault_path: &str) -> CalendarList {
+        let mut list = CalendarList {
+            calendars: vec![Calendar::gregorian().info],
+            errors: Vec::new(),
+        };
+        for path in calendar_files(vault_path) {
+            match load_calendar(&path) {
+                Ok(calendar) => list.calendars.push(calendar.info),
+                Err(message) => list.errors.push(CalendarFileError {
+                    file: file_name(&path),
+                    message,
+                }),
+            }
+        }
+        list
+    }
+
+    pub fn calendar(vault_path: &str, id: &str) -> AppResult<Calendar> {
+        if id == GREGORIAN {
+            return Ok(Calendar::gregorian());
+        }
+        let path = calendar_files(vault_path)
+            .into_iter()
+            .find(|p| p.file_stem().is_some_and(|stem| stem == id))
+            .ok_or_else(|| AppError::NotFound(format!("Calendar '{id}' not found")))?;
+        load_calendar(&path).map_err(AppError::InvalidInput)
+    }
+
+    /// Place every entity with a readable start date on a timeline, sorted
+    /// by start and then end. `date_fields` maps entity types to the keys of
+    /// their `date` fields.
+    pub fn build(
+        entities: &[Entity],
+        relations: &[Relation],
+        calendar: &Calendar,
+        options: &TimelineOptions,
+        date_fields: &HashMap<String, Vec<String>>,
+    ) -> Timeline {
+        let titles: HashMap<&str, String> = entities
+            .iter()
+            .map(|e| (e.id.as_str(), title(&e.path)))
+            .collect();
+        let mut relations_by_entity: HashMap<&str, Vec<TimelineRelation>> = HashMap::new();
+        for relation in relations {
+            let Some(title) = titles.get(relation.to_entity_id.as_str()) else {
+                continue;
+            };
+            relations_by_entity
+                .entry(relation.from_entity_id.as_str())
+                .or_default()
+                .push(TimelineRelation {
+                    relation_id: relation.id.clone(),
+                    relation_type: relation.relation_type.clone(),
+                    direction: relation.direction.clone(),
+                    entity_id: relation.to_entity_id.clone(),
+                    title: title.clone(),
+                });
+        }
+
+        let mut timeline = Timeline {
+            calendar: calendar.info.clone(),
+            events: Vec::new(),
+            unparsed: Vec::new(),
+        };
+        for entity in entities {
+            if !options.types.is_empty() && !options.types.contains(&entity.entity_type) {
+                continue;
+            }
+            let labels = entity.labels_vec();
+            if !options.labels.is_empty() && !labels.iter().any(|l| options.labels.contains(l)) {
+                continue;
+            }
+            let fields = entity.fields_map();
+            let start_keys: Vec<&str> = if options.start_fields.is_empty() {
+                let schema_dates = date_fields.get(&entity.entity_type).into_iter().flatten();
+                DEFAULT_START_FIELDS
+                    .iter()
+                    .copied()
+                    .chain(schema_dates.map(String::as_str))
+                    .collect()
+            } else {
+                options.start_fields.iter().map(String::as_str).collect()
+            };
+            let end_keys: Vec<&str> = if options.end_fields.is_empty() {
+                DEFAULT_END_FIELDS.to_vec()
+            } else {
+                options.end_fields.iter().map(String::as_str).collect()
+            };
+
+            let Some((start_field, start_value)) = first_value(&fields, &start_keys) else {
+                continue;
+            };
+            let mut unparsed = |field: &str, value: &str| {
+                timeline.unparsed.push(TimelineUnparsed {
+                    entity_id: entity.id.clone(),
+                    path: entity.path.clone(),
+                    field: field.to_string(),
+                    value: value.to_string(),
+                })
+            };
+            let Some(start) = calendar.parse(&start_value) else {
+                unparsed(start_field, &start_value);
+                continue;
+            };
+            let end = first_value(&fields, &end_keys).and_then(|(field, value)| {
+                match calendar.parse(&value) {
+                    Some(date) => Some((field, date)),
+                    None => {
+                        unparsed(field, &value);
+                        None
+                    }
+                }
+            });
+
+            let last = end.as_ref().map_or(start.ordinal, |(_, d)| d.ordinal);
+            if options.from.is_some_and(|from| last < from)
+                || options.to.is_some_and(|to| start.ordinal > to)
+            {
+                continue;
+            }
+            timeline.events.push(TimelineEvent {
+                entity_id: entity.id.clone(),
+                path: entity.path.clone(),
+                title: title(&entity.path),
+                entity_type: entity.entity_type.clone(),
+                labels,
+                start_field: start_field.to_string(),
+                start,
+                end_field: end.as_ref().map(|(field, _)| field.to_string()),
+                end: end.map(|(_, date)| date),
+                relations: relations_by_entity
+                    .remove(entity.id.as_str())
+                    .unwrap_or_default(),
+            });
+        }
+
+        timeline.events.sort_by(|a, b| {
+            let end = |e: &TimelineEvent| e.end.as_ref().map_or(e.start.ordinal, |d| d.ordinal);
+            (a.start.ordinal, end(a), &a.path).cmp(&(b.start.ordinal, end(b), &b.path))
+        });
+        timeline
+    }
+}
+
+/// The first of `keys` set to a string or number, as text.
+fn first_value<'a>(fields: &serde_json::Value, keys: &[&'a str]) -> Option<(&'a str, String)> {
+    keys.iter().find_map(|key| match fields.get(*key)? {
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some((*key, s.clone())),
+        serde_json::Value::Number(n) => Some((*key, n.to_string())),
+        _ => None,
+    })
+}
+
+fn title(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The `*.toml` files directly inside a vault's calendars folder, by name.
+fn calendar_files(vault_path: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(Path::new(vault_path).join(VAULT_CALENDARS_DIR)) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "toml"))
+        .filter(|p| p.file_stem().is_some_and(|stem| stem != GREGORIAN))
+        .collect();
+    files.sort();
+    files
+}
+
+fn load_calendar(path: &Path) -> Result<Calendar, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {e}", file_name(path)))?;
+    let parsed: CalendarToml = toml::from_str(&content)
+        .map_err(|e| format!("TOML parse error in {}: {e}", file_name(path)))?;
+    let id = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Calendar::from_toml(&id, parsed.calendar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn harptos() -> Calendar {
+        let body: CalendarToml = toml::from_str(
+            r#"
+[calendar]
+name = "Harptos"
+era = "DR"
+weekdays = ["First", "Second", "Third"]
+months = [
+    { name = "Hammer", days = 30 },
+    { name = "Deep Winter", days = 1 },
+    { name = "Alturiak", days = 30 },
+]
+"#,
+        )
+        .unwrap();
+        Calendar::from_toml("harptos", body.calendar).unwrap()
+    }
+
+    #[test]
+    fn parses_numeric_and_named_dates() {
+        let calendar = harptos();
+        assert_eq!(calendar.info().year_length, 61);
+
+        let date = calendar.parse("15 Alturiak 1492 DR").unwrap();
+        assert_eq!((date.year, date.month, date.day), (1492, Some(3), Some(15)));
+        assert_eq!(date.ordinal, 1492 * 61 + 31 + 14);
+        assert_eq!(date.label, "15 Alturiak 1492 DR");
+        assert_eq!(calendar.parse("1492-03-15").unwrap().ordinal, date.ordinal);
+        assert_eq!(calendar.parse("Alturiak 15th, 1492").unwrap(), {
+            let mut d = date.clone();
+            d.raw = "Alturiak 15th, 1492".into();
+            d
+        });
+
+        let winter = calendar.parse("1 Deep Winter 1492").unwrap();
+        assert_eq!(winter.month, Some(2));
+        assert_eq!(
+            calendar.parse("Hammer 1492").unwrap().label,
+            "Hammer 1492 DR"
+        );
+        assert_eq!(calendar.parse("-300").unwrap().ordinal, -300 * 61);
+        assert_eq!(calendar.parse("1492").unwrap().weekday, None);
+        assert!(calendar.parse("2 Deep Winter 1492").is_none());
+        assert!(calendar.parse("1492-04-01").is_none());
+        assert!(calendar.parse("soon").is_none());
+        assert!(calendar.parse("9223372036854775807").is_none());
+        assert!(calendar.parse("-9223372036854775807-02-01").is_none());
+    }
+
+    #[test]
+    fn gregorian_dates_follow_the_real_calendar() {
+        let calendar = Calendar::gregorian();
+        let date = calendar.parse("2024-02-29T10:00:00Z").unwrap();
+        assert_eq!(date.label, "29 February 2024");
+        assert_eq!(date.weekday.as_deref(), Some("Thursday"));
+        assert!(calendar.parse("2023-02-29").is_none());
+        assert_eq!(
+            calendar.parse("March 2024").unwrap().ordinal,
+            date.ordinal + 1
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_calendars() {
+        let body = |months: &str| {
+            toml::from_str::<CalendarToml>(&format!("[calendar]\nname = \"X\"\nmonths = {months}"))
+                .unwrap()
+                .calendar
+        };
+        assert!(Calendar::from_toml("x", body("[]")).is_err());
+        assert!(Calendar::from_toml("x", body("[{ name = \"A\", days = 0 }]")).is_err());
+        assert!(Calendar::from_toml(
+            "x",
+            body("[{ name = \"A\", days = 1 }, { name = \"a\", days = 2 }]")
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::models::trash::{
+    BulkTrashResult, TrashItem, TrashOperationFailure, TrashOperationSuccess,
+};
+use crate::services::file_service::{FileService, RenameStrategy};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// Keeps a vault's `.trash/` folder and the `trash_items` index in step.
+///
+/// The files and their `.meta.json` sidecars remain the source of truth;
+/// the index adds who deleted what. Items trashed without going through
+/// this service (plugins, snapshot restores) are indexed lazily by `list`.
+pub struct TrashService;
+
+impl TrashService {
+    /// Move a file to the trash and index it.
+    pub async fn trash(
+        db: &Database,
+        vault_id: &str,
+        vault_path: &str,
+        file_path: &str,
+        deleted_by: Option<&str>,
+    ) -> AppResult<TrashItem> {
+        let mut item = FileService::delete_file(vault_path, file_path)?;
+        db.insert_trash_item(vault_id, &item, deleted_by).await?;
+        item.deleted_by = deleted_by.map(str::to_string);
+        Ok(item)
+    }
+
+    /// Items in the vault's trash, newest first.
+    ///
+    /// Index rows whose file has disappeared are dropped and files missing
+    /// from the index are added, so the result always mirrors the disk.
+    pub async fn list(
+        db: &Database,
+        vault_id: &str,
+        vault_path: &str,
+    ) -> AppResult<Vec<TrashItem>> {
+        let on_disk = FileService::list_trash(vault_path)?;
+        let mut indexed: HashMap<String, TrashItem> = db
+            .list_trash_items(vault_id)
+            .await?
+            .into_iter()
+            .map(|item| (item.trash_name.clone(), item))
+            .collect();
+
+        let mut items = Vec::with_capacity(on_disk.len());
+        for disk_item in on_disk {
+            match indexed.remove(&disk_item.trash_name) {
+                Some(item) => items.push(item),
+                None => {
+                    db.insert_trash_item(vault_id, &disk_item, None).await?;
+                    items.push(disk_item);
+                }
+            }
+        }
+        for stale in indexed.into_keys() {
+            db.delete_trash_item(vault_id, &stale).await?;
+        }
+
+        items.sort_by(|a, b| b.trashed_at.cmp(&a.trashed_at));
+        Ok(items)
+    }
+
+    /// Restore one item, returning the path it was restored to.
+    pub async fn restore(
+        db: &Database,
+        vault_id: &str,
+        vault_path: &str,
+        trash_name: &str,
+        strategy: RenameStrategy,
+    ) -> AppResult<String> {
+        let path = FileService::restore_file(vault_path, trash_name, strategy)?;
+        db.delete_trash_item(vault_id, trash_name).await?;
+        Ok(path)
+    }
+
+    /// Permanently delete one item.
+    pub async fn purge(
+        db: &Database,
+        vault_id: &str,
+        vault_path: &str,
+        trash_name: &str,
+    ) -> AppResult<()> {
+        FileService::delete_from_trash(vault_path, trash_name)?;
+        db.delete_trash_item(vault_id, trash_name).await?;
+        Ok(())
+    }
+
+    pub async fn restore_many(
+        db: &Database,
+        vault_id: &str,
+        vault_path: &str,
+        trash_names: &[String],
+        strategy: RenameStrategy,
+    ) -> BulkTrashResult {
+        let mut result = BulkTrashResult::default();
+        for trash_name in trash_names {
+            match Self::restore(db, vault_id, vault_path, trash_name, strategy).await {
+                Ok(path) => result.succeeded.push(TrashOperationSuccess {
+                    trash_name: trash_name.clone(),
+                    path: Some(path),
+                }),
+                Err(e) => result.failed.push(TrashOperationFailure {
+                    trash_name: trash_name.clone(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+        result
+    }
+
+    /// Purge the named items, or every item when `trash_names` is `None`,
+    /// optionally limited to items deleted before `older_than`.
+    pub async fn purge_many(
+        db: &Database,
+        vault_id: &str,
+        vault_path: &str,
+        trash_names: Option<&[String]>,
+        older_than: Option<DateTime<Utc>>,
+    ) -> AppResult<BulkTrashResult> {
+        let items = Self::list(db, vault_id, vault_path).await?;
+        let deleted_at: HashMap<&str, Option<DateTime<Utc>>> = items
+            .iter()
+            .map(|item| (item.trash_name.as_str(), parse_trashed_at(&item.trashed_at)))
+            .collect();
+
+        let selected: Vec<String> = match trash_names {
+            Some(names) => names.to_vec(),
+            None => items.iter().map(|item| item.trash_name.clone()).collect(),
+        };
+
+        let mut result = BulkTrashResult::default();
+        for trash_name in selected {
+            if let Some(cutoff) = older_than {
+                // Items with an unknown deletion time are never purged by age.
+                let old_enough = deleted_at
+                    .get(trash_name.as_str())
+                    .copied()
+                    .flatten()
+                    .is_some_and(|at| at <= cutoff);
+                if !old_enough {
+                    continue;
+                }
+            }
+            match Self::purge(db, vault_id, vault_path, &trash_name).await {
+                Ok(()) => result.succeeded.push(TrashOperationSuccess {
+                    trash_name,
+                    path: None,
+                }),
+                Err(e) => result.failed.push(TrashOperationFailure {
+                    trash_name,
+                    error: e.to_string(),
+                }),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Purge items older than `retention_days` from every vault.
+    /// Returns the number of purged items.
+    pub async fn purge_expired(db: &Database, retention_days: u64) -> AppResult<usize> {
+        let cutoff = Utc::now() - Duration::days(retention_days as i64);
+        let mut purged = 0;
+        for vault in db.list_vaults().await? {
+            if !std::path::Path::new(&vault.path).exists() {
+                continue;
+            }
+            match Self::purge_many(db, &vault.id, &vault.path, None, Some(cutoff)).await {
+                Ok(result) => {
+                    for failure in &result.failed {
+                        warn!(
+                            "Failed to purge {} from trash of vault {}: {}",
+                            failure.trash_name, vault.id, failure.error
+                        );
+                    }
+                    purged += result.succeeded.len();
+                }
+                Err(e) => warn!("Trash retention failed for vault {}: {}", vault.id, e),
+            }
+        }
+        if purged > 0 {
+            info!("Purged {} expired trash item(s)", purged);
+        }
+        Ok(purged)
+    }
+}
+
+fn parse_trashed_at(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
@@ -0,0 +1,327 @@
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::upload::UploadSession;
+use crate::models::{CreateUploadSessionRequest, UploadSessionResponse, UploadedRange};
+use crate::services::file_service::FileService;
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::Read;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Lifecycle of resumable uploads: sessions live in `upload_sessions`,
+/// chunks are written at their offsets into a temp file and checked
+/// against their SHA-256, and the whole file is verified before it is
+/// moved into the vault.
+pub struct UploadService;
+
+impl UploadService {
+    pub async fn create(
+        db: &Database,
+        vault_id: &str,
+        vault_path: &str,
+        owner_id: Option<&str>,
+        req: &CreateUploadSessionRequest,
+        ttl_hours: u64,
+    ) -> AppResult<UploadSessionResponse> {
+        let sha256 = req.sha256.as_deref().map(normalize_sha256).transpose()?;
+        let now = Utc::now();
+        let session = UploadSession {
+            id: Uuid::new_v4().to_string(),
+            vault_id: vault_id.to_string(),
+            owner_id: owner_id.map(str::to_string),
+            filename: req.filename.clone(),
+            path: req.path.clone(),
+            total_size: req.total_size,
+            sha256,
+            created_at: now,
+            expires_at: now + Duration::hours(ttl_hours as i64),
+        };
+
+        FileService::create_upload_session_temp(vault_path, &session.id)?;
+        if let Err(e) = db.create_upload_session(&session).await {
+            let _ = FileService::delete_upload_session_temp(vault_path, &session.id);
+            return Err(e);
+        }
+
+        Ok(UploadSessionResponse {
+            session_id: session.id,
+            uploaded_bytes: 0,
+            total_size: session.total_size,
+            expires_at: Some(session.expires_at),
+            received: Vec::new(),
+        })
+    }
+
+    /// Look up a live session of `vault_id` that `user_id` may write to.
+    pub async fn open(
+        db: &Database,
+        vault_id: &str,
+        session_id: &str,
+        user_id: Option<&str>,
+    ) -> AppResult<UploadSession> {
+        let session = db.get_upload_session(session_id).await?;
+        if session.vault_id != vault_id {
+            return Err(AppError::NotFound("Upload session not found".to_string()));
+        }
+        if session.is_expired() {
+            return Err(AppError::NotFound("Upload session has expired".to_string()));
+        }
+        if session.owner_id.is_some() && session.owner_id.as_deref() != user_id {
+            return Err(AppError::Forbidden(
+                "Upload session belongs to another user".to_string(),
+            ));
+        }
+        Ok(session)
+    }
+
+    /// Store a chunk at `offset`, or after the furthest received byte when
+    /// no offset is given. Returns the number of bytes received so far.
+    pub async fn write_chunk(
+        db: &Database,
+        vault_path: &str,
+        session: &UploadSession,
+        offset: Option<u64>,
+        bytes: &[u8],
+        sha256: Option<&str>,
+        ttl_hours: u64,
+    ) -> AppResult<u64> {
+        if let Some(expected) = sha256 {
+            let expected = normalize_sha256(expected)?;
+            if hex::encode(Sha256::digest(bytes)) != expected {
+                return Err(AppError::InvalidInput(
+                    "Chunk checksum mismatch; resend the chunk".to_string(),
+                ));
+            }
+        }
+
+        let chunks = db.list_upload_chunks(&session.id).await?;
+        let offset = offset.unwrap_or_else(|| {
+            chunks
+                .iter()
+                .map(|(offset, length)| offset + length)
+                .max()
+                .unwrap_or(0)
+        });
+        let end = offset + bytes.len() as u64;
+        if session.total_size.is_some_and(|total| end > total) {
+            return Err(AppError::InvalidInput(
+                "Chunk extends past the declared file size".to_string(),
+            ));
+        }
+
+        FileService::write_upload_chunk_at(vault_path, &session.id, offset, bytes)?;
+        let chunk_sha = sha256.map(str::to_ascii_lowercase);
+        db.record_upload_chunk(
+            &session.id,
+            offset,
+            bytes.len() as u64,
+            chunk_sha.as_deref(),
+        )
+        .await?;
+        db.extend_upload_session(&session.id, Utc::now() + Duration::hours(ttl_hours as i64))
+            .await?;
+
+        let mut chunks = chunks;
+        chunks.retain(|(existing, _)| *existing != offset);
+        chunks.push((offset, bytes.len() as u64));
+        Ok(received_bytes(&merge_ranges(&chunks)))
+    }
+
+    pub async fn status(
+        db: &Database,
+        session: &UploadSession,
+    ) -> AppResult<UploadSessionResponse> {
+        let received = merge_ranges(&db.list_upload_chunks(&session.id).await?);
+        Ok(UploadSessionResponse {
+            session_id: session.id.clone(),
+            uploaded_bytes: received_bytes(&received),
+            total_size: session.total_size,
+            expires_at: Some(session.expires_at),
+            received,
+        })
+    }
+
+    /// Check that every byte has arrived and that the file matches
+    /// `sha256` (or the checksum given when the session was created).
+    /// A checksum mismatch discards the session.
+    pub async fn verify_complete(
+        db: &Database,
+        vault_path: &str,
+        session: &UploadSession,
+        sha256: Option<&str>,
+    ) -> AppResult<()> {
+        let received = merge_ranges(&db.list_upload_chunks(&session.id).await?);
+        let end = match received.as_slice() {
+            [] => 0,
+            [range] if range.offset == 0 => range.length,
+            _ => {
+                return Err(AppError::InvalidInput(format!(
+                    "Upload is incomplete: {} separate ranges received",
+                    received.len()
+                )))
+            }
+        };
+        if let Some(total) = session.total_size {
+            if end != total {
+                return Err(AppError::InvalidInput(format!(
+                    "Upload is incomplete: {} of {} bytes received",
+                    end, total
+                )));
+            }
+        }
+
+        let temp_path = FileService::upload_session_temp_path(vault_path, &session.id);
+        let expected = match sha256 {
+            Some(sha) => Some(normalize_sha256(sha)?),
+            None => session.sha256.clone(),
+        };
+        let actual = tokio::task::spawn_blocking(move || -> AppResult<String> {
+            // Drop anything beyond the last received byte (e.g. a chunk that
+            // was later resent shorter).
+            let file = std::fs::OpenOptions::new().write(true).open(&temp_path)?;
+            file.set_len(end)?;
+            drop(file);
+            sha256_file(&temp_path)
+        })
+        .await
+        .map_err(|e| AppError::InternalError(format!("Checksum task failed: {}", e)))??;
+
+        if let Some(expected) = expected {
+            if actual != expected {
+                Self::discard(db, vault_path, &session.id).await?;
+                return Err(AppError::InvalidInput(
+                    "File checksum mismatch; the upload was discarded".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a session's temp file and its records.
+    pub async fn discard(db: &Database, vault_path: &str, session_id: &str) -> AppResult<()> {
+        FileService::delete_upload_session_temp(vault_path, session_id)?;
+        db.delete_upload_session(session_id).await
+    }
+
+    /// Delete expired sessions, plus temp files idle for longer than the
+    /// TTL that no session owns (e.g. interrupted multipart uploads).
+    /// Returns the number of temp files removed.
+    pub async fn sweep(db: &Database, ttl_hours: u64) -> AppResult<usize> {
+        let mut removed = 0;
+        for session in db.list_expired_upload_sessions(Utc::now()).await? {
+            if let Ok(vault) = db.get_vault(&session.vault_id).await {
+                let temp_path = FileService::upload_session_temp_path(&vault.path, &session.id);
+                if temp_path.exists() {
+                    removed += 1;
+                }
+                if let Err(e) = FileService::delete_upload_session_temp(&vault.path, &session.id) {
+                    warn!("Failed to remove upload {}: {}", session.id, e);
+                    continue;
+                }
+            }
+            db.delete_upload_session(&session.id).await?;
+        }
+
+        let idle_limit = std::time::Duration::from_secs(ttl_hours * 3600);
+        for vault in db.list_vaults().await? {
+            let upload_dir = FileService::upload_temp_dir(&vault.path);
+            let Ok(entries) = std::fs::read_dir(&upload_dir) else {
+                continue;
+            };
+            let live: HashSet<String> = db
+                .list_upload_session_ids(&vault.id)
+                .await?
+                .into_iter()
+                .collect();
+            for entry in entries.filter_map(|e| e.ok()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let idle = entry
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .is_some_and(|elapsed| elapsed >= idle_limit);
+                if !live.contains(&name) && idle && std::fs::remove_file(entry.path()).is_ok() {
+                    removed += 1;
+                }
+            }
+        }
+
+        if removed > 0 {
+            info!("Removed {} stale upload temp file(s)", removed);
+        }
+        Ok(removed)
+    }
+}
+
+/// Merge `(offset, length)` chunks into sorted, non-overlapping ranges.
+pub fn merge_ranges(chunks: &[(u64, u64)]) -> Vec<UploadedRange> {
+    let mut sorted: Vec<(u64, u64)> = chunks.iter().copied().filter(|(_, l)| *l > 0).collect();
+    sorted.sort_unstable();
+    let mut merged: Vec<UploadedRange> = Vec::new();
+    for (offset, length) in sorted {
+        match merged.last_mut() {
+            Some(last) if offset <= last.offset + last.length => {
+                let end = (last.offset + last.length).max(offset + length);
+                last.length = end - last.offset;
+            }
+            _ => merged.push(UploadedRange { offset, length }),
+        }
+    }
+    merged
+}
+
+fn received_bytes(ranges: &[UploadedRange]) -> u64 {
+    ranges.iter().map(|r| r.length).sum()
+}
+
+fn normalize_sha256(value: &str) -> AppResult<String> {
+    let value = value.trim().to_ascii_lowercase();
+    if value.len() != 64 || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(AppError::InvalidInput(
+            "SHA-256 must be 64 hexadecimal characters".to_string(),
+        ));
+    }
+    Ok(value)
+}
+
+fn sha256_file(path: &std::path::Path) -> AppResult<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_ranges_joins_overlapping_and_adjacent_chunks() {
+        let merged = merge_ranges(&[(10, 5), (0, 4), (4, 6), (30, 0), (12, 10), (40, 2)]);
+        assert_eq!(
+            merged,
+            vec![
+                UploadedRange {
+                    offset: 0,
+                    length: 22
+                },
+                UploadedRange {
+                    offset: 40,
+                    length: 2
+                },
+            ]
+        );
+        assert_eq!(received_bytes(&merged), 24);
+        assert!(merge_ranges(&[]).is_empty());
+    }
+}
@@ -0,0 +1,528 @@
+//! WebAuthn registration and assertion for passkeys and security keys.
+//!
+//! Only what a relying party needs is implemented: client data and
+//! authenticator data checks, COSE public keys (ES256, EdDSA, RS256) and
+//! signature counters. Attestation statements are not verified — we ask
+//! browsers for `"none"` attestation and trust the key on first use, so any
+//! authenticator model is accepted.
+
+use crate::config::AuthConfig;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::models::webauthn::{WebAuthnChallenge, WebAuthnCredential, WebAuthnPurpose};
+use crate::models::{PublicKeyCredential, WebAuthnCeremony};
+use chrono::{Duration, Utc};
+use ciborium::Value as Cbor;
+use data_encoding::BASE64URL_NOPAD;
+use rand::Rng;
+use ring::signature;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+const COSE_ES256: i64 = -7;
+const COSE_EDDSA: i64 = -8;
+const COSE_RS256: i64 = -257;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+
+fn rejected(reason: &str) -> AppError {
+    AppError::Unauthorized(format!("WebAuthn verification failed: {reason}"))
+}
+
+fn decode_b64url(value: &str, field: &str) -> AppResult<Vec<u8>> {
+    // Some clients pad or use the standard alphabet; accept both.
+    let normalized: String = value
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '+' => '-',
+            '/' => '_',
+            c => c,
+        })
+        .collect();
+    BASE64URL_NOPAD
+        .decode(normalized.as_bytes())
+        .map_err(|_| AppError::InvalidInput(format!("{field} is not valid base64url")))
+}
+
+/// The parts of `authenticatorData` we rely on.
+struct AuthenticatorData<'a> {
+    raw: &'a [u8],
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    attested: Option<AttestedCredential>,
+}
+
+struct AttestedCredential {
+    aaguid: [u8; 16],
+    credential_id: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+fn parse_authenticator_data(raw: &[u8]) -> AppResult<AuthenticatorData<'_>> {
+    if raw.len() < 37 {
+        return Err(rejected("authenticator data is truncated"));
+    }
+    let flags = raw[32];
+    let sign_count = u32::from_be_bytes([raw[33], raw[34], raw[35], raw[36]]);
+
+    let attested = if flags & FLAG_ATTESTED_CREDENTIAL != 0 {
+        let rest = &raw[37..];
+        if rest.len() < 18 {
+            return Err(rejected("attested credential data is truncated"));
+        }
+        let mut aaguid = [0u8; 16];
+        aaguid.copy_from_slice(&rest[..16]);
+        let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+        let rest = &rest[18..];
+        if rest.len() < id_len {
+            return Err(rejected("credential ID is truncated"));
+        }
+        let (credential_id, rest) = rest.split_at(id_len);
+
+        // The COSE key is followed by optional extensions, so its length is
+        // only known after decoding it.
+        let mut reader = rest;
+        ciborium::from_reader::<Cbor, _>(&mut reader)
+            .map_err(|_| rejected("credential public key is not valid CBOR"))?;
+        let key_len = rest.len() - reader.len();
+        Some(AttestedCredential {
+            aaguid,
+            credential_id: credential_id.to_vec(),
+            public_key: rest[..key_len].to_vec(),
+        })
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        raw,
+        rp_id_hash: &raw[..32],
+        flags,
+        sign_count,
+        attested,
+    })
+}
+
+fn cbor_map_get(map: &[(Cbor, Cbor)], key: i64) -> Option<&Cbor> {
+    map.iter()
+        .find(|(k, _)| k.as_integer().is_some_and(|k| i128::from(k) == key as i128))
+        .map(|(_, v)| v)
+}
+
+fn cbor_int(value: Option<&Cbor>) -> Option<i64> {
+    value
+        .and_then(Cbor::as_integer)
+        .and_then(|i| i64::try_from(i128::from(i)).ok())
+}
+
+fn cbor_bytes(value: Option<&Cbor>) -> Option<&[u8]> {
+    value.and_then(Cbor::as_bytes).map(Vec::as_slice)
+}
+
+/// A COSE_Key reduced to what `ring` needs to verify a signature.
+enum CosePublicKey {
+    Es256(Vec<u8>),
+    EdDsa(Vec<u8>),
+    Rs256 { n: Vec<u8>, e: Vec<u8> },
+}
+
+impl CosePublicKey {
+    fn parse(encoded: &[u8]) -> AppResult<(i64, Self)> {
+        let key: Cbor = ciborium::from_reader(encoded)
+            .map_err(|_| rejected("credential public key is not valid CBOR"))?;
+        let map = key
+            .as_map()
+            .ok_or_else(|| rejected("credential public key is not a COSE key"))?;
+        let algorithm = cbor_int(cbor_map_get(map, 3))
+            .ok_or_else(|| rejected("credential public key has no algorithm"))?;
+        let kty = cbor_int(cbor_map_get(map, 1));
+
+        let key = match (algorithm, kty) {
+            (COSE_ES256, Some(2)) => {
+                let (Some(1), Some(x), Some(y)) = (
+                    cbor_int(cbor_map_get(map, -1)),
+                    cbor_bytes(cbor_map_get(map, -2)),
+                    cbor_bytes(cbor_map_get(map, -3)),
+                ) else {
+                    return Err(rejected("ES256 key is not on P-256"));
+                };
+                if x.len() != 32 || y.len() != 32 {
+                    return Err(rejected("ES256 key has invalid coordinates"));
+                }
+                let mut point = Vec::with_capacity(65);
+                point.push(0x04);
+                point.extend_from_slice(x);
+                point.extend_from_slice(y);
+                Self::Es256(point)
+            }
+            (COSE_EDDSA, Some(1)) => {
+                let (Some(6), Some(x)) = (
+                    cbor_int(cbor_map_get(map, -1)),
+                    cbor_bytes(cbor_map_get(map, -2)),
+                ) else {
+                    return Err(rejected("EdDSA key is not Ed25519"));
+                };
+                Self::EdDsa(x.to_vec())
+            }
+            (COSE_RS256, Some(3)) => {
+                let (Some(n), Some(e)) = (
+                    cbor_bytes(cbor_map_get(map, -1)),
+                    cbor_bytes(cbor_map_get(map, -2)),
+                ) else {
+                    return Err(rejected("RS256 key is incomplete"));
+                };
+                Self::Rs256 {
+                    n: n.to_vec(),
+                    e: e.to_vec(),
+                }
+            }
+            _ => {
+                return Err(AppError::InvalidInput(format!(
+                    "Unsupported authenticator algorithm {algorithm}"
+                )))
+            }
+        };
+        Ok((algorithm, key))
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            Self::Es256(point) => {
+                signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+            Self::EdDsa(key) => signature::UnparsedPublicKey::new(&signature::ED25519, key)
+                .verify(message, signature)
+                .is_ok(),
+            Self::Rs256 { n, e } => signature::RsaPublicKeyComponents { n, e }
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+                .is_ok(),
+        }
+    }
+}
+
+/// Check `clientDataJSON` and return its raw bytes.
+fn verify_client_data(
+    cfg: &AuthConfig,
+    encoded: &str,
+    expected_type: &str,
+    challenge: &[u8],
+) -> AppResult<Vec<u8>> {
+    let raw = decode_b64url(encoded, "clientDataJSON")?;
+    let client_data: Value = serde_json::from_slice(&raw)
+        .map_err(|_| AppError::InvalidInput("clientDataJSON is not valid JSON".to_string()))?;
+
+    if client_data["type"].as_str() != Some(expected_type) {
+        return Err(rejected("unexpected ceremony type"));
+    }
+    let received = client_data["challenge"]
+        .as_str()
+        .map(|c| decode_b64url(c, "challenge"))
+        .transpose()?;
+    if received.as_deref() != Some(challenge) {
+        return Err(rejected("challenge does not match"));
+    }
+    let origin = client_data["origin"].as_str().unwrap_or_default();
+    let allowed = cfg
+        .webauthn_origins
+        .iter()
+        .any(|o| o.trim_end_matches('/') == origin);
+    if !allowed {
+        return Err(rejected(&format!("origin '{origin}' is not allowed")));
+    }
+    Ok(raw)
+}
+
+fn verify_rp_and_flags(
+    cfg: &AuthConfig,
+    data: &AuthenticatorData<'_>,
+    require_user_verification: bool,
+) -> AppResult<()> {
+    if data.rp_id_hash != Sha256::digest(cfg.webauthn_rp_id.as_bytes()).as_slice() {
+        return Err(rejected("credential belongs to a different relying party"));
+    }
+    if data.flags & FLAG_USER_PRESENT == 0 {
+        return Err(rejected("user presence was not confirmed"));
+    }
+    if require_user_verification && data.flags & FLAG_USER_VERIFIED == 0 {
+        return Err(rejected("user verification is required"));
+    }
+    Ok(())
+}
+
+fn credential_descriptors(credentials: &[WebAuthnCredential]) -> Vec<Value> {
+    credentials
+        .iter()
+        .map(|c| json!({ "type": "public-key", "id": c.id }))
+        .collect()
+}
+
+/// The authenticator proved possession of a registered credential.
+#[derive(Debug, Clone)]
+pub struct VerifiedAssertion {
+    pub credential: WebAuthnCredential,
+    pub purpose: WebAuthnPurpose,
+}
+
+pub struct WebAuthnService;
+
+impl WebAuthnService {
+    async fn issue_challenge(
+        db: &Database,
+        cfg: &AuthConfig,
+        user_id: Option<&str>,
+        purpose: WebAuthnPurpose,
+    ) -> AppResult<WebAuthnChallenge> {
+        let challenge = WebAuthnChallenge {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.map(str::to_string),
+            purpose,
+            challenge: rand::rng().random::<[u8; 32]>().to_vec(),
+            expires_at: Utc::now() + Duration::seconds(cfg.webauthn_challenge_ttl_secs as i64),
+        };
+        db.create_webauthn_challenge(&challenge).await?;
+        Ok(challenge)
+    }
+
+    async fn take_challenge(
+        db: &Database,
+        ceremony_id: &str,
+        purposes: &[WebAuthnPurpose],
+    ) -> AppResult<WebAuthnChallenge> {
+        let challenge = db
+            .take_webauthn_challenge(ceremony_id)
+            .await?
+            .filter(|c| purposes.contains(&c.purpose))
+            .ok_or_else(|| rejected("unknown or already used ceremony"))?;
+        if challenge.is_expired() {
+            return Err(rejected("ceremony has expired"));
+        }
+        Ok(challenge)
+    }
+
+    /// Options for `navigator.credentials.create()` for a signed-in user.
+    pub async fn begin_registration(
+        db: &Database,
+        cfg: &AuthConfig,
+        user_id: &str,
+        username: &str,
+    ) -> AppResult<WebAuthnCeremony> {
+        let existing = db.list_webauthn_credentials(user_id).await?;
+        let challenge =
+            Self::issue_challenge(db, cfg, Some(user_id), WebAuthnPurpose::Register).await?;
+        let algorithms: Vec<Value> = [COSE_ES256, COSE_EDDSA, COSE_RS256]
+            .iter()
+            .map(|alg| json!({ "type": "public-key", "alg": alg }))
+            .collect();
+
+        Ok(WebAuthnCeremony {
+            ceremony_id: challenge.id,
+            public_key: json!({
+                "rp": { "id": cfg.webauthn_rp_id, "name": cfg.webauthn_rp_name },
+                "user": {
+                    "id": BASE64URL_NOPAD.encode(user_id.as_bytes()),
+                    "name": username,
+                    "displayName": username,
+                },
+                "challenge": BASE64URL_NOPAD.encode(&challenge.challenge),
+                "pubKeyCredParams": algorithms,
+                "timeout": cfg.webauthn_challenge_ttl_secs * 1000,
+                "excludeCredentials": credential_descriptors(&existing),
+                "authenticatorSelection": {
+                    "residentKey": "preferred",
+                    "userVerification": "preferred",
+                },
+                "attestation": "none",
+            }),
+        })
+    }
+
+    /// Verify a registration response and store the new credential.
+    pub async fn finish_registration(
+        db: &Database,
+        cfg: &AuthConfig,
+        user_id: &str,
+        ceremony_id: &str,
+        name: &str,
+        credential: &PublicKeyCredential,
+    ) -> AppResult<WebAuthnCredential> {
+        let challenge = Self::take_challenge(db, ceremony_id, &[WebAuthnPurpose::Register]).await?;
+        if challenge.user_id.as_deref() != Some(user_id) {
+            return Err(rejected("ceremony was started by another user"));
+        }
+        if credential.credential_type != "public-key" {
+            return Err(rejected("unexpected credential type"));
+        }
+        verify_client_data(
+            cfg,
+            &credential.response.client_data_json,
+            "webauthn.create",
+            &challenge.challenge,
+        )?;
+
+        let attestation = credential
+            .response
+            .attestation_object
+            .as_deref()
+            .ok_or_else(|| AppError::InvalidInput("attestationObject is required".to_string()))?;
+        let attestation = decode_b64url(attestation, "attestationObject")?;
+        let attestation: Cbor = ciborium::from_reader(attestation.as_slice())
+            .map_err(|_| rejected("attestation object is not valid CBOR"))?;
+        let auth_data = attestation
+            .as_map()
+            .and_then(|map| {
+                map.iter()
+                    .find(|(k, _)| k.as_text() == Some("authData"))
+                    .and_then(|(_, v)| v.as_bytes())
+            })
+            .ok_or_else(|| rejected("attestation object has no authenticator data"))?;
+
+        let data = parse_authenticator_data(auth_data)?;
+        verify_rp_and_flags(cfg, &data, false)?;
+        let attested = data
+            .attested
+            .ok_or_else(|| rejected("no credential was attested"))?;
+        let id = BASE64URL_NOPAD.encode(&attested.credential_id);
+        if decode_b64url(&credential.id, "id")? != attested.credential_id {
+            return Err(rejected("credential ID does not match authenticator data"));
+        }
+        let (algorithm, _) = CosePublicKey::parse(&attested.public_key)?;
+
+        let stored = WebAuthnCredential {
+            id,
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            public_key: attested.public_key,
+            algorithm,
+            sign_count: data.sign_count,
+            aaguid: Some(Uuid::from_bytes(attested.aaguid).to_string()),
+            created_at: Utc::now(),
+            last_used_at: None,
+        };
+        db.create_webauthn_credential(&stored).await?;
+        Ok(stored)
+    }
+
+    /// Options for `navigator.credentials.get()`. With a `user_id` only that
+    /// user's authenticators are offered; without one the authenticator may
+    /// offer any discoverable passkey for this site.
+    pub async fn begin_login(
+        db: &Database,
+        cfg: &AuthConfig,
+        user_id: Option<&str>,
+        purpose: WebAuthnPurpose,
+    ) -> AppResult<WebAuthnCeremony> {
+        let allowed = match user_id {
+            Some(user_id) => db.list_webauthn_credentials(user_id).await?,
+            None => Vec::new(),
+        };
+        let challenge = Self::issue_challenge(db, cfg, user_id, purpose).await?;
+        let user_verification = match purpose {
+            WebAuthnPurpose::Login => "required",
+            _ => "preferred",
+        };
+
+        Ok(WebAuthnCeremony {
+            ceremony_id: challenge.id,
+            public_key: json!({
+                "challenge": BASE64URL_NOPAD.encode(&challenge.challenge),
+                "rpId": cfg.webauthn_rp_id,
+                "timeout": cfg.webauthn_challenge_ttl_secs * 1000,
+                "allowCredentials": credential_descriptors(&allowed),
+                "userVerification": user_verification,
+            }),
+        })
+    }
+
+    /// Verify an assertion for a login or second-factor ceremony and advance
+    /// the credential's signature counter.
+    ///
+    /// Passwordless logins require user verification (PIN or biometric), so
+    /// that the passkey alone still amounts to two factors.
+    pub async fn finish_login(
+        db: &Database,
+        cfg: &AuthConfig,
+        ceremony_id: &str,
+        credential: &PublicKeyCredential,
+    ) -> AppResult<VerifiedAssertion> {
+        let challenge = Self::take_challenge(
+            db,
+            ceremony_id,
+            &[WebAuthnPurpose::Login, WebAuthnPurpose::SecondFactor],
+        )
+        .await?;
+        if credential.credential_type != "public-key" {
+            return Err(rejected("unexpected credential type"));
+        }
+
+        let credential_id = BASE64URL_NOPAD.encode(&decode_b64url(&credential.id, "id")?);
+        let stored = db
+            .get_webauthn_credential(&credential_id)
+            .await?
+            .ok_or_else(|| rejected("unknown authenticator"))?;
+        if challenge
+            .user_id
+            .as_deref()
+            .is_some_and(|user_id| user_id != stored.user_id)
+        {
+            return Err(rejected("authenticator belongs to another user"));
+        }
+        if let Some(handle) = credential.response.user_handle.as_deref() {
+            if decode_b64url(handle, "userHandle")? != stored.user_id.as_bytes() {
+                return Err(rejected("user handle does not match the authenticator"));
+            }
+        }
+
+        let client_data = verify_client_data(
+            cfg,
+            &credential.response.client_data_json,
+            "webauthn.get",
+            &challenge.challenge,
+        )?;
+        let auth_data = credential
+            .response
+            .authenticator_data
+            .as_deref()
+            .ok_or_else(|| AppError::InvalidInput("authenticatorData is required".to_string()))?;
+        let auth_data = decode_b64url(auth_data, "authenticatorData")?;
+        let data = parse_authenticator_data(&auth_data)?;
+        verify_rp_and_flags(cfg, &data, challenge.purpose == WebAuthnPurpose::Login)?;
+
+        let sig = credential
+            .response
+            .signature
+            .as_deref()
+            .ok_or_else(|| AppError::InvalidInput("signature is required".to_string()))?;
+        let sig = decode_b64url(sig, "signature")?;
+        let mut signed = data.raw.to_vec();
+        signed.extend_from_slice(&Sha256::digest(&client_data));
+        let (_, key) = CosePublicKey::parse(&stored.public_key)?;
+        if !key.verify(&signed, &sig) {
+            return Err(rejected("signature is invalid"));
+        }
+
+        // Authenticators that keep a counter must increase it; one that goes
+        // backwards suggests a cloned key.
+        if (data.sign_count != 0 || stored.sign_count != 0) && data.sign_count <= stored.sign_count
+        {
+            return Err(rejected("signature counter did not increase"));
+        }
+        db.record_webauthn_use(&stored.id, stored.sign_count, data.sign_count)
+            .await?;
+
+        Ok(VerifiedAssertion {
+            credential: WebAuthnCredential {
+                sign_count: data.sign_count,
+                last_used_at: Some(Utc::now()),
+                ..stored
+            },
+            purpose: challenge.purpose,
+        })
+    }
+}
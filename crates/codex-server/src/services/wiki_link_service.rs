@@ -14,6 +14,38 @@ pub struct ResolvedLink {
     pub alternatives: Vec<String>,
 }
 
+impl ResolvedLink {
+    /// Drop the matches `can_read` rejects. A link whose matches are all
+    /// hidden resolves as if its target did not exist, without naming the
+    /// folder the hidden note is in.
+    pub fn retain_readable(mut self, wiki_link: &str, can_read: impl Fn(&str) -> bool) -> Self {
+        if !self.exists {
+            return self;
+        }
+        self.alternatives.retain(|path| can_read(path));
+        if can_read(&self.path) {
+            return self;
+        }
+        if !self.alternatives.is_empty() {
+            self.path = self.alternatives.remove(0);
+            return self;
+        }
+
+        let (link_target, _fragment) = WikiLinkResolver::split_fragment(wiki_link);
+        let link_target = WikiLinkResolver::decode_percent_encoding(&link_target);
+        // An explicit path only repeats what the caller asked for
+        if !link_target.contains('/') && !link_target.contains('\\') {
+            self.path = if link_target.ends_with(".md") {
+                link_target
+            } else {
+                format!("{}.md", link_target)
+            };
+        }
+        self.exists = false;
+        self
+    }
+}
+
 pub struct WikiLinkResolver;
 
 impl WikiLinkResolver {
@@ -317,6 +349,21 @@ impl FileIndex {
         Self::default()
     }
 
+    /// Build an index from vault-relative file paths, e.g. only the files a
+    /// caller may read.
+    pub fn from_paths<'a>(paths: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut index = Self::new();
+        for path in paths {
+            let file = Path::new(path);
+            let Some(name) = file.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+            index.add_file(stem, name, path, path.matches('/').count());
+        }
+        index
+    }
+
     fn add_file(&mut self, stem: &str, name: &str, path: &str, depth: usize) {
         let stem_lower = stem.to_lowercase();
         let name_lower = name.to_lowercase();
@@ -479,6 +526,31 @@ mod tests {
         assert!(result.exists);
     }
 
+    #[test]
+    fn test_hidden_matches_are_dropped() {
+        let temp = create_test_vault();
+        let vault_path = temp.path().to_str().unwrap();
+        let outside_folder = |path: &str| !path.starts_with("folder/");
+
+        let result = WikiLinkResolver::resolve(vault_path, "Note")
+            .unwrap()
+            .retain_readable("Note", outside_folder);
+        assert_eq!(result.path, "Note.md");
+        assert!(result.alternatives.is_empty());
+
+        let result = WikiLinkResolver::resolve(vault_path, "SubNote#intro")
+            .unwrap()
+            .retain_readable("SubNote#intro", outside_folder);
+        assert!(!result.exists);
+        assert_eq!(result.path, "SubNote.md");
+
+        let result = WikiLinkResolver::resolve(vault_path, "Note")
+            .unwrap()
+            .retain_readable("Note", |path| path != "Note.md");
+        assert!(result.exists);
+        assert_eq!(result.path, "folder/Note.md");
+    }
+
     #[test]
     fn test_ambiguous_link_returns_alternatives() {
         let temp = create_test_vault();
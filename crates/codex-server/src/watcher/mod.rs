@@ -1,5 +1,6 @@
 use crate::error::{AppError, AppResult};
 use crate::models::{FileChangeEvent, FileChangeType};
+use crate::services::schema_service::VAULT_TYPES_DIR;
 use chrono::Utc;
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode};
 use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, RecommendedCache};
@@ -109,11 +110,15 @@ impl FileWatcher {
         for (vault_id, vault_path) in paths.iter() {
             for path in &event.paths {
                 if path.starts_with(vault_path) {
-                    // Skip hidden files and .obsidian directory
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if name.starts_with('.') {
-                            continue;
-                        }
+                    // Skip hidden files and anything inside a hidden directory
+                    // (.obsidian, .git, .trash, ...), except the vault's own
+                    // type definitions, which are hot-reloaded
+                    let relative = path.strip_prefix(vault_path).unwrap_or(path);
+                    let hidden = relative
+                        .components()
+                        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+                    if hidden && !relative.starts_with(VAULT_TYPES_DIR) {
+                        continue;
                     }
 
                     let relative_path = path
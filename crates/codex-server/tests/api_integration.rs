@@ -6,7 +6,7 @@ use argon2::{
 use codex::config::AppConfig;
 use codex::db::Database;
 use codex::middleware::AuthMiddleware;
-use codex::routes::{api_keys, auth, files, vaults, AppState};
+use codex::routes::{api_keys, auth, files, totp, vaults, webauthn, AppState};
 use codex::services::{MarkdownParser, SearchIndex};
 use codex::watcher::FileWatcher;
 use serde_json::json;
@@ -33,7 +33,7 @@ async fn verify_api_keys_and_totp() {
         .await
         .unwrap();
 
-    let search_index = SearchIndex::new();
+    let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
     let (watcher, _) = FileWatcher::new().unwrap();
     let watcher = Arc::new(Mutex::new(watcher));
     let (event_tx, _) = broadcast::channel(100);
@@ -52,7 +52,8 @@ async fn verify_api_keys_and_totp() {
         document_parser: Arc::new(MarkdownParser),
         entity_type_registry: codex::services::EntityTypeRegistry::new(),
         relation_type_registry: codex::services::RelationTypeRegistry::new(),
-        plugins_dir: String::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
     });
 
     let mut config = AppConfig::default();
@@ -66,7 +67,9 @@ async fn verify_api_keys_and_totp() {
             .app_data(config.clone())
             .wrap(AuthMiddleware)
             .configure(auth::configure)
-            .configure(api_keys::configure),
+            .configure(api_keys::configure)
+            .configure(totp::configure)
+            .configure(webauthn::configure),
     )
     .await;
 
@@ -109,6 +112,30 @@ async fn verify_api_keys_and_totp() {
     );
     assert_eq!(me_body["username"], "admin");
 
+    // Even an unrestricted key cannot enrol or change sign-in credentials.
+    for req in [
+        test::TestRequest::post().uri("/api/auth/webauthn/register/start"),
+        test::TestRequest::post()
+            .uri("/api/auth/webauthn/register/finish")
+            .set_json(json!({})),
+        test::TestRequest::patch()
+            .uri("/api/auth/webauthn/credentials/some-id")
+            .set_json(json!({ "name": "key" })),
+        test::TestRequest::delete().uri("/api/auth/webauthn/credentials/some-id"),
+        test::TestRequest::post().uri("/api/auth/totp/enroll"),
+        test::TestRequest::post()
+            .uri("/api/auth/change-password")
+            .set_json(json!({ "current_password": "hunter2", "new_password": "hunter3" })),
+    ] {
+        let resp = test::call_service(
+            &app,
+            req.insert_header(("X-API-Key", api_key.clone()))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 403);
+    }
+
     // 3. List API Keys
     let list_req = test::TestRequest::get()
         .uri("/api/auth/api-keys")
@@ -153,7 +180,7 @@ async fn test_public_vault_allows_anonymous_reads() {
     std::fs::create_dir_all(&vault_root).unwrap();
 
     // ── Build app ─────────────────────────────────────────────────────────
-    let search_index = SearchIndex::new();
+    let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
     let (watcher, _) = FileWatcher::new().unwrap();
     let watcher = Arc::new(Mutex::new(watcher));
     let (event_tx, _) = broadcast::channel(100);
@@ -170,7 +197,8 @@ async fn test_public_vault_allows_anonymous_reads() {
         document_parser: Arc::new(MarkdownParser),
         entity_type_registry: codex::services::EntityTypeRegistry::new(),
         relation_type_registry: codex::services::RelationTypeRegistry::new(),
-        plugins_dir: String::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
     });
 
     let mut config = AppConfig::default();
@@ -305,3 +333,197 @@ async fn test_public_vault_allows_anonymous_reads() {
 
     let _ = state; // keep state alive
 }
+
+#[actix_web::test]
+async fn scoped_api_keys_are_limited_to_vaults_roles_and_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("scoped-key-test.db");
+    let db = Database::new(&format!("sqlite://{}", db_path.display()))
+        .await
+        .unwrap();
+    db.bootstrap_admin_if_empty(Some("admin"), Some("secret123"))
+        .await
+        .unwrap();
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let state = web::Data::new(AppState {
+        db: db.clone(),
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: broadcast::channel(100).0,
+        ws_broadcaster: tokio::sync::broadcast::channel::<codex::models::WsMessage>(16).0,
+        change_log_retention_days: 7,
+        ml_undo_store: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
+        shutdown_tx: tokio::sync::broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
+    });
+
+    let mut config = AppConfig::default();
+    config.auth.enabled = true;
+    config.auth.jwt_secret = "scoped-key-secret".to_string();
+    let config = web::Data::new(config);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .app_data(config.clone())
+            .wrap(AuthMiddleware)
+            .configure(auth::configure)
+            .configure(api_keys::configure)
+            .configure(vaults::configure)
+            .configure(files::configure),
+    )
+    .await;
+
+    let login_resp = test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(json!({ "username": "admin", "password": "secret123" }))
+            .to_request(),
+    )
+    .await;
+    let login_body: serde_json::Value = test::read_body_json(login_resp).await;
+    let auth_header = format!("Bearer {}", login_body["access_token"].as_str().unwrap());
+
+    let me: serde_json::Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/auth/me")
+            .insert_header((header::AUTHORIZATION, auth_header.clone()))
+            .to_request(),
+    )
+    .await;
+    let admin_id = me["id"].as_str().unwrap().to_string();
+
+    let mut vault_ids = Vec::new();
+    for name in ["Allowed", "Other"] {
+        let dir = temp_dir.path().join(name);
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        std::fs::create_dir_all(dir.join("private")).unwrap();
+        std::fs::write(dir.join("notes/a.md"), "# A").unwrap();
+        std::fs::write(dir.join("private/b.md"), "# B").unwrap();
+        let vault = db
+            .create_vault_for_owner(
+                name.to_string(),
+                dir.to_string_lossy().to_string(),
+                Some(&admin_id),
+            )
+            .await
+            .unwrap();
+        vault_ids.push(vault.id);
+    }
+    let (allowed, other) = (&vault_ids[0], &vault_ids[1]);
+
+    // Keys cannot carry the owner role.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/auth/api-keys")
+            .insert_header((header::AUTHORIZATION, auth_header.clone()))
+            .set_json(json!({ "name": "Too strong", "role": "owner" }))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    let created: serde_json::Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/auth/api-keys")
+            .insert_header((header::AUTHORIZATION, auth_header.clone()))
+            .set_json(json!({
+                "name": "CI reader",
+                "vault_ids": [allowed],
+                "role": "viewer",
+                "path_globs": ["notes/**"],
+                "scopes": ["read"],
+            }))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(created["role"], "viewer");
+    assert_eq!(created["path_globs"], json!(["notes/**"]));
+    let api_key = created["api_key"].as_str().unwrap().to_string();
+
+    let status_for = |req: test::TestRequest| {
+        let req = req
+            .insert_header(("X-API-Key", api_key.clone()))
+            .to_request();
+        let app = &app;
+        async move { test::call_service(app, req).await.status().as_u16() }
+    };
+
+    let uri = |vault: &str, path: &str| format!("/api/vaults/{}/files/{}", vault, path);
+    assert_eq!(
+        status_for(test::TestRequest::get().uri(&uri(allowed, "notes/a.md"))).await,
+        200
+    );
+    assert_eq!(
+        status_for(test::TestRequest::get().uri(&uri(allowed, "private/b.md"))).await,
+        403
+    );
+    assert_eq!(
+        status_for(test::TestRequest::get().uri(&uri(allowed, "notes/%2E%2E/private/b.md"))).await,
+        403
+    );
+    assert_eq!(
+        status_for(test::TestRequest::get().uri(&uri(other, "notes/a.md"))).await,
+        403
+    );
+    assert_eq!(
+        status_for(
+            test::TestRequest::put()
+                .uri(&uri(allowed, "notes/a.md"))
+                .set_json(json!({ "content": "# changed" }))
+        )
+        .await,
+        403
+    );
+    // A restricted key cannot mint new keys.
+    assert_eq!(
+        status_for(
+            test::TestRequest::post()
+                .uri("/api/auth/api-keys")
+                .set_json(json!({ "name": "escalate" }))
+        )
+        .await,
+        403
+    );
+
+    let listed: serde_json::Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/vaults")
+            .insert_header(("X-API-Key", api_key.clone()))
+            .to_request(),
+    )
+    .await;
+    let listed_ids: Vec<&str> = listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(listed_ids, vec![allowed.as_str()]);
+
+    let keys: serde_json::Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/auth/api-keys")
+            .insert_header((header::AUTHORIZATION, auth_header.clone()))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(keys[0]["scopes"], json!(["read"]));
+    assert!(keys[0]["last_used_at"].is_string());
+
+    let audit = db.get_audit_log(Some(50)).await.unwrap();
+    assert!(audit
+        .iter()
+        .any(|entry| entry.event_type == "api_key_denied" && !entry.success));
+}
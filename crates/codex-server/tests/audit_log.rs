@@ -0,0 +1,410 @@
+use actix_web::{http::header, test, web, App};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+use codex::config::AppConfig;
+use codex::db::Database;
+use codex::middleware::{AuditMiddleware, AuthMiddleware, RequestIdMiddleware};
+use codex::models::audit::AuditEvent;
+use codex::models::{AuditLogEntry, AuditOutcome, CreateVaultRequest};
+use codex::routes::{admin, auth, files, vaults, AppState};
+use codex::services::{MarkdownParser, SearchIndex};
+use codex::watcher::FileWatcher;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::{broadcast, Mutex};
+
+fn password_hash(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+async fn setup() -> (TempDir, web::Data<AppState>, web::Data<AppConfig>) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_url = format!("sqlite://{}", temp_dir.path().join("audit.db").display());
+    let db = Database::new(&db_url).await.unwrap();
+    db.bootstrap_admin_if_empty(Some("admin"), Some("hunter2"))
+        .await
+        .unwrap();
+    db.create_user("alice", &password_hash("password123"))
+        .await
+        .unwrap();
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let (event_tx, _) = broadcast::channel(100);
+    let state = web::Data::new(AppState {
+        db,
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: event_tx,
+        ws_broadcaster: tokio::sync::broadcast::channel::<codex::models::WsMessage>(16).0,
+        change_log_retention_days: 7,
+        ml_undo_store: std::sync::Arc::new(tokio::sync::Mutex::new(
+            std::collections::HashMap::new(),
+        )),
+        shutdown_tx: tokio::sync::broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
+    });
+
+    let mut config = AppConfig::default();
+    config.auth.enabled = true;
+    config.auth.jwt_secret = "integration-test-secret".to_string();
+    (temp_dir, state, web::Data::new(config))
+}
+
+async fn send<S, B>(
+    app: &S,
+    method: &str,
+    uri: &str,
+    token: &str,
+    request_id: Option<&str>,
+    body: Option<Value>,
+) -> (u16, web::Bytes)
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let mut req = match method {
+        "GET" => test::TestRequest::get(),
+        "POST" => test::TestRequest::post(),
+        "DELETE" => test::TestRequest::delete(),
+        other => panic!("unsupported method {other}"),
+    }
+    .uri(uri)
+    .peer_addr("203.0.113.7:40000".parse().unwrap())
+    // Client-supplied forwarding headers must not replace the peer address.
+    .insert_header(("X-Forwarded-For", "198.51.100.1"))
+    .insert_header((header::AUTHORIZATION, format!("Bearer {token}")));
+    if let Some(request_id) = request_id {
+        req = req.insert_header(("X-Request-ID", request_id));
+    }
+    if let Some(body) = body {
+        req = req.set_json(body);
+    }
+    let resp = test::call_service(app, req.to_request()).await;
+    let status = resp.status().as_u16();
+    (status, test::read_body(resp).await)
+}
+
+async fn login<S, B>(app: &S, username: &str, password: &str) -> String
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "username": username, "password": password }))
+        .to_request();
+    let resp = test::call_service(app, req).await;
+    assert!(resp.status().is_success());
+    let body: Value = test::read_body_json(resp).await;
+    body["access_token"].as_str().unwrap().to_string()
+}
+
+fn page(body: &[u8]) -> (Vec<AuditLogEntry>, Option<String>) {
+    let page: Value = serde_json::from_slice(body).unwrap();
+    (
+        serde_json::from_value(page["entries"].clone()).unwrap(),
+        page["next_cursor"].as_str().map(str::to_string),
+    )
+}
+
+#[actix_web::test]
+async fn requests_are_recorded_filterable_and_exportable() {
+    let (temp_dir, state, config) = setup().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .app_data(config.clone())
+            .wrap(AuthMiddleware)
+            .wrap(AuditMiddleware)
+            .wrap(RequestIdMiddleware)
+            .configure(auth::configure)
+            .configure(admin::configure)
+            .configure(vaults::configure)
+            .configure(files::configure),
+    )
+    .await;
+    let admin_token = login(&app, "admin", "hunter2").await;
+    let alice_token = login(&app, "alice", "password123").await;
+
+    let vault_dir = temp_dir.path().join("vault");
+    std::fs::create_dir_all(&vault_dir).unwrap();
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/vaults",
+        &admin_token,
+        None,
+        Some(json!(CreateVaultRequest {
+            name: "Audited".to_string(),
+            path: Some(vault_dir.to_string_lossy().to_string()),
+        })),
+    )
+    .await;
+    assert_eq!(status, 201, "{body:?}");
+    let vault: Value = serde_json::from_slice(&body).unwrap();
+    let vault_id = vault["id"].as_str().unwrap().to_string();
+
+    let files_uri = format!("/api/vaults/{vault_id}/files");
+    let note_uri = format!("{files_uri}/notes/Plan%20B.md");
+    let (status, _) = send(
+        &app,
+        "POST",
+        &files_uri,
+        &admin_token,
+        Some("req-write"),
+        Some(json!({ "path": "notes/Plan B.md", "content": "# Plan B" })),
+    )
+    .await;
+    assert!(status < 300, "{status}");
+    let (status, _) = send(&app, "GET", &note_uri, &admin_token, Some("req-read"), None).await;
+    assert_eq!(status, 200);
+    // Alice has no access to the vault.
+    let (status, _) = send(
+        &app,
+        "GET",
+        &note_uri,
+        &alice_token,
+        Some("req-denied"),
+        None,
+    )
+    .await;
+    assert_eq!(status, 403);
+    let (status, _) = send(
+        &app,
+        "GET",
+        "/api/admin/audit-log",
+        &alice_token,
+        Some("req-admin-denied"),
+        None,
+    )
+    .await;
+    assert_eq!(status, 403);
+    let (status, _) = send(&app, "DELETE", &note_uri, &admin_token, None, None).await;
+    assert!(status < 300, "{status}");
+
+    // File events, newest first, with actor, vault, path and request id.
+    let (status, body) = send(
+        &app,
+        "GET",
+        &format!(
+            "/api/admin/audit-log?vault_id={vault_id}&event_type=file_read,file_write,file_delete"
+        ),
+        &admin_token,
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, 200);
+    let (entries, next_cursor) = page(&body);
+    assert_eq!(next_cursor, None);
+    let events: Vec<(&str, AuditOutcome)> = entries
+        .iter()
+        .map(|e| (e.event_type.as_str(), e.outcome))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            ("file_delete", AuditOutcome::Success),
+            ("file_read", AuditOutcome::Denied),
+            ("file_read", AuditOutcome::Success),
+            ("file_write", AuditOutcome::Success),
+        ]
+    );
+    let denied = &entries[1];
+    assert_eq!(denied.username.as_deref(), Some("alice"));
+    assert_eq!(denied.path.as_deref(), Some("notes/Plan B.md"));
+    assert_eq!(denied.request_id.as_deref(), Some("req-denied"));
+    assert!(!denied.success);
+    let read = &entries[2];
+    assert_eq!(read.username.as_deref(), Some("admin"));
+    assert_eq!(read.vault_id.as_deref(), Some(vault_id.as_str()));
+    assert_eq!(read.request_id.as_deref(), Some("req-read"));
+    assert_eq!(read.ip_address.as_deref(), Some("203.0.113.7"));
+
+    // Other refusals are recorded as permission denials.
+    let (_, body) = send(
+        &app,
+        "GET",
+        "/api/admin/audit-log?event_type=permission_denied&outcome=denied",
+        &admin_token,
+        None,
+        None,
+    )
+    .await;
+    let (entries, _) = page(&body);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].request_id.as_deref(), Some("req-admin-denied"));
+    assert_eq!(entries[0].username.as_deref(), Some("alice"));
+
+    // Cursor pagination walks the same entries one at a time.
+    let mut walked = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut uri = format!(
+            "/api/admin/audit-log?vault_id={vault_id}&event_type=file_read,file_write,file_delete&limit=1"
+        );
+        if let Some(cursor) = &cursor {
+            uri.push_str(&format!("&cursor={cursor}"));
+        }
+        let (status, body) = send(&app, "GET", &uri, &admin_token, None, None).await;
+        assert_eq!(status, 200);
+        let (entries, next) = page(&body);
+        walked.extend(entries.into_iter().map(|e| e.event_type));
+        match next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(
+        walked,
+        vec!["file_delete", "file_read", "file_read", "file_write"]
+    );
+
+    let since = chrono::Utc::now() + chrono::Duration::hours(1);
+    let (_, body) = send(
+        &app,
+        "GET",
+        &format!(
+            "/api/admin/audit-log?since={}",
+            since.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        ),
+        &admin_token,
+        None,
+        None,
+    )
+    .await;
+    assert!(page(&body).0.is_empty());
+
+    // Exports run oldest first.
+    let (status, body) = send(
+        &app,
+        "GET",
+        &format!(
+            "/api/admin/audit-log/export?format=ndjson&vault_id={vault_id}&event_type=file_read"
+        ),
+        &admin_token,
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, 200);
+    let lines: Vec<AuditLogEntry> = std::str::from_utf8(&body)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].request_id.as_deref(), Some("req-read"));
+    assert_eq!(lines[1].request_id.as_deref(), Some("req-denied"));
+
+    let (status, body) = send(
+        &app,
+        "GET",
+        &format!(
+            "/api/admin/audit-log/export?format=csv&vault_id={vault_id}&event_type=file_write"
+        ),
+        &admin_token,
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, 200);
+    let csv = std::str::from_utf8(&body).unwrap();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len(), 2, "{csv}");
+    assert!(rows[0].starts_with("id,timestamp,user_id,username,event_type,outcome"));
+    assert!(rows[1].contains(",admin,file_write,success,"));
+    assert!(rows[1].contains(",notes/Plan B.md,"));
+    assert!(rows[1].contains(",req-write,"));
+
+    let (status, _) = send(
+        &app,
+        "GET",
+        "/api/admin/audit-log/export?format=xml",
+        &admin_token,
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, 400);
+
+    let (_, body) = send(
+        &app,
+        "GET",
+        "/api/admin/audit-log?event_type=audit_log_exported",
+        &admin_token,
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(page(&body).0.len(), 2);
+}
+
+#[actix_web::test]
+async fn hash_chain_detects_edited_and_removed_entries() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_url = format!("sqlite://{}", temp_dir.path().join("chain.db").display());
+    let db = Database::new(&db_url)
+        .await
+        .unwrap()
+        .with_audit_hash_chain(true);
+
+    let report = db.verify_audit_chain().await.unwrap();
+    assert!(report.enabled && report.valid);
+    assert_eq!(report.checked, 0);
+
+    for i in 0..4 {
+        db.record_audit_event(AuditEvent {
+            user_id: Some("u1".to_string()),
+            detail: Some(format!("event {i}")),
+            path: Some(format!("notes/{i}.md")),
+            ..AuditEvent::new("file_write")
+        })
+        .await
+        .unwrap();
+    }
+    let report = db.verify_audit_chain().await.unwrap();
+    assert!(report.valid);
+    assert_eq!(report.checked, 4);
+    let entries = db.get_audit_log(None).await.unwrap();
+    assert_eq!(report.head_hash, entries[0].hash);
+    assert_eq!(entries[3].prev_hash, Some("0".repeat(64)));
+    assert_eq!(entries[2].prev_hash, entries[3].hash);
+
+    sqlx::query("UPDATE audit_log SET detail = 'nothing to see' WHERE id = $1")
+        .bind(entries[2].id)
+        .execute(db.pool())
+        .await
+        .unwrap();
+    let report = db.verify_audit_chain().await.unwrap();
+    assert!(!report.valid);
+    assert_eq!(report.broken_at_id, Some(entries[2].id));
+
+    sqlx::query("DELETE FROM audit_log WHERE id = $1")
+        .bind(entries[2].id)
+        .execute(db.pool())
+        .await
+        .unwrap();
+    let report = db.verify_audit_chain().await.unwrap();
+    assert!(!report.valid);
+    assert_eq!(report.broken_at_id, Some(entries[1].id));
+}
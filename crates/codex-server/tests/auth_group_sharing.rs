@@ -7,8 +7,8 @@ use codex::config::AppConfig;
 use codex::db::Database;
 use codex::middleware::AuthMiddleware;
 use codex::models::{CreateGroupRequest, CreateVaultRequest};
-use codex::routes::{auth, groups, vaults, AppState};
-use codex::services::{MarkdownParser, SearchIndex};
+use codex::routes::{auth, entities, files, groups, search, vaults, AppState};
+use codex::services::{MarkdownParser, ReindexService, SearchIndex};
 use codex::watcher::FileWatcher;
 use serde_json::json;
 use std::sync::Arc;
@@ -37,7 +37,7 @@ async fn username_login_group_membership_and_vault_sharing_work() {
         .await
         .unwrap();
 
-    let search_index = SearchIndex::new();
+    let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
     let (watcher, _) = FileWatcher::new().unwrap();
     let watcher = Arc::new(Mutex::new(watcher));
     let (event_tx, _) = broadcast::channel(100);
@@ -56,7 +56,8 @@ async fn username_login_group_membership_and_vault_sharing_work() {
         document_parser: Arc::new(MarkdownParser),
         entity_type_registry: codex::services::EntityTypeRegistry::new(),
         relation_type_registry: codex::services::RelationTypeRegistry::new(),
-        plugins_dir: String::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
     });
 
     let mut config = AppConfig::default();
@@ -240,3 +241,445 @@ async fn username_login_group_membership_and_vault_sharing_work() {
     let delete_resp = test::call_service(&app, delete_req).await;
     assert_eq!(delete_resp.status().as_u16(), 403);
 }
+
+#[actix_web::test]
+async fn path_acls_narrow_access_inside_a_shared_vault() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("path-acls.db");
+    let db = Database::new(&format!("sqlite://{}", db_path.display()))
+        .await
+        .unwrap();
+
+    db.bootstrap_admin_if_empty(Some("admin"), Some("hunter2"))
+        .await
+        .unwrap();
+    db.create_user("alice", &password_hash("password123"))
+        .await
+        .unwrap();
+
+    let vault_dir = temp_dir.path().join("acl-vault");
+    for (path, content) in [
+        ("notes/todo.md", "# Todo\nbudget review"),
+        ("drafts/plan.md", "# Plan\nbudget draft"),
+        ("secret/salaries.md", "# Salaries\nbudget numbers"),
+        ("hr/policy.md", "# Policy\nbudget policy"),
+    ] {
+        let full = vault_dir.join(path);
+        std::fs::create_dir_all(full.parent().unwrap()).unwrap();
+        std::fs::write(full, content).unwrap();
+    }
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let state = web::Data::new(AppState {
+        db: db.clone(),
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: broadcast::channel(100).0,
+        ws_broadcaster: broadcast::channel::<codex::models::WsMessage>(16).0,
+        change_log_retention_days: 7,
+        ml_undo_store: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        shutdown_tx: broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
+    });
+
+    let mut config = AppConfig::default();
+    config.auth.enabled = true;
+    config.auth.jwt_secret = "integration-test-secret".to_string();
+    let config = web::Data::new(config);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .app_data(config.clone())
+            .wrap(AuthMiddleware)
+            .configure(auth::configure)
+            .configure(groups::configure)
+            .configure(vaults::configure)
+            .configure(files::configure)
+            .configure(search::configure)
+            .configure(entities::configure),
+    )
+    .await;
+
+    let mut tokens = Vec::new();
+    for (username, password) in [("admin", "hunter2"), ("alice", "password123")] {
+        let req = test::TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(json!({ "username": username, "password": password }))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        tokens.push(format!("Bearer {}", body["access_token"].as_str().unwrap()));
+    }
+    let (admin, alice) = (tokens[0].clone(), tokens[1].clone());
+
+    let req = test::TestRequest::post()
+        .uri("/api/vaults")
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .set_json(&CreateVaultRequest {
+            name: "ACL Vault".to_string(),
+            path: Some(vault_dir.to_string_lossy().to_string()),
+        })
+        .to_request();
+    let vault: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let vault_id = vault["id"].as_str().unwrap().to_string();
+    state
+        .search_index
+        .index_vault(&vault_id, &vault_dir.to_string_lossy())
+        .unwrap();
+
+    let alice_user_id = db
+        .get_user_by_username("alice")
+        .await
+        .unwrap()
+        .map(|(id, _)| id)
+        .unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/api/groups")
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .set_json(&CreateGroupRequest {
+            name: "writers".to_string(),
+        })
+        .to_request();
+    let group: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let group_id = group["id"].as_str().unwrap().to_string();
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/groups/{}/members", group_id))
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .set_json(json!({ "user_id": alice_user_id }))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/shares/groups", vault_id))
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .set_json(json!({ "group_id": group_id, "role": "editor" }))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let acls_uri = format!("/api/vaults/{}/shares/paths", vault_id);
+    let mut secret_acl_id = String::new();
+    for rule in [
+        json!({ "pattern": "secret/**", "principal_type": "everyone", "access": "none" }),
+        json!({ "pattern": "drafts/**", "principal_type": "group", "principal_id": group_id, "access": "read" }),
+        json!({ "pattern": "hr/**", "principal_type": "everyone", "access": "none" }),
+        json!({ "pattern": "hr/**", "principal_type": "user", "principal_id": alice_user_id, "access": "read" }),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&acls_uri)
+            .insert_header((header::AUTHORIZATION, admin.clone()))
+            .set_json(&rule)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let entry: serde_json::Value = test::read_body_json(resp).await;
+        if entry["pattern"] == "secret/**" {
+            secret_acl_id = entry["id"].as_str().unwrap().to_string();
+        }
+    }
+
+    // Malformed rules and non-managers are refused.
+    let req = test::TestRequest::post()
+        .uri(&acls_uri)
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .set_json(json!({ "pattern": "../**", "principal_type": "everyone", "access": "none" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 400);
+    let req = test::TestRequest::post()
+        .uri(&acls_uri)
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .set_json(json!({ "pattern": "secret/**", "principal_type": "everyone", "access": "read" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    let read_status = |token: &str, path: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/vaults/{}/files/{}", vault_id, path))
+            .insert_header((header::AUTHORIZATION, token.to_string()))
+            .to_request()
+    };
+    let write_status = |token: &str, path: &str| {
+        test::TestRequest::put()
+            .uri(&format!("/api/vaults/{}/files/{}", vault_id, path))
+            .insert_header((header::AUTHORIZATION, token.to_string()))
+            .set_json(json!({ "content": "# Edited\nbudget review", "last_modified": null }))
+            .to_request()
+    };
+
+    assert_eq!(
+        test::call_service(&app, read_status(&alice, "secret/salaries.md"))
+            .await
+            .status()
+            .as_u16(),
+        403
+    );
+    assert_eq!(
+        test::call_service(&app, read_status(&alice, "notes/../secret/salaries.md"))
+            .await
+            .status()
+            .as_u16(),
+        403
+    );
+    assert_eq!(
+        test::call_service(&app, read_status(&admin, "secret/salaries.md"))
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    assert_eq!(
+        test::call_service(&app, read_status(&alice, "drafts/plan.md"))
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    assert_eq!(
+        test::call_service(&app, write_status(&alice, "drafts/plan.md"))
+            .await
+            .status()
+            .as_u16(),
+        403
+    );
+    assert_eq!(
+        test::call_service(&app, read_status(&alice, "hr/policy.md"))
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    assert_eq!(
+        test::call_service(&app, write_status(&alice, "hr/policy.md"))
+            .await
+            .status()
+            .as_u16(),
+        403
+    );
+    assert_eq!(
+        test::call_service(&app, write_status(&alice, "notes/todo.md"))
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/files", vault_id))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .set_json(json!({ "path": "drafts/new.md", "content": "nope" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    // Rules apply to the normalized path, however it is spelled.
+    for path in [
+        "./drafts/new.md",
+        "drafts//new.md",
+        "notes/../drafts/new.md",
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/vaults/{}/files", vault_id))
+            .insert_header((header::AUTHORIZATION, alice.clone()))
+            .set_json(json!({ "path": path, "content": "nope" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+    }
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/rename", vault_id))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .set_json(json!({ "from": "notes/todo.md", "to": "./drafts/todo.md" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+    assert!(!vault_dir.join("drafts/new.md").exists());
+    assert!(vault_dir.join("notes/todo.md").exists());
+
+    // The tree and search results hide what alice cannot read.
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{}/files", vault_id))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .to_request();
+    let tree: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let top: Vec<&str> = tree
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|node| node["name"].as_str().unwrap())
+        .collect();
+    assert!(top.contains(&"drafts") && top.contains(&"hr") && top.contains(&"notes"));
+    assert!(!top.contains(&"secret"));
+
+    let search_paths = |token: String| {
+        test::TestRequest::get()
+            .uri(&format!("/api/vaults/{}/search?q=budget", vault_id))
+            .insert_header((header::AUTHORIZATION, token))
+            .to_request()
+    };
+    let results: serde_json::Value =
+        test::call_and_read_body_json(&app, search_paths(alice.clone())).await;
+    assert_eq!(results["total_count"], 3);
+    assert!(results["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|r| !r["path"].as_str().unwrap().starts_with("secret/")));
+    let results: serde_json::Value =
+        test::call_and_read_body_json(&app, search_paths(admin.clone())).await;
+    assert_eq!(results["total_count"], 4);
+
+    // Link resolution does not reveal where hidden notes live.
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/resolve-link", vault_id))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .set_json(json!({ "link": "salaries" }))
+        .to_request();
+    let link: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(link["exists"], false);
+    assert_eq!(link["path"], "salaries.md");
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/resolve-links", vault_id))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .set_json(json!({ "links": ["salaries", "plan"] }))
+        .to_request();
+    let links: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(links["resolved"]["salaries"]["exists"], false);
+    assert_eq!(links["resolved"]["plan"]["path"], "drafts/plan.md");
+
+    // Sync reports hidden files as deleted and daily notes honour the rules.
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/sync", vault_id))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .set_json(json!({ "files": [{ "path": "secret/salaries.md", "client_mtime": 0 }] }))
+        .to_request();
+    let sync: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(sync["deleted"], json!(["secret/salaries.md"]));
+    assert_eq!(sync["server_newer"], json!([]));
+
+    for (date, status) in [("secret/2024-01-01", 403), ("drafts/2024-01-01", 403)] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/vaults/{}/daily", vault_id))
+            .insert_header((header::AUTHORIZATION, alice.clone()))
+            .set_json(json!({ "date": date }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+    assert!(!vault_dir.join("drafts/2024-01-01.md").exists());
+
+    // Trashed files keep the rules of their original path.
+    std::fs::write(vault_dir.join("secret/old.md"), "# Old").unwrap();
+    for path in ["secret/old.md", "hr/policy.md"] {
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/vaults/{}/files/{}", vault_id, path))
+            .insert_header((header::AUTHORIZATION, admin.clone()))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+    let trash_uri = format!("/api/vaults/{}/trash", vault_id);
+    let list_trash = |token: &str| {
+        test::TestRequest::get()
+            .uri(&trash_uri)
+            .insert_header((header::AUTHORIZATION, token.to_string()))
+            .to_request()
+    };
+    let trash: serde_json::Value = test::call_and_read_body_json(&app, list_trash(&alice)).await;
+    let trash = trash.as_array().unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0]["original_path"], "hr/policy.md");
+    let policy_trash_name = trash[0]["trash_name"].as_str().unwrap().to_string();
+
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/{}/restore", trash_uri, policy_trash_name))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/restore", trash_uri))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .set_json(json!({ "trash_names": [policy_trash_name] }))
+        .to_request();
+    let restored: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(restored["succeeded"], json!([]));
+    assert_eq!(restored["failed"].as_array().unwrap().len(), 1);
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/purge", trash_uri))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .set_json(json!({}))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::delete()
+        .uri(&format!("{}/{}", trash_uri, policy_trash_name))
+        .insert_header((header::AUTHORIZATION, alice.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+    let trash: serde_json::Value = test::call_and_read_body_json(&app, list_trash(&admin)).await;
+    assert_eq!(trash.as_array().unwrap().len(), 2);
+
+    // Computed fields only count entities the caller can read.
+    for (path, frontmatter) in [
+        ("notes/Guild.md", "codex_type: faction"),
+        (
+            "notes/Ann.md",
+            "codex_type: character\nmember_of: '[[Guild]]'",
+        ),
+        (
+            "secret/Spy.md",
+            "codex_type: character\nmember_of: '[[Guild]]'",
+        ),
+    ] {
+        std::fs::write(vault_dir.join(path), format!("---\n{frontmatter}\n---\n")).unwrap();
+    }
+    ReindexService::reindex_vault(&db, &vault_id, &vault_dir.to_string_lossy())
+        .await
+        .unwrap();
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/vaults/{}/types/entity/faction", vault_id))
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .set_json(json!({ "name": "Faction", "fields": [{
+            "key": "members", "label": "Members", "type": "number",
+            "computed": { "kind": "count", "relation": "member_of" },
+        }] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+    let members = |token: &str| {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/vaults/{}/entities?entity_type=faction&filter=members%3E%3D1",
+                vault_id
+            ))
+            .insert_header((header::AUTHORIZATION, token.to_string()))
+            .to_request()
+    };
+    for (token, count) in [(&alice, 1), (&admin, 2)] {
+        let body: serde_json::Value = test::call_and_read_body_json(&app, members(token)).await;
+        let guild = &body["entities"][0];
+        let fields: serde_json::Value =
+            serde_json::from_str(guild["fields"].as_str().unwrap()).unwrap();
+        assert_eq!(fields["members"], count);
+    }
+
+    // Removing the rule restores access.
+    let req = test::TestRequest::get()
+        .uri(&acls_uri)
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .to_request();
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed.as_array().unwrap().len(), 4);
+    let req = test::TestRequest::delete()
+        .uri(&format!("{}/{}", acls_uri, secret_acl_id))
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 204);
+    assert_eq!(
+        test::call_service(&app, read_status(&alice, "secret/salaries.md"))
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+}
@@ -1,8 +1,9 @@
 use actix_web::{test, web, App};
 use codex::db::Database;
-use codex::routes::{entities, AppState};
+use codex::routes::{entities, files, AppState};
 use codex::services::{
-    EntityTypeRegistry, MarkdownParser, ReindexService, RelationTypeRegistry, SearchIndex,
+    EntityTypeRegistry, MarkdownParser, ReindexService, RelationService, RelationTypeRegistry,
+    SearchIndex,
 };
 use codex::watcher::FileWatcher;
 use std::sync::Arc;
@@ -14,7 +15,7 @@ async fn setup(temp_dir: &TempDir) -> (web::Data<AppState>, String) {
     let db_url = format!("sqlite://{}", db_path.display());
     let db = Database::new(&db_url).await.unwrap();
 
-    let search_index = SearchIndex::new();
+    let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
     let (watcher, _) = FileWatcher::new().unwrap();
     let watcher = Arc::new(Mutex::new(watcher));
     let (event_tx, _) = broadcast::channel(100);
@@ -31,7 +32,8 @@ async fn setup(temp_dir: &TempDir) -> (web::Data<AppState>, String) {
         document_parser: Arc::new(MarkdownParser),
         entity_type_registry: EntityTypeRegistry::new(),
         relation_type_registry: RelationTypeRegistry::new(),
-        plugins_dir: String::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
     });
 
     let vault_dir = temp_dir.path().join("vault");
@@ -82,6 +84,8 @@ async fn test_list_entity_types_with_registered_type() {
             labels: vec!["graphable".into()],
             display_field: Some("full_name".into()),
             show_on_create: vec!["full_name".into()],
+            extends: None,
+            mixins: vec![],
             fields: vec![],
         })
         .await;
@@ -361,3 +365,1383 @@ async fn test_entity_count_in_db_after_reindex() {
 
     assert_eq!(count.0, 1, "should have 1 indexed entity");
 }
+
+// ── Schema validation ──────────────────────────────────────────────────────
+
+async fn register_character_schema(state: &AppState) {
+    let field = |key: &str, field_type: codex::models::FieldType| codex::models::FieldSchema {
+        key: key.into(),
+        label: key.into(),
+        field_type,
+        required: false,
+        item_type: None,
+        values: vec![],
+        default: None,
+        target_label: None,
+        relation: None,
+        description: None,
+        computed: None,
+    };
+    let mut name = field("name", codex::models::FieldType::String);
+    name.required = true;
+    let mut status = field("status", codex::models::FieldType::Enum);
+    status.values = vec!["alive".into(), "dead".into()];
+    let mut ally = field("ally", codex::models::FieldType::EntityRef);
+    ally.target_label = Some("person".into());
+    state
+        .entity_type_registry
+        .register(codex::models::EntityTypeSchema {
+            id: "character".into(),
+            plugin_id: "worldbuilding".into(),
+            name: "Character".into(),
+            icon: None,
+            color: None,
+            template: None,
+            labels: vec!["person".into()],
+            display_field: Some("name".into()),
+            show_on_create: vec![],
+            extends: None,
+            mixins: vec![],
+            fields: vec![name, status, ally],
+        })
+        .await;
+}
+
+#[actix_web::test]
+async fn test_entity_validation_mode_controls_saves() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    register_character_schema(&state).await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure)
+            .configure(files::configure),
+    )
+    .await;
+
+    let invalid = "---\ncodex_type: character\nstatus: undead\n---\n# Nobody\n";
+    let save = |content: &str| {
+        test::TestRequest::put()
+            .uri(&format!("/api/vaults/{vault_id}/files/nobody.md"))
+            .set_json(serde_json::json!({ "content": content }))
+            .to_request()
+    };
+
+    // The default mode only warns.
+    let resp = test::call_service(&app, save(invalid)).await;
+    assert_eq!(resp.status().as_u16(), 200);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/vaults/{vault_id}/entities/validation"))
+        .set_json(serde_json::json!({ "mode": "reject" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let resp = test::call_service(&app, save(invalid)).await;
+    assert_eq!(resp.status().as_u16(), 422);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "VALIDATION_FAILED");
+    let codes: Vec<(&str, &str)> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["field"].as_str().unwrap(), e["code"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        codes,
+        vec![("name", "required"), ("status", "invalid_value")]
+    );
+
+    let valid = "---\ncodex_type: character\nname: Nobody\nstatus: dead\n---\n# Nobody\n";
+    let resp = test::call_service(&app, save(valid)).await;
+    assert_eq!(resp.status().as_u16(), 200);
+
+    // Notes that are not entities are never checked.
+    let resp = test::call_service(&app, save("# Just a note\n")).await;
+    assert_eq!(resp.status().as_u16(), 200);
+}
+
+#[actix_web::test]
+async fn test_reject_mode_covers_created_uploaded_and_imported_notes() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    register_character_schema(&state).await;
+    state
+        .db
+        .set_vault_entity_validation(&vault_id, codex::models::EntityValidationMode::Reject)
+        .await
+        .unwrap();
+    let vault_dir = temp.path().join("vault");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure)
+            .configure(files::configure),
+    )
+    .await;
+
+    let invalid = "---\ncodex_type: character\nstatus: undead\n---\n# Nobody\n";
+    let valid = "---\ncodex_type: character\nname: Alice\n---\n# Alice\n";
+
+    for (path, content, status) in [("nobody.md", invalid, 422), ("alice.md", valid, 201)] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/vaults/{vault_id}/files"))
+            .set_json(serde_json::json!({ "path": path, "content": content }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+    assert!(!vault_dir.join("nobody.md").exists());
+
+    let boundary = "codex-boundary";
+    let multipart = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"uploaded.md\"\r\n\
+         Content-Type: text/markdown\r\n\r\n{invalid}\r\n--{boundary}--\r\n"
+    );
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{vault_id}/upload"))
+        .insert_header((
+            "Content-Type",
+            format!("multipart/form-data; boundary={boundary}"),
+        ))
+        .set_payload(multipart)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 422);
+    assert!(!vault_dir.join("uploaded.md").exists());
+
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, content) in [("imported/ok.md", valid), ("imported/bad.md", invalid)] {
+        archive
+            .start_file(name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        std::io::Write::write_all(&mut archive, content.as_bytes()).unwrap();
+    }
+    let archive = archive.finish().unwrap().into_inner();
+    let req = test::TestRequest::post()
+        .uri(&format!(
+            "/api/vaults/{vault_id}/import-archive?archive_type=zip"
+        ))
+        .set_payload(archive)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 422);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("imported/bad.md"));
+    assert!(!vault_dir.join("imported/ok.md").exists());
+}
+
+#[actix_web::test]
+async fn test_entity_validation_report_lists_invalid_entities() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    register_character_schema(&state).await;
+
+    let vault_dir = temp.path().join("vault");
+    std::fs::write(
+        vault_dir.join("alice.md"),
+        "---\ncodex_type: character\nname: Alice\nstatus: alive\n---\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("bob.md"),
+        "---\ncodex_type: character\nstatus: zombie\n---\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("keep.md"),
+        "---\ncodex_type: location\nname: Keep\n---\n",
+    )
+    .unwrap();
+    ReindexService::reindex_vault(&state.db, &vault_id, vault_dir.to_str().unwrap())
+        .await
+        .expect("reindex should succeed");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{vault_id}/entities/validation"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["mode"], "warn");
+    assert_eq!(body["checked"], 2);
+    assert_eq!(body["unknown_type"], 1);
+    let invalid = body["invalid"].as_array().unwrap();
+    assert_eq!(invalid.len(), 1);
+    assert_eq!(invalid[0]["path"], "bob.md");
+    assert_eq!(invalid[0]["errors"].as_array().unwrap().len(), 2);
+}
+
+// ── Entity writes ──────────────────────────────────────────────────────────
+
+#[actix_web::test]
+async fn test_entity_create_update_delete() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    register_character_schema(&state).await;
+    let vault_dir = temp.path().join("vault");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+
+    let create = |body: serde_json::Value| {
+        test::TestRequest::post()
+            .uri(&format!("/api/vaults/{vault_id}/entities"))
+            .set_json(body)
+            .to_request()
+    };
+
+    // Alice refers to Dave before Dave exists.
+    let resp = test::call_service(
+        &app,
+        create(serde_json::json!({
+            "entity_type": "character",
+            "folder": "people",
+            "fields": { "status": "alive", "name": "Alice", "ally": "[[Dave]]" },
+        })),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let alice: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(alice["path"], "people/Alice.md");
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join("people/Alice.md")).unwrap(),
+        "---\ncodex_type: character\ncodex_plugin: worldbuilding\nname: Alice\nstatus: alive\n\
+         ally: '[[Dave]]'\n---\n# Alice\n"
+    );
+    let alice_id = alice["id"].as_str().unwrap().to_string();
+
+    let resp = test::call_service(
+        &app,
+        create(serde_json::json!({
+            "entity_type": "character",
+            "fields": { "name": "Dave" },
+        })),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let dave: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(dave["path"], "Dave.md");
+
+    // Creating Dave resolved Alice's reference right away.
+    let relations = RelationService::get_for_entity(&state.db, &alice_id)
+        .await
+        .unwrap();
+    assert!(relations
+        .iter()
+        .any(|r| r.from_entity_id == alice_id && r.to_entity_id == dave["id"]));
+
+    // A second entity with the same name gets a numbered file.
+    let resp = test::call_service(
+        &app,
+        create(serde_json::json!({
+            "entity_type": "character",
+            "fields": { "name": "Dave" },
+        })),
+    )
+    .await;
+    let second: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(second["path"], "Dave 2.md");
+
+    // Values are checked against the schema.
+    let resp = test::call_service(
+        &app,
+        create(serde_json::json!({
+            "entity_type": "character",
+            "fields": { "name": "Eve", "status": "undead" },
+        })),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 422);
+    assert!(!vault_dir.join("Eve.md").exists());
+
+    // Updates rewrite only the frontmatter.
+    let alice_path = vault_dir.join("people/Alice.md");
+    let mut raw = std::fs::read_to_string(&alice_path).unwrap();
+    raw.push_str("\nShe keeps *bees*.\r\n");
+    std::fs::write(&alice_path, raw).unwrap();
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/vaults/{vault_id}/entities/{alice_id}"))
+        .set_json(serde_json::json!({ "fields": { "status": "dead", "ally": null } }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 200);
+    assert_eq!(
+        std::fs::read_to_string(&alice_path).unwrap(),
+        "---\ncodex_type: character\ncodex_plugin: worldbuilding\nname: Alice\nstatus: dead\n\
+         ---\n# Alice\n\nShe keeps *bees*.\r\n"
+    );
+    let relations = RelationService::get_for_entity(&state.db, &alice_id)
+        .await
+        .unwrap();
+    assert!(
+        relations.is_empty(),
+        "removing the field drops its relation"
+    );
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/vaults/{vault_id}/entities/{alice_id}"))
+        .set_json(serde_json::json!({ "fields": { "status": "asleep" } }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 422);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/vaults/{vault_id}/entities/{alice_id}"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 204);
+    assert!(!alice_path.exists());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{vault_id}/entities/{alice_id}"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
+}
+
+// ── Explicit relations ─────────────────────────────────────────────────────
+
+async fn register_member_of(state: &AppState) {
+    let mut rank = codex::models::FieldSchema {
+        key: "rank".into(),
+        label: "Rank".into(),
+        field_type: codex::models::FieldType::Enum,
+        required: false,
+        item_type: None,
+        values: vec!["captain".into(), "recruit".into()],
+        default: None,
+        target_label: None,
+        relation: None,
+        description: None,
+        computed: None,
+    };
+    rank.required = true;
+    state
+        .relation_type_registry
+        .register(codex::models::RelationTypeSchema {
+            id: "member_of".into(),
+            plugin_id: "worldbuilding".into(),
+            name: "member_of".into(),
+            label: "Member Of".into(),
+            from_label: Some("person".into()),
+            to_label: Some("organization".into()),
+            directed: true,
+            inverse_label: Some("has_member".into()),
+            color: None,
+            metadata_fields: vec![rank],
+        })
+        .await;
+    state
+        .entity_type_registry
+        .register(codex::models::EntityTypeSchema {
+            id: "faction".into(),
+            plugin_id: "worldbuilding".into(),
+            name: "Faction".into(),
+            icon: None,
+            color: None,
+            template: None,
+            labels: vec!["organization".into()],
+            display_field: None,
+            show_on_create: vec![],
+            extends: None,
+            mixins: vec![],
+            fields: vec![],
+        })
+        .await;
+}
+
+#[actix_web::test]
+async fn test_explicit_relations_are_validated_and_persisted() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    register_character_schema(&state).await;
+    register_member_of(&state).await;
+
+    let vault_dir = temp.path().join("vault");
+    let vault_path = vault_dir.to_str().unwrap().to_string();
+    std::fs::write(
+        vault_dir.join("alice.md"),
+        "---\ncodex_type: character\nname: Alice\nally: '[[bob]]'\n---\nAlice's story.\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("bob.md"),
+        "---\ncodex_type: character\nname: Bob\n---\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("Guild.md"),
+        "---\ncodex_type: faction\n---\n",
+    )
+    .unwrap();
+    ReindexService::reindex_vault(&state.db, &vault_id, &vault_path)
+        .await
+        .unwrap();
+    let id_of = |path: &str| codex::services::entity_service::entity_id(&vault_id, path);
+    let (alice, guild) = (id_of("alice.md"), id_of("Guild.md"));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+    let create = |from: &str, to: &str, metadata: serde_json::Value| {
+        test::TestRequest::post()
+            .uri(&format!("/api/vaults/{vault_id}/relations"))
+            .set_json(serde_json::json!({
+                "from_entity_id": from,
+                "to_entity_id": to,
+                "relation_type": "member_of",
+                "metadata": metadata,
+            }))
+            .to_request()
+    };
+
+    let resp = test::call_service(
+        &app,
+        create(&alice, &guild, serde_json::json!({ "rank": "captain" })),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let relation: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(relation["source"], "explicit");
+    assert_eq!(relation["direction"], "forward");
+    let relation_id = relation["id"].as_str().unwrap().to_string();
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join("alice.md")).unwrap(),
+        "---\ncodex_type: character\nname: Alice\nally: '[[bob]]'\ncodex_relations:\n\
+         - type: member_of\n  target: '[[Guild]]'\n  metadata:\n    rank: captain\n---\n\
+         Alice's story.\n"
+    );
+    let inverse = RelationService::get_for_entity(&state.db, &guild)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|r| r.from_entity_id == guild && r.relation_type == "has_member")
+        .expect("inverse edge");
+
+    let resp = test::call_service(
+        &app,
+        create(&alice, &guild, serde_json::json!({ "rank": "captain" })),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 409);
+
+    // Labels of both ends and metadata are checked.
+    for (from, to, metadata) in [
+        (&guild, &alice, serde_json::json!({ "rank": "captain" })),
+        (&alice, &guild, serde_json::json!({ "rank": "king" })),
+        (&alice, &guild, serde_json::json!({})),
+        (
+            &alice,
+            &guild,
+            serde_json::json!({ "rank": "captain", "mood": "grim" }),
+        ),
+    ] {
+        let resp = test::call_service(&app, create(from, to, metadata)).await;
+        assert_eq!(resp.status().as_u16(), 422);
+    }
+
+    // The relation survives a reindex with the same id.
+    ReindexService::reindex_vault(&state.db, &vault_id, &vault_path)
+        .await
+        .unwrap();
+    let reindexed = RelationService::get(&state.db, &relation_id)
+        .await
+        .unwrap()
+        .expect("relation kept");
+    assert_eq!(reindexed.metadata.as_deref(), Some(r#"{"rank":"captain"}"#));
+    assert!(RelationService::get(&state.db, &inverse.id)
+        .await
+        .unwrap()
+        .is_some());
+
+    // Either edge can be edited.
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/vaults/{vault_id}/relations/{}", inverse.id))
+        .set_json(serde_json::json!({ "metadata": { "rank": "recruit" } }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 200);
+    let updated: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(updated["id"], relation_id.as_str());
+    assert_eq!(updated["metadata"], r#"{"rank":"recruit"}"#);
+
+    // Relations derived from fields are edited through the field.
+    let field_relation = RelationService::get_for_entity(&state.db, &alice)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|r| r.source == "field")
+        .expect("ally relation");
+    let req = test::TestRequest::delete()
+        .uri(&format!(
+            "/api/vaults/{vault_id}/relations/{}",
+            field_relation.id
+        ))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/vaults/{vault_id}/relations/{relation_id}"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 204);
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join("alice.md")).unwrap(),
+        "---\ncodex_type: character\nname: Alice\nally: '[[bob]]'\n---\nAlice's story.\n"
+    );
+    let remaining = RelationService::get_for_entity(&state.db, &guild)
+        .await
+        .unwrap();
+    assert!(remaining.is_empty());
+}
+
+#[actix_web::test]
+async fn test_graph_neighborhood_path_components_and_stats() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    register_character_schema(&state).await;
+
+    let vault_dir = temp.path().join("vault");
+    let vault_path = vault_dir.to_str().unwrap().to_string();
+    for (name, ally) in [
+        ("alice", Some("bob")),
+        ("bob", Some("carol")),
+        ("carol", None),
+        ("dave", None),
+    ] {
+        let ally = ally
+            .map(|a| format!("ally: '[[{a}]]'\n"))
+            .unwrap_or_default();
+        std::fs::write(
+            vault_dir.join(format!("{name}.md")),
+            format!(
+                "---\ncodex_type: character\ncodex_labels: [person]\nname: {name}\n{ally}---\n"
+            ),
+        )
+        .unwrap();
+    }
+    ReindexService::reindex_vault(&state.db, &vault_id, &vault_path)
+        .await
+        .unwrap();
+    let id_of = |path: &str| codex::services::entity_service::entity_id(&vault_id, path);
+    let (alice, carol, dave) = (id_of("alice.md"), id_of("carol.md"), id_of("dave.md"));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+    let get = |uri: String| test::TestRequest::get().uri(&uri).to_request();
+    let titles = |graph: &serde_json::Value| -> Vec<String> {
+        graph["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let resp = test::call_service(
+        &app,
+        get(format!(
+            "/api/vaults/{vault_id}/graph/neighborhood?entity={alice}"
+        )),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 200);
+    let near: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(titles(&near), ["alice", "bob"]);
+    assert_eq!(near["nodes"][0]["node_type"], "entity");
+    assert_eq!(near["edges"][0]["relation_type"], "ally");
+    assert_eq!(near["edges"][0]["edge_type"], "relation");
+
+    let resp = test::call_service(
+        &app,
+        get(format!(
+            "/api/vaults/{vault_id}/graph/neighborhood?entity={alice}&depth=2&relation_types=ally&labels=person"
+        )),
+    )
+    .await;
+    let far: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(titles(&far), ["alice", "bob", "carol"]);
+    assert_eq!(far["edges"].as_array().unwrap().len(), 2);
+
+    let resp = test::call_service(
+        &app,
+        get(format!(
+            "/api/vaults/{vault_id}/graph/neighborhood?entity={alice}&relation_types=member_of"
+        )),
+    )
+    .await;
+    let none: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(titles(&none), ["alice"]);
+
+    let resp = test::call_service(
+        &app,
+        get(format!(
+            "/api/vaults/{vault_id}/graph/neighborhood?entity={alice}&depth=9"
+        )),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let resp = test::call_service(
+        &app,
+        get(format!(
+            "/api/vaults/{vault_id}/graph/neighborhood?entity=missing"
+        )),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 404);
+
+    let resp = test::call_service(
+        &app,
+        get(format!(
+            "/api/vaults/{vault_id}/graph/path?from={carol}&to={alice}"
+        )),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 200);
+    let path: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(titles(&path), ["carol", "bob", "alice"]);
+    let resp = test::call_service(
+        &app,
+        get(format!(
+            "/api/vaults/{vault_id}/graph/path?from={alice}&to={dave}"
+        )),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 404);
+
+    let resp = test::call_service(
+        &app,
+        get(format!("/api/vaults/{vault_id}/graph/components")),
+    )
+    .await;
+    let components: serde_json::Value = test::read_body_json(resp).await;
+    let sizes: Vec<usize> = components["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["nodes"].as_array().unwrap().len())
+        .collect();
+    assert_eq!(sizes, [3, 1]);
+
+    let resp = test::call_service(
+        &app,
+        get(format!("/api/vaults/{vault_id}/graph/stats?limit=1")),
+    )
+    .await;
+    let stats: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(stats["node_count"], 4);
+    assert_eq!(stats["edge_count"], 2);
+    assert_eq!(stats["component_count"], 2);
+    assert_eq!(stats["nodes"][0]["label"], "bob");
+    assert_eq!(stats["nodes"][0]["degree"], 2);
+    // bob links alice and carol: one of the three pairs without him
+    let betweenness = stats["nodes"][0]["betweenness"].as_f64().unwrap();
+    assert!((betweenness - 1.0 / 3.0).abs() < 1e-9);
+}
+
+#[actix_web::test]
+async fn test_get_graph_combines_links_tags_and_relations() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    register_character_schema(&state).await;
+
+    let vault_dir = temp.path().join("vault");
+    std::fs::create_dir_all(vault_dir.join("notes")).unwrap();
+    std::fs::write(
+        vault_dir.join("alice.md"),
+        "---\ncodex_type: character\nname: Alice\nally: '[[bob]]'\n---\nSee [[Journal]]. #cast\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("bob.md"),
+        "---\ncodex_type: character\nname: Bob\n---\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("notes/Journal.md"),
+        "Met [[alice]] twice: [[alice]]. Then [[Missing]].\n",
+    )
+    .unwrap();
+    std::fs::write(vault_dir.join("notes/Stray.md"), "Nothing here.\n").unwrap();
+    ReindexService::reindex_vault(&state.db, &vault_id, vault_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let alice = codex::services::entity_service::entity_id(&vault_id, "alice.md");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+    let graph = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/vaults/{vault_id}/graph?{query}"))
+            .to_request()
+    };
+    let ids = |body: &serde_json::Value| -> Vec<String> {
+        body["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let resp = test::call_service(
+        &app,
+        graph("include=links,tags,relations&orphans=false&unresolved=true"),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let nodes = ids(&body);
+    assert!(nodes.contains(&alice));
+    assert!(nodes.contains(&"#cast".to_string()));
+    assert!(nodes.contains(&"Missing.md".to_string()));
+    assert!(!nodes.contains(&"notes/Stray.md".to_string()));
+    let edge_types: Vec<&str> = body["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["edge_type"].as_str().unwrap())
+        .collect();
+    for edge_type in ["relation", "link", "tag"] {
+        assert!(edge_types.contains(&edge_type), "missing {edge_type} edge");
+    }
+    let journal_link = body["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["source"] == "notes/Journal.md" && e["target"] == alice.as_str())
+        .unwrap();
+    assert_eq!(journal_link["count"], 2);
+    let alice_node = body["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|n| n["id"] == alice.as_str())
+        .unwrap();
+    assert_eq!(alice_node["node_type"], "entity");
+    // Two links from the journal
+    assert_eq!(alice_node["size"], 3.0);
+
+    let resp = test::call_service(&app, graph("include=links&folder=notes")).await;
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(ids(&body), ["notes/Journal.md", "notes/Stray.md"]);
+    assert!(body["edges"].as_array().unwrap().is_empty());
+
+    let resp = test::call_service(&app, graph("include=links,backlinks")).await;
+    assert_eq!(resp.status().as_u16(), 400);
+}
+
+// ── Vault-local types ──────────────────────────────────────────────────────
+
+#[actix_web::test]
+async fn test_vault_types_crud_reindexes_affected_entities() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    register_character_schema(&state).await;
+    let vault_dir = temp.path().join("vault");
+    std::fs::write(
+        vault_dir.join("Ann.md"),
+        "---\ncodex_type: character\nname: Ann\n---\n# Ann\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("Bob.md"),
+        "---\ncodex_type: villain\nname: Bob\n---\n# Bob\n",
+    )
+    .unwrap();
+    ReindexService::reindex_vault(&state.db, &vault_id, &vault_dir.to_string_lossy())
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+    let type_uri = |kind: &str, id: &str| format!("/api/vaults/{vault_id}/types/{kind}/{id}");
+
+    // A local "character" shadows the plugin's within this vault.
+    let req = test::TestRequest::put()
+        .uri(&type_uri("entity", "character"))
+        .set_json(serde_json::json!({ "name": "Hero", "labels": ["hero"] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 200);
+    let change: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(change["reindexed"], 1);
+    assert_eq!(
+        change["conflicts"],
+        serde_json::json!([{ "kind": "entity", "id": "character", "plugin_id": "worldbuilding" }])
+    );
+    let file = vault_dir.join(".codex/types/character.toml");
+    assert!(std::fs::read_to_string(&file)
+        .unwrap()
+        .starts_with("[entity_type]\nname = \"Hero\"\n"));
+
+    let ann_id = codex::services::entity_service::entity_id(&vault_id, "Ann.md");
+    let entity = |id: String| {
+        test::TestRequest::get()
+            .uri(&format!("/api/vaults/{vault_id}/entities/{id}"))
+            .to_request()
+    };
+    let ann: serde_json::Value = test::call_and_read_body_json(&app, entity(ann_id.clone())).await;
+    assert_eq!(ann["labels"], "[\"hero\"]");
+
+    // Outside this vault the plugin type is unchanged.
+    let global = state.entity_type_registry.get_by_id("character").await;
+    assert_eq!(global.unwrap().name, "Character");
+
+    // Relation types are named by their `name`, which must match the URL.
+    let req = test::TestRequest::put()
+        .uri(&type_uri("relation", "rival_of"))
+        .set_json(serde_json::json!({ "name": "enemy_of", "label": "Enemy Of" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 400);
+    let req = test::TestRequest::put()
+        .uri(&type_uri("relation", "rival_of"))
+        .set_json(serde_json::json!({ "name": "rival_of", "label": "Rival Of" }))
+        .to_request();
+    let change: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(change["reindexed"], 0);
+    assert_eq!(change["conflicts"], serde_json::json!([]));
+
+    std::fs::write(vault_dir.join(".codex/types/broken.toml"), "name = 1\n").unwrap();
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{vault_id}/types"))
+        .to_request();
+    let report: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(report["entity_types"][0]["id"], "character");
+    assert_eq!(
+        report["entity_types"][0]["plugin_id"],
+        format!("vault:{vault_id}")
+    );
+    assert_eq!(report["relation_types"][0]["name"], "rival_of");
+    assert_eq!(report["conflicts"].as_array().unwrap().len(), 1);
+    assert_eq!(report["errors"][0]["file"], "broken.toml");
+
+    // Deleting the local type re-indexes its entities against the plugin's.
+    let req = test::TestRequest::delete()
+        .uri(&type_uri("entity", "character"))
+        .to_request();
+    let change: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(change["reindexed"], 1);
+    assert!(!file.exists());
+    let ann: serde_json::Value = test::call_and_read_body_json(&app, entity(ann_id)).await;
+    assert_eq!(ann["labels"], "[\"person\"]");
+
+    let req = test::TestRequest::delete()
+        .uri(&type_uri("entity", "character"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+}
+
+// ── Schema migration ───────────────────────────────────────────────────────
+
+#[actix_web::test]
+async fn test_migrate_entity_type_dry_run_then_apply() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    let vault_dir = temp.path().join("vault");
+    let ann = "---\ncodex_type: character\nname: Ann\nbirthplace: Rivertown\n---\n# Ann\n";
+    std::fs::write(vault_dir.join("Ann.md"), ann).unwrap();
+    std::fs::write(
+        vault_dir.join("Bob.md"),
+        "---\ncodex_type: character\nname: Bob\n---\n# Bob\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("Rivertown.md"),
+        "---\ncodex_type: location\nbirthplace: nowhere\n---\n",
+    )
+    .unwrap();
+    ReindexService::reindex_vault(&state.db, &vault_id, &vault_dir.to_string_lossy())
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+    let migrate = |dry_run: bool| {
+        test::TestRequest::post()
+            .uri(&format!(
+                "/api/vaults/{vault_id}/entity-types/character/migrate"
+            ))
+            .set_json(serde_json::json!({
+                "dry_run": dry_run,
+                "operations": [
+                    { "op": "rename_field", "from": "birthplace", "to": "origin" },
+                    { "op": "change_type", "field": "origin", "to": "entity_ref" },
+                    { "op": "set_default", "field": "status", "value": "alive" },
+                ],
+            }))
+            .to_request()
+    };
+
+    let report: serde_json::Value = test::call_and_read_body_json(&app, migrate(true)).await;
+    assert_eq!(report["checked"], 2);
+    assert_eq!(report["migrated"], 0);
+    assert_eq!(report["entities"].as_array().unwrap().len(), 2);
+    assert_eq!(report["entities"][0]["path"], "Ann.md");
+    assert_eq!(
+        report["entities"][0]["changes"],
+        serde_json::json!([
+            { "field": "birthplace", "before": "Rivertown", "after": null },
+            { "field": "origin", "before": null, "after": "[[Rivertown]]" },
+            { "field": "status", "before": null, "after": "alive" },
+        ])
+    );
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join("Ann.md")).unwrap(),
+        ann
+    );
+
+    let report: serde_json::Value = test::call_and_read_body_json(&app, migrate(false)).await;
+    assert_eq!(report["migrated"], 2);
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join("Ann.md")).unwrap(),
+        "---\ncodex_type: character\nname: Ann\norigin: '[[Rivertown]]'\nstatus: alive\n---\n# Ann\n"
+    );
+    // Other types are left alone.
+    assert!(std::fs::read_to_string(vault_dir.join("Rivertown.md"))
+        .unwrap()
+        .contains("birthplace: nowhere"));
+
+    // The converted field now yields a relation.
+    let ann_id = codex::services::entity_service::entity_id(&vault_id, "Ann.md");
+    let relations = RelationService::get_for_entity(&state.db, &ann_id)
+        .await
+        .unwrap();
+    assert!(relations
+        .iter()
+        .any(|r| r.relation_type == "origin" && r.direction == "forward"));
+
+    // Running it again changes nothing.
+    let report: serde_json::Value = test::call_and_read_body_json(&app, migrate(false)).await;
+    assert_eq!(report["migrated"], 0);
+    assert!(report["entities"].as_array().unwrap().is_empty());
+
+    let req = test::TestRequest::post()
+        .uri(&format!(
+            "/api/vaults/{vault_id}/entity-types/character/migrate"
+        ))
+        .set_json(serde_json::json!({
+            "operations": [{ "op": "drop_field", "field": "codex_type" }],
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 400);
+}
+
+// ── Timeline ───────────────────────────────────────────────────────────────
+
+#[actix_web::test]
+async fn test_timeline_orders_entities_in_custom_and_gregorian_calendars() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    let vault_dir = temp.path().join("vault");
+    std::fs::create_dir_all(vault_dir.join(".codex/calendars")).unwrap();
+    std::fs::write(
+        vault_dir.join(".codex/calendars/harptos.toml"),
+        "[calendar]\nname = \"Harptos\"\nera = \"DR\"\nmonths = [\n\
+         { name = \"Hammer\", days = 30 },\n{ name = \"Alturiak\", days = 30 },\n]\n",
+    )
+    .unwrap();
+    let notes = [
+        (
+            "Battle.md",
+            "codex_type: event\ndate: 15 Alturiak 1492 DR\nlocation: '[[Waterdeep]]'",
+        ),
+        (
+            "Founding.md",
+            "codex_type: event\ndate: 1 Hammer 1300\nend_date: 1310",
+        ),
+        ("Party.md", "codex_type: event\ndate: soon"),
+        ("Waterdeep.md", "codex_type: location"),
+        (
+            "Launch.md",
+            "codex_type: project\nstart: 2026-01-10\nend: 2026-02-01",
+        ),
+        ("Retro.md", "codex_type: project\nstart: 2025-06-01"),
+    ];
+    for (name, frontmatter) in notes {
+        std::fs::write(vault_dir.join(name), format!("---\n{frontmatter}\n---\n")).unwrap();
+    }
+    ReindexService::reindex_vault(&state.db, &vault_id, &vault_dir.to_string_lossy())
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+    let get = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/vaults/{vault_id}/timeline?{query}"))
+            .to_request()
+    };
+    let paths = |timeline: &serde_json::Value| -> Vec<String> {
+        timeline["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["path"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let timeline: serde_json::Value =
+        test::call_and_read_body_json(&app, get("calendar=harptos&types=event")).await;
+    assert_eq!(paths(&timeline), ["Founding.md", "Battle.md"]);
+    let founding = &timeline["events"][0];
+    assert_eq!(founding["start"]["label"], "1 Hammer 1300 DR");
+    assert_eq!(founding["end_field"], "end_date");
+    assert_eq!(founding["end"]["label"], "1310 DR");
+    let battle = &timeline["events"][1];
+    assert_eq!(battle["start"]["ordinal"], 1492 * 60 + 30 + 14);
+    assert_eq!(battle["relations"][0]["relation_type"], "location");
+    assert_eq!(battle["relations"][0]["title"], "Waterdeep");
+    assert_eq!(timeline["unparsed"][0]["path"], "Party.md");
+
+    // Ranges keep events that overlap them.
+    let timeline: serde_json::Value =
+        test::call_and_read_body_json(&app, get("calendar=harptos&types=event&from=1305&to=1400"))
+            .await;
+    assert_eq!(paths(&timeline), ["Founding.md"]);
+
+    let timeline: serde_json::Value =
+        test::call_and_read_body_json(&app, get("types=project")).await;
+    assert_eq!(paths(&timeline), ["Retro.md", "Launch.md"]);
+    assert_eq!(timeline["events"][1]["start"]["weekday"], "Saturday");
+    let timeline: serde_json::Value =
+        test::call_and_read_body_json(&app, get("types=project&from=2026-01-15")).await;
+    assert_eq!(paths(&timeline), ["Launch.md"]);
+
+    // Only the chosen start field is read.
+    let timeline: serde_json::Value =
+        test::call_and_read_body_json(&app, get("start=end&types=project")).await;
+    assert_eq!(paths(&timeline), ["Launch.md"]);
+    assert_eq!(timeline["events"][0]["start"]["label"], "1 February 2026");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{vault_id}/calendars"))
+        .to_request();
+    let calendars: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let ids: Vec<&str> = calendars["calendars"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["gregorian", "harptos"]);
+    assert_eq!(calendars["calendars"][1]["year_length"], 60);
+
+    let resp = test::call_service(&app, get("calendar=missing")).await;
+    assert_eq!(resp.status().as_u16(), 404);
+    let resp = test::call_service(&app, get("from=someday")).await;
+    assert_eq!(resp.status().as_u16(), 400);
+}
+
+// ── Computed fields ────────────────────────────────────────────────────────
+
+#[actix_web::test]
+async fn test_computed_fields_are_computed_on_read_and_filterable() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    let vault_dir = temp.path().join("vault");
+    std::fs::create_dir_all(vault_dir.join(".codex/calendars")).unwrap();
+    std::fs::write(
+        vault_dir.join(".codex/calendars/harptos.toml"),
+        "[calendar]\nname = \"Harptos\"\nera = \"DR\"\ncurrent = \"1 Alturiak 1492\"\n\
+         months = [\n{ name = \"Hammer\", days = 30 },\n{ name = \"Alturiak\", days = 30 },\n]\n",
+    )
+    .unwrap();
+    let notes = [
+        ("Guild.md", "codex_type: faction"),
+        (
+            "Ann.md",
+            "codex_type: character\nmember_of: '[[Guild]]'\nborn: 10 Hammer 1450 DR",
+        ),
+        (
+            "Bob.md",
+            "codex_type: character\nmember_of: '[[Guild]]'\nborn: 5 Alturiak 1460",
+        ),
+        (
+            "Tavern.md",
+            "codex_type: location\nlocated_in: '[[Waterdeep]]'",
+        ),
+        (
+            "Waterdeep.md",
+            "codex_type: location\nlocated_in: '[[Sword Coast]]'",
+        ),
+        ("Sword Coast.md", "codex_type: location"),
+    ];
+    for (name, frontmatter) in notes {
+        std::fs::write(vault_dir.join(name), format!("---\n{frontmatter}\n---\n")).unwrap();
+    }
+    ReindexService::reindex_vault(&state.db, &vault_id, &vault_dir.to_string_lossy())
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+    let types = [
+        (
+            "faction",
+            serde_json::json!({ "key": "members", "label": "Members", "type": "number",
+                "computed": { "kind": "count", "relation": "member_of" } }),
+        ),
+        (
+            "location",
+            serde_json::json!({ "key": "within", "label": "Within", "type": "list",
+                "computed": { "kind": "transitive", "relation": "located_in" } }),
+        ),
+        (
+            "character",
+            serde_json::json!({ "key": "age", "label": "Age", "type": "number", "required": true,
+                "computed": { "kind": "age", "from": "born", "calendar": "harptos" } }),
+        ),
+    ];
+    for (id, field) in types {
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/vaults/{vault_id}/types/entity/{id}"))
+            .set_json(serde_json::json!({ "name": id, "fields": [field] }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+    }
+
+    let fields = |path: &str| {
+        let id = codex::services::entity_service::entity_id(&vault_id, path);
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/vaults/{vault_id}/entities/{id}"))
+            .to_request();
+        let app = &app;
+        async move {
+            let entity: serde_json::Value = test::call_and_read_body_json(app, req).await;
+            serde_json::from_str::<serde_json::Value>(entity["fields"].as_str().unwrap()).unwrap()
+        }
+    };
+    assert_eq!(fields("Guild.md").await["members"], 2);
+    assert_eq!(
+        fields("Tavern.md").await["within"],
+        serde_json::json!(["Waterdeep", "Sword Coast"])
+    );
+    assert_eq!(fields("Ann.md").await["age"], 42);
+    assert_eq!(fields("Bob.md").await["age"], 31);
+
+    let list = |filter: &str| {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/vaults/{vault_id}/entities?filter={}",
+                urlencoding::encode(filter)
+            ))
+            .to_request()
+    };
+    let paths = |body: serde_json::Value| -> Vec<String> {
+        let mut paths: Vec<String> = body["entities"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["path"].as_str().unwrap().to_string())
+            .collect();
+        paths.sort();
+        paths
+    };
+    let body = test::call_and_read_body_json(&app, list("members>=2")).await;
+    assert_eq!(paths(body), ["Guild.md"]);
+    let body = test::call_and_read_body_json(&app, list("within=sword coast")).await;
+    assert_eq!(paths(body), ["Tavern.md", "Waterdeep.md"]);
+    let body = test::call_and_read_body_json(&app, list("age>35")).await;
+    assert_eq!(paths(body), ["Ann.md"]);
+    let resp = test::call_service(&app, list("age")).await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    // Computed fields are not written to frontmatter or required on save,
+    // and values that depend on an edited entity are recomputed.
+    let bob = codex::services::entity_service::entity_id(&vault_id, "Bob.md");
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/vaults/{vault_id}/entities/{bob}"))
+        .set_json(serde_json::json!({ "fields": { "member_of": null } }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 200);
+    let entity: serde_json::Value = test::read_body_json(resp).await;
+    assert!(entity["fields"].as_str().unwrap().contains("\"age\":31"));
+    let raw = std::fs::read_to_string(vault_dir.join("Bob.md")).unwrap();
+    assert!(!raw.contains("age"));
+    assert_eq!(fields("Guild.md").await["members"], 1);
+
+    // Values are not stored, so ages follow the calendar's current date.
+    let ann = codex::services::entity_service::entity_id(&vault_id, "Ann.md");
+    let stored = codex::services::EntityService::get(&state.db, &ann)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!stored.fields.contains("age"));
+    std::fs::write(
+        vault_dir.join(".codex/calendars/harptos.toml"),
+        "[calendar]\nname = \"Harptos\"\nera = \"DR\"\ncurrent = \"1 Alturiak 1502\"\n\
+         months = [\n{ name = \"Hammer\", days = 30 },\n{ name = \"Alturiak\", days = 30 },\n]\n",
+    )
+    .unwrap();
+    assert_eq!(fields("Ann.md").await["age"], 52);
+}
+
+// ── Type inheritance ───────────────────────────────────────────────────────
+
+#[actix_web::test]
+async fn test_vault_types_extend_plugin_types_and_queries_include_subtypes() {
+    let temp = TempDir::new().unwrap();
+    let (state, vault_id) = setup(&temp).await;
+    register_character_schema(&state).await;
+    let vault_dir = temp.path().join("vault");
+    std::fs::write(
+        vault_dir.join("Ann.md"),
+        "---\ncodex_type: character\nname: Ann\n---\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("Zed.md"),
+        "---\ncodex_type: wizard\nname: Zed\nschool: fire\n---\n",
+    )
+    .unwrap();
+    std::fs::write(vault_dir.join("Orb.md"), "---\ncodex_type: item\n---\n").unwrap();
+    ReindexService::reindex_vault(&state.db, &vault_id, &vault_dir.to_string_lossy())
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(entities::configure),
+    )
+    .await;
+    let put_type = |id: &str, body: serde_json::Value| {
+        test::TestRequest::put()
+            .uri(&format!("/api/vaults/{vault_id}/types/entity/{id}"))
+            .set_json(body)
+            .to_request()
+    };
+    let req = put_type(
+        "caster",
+        serde_json::json!({ "name": "Caster", "labels": ["magic"],
+            "fields": [{ "key": "school", "label": "School" }] }),
+    );
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+    let req = put_type(
+        "wizard",
+        serde_json::json!({ "name": "Wizard", "extends": "character", "mixins": ["caster"] }),
+    );
+    let change: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(change["reindexed"], 1);
+    let file = std::fs::read_to_string(vault_dir.join(".codex/types/wizard.toml")).unwrap();
+    assert!(file.contains("extends = \"character\"\nmixins = [\"caster\"]"));
+
+    // The resolved type carries the parent's and mixin's fields and labels.
+    let wizard = state
+        .entity_types(&vault_id)
+        .get_by_id("wizard")
+        .await
+        .unwrap();
+    let keys: Vec<&str> = wizard.fields.iter().map(|f| f.key.as_str()).collect();
+    assert_eq!(keys, ["name", "status", "ally", "school"]);
+    assert_eq!(wizard.labels, ["person", "magic"]);
+    assert_eq!(wizard.display_field.as_deref(), Some("name"));
+    let zed_id = codex::services::entity_service::entity_id(&vault_id, "Zed.md");
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{vault_id}/entities/{zed_id}"))
+        .to_request();
+    let zed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(zed["labels"], "[\"person\",\"magic\"]");
+
+    let list = |entity_type: &str| {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/vaults/{vault_id}/entities?entity_type={entity_type}"
+            ))
+            .to_request()
+    };
+    let paths = |body: serde_json::Value| -> Vec<String> {
+        let mut paths: Vec<String> = body["entities"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["path"].as_str().unwrap().to_string())
+            .collect();
+        paths.sort();
+        paths
+    };
+    let body = test::call_and_read_body_json(&app, list("character")).await;
+    assert_eq!(paths(body), ["Ann.md", "Zed.md"]);
+    let body = test::call_and_read_body_json(&app, list("wizard")).await;
+    assert_eq!(paths(body), ["Zed.md"]);
+
+    // A type that inherits from itself is reported and not registered.
+    let req = put_type(
+        "item",
+        serde_json::json!({ "name": "Item", "extends": "relic" }),
+    );
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+    let req = put_type(
+        "relic",
+        serde_json::json!({ "name": "Relic", "extends": "item" }),
+    );
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{vault_id}/types"))
+        .to_request();
+    let report: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let errors = report["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0]["file"], "item.toml");
+    assert_eq!(
+        errors[0]["message"],
+        "Entity type 'item' inherits from itself (item -> relic -> item)"
+    );
+    assert!(state
+        .entity_types(&vault_id)
+        .get_by_id("item")
+        .await
+        .is_none());
+}
@@ -0,0 +1,192 @@
+use actix_web::http::header;
+use actix_web::{test, web, App};
+use codex::config::AppConfig;
+use codex::db::Database;
+use codex::models::WsMessage;
+use codex::routes::{files, AppState};
+use codex::services::{GitAutoCommitter, MarkdownParser, SearchIndex};
+use codex::watcher::FileWatcher;
+use serde_json::json;
+use std::io::Read;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::{broadcast, Mutex};
+
+#[actix_web::test]
+async fn raw_files_support_ranges_and_archives_stream_with_progress() {
+    let temp_dir = TempDir::new().unwrap();
+    let vault_dir = temp_dir.path().join("vault");
+    std::fs::create_dir_all(vault_dir.join("media")).unwrap();
+    std::fs::create_dir_all(vault_dir.join(".trash")).unwrap();
+    let video: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(vault_dir.join("media/clip.mp4"), &video).unwrap();
+    std::fs::write(vault_dir.join("note.md"), "# Note\n").unwrap();
+    std::fs::write(vault_dir.join(".trash/old.md"), "trashed").unwrap();
+
+    let db_path = temp_dir.path().join("streaming-test.db");
+    let db = Database::new(&format!("sqlite://{}", db_path.display()))
+        .await
+        .unwrap();
+    let vault = db
+        .create_vault("Media".to_string(), vault_dir.to_string_lossy().to_string())
+        .await
+        .unwrap();
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let (ws_tx, mut ws_rx) = broadcast::channel::<WsMessage>(64);
+    let state = web::Data::new(AppState {
+        db: db.clone(),
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: broadcast::channel(100).0,
+        ws_broadcaster: ws_tx,
+        change_log_retention_days: 7,
+        ml_undo_store: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        shutdown_tx: broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: GitAutoCommitter::new(),
+    });
+    let config = web::Data::new(AppConfig::default());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .app_data(config.clone())
+            .configure(files::configure),
+    )
+    .await;
+
+    let raw_uri = format!("/api/vaults/{}/raw/media/clip.mp4", vault.id);
+
+    // Full response advertises range support.
+    let req = test::TestRequest::get().uri(&raw_uri).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "video/mp4"
+    );
+    let etag = resp.headers().get(header::ETAG).unwrap().clone();
+    assert_eq!(test::read_body(resp).await.as_ref(), video.as_slice());
+
+    // A byte range returns 206 with just that slice.
+    let req = test::TestRequest::get()
+        .uri(&raw_uri)
+        .insert_header((header::RANGE, "bytes=100-199"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 206);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_RANGE).unwrap(),
+        "bytes 100-199/200000"
+    );
+    assert_eq!(test::read_body(resp).await.as_ref(), &video[100..200]);
+
+    // If-Range with the current ETag keeps the range...
+    let req = test::TestRequest::get()
+        .uri(&raw_uri)
+        .insert_header((header::RANGE, "bytes=0-9"))
+        .insert_header((header::IF_RANGE, etag.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 206);
+
+    // ...while a stale validator gets the whole file.
+    let req = test::TestRequest::get()
+        .uri(&raw_uri)
+        .insert_header((header::RANGE, "bytes=0-9"))
+        .insert_header((header::IF_RANGE, "\"stale\""))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers().get(header::CONTENT_RANGE).is_none());
+    assert_eq!(test::read_body(resp).await.len(), video.len());
+
+    // Downloads are attachments and honour ranges too.
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{}/download/media/clip.mp4", vault.id))
+        .insert_header((header::RANGE, "bytes=-5"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 206);
+    assert!(resp
+        .headers()
+        .get(header::CONTENT_DISPOSITION)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("attachment"));
+    assert_eq!(
+        test::read_body(resp).await.as_ref(),
+        &video[video.len() - 5..]
+    );
+
+    // Without `paths` the whole vault is exported, minus the trash.
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/download-zip", vault.id))
+        .set_json(json!({ "archive_id": "export-1" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("X-Archive-Id").unwrap(), "export-1");
+    let body = test::read_body(resp).await;
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(body.to_vec())).unwrap();
+    let mut names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    names.sort();
+    assert_eq!(names, vec!["media/clip.mp4", "note.md"]);
+    let mut clip = Vec::new();
+    archive
+        .by_name("media/clip.mp4")
+        .unwrap()
+        .read_to_end(&mut clip)
+        .unwrap();
+    assert_eq!(clip, video);
+
+    let finished = loop {
+        match ws_rx.recv().await.unwrap() {
+            WsMessage::ArchiveProgress {
+                archive_id,
+                finished: true,
+                files_done,
+                files_total,
+                error,
+                ..
+            } => break (archive_id, files_done, files_total, error),
+            _ => continue,
+        }
+    };
+    assert_eq!(finished, ("export-1".to_string(), 2, 2, None));
+
+    // Tar downloads accept a `paths` filter.
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/download-tar", vault.id))
+        .set_json(json!({ "paths": ["note.md"] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp
+        .headers()
+        .get(header::CONTENT_DISPOSITION)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .contains("note.md.tar.gz"));
+    let body = test::read_body(resp).await;
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(body.as_ref()));
+    let names: Vec<String> = tar
+        .entries()
+        .unwrap()
+        .map(|e| e.unwrap().path().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, vec!["note.md"]);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/download-zip", vault.id))
+        .set_json(json!({ "paths": [] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
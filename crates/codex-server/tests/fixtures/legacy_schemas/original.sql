@@ -0,0 +1,35 @@
+-- Core tables as created by the earliest releases, before any of the
+-- columns later added with ALTER TABLE existed.
+CREATE TABLE vaults (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    path TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE TABLE preferences (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    theme TEXT NOT NULL DEFAULT 'dark',
+    editor_mode TEXT NOT NULL DEFAULT 'side_by_side',
+    font_size INTEGER NOT NULL DEFAULT 14,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO preferences (id, theme, editor_mode, font_size, updated_at) VALUES (1, 'light', 'side_by_side', 16, '2025-01-01T00:00:00Z');
+CREATE TABLE recent_files (
+    vault_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    last_accessed TEXT NOT NULL,
+    PRIMARY KEY (vault_id, path),
+    FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+);
+CREATE TABLE users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+INSERT INTO users (id, username, password_hash, created_at) VALUES ('u-alice', 'alice', 'not-a-real-hash', '2025-01-01T00:00:00Z');
+INSERT INTO users (id, username, password_hash, created_at) VALUES ('u-bob', 'bob', 'not-a-real-hash', '2025-02-01T00:00:00Z');
+INSERT INTO vaults (id, name, path, created_at, updated_at) VALUES ('v-notes', 'Notes', '/srv/vaults/notes', '2025-01-02T00:00:00Z', '2025-01-02T00:00:00Z');
+INSERT INTO recent_files (vault_id, path, last_accessed) VALUES ('v-notes', 'inbox.md', '2025-01-03T00:00:00Z');
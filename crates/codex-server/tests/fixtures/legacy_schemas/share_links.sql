@@ -0,0 +1,287 @@
+-- Schema written by the bootstrap statements immediately before
+-- versioned migrations, including share links.
+PRAGMA foreign_keys=OFF;
+CREATE TABLE api_keys (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                prefix TEXT NOT NULL,
+                key_hash TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT,
+                revoked INTEGER NOT NULL DEFAULT 0, restrictions TEXT, last_used_at TEXT, last_used_ip TEXT,
+                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+            );
+INSERT INTO "api_keys" VALUES('k-1','sync','cdx_abcd','not-a-real-hash','u-alice','2025-03-02T00:00:00Z',NULL,0,'{"vault_ids":["v-notes"]}',NULL,NULL);
+CREATE TABLE audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                user_id TEXT,
+                username TEXT,
+                event_type TEXT NOT NULL,
+                detail TEXT,
+                ip_address TEXT,
+                success INTEGER NOT NULL DEFAULT 1
+            );
+CREATE TABLE bookmarks (
+                id TEXT PRIMARY KEY NOT NULL,
+                vault_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                title TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+                UNIQUE(vault_id, path)
+            );
+CREATE TABLE entities (
+                id          TEXT PRIMARY KEY NOT NULL,
+                vault_id    TEXT NOT NULL,
+                path        TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                plugin_id   TEXT NOT NULL,
+                labels      TEXT NOT NULL,
+                fields      TEXT NOT NULL,
+                modified_at TEXT NOT NULL,
+                indexed_at  TEXT NOT NULL,
+                UNIQUE(vault_id, path),
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+CREATE TABLE file_change_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                vault_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                event_type TEXT NOT NULL,
+                etag TEXT,
+                old_path TEXT,
+                timestamp INTEGER NOT NULL,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+CREATE TABLE group_members (
+                group_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                added_at TEXT NOT NULL,
+                PRIMARY KEY (group_id, user_id),
+                FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+            );
+CREATE TABLE groups (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                created_at TEXT NOT NULL,
+                created_by_user_id TEXT NOT NULL,
+                FOREIGN KEY (created_by_user_id) REFERENCES users(id) ON DELETE CASCADE
+            );
+CREATE TABLE invitations (
+                id TEXT PRIMARY KEY,
+                token TEXT NOT NULL UNIQUE,
+                role TEXT NOT NULL DEFAULT 'viewer',
+                vault_id TEXT,
+                created_by_user_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                accepted INTEGER NOT NULL DEFAULT 0,
+                accepted_by_user_id TEXT,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+                FOREIGN KEY (created_by_user_id) REFERENCES users(id) ON DELETE CASCADE
+            );
+CREATE TABLE labels (
+                name        TEXT PRIMARY KEY NOT NULL,
+                description TEXT,
+                source      TEXT NOT NULL CHECK(source IN ('core', 'plugin')),
+                plugin_id   TEXT
+            );
+CREATE TABLE ml_undo_receipts (
+                receipt_id TEXT PRIMARY KEY NOT NULL,
+                vault_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                description TEXT NOT NULL,
+                reverse_action TEXT NOT NULL,
+                applied_at TEXT NOT NULL,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+CREATE TABLE preferences (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                theme TEXT NOT NULL DEFAULT 'dark',
+                editor_mode TEXT NOT NULL DEFAULT 'side_by_side',
+                font_size INTEGER NOT NULL DEFAULT 14,
+                window_layout TEXT,
+                icon_map TEXT,
+                updated_at TEXT NOT NULL
+            );
+INSERT INTO "preferences" VALUES(1,'dark','side_by_side',14,NULL,NULL,'2025-01-01T00:00:00Z');
+CREATE TABLE recent_files (
+                vault_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                last_accessed TEXT NOT NULL,
+                PRIMARY KEY (vault_id, path),
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+INSERT INTO "recent_files" VALUES('v-notes','inbox.md','2025-01-03T00:00:00Z');
+CREATE TABLE reindex_log (
+                vault_id     TEXT PRIMARY KEY NOT NULL,
+                completed_at TEXT NOT NULL,
+                file_count   INTEGER NOT NULL,
+                duration_ms  INTEGER NOT NULL,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+CREATE TABLE relations (
+                id              TEXT PRIMARY KEY NOT NULL,
+                vault_id        TEXT NOT NULL,
+                from_entity_id  TEXT NOT NULL,
+                to_entity_id    TEXT NOT NULL,
+                relation_type   TEXT NOT NULL,
+                direction       TEXT NOT NULL CHECK(direction IN ('forward', 'inverse')),
+                metadata        TEXT,
+                source          TEXT NOT NULL CHECK(source IN ('field', 'explicit')),
+                source_field    TEXT,
+                created_at      TEXT NOT NULL,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+                FOREIGN KEY (from_entity_id) REFERENCES entities(id) ON DELETE CASCADE,
+                FOREIGN KEY (to_entity_id) REFERENCES entities(id) ON DELETE CASCADE
+            );
+CREATE TABLE sessions (
+                token_id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                revoked INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+            );
+CREATE TABLE share_links (
+                id TEXT PRIMARY KEY,
+                token_hash TEXT NOT NULL UNIQUE,
+                vault_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                is_folder INTEGER NOT NULL DEFAULT 0,
+                permission TEXT NOT NULL DEFAULT 'view',
+                password_hash TEXT,
+                expires_at TEXT,
+                created_by TEXT,
+                created_at TEXT NOT NULL,
+                revoked_at TEXT,
+                access_count INTEGER NOT NULL DEFAULT 0,
+                last_accessed_at TEXT,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+INSERT INTO "share_links" VALUES('s-1','deadbeef','v-notes','inbox.md',0,'view',NULL,NULL,NULL,'2025-04-02T00:00:00Z',NULL,0,NULL);
+CREATE TABLE trash_items (
+                vault_id      TEXT NOT NULL,
+                trash_name    TEXT NOT NULL,
+                original_path TEXT NOT NULL,
+                deleted_by    TEXT,
+                deleted_at    TEXT NOT NULL,
+                size          INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (vault_id, trash_name),
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+CREATE TABLE upload_session_chunks (
+                session_id   TEXT NOT NULL,
+                chunk_offset INTEGER NOT NULL,
+                length       INTEGER NOT NULL,
+                sha256       TEXT,
+                PRIMARY KEY (session_id, chunk_offset),
+                FOREIGN KEY (session_id) REFERENCES upload_sessions(id) ON DELETE CASCADE
+            );
+CREATE TABLE upload_sessions (
+                id         TEXT PRIMARY KEY,
+                vault_id   TEXT NOT NULL,
+                owner_id   TEXT,
+                filename   TEXT NOT NULL,
+                path       TEXT NOT NULL,
+                total_size INTEGER,
+                sha256     TEXT,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+CREATE TABLE user_preferences (
+                user_id TEXT PRIMARY KEY,
+                theme TEXT NOT NULL DEFAULT 'dark',
+                editor_mode TEXT NOT NULL DEFAULT 'side_by_side',
+                font_size INTEGER NOT NULL DEFAULT 14,
+                window_layout TEXT,
+                icon_map TEXT,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+            );
+CREATE TABLE users (
+                id TEXT PRIMARY KEY,
+                username TEXT NOT NULL UNIQUE,
+                password_hash TEXT NOT NULL,
+                is_admin INTEGER NOT NULL DEFAULT 0,
+                must_change_password INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL
+            , is_active INTEGER NOT NULL DEFAULT 1, failed_login_attempts INTEGER NOT NULL DEFAULT 0, locked_until TEXT, totp_secret TEXT, totp_enabled INTEGER NOT NULL DEFAULT 0, totp_backup_codes TEXT);
+INSERT INTO "users" VALUES('u-alice','alice','not-a-real-hash',0,0,'2025-01-01T00:00:00Z',1,0,NULL,NULL,0,NULL);
+INSERT INTO "users" VALUES('u-bob','bob','not-a-real-hash',0,0,'2025-02-01T00:00:00Z',1,0,NULL,NULL,0,NULL);
+CREATE TABLE vault_git_settings (
+                vault_id           TEXT PRIMARY KEY NOT NULL,
+                enabled            INTEGER NOT NULL DEFAULT 0,
+                auto_commit        INTEGER NOT NULL DEFAULT 1,
+                remote_url         TEXT,
+                branch             TEXT NOT NULL DEFAULT 'main',
+                sync_interval_secs INTEGER NOT NULL DEFAULT 0,
+                last_sync_at       TEXT,
+                last_sync_error    TEXT,
+                updated_at         TEXT NOT NULL,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+CREATE TABLE vault_group_shares (
+                vault_id TEXT NOT NULL,
+                group_id TEXT NOT NULL,
+                role TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (vault_id, group_id),
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+                FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE
+            );
+CREATE TABLE vault_path_acls (
+                id TEXT PRIMARY KEY,
+                vault_id TEXT NOT NULL,
+                pattern TEXT NOT NULL,
+                principal_type TEXT NOT NULL,
+                principal_id TEXT,
+                access TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE
+            );
+INSERT INTO "vault_path_acls" VALUES('acl-1','v-notes','hr/**','everyone',NULL,'none','2025-04-01T00:00:00Z');
+CREATE TABLE vault_user_shares (
+                vault_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                role TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (vault_id, user_id),
+                FOREIGN KEY (vault_id) REFERENCES vaults(id) ON DELETE CASCADE,
+                FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+            );
+CREATE TABLE vaults (
+                id TEXT PRIMARY KEY NOT NULL,
+                name TEXT NOT NULL,
+                path TEXT NOT NULL UNIQUE,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            , owner_user_id TEXT REFERENCES users(id), visibility TEXT NOT NULL DEFAULT 'private', document_format TEXT NOT NULL DEFAULT 'markdown');
+INSERT INTO "vaults" VALUES('v-notes','Notes','/srv/vaults/notes','2025-01-02T00:00:00Z','2025-01-02T00:00:00Z',NULL,'private','markdown');
+CREATE INDEX idx_group_members_user_id ON group_members(user_id);
+CREATE INDEX idx_vault_user_shares_user_id ON vault_user_shares(user_id);
+CREATE INDEX idx_vault_group_shares_group_id ON vault_group_shares(group_id);
+CREATE INDEX idx_vault_path_acls_vault_id ON vault_path_acls(vault_id);
+CREATE INDEX idx_share_links_vault_id ON share_links(vault_id);
+CREATE INDEX idx_file_change_log_vault_timestamp
+            ON file_change_log(vault_id, timestamp);
+CREATE INDEX idx_bookmarks_vault_id ON bookmarks(vault_id);
+CREATE INDEX idx_ml_undo_receipts_vault_id ON ml_undo_receipts(vault_id);
+CREATE INDEX idx_audit_log_timestamp ON audit_log(timestamp);
+CREATE INDEX idx_audit_log_user_id ON audit_log(user_id);
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id);
+CREATE INDEX idx_api_keys_prefix ON api_keys(prefix);
+CREATE UNIQUE INDEX idx_invitations_token ON invitations(token);
+CREATE INDEX idx_entities_vault_id ON entities(vault_id);
+CREATE INDEX idx_entities_vault_type ON entities(vault_id, entity_type);
+CREATE INDEX idx_relations_from ON relations(from_entity_id);
+CREATE INDEX idx_relations_to ON relations(to_entity_id);
+CREATE INDEX idx_relations_vault ON relations(vault_id);
+CREATE INDEX idx_upload_sessions_expires_at ON upload_sessions(expires_at);
+CREATE INDEX idx_trash_items_deleted_at ON trash_items(deleted_at);
+DELETE FROM "sqlite_sequence";
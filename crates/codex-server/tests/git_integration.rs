@@ -0,0 +1,222 @@
+use actix_web::{test, web, App};
+use codex::config::AppConfig;
+use codex::db::Database;
+use codex::routes::{files, git, AppState};
+use codex::services::{GitAutoCommitter, MarkdownParser, SearchIndex};
+use codex::watcher::FileWatcher;
+use serde_json::json;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::{broadcast, Mutex};
+
+fn run_git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=Remote",
+            "-c",
+            "user.email=remote@example.com",
+        ])
+        .args(args)
+        .output()
+        .expect("git must be installed");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[actix_web::test]
+async fn git_vault_commits_syncs_and_surfaces_conflicts() {
+    let temp_dir = TempDir::new().unwrap();
+    let vault_dir = temp_dir.path().join("vault");
+    let remote_dir = temp_dir.path().join("remote.git");
+    let clone_dir = temp_dir.path().join("clone");
+    std::fs::create_dir_all(&vault_dir).unwrap();
+    run_git(
+        temp_dir.path(),
+        &[
+            "init",
+            "-q",
+            "--bare",
+            "-b",
+            "main",
+            remote_dir.to_str().unwrap(),
+        ],
+    );
+
+    let db_path = temp_dir.path().join("git-test.db");
+    let db = Database::new(&format!("sqlite://{}", db_path.display()))
+        .await
+        .unwrap();
+    let vault = db
+        .create_vault("Git".to_string(), vault_dir.to_string_lossy().to_string())
+        .await
+        .unwrap();
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let (ws_tx, mut ws_rx) = broadcast::channel::<codex::models::WsMessage>(16);
+    let state = web::Data::new(AppState {
+        db: db.clone(),
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: broadcast::channel(100).0,
+        ws_broadcaster: ws_tx,
+        change_log_retention_days: 7,
+        ml_undo_store: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        shutdown_tx: broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: GitAutoCommitter::new(),
+    });
+    let mut config = AppConfig::default();
+    // The test remote is a bare repository on disk.
+    config.git.allow_local_remotes = true;
+    let config = web::Data::new(config);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .app_data(config.clone())
+            .configure(files::configure)
+            .configure(git::configure),
+    )
+    .await;
+
+    // Log is rejected until git is enabled.
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{}/git/log", vault.id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+
+    // Remotes that git would read as an option or a helper are refused.
+    for remote_url in ["--upload-pack=touch pwned", "ext::sh -c id"] {
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/vaults/{}/git", vault.id))
+            .set_json(json!({ "enabled": true, "remote_url": remote_url }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+    assert!(!vault_dir.join(".git").exists());
+
+    // Enable git with the bare repository as remote.
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/vaults/{}/git", vault.id))
+        .set_json(json!({
+            "enabled": true,
+            "remote_url": remote_dir.to_string_lossy(),
+            "branch": "main"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert!(vault_dir.join(".git").exists());
+
+    // Write a note and commit it.
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/files", vault.id))
+        .set_json(json!({ "path": "note.md", "content": "line one\n" }))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/git/commit", vault.id))
+        .set_json(json!({ "message": "Add note" }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["commit"].is_string());
+
+    // Sync pushes to the bare remote.
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/git/sync", vault.id))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["pushed"], true);
+    let remote_log = run_git(&remote_dir, &["log", "--format=%s", "main"]);
+    assert!(remote_log.contains("Add note"));
+
+    // A second clone pushes a new file; sync pulls it in.
+    run_git(
+        temp_dir.path(),
+        &[
+            "clone",
+            "-q",
+            remote_dir.to_str().unwrap(),
+            clone_dir.to_str().unwrap(),
+        ],
+    );
+    std::fs::write(clone_dir.join("remote.md"), "from remote\n").unwrap();
+    run_git(&clone_dir, &["add", "-A"]);
+    run_git(&clone_dir, &["commit", "-q", "-m", "Remote note"]);
+    run_git(&clone_dir, &["push", "-q", "origin", "main"]);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/git/sync", vault.id))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["pulled"], true);
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join("remote.md")).unwrap(),
+        "from remote\n"
+    );
+
+    // Log, diff and blame for a single file.
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{}/git/log?path=note.md", vault.id))
+        .to_request();
+    let log: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(log.as_array().unwrap().len(), 1);
+    assert_eq!(log[0]["summary"], "Add note");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{}/git/blame/note.md", vault.id))
+        .to_request();
+    let blame: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(blame[0]["content"], "line one");
+
+    std::fs::write(vault_dir.join("note.md"), "line one\nline two\n").unwrap();
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{}/git/diff/note.md", vault.id))
+        .to_request();
+    let diff: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(diff["patch"].as_str().unwrap().contains("+line two"));
+
+    // Both sides edit note.md: local wins, remote lands in a conflict copy.
+    run_git(&clone_dir, &["pull", "-q", "origin", "main"]);
+    std::fs::write(clone_dir.join("note.md"), "line one\nremote edit\n").unwrap();
+    run_git(&clone_dir, &["commit", "-q", "-am", "Remote edit"]);
+    run_git(&clone_dir, &["push", "-q", "origin", "main"]);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/git/sync", vault.id))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let conflicts = body["conflicts"].as_array().unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0]["path"], "note.md");
+    let copy = conflicts[0]["conflict_copy"].as_str().unwrap();
+    assert!(copy.starts_with("conflict_note_"));
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join(copy)).unwrap(),
+        "line one\nremote edit\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join("note.md")).unwrap(),
+        "line one\nline two\n"
+    );
+
+    match ws_rx.try_recv().unwrap() {
+        codex::models::WsMessage::SyncConflict { path, .. } => assert_eq!(path, "note.md"),
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
@@ -0,0 +1,166 @@
+use codex::config::AuthConfig;
+use codex::db::Database;
+use codex::services::GroupMappingService;
+use tempfile::TempDir;
+
+async fn setup() -> (TempDir, Database, String, String) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_url = format!("sqlite://{}", temp_dir.path().join("groups.db").display());
+    let db = Database::new(&db_url).await.unwrap();
+    db.bootstrap_admin_if_empty(Some("admin"), Some("hunter2"))
+        .await
+        .unwrap();
+    let (admin_id, _) = db.get_user_by_username("admin").await.unwrap().unwrap();
+    let (user_id, _, _, _) = db
+        .create_user_with_options("grace", "ldap-managed", false, false)
+        .await
+        .unwrap();
+    (temp_dir, db, admin_id, user_id)
+}
+
+fn config() -> AuthConfig {
+    let mut cfg = AuthConfig::default();
+    cfg.group_mappings.insert(
+        "engineering".to_string(),
+        vec!["Engineering".to_string(), "Writers".to_string()],
+    );
+    cfg.group_mappings
+        .insert("editors".to_string(), vec!["Editors".to_string()]);
+    cfg.admin_groups = vec!["codex-admins".to_string()];
+    cfg
+}
+
+async fn member_groups(db: &Database, user_id: &str) -> Vec<String> {
+    let mut names: Vec<String> = db
+        .list_member_groups(user_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|g| g.name)
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn sign_in_reconciles_mapped_groups_and_admin_status() {
+    let (_tmp, db, admin_id, user_id) = setup().await;
+    let cfg = config();
+    for name in ["Engineering", "Writers", "Editors", "Book club"] {
+        let group = db.create_group(name, &admin_id).await.unwrap();
+        if name == "Book club" {
+            db.add_user_to_group(&group.id, &user_id).await.unwrap();
+        }
+    }
+
+    let outcome = GroupMappingService::sync(
+        &db,
+        &cfg,
+        &user_id,
+        "grace",
+        "ldap",
+        &[
+            "cn=engineering,ou=groups,dc=example,dc=com".to_string(),
+            "cn=codex-admins,ou=groups,dc=example,dc=com".to_string(),
+        ],
+    )
+    .await
+    .unwrap();
+    assert_eq!(outcome.added, vec!["Engineering", "Writers"]);
+    assert!(outcome.removed.is_empty());
+    assert_eq!(outcome.admin, Some(true));
+    assert!(db.is_user_admin(&user_id).await.unwrap());
+    assert_eq!(
+        member_groups(&db, &user_id).await,
+        vec!["Book club", "Engineering", "Writers"]
+    );
+
+    // Signing in again with the same groups changes nothing.
+    let outcome = GroupMappingService::sync(
+        &db,
+        &cfg,
+        &user_id,
+        "grace",
+        "ldap",
+        &[
+            "cn=engineering,ou=groups,dc=example,dc=com".to_string(),
+            "cn=codex-admins,ou=groups,dc=example,dc=com".to_string(),
+        ],
+    )
+    .await
+    .unwrap();
+    assert_eq!(outcome, Default::default());
+
+    // Moving teams in the directory moves the user in Codex; groups that
+    // are not mapped ("Book club") are left alone.
+    let outcome = GroupMappingService::sync(
+        &db,
+        &cfg,
+        &user_id,
+        "grace",
+        "oidc",
+        &["editors".to_string()],
+    )
+    .await
+    .unwrap();
+    assert_eq!(outcome.added, vec!["Editors"]);
+    assert_eq!(outcome.removed, vec!["Engineering", "Writers"]);
+    assert_eq!(outcome.admin, Some(false));
+    assert!(!db.is_user_admin(&user_id).await.unwrap());
+    assert_eq!(
+        member_groups(&db, &user_id).await,
+        vec!["Book club", "Editors"]
+    );
+
+    let audit = db.get_audit_log(Some(50)).await.unwrap();
+    let events: Vec<&str> = audit.iter().map(|e| e.event_type.as_str()).collect();
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| **e == "directory_group_added")
+            .count(),
+        3
+    );
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| **e == "directory_group_removed")
+            .count(),
+        2
+    );
+    assert!(events.contains(&"directory_admin_granted"));
+    assert!(events.contains(&"directory_admin_revoked"));
+    assert!(audit.iter().any(|e| e
+        .detail
+        .as_deref()
+        .is_some_and(|d| d.contains("Removed grace from group 'Writers' per oidc"))));
+}
+
+#[tokio::test]
+async fn missing_groups_are_skipped_and_unconfigured_sync_is_a_no_op() {
+    let (_tmp, db, admin_id, user_id) = setup().await;
+    db.create_group("Editors", &admin_id).await.unwrap();
+
+    let mut cfg = config();
+    cfg.admin_groups.clear();
+    let outcome = GroupMappingService::sync(
+        &db,
+        &cfg,
+        &user_id,
+        "grace",
+        "oidc",
+        &["engineering".to_string(), "editors".to_string()],
+    )
+    .await
+    .unwrap();
+    // "Engineering" and "Writers" do not exist in Codex.
+    assert_eq!(outcome.added, vec!["Editors"]);
+    assert_eq!(outcome.admin, None);
+
+    let outcome =
+        GroupMappingService::sync(&db, &AuthConfig::default(), &user_id, "grace", "oidc", &[])
+            .await
+            .unwrap();
+    assert_eq!(outcome, Default::default());
+    assert_eq!(member_groups(&db, &user_id).await, vec!["Editors"]);
+}
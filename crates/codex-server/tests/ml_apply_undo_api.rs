@@ -31,7 +31,7 @@ async fn apply_tag_and_undo_restores_file_and_receipt_is_single_use() {
         .await
         .unwrap();
 
-    let search_index = SearchIndex::new();
+    let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
     let (watcher, _) = FileWatcher::new().unwrap();
     let watcher = Arc::new(Mutex::new(watcher));
     let (event_tx, _) = broadcast::channel(100);
@@ -48,7 +48,8 @@ async fn apply_tag_and_undo_restores_file_and_receipt_is_single_use() {
         document_parser: Arc::new(MarkdownParser),
         entity_type_registry: codex::services::EntityTypeRegistry::new(),
         relation_type_registry: codex::services::RelationTypeRegistry::new(),
-        plugins_dir: String::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
     });
 
     let app = test::init_service(App::new().app_data(state.clone()).configure(ml::configure)).await;
@@ -126,7 +127,7 @@ async fn apply_move_and_undo_restores_original_path() {
         .await
         .unwrap();
 
-    let search_index = SearchIndex::new();
+    let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
     let (watcher, _) = FileWatcher::new().unwrap();
     let watcher = Arc::new(Mutex::new(watcher));
     let (event_tx, _) = broadcast::channel(100);
@@ -143,7 +144,8 @@ async fn apply_move_and_undo_restores_original_path() {
         document_parser: Arc::new(MarkdownParser),
         entity_type_registry: codex::services::EntityTypeRegistry::new(),
         relation_type_registry: codex::services::RelationTypeRegistry::new(),
-        plugins_dir: String::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
     });
 
     let app = test::init_service(App::new().app_data(state.clone()).configure(ml::configure)).await;
@@ -207,7 +209,7 @@ async fn undo_receipt_persists_across_app_reinitialization() {
         .unwrap();
 
     let receipt_id = {
-        let search_index = SearchIndex::new();
+        let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
         let (watcher, _) = FileWatcher::new().unwrap();
         let watcher = Arc::new(Mutex::new(watcher));
         let (event_tx, _) = broadcast::channel(100);
@@ -224,7 +226,8 @@ async fn undo_receipt_persists_across_app_reinitialization() {
             document_parser: Arc::new(MarkdownParser),
             entity_type_registry: codex::services::EntityTypeRegistry::new(),
             relation_type_registry: codex::services::RelationTypeRegistry::new(),
-            plugins_dir: String::new(),
+            plugins_dir: std::path::PathBuf::new(),
+            git_autocommit: codex::services::GitAutoCommitter::new(),
         });
 
         let app =
@@ -255,7 +258,7 @@ async fn undo_receipt_persists_across_app_reinitialization() {
     assert!(content_after_apply.contains("persisted"));
 
     {
-        let search_index = SearchIndex::new();
+        let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
         let (watcher, _) = FileWatcher::new().unwrap();
         let watcher = Arc::new(Mutex::new(watcher));
         let (event_tx, _) = broadcast::channel(100);
@@ -272,7 +275,8 @@ async fn undo_receipt_persists_across_app_reinitialization() {
             document_parser: Arc::new(MarkdownParser),
             entity_type_registry: codex::services::EntityTypeRegistry::new(),
             relation_type_registry: codex::services::RelationTypeRegistry::new(),
-            plugins_dir: String::new(),
+            plugins_dir: std::path::PathBuf::new(),
+            git_autocommit: codex::services::GitAutoCommitter::new(),
         });
 
         let app =
@@ -0,0 +1,599 @@
+//! OIDC sign-in against a mock issuer served from the test process: PKCE,
+//! nonce and state binding, ID token validation, key rotation, named
+//! providers and RP-initiated logout.
+
+use actix_web::{test, web, App, HttpRequest, HttpResponse, HttpServer};
+use codex::config::{AppConfig, OidcProviderConfig};
+use codex::db::Database;
+use codex::middleware::AuthMiddleware;
+use codex::routes::{auth, oidc, AppState};
+use codex::services::{MarkdownParser, SearchIndex};
+use codex::watcher::FileWatcher;
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use tempfile::TempDir;
+use tokio::sync::{broadcast, Mutex};
+
+const CLIENT_ID: &str = "codex-test";
+const REDIRECT_URI: &str = "http://localhost:8080/api/auth/oidc/callback";
+
+// ── Mock issuer ─────────────────────────────────────────────────────────
+
+struct SigningKey {
+    kid: String,
+    pkcs8: Vec<u8>,
+    x: String,
+    y: String,
+}
+
+impl SigningKey {
+    fn generate(kid: &str) -> Self {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+        // Uncompressed point: 0x04 || x || y.
+        let point = pair.public_key().as_ref();
+        Self {
+            kid: kid.to_string(),
+            pkcs8: pkcs8.as_ref().to_vec(),
+            x: BASE64URL_NOPAD.encode(&point[1..33]),
+            y: BASE64URL_NOPAD.encode(&point[33..65]),
+        }
+    }
+
+    fn jwk(&self) -> Value {
+        json!({
+            "kty": "EC", "crv": "P-256", "use": "sig", "alg": "ES256",
+            "kid": self.kid, "x": self.x, "y": self.y,
+        })
+    }
+
+    fn sign(&self, claims: &Value) -> String {
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(self.kid.clone());
+        encode(&header, claims, &EncodingKey::from_ec_der(&self.pkcs8)).unwrap()
+    }
+}
+
+struct PendingCode {
+    challenge: String,
+    nonce: String,
+    subject: String,
+}
+
+#[derive(Default)]
+struct IssuerState {
+    base: String,
+    /// Published in the JWKS; the last one signs new tokens.
+    keys: StdMutex<Vec<SigningKey>>,
+    /// Signs tokens with a key that is never published.
+    rogue_key: StdMutex<Option<SigningKey>>,
+    /// Merged into (or, when null, removed from) every ID token's claims.
+    overrides: StdMutex<Map<String, Value>>,
+    codes: StdMutex<HashMap<String, PendingCode>>,
+    jwks_fetches: AtomicUsize,
+}
+
+struct MockIssuer {
+    state: web::Data<IssuerState>,
+}
+
+impl MockIssuer {
+    async fn start() -> Self {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let state = web::Data::new(IssuerState {
+            base,
+            keys: StdMutex::new(vec![SigningKey::generate("key-1")]),
+            ..Default::default()
+        });
+        let data = state.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(discovery),
+                )
+                .route("/jwks", web::get().to(jwks))
+                .route("/token", web::post().to(token))
+                .route("/userinfo", web::get().to(userinfo))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+        Self { state }
+    }
+
+    fn base(&self) -> &str {
+        &self.state.base
+    }
+
+    fn provider(&self, display_name: &str) -> OidcProviderConfig {
+        OidcProviderConfig {
+            display_name: Some(display_name.to_string()),
+            issuer_url: self.base().to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Some("client-secret".to_string()),
+            redirect_uri: REDIRECT_URI.to_string(),
+            scopes: vec!["openid".to_string(), "profile".to_string()],
+            groups_claim: None,
+            post_logout_redirect_uri: Some("http://localhost:8080/".to_string()),
+        }
+    }
+
+    /// Play the user's part at the authorization endpoint: sign in as
+    /// `subject` and return the code the provider would redirect back with.
+    fn approve(&self, authorize_url: &str, subject: &str) -> String {
+        let url = reqwest::Url::parse(authorize_url).unwrap();
+        assert!(authorize_url.starts_with(&format!("{}/authorize?", self.base())));
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["client_id"], CLIENT_ID);
+        assert_eq!(params["redirect_uri"], REDIRECT_URI);
+        assert_eq!(params["code_challenge_method"], "S256");
+        let code = format!("code-{}", uuid::Uuid::new_v4());
+        self.state.codes.lock().unwrap().insert(
+            code.clone(),
+            PendingCode {
+                challenge: params["code_challenge"].clone(),
+                nonce: params["nonce"].clone(),
+                subject: subject.to_string(),
+            },
+        );
+        code
+    }
+
+    fn set_claim(&self, name: &str, value: Value) {
+        self.state
+            .overrides
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), value);
+    }
+
+    fn rotate_key(&self, kid: &str) {
+        self.state
+            .keys
+            .lock()
+            .unwrap()
+            .push(SigningKey::generate(kid));
+    }
+}
+
+async fn discovery(state: web::Data<IssuerState>) -> HttpResponse {
+    let base = &state.base;
+    HttpResponse::Ok().json(json!({
+        "issuer": base,
+        "authorization_endpoint": format!("{base}/authorize"),
+        "token_endpoint": format!("{base}/token"),
+        "jwks_uri": format!("{base}/jwks"),
+        "userinfo_endpoint": format!("{base}/userinfo"),
+        "end_session_endpoint": format!("{base}/logout"),
+    }))
+}
+
+async fn jwks(state: web::Data<IssuerState>) -> HttpResponse {
+    state.jwks_fetches.fetch_add(1, Ordering::SeqCst);
+    let keys: Vec<Value> = state.keys.lock().unwrap().iter().map(|k| k.jwk()).collect();
+    HttpResponse::Ok().json(json!({ "keys": keys }))
+}
+
+async fn token(
+    state: web::Data<IssuerState>,
+    form: web::Form<HashMap<String, String>>,
+) -> HttpResponse {
+    let Some(pending) = state.codes.lock().unwrap().remove(&form["code"]) else {
+        return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+    };
+    let verifier = form.get("code_verifier").cloned().unwrap_or_default();
+    let challenge = BASE64URL_NOPAD.encode(&Sha256::digest(verifier.as_bytes()));
+    if challenge != pending.challenge
+        || form["client_id"] != CLIENT_ID
+        || form["redirect_uri"] != REDIRECT_URI
+    {
+        return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut claims = json!({
+        "iss": state.base,
+        "sub": pending.subject,
+        "aud": CLIENT_ID,
+        "iat": now,
+        "exp": now + 300,
+        "nonce": pending.nonce,
+    });
+    for (name, value) in state.overrides.lock().unwrap().iter() {
+        if value.is_null() {
+            claims.as_object_mut().unwrap().remove(name);
+        } else {
+            claims[name] = value.clone();
+        }
+    }
+    let id_token = match state.rogue_key.lock().unwrap().as_ref() {
+        Some(rogue) => rogue.sign(&claims),
+        None => state.keys.lock().unwrap().last().unwrap().sign(&claims),
+    };
+    HttpResponse::Ok().json(json!({
+        "access_token": format!("at:{}", pending.subject),
+        "token_type": "Bearer",
+        "expires_in": 300,
+        "id_token": id_token,
+    }))
+}
+
+async fn userinfo(req: HttpRequest) -> HttpResponse {
+    let subject = req
+        .headers()
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer at:"))
+        .unwrap_or_default()
+        .to_string();
+    HttpResponse::Ok().json(json!({
+        "sub": subject,
+        "preferred_username": subject,
+        "groups": ["engineering"],
+    }))
+}
+
+// ── Codex under test ────────────────────────────────────────────────────
+
+async fn setup(config: AppConfig) -> (TempDir, web::Data<AppState>, web::Data<AppConfig>) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_url = format!("sqlite://{}", temp_dir.path().join("oidc.db").display());
+    let db = Database::new(&db_url).await.unwrap();
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let (event_tx, _) = broadcast::channel(100);
+    let state = web::Data::new(AppState {
+        db,
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: event_tx,
+        ws_broadcaster: tokio::sync::broadcast::channel::<codex::models::WsMessage>(16).0,
+        change_log_retention_days: 7,
+        ml_undo_store: std::sync::Arc::new(tokio::sync::Mutex::new(
+            std::collections::HashMap::new(),
+        )),
+        shutdown_tx: tokio::sync::broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
+    });
+    (temp_dir, state, web::Data::new(config))
+}
+
+fn config_with_default(issuer: &MockIssuer) -> AppConfig {
+    let provider = issuer.provider("Mock");
+    let mut config = AppConfig::default();
+    config.auth.enabled = true;
+    config.auth.provider = "oidc".to_string();
+    config.auth.jwt_secret = "integration-test-secret".to_string();
+    config.auth.oidc_issuer_url = Some(provider.issuer_url);
+    config.auth.oidc_client_id = Some(provider.client_id);
+    config.auth.oidc_client_secret = provider.client_secret;
+    config.auth.oidc_redirect_uri = Some(provider.redirect_uri);
+    config
+}
+
+macro_rules! app {
+    ($state:expr, $config:expr) => {
+        test::init_service(
+            App::new()
+                .app_data($state.clone())
+                .app_data($config.clone())
+                .wrap(AuthMiddleware)
+                .configure(auth::configure)
+                .configure(oidc::configure),
+        )
+        .await
+    };
+}
+
+async fn get<S, B>(app: &S, uri: &str) -> (u16, Value)
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let resp = test::call_service(app, test::TestRequest::get().uri(uri).to_request()).await;
+    let status = resp.status().as_u16();
+    let bytes = test::read_body(resp).await;
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn post<S, B>(app: &S, uri: &str, body: Value) -> (u16, Value)
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let req = test::TestRequest::post()
+        .uri(uri)
+        .set_json(body)
+        .to_request();
+    let resp = test::call_service(app, req).await;
+    let status = resp.status().as_u16();
+    let bytes = test::read_body(resp).await;
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+/// Run a whole sign-in as `subject`; returns the callback's status and body.
+async fn sign_in<S, B>(
+    app: &S,
+    issuer: &MockIssuer,
+    provider: Option<&str>,
+    subject: &str,
+) -> (u16, Value)
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let uri = match provider {
+        Some(name) => format!("/api/auth/oidc/authorize?provider={name}"),
+        None => "/api/auth/oidc/authorize".to_string(),
+    };
+    let (status, authorize) = get(app, &uri).await;
+    assert_eq!(status, 200, "{authorize}");
+    let code = issuer.approve(authorize["authorize_url"].as_str().unwrap(), subject);
+    get(
+        app,
+        &format!(
+            "/api/auth/oidc/callback?code={code}&state={}",
+            authorize["state"].as_str().unwrap()
+        ),
+    )
+    .await
+}
+
+#[actix_web::test]
+async fn sign_in_binds_state_nonce_and_pkce_server_side() {
+    let issuer = MockIssuer::start().await;
+    let mut config = config_with_default(&issuer);
+    config
+        .auth
+        .group_mappings
+        .insert("engineering".to_string(), vec!["Engineering".to_string()]);
+    let (_tmp, state, config) = setup(config).await;
+    let app = app!(state, config);
+
+    let (status, authorize) = get(&app, "/api/auth/oidc/authorize").await;
+    assert_eq!(status, 200, "{authorize}");
+    assert_eq!(authorize["provider"], "default");
+    let state_token = authorize["state"].as_str().unwrap().to_string();
+    let code = issuer.approve(authorize["authorize_url"].as_str().unwrap(), "ada");
+
+    // An unknown state is refused before the code is spent.
+    let (status, _) = get(
+        &app,
+        &format!("/api/auth/oidc/callback?code={code}&state=forged"),
+    )
+    .await;
+    assert_eq!(status, 401);
+
+    let callback = format!("/api/auth/oidc/callback?code={code}&state={state_token}");
+    let (status, tokens) = get(&app, &callback).await;
+    assert_eq!(status, 200, "{tokens}");
+    assert!(tokens["access_token"].as_str().is_some());
+    let (user_id, _) = state.db.get_user_by_username("ada").await.unwrap().unwrap();
+    assert!(state
+        .db
+        .list_member_groups(&user_id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Each state finishes one sign-in.
+    let (status, _) = get(&app, &callback).await;
+    assert_eq!(status, 401);
+
+    // A sign-in whose state has expired is refused too.
+    let mut short = (**config).clone();
+    short.auth.oidc_state_ttl_secs = 0;
+    let short = web::Data::new(short);
+    let expired_app = app!(state, short);
+    let (status, body) = sign_in(&expired_app, &issuer, None, "ada").await;
+    assert_eq!(status, 401, "{body}");
+}
+
+#[actix_web::test]
+async fn id_tokens_failing_validation_are_rejected() {
+    let issuer = MockIssuer::start().await;
+    let (_tmp, state, config) = setup(config_with_default(&issuer)).await;
+    let app = app!(state, config);
+    let now = chrono::Utc::now().timestamp();
+
+    let cases: Vec<(&str, Value)> = vec![
+        ("nonce", json!("replayed-nonce")),
+        ("nonce", Value::Null),
+        ("aud", json!("someone-else")),
+        ("aud", json!([CLIENT_ID, "someone-else"])),
+        ("iss", json!("https://impostor.example.com")),
+        ("exp", json!(now - 600)),
+        ("iat", json!(now + 600)),
+        ("nbf", json!(now + 600)),
+    ];
+    for (claim, value) in cases {
+        issuer.state.overrides.lock().unwrap().clear();
+        issuer.set_claim(claim, value.clone());
+        let (status, body) = sign_in(&app, &issuer, None, "mallory").await;
+        assert_eq!(status, 401, "{claim}={value}: {body}");
+        assert!(
+            body["details"].as_str().unwrap().contains("ID token"),
+            "{claim}={value}: {body}"
+        );
+    }
+
+    // Several audiences are fine when we are the authorized party.
+    issuer.state.overrides.lock().unwrap().clear();
+    issuer.set_claim("aud", json!([CLIENT_ID, "someone-else"]));
+    issuer.set_claim("azp", json!(CLIENT_ID));
+    let (status, body) = sign_in(&app, &issuer, None, "grace").await;
+    assert_eq!(status, 200, "{body}");
+
+    // Expiry within the configured clock skew is tolerated.
+    issuer.state.overrides.lock().unwrap().clear();
+    issuer.set_claim("exp", json!(now - 30));
+    let (status, body) = sign_in(&app, &issuer, None, "grace").await;
+    assert_eq!(status, 200, "{body}");
+
+    // A signature from a key the provider never published fails.
+    issuer.state.overrides.lock().unwrap().clear();
+    *issuer.state.rogue_key.lock().unwrap() = Some(SigningKey::generate("key-1"));
+    let (status, body) = sign_in(&app, &issuer, None, "mallory").await;
+    assert_eq!(status, 401, "{body}");
+    assert!(
+        body["details"]
+            .as_str()
+            .unwrap()
+            .contains("InvalidSignature"),
+        "{body}"
+    );
+
+    assert!(state
+        .db
+        .get_user_by_username("mallory")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[actix_web::test]
+async fn rotated_signing_keys_are_fetched_once_seen() {
+    let issuer = MockIssuer::start().await;
+    let (_tmp, state, config) = setup(config_with_default(&issuer)).await;
+    let app = app!(state, config);
+
+    let (status, body) = sign_in(&app, &issuer, None, "ada").await;
+    assert_eq!(status, 200, "{body}");
+    let (status, body) = sign_in(&app, &issuer, None, "ada").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(issuer.state.jwks_fetches.load(Ordering::SeqCst), 1);
+
+    issuer.rotate_key("key-2");
+    let (status, body) = sign_in(&app, &issuer, None, "ada").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(issuer.state.jwks_fetches.load(Ordering::SeqCst), 2);
+}
+
+#[actix_web::test]
+async fn named_providers_and_rp_initiated_logout() {
+    let alpha = MockIssuer::start().await;
+    let beta = MockIssuer::start().await;
+    let mut config = AppConfig::default();
+    config.auth.enabled = true;
+    config.auth.provider = "oidc".to_string();
+    config.auth.jwt_secret = "integration-test-secret".to_string();
+    config
+        .auth
+        .oidc_providers
+        .insert("alpha".to_string(), alpha.provider("Alpha Corp"));
+    let mut beta_provider = beta.provider("Beta Inc");
+    beta_provider.client_secret = None;
+    config
+        .auth
+        .oidc_providers
+        .insert("beta".to_string(), beta_provider);
+    let (_tmp, state, config) = setup(config).await;
+    let app = app!(state, config);
+
+    let (status, providers) = get(&app, "/api/auth/oidc/providers").await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        providers,
+        json!([
+            { "name": "alpha", "display_name": "Alpha Corp" },
+            { "name": "beta", "display_name": "Beta Inc" },
+        ])
+    );
+
+    let (status, _) = get(&app, "/api/auth/oidc/authorize").await;
+    assert_eq!(status, 400);
+    let (status, _) = get(&app, "/api/auth/oidc/authorize?provider=gamma").await;
+    assert_eq!(status, 404);
+
+    // A code issued by one provider cannot finish a sign-in begun with another.
+    let (_, authorize) = get(&app, "/api/auth/oidc/authorize?provider=alpha").await;
+    let code = beta.approve(
+        &authorize["authorize_url"]
+            .as_str()
+            .unwrap()
+            .replace(alpha.base(), beta.base()),
+        "eve",
+    );
+    let (status, _) = get(
+        &app,
+        &format!(
+            "/api/auth/oidc/callback?code={code}&state={}",
+            authorize["state"].as_str().unwrap()
+        ),
+    )
+    .await;
+    assert_eq!(status, 401);
+
+    let (status, tokens) = sign_in(&app, &beta, Some("beta"), "ada").await;
+    assert_eq!(status, 200, "{tokens}");
+
+    // The OIDC session survives refresh-token rotation.
+    let (status, refreshed) = post(
+        &app,
+        "/api/auth/refresh",
+        json!({ "refresh_token": tokens["refresh_token"] }),
+    )
+    .await;
+    assert_eq!(status, 200, "{refreshed}");
+    let refresh_token = refreshed["refresh_token"].clone();
+
+    let (status, logout) = post(
+        &app,
+        "/api/auth/oidc/logout",
+        json!({ "refresh_token": refresh_token }),
+    )
+    .await;
+    assert_eq!(status, 200, "{logout}");
+    let logout_url = reqwest::Url::parse(logout["logout_url"].as_str().unwrap()).unwrap();
+    assert!(logout_url
+        .as_str()
+        .starts_with(&format!("{}/logout?", beta.base())));
+    let params: HashMap<_, _> = logout_url.query_pairs().into_owned().collect();
+    assert_eq!(params["client_id"], CLIENT_ID);
+    assert_eq!(params["post_logout_redirect_uri"], "http://localhost:8080/");
+    assert_eq!(params["id_token_hint"].split('.').count(), 3);
+
+    // The session is gone with it.
+    let (status, _) = post(
+        &app,
+        "/api/auth/refresh",
+        json!({ "refresh_token": refresh_token }),
+    )
+    .await;
+    assert_eq!(status, 401);
+}
@@ -0,0 +1,683 @@
+//! Runs the storage layer against SQLite and a throwaway PostgreSQL server.
+//!
+//! The Postgres cases start a private server with `initdb`/`pg_ctl` when the
+//! binaries are available (or use `CODEX_TEST_POSTGRES_URL`) and are skipped
+//! otherwise.
+
+use chrono::{Duration, Utc};
+use codex::db::{Database, DatabaseBackend};
+use codex::models::bookmarks::Bookmark;
+use codex::models::git::VaultGitSettings;
+use codex::models::trash::TrashItem;
+use codex::models::upload::UploadSession;
+use codex::models::{
+    AclPrincipalType, ApiKeyRestrictions, EditorMode, FileChangeEvent, FileChangeType,
+    MlUndoReceipt, PathAccess, PathAclEntry, ReverseAction, ShareLink, ShareLinkPermission,
+    VaultRole, WsMessage,
+};
+use codex::services::{ClusterService, EntityService, LabelService, ReindexService};
+use std::collections::BTreeSet;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+/// A PostgreSQL cluster living in a temporary directory, stopped on drop.
+struct ThrowawayPostgres {
+    dir: TempDir,
+    bin: PathBuf,
+    port: u16,
+    /// Postgres refuses to run as root, so commands go through this user.
+    run_as: Option<String>,
+}
+
+impl ThrowawayPostgres {
+    fn start() -> Option<Self> {
+        let bin = find_postgres_bin()?;
+        let dir = TempDir::new().ok()?;
+        let run_as = is_root().then(|| "postgres".to_string());
+        if let Some(user) = &run_as {
+            let status = Command::new("chown")
+                .arg(user)
+                .arg(dir.path())
+                .status()
+                .ok()?;
+            if !status.success() {
+                return None;
+            }
+        }
+        let port = TcpListener::bind("127.0.0.1:0")
+            .ok()?
+            .local_addr()
+            .ok()?
+            .port();
+
+        let server = Self {
+            dir,
+            bin,
+            port,
+            run_as,
+        };
+        let data = server.dir.path().join("data");
+        let initialised = server.run(
+            "initdb",
+            &[
+                "-D",
+                data.to_str()?,
+                "-A",
+                "trust",
+                "-U",
+                "codex",
+                "-E",
+                "UTF8",
+                "--locale=C",
+            ],
+        );
+        let options = format!(
+            "-p {} -k {} -c listen_addresses=127.0.0.1 -c fsync=off",
+            server.port,
+            server.dir.path().display()
+        );
+        let log = server.dir.path().join("postgres.log");
+        let started = initialised
+            && server.run(
+                "pg_ctl",
+                &[
+                    "-D",
+                    data.to_str()?,
+                    "-o",
+                    &options,
+                    "-l",
+                    log.to_str()?,
+                    "-w",
+                    "start",
+                ],
+            );
+        if !started {
+            eprintln!(
+                "could not start a throwaway Postgres: {}",
+                std::fs::read_to_string(&log).unwrap_or_default()
+            );
+            return None;
+        }
+        Some(server)
+    }
+
+    fn run(&self, program: &str, args: &[&str]) -> bool {
+        let program = self.bin.join(program);
+        let mut command = match &self.run_as {
+            Some(user) => {
+                let mut command = Command::new("runuser");
+                command.args(["-u", user, "--"]).arg(program);
+                command
+            }
+            None => Command::new(program),
+        };
+        command
+            .args(args)
+            .current_dir(self.dir.path())
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    fn url(&self, database: &str) -> String {
+        format!("postgres://codex@127.0.0.1:{}/{}", self.port, database)
+    }
+}
+
+impl Drop for ThrowawayPostgres {
+    fn drop(&mut self) {
+        let data = self.dir.path().join("data");
+        self.run(
+            "pg_ctl",
+            &["-D", &data.to_string_lossy(), "-m", "immediate", "stop"],
+        );
+    }
+}
+
+fn find_postgres_bin() -> Option<PathBuf> {
+    let on_path = std::env::var_os("PATH")
+        .and_then(|paths| std::env::split_paths(&paths).find(|dir| dir.join("initdb").is_file()));
+    on_path.or_else(|| {
+        let mut versions: Vec<PathBuf> = std::fs::read_dir("/usr/lib/postgresql")
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path().join("bin")))
+            .filter(|bin| bin.join("initdb").is_file())
+            .collect();
+        versions.sort();
+        versions.pop()
+    })
+}
+
+fn is_root() -> bool {
+    Command::new("id")
+        .arg("-u")
+        .output()
+        .is_ok_and(|output| output.stdout.starts_with(b"0\n"))
+}
+
+/// A Postgres server to test against: `CODEX_TEST_POSTGRES_URL` (pointing at
+/// a database the user may `CREATE DATABASE` from) or a throwaway one.
+enum PostgresServer {
+    External(String),
+    Throwaway(ThrowawayPostgres),
+}
+
+impl PostgresServer {
+    fn get() -> Option<Self> {
+        if let Ok(url) = std::env::var("CODEX_TEST_POSTGRES_URL") {
+            return Some(Self::External(url));
+        }
+        let server = ThrowawayPostgres::start().map(Self::Throwaway);
+        if server.is_none() {
+            eprintln!("skipping: no PostgreSQL binaries and no CODEX_TEST_POSTGRES_URL");
+        }
+        server
+    }
+
+    /// URL of a new, empty database.
+    async fn create_database(&self, name: &str) -> String {
+        let admin_url = match self {
+            Self::External(url) => url.clone(),
+            Self::Throwaway(server) => server.url("postgres"),
+        };
+        let admin = Database::connect(&admin_url).await.unwrap();
+        let name = format!("codex_{}_{}", name, uuid::Uuid::new_v4().simple());
+        sqlx::query(&format!("CREATE DATABASE {name}"))
+            .execute(admin.pool())
+            .await
+            .unwrap();
+        admin.pool().close().await;
+
+        match admin_url.rsplit_once('/') {
+            Some((server, _)) => format!("{server}/{name}"),
+            None => panic!("unexpected Postgres URL {admin_url}"),
+        }
+    }
+}
+
+async fn table_columns(db: &Database) -> BTreeSet<(String, String)> {
+    let sql = match db.backend() {
+        DatabaseBackend::Sqlite => {
+            "SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p \
+             WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'"
+        }
+        DatabaseBackend::Postgres => {
+            "SELECT table_name::text, column_name::text FROM information_schema.columns \
+             WHERE table_schema = current_schema()"
+        }
+    };
+    let rows: Vec<(String, String)> = sqlx::query_as(sql).fetch_all(db.pool()).await.unwrap();
+    rows.into_iter().collect()
+}
+
+/// Exercise every kind of query the server issues: inserts, upserts,
+/// conflicts, joins, counters and deletes across the whole schema.
+async fn exercise_storage(db: &Database, vault_dir: &Path) {
+    let backend = format!("{:?}", db.backend());
+    let status = db.migration_status().await.unwrap();
+    assert_eq!(status.current_version, status.latest_version, "{backend}");
+    assert!(status.pending.is_empty());
+    assert!(db.migrate().await.unwrap().is_empty());
+
+    // Users, lockout and TOTP.
+    assert!(db
+        .bootstrap_admin_if_empty(Some("admin"), Some("hunter2 hunter2"))
+        .await
+        .unwrap());
+    let (alice, _, _, _) = db
+        .create_user_with_options("alice", "hash-a", false, true)
+        .await
+        .unwrap();
+    let (bob, _, _, _) = db
+        .create_user_with_options("bob", "hash-b", false, false)
+        .await
+        .unwrap();
+    assert!(db
+        .create_user_with_options("alice", "again", false, false)
+        .await
+        .is_err());
+    assert_eq!(db.user_count().await.unwrap(), 3);
+    assert!(db.user_must_change_password(&alice).await.unwrap());
+    assert!(!db.is_user_admin(&alice).await.unwrap());
+    let usernames: Vec<String> = db
+        .list_users()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|u| u.username)
+        .collect();
+    assert_eq!(usernames, vec!["admin", "alice", "bob"]);
+    assert_eq!(db.record_failed_login(&bob).await.unwrap(), 1);
+    assert_eq!(db.record_failed_login(&bob).await.unwrap(), 2);
+    db.lock_user_until(&bob, Utc::now() + Duration::minutes(5))
+        .await
+        .unwrap();
+    assert!(db.get_lockout_status(&bob).await.unwrap().is_some());
+    db.clear_failed_logins(&bob).await.unwrap();
+    assert!(db.get_lockout_status(&bob).await.unwrap().is_none());
+    db.deactivate_user(&bob).await.unwrap();
+    assert!(!db.is_user_active(&bob).await.unwrap());
+    db.reactivate_user(&bob).await.unwrap();
+    db.set_totp_secret(&alice, "SECRET", &["code-1".to_string()])
+        .await
+        .unwrap();
+    db.enable_totp(&alice).await.unwrap();
+    assert!(db.get_totp_state(&alice).await.unwrap().0);
+    assert!(db.consume_backup_code(&alice, "code-1").await.unwrap());
+    assert!(!db.consume_backup_code(&alice, "code-1").await.unwrap());
+
+    // Sessions and API keys.
+    db.create_session("tok-1", &alice, Utc::now() + Duration::hours(1))
+        .await
+        .unwrap();
+    db.create_session("tok-2", &alice, Utc::now() - Duration::hours(1))
+        .await
+        .unwrap();
+    assert_eq!(db.list_active_sessions(&alice).await.unwrap().len(), 1);
+    assert_eq!(db.purge_expired_sessions().await.unwrap(), 1);
+    db.revoke_session("tok-1").await.unwrap();
+    assert!(db.is_session_revoked("tok-1").await.unwrap());
+    db.create_api_key(
+        "key-1",
+        "sync",
+        "cdx_abcd",
+        "key-hash",
+        &alice,
+        None,
+        &ApiKeyRestrictions {
+            path_globs: vec!["notes/**".to_string()],
+            ..ApiKeyRestrictions::default()
+        },
+    )
+    .await
+    .unwrap();
+    db.touch_api_key("key-1", Some("10.0.0.1")).await.unwrap();
+    let (key, hash) = db.get_api_key_by_prefix("cdx_abcd").await.unwrap().unwrap();
+    assert_eq!(hash, "key-hash");
+    assert_eq!(key.restrictions.path_globs, vec!["notes/**"]);
+    assert_eq!(key.last_used_ip.as_deref(), Some("10.0.0.1"));
+    db.revoke_api_key("key-1", &alice).await.unwrap();
+    assert!(db.list_api_keys(&alice).await.unwrap()[0].revoked);
+
+    // Vaults, groups, shares and path ACLs.
+    let vault = db
+        .create_vault_for_owner(
+            "Notes".to_string(),
+            vault_dir.to_string_lossy().to_string(),
+            Some(&alice),
+        )
+        .await
+        .unwrap();
+    let group = db.create_group("team", &alice).await.unwrap();
+    db.add_user_to_group(&group.id, &bob).await.unwrap();
+    db.add_user_to_group(&group.id, &bob).await.unwrap();
+    assert_eq!(db.list_group_members(&group.id).await.unwrap().len(), 2);
+    assert!(db.is_group_manager(&group.id, &alice).await.unwrap());
+    db.share_vault_with_group(&vault.id, &group.id, &VaultRole::Viewer)
+        .await
+        .unwrap();
+    db.share_vault_with_user(&vault.id, &bob, &VaultRole::Editor)
+        .await
+        .unwrap();
+    db.share_vault_with_user(&vault.id, &bob, &VaultRole::Viewer)
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_vault_role_for_user(&vault.id, &bob).await.unwrap(),
+        Some(VaultRole::Viewer)
+    );
+    assert_eq!(db.list_vaults_for_user(&bob).await.unwrap().len(), 1);
+    let shares = db.list_vault_shares(&vault.id).await.unwrap();
+    assert_eq!(shares.user_shares.len(), 1);
+    assert_eq!(shares.group_shares.len(), 1);
+    db.revoke_vault_group_share(&vault.id, &group.id)
+        .await
+        .unwrap();
+    db.remove_user_from_group(&group.id, &bob).await.unwrap();
+    db.create_path_acl(&PathAclEntry {
+        id: "acl-1".to_string(),
+        vault_id: vault.id.clone(),
+        pattern: "hr/**".to_string(),
+        principal_type: AclPrincipalType::Everyone,
+        principal_id: None,
+        access: PathAccess::None,
+        created_at: Utc::now(),
+    })
+    .await
+    .unwrap();
+    assert_eq!(db.list_path_acls(&vault.id).await.unwrap().len(), 1);
+    db.set_vault_visibility(&vault.id, "public").await.unwrap();
+    assert_eq!(db.get_vault_visibility(&vault.id).await.unwrap(), "public");
+
+    // Share links.
+    db.create_share_link(
+        &ShareLink {
+            id: "link-1".to_string(),
+            vault_id: vault.id.clone(),
+            path: "notes".to_string(),
+            is_folder: true,
+            permission: ShareLinkPermission::Comment,
+            password_protected: false,
+            expires_at: None,
+            created_by: Some(alice.clone()),
+            created_at: Utc::now(),
+            revoked_at: None,
+            access_count: 0,
+            last_accessed_at: None,
+        },
+        "token-hash",
+        None,
+    )
+    .await
+    .unwrap();
+    db.record_share_link_access("link-1").await.unwrap();
+    let (link, _) = db
+        .get_share_link_by_token_hash("token-hash")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(link.is_folder);
+    assert_eq!(link.access_count, 1);
+    db.revoke_share_link(&vault.id, "link-1").await.unwrap();
+
+    // Preferences, recents, bookmarks and the change log.
+    let mut prefs = db.get_preferences_for_user(Some(&alice)).await.unwrap();
+    prefs.editor_mode = EditorMode::Raw;
+    prefs.font_size = 18;
+    db.update_preferences_for_user(Some(&alice), &prefs)
+        .await
+        .unwrap();
+    db.update_preference_for_user(Some(&alice), "theme", "light")
+        .await
+        .unwrap();
+    let prefs = db.get_preferences_for_user(Some(&alice)).await.unwrap();
+    assert_eq!(prefs.font_size, 18);
+    assert_eq!(prefs.theme, "light");
+    db.update_preference("font_size", "12").await.unwrap();
+    assert_eq!(db.get_preferences().await.unwrap().font_size, 12);
+    for path in ["a.md", "b.md", "a.md"] {
+        db.record_recent_file(&vault.id, path).await.unwrap();
+    }
+    assert_eq!(db.get_recent_files(&vault.id, 10).await.unwrap().len(), 2);
+    for title in ["First", "Renamed"] {
+        db.create_bookmark(&Bookmark {
+            id: uuid::Uuid::new_v4().to_string(),
+            vault_id: vault.id.clone(),
+            path: "a.md".to_string(),
+            title: title.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+        })
+        .await
+        .unwrap();
+    }
+    let bookmarks = db.list_bookmarks(&vault.id).await.unwrap();
+    assert_eq!(bookmarks.len(), 1);
+    assert_eq!(bookmarks[0].title, "Renamed");
+    db.log_file_change(&vault.id, "a.md", "modified", Some("\"1\""), None, 7)
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_file_changes_since(&vault.id, 0).await.unwrap().len(),
+        1
+    );
+
+    // ML undo receipts, trash, uploads and git settings.
+    let receipt = MlUndoReceipt {
+        receipt_id: "r-1".to_string(),
+        vault_id: vault.id.clone(),
+        file_path: "a.md".to_string(),
+        description: "tag".to_string(),
+        reverse_action: ReverseAction::RemoveTag {
+            tag: "x".to_string(),
+        },
+        applied_at: Utc::now(),
+    };
+    db.save_ml_undo_receipt(&receipt).await.unwrap();
+    db.save_ml_undo_receipt(&receipt).await.unwrap();
+    db.consume_ml_undo_receipt(&vault.id, "r-1").await.unwrap();
+    let trashed = TrashItem {
+        trash_name: "20250101_000000.000_a.md".to_string(),
+        original_path: "a.md".to_string(),
+        trashed_at: Utc::now().to_rfc3339(),
+        size: 3,
+        deleted_by: None,
+        deleted_by_username: None,
+    };
+    db.insert_trash_item(&vault.id, &trashed, Some(&alice))
+        .await
+        .unwrap();
+    db.insert_trash_item(&vault.id, &trashed, Some(&alice))
+        .await
+        .unwrap();
+    let trash = db.list_trash_items(&vault.id).await.unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].deleted_by_username.as_deref(), Some("alice"));
+    db.create_upload_session(&UploadSession {
+        id: "up-1".to_string(),
+        vault_id: vault.id.clone(),
+        owner_id: Some(alice.clone()),
+        filename: "big.bin".to_string(),
+        path: "big.bin".to_string(),
+        total_size: Some(10),
+        sha256: None,
+        created_at: Utc::now(),
+        expires_at: Utc::now() - Duration::minutes(1),
+    })
+    .await
+    .unwrap();
+    db.record_upload_chunk("up-1", 0, 4, None).await.unwrap();
+    db.record_upload_chunk("up-1", 0, 5, None).await.unwrap();
+    assert_eq!(db.list_upload_chunks("up-1").await.unwrap(), vec![(0, 5)]);
+    assert_eq!(
+        db.list_expired_upload_sessions(Utc::now())
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    db.delete_upload_session("up-1").await.unwrap();
+    db.upsert_vault_git_settings(&VaultGitSettings {
+        vault_id: vault.id.clone(),
+        enabled: true,
+        auto_commit: false,
+        remote_url: None,
+        branch: "main".to_string(),
+        sync_interval_secs: 60,
+        last_sync_at: None,
+        last_sync_error: None,
+    })
+    .await
+    .unwrap();
+    db.record_vault_git_sync(&vault.id, Some("offline"))
+        .await
+        .unwrap();
+    let git = db.get_vault_git_settings(&vault.id).await.unwrap();
+    assert!(git.enabled && !git.auto_commit);
+    assert_eq!(git.last_sync_error.as_deref(), Some("offline"));
+    assert_eq!(db.list_git_enabled_vaults().await.unwrap().len(), 1);
+
+    // Entities, labels and field-derived relations.
+    LabelService::seed_core_labels(db).await.unwrap();
+    LabelService::seed_core_labels(db).await.unwrap();
+    assert!(!LabelService::list(db).await.unwrap().is_empty());
+    std::fs::write(
+        vault_dir.join("hero.md"),
+        "---\ncodex_type: character\ncodex_plugin: worldbuilding\nhome: \"[[castle]]\"\n---\n",
+    )
+    .unwrap();
+    std::fs::write(
+        vault_dir.join("castle.md"),
+        "---\ncodex_type: location\ncodex_plugin: worldbuilding\n---\n",
+    )
+    .unwrap();
+    ReindexService::reindex_vault(db, &vault.id, &vault.path)
+        .await
+        .unwrap();
+    let characters = EntityService::list(db, &vault.id, &["character".into()], None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(characters.len(), 1, "{backend}");
+    assert_eq!(
+        EntityService::list_all_in_vault(db, &vault.id)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    // Audit log and cascading deletes.
+    db.write_audit_log(Some(&alice), Some("alice"), "login", None, None, true)
+        .await
+        .unwrap();
+    let audit = db.get_audit_log(Some(10)).await.unwrap();
+    assert!(audit.iter().any(|e| e.event_type == "login" && e.success));
+    db.delete_vault(&vault.id).await.unwrap();
+    assert!(db.get_vault(&vault.id).await.is_err());
+    db.delete_user(&bob).await.unwrap();
+    assert_eq!(db.user_count().await.unwrap(), 2);
+}
+
+#[actix_web::test]
+async fn storage_behaves_the_same_on_sqlite_and_postgres() {
+    let dir = TempDir::new().unwrap();
+    let sqlite = Database::new(&format!(
+        "sqlite://{}",
+        dir.path().join("codex.db").display()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(sqlite.backend(), DatabaseBackend::Sqlite);
+    let vault_dir = dir.path().join("sqlite-vault");
+    std::fs::create_dir_all(&vault_dir).unwrap();
+    exercise_storage(&sqlite, &vault_dir).await;
+
+    let Some(server) = PostgresServer::get() else {
+        return;
+    };
+    let postgres = Database::new(&server.create_database("storage").await)
+        .await
+        .unwrap();
+    assert_eq!(postgres.backend(), DatabaseBackend::Postgres);
+    assert_eq!(table_columns(&postgres).await, table_columns(&sqlite).await);
+    let vault_dir = dir.path().join("postgres-vault");
+    std::fs::create_dir_all(&vault_dir).unwrap();
+    exercise_storage(&postgres, &vault_dir).await;
+
+    assert!(postgres
+        .vacuum_into(&dir.path().join("copy.db"))
+        .await
+        .is_err());
+}
+
+#[actix_web::test]
+async fn postgres_migrations_are_versioned_and_serialised() {
+    let Some(server) = PostgresServer::get() else {
+        return;
+    };
+    let url = server.create_database("migrations").await;
+
+    // Two instances starting together: one applies, the other waits and
+    // finds nothing left to do.
+    let (a, b) = tokio::join!(
+        async { Database::connect(&url).await.unwrap().migrate().await },
+        async { Database::connect(&url).await.unwrap().migrate().await },
+    );
+    let applied = a.unwrap().len() + b.unwrap().len();
+    let db = Database::connect(&url).await.unwrap();
+    let status = db.migration_status().await.unwrap();
+    assert_eq!(applied as i64, status.latest_version);
+    assert!(!status.legacy);
+
+    sqlx::query("UPDATE schema_migrations SET checksum = 'edited' WHERE version = 1")
+        .execute(db.pool())
+        .await
+        .unwrap();
+    assert!(db.migrate().await.is_err());
+}
+
+async fn recv_within<T: Clone>(rx: &mut broadcast::Receiver<T>) -> Option<T> {
+    tokio::time::timeout(std::time::Duration::from_millis(500), rx.recv())
+        .await
+        .ok()
+        .and_then(Result::ok)
+}
+
+#[actix_web::test]
+async fn live_events_fan_out_across_instances() {
+    let Some(server) = PostgresServer::get() else {
+        return;
+    };
+    let url = server.create_database("events").await;
+
+    let mut instances = Vec::new();
+    for _ in 0..2 {
+        let db = Database::new(&url).await.unwrap();
+        let (event_tx, _) = broadcast::channel::<FileChangeEvent>(16);
+        let (ws_tx, _) = broadcast::channel::<WsMessage>(16);
+        ClusterService::relay_events(&db, &url, event_tx.clone(), ws_tx.clone())
+            .await
+            .unwrap();
+        instances.push((db, event_tx, ws_tx));
+    }
+    let (_, a_events, a_ws) = &instances[0];
+    let (_, b_events, b_ws) = &instances[1];
+    let mut a_ws_rx = a_ws.subscribe();
+    let mut b_ws_rx = b_ws.subscribe();
+    let mut b_events_rx = b_events.subscribe();
+
+    a_ws.send(WsMessage::SyncConflict {
+        vault_id: "v1".to_string(),
+        path: "notes/a.md".to_string(),
+        conflict_path: None,
+    })
+    .unwrap();
+    assert!(matches!(
+        recv_within(&mut a_ws_rx).await,
+        Some(WsMessage::SyncConflict { .. })
+    ));
+    match recv_within(&mut b_ws_rx).await {
+        Some(WsMessage::SyncConflict { path, .. }) => assert_eq!(path, "notes/a.md"),
+        other => panic!("expected the conflict on the other instance, got {other:?}"),
+    }
+
+    a_events
+        .send(FileChangeEvent {
+            vault_id: "v1".to_string(),
+            path: "notes/b.md".to_string(),
+            event_type: FileChangeType::Modified,
+            timestamp: Utc::now(),
+        })
+        .unwrap();
+    let relayed = recv_within(&mut b_events_rx).await.unwrap();
+    assert_eq!(relayed.path, "notes/b.md");
+
+    // Nothing is echoed back or delivered twice.
+    assert!(recv_within(&mut a_ws_rx).await.is_none());
+    assert!(recv_within(&mut b_ws_rx).await.is_none());
+    assert!(recv_within(&mut b_events_rx).await.is_none());
+}
+
+#[actix_web::test]
+async fn only_one_instance_runs_the_background_jobs() {
+    let dir = TempDir::new().unwrap();
+    let url = format!("sqlite://{}", dir.path().join("codex.db").display());
+    let sqlite = Database::new(&url).await.unwrap();
+    assert!(ClusterService::elect_leader(&sqlite, &url)
+        .await
+        .is_leader());
+
+    let Some(server) = PostgresServer::get() else {
+        return;
+    };
+    let url = server.create_database("leader").await;
+    let mut leaders = 0;
+    for _ in 0..3 {
+        let db = Database::new(&url).await.unwrap();
+        if ClusterService::elect_leader(&db, &url).await.is_leader() {
+            leaders += 1;
+        }
+    }
+    assert_eq!(leaders, 1);
+}
@@ -36,7 +36,7 @@ async fn preferences_are_scoped_per_authenticated_user() {
         .await
         .unwrap();
 
-    let search_index = SearchIndex::new();
+    let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
     let (watcher, _) = FileWatcher::new().unwrap();
     let watcher = Arc::new(Mutex::new(watcher));
     let (event_tx, _) = broadcast::channel(100);
@@ -55,7 +55,8 @@ async fn preferences_are_scoped_per_authenticated_user() {
         document_parser: Arc::new(MarkdownParser),
         entity_type_registry: codex::services::EntityTypeRegistry::new(),
         relation_type_registry: codex::services::RelationTypeRegistry::new(),
-        plugins_dir: String::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
     });
 
     let mut config = AppConfig::default();
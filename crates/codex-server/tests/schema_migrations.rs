@@ -0,0 +1,169 @@
+use codex::db::Database;
+use codex::error::AppError;
+use sqlx::AnyPool;
+use std::collections::BTreeMap;
+use tempfile::TempDir;
+
+const LATEST: i64 = 13;
+/// Version legacy (pre-`schema_migrations`) databases are adopted at.
+const LEGACY: i64 = 7;
+
+fn db_url(dir: &TempDir, name: &str) -> String {
+    format!("sqlite://{}", dir.path().join(name).display())
+}
+
+/// Column names per table, used to compare an upgraded schema with a fresh one.
+async fn schema_shape(pool: &AnyPool) -> BTreeMap<String, Vec<String>> {
+    let tables: Vec<(String,)> = sqlx::query_as(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap();
+
+    let mut shape = BTreeMap::new();
+    for (table,) in tables {
+        let columns: Vec<(String,)> =
+            sqlx::query_as("SELECT name FROM pragma_table_info(?) ORDER BY name")
+                .bind(&table)
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        shape.insert(table, columns.into_iter().map(|(c,)| c).collect());
+    }
+    shape
+}
+
+async fn fresh_shape(dir: &TempDir) -> BTreeMap<String, Vec<String>> {
+    let db = Database::new(&db_url(dir, "fresh.db")).await.unwrap();
+    schema_shape(db.pool()).await
+}
+
+async fn load_fixture(dir: &TempDir, fixture: &str) -> String {
+    let url = db_url(dir, &format!("{fixture}.db"));
+    let db = Database::connect(&url).await.unwrap();
+    let sql = std::fs::read_to_string(format!(
+        "{}/tests/fixtures/legacy_schemas/{fixture}.sql",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .unwrap();
+    sqlx::raw_sql(&sql).execute(db.pool()).await.unwrap();
+    db.pool().close().await;
+    url
+}
+
+#[actix_web::test]
+async fn legacy_databases_are_adopted_without_data_loss() {
+    let dir = TempDir::new().unwrap();
+    let expected = fresh_shape(&dir).await;
+
+    for fixture in ["original", "baseline", "share_links"] {
+        let url = load_fixture(&dir, fixture).await;
+
+        let db = Database::connect(&url).await.unwrap();
+        let status = db.migration_status().await.unwrap();
+        assert!(status.legacy, "{fixture}");
+        assert_eq!(status.current_version, 0, "{fixture}");
+        let pending: Vec<i64> = status.pending.iter().map(|m| m.version).collect();
+        assert_eq!(
+            pending,
+            (LEGACY + 1..=LATEST).collect::<Vec<_>>(),
+            "{fixture}"
+        );
+
+        let applied = db.migrate().await.unwrap();
+        assert_eq!(applied.len(), LATEST as usize, "{fixture}");
+
+        let status = db.migration_status().await.unwrap();
+        assert!(!status.legacy, "{fixture}");
+        assert_eq!(status.current_version, LATEST, "{fixture}");
+        assert!(status.pending.is_empty(), "{fixture}");
+        assert_eq!(schema_shape(db.pool()).await, expected, "{fixture}");
+
+        let vault = db.get_vault("v-notes").await.unwrap();
+        assert_eq!(vault.name, "Notes", "{fixture}");
+        let users: Vec<(String, i64)> =
+            sqlx::query_as("SELECT username, is_active FROM users ORDER BY username")
+                .fetch_all(db.pool())
+                .await
+                .unwrap();
+        assert_eq!(
+            users,
+            vec![("alice".to_string(), 1), ("bob".to_string(), 1)],
+            "{fixture}"
+        );
+        let recent = db.get_recent_files("v-notes", 10).await.unwrap();
+        assert_eq!(recent, vec!["inbox.md".to_string()], "{fixture}");
+
+        // Re-running is a no-op.
+        assert!(db.migrate().await.unwrap().is_empty(), "{fixture}");
+        db.pool().close().await;
+    }
+
+    let db = Database::new(&db_url(&dir, "share_links.db"))
+        .await
+        .unwrap();
+    let links = db.list_share_links("v-notes").await.unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].path, "inbox.md");
+}
+
+#[actix_web::test]
+async fn every_version_upgrades_to_the_latest_schema() {
+    let dir = TempDir::new().unwrap();
+    let expected = fresh_shape(&dir).await;
+
+    for version in 1..=LATEST {
+        let db = Database::connect(&db_url(&dir, &format!("v{version}.db")))
+            .await
+            .unwrap();
+        let applied = db.migrate_to(version).await.unwrap();
+        assert_eq!(applied.len(), version as usize);
+        assert_eq!(
+            db.migration_status().await.unwrap().current_version,
+            version
+        );
+
+        let applied = db.migrate().await.unwrap();
+        assert_eq!(applied.len(), (LATEST - version) as usize);
+        assert_eq!(schema_shape(db.pool()).await, expected, "from v{version}");
+
+        // Downgrades are refused.
+        assert!(matches!(
+            db.migrate_to(1).await,
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+}
+
+#[actix_web::test]
+async fn unknown_or_tampered_migrations_are_refused() {
+    let dir = TempDir::new().unwrap();
+
+    let url = db_url(&dir, "newer.db");
+    let db = Database::new(&url).await.unwrap();
+    sqlx::query(
+        "INSERT INTO schema_migrations (version, name, checksum, applied_at, duration_ms)
+         VALUES (9999, 'from_the_future', 'x', '2030-01-01T00:00:00Z', 0)",
+    )
+    .execute(db.pool())
+    .await
+    .unwrap();
+    assert!(db.migration_status().await.is_err());
+    assert!(db.migrate().await.is_err());
+    db.pool().close().await;
+    assert!(Database::new(&url).await.is_err());
+
+    let db = Database::new(&db_url(&dir, "tampered.db")).await.unwrap();
+    sqlx::query("UPDATE schema_migrations SET checksum = 'edited' WHERE version = 3")
+        .execute(db.pool())
+        .await
+        .unwrap();
+    assert!(db.migrate().await.is_err());
+
+    let db = Database::new(&db_url(&dir, "target.db")).await.unwrap();
+    assert!(matches!(
+        db.migrate_to(LATEST + 1).await,
+        Err(AppError::InvalidInput(_))
+    ));
+}
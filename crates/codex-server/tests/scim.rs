@@ -0,0 +1,443 @@
+use actix_web::{http::header, test, web, App};
+use codex::config::AppConfig;
+use codex::db::Database;
+use codex::middleware::AuthMiddleware;
+use codex::routes::{admin, auth, scim, AppState};
+use codex::services::{MarkdownParser, SearchIndex};
+use codex::watcher::FileWatcher;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::{broadcast, Mutex};
+
+const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+const PATCH_OP_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+
+async fn setup() -> (TempDir, web::Data<AppState>, web::Data<AppConfig>) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_url = format!("sqlite://{}", temp_dir.path().join("scim.db").display());
+    let db = Database::new(&db_url).await.unwrap();
+    db.bootstrap_admin_if_empty(Some("admin"), Some("hunter2"))
+        .await
+        .unwrap();
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let (event_tx, _) = broadcast::channel(100);
+    let state = web::Data::new(AppState {
+        db,
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: event_tx,
+        ws_broadcaster: tokio::sync::broadcast::channel::<codex::models::WsMessage>(16).0,
+        change_log_retention_days: 7,
+        ml_undo_store: std::sync::Arc::new(tokio::sync::Mutex::new(
+            std::collections::HashMap::new(),
+        )),
+        shutdown_tx: tokio::sync::broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
+    });
+
+    let mut config = AppConfig::default();
+    config.auth.enabled = true;
+    config.auth.jwt_secret = "integration-test-secret".to_string();
+    (temp_dir, state, web::Data::new(config))
+}
+
+macro_rules! app {
+    ($state:expr, $config:expr) => {
+        test::init_service(
+            App::new()
+                .app_data($state.clone())
+                .app_data($config.clone())
+                .wrap(AuthMiddleware)
+                .configure(auth::configure)
+                .configure(admin::configure)
+                .configure(scim::configure),
+        )
+        .await
+    };
+}
+
+async fn call<S, B>(
+    app: &S,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (u16, Value)
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let mut req = match method {
+        "GET" => test::TestRequest::get(),
+        "POST" => test::TestRequest::post(),
+        "PUT" => test::TestRequest::put(),
+        "PATCH" => test::TestRequest::patch(),
+        "DELETE" => test::TestRequest::delete(),
+        other => panic!("unsupported method {other}"),
+    }
+    .uri(uri);
+    if let Some(body) = body {
+        req = req
+            .insert_header((header::CONTENT_TYPE, "application/scim+json"))
+            .set_payload(body.to_string());
+    }
+    if let Some(token) = token {
+        req = req.insert_header((header::AUTHORIZATION, format!("Bearer {token}")));
+    }
+    let resp = test::call_service(app, req.to_request()).await;
+    let status = resp.status().as_u16();
+    let bytes = test::read_body(resp).await;
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn login<S, B>(app: &S, username: &str, password: &str) -> (u16, Value)
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "username": username, "password": password }))
+        .to_request();
+    let resp = test::call_service(app, req).await;
+    let status = resp.status().as_u16();
+    (status, test::read_body_json(resp).await)
+}
+
+/// Sign in as the bootstrap admin and issue a SCIM token.
+async fn issue_scim_token<S, B>(app: &S) -> (String, Value)
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let (status, admin) = login(app, "admin", "hunter2").await;
+    assert_eq!(status, 200, "{admin}");
+    let admin_token = admin["access_token"].as_str().unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/api/admin/scim/tokens")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {admin_token}")))
+        .set_json(json!({ "name": "Okta" }))
+        .to_request();
+    let resp = test::call_service(app, req).await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let created: Value = test::read_body_json(resp).await;
+    (created["token"].as_str().unwrap().to_string(), created)
+}
+
+fn patch_op(operations: Value) -> Value {
+    json!({ "schemas": [PATCH_OP_SCHEMA], "Operations": operations })
+}
+
+#[actix_web::test]
+async fn scim_requires_an_unrevoked_token() {
+    let (_tmp, state, config) = setup().await;
+    let app = app!(state, config);
+
+    let (status, body) = call(&app, "GET", "/scim/v2/Users", None, None).await;
+    assert_eq!(status, 401);
+    assert_eq!(body["schemas"][0], ERROR_SCHEMA);
+    assert_eq!(body["status"], "401");
+
+    // A user's own access token is not a SCIM token.
+    let (_, admin) = login(&app, "admin", "hunter2").await;
+    let admin_token = admin["access_token"].as_str().unwrap();
+    let (status, _) = call(&app, "GET", "/scim/v2/Users", Some(admin_token), None).await;
+    assert_eq!(status, 401);
+
+    let (token, created) = issue_scim_token(&app).await;
+    assert!(token.starts_with("scim_"));
+    let (status, list) = call(&app, "GET", "/scim/v2/Users", Some(&token), None).await;
+    assert_eq!(status, 200, "{list}");
+    assert_eq!(list["totalResults"], 1);
+    assert_eq!(list["Resources"][0]["userName"], "admin");
+
+    let (status, spc) = call(
+        &app,
+        "GET",
+        "/scim/v2/ServiceProviderConfig",
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(spc["patch"]["supported"], true);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!(
+            "/api/admin/scim/tokens/{}",
+            created["id"].as_str().unwrap()
+        ))
+        .insert_header((header::AUTHORIZATION, format!("Bearer {admin_token}")))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+    let (status, _) = call(&app, "GET", "/scim/v2/Users", Some(&token), None).await;
+    assert_eq!(status, 401);
+}
+
+#[actix_web::test]
+async fn deprovisioning_deactivates_the_user_and_revokes_sessions() {
+    let (_tmp, state, config) = setup().await;
+    let app = app!(state, config);
+    let (token, _) = issue_scim_token(&app).await;
+
+    let (status, user) = call(
+        &app,
+        "POST",
+        "/scim/v2/Users",
+        Some(&token),
+        Some(json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User"],
+            "userName": "ada@example.com",
+            "externalId": "00u1ada",
+            "name": { "givenName": "Ada", "familyName": "Lovelace" },
+            "emails": [{ "value": "ada@example.com", "type": "work", "primary": true }],
+            "password": "Analytical-Engine-1843",
+            "active": true,
+        })),
+    )
+    .await;
+    assert_eq!(status, 201, "{user}");
+    assert_eq!(user["displayName"], "Ada Lovelace");
+    assert_eq!(user["active"], true);
+    let user_id = user["id"].as_str().unwrap().to_string();
+
+    // Providers look users up by userName or externalId before creating them.
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/scim/v2/Users?filter=externalId%20eq%20%2200u1ada%22",
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(found["totalResults"], 1);
+    assert_eq!(found["Resources"][0]["id"], user_id.as_str());
+
+    let (status, duplicate) = call(
+        &app,
+        "POST",
+        "/scim/v2/Users",
+        Some(&token),
+        Some(json!({ "userName": "ada@example.com" })),
+    )
+    .await;
+    assert_eq!(status, 409);
+    assert_eq!(duplicate["scimType"], "uniqueness");
+
+    let (status, session) = login(&app, "ada@example.com", "Analytical-Engine-1843").await;
+    assert_eq!(status, 200, "{session}");
+    let refresh_token = session["refresh_token"].as_str().unwrap().to_string();
+
+    // Okta-style deactivation: a path-less replace.
+    let (status, patched) = call(
+        &app,
+        "PATCH",
+        &format!("/scim/v2/Users/{user_id}"),
+        Some(&token),
+        Some(patch_op(
+            json!([{ "op": "replace", "value": { "active": false } }]),
+        )),
+    )
+    .await;
+    assert_eq!(status, 200, "{patched}");
+    assert_eq!(patched["active"], false);
+    assert!(!state.db.is_user_active(&user_id).await.unwrap());
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/refresh")
+        .set_json(json!({ "refresh_token": refresh_token }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+    let (status, _) = login(&app, "ada@example.com", "Analytical-Engine-1843").await;
+    assert_eq!(status, 401);
+
+    let audit = state.db.get_audit_log(Some(50)).await.unwrap();
+    let deactivated = audit
+        .iter()
+        .find(|e| e.event_type == "user_deactivated")
+        .expect("deprovisioning is audited");
+    assert_eq!(deactivated.username.as_deref(), Some("scim:Okta"));
+    assert!(deactivated
+        .detail
+        .as_deref()
+        .unwrap()
+        .contains("revoked 1 session"));
+
+    // Entra-style reactivation with a string boolean and a path.
+    let (status, patched) = call(
+        &app,
+        "PATCH",
+        &format!("/scim/v2/Users/{user_id}"),
+        Some(&token),
+        Some(patch_op(json!([
+            { "op": "Replace", "path": "active", "value": "True" },
+            { "op": "Replace", "path": "emails[type eq \"work\"].value", "value": "ada@lovelace.org" },
+        ]))),
+    )
+    .await;
+    assert_eq!(status, 200, "{patched}");
+    assert_eq!(patched["active"], true);
+    assert_eq!(patched["emails"][0]["value"], "ada@lovelace.org");
+    let (status, _) = login(&app, "ada@example.com", "Analytical-Engine-1843").await;
+    assert_eq!(status, 200);
+
+    // DELETE deprovisions but keeps the account (and its vaults).
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/scim/v2/Users/{user_id}"),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, 204);
+    let (status, user) = call(
+        &app,
+        "GET",
+        &format!("/scim/v2/Users/{user_id}"),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(user["active"], false);
+
+    let (status, missing) = call(&app, "GET", "/scim/v2/Users/nope", Some(&token), None).await;
+    assert_eq!(status, 404);
+    assert_eq!(missing["schemas"][0], ERROR_SCHEMA);
+}
+
+#[actix_web::test]
+async fn groups_and_memberships_follow_the_provider() {
+    let (_tmp, state, config) = setup().await;
+    let app = app!(state, config);
+    let (token, _) = issue_scim_token(&app).await;
+
+    let mut ids = Vec::new();
+    for name in ["grace", "linus"] {
+        let (status, user) = call(
+            &app,
+            "POST",
+            "/scim/v2/Users",
+            Some(&token),
+            Some(json!({ "userName": name })),
+        )
+        .await;
+        assert_eq!(status, 201, "{user}");
+        ids.push(user["id"].as_str().unwrap().to_string());
+    }
+
+    let (status, group) = call(
+        &app,
+        "POST",
+        "/scim/v2/Groups",
+        Some(&token),
+        Some(json!({
+            "displayName": "Writers",
+            "externalId": "grp-1",
+            "members": [{ "value": ids[0] }],
+        })),
+    )
+    .await;
+    assert_eq!(status, 201, "{group}");
+    // The issuing admin is not made a member, unlike groups created in the UI.
+    assert_eq!(group["members"].as_array().unwrap().len(), 1);
+    let group_id = group["id"].as_str().unwrap().to_string();
+    let group_uri = format!("/scim/v2/Groups/{group_id}");
+
+    let (status, group) = call(
+        &app,
+        "PATCH",
+        &group_uri,
+        Some(&token),
+        Some(patch_op(json!([
+            { "op": "add", "path": "members", "value": [{ "value": ids[1] }] },
+            { "op": "replace", "path": "displayName", "value": "Editors" },
+        ]))),
+    )
+    .await;
+    assert_eq!(status, 200, "{group}");
+    assert_eq!(group["displayName"], "Editors");
+    assert_eq!(group["members"].as_array().unwrap().len(), 2);
+
+    let (_, user) = call(
+        &app,
+        "GET",
+        &format!("/scim/v2/Users/{}", ids[1]),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(user["groups"][0]["value"], group_id.as_str());
+    assert_eq!(user["groups"][0]["display"], "Editors");
+
+    let (status, group) = call(
+        &app,
+        "PATCH",
+        &group_uri,
+        Some(&token),
+        Some(patch_op(json!([
+            { "op": "remove", "path": format!("members[value eq \"{}\"]", ids[0]) },
+        ]))),
+    )
+    .await;
+    assert_eq!(status, 200, "{group}");
+    assert_eq!(group["members"][0]["value"], ids[1].as_str());
+    assert_eq!(group["members"].as_array().unwrap().len(), 1);
+
+    let (status, rejected) = call(
+        &app,
+        "PATCH",
+        &group_uri,
+        Some(&token),
+        Some(patch_op(json!([
+            { "op": "add", "path": "members", "value": [{ "value": "no-such-user" }] },
+        ]))),
+    )
+    .await;
+    assert_eq!(status, 400, "{rejected}");
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/scim/v2/Groups?filter=displayName%20eq%20%22Editors%22",
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(found["totalResults"], 1);
+
+    let (status, _) = call(&app, "DELETE", &group_uri, Some(&token), None).await;
+    assert_eq!(status, 204);
+    let (status, _) = call(&app, "GET", &group_uri, Some(&token), None).await;
+    assert_eq!(status, 404);
+    assert!(state
+        .db
+        .list_group_ids_for_member(&ids[1])
+        .await
+        .unwrap()
+        .is_empty());
+}
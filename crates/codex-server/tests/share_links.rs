@@ -0,0 +1,319 @@
+use actix_web::{http::header, test, web, App};
+use codex::config::AppConfig;
+use codex::db::Database;
+use codex::middleware::AuthMiddleware;
+use codex::models::{CreateVaultRequest, ShareLink, ShareLinkPermission};
+use codex::routes::{auth, share_links, vaults, AppState};
+use codex::services::{GitAutoCommitter, MarkdownParser, SearchIndex};
+use codex::watcher::FileWatcher;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::{broadcast, Mutex};
+
+#[actix_web::test]
+async fn share_links_expose_only_their_note_or_folder() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("share-links.db");
+    let db = Database::new(&format!("sqlite://{}", db_path.display()))
+        .await
+        .unwrap();
+    db.bootstrap_admin_if_empty(Some("admin"), Some("hunter2"))
+        .await
+        .unwrap();
+
+    let vault_dir = temp_dir.path().join("vault");
+    for (path, content) in [
+        (
+            "docs/guide.md",
+            "# Guide\nSee [[other]], [[secret|the secret]] and ![[diagram.png]]",
+        ),
+        ("docs/other.md", "# Other"),
+        ("docs/diagram.png", "not really a png"),
+        ("private/secret.md", "# Secret"),
+    ] {
+        let full = vault_dir.join(path);
+        std::fs::create_dir_all(full.parent().unwrap()).unwrap();
+        std::fs::write(full, content).unwrap();
+    }
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let state = web::Data::new(AppState {
+        db: db.clone(),
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: broadcast::channel(100).0,
+        ws_broadcaster: broadcast::channel::<codex::models::WsMessage>(16).0,
+        change_log_retention_days: 7,
+        ml_undo_store: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        shutdown_tx: broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: GitAutoCommitter::new(),
+    });
+
+    let mut config = AppConfig::default();
+    config.auth.enabled = true;
+    config.auth.jwt_secret = "integration-test-secret".to_string();
+    let config = web::Data::new(config);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .app_data(config.clone())
+            .wrap(AuthMiddleware)
+            .configure(auth::configure)
+            .configure(vaults::configure)
+            .configure(share_links::configure),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "username": "admin", "password": "hunter2" }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let admin = format!("Bearer {}", body["access_token"].as_str().unwrap());
+
+    let req = test::TestRequest::post()
+        .uri("/api/vaults")
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .set_json(&CreateVaultRequest {
+            name: "Shared".to_string(),
+            path: Some(vault_dir.to_string_lossy().to_string()),
+        })
+        .to_request();
+    let vault: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let vault_id = vault["id"].as_str().unwrap().to_string();
+    let links_uri = format!("/api/vaults/{}/shares/links", vault_id);
+
+    let create = |body: serde_json::Value| {
+        test::TestRequest::post()
+            .uri(&links_uri)
+            .insert_header((header::AUTHORIZATION, admin.clone()))
+            .set_json(body)
+            .to_request()
+    };
+
+    // Links cannot be created anonymously or for missing paths.
+    let req = test::TestRequest::post()
+        .uri(&links_uri)
+        .set_json(json!({ "path": "docs/guide.md" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+    let resp = test::call_service(&app, create(json!({ "path": "docs/missing.md" }))).await;
+    assert_eq!(resp.status().as_u16(), 404);
+
+    // A single note: links outside the note render as plain text.
+    let resp = test::call_service(&app, create(json!({ "path": "docs/guide.md" }))).await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let note_link: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(note_link["is_folder"], false);
+    assert_eq!(note_link["permission"], "view");
+    let note_url = note_link["url"].as_str().unwrap().to_string();
+
+    let req = test::TestRequest::get().uri(&note_url).to_request();
+    let shared: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let html = shared["html"].as_str().unwrap();
+    assert!(html.contains("Guide"));
+    assert!(html.contains("<span class=\"wiki-link restricted-link\">the secret</span>"));
+    assert!(!html.contains("private/secret.md"));
+    assert!(!html.contains("docs/other.md"));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/files/docs/other.md", note_url))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+
+    // A password-protected folder with comment permission.
+    let resp = test::call_service(
+        &app,
+        create(json!({
+            "path": "docs/",
+            "permission": "comment",
+            "password": "open sesame",
+            "expires_in_hours": 24,
+        })),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let folder_link: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(folder_link["path"], "docs");
+    assert_eq!(folder_link["is_folder"], true);
+    assert_eq!(folder_link["password_protected"], true);
+    assert!(folder_link["expires_at"].is_string());
+    let folder_url = folder_link["url"].as_str().unwrap().to_string();
+
+    let req = test::TestRequest::get().uri(&folder_url).to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+    let req = test::TestRequest::get()
+        .uri(&folder_url)
+        .insert_header(("X-Share-Password", "guess"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+
+    let get = |uri: String| {
+        test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("X-Share-Password", "open sesame"))
+            .to_request()
+    };
+    let listing: serde_json::Value =
+        test::call_and_read_body_json(&app, get(folder_url.clone())).await;
+    assert_eq!(listing["permission"], "comment");
+    let names: Vec<&str> = listing["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|node| node["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["diagram.png", "guide.md", "other.md"]);
+
+    let note: serde_json::Value =
+        test::call_and_read_body_json(&app, get(format!("{}/files/docs/guide.md", folder_url)))
+            .await;
+    let html = note["html"].as_str().unwrap();
+    assert!(html.contains(&format!("href=\"{}/files/docs/other.md\"", folder_url)));
+    assert!(html.contains(&format!("src=\"{}/files/docs/diagram.png\"", folder_url)));
+    assert!(!html.contains("private/secret.md"));
+
+    let image =
+        test::call_and_read_body(&app, get(format!("{}/files/docs/diagram.png", folder_url))).await;
+    assert_eq!(&image[..], b"not really a png");
+    for outside in ["private/secret.md", "docs/../private/secret.md"] {
+        let resp = test::call_service(&app, get(format!("{}/files/{}", folder_url, outside))).await;
+        assert_eq!(resp.status().as_u16(), 404, "{outside}");
+    }
+
+    // Expired links are refused.
+    let expired_token = "expired-token";
+    db.create_share_link(
+        &ShareLink {
+            id: "expired-link".to_string(),
+            vault_id: vault_id.clone(),
+            path: "docs".to_string(),
+            is_folder: true,
+            permission: ShareLinkPermission::View,
+            password_protected: false,
+            expires_at: Some(chrono::Utc::now() - chrono::Duration::minutes(1)),
+            created_by: None,
+            created_at: chrono::Utc::now() - chrono::Duration::hours(2),
+            revoked_at: None,
+            access_count: 0,
+            last_accessed_at: None,
+        },
+        &hex::encode(Sha256::digest(expired_token.as_bytes())),
+        None,
+    )
+    .await
+    .unwrap();
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/public/shares/{}", expired_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+
+    // Revocation takes effect immediately and stays visible in the list.
+    let req = test::TestRequest::delete()
+        .uri(&format!(
+            "{}/{}",
+            links_uri,
+            folder_link["id"].as_str().unwrap()
+        ))
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 204);
+    let resp = test::call_service(&app, get(folder_url.clone())).await;
+    assert_eq!(resp.status().as_u16(), 404);
+
+    let req = test::TestRequest::get()
+        .uri(&links_uri)
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .to_request();
+    let links: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let folder = links
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|link| link["id"] == folder_link["id"])
+        .unwrap();
+    assert!(folder["revoked_at"].is_string());
+    assert_eq!(folder["access_count"], 3);
+    assert!(folder.get("token").is_none());
+
+    // Every public access attempt is audited.
+    let audit = db.get_audit_log(Some(100)).await.unwrap();
+    let count = |event: &str| audit.iter().filter(|e| e.event_type == event).count();
+    assert_eq!(count("share_link_created"), 2);
+    assert_eq!(count("share_link_revoked"), 1);
+    assert_eq!(count("share_link_accessed"), 4);
+    assert_eq!(count("share_link_denied"), 7);
+
+    // Comments are only accepted through links with comment permission.
+    let comment = |url: &str, body: serde_json::Value| {
+        test::TestRequest::post()
+            .uri(&format!("{}/comments", url))
+            .set_json(body)
+            .to_request()
+    };
+    let resp = test::call_service(&app, comment(&note_url, json!({ "body": "Nice" }))).await;
+    assert_eq!(resp.status().as_u16(), 403);
+
+    let resp = test::call_service(
+        &app,
+        create(json!({ "path": "docs", "permission": "comment" })),
+    )
+    .await;
+    let commentable: serde_json::Value = test::read_body_json(resp).await;
+    let commentable_url = commentable["url"].as_str().unwrap().to_string();
+
+    for (body, status) in [
+        (json!({ "body": "Which note?" }), 400),
+        (json!({ "path": "docs/guide.md", "body": "  " }), 400),
+        (json!({ "path": "docs/diagram.png", "body": "Blurry" }), 400),
+        (json!({ "path": "private/secret.md", "body": "Hi" }), 404),
+        (json!({ "path": "docs/missing.md", "body": "Hi" }), 404),
+    ] {
+        let resp = test::call_service(&app, comment(&commentable_url, body.clone())).await;
+        assert_eq!(resp.status().as_u16(), status, "{body}");
+    }
+    let resp = test::call_service(
+        &app,
+        comment(
+            &commentable_url,
+            json!({ "path": "docs/guide.md", "author_name": "Visitor", "body": " Typo in line 2 " }),
+        ),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let resp = test::call_service(
+        &app,
+        comment(
+            &commentable_url,
+            json!({ "path": "docs/other.md", "body": "Empty?" }),
+        ),
+    )
+    .await;
+    assert_eq!(resp.status().as_u16(), 201);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("{}/comments?path=docs/guide.md", commentable_url))
+        .to_request();
+    let comments: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(comments.as_array().unwrap().len(), 1);
+    assert_eq!(comments[0]["body"], "Typo in line 2");
+    assert_eq!(comments[0]["author_name"], "Visitor");
+
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "{}/{}/comments",
+            links_uri,
+            commentable["id"].as_str().unwrap()
+        ))
+        .insert_header((header::AUTHORIZATION, admin.clone()))
+        .to_request();
+    let comments: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(comments.as_array().unwrap().len(), 2);
+}
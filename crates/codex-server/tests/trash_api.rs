@@ -0,0 +1,130 @@
+use actix_web::{test, web, App};
+use codex::config::AppConfig;
+use codex::db::Database;
+use codex::routes::{files, AppState};
+use codex::services::{GitAutoCommitter, MarkdownParser, SearchIndex, TrashService};
+use codex::watcher::FileWatcher;
+use serde_json::json;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::{broadcast, Mutex};
+
+#[actix_web::test]
+async fn trash_is_indexed_and_supports_bulk_restore_and_purge() {
+    let temp_dir = TempDir::new().unwrap();
+    let vault_dir = temp_dir.path().join("vault");
+    std::fs::create_dir_all(&vault_dir).unwrap();
+
+    let db_path = temp_dir.path().join("trash-test.db");
+    let db = Database::new(&format!("sqlite://{}", db_path.display()))
+        .await
+        .unwrap();
+    let vault = db
+        .create_vault("Trash".to_string(), vault_dir.to_string_lossy().to_string())
+        .await
+        .unwrap();
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let state = web::Data::new(AppState {
+        db: db.clone(),
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: broadcast::channel(100).0,
+        ws_broadcaster: broadcast::channel::<codex::models::WsMessage>(16).0,
+        change_log_retention_days: 7,
+        ml_undo_store: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        shutdown_tx: broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: GitAutoCommitter::new(),
+    });
+    let config = web::Data::new(AppConfig::default());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .app_data(config.clone())
+            .configure(files::configure),
+    )
+    .await;
+
+    for (path, content) in [
+        ("notes/a.md", "alpha"),
+        ("b.md", "bravo"),
+        ("c.md", "charlie"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/vaults/{}/files", vault.id))
+            .set_json(json!({ "path": path, "content": content }))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/vaults/{}/files/{}", vault.id, path))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+    // The parent folder is gone too, so restoring must recreate it.
+    std::fs::remove_dir(vault_dir.join("notes")).unwrap();
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/vaults/{}/trash", vault.id))
+        .to_request();
+    let items: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let items = items.as_array().unwrap();
+    assert_eq!(items.len(), 3);
+    let name_of = |original: &str| {
+        items
+            .iter()
+            .find(|i| i["original_path"] == original)
+            .unwrap()["trash_name"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let a = name_of("notes/a.md");
+    let b = name_of("b.md");
+    let c = name_of("c.md");
+    let a_item = items.iter().find(|i| i["trash_name"] == a).unwrap();
+    assert_eq!(a_item["size"], 5);
+
+    // b.md is taken again, so a plain restore conflicts and autorename succeeds.
+    std::fs::write(vault_dir.join("b.md"), "new bravo").unwrap();
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/trash/{}/restore", vault.id, b))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/trash/restore", vault.id))
+        .set_json(json!({ "trash_names": [a, b, "missing"], "strategy": "autorename" }))
+        .to_request();
+    let result: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(result["succeeded"].as_array().unwrap().len(), 2);
+    assert_eq!(result["failed"][0]["trash_name"], "missing");
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join("notes/a.md")).unwrap(),
+        "alpha"
+    );
+    assert_eq!(
+        std::fs::read_to_string(vault_dir.join("b (1).md")).unwrap(),
+        "bravo"
+    );
+
+    // Age-limited purge keeps recent items; an empty body empties the trash.
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/trash/purge", vault.id))
+        .set_json(json!({ "older_than_days": 1 }))
+        .to_request();
+    let result: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(result["succeeded"].as_array().unwrap().is_empty());
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/vaults/{}/trash/purge", vault.id))
+        .to_request();
+    let result: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(result["succeeded"][0]["trash_name"], c.as_str());
+    assert!(db.list_trash_items(&vault.id).await.unwrap().is_empty());
+    assert_eq!(TrashService::purge_expired(&db, 30).await.unwrap(), 0);
+}
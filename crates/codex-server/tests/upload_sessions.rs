@@ -0,0 +1,183 @@
+use actix_web::{test, web, App};
+use codex::config::AppConfig;
+use codex::db::Database;
+use codex::routes::{files, AppState};
+use codex::services::{FileService, GitAutoCommitter, MarkdownParser, SearchIndex, UploadService};
+use codex::watcher::FileWatcher;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::{broadcast, Mutex};
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[actix_web::test]
+async fn upload_sessions_accept_out_of_order_chunks_and_verify_checksums() {
+    let temp_dir = TempDir::new().unwrap();
+    let vault_dir = temp_dir.path().join("vault");
+    std::fs::create_dir_all(&vault_dir).unwrap();
+
+    let db_path = temp_dir.path().join("upload-test.db");
+    let db = Database::new(&format!("sqlite://{}", db_path.display()))
+        .await
+        .unwrap();
+    let vault = db
+        .create_vault(
+            "Uploads".to_string(),
+            vault_dir.to_string_lossy().to_string(),
+        )
+        .await
+        .unwrap();
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let state = web::Data::new(AppState {
+        db: db.clone(),
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: broadcast::channel(100).0,
+        ws_broadcaster: broadcast::channel::<codex::models::WsMessage>(16).0,
+        change_log_retention_days: 7,
+        ml_undo_store: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        shutdown_tx: broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: GitAutoCommitter::new(),
+    });
+    let config = web::Data::new(AppConfig::default());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .app_data(config.clone())
+            .configure(files::configure),
+    )
+    .await;
+
+    let contents: Vec<u8> = (0..30_000u32).map(|i| (i % 253) as u8).collect();
+    let sessions_uri = format!("/api/vaults/{}/upload-sessions", vault.id);
+
+    let req = test::TestRequest::post()
+        .uri(&sessions_uri)
+        .set_json(json!({
+            "filename": "data.bin",
+            "path": "files",
+            "total_size": contents.len(),
+            "sha256": sha256_hex(&contents),
+        }))
+        .to_request();
+    let session: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let session_id = session["session_id"].as_str().unwrap().to_string();
+    assert!(session["expires_at"].is_string());
+    let session_uri = format!("{}/{}", sessions_uri, session_id);
+
+    let put_chunk = |offset: usize, len: usize, checksum: String| {
+        test::TestRequest::put()
+            .uri(&format!("{}?offset={}", session_uri, offset))
+            .insert_header(("X-Chunk-Sha256", checksum))
+            .set_payload(contents[offset..offset + len].to_vec())
+            .to_request()
+    };
+
+    // A chunk whose checksum does not match is rejected.
+    let req = put_chunk(20_000, 10_000, sha256_hex(b"something else"));
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    // Last chunk first, then the first one: a gap remains.
+    let req = put_chunk(20_000, 10_000, sha256_hex(&contents[20_000..]));
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["uploaded_bytes"], 10_000);
+    let req = put_chunk(0, 10_000, sha256_hex(&contents[..10_000]));
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get().uri(&session_uri).to_request();
+    let status: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(status["uploaded_bytes"], 20_000);
+    assert_eq!(
+        status["received"],
+        json!([
+            { "offset": 0, "length": 10_000 },
+            { "offset": 20_000, "length": 10_000 }
+        ])
+    );
+
+    let finish_uri = format!("{}/finish", session_uri);
+    let req = test::TestRequest::post()
+        .uri(&finish_uri)
+        .set_json(json!({ "filename": "data.bin", "path": "files" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    // Chunks past the declared size are refused.
+    let req = test::TestRequest::put()
+        .uri(&format!("{}?offset={}", session_uri, 29_999))
+        .set_payload(vec![0u8; 2])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let req = put_chunk(10_000, 10_000, sha256_hex(&contents[10_000..20_000]));
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri(&finish_uri)
+        .set_json(json!({ "filename": "data.bin", "path": "files" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    assert_eq!(
+        std::fs::read(vault_dir.join("files/data.bin")).unwrap(),
+        contents
+    );
+    assert!(db.get_upload_session(&session_id).await.is_err());
+
+    // A whole-file checksum mismatch discards the upload.
+    let req = test::TestRequest::post()
+        .uri(&sessions_uri)
+        .set_json(json!({ "filename": "bad.bin", "path": "", "total_size": 4 }))
+        .to_request();
+    let session: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let bad_uri = format!(
+        "{}/{}",
+        sessions_uri,
+        session["session_id"].as_str().unwrap()
+    );
+    let req = test::TestRequest::put()
+        .uri(&bad_uri)
+        .set_payload(b"abcd".to_vec())
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post()
+        .uri(&format!("{}/finish", bad_uri))
+        .set_json(json!({ "filename": "bad.bin", "path": "", "sha256": sha256_hex(b"abce") }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    let req = test::TestRequest::get().uri(&bad_uri).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    assert!(!vault_dir.join("bad.bin").exists());
+
+    // Expired sessions are refused and swept along with their temp file.
+    let req = test::TestRequest::post()
+        .uri(&sessions_uri)
+        .set_json(json!({ "filename": "stale.bin", "path": "" }))
+        .to_request();
+    let session: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let stale_id = session["session_id"].as_str().unwrap().to_string();
+    let temp_path = FileService::upload_session_temp_path(&vault.path, &stale_id);
+    assert!(temp_path.exists());
+    db.extend_upload_session(&stale_id, chrono::Utc::now() - chrono::Duration::minutes(1))
+        .await
+        .unwrap();
+    let req = test::TestRequest::put()
+        .uri(&format!("{}/{}", sessions_uri, stale_id))
+        .set_payload(b"late".to_vec())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    assert_eq!(UploadService::sweep(&db, 24).await.unwrap(), 1);
+    assert!(!temp_path.exists());
+    assert!(db.get_upload_session(&stale_id).await.is_err());
+}
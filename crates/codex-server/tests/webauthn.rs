@@ -0,0 +1,660 @@
+use actix_web::{http::header, test, web, App};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+use ciborium::Value as Cbor;
+use codex::config::AppConfig;
+use codex::db::Database;
+use codex::middleware::AuthMiddleware;
+use codex::routes::{admin, auth, webauthn, AppState};
+use codex::services::{MarkdownParser, SearchIndex};
+use codex::watcher::FileWatcher;
+use data_encoding::BASE64URL_NOPAD;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::{broadcast, Mutex};
+
+const ORIGIN: &str = "https://notes.example.com";
+const RP_ID: &str = "notes.example.com";
+
+fn password_hash(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+fn b64(bytes: &[u8]) -> String {
+    BASE64URL_NOPAD.encode(bytes)
+}
+
+fn cbor(value: &Cbor) -> Vec<u8> {
+    let mut out = Vec::new();
+    ciborium::into_writer(value, &mut out).unwrap();
+    out
+}
+
+fn cbor_int_map(entries: Vec<(i64, Cbor)>) -> Cbor {
+    Cbor::Map(
+        entries
+            .into_iter()
+            .map(|(k, v)| (Cbor::Integer(k.into()), v))
+            .collect(),
+    )
+}
+
+enum Key {
+    Es256(EcdsaKeyPair),
+    Ed25519(Ed25519KeyPair),
+}
+
+/// A software authenticator that behaves like a platform passkey.
+struct SoftAuthenticator {
+    key: Key,
+    credential_id: Vec<u8>,
+    user_handle: Option<Vec<u8>>,
+    sign_count: u32,
+    origin: String,
+    user_verified: bool,
+}
+
+impl SoftAuthenticator {
+    fn es256() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+        Self::with_key(Key::Es256(key))
+    }
+
+    fn ed25519() -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        Self::with_key(Key::Ed25519(key))
+    }
+
+    fn with_key(key: Key) -> Self {
+        Self {
+            key,
+            credential_id: uuid::Uuid::new_v4().as_bytes().to_vec(),
+            user_handle: None,
+            sign_count: 0,
+            origin: ORIGIN.to_string(),
+            user_verified: true,
+        }
+    }
+
+    fn id(&self) -> String {
+        b64(&self.credential_id)
+    }
+
+    fn cose_key(&self) -> Vec<u8> {
+        let key = match &self.key {
+            Key::Es256(key) => {
+                let point = key.public_key().as_ref();
+                cbor_int_map(vec![
+                    (1, Cbor::Integer(2.into())),
+                    (3, Cbor::Integer((-7).into())),
+                    (-1, Cbor::Integer(1.into())),
+                    (-2, Cbor::Bytes(point[1..33].to_vec())),
+                    (-3, Cbor::Bytes(point[33..].to_vec())),
+                ])
+            }
+            Key::Ed25519(key) => cbor_int_map(vec![
+                (1, Cbor::Integer(1.into())),
+                (3, Cbor::Integer((-8).into())),
+                (-1, Cbor::Integer(6.into())),
+                (-2, Cbor::Bytes(key.public_key().as_ref().to_vec())),
+            ]),
+        };
+        cbor(&key)
+    }
+
+    fn client_data(&self, kind: &str, options: &Value) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "type": kind,
+            "challenge": options["challenge"],
+            "origin": self.origin,
+            "crossOrigin": false,
+        }))
+        .unwrap()
+    }
+
+    fn flags(&self) -> u8 {
+        if self.user_verified {
+            0x05
+        } else {
+            0x01
+        }
+    }
+
+    /// Respond to `navigator.credentials.create()`.
+    fn register(&mut self, options: &Value) -> Value {
+        let user_id = options["user"]["id"].as_str().unwrap();
+        self.user_handle = Some(BASE64URL_NOPAD.decode(user_id.as_bytes()).unwrap());
+        let rp_id = options["rp"]["id"].as_str().unwrap();
+
+        let mut auth_data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        auth_data.push(self.flags() | 0x40);
+        auth_data.extend_from_slice(&self.sign_count.to_be_bytes());
+        auth_data.extend_from_slice(&[0u8; 16]);
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        auth_data.extend_from_slice(&self.cose_key());
+
+        let attestation = Cbor::Map(vec![
+            (Cbor::Text("fmt".into()), Cbor::Text("none".into())),
+            (Cbor::Text("attStmt".into()), Cbor::Map(vec![])),
+            (Cbor::Text("authData".into()), Cbor::Bytes(auth_data)),
+        ]);
+        json!({
+            "id": self.id(),
+            "rawId": self.id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": b64(&self.client_data("webauthn.create", options)),
+                "attestationObject": b64(&cbor(&attestation)),
+            },
+        })
+    }
+
+    /// Respond to `navigator.credentials.get()`.
+    fn assert(&mut self, options: &Value) -> Value {
+        self.sign_count += 1;
+        let rp_id = options["rpId"].as_str().unwrap();
+        let mut auth_data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        auth_data.push(self.flags());
+        auth_data.extend_from_slice(&self.sign_count.to_be_bytes());
+
+        let client_data = self.client_data("webauthn.get", options);
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client_data));
+        let signature = match &self.key {
+            Key::Es256(key) => key
+                .sign(&SystemRandom::new(), &signed)
+                .unwrap()
+                .as_ref()
+                .to_vec(),
+            Key::Ed25519(key) => key.sign(&signed).as_ref().to_vec(),
+        };
+
+        json!({
+            "id": self.id(),
+            "rawId": self.id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": b64(&client_data),
+                "authenticatorData": b64(&auth_data),
+                "signature": b64(&signature),
+                "userHandle": self.user_handle.as_deref().map(b64),
+            },
+        })
+    }
+}
+
+async fn setup(
+    configure: impl FnOnce(&mut AppConfig),
+) -> (TempDir, web::Data<AppState>, web::Data<AppConfig>) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_url = format!("sqlite://{}", temp_dir.path().join("webauthn.db").display());
+    let db = Database::new(&db_url).await.unwrap();
+    db.bootstrap_admin_if_empty(Some("admin"), Some("hunter2"))
+        .await
+        .unwrap();
+    db.create_user("alice", &password_hash("password123"))
+        .await
+        .unwrap();
+
+    let (watcher, _) = FileWatcher::new().unwrap();
+    let (event_tx, _) = broadcast::channel(100);
+    let state = web::Data::new(AppState {
+        db,
+        search_index: SearchIndex::in_dir(temp_dir.path().join("indices")),
+        watcher: Arc::new(Mutex::new(watcher)),
+        event_broadcaster: event_tx,
+        ws_broadcaster: tokio::sync::broadcast::channel::<codex::models::WsMessage>(16).0,
+        change_log_retention_days: 7,
+        ml_undo_store: std::sync::Arc::new(tokio::sync::Mutex::new(
+            std::collections::HashMap::new(),
+        )),
+        shutdown_tx: tokio::sync::broadcast::channel::<()>(1).0,
+        document_parser: Arc::new(MarkdownParser),
+        entity_type_registry: codex::services::EntityTypeRegistry::new(),
+        relation_type_registry: codex::services::RelationTypeRegistry::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
+    });
+
+    let mut config = AppConfig::default();
+    config.auth.enabled = true;
+    config.auth.jwt_secret = "integration-test-secret".to_string();
+    config.auth.webauthn_rp_id = RP_ID.to_string();
+    config.auth.webauthn_origins = vec![ORIGIN.to_string()];
+    configure(&mut config);
+    (temp_dir, state, web::Data::new(config))
+}
+
+macro_rules! app {
+    ($state:expr, $config:expr) => {
+        test::init_service(
+            App::new()
+                .app_data($state.clone())
+                .app_data($config.clone())
+                .wrap(AuthMiddleware)
+                .configure(auth::configure)
+                .configure(admin::configure)
+                .configure(webauthn::configure),
+        )
+        .await
+    };
+}
+
+async fn post_json<S, B>(app: &S, uri: &str, token: Option<&str>, body: Value) -> (u16, Value)
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let mut req = test::TestRequest::post().uri(uri).set_json(body);
+    if let Some(token) = token {
+        req = req.insert_header((header::AUTHORIZATION, format!("Bearer {token}")));
+    }
+    let resp = test::call_service(app, req.to_request()).await;
+    let status = resp.status().as_u16();
+    let bytes = test::read_body(resp).await;
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn password_login<S, B>(app: &S, username: &str, password: &str) -> Value
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let (status, body) = post_json(
+        app,
+        "/api/auth/login",
+        None,
+        json!({ "username": username, "password": password }),
+    )
+    .await;
+    assert_eq!(status, 200, "{body}");
+    body
+}
+
+async fn register<S, B>(
+    app: &S,
+    token: &str,
+    authenticator: &mut SoftAuthenticator,
+    name: &str,
+) -> (u16, Value)
+where
+    S: actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+    B: actix_web::body::MessageBody,
+{
+    let (status, ceremony) = post_json(
+        app,
+        "/api/auth/webauthn/register/start",
+        Some(token),
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, 200, "{ceremony}");
+    let credential = authenticator.register(&ceremony["public_key"]);
+    post_json(
+        app,
+        "/api/auth/webauthn/register/finish",
+        Some(token),
+        json!({
+            "ceremony_id": ceremony["ceremony_id"],
+            "name": name,
+            "credential": credential,
+        }),
+    )
+    .await
+}
+
+#[actix_web::test]
+async fn passkeys_register_and_act_as_second_factor_or_alone() {
+    let (_dir, state, config) = setup(|_| {}).await;
+    let app = app!(state, config);
+
+    let login = password_login(&app, "alice", "password123").await;
+    assert_eq!(login["webauthn_required"], false);
+    let alice_token = login["access_token"].as_str().unwrap().to_string();
+
+    // Two named authenticators of different kinds.
+    let mut laptop = SoftAuthenticator::es256();
+    let (status, info) = register(&app, &alice_token, &mut laptop, "Laptop").await;
+    assert_eq!(status, 201, "{info}");
+    assert_eq!(info["name"], "Laptop");
+    assert_eq!(info["id"], laptop.id());
+    let mut key = SoftAuthenticator::ed25519();
+    let (status, _) = register(&app, &alice_token, &mut key, "Security key").await;
+    assert_eq!(status, 201);
+    let (status, _) = register(
+        &app,
+        &alice_token,
+        &mut SoftAuthenticator::es256(),
+        "Laptop",
+    )
+    .await;
+    assert_eq!(status, 409);
+
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/auth/webauthn/credentials")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {alice_token}")))
+            .to_request(),
+    )
+    .await;
+    let listed: Value = test::read_body_json(resp).await;
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+
+    // Admins see the enrolled factors.
+    let admin = password_login(&app, "admin", "hunter2").await;
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/admin/users")
+            .insert_header((
+                header::AUTHORIZATION,
+                format!("Bearer {}", admin["access_token"].as_str().unwrap()),
+            ))
+            .to_request(),
+    )
+    .await;
+    let users: Value = test::read_body_json(resp).await;
+    let alice = users
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|u| u["username"] == "alice")
+        .unwrap();
+    assert_eq!(alice["factors"]["totp"], false);
+    let names: Vec<&str> = alice["factors"]["webauthn"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["Laptop", "Security key"]);
+
+    // A password alone no longer yields tokens.
+    let login = password_login(&app, "alice", "password123").await;
+    assert_eq!(login["webauthn_required"], true);
+    assert_eq!(login["access_token"], "");
+    let ceremony = &login["webauthn"];
+    assert_eq!(
+        ceremony["public_key"]["allowCredentials"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+    key.user_verified = false;
+    let assertion = key.assert(&ceremony["public_key"]);
+    let (status, tokens) = post_json(
+        &app,
+        "/api/auth/webauthn/login/finish",
+        None,
+        json!({ "ceremony_id": ceremony["ceremony_id"], "credential": assertion }),
+    )
+    .await;
+    assert_eq!(status, 200, "{tokens}");
+    let token = tokens["access_token"].as_str().unwrap();
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/auth/me")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+
+    // The ceremony cannot be replayed.
+    let (status, _) = post_json(
+        &app,
+        "/api/auth/webauthn/login/finish",
+        None,
+        json!({ "ceremony_id": ceremony["ceremony_id"], "credential": key.assert(&ceremony["public_key"]) }),
+    )
+    .await;
+    assert_eq!(status, 401);
+
+    // Passwordless, with a discoverable passkey.
+    let (status, ceremony) =
+        post_json(&app, "/api/auth/webauthn/login/start", None, json!({})).await;
+    assert_eq!(status, 200);
+    assert_eq!(ceremony["public_key"]["userVerification"], "required");
+    let (status, tokens) = post_json(
+        &app,
+        "/api/auth/webauthn/login/finish",
+        None,
+        json!({ "ceremony_id": ceremony["ceremony_id"], "credential": laptop.assert(&ceremony["public_key"]) }),
+    )
+    .await;
+    assert_eq!(status, 200, "{tokens}");
+    assert!(!tokens["access_token"].as_str().unwrap().is_empty());
+    assert_eq!(tokens["totp_required"], false);
+
+    // With TOTP enabled, a passkey alone still leaves the TOTP step pending.
+    let (alice_id, _, _) = state
+        .db
+        .get_user_auth_by_username("alice")
+        .await
+        .unwrap()
+        .unwrap();
+    state
+        .db
+        .set_totp_secret(&alice_id, "JBSWY3DPEHPK3PXP", &[])
+        .await
+        .unwrap();
+    state.db.enable_totp(&alice_id).await.unwrap();
+    let (_, ceremony) = post_json(&app, "/api/auth/webauthn/login/start", None, json!({})).await;
+    let (status, tokens) = post_json(
+        &app,
+        "/api/auth/webauthn/login/finish",
+        None,
+        json!({ "ceremony_id": ceremony["ceremony_id"], "credential": laptop.assert(&ceremony["public_key"]) }),
+    )
+    .await;
+    assert_eq!(status, 200, "{tokens}");
+    assert_eq!(tokens["totp_required"], true);
+    state.db.disable_totp(&alice_id).await.unwrap();
+
+    // Passwordless needs user verification.
+    let (_, ceremony) = post_json(&app, "/api/auth/webauthn/login/start", None, json!({})).await;
+    let (status, _) = post_json(
+        &app,
+        "/api/auth/webauthn/login/finish",
+        None,
+        json!({ "ceremony_id": ceremony["ceremony_id"], "credential": key.assert(&ceremony["public_key"]) }),
+    )
+    .await;
+    assert_eq!(status, 401);
+
+    // Remove both; the password is enough again.
+    for id in [laptop.id(), key.id()] {
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::delete()
+                .uri(&format!("/api/auth/webauthn/credentials/{id}"))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {alice_token}")))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 204);
+    }
+    let login = password_login(&app, "alice", "password123").await;
+    assert_eq!(login["webauthn_required"], false);
+    assert!(!login["access_token"].as_str().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn forged_or_replayed_assertions_are_rejected() {
+    let (_dir, state, config) = setup(|_| {}).await;
+    let app = app!(state, config);
+    let token = password_login(&app, "alice", "password123").await["access_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let mut laptop = SoftAuthenticator::es256();
+    assert_eq!(register(&app, &token, &mut laptop, "Laptop").await.0, 201);
+
+    // Renaming.
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::patch()
+            .uri(&format!("/api/auth/webauthn/credentials/{}", laptop.id()))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(json!({ "name": "Work laptop" }))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let (alice_id, _, _) = state
+        .db
+        .get_user_auth_by_username("alice")
+        .await
+        .unwrap()
+        .unwrap();
+    let credentials = state.db.list_webauthn_credentials(&alice_id).await.unwrap();
+    assert_eq!(credentials[0].name, "Work laptop");
+
+    let start = || async {
+        let (_, ceremony) = post_json(
+            &app,
+            "/api/auth/webauthn/login/start",
+            None,
+            json!({ "username": "alice" }),
+        )
+        .await;
+        ceremony
+    };
+    let finish = |ceremony: Value, credential: Value| {
+        let app = &app;
+        async move {
+            post_json(
+                app,
+                "/api/auth/webauthn/login/finish",
+                None,
+                json!({ "ceremony_id": ceremony["ceremony_id"], "credential": credential }),
+            )
+            .await
+            .0
+        }
+    };
+
+    let (status, ceremony) = post_json(
+        &app,
+        "/api/auth/webauthn/login/start",
+        None,
+        json!({ "username": "nobody" }),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(ceremony["public_key"]["allowCredentials"], json!([]));
+
+    // Wrong origin.
+    laptop.origin = "https://evil.example.com".to_string();
+    let ceremony = start().await;
+    assert_eq!(
+        finish(ceremony.clone(), laptop.assert(&ceremony["public_key"])).await,
+        401
+    );
+    laptop.origin = ORIGIN.to_string();
+
+    // Tampered signature.
+    let ceremony = start().await;
+    let mut assertion = laptop.assert(&ceremony["public_key"]);
+    assertion["response"]["clientDataJSON"] = json!(b64(&serde_json::to_vec(&json!({
+        "type": "webauthn.get",
+        "challenge": ceremony["public_key"]["challenge"],
+        "origin": ORIGIN,
+        "extra": true,
+    }))
+    .unwrap()));
+    assert_eq!(finish(ceremony, assertion).await, 401);
+
+    // A key that someone else registered cannot answer a ceremony for alice.
+    let ceremony = start().await;
+    let mut stranger = SoftAuthenticator::es256();
+    assert_eq!(
+        finish(ceremony.clone(), stranger.assert(&ceremony["public_key"])).await,
+        401
+    );
+
+    // A counter that does not advance suggests a cloned authenticator.
+    let ceremony = start().await;
+    assert_eq!(
+        finish(ceremony.clone(), laptop.assert(&ceremony["public_key"])).await,
+        200
+    );
+    laptop.sign_count -= 1;
+    let ceremony = start().await;
+    assert_eq!(
+        finish(ceremony.clone(), laptop.assert(&ceremony["public_key"])).await,
+        401
+    );
+
+    // Registration ceremonies cannot be used to sign in.
+    let (_, registration) = post_json(
+        &app,
+        "/api/auth/webauthn/register/start",
+        Some(&token),
+        json!({}),
+    )
+    .await;
+    let mut options = registration["public_key"].clone();
+    options["rpId"] = json!(RP_ID);
+    laptop.sign_count += 10;
+    assert_eq!(finish(registration, laptop.assert(&options)).await, 401);
+}
+
+#[actix_web::test]
+async fn policy_controls_passwordless_and_second_factor() {
+    let (_dir, state, config) = setup(|config| {
+        config.auth.webauthn_passwordless = false;
+        config.auth.webauthn_second_factor = false;
+    })
+    .await;
+    let app = app!(state, config);
+    let token = password_login(&app, "alice", "password123").await["access_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let mut laptop = SoftAuthenticator::es256();
+    assert_eq!(register(&app, &token, &mut laptop, "Laptop").await.0, 201);
+
+    let (status, _) = post_json(&app, "/api/auth/webauthn/login/start", None, json!({})).await;
+    assert_eq!(status, 403);
+    let login = password_login(&app, "alice", "password123").await;
+    assert_eq!(login["webauthn_required"], false);
+    assert!(!login["access_token"].as_str().unwrap().is_empty());
+
+    // Managing authenticators requires a session.
+    let (status, _) = post_json(&app, "/api/auth/webauthn/register/start", None, json!({})).await;
+    assert_eq!(status, 401);
+}
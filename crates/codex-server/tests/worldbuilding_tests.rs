@@ -22,7 +22,7 @@ async fn setup(temp_dir: &TempDir) -> (web::Data<AppState>, String) {
     let db_url = format!("sqlite://{}", db_path.display());
     let db = Database::new(&db_url).await.unwrap();
 
-    let search_index = SearchIndex::new();
+    let search_index = SearchIndex::in_dir(temp_dir.path().join("indices"));
     let (watcher, _) = FileWatcher::new().unwrap();
     let watcher = Arc::new(Mutex::new(watcher));
     let (event_tx, _) = broadcast::channel(100);
@@ -39,7 +39,8 @@ async fn setup(temp_dir: &TempDir) -> (web::Data<AppState>, String) {
         document_parser: Arc::new(MarkdownParser),
         entity_type_registry: EntityTypeRegistry::new(),
         relation_type_registry: RelationTypeRegistry::new(),
-        plugins_dir: String::new(),
+        plugins_dir: std::path::PathBuf::new(),
+        git_autocommit: codex::services::GitAutoCommitter::new(),
     });
 
     let vault_dir = temp_dir.path().join("vault");
@@ -513,6 +514,8 @@ async fn test_entity_type_template_endpoint_with_registered_schema() {
             labels: vec!["graphable".into()],
             display_field: Some("full_name".into()),
             show_on_create: vec!["full_name".into()],
+            extends: None,
+            mixins: vec![],
             fields: vec![],
         })
         .await;
@@ -75,6 +75,15 @@ pub struct AdminUser {
     #[serde(default = "default_true")]
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
+    /// Second factors and passkeys the user has enrolled.
+    #[serde(default)]
+    pub factors: EnrolledFactors,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnrolledFactors {
+    pub totp: bool,
+    pub webauthn: Vec<WebAuthnCredentialInfo>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -101,6 +110,94 @@ pub struct TotpVerifyRequest {
     pub code: String,
 }
 
+// ── WebAuthn / passkey types ────────────────────────────────────────
+
+/// A registered authenticator, without its key material.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebAuthnCredentialInfo {
+    /// Base64url credential ID, as reported by the authenticator.
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Options for `navigator.credentials.create()` / `.get()`, to be echoed
+/// back with the authenticator's response under the same `ceremony_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebAuthnCeremony {
+    pub ceremony_id: String,
+    /// `PublicKeyCredentialCreationOptions` or
+    /// `PublicKeyCredentialRequestOptions`, binary fields base64url-encoded.
+    pub public_key: Value,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebAuthnRegisterStartRequest {
+    /// Display name for the new authenticator (e.g. "YubiKey 5").
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebAuthnRegisterFinishRequest {
+    pub ceremony_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebAuthnLoginStartRequest {
+    /// Restricts the ceremony to this user's authenticators. Omit it to let
+    /// the authenticator offer a discoverable passkey.
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebAuthnLoginFinishRequest {
+    pub ceremony_id: String,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameWebAuthnCredentialRequest {
+    pub name: String,
+}
+
+/// A `PublicKeyCredential` serialised by the browser, binary fields
+/// base64url-encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicKeyCredential {
+    pub id: String,
+    #[serde(rename = "rawId", default)]
+    pub raw_id: Option<String>,
+    #[serde(rename = "type", default = "default_public_key_type")]
+    pub credential_type: String,
+    pub response: AuthenticatorResponse,
+}
+
+fn default_public_key_type() -> String {
+    "public-key".to_string()
+}
+
+/// Union of `AuthenticatorAttestationResponse` (registration) and
+/// `AuthenticatorAssertionResponse` (sign-in).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthenticatorResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject", default)]
+    pub attestation_object: Option<String>,
+    #[serde(rename = "authenticatorData", default)]
+    pub authenticator_data: Option<String>,
+    #[serde(default)]
+    pub signature: Option<String>,
+    #[serde(rename = "userHandle", default)]
+    pub user_handle: Option<String>,
+}
+
 // ── Invitation types ────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -162,6 +259,40 @@ pub struct BulkImportError {
     pub error: String,
 }
 
+/// Endpoint families an API key can be limited to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    Read,
+    Write,
+    Search,
+    Admin,
+}
+
+/// Limits applied to an API key on top of its owner's permissions.
+/// Empty lists and a missing role mean "unrestricted".
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ApiKeyRestrictions {
+    /// Vaults the key may access.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vault_ids: Vec<String>,
+    /// Highest vault role the key acts with (`viewer` or `editor`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<VaultRole>,
+    /// Vault-relative path globs (e.g. `notes/**/*.md`) the key may touch.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_globs: Vec<String>,
+    /// Endpoint families the key may call.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+impl ApiKeyRestrictions {
+    pub fn is_unrestricted(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiKeyInfo {
     pub id: String,
@@ -171,6 +302,12 @@ pub struct ApiKeyInfo {
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
     pub revoked: bool,
+    #[serde(default, flatten)]
+    pub restrictions: ApiKeyRestrictions,
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_used_ip: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -178,6 +315,8 @@ pub struct CreateApiKeyRequest {
     pub name: String,
     /// Optional expiration in days from now. None = never expires.
     pub expires_in_days: Option<u64>,
+    #[serde(default, flatten)]
+    pub restrictions: ApiKeyRestrictions,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -188,6 +327,63 @@ pub struct CreateApiKeyResponse {
     pub api_key: String,
     pub prefix: String,
     pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, flatten)]
+    pub restrictions: ApiKeyRestrictions,
+}
+
+/// A bearer token an identity provider uses to call the SCIM 2.0 API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScimTokenInfo {
+    pub id: String,
+    pub name: String,
+    pub created_by_user_id: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateScimTokenRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateScimTokenResponse {
+    /// The full token — only shown once at creation time.
+    pub token: String,
+    #[serde(flatten)]
+    pub info: ScimTokenInfo,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    #[default]
+    Success,
+    Failure,
+    /// Refused for lack of authentication or permission.
+    Denied,
+}
+
+impl AuditOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "failure",
+            Self::Denied => "denied",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "success" => Some(Self::Success),
+            "failure" => Some(Self::Failure),
+            "denied" => Some(Self::Denied),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -200,6 +396,65 @@ pub struct AuditLogEntry {
     pub detail: Option<String>,
     pub ip_address: Option<String>,
     pub success: bool,
+    #[serde(default)]
+    pub outcome: AuditOutcome,
+    #[serde(default)]
+    pub vault_id: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The `X-Request-ID` of the request that caused the event.
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// Hash-chain link to the previous chained entry, when chaining is on.
+    #[serde(default)]
+    pub prev_hash: Option<String>,
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+/// Filters for `GET /api/admin/audit-log` and its export. `event_type`
+/// takes a comma-separated list.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditLogQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<AuditOutcome>,
+    /// `next_cursor` from the previous page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+/// A page of audit entries, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLogPage {
+    pub entries: Vec<AuditLogEntry>,
+    /// Pass as `cursor` to fetch the next (older) page; absent on the last.
+    pub next_cursor: Option<String>,
+}
+
+/// Result of re-computing the audit log hash chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditChainReport {
+    pub enabled: bool,
+    /// Chained entries checked.
+    pub checked: u64,
+    pub valid: bool,
+    /// Hash of the newest chained entry; record it elsewhere to also
+    /// detect removal of the newest entries.
+    pub head_hash: Option<String>,
+    /// First entry whose hash or link does not match.
+    pub broken_at_id: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -270,6 +525,128 @@ pub struct VaultShareList {
     pub group_shares: Vec<VaultShareEntry>,
 }
 
+/// Access granted by a path ACL entry, ordered from least to most.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PathAccess {
+    None,
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AclPrincipalType {
+    User,
+    Group,
+    Everyone,
+}
+
+/// A path-scoped rule narrowing vault access for a user, a group or
+/// everyone. Rules can only restrict what the vault role already allows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathAclEntry {
+    pub id: String,
+    pub vault_id: String,
+    /// Vault-relative glob, e.g. `hr/**`.
+    pub pattern: String,
+    pub principal_type: AclPrincipalType,
+    /// User or group id; `None` for `everyone`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub principal_id: Option<String>,
+    pub access: PathAccess,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePathAclRequest {
+    pub pattern: String,
+    pub principal_type: AclPrincipalType,
+    #[serde(default)]
+    pub principal_id: Option<String>,
+    pub access: PathAccess,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareLinkPermission {
+    #[default]
+    View,
+    Comment,
+}
+
+/// An unguessable public link to a single note or folder of a vault.
+/// The token itself is only returned when the link is created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareLink {
+    pub id: String,
+    pub vault_id: String,
+    pub path: String,
+    pub is_folder: bool,
+    pub permission: ShareLinkPermission,
+    pub password_protected: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub access_count: i64,
+    pub last_accessed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateShareLinkRequest {
+    pub path: String,
+    #[serde(default)]
+    pub permission: ShareLinkPermission,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub expires_in_hours: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateShareLinkResponse {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    pub token: String,
+    /// Public API path serving the shared content
+    pub url: String,
+}
+
+/// A comment left on a shared note through a link with `comment` permission.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareLinkComment {
+    pub id: String,
+    pub link_id: String,
+    /// The note commented on; the link's own path for note links
+    pub path: String,
+    pub author_name: Option<String>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateShareLinkCommentRequest {
+    /// Note within a shared folder; defaults to the shared note
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub author_name: Option<String>,
+    pub body: String,
+}
+
+/// What a share link resolves to: a rendered note, or a folder listing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedContent {
+    pub path: String,
+    pub permission: ShareLinkPermission,
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<FileNode>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileNode {
     pub name: String,
@@ -505,6 +882,16 @@ pub struct CreateUploadSessionRequest {
     pub filename: String,
     pub path: String,
     pub total_size: Option<u64>,
+    /// Hex SHA-256 of the complete file, checked at `finish`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// A contiguous byte range the server has received for an upload session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadedRange {
+    pub offset: u64,
+    pub length: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -512,6 +899,11 @@ pub struct UploadSessionResponse {
     pub session_id: String,
     pub uploaded_bytes: u64,
     pub total_size: Option<u64>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Received ranges, merged and sorted; gaps still need uploading.
+    #[serde(default)]
+    pub received: Vec<UploadedRange>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -530,6 +922,26 @@ pub enum WsMessage {
         file_count: i64,
         duration_ms: i64,
     },
+    /// A file conflicted while merging a vault's git remote. The local version
+    /// was kept and the incoming one saved at `conflict_path`.
+    SyncConflict {
+        vault_id: String,
+        path: String,
+        conflict_path: Option<String>,
+    },
+    /// Progress of a streamed zip/tar download, matched to the request by
+    /// the `X-Archive-Id` response header. `error` is set if the archive was
+    /// cut short.
+    ArchiveProgress {
+        vault_id: String,
+        archive_id: String,
+        files_done: u64,
+        files_total: u64,
+        bytes_done: u64,
+        bytes_total: u64,
+        finished: bool,
+        error: Option<String>,
+    },
     SyncPing,
     SyncPong {
         server_time: i64,
@@ -161,6 +161,7 @@ export interface EntityRelation {
     directed: boolean;
     metadata: Record<string, unknown>;
     plugin_id?: string;
+    direction: 'forward' | 'inverse';
     is_inverse: boolean;
 }
 
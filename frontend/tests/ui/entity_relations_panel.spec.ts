@@ -34,6 +34,7 @@ const entityResponse = {
             label: 'knows',
             directed: true,
             metadata: {},
+            direction: 'forward',
             is_inverse: false,
         },
         {
@@ -45,6 +46,7 @@ const entityResponse = {
             label: 'mentors',
             directed: true,
             metadata: {},
+            direction: 'inverse',
             is_inverse: true,
         },
     ],